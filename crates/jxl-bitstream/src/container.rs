@@ -11,6 +11,10 @@ pub struct ContainerDetectingReader {
     state: DetectState,
     jxlp_index_state: JxlpIndexState,
     previous_consumed_bytes: usize,
+    total_consumed_bytes: u64,
+    boxes: Vec<ContainerBoxInfo>,
+    pending_events: std::collections::VecDeque<ParseEvent<'static>>,
+    lenient: bool,
 }
 
 #[derive(Debug, Default)]
@@ -29,6 +33,75 @@ enum DetectState {
         bytes_left: Option<usize>,
         pending_no_more_aux_box: bool,
     },
+    /// Skipping malformed box or trailing data, in lenient mode.
+    Skipping {
+        bytes_left: Option<usize>,
+    },
+}
+
+/// Layout of a box in a JPEG XL container.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ContainerBoxInfo {
+    ty: ContainerBoxType,
+    brotli_box_type: Option<ContainerBoxType>,
+    offset: u64,
+    header_size: usize,
+    box_size: Option<u64>,
+}
+
+impl ContainerBoxInfo {
+    /// Returns the type of the box, as written in the box header.
+    #[inline]
+    pub fn box_type(&self) -> ContainerBoxType {
+        self.ty
+    }
+
+    /// Returns the type of the Brotli-compressed box, if the box is `brob` box and its contents
+    /// type is read.
+    #[inline]
+    pub fn brotli_box_type(&self) -> Option<ContainerBoxType> {
+        self.brotli_box_type
+    }
+
+    /// Returns whether the box is a Brotli-compressed box.
+    #[inline]
+    pub fn is_brotli_compressed(&self) -> bool {
+        self.ty == ContainerBoxType::BROTLI_COMPRESSED
+    }
+
+    /// Returns the offset of the box header from the beginning of the file.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the size of the box header.
+    #[inline]
+    pub fn header_size(&self) -> usize {
+        self.header_size
+    }
+
+    /// Returns the size of the box payload, excluding the box header.
+    ///
+    /// Returns `None` if the box extends to the end of the file.
+    #[inline]
+    pub fn box_size(&self) -> Option<u64> {
+        self.box_size
+    }
+
+    /// Returns the offset of the box payload from the beginning of the file.
+    #[inline]
+    pub fn payload_offset(&self) -> u64 {
+        self.offset + self.header_size as u64
+    }
+
+    /// Returns the offset right after the end of the box from the beginning of the file.
+    ///
+    /// Returns `None` if the box extends to the end of the file.
+    #[inline]
+    pub fn end_offset(&self) -> Option<u64> {
+        self.box_size.map(|size| self.payload_offset() + size)
+    }
 }
 
 /// Structure of the decoded bitstream.
@@ -44,6 +117,28 @@ pub enum BitstreamKind {
     Invalid,
 }
 
+/// Position in the input to resume parsing from, returned by
+/// [`ContainerDetectingReader::resume_position`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ResumePosition {
+    /// Offset in the input to resume feeding bytes from.
+    pub input_offset: u64,
+    /// Structure of the bitstream, either bare codestream or container.
+    pub kind: BitstreamKind,
+    /// Whether the position is inside a codestream box, or at the beginning of a box header.
+    pub in_codestream_box: bool,
+    /// Number of codestream bytes left in the current codestream box.
+    ///
+    /// `None` if the codestream box extends to the end of the file, or if the position is not
+    /// inside a codestream box.
+    pub codestream_bytes_left: Option<u64>,
+    /// Index of the last partial codestream box read, or `None` if the codestream is in a single
+    /// `jxlc` box.
+    pub jxlp_index: Option<u32>,
+    /// Whether the last partial codestream box read is the final one.
+    pub jxlp_finished: bool,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
 enum JxlpIndexState {
     #[default]
@@ -58,6 +153,25 @@ impl ContainerDetectingReader {
         Self::default()
     }
 
+    /// Sets whether to recover from common mistakes in containers.
+    ///
+    /// In lenient mode, the reader logs warnings instead of returning errors for malformed
+    /// containers seen in the wild:
+    /// - partial codestream boxes out of order, or mixed with a codestream box, which are read in
+    ///   file order;
+    /// - boxes with size too small to hold their header or required fields, which are skipped;
+    /// - garbage after the box containing the end of the codestream, which is ignored.
+    ///
+    /// Lenient mode is disabled by default.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Returns whether the reader is in lenient mode.
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
     pub fn kind(&self) -> BitstreamKind {
         match self.state {
             DetectState::WaitingSignature => BitstreamKind::Unknown,
@@ -65,6 +179,7 @@ impl ContainerDetectingReader {
             | DetectState::WaitingJxlpIndex(..)
             | DetectState::InAuxBox { .. } => BitstreamKind::Container,
             DetectState::InCodestream { kind, .. } => kind,
+            DetectState::Skipping { .. } => BitstreamKind::Container,
         }
     }
 
@@ -90,4 +205,120 @@ impl ContainerDetectingReader {
     pub fn previous_consumed_bytes(&self) -> usize {
         self.previous_consumed_bytes
     }
+
+    /// Returns the list of boxes read so far, in container order.
+    ///
+    /// The list is empty if the bitstream is a bare codestream. The signature box is included as
+    /// the first box.
+    pub fn boxes(&self) -> &[ContainerBoxInfo] {
+        &self.boxes
+    }
+
+    /// Computes the position in the input corresponding to the given offset in the codestream.
+    ///
+    /// The position can be used to create a reader with [`resume_at`], which continues parsing
+    /// from the position without feeding previous bytes again. Returns `None` if the codestream
+    /// offset is not read yet, or the bitstream structure is not known.
+    ///
+    /// [`resume_at`]: ContainerDetectingReader::resume_at
+    pub fn resume_position(&self, codestream_offset: u64) -> Option<ResumePosition> {
+        match self.kind() {
+            BitstreamKind::BareCodestream => {
+                return (codestream_offset <= self.total_consumed_bytes).then_some(
+                    ResumePosition {
+                        input_offset: codestream_offset,
+                        kind: BitstreamKind::BareCodestream,
+                        in_codestream_box: true,
+                        codestream_bytes_left: None,
+                        jxlp_index: None,
+                        jxlp_finished: false,
+                    },
+                );
+            }
+            BitstreamKind::Container => {}
+            _ => return None,
+        }
+
+        let mut codestream_start = 0u64;
+        let mut jxlp_index = None;
+        for (idx, info) in self.boxes.iter().enumerate() {
+            let index_size = if info.ty == ContainerBoxType::PARTIAL_CODESTREAM {
+                jxlp_index = Some(jxlp_index.map(|x| x + 1).unwrap_or(0u32));
+                4
+            } else if info.ty == ContainerBoxType::CODESTREAM {
+                jxlp_index = None;
+                0
+            } else {
+                continue;
+            };
+
+            let payload_start = info.offset + info.header_size as u64 + index_size;
+            let codestream_end = info
+                .box_size
+                .map(|size| codestream_start + size.saturating_sub(index_size));
+            if let Some(end) = codestream_end {
+                if codestream_offset > end {
+                    codestream_start = end;
+                    continue;
+                }
+            }
+
+            let is_last_jxlp = !self.boxes[idx + 1..]
+                .iter()
+                .any(|info| info.ty == ContainerBoxType::PARTIAL_CODESTREAM);
+            let jxlp_finished = jxlp_index.is_some()
+                && is_last_jxlp
+                && self.jxlp_index_state == JxlpIndexState::JxlpFinished;
+            let input_offset = payload_start + (codestream_offset - codestream_start);
+            if input_offset > self.total_consumed_bytes {
+                return None;
+            }
+
+            let codestream_bytes_left = codestream_end.map(|end| end - codestream_offset);
+            return Some(ResumePosition {
+                input_offset,
+                kind: BitstreamKind::Container,
+                in_codestream_box: codestream_bytes_left != Some(0),
+                codestream_bytes_left: codestream_bytes_left.filter(|&x| x != 0),
+                jxlp_index,
+                jxlp_finished,
+            });
+        }
+
+        None
+    }
+
+    /// Creates a reader which continues parsing from the given position.
+    ///
+    /// Bytes fed to the reader should start from [`ResumePosition::input_offset`]. Boxes read
+    /// before the position are not recorded in the box list of the new reader.
+    pub fn resume_at(position: ResumePosition) -> Self {
+        let state = if position.in_codestream_box {
+            let bytes_left = position.codestream_bytes_left.map(|x| x as usize);
+            DetectState::InCodestream {
+                kind: position.kind,
+                bytes_left,
+                pending_no_more_aux_box: bytes_left.is_none(),
+            }
+        } else {
+            DetectState::WaitingBoxHeader
+        };
+
+        let jxlp_index_state = match (position.kind, position.jxlp_index) {
+            (BitstreamKind::BareCodestream, _) => JxlpIndexState::Initial,
+            (_, None) => JxlpIndexState::SingleJxlc,
+            (_, Some(_)) if position.jxlp_finished => JxlpIndexState::JxlpFinished,
+            (_, Some(index)) => JxlpIndexState::Jxlp(index),
+        };
+
+        Self {
+            state,
+            jxlp_index_state,
+            previous_consumed_bytes: 0,
+            total_consumed_bytes: position.input_offset,
+            boxes: Vec::new(),
+            pending_events: Default::default(),
+            lenient: false,
+        }
+    }
 }
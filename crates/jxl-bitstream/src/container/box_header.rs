@@ -17,12 +17,22 @@ pub enum HeaderParseResult {
 }
 
 impl ContainerBoxHeader {
-    pub(super) fn parse(buf: &[u8]) -> Result<HeaderParseResult, Error> {
+    /// Parses a box header.
+    ///
+    /// If `lenient` is `true`, boxes with size smaller than the header are read as empty boxes.
+    pub(super) fn parse(buf: &[u8], lenient: bool) -> Result<HeaderParseResult, Error> {
         let (tbox, box_size, header_size) = match *buf {
             [0, 0, 0, 1, t0, t1, t2, t3, s0, s1, s2, s3, s4, s5, s6, s7, ..] => {
                 let xlbox = u64::from_be_bytes([s0, s1, s2, s3, s4, s5, s6, s7]);
                 let tbox = ContainerBoxType([t0, t1, t2, t3]);
-                let xlbox = xlbox.checked_sub(16).ok_or(Error::InvalidBox)?;
+                let xlbox = match xlbox.checked_sub(16) {
+                    Some(xlbox) => xlbox,
+                    None if lenient => {
+                        tracing::warn!(?tbox, xlbox, "Box size is too small, reading as empty box");
+                        0
+                    }
+                    None => return Err(Error::InvalidBox),
+                };
                 (tbox, Some(xlbox), 16)
             }
             [s0, s1, s2, s3, t0, t1, t2, t3, ..] => {
@@ -32,6 +42,9 @@ impl ContainerBoxHeader {
                     None
                 } else if let Some(sbox) = sbox.checked_sub(8) {
                     Some(sbox as u64)
+                } else if lenient {
+                    tracing::warn!(?tbox, sbox, "Box size is too small, reading as empty box");
+                    Some(0)
                 } else {
                     return Err(Error::InvalidBox);
                 };
@@ -85,4 +98,5 @@ impl ContainerBoxType {
     pub const CODESTREAM: Self = Self(*b"jxlc");
     pub const PARTIAL_CODESTREAM: Self = Self(*b"jxlp");
     pub const JPEG_RECONSTRUCTION: Self = Self(*b"jbrd");
+    pub const GAIN_MAP: Self = Self(*b"jhgm");
 }
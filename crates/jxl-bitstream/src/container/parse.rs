@@ -1,11 +1,15 @@
 use super::box_header::*;
-use super::{BitstreamKind, ContainerDetectingReader, DetectState, JxlpIndexState};
+use super::{
+    BitstreamKind, ContainerBoxInfo, ContainerDetectingReader, DetectState, JxlpIndexState,
+};
 use crate::error::{Error, Result};
 
 /// Iterator that reads over a buffer and emits parser events.
 pub struct ParseEvents<'inner, 'buf> {
     inner: &'inner mut ContainerDetectingReader,
     remaining_input: &'buf [u8],
+    input_offset: u64,
+    input_len: usize,
     finished: bool,
 }
 
@@ -16,6 +20,8 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
     pub(super) fn new(parser: &'inner mut ContainerDetectingReader, input: &'buf [u8]) -> Self {
         parser.previous_consumed_bytes = 0;
         Self {
+            input_offset: parser.total_consumed_bytes,
+            input_len: input.len(),
             inner: parser,
             remaining_input: input,
             finished: false,
@@ -25,10 +31,27 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
     fn emit_single(&mut self) -> Result<Option<ParseEvent<'buf>>> {
         let state = &mut self.inner.state;
         let jxlp_index_state = &mut self.inner.jxlp_index_state;
+        let boxes = &mut self.inner.boxes;
+        let pending_events = &mut self.inner.pending_events;
+        let lenient = self.inner.lenient;
         let buf = &mut self.remaining_input;
 
         loop {
-            if buf.is_empty() {
+            if let Some(event) = pending_events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            // End of a box can be emitted without more data.
+            let at_box_end = matches!(
+                state,
+                DetectState::InAuxBox {
+                    bytes_left: Some(0),
+                    ..
+                } | DetectState::Skipping {
+                    bytes_left: Some(0)
+                }
+            );
+            if buf.is_empty() && !at_box_end {
                 self.finished = true;
                 return Ok(None);
             }
@@ -47,6 +70,16 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                         )));
                     } else if buf.starts_with(&Self::CONTAINER_SIG) {
                         tracing::trace!("Container signature found");
+                        let info = ContainerBoxInfo {
+                            ty: ContainerBoxType::JXL,
+                            brotli_box_type: None,
+                            offset: 0,
+                            header_size: 8,
+                            box_size: Some(4),
+                        };
+                        boxes.push(info);
+                        pending_events.push_back(ParseEvent::BoxStart(info));
+                        pending_events.push_back(ParseEvent::BoxEnd(info));
                         *state = DetectState::WaitingBoxHeader;
                         *buf = &buf[Self::CONTAINER_SIG.len()..];
                         return Ok(Some(ParseEvent::BitstreamKind(BitstreamKind::Container)));
@@ -65,91 +98,159 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                     }
                 }
 
-                DetectState::WaitingBoxHeader => match ContainerBoxHeader::parse(buf)? {
-                    HeaderParseResult::Done {
-                        header,
-                        header_size,
-                    } => {
-                        *buf = &buf[header_size..];
-                        let tbox = header.box_type();
-                        if tbox == ContainerBoxType::CODESTREAM {
-                            match jxlp_index_state {
-                                JxlpIndexState::Initial => {
-                                    *jxlp_index_state = JxlpIndexState::SingleJxlc;
-                                }
-                                JxlpIndexState::SingleJxlc => {
-                                    tracing::debug!("Duplicate jxlc box found");
-                                    return Err(Error::InvalidBox);
-                                }
-                                JxlpIndexState::Jxlp(_) | JxlpIndexState::JxlpFinished => {
-                                    tracing::debug!("Found jxlc box instead of jxlp box");
-                                    return Err(Error::InvalidBox);
-                                }
+                DetectState::WaitingBoxHeader => {
+                    let codestream_done = matches!(
+                        jxlp_index_state,
+                        JxlpIndexState::SingleJxlc | JxlpIndexState::JxlpFinished
+                    );
+                    let parsed = if lenient && codestream_done {
+                        match ContainerBoxHeader::parse(buf, false) {
+                            Ok(parsed) => parsed,
+                            Err(_) => {
+                                tracing::warn!("Ignoring trailing data after the codestream");
+                                *state = DetectState::Skipping { bytes_left: None };
+                                continue;
                             }
+                        }
+                    } else {
+                        ContainerBoxHeader::parse(buf, lenient)?
+                    };
 
-                            let bytes_left = header.box_size().map(|x| x as usize);
-                            *state = DetectState::InCodestream {
-                                kind: BitstreamKind::Container,
-                                bytes_left,
-                                pending_no_more_aux_box: bytes_left.is_none(),
+                    match parsed {
+                        HeaderParseResult::Done {
+                            header,
+                            header_size,
+                        } => {
+                            let info = ContainerBoxInfo {
+                                ty: header.box_type(),
+                                brotli_box_type: None,
+                                offset: self.input_offset + (self.input_len - buf.len()) as u64,
+                                header_size,
+                                box_size: header.box_size(),
                             };
-                        } else if tbox == ContainerBoxType::PARTIAL_CODESTREAM {
-                            if let Some(box_size) = header.box_size() {
-                                if box_size < 4 {
-                                    return Err(Error::InvalidBox);
+                            boxes.push(info);
+                            pending_events.push_back(ParseEvent::BoxStart(info));
+                            *buf = &buf[header_size..];
+                            let tbox = header.box_type();
+                            if tbox == ContainerBoxType::CODESTREAM {
+                                match jxlp_index_state {
+                                    JxlpIndexState::Initial => {
+                                        *jxlp_index_state = JxlpIndexState::SingleJxlc;
+                                    }
+                                    JxlpIndexState::SingleJxlc if lenient => {
+                                        tracing::warn!(
+                                            "Duplicate jxlc box found, reading in order"
+                                        );
+                                    }
+                                    JxlpIndexState::SingleJxlc => {
+                                        tracing::debug!("Duplicate jxlc box found");
+                                        return Err(Error::InvalidBox);
+                                    }
+                                    JxlpIndexState::Jxlp(_) | JxlpIndexState::JxlpFinished
+                                        if lenient =>
+                                    {
+                                        tracing::warn!(
+                                            "Found jxlc box instead of jxlp box, reading in order"
+                                        );
+                                        *jxlp_index_state = JxlpIndexState::SingleJxlc;
+                                    }
+                                    JxlpIndexState::Jxlp(_) | JxlpIndexState::JxlpFinished => {
+                                        tracing::debug!("Found jxlc box instead of jxlp box");
+                                        return Err(Error::InvalidBox);
+                                    }
                                 }
-                            }
 
-                            match jxlp_index_state {
-                                JxlpIndexState::Initial => {
-                                    *jxlp_index_state = JxlpIndexState::Jxlp(0);
-                                }
-                                JxlpIndexState::Jxlp(index) => {
-                                    *index += 1;
-                                }
-                                JxlpIndexState::SingleJxlc => {
-                                    tracing::debug!("jxlp box found after jxlc box");
-                                    return Err(Error::InvalidBox);
-                                }
-                                JxlpIndexState::JxlpFinished => {
-                                    tracing::debug!("found another jxlp box after the final one");
-                                    return Err(Error::InvalidBox);
+                                let bytes_left = header.box_size().map(|x| x as usize);
+                                *state = DetectState::InCodestream {
+                                    kind: BitstreamKind::Container,
+                                    bytes_left,
+                                    pending_no_more_aux_box: bytes_left.is_none(),
+                                };
+                            } else if tbox == ContainerBoxType::PARTIAL_CODESTREAM {
+                                if let Some(box_size) = header.box_size() {
+                                    if box_size < 4 && lenient {
+                                        tracing::warn!(box_size, "Skipping too small jxlp box");
+                                        *state = DetectState::Skipping {
+                                            bytes_left: Some(box_size as usize),
+                                        };
+                                        continue;
+                                    }
+                                    if box_size < 4 {
+                                        return Err(Error::InvalidBox);
+                                    }
                                 }
-                            }
 
-                            *state = DetectState::WaitingJxlpIndex(header);
-                        } else {
-                            let bytes_left = header.box_size().map(|x| x as usize);
-                            let ty = header.box_type();
-                            let mut brotli_compressed = ty == ContainerBoxType::BROTLI_COMPRESSED;
-                            if brotli_compressed {
-                                if let Some(0..=3) = bytes_left {
-                                    tracing::error!(
-                                        bytes_left = bytes_left.unwrap(),
-                                        "Brotli-compressed box is too small"
+                                match jxlp_index_state {
+                                    JxlpIndexState::Initial => {
+                                        *jxlp_index_state = JxlpIndexState::Jxlp(0);
+                                    }
+                                    JxlpIndexState::Jxlp(index) => {
+                                        *index += 1;
+                                    }
+                                    JxlpIndexState::SingleJxlc | JxlpIndexState::JxlpFinished
+                                        if lenient =>
+                                    {
+                                        tracing::warn!(
+                                        "jxlp box found after the end of codestream, reading in \
+                                         order"
                                     );
-                                    return Err(Error::InvalidBox);
+                                        *jxlp_index_state = JxlpIndexState::Jxlp(0);
+                                    }
+                                    JxlpIndexState::SingleJxlc => {
+                                        tracing::debug!("jxlp box found after jxlc box");
+                                        return Err(Error::InvalidBox);
+                                    }
+                                    JxlpIndexState::JxlpFinished => {
+                                        tracing::debug!(
+                                            "found another jxlp box after the final one"
+                                        );
+                                        return Err(Error::InvalidBox);
+                                    }
                                 }
-                                brotli_compressed = true;
-                            }
 
-                            *state = DetectState::InAuxBox {
-                                header,
-                                brotli_box_type: None,
-                                bytes_left,
-                            };
+                                *state = DetectState::WaitingJxlpIndex(header);
+                            } else {
+                                let bytes_left = header.box_size().map(|x| x as usize);
+                                let ty = header.box_type();
+                                let mut brotli_compressed =
+                                    ty == ContainerBoxType::BROTLI_COMPRESSED;
+                                if brotli_compressed {
+                                    if let (Some(0..=3), true) = (bytes_left, lenient) {
+                                        tracing::warn!(
+                                            bytes_left = bytes_left.unwrap(),
+                                            "Skipping too small Brotli-compressed box"
+                                        );
+                                        *state = DetectState::Skipping { bytes_left };
+                                        continue;
+                                    }
+                                    if let Some(0..=3) = bytes_left {
+                                        tracing::error!(
+                                            bytes_left = bytes_left.unwrap(),
+                                            "Brotli-compressed box is too small"
+                                        );
+                                        return Err(Error::InvalidBox);
+                                    }
+                                    brotli_compressed = true;
+                                }
 
-                            if !brotli_compressed {
-                                return Ok(Some(ParseEvent::AuxBoxStart {
-                                    ty,
-                                    brotli_compressed: false,
-                                    last_box: bytes_left.is_none(),
-                                }));
+                                *state = DetectState::InAuxBox {
+                                    header,
+                                    brotli_box_type: None,
+                                    bytes_left,
+                                };
+
+                                if !brotli_compressed {
+                                    pending_events.push_back(ParseEvent::AuxBoxStart {
+                                        ty,
+                                        brotli_compressed: false,
+                                        last_box: bytes_left.is_none(),
+                                    });
+                                }
                             }
                         }
+                        HeaderParseResult::NeedMoreData => return Ok(None),
                     }
-                    HeaderParseResult::NeedMoreData => return Ok(None),
-                },
+                }
 
                 DetectState::WaitingJxlpIndex(header) => {
                     let &[b0, b1, b2, b3, ..] = &**buf else {
@@ -167,6 +268,18 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                                 *jxlp_index_state = JxlpIndexState::JxlpFinished;
                             }
                         }
+                        JxlpIndexState::Jxlp(expected_index) if lenient => {
+                            tracing::warn!(
+                                expected_index,
+                                actual_index = index,
+                                "Out-of-order jxlp box found, reading in order",
+                            );
+                            *jxlp_index_state = if is_last {
+                                JxlpIndexState::JxlpFinished
+                            } else {
+                                JxlpIndexState::Jxlp(index)
+                            };
+                        }
                         JxlpIndexState::Jxlp(expected_index) => {
                             tracing::debug!(
                                 expected_index,
@@ -212,6 +325,9 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                         let (payload, remaining) = buf.split_at(*bytes_left);
                         *state = DetectState::WaitingBoxHeader;
                         *buf = remaining;
+                        if let Some(&info) = boxes.last() {
+                            pending_events.push_back(ParseEvent::BoxEnd(info));
+                        }
                         payload
                     } else {
                         let payload = *buf;
@@ -246,6 +362,15 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                     ty.copy_from_slice(ty_slice);
                     let is_reserved_box_type =
                         &ty[..3] == b"jxl" || &ty == b"brob" || &ty == b"jbrd";
+                    if is_reserved_box_type && lenient {
+                        tracing::warn!(
+                            ty = ?ContainerBoxType(ty),
+                            "Skipping Brotli-compressed box with reserved box type"
+                        );
+                        let bytes_left = *bytes_left;
+                        *state = DetectState::Skipping { bytes_left };
+                        continue;
+                    }
                     if is_reserved_box_type {
                         return Err(Error::ValidationFailed(
                             "brob box, jxl boxes and jbrd box cannot be Brotli-compressed",
@@ -254,6 +379,9 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
 
                     let ty = ContainerBoxType(ty);
                     *brotli_box_type = Some(ty);
+                    if let Some(info) = boxes.last_mut() {
+                        info.brotli_box_type = Some(ty);
+                    }
 
                     return Ok(Some(ParseEvent::AuxBoxStart {
                         ty,
@@ -262,6 +390,23 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                     }));
                 }
 
+                DetectState::Skipping { bytes_left: None } => {
+                    *buf = &[];
+                }
+                DetectState::Skipping {
+                    bytes_left: Some(bytes_left),
+                } => {
+                    let num_bytes_to_skip = (*bytes_left).min(buf.len());
+                    *buf = &buf[num_bytes_to_skip..];
+                    *bytes_left -= num_bytes_to_skip;
+                    if *bytes_left == 0 {
+                        *state = DetectState::WaitingBoxHeader;
+                        if let Some(&info) = boxes.last() {
+                            pending_events.push_back(ParseEvent::BoxEnd(info));
+                        }
+                    }
+                }
+
                 DetectState::InAuxBox {
                     header: ContainerBoxHeader { ty, .. },
                     brotli_box_type,
@@ -276,6 +421,9 @@ impl<'inner, 'buf> ParseEvents<'inner, 'buf> {
                     let payload = match bytes_left {
                         Some(0) => {
                             *state = DetectState::WaitingBoxHeader;
+                            if let Some(&info) = boxes.last() {
+                                pending_events.push_back(ParseEvent::BoxEnd(info));
+                            }
                             return Ok(Some(ParseEvent::AuxBoxEnd(ty)));
                         }
                         Some(bytes_left) => {
@@ -327,12 +475,19 @@ impl<'buf> Iterator for ParseEvents<'_, 'buf> {
             self.finished = true;
         }
 
-        self.inner.previous_consumed_bytes += initial_buf.len() - self.remaining_input.len();
+        let consumed_bytes = initial_buf.len() - self.remaining_input.len();
+        self.inner.previous_consumed_bytes += consumed_bytes;
+        self.inner.total_consumed_bytes += consumed_bytes as u64;
         event.transpose()
     }
 }
 
 /// Parser event emitted by [`ParseEvents`].
+///
+/// Box-level events, [`BoxStart`][Self::BoxStart] and [`BoxEnd`][Self::BoxEnd], are emitted for
+/// every box in the container including the signature box, codestream boxes and unknown boxes, so
+/// that the layout of the file can be indexed. Events about the contents of boxes are emitted
+/// between them.
 pub enum ParseEvent<'buf> {
     /// Bitstream structure is detected.
     BitstreamKind(BitstreamKind),
@@ -342,6 +497,13 @@ pub enum ParseEvent<'buf> {
     /// of `Codestream` events.
     Codestream(&'buf [u8]),
     NoMoreAuxBox,
+    /// Box header is read.
+    BoxStart(ContainerBoxInfo),
+    /// Box payload is read to the end.
+    ///
+    /// This is not emitted for the box extending to the end of the file, and the box being read
+    /// when parsing is resumed with [`ContainerDetectingReader::resume_at`].
+    BoxEnd(ContainerBoxInfo),
     AuxBoxStart {
         ty: ContainerBoxType,
         brotli_compressed: bool,
@@ -360,6 +522,8 @@ impl std::fmt::Debug for ParseEvent<'_> {
                 .field(&format_args!("{} byte(s)", buf.len()))
                 .finish(),
             Self::NoMoreAuxBox => write!(f, "NoMoreAuxBox"),
+            Self::BoxStart(info) => f.debug_tuple("BoxStart").field(info).finish(),
+            Self::BoxEnd(info) => f.debug_tuple("BoxEnd").field(info).finish(),
             Self::AuxBoxStart {
                 ty,
                 brotli_compressed,
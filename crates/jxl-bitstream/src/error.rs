@@ -15,6 +15,11 @@ pub enum Error {
     },
     /// The bitstream is invalid.
     ValidationFailed(&'static str),
+    /// Table of contents of a frame declares more entries than allowed.
+    TooManyTocEntries {
+        entry_count: u64,
+        limit: u64,
+    },
     /// The codestream does not conform to the current decoder profile.
     ProfileConformance(&'static str),
     /// The bitstream couldn't be skipped to the given position, mainly due to the direction being
@@ -52,6 +57,9 @@ impl std::fmt::Display for Error {
             Self::ValidationFailed(msg) => {
                 write!(f, "bitstream validation failed: {msg}")
             }
+            Self::TooManyTocEntries { entry_count, limit } => {
+                write!(f, "too many TOC entries ({entry_count}); limit is {limit}")
+            }
             Self::ProfileConformance(msg) => {
                 write!(f, "not supported by current profile: {msg}")
             }
@@ -0,0 +1,122 @@
+use crate::Bitstream;
+
+/// Result of parsing with [`IncrementalBuffer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incremental<T> {
+    /// Parsing is done.
+    Done {
+        /// Parsed value.
+        value: T,
+        /// Number of bytes consumed from the newly given bytes. Buffered bytes are always
+        /// consumed as a whole.
+        consumed: usize,
+    },
+    /// Data ran out while parsing, and more bytes are needed.
+    ///
+    /// Given bytes are buffered, and parsing should be retried with subsequent bytes.
+    NeedMoreBytes {
+        /// Offset where the data ran out, counted from the first buffered byte.
+        offset: usize,
+    },
+}
+
+/// Buffer for parsing headers from partially available data.
+///
+/// Data given to [`parse`][Self::parse] is parsed in place if nothing is buffered, so that
+/// streaming decoders don't need to copy bytes which are parsed in a single call. Only the bytes
+/// of a header which is split across calls are copied into the buffer, which is usually small.
+#[derive(Debug, Default, Clone)]
+pub struct IncrementalBuffer {
+    pending: Vec<u8>,
+}
+
+impl IncrementalBuffer {
+    /// Minimum number of bytes copied at once when retrying with buffered bytes.
+    const MIN_CHUNK_SIZE: usize = 64;
+
+    /// Creates an empty buffer.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns buffered bytes, which are waiting for subsequent bytes.
+    #[inline]
+    pub fn pending(&self) -> &[u8] {
+        &self.pending
+    }
+
+    /// Returns whether nothing is buffered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Discards buffered bytes.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Parses a value from buffered bytes followed by `bytes`, using `parse`.
+    ///
+    /// `parse` may be called multiple times with growing data, until it succeeds or data runs
+    /// out. Errors are treated as end of data if `is_eof` returns `true`, and every other error is
+    /// returned as is, leaving the buffer unchanged.
+    ///
+    /// Parsed values should end at byte boundary; the number of consumed bytes is rounded down.
+    pub fn parse<T, E>(
+        &mut self,
+        bytes: &[u8],
+        mut parse: impl FnMut(&mut Bitstream) -> Result<T, E>,
+        is_eof: impl Fn(&E) -> bool,
+    ) -> Result<Incremental<T>, E> {
+        if self.pending.is_empty() {
+            let mut bitstream = Bitstream::new(bytes);
+            return match parse(&mut bitstream) {
+                Ok(value) => Ok(Incremental::Done {
+                    value,
+                    consumed: bitstream.num_read_bits() / 8,
+                }),
+                Err(e) if is_eof(&e) => {
+                    self.pending.extend_from_slice(bytes);
+                    Ok(Incremental::NeedMoreBytes {
+                        offset: self.pending.len(),
+                    })
+                }
+                Err(e) => Err(e),
+            };
+        }
+
+        // Every buffered byte is needed, as the last try ran out of data. Copy subsequent bytes
+        // gradually, so that only the bytes of the value are copied.
+        let buffered_len = self.pending.len();
+        let mut copied = 0usize;
+        loop {
+            let chunk_size = (buffered_len + copied).max(Self::MIN_CHUNK_SIZE);
+            let next = bytes.len().min(copied + chunk_size);
+            self.pending.extend_from_slice(&bytes[copied..next]);
+            copied = next;
+
+            let mut bitstream = Bitstream::new(&self.pending);
+            match parse(&mut bitstream) {
+                Ok(value) => {
+                    let consumed = (bitstream.num_read_bits() / 8).saturating_sub(buffered_len);
+                    self.pending.clear();
+                    return Ok(Incremental::Done { value, consumed });
+                }
+                Err(e) if is_eof(&e) => {
+                    if copied == bytes.len() {
+                        return Ok(Incremental::NeedMoreBytes {
+                            offset: self.pending.len(),
+                        });
+                    }
+                }
+                Err(e) => {
+                    self.pending.truncate(buffered_len);
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
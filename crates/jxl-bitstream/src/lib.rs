@@ -4,10 +4,12 @@
 mod bitstream;
 pub mod container;
 mod error;
+mod incremental;
 
 pub use bitstream::{Bitstream, U32Specifier, U};
-pub use container::{BitstreamKind, ContainerDetectingReader, ParseEvent};
+pub use container::{BitstreamKind, ContainerDetectingReader, ParseEvent, ResumePosition};
 pub use error::{Error, Result};
+pub use incremental::{Incremental, IncrementalBuffer};
 
 /// Perform `UnpackSigned` for `u32`, as specified in the JPEG XL specification.
 #[inline]
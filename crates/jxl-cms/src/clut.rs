@@ -0,0 +1,277 @@
+use crate::{Error, Result};
+
+/// Maximum number of channels an ICC profile can have.
+pub(crate) const MAX_CHANNELS: usize = 15;
+
+/// Multidimensional color lookup table.
+#[derive(Debug, Clone)]
+pub(crate) struct Clut {
+    grid_points: Vec<usize>,
+    /// Strides of each input dimension, in number of samples.
+    strides: Vec<usize>,
+    out_channels: usize,
+    data: Vec<f32>,
+}
+
+impl Clut {
+    /// Parses CLUT of `lutAtoBType` or `lutBtoAType`.
+    pub(crate) fn parse(data: &[u8], in_channels: usize, out_channels: usize) -> Result<Self> {
+        if in_channels == 0 || in_channels > MAX_CHANNELS {
+            return Err(Error::IccParseFailure("invalid number of CLUT channels"));
+        }
+        if data.len() < 20 {
+            return Err(Error::IccParseFailure("CLUT data too short"));
+        }
+
+        let grid_points = data[..in_channels]
+            .iter()
+            .map(|&v| v as usize)
+            .collect::<Vec<_>>();
+        let precision = data[16] as usize;
+        if precision != 1 && precision != 2 {
+            return Err(Error::IccParseFailure("invalid CLUT precision"));
+        }
+
+        let num_samples = Self::num_samples(&grid_points, out_channels)?;
+        let data = decode_samples(&data[20..], num_samples, precision)?;
+        Self::new(grid_points, out_channels, data)
+    }
+
+    /// Parses CLUT of `lut8Type` or `lut16Type`, which has the same number of grid points in
+    /// every dimension.
+    ///
+    /// Returns the CLUT and the number of bytes consumed.
+    pub(crate) fn parse_legacy(
+        data: &[u8],
+        grid_points: usize,
+        in_channels: usize,
+        out_channels: usize,
+        precision: usize,
+    ) -> Result<(Self, usize)> {
+        if in_channels == 0 || in_channels > MAX_CHANNELS {
+            return Err(Error::IccParseFailure("invalid number of CLUT channels"));
+        }
+
+        let grid_points = vec![grid_points; in_channels];
+        let num_samples = Self::num_samples(&grid_points, out_channels)?;
+        let data = decode_samples(data, num_samples, precision)?;
+        let clut = Self::new(grid_points, out_channels, data)?;
+        Ok((clut, num_samples * precision))
+    }
+
+    fn num_samples(grid_points: &[usize], out_channels: usize) -> Result<usize> {
+        if grid_points.contains(&0) {
+            return Err(Error::IccParseFailure("CLUT has zero grid points"));
+        }
+        grid_points
+            .iter()
+            .try_fold(out_channels, |acc, &v| acc.checked_mul(v))
+            .ok_or(Error::IccParseFailure("CLUT too large"))
+    }
+
+    pub(crate) fn new(
+        grid_points: Vec<usize>,
+        out_channels: usize,
+        data: Vec<f32>,
+    ) -> Result<Self> {
+        if grid_points.is_empty()
+            || grid_points.len() > MAX_CHANNELS
+            || out_channels == 0
+            || out_channels > MAX_CHANNELS
+        {
+            return Err(Error::IccParseFailure("invalid number of CLUT channels"));
+        }
+        if data.len() != Self::num_samples(&grid_points, out_channels)? {
+            return Err(Error::IccParseFailure("CLUT size mismatch"));
+        }
+
+        let mut strides = vec![0usize; grid_points.len()];
+        let mut stride = out_channels;
+        for (s, &points) in strides.iter_mut().zip(&grid_points).rev() {
+            *s = stride;
+            stride *= points;
+        }
+
+        Ok(Self {
+            grid_points,
+            strides,
+            out_channels,
+            data,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn in_channels(&self) -> usize {
+        self.grid_points.len()
+    }
+
+    #[inline]
+    pub(crate) fn out_channels(&self) -> usize {
+        self.out_channels
+    }
+
+    /// Evaluates the CLUT at the given input.
+    ///
+    /// Three-dimensional part of the table is evaluated using tetrahedral interpolation, and
+    /// remaining dimensions are linearly interpolated.
+    pub(crate) fn eval(&self, input: &[f32], output: &mut [f32]) {
+        self.eval_dim(0, 0, input, &mut output[..self.out_channels]);
+    }
+
+    fn grid_pos(&self, dim: usize, value: f32) -> (usize, usize, f32) {
+        let points = self.grid_points[dim];
+        if points == 1 {
+            return (0, 0, 0.0);
+        }
+
+        let max = (points - 1) as f32;
+        let x = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0) * max
+        };
+        let idx = (x as usize).min(points - 2);
+        (idx * self.strides[dim], self.strides[dim], x - idx as f32)
+    }
+
+    fn eval_dim(&self, dim: usize, base: usize, input: &[f32], output: &mut [f32]) {
+        let remaining = self.in_channels() - dim;
+        if remaining == 0 {
+            output.copy_from_slice(&self.data[base..][..self.out_channels]);
+            return;
+        }
+        if remaining == 3 {
+            self.eval_tetrahedral(dim, base, input, output);
+            return;
+        }
+
+        let (offset, step, frac) = self.grid_pos(dim, input[dim]);
+        let mut hi = [0f32; MAX_CHANNELS];
+        let hi = &mut hi[..self.out_channels];
+        self.eval_dim(dim + 1, base + offset, input, output);
+        if step == 0 || frac == 0.0 {
+            return;
+        }
+        self.eval_dim(dim + 1, base + offset + step, input, hi);
+        for (lo, hi) in output.iter_mut().zip(hi) {
+            *lo += (*hi - *lo) * frac;
+        }
+    }
+
+    fn eval_tetrahedral(&self, dim: usize, base: usize, input: &[f32], output: &mut [f32]) {
+        let (ox, sx, rx) = self.grid_pos(dim, input[dim]);
+        let (oy, sy, ry) = self.grid_pos(dim + 1, input[dim + 1]);
+        let (oz, sz, rz) = self.grid_pos(dim + 2, input[dim + 2]);
+
+        let base = base + ox + oy + oz;
+        let sample = |offset: usize, c: usize| self.data[base + offset + c];
+
+        // Pick the tetrahedron containing the point, and walk along its edges from the origin.
+        let (first, second) = if rx >= ry && ry >= rz {
+            (sx, sx + sy)
+        } else if rx >= rz && rz >= ry {
+            (sx, sx + sz)
+        } else if rz >= rx && rx >= ry {
+            (sz, sx + sz)
+        } else if ry >= rx && rx >= rz {
+            (sy, sx + sy)
+        } else if ry >= rz && rz >= rx {
+            (sy, sy + sz)
+        } else {
+            (sz, sy + sz)
+        };
+        let last = sx + sy + sz;
+
+        let mut weights = [rx, ry, rz];
+        weights.sort_unstable_by(|a, b| b.total_cmp(a));
+        let [w0, w1, w2] = weights;
+
+        for (c, out) in output.iter_mut().enumerate() {
+            let c000 = sample(0, c);
+            let c1 = sample(first, c);
+            let c2 = sample(second, c);
+            let c3 = sample(last, c);
+            *out = c000 + (c1 - c000) * w0 + (c2 - c1) * w1 + (c3 - c2) * w2;
+        }
+    }
+}
+
+/// Decodes normalized samples with the given precision in bytes.
+pub(crate) fn decode_samples(
+    data: &[u8],
+    num_samples: usize,
+    precision: usize,
+) -> Result<Vec<f32>> {
+    let data = num_samples
+        .checked_mul(precision)
+        .and_then(|len| data.get(..len))
+        .ok_or(Error::IccParseFailure("CLUT data too short"))?;
+    Ok(if precision == 1 {
+        data.iter().map(|&v| v as f32 / 255.0).collect()
+    } else {
+        data.chunks_exact(2)
+            .map(|v| u16::from_be_bytes([v[0], v[1]]) as f32 / 65535.0)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear_map(input: [f32; 3]) -> [f32; 2] {
+        let [x, y, z] = input;
+        [
+            0.1 + 0.5 * x + 0.2 * y + 0.1 * z,
+            0.9 - 0.3 * x + 0.1 * y - 0.4 * z,
+        ]
+    }
+
+    #[test]
+    fn tetrahedral_linear_exact() {
+        let mut data = Vec::new();
+        for x in 0..3 {
+            for y in 0..4 {
+                for z in 0..5 {
+                    let input = [x as f32 / 2.0, y as f32 / 3.0, z as f32 / 4.0];
+                    data.extend(linear_map(input));
+                }
+            }
+        }
+        let clut = Clut::new(vec![3, 4, 5], 2, data).unwrap();
+
+        for input in [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.3, 0.7, 0.1],
+            [0.9, 0.2, 0.6],
+            [0.25, 0.25, 0.8],
+        ] {
+            let expected = linear_map(input);
+            let mut output = [0f32; 2];
+            clut.eval(&input, &mut output);
+            for (e, o) in expected.into_iter().zip(output) {
+                assert!((e - o).abs() < 1e-5, "input = {input:?}, {e} != {o}");
+            }
+        }
+    }
+
+    #[test]
+    fn four_inputs() {
+        let mut data = Vec::new();
+        for w in 0..2 {
+            for x in 0..2 {
+                for y in 0..2 {
+                    for z in 0..2 {
+                        data.push((w + x + y + z) as f32 / 4.0);
+                    }
+                }
+            }
+        }
+        let clut = Clut::new(vec![2; 4], 1, data).unwrap();
+
+        let mut output = [0f32];
+        clut.eval(&[0.5, 0.25, 0.75, 1.0], &mut output);
+        assert!((output[0] - 0.625).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{Error, Result};
+
+/// One-dimensional transfer curve of an ICC profile.
+#[derive(Debug, Clone)]
+pub(crate) enum Curve {
+    Identity,
+    Gamma(f32),
+    /// Parametric curve of function type 4, which is general enough to represent every other type.
+    ///
+    /// `Y = (aX + b)^g + e` if `X >= d`, `Y = cX + f` otherwise.
+    Parametric {
+        g: f32,
+        a: f32,
+        b: f32,
+        c: f32,
+        d: f32,
+        e: f32,
+        f: f32,
+    },
+    Table(Vec<f32>),
+}
+
+impl Curve {
+    /// Parses `curveType` or `parametricCurveType` at the start of `data`.
+    ///
+    /// Returns the curve and the number of bytes consumed, excluding padding.
+    pub(crate) fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        if data.len() < 12 {
+            return Err(Error::IccParseFailure("curve data too short"));
+        }
+
+        match &data[..4] {
+            b"curv" => {
+                let count = u32::from_be_bytes([data[8], data[9], data[10], data[11]]) as usize;
+                let size = count
+                    .checked_mul(2)
+                    .and_then(|x| x.checked_add(12))
+                    .ok_or(Error::IccParseFailure("curve data too short"))?;
+                if data.len() < size {
+                    return Err(Error::IccParseFailure("curve data too short"));
+                }
+
+                let entries = &data[12..size];
+                let curve = match count {
+                    0 => Self::Identity,
+                    1 => {
+                        let gamma = u16::from_be_bytes([entries[0], entries[1]]) as f32 / 256.0;
+                        if gamma == 1.0 {
+                            Self::Identity
+                        } else {
+                            Self::Gamma(gamma)
+                        }
+                    }
+                    _ => Self::Table(
+                        entries
+                            .chunks_exact(2)
+                            .map(|v| u16::from_be_bytes([v[0], v[1]]) as f32 / 65535.0)
+                            .collect(),
+                    ),
+                };
+                Ok((curve, size))
+            }
+            b"para" => {
+                let function_type = u16::from_be_bytes([data[8], data[9]]);
+                let num_params = match function_type {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return Err(Error::IccParseFailure("invalid parametricCurveType")),
+                };
+                let size = 12 + num_params * 4;
+                if data.len() < size {
+                    return Err(Error::IccParseFailure("curve data too short"));
+                }
+
+                let mut params = [0f32; 7];
+                for (param, bytes) in params.iter_mut().zip(data[12..size].chunks_exact(4)) {
+                    let v = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    *param = v as f32 / 65536.0;
+                }
+
+                let curve = match function_type {
+                    0 => {
+                        let [g, ..] = params;
+                        if g == 1.0 {
+                            Self::Identity
+                        } else {
+                            Self::Gamma(g)
+                        }
+                    }
+                    1 => {
+                        let [g, a, b, ..] = params;
+                        Self::Parametric {
+                            g,
+                            a,
+                            b,
+                            c: 0.0,
+                            d: -b / a,
+                            e: 0.0,
+                            f: 0.0,
+                        }
+                    }
+                    2 => {
+                        let [g, a, b, c, ..] = params;
+                        Self::Parametric {
+                            g,
+                            a,
+                            b,
+                            c: 0.0,
+                            d: -b / a,
+                            e: c,
+                            f: c,
+                        }
+                    }
+                    3 => {
+                        let [g, a, b, c, d, ..] = params;
+                        Self::Parametric {
+                            g,
+                            a,
+                            b,
+                            c,
+                            d,
+                            e: 0.0,
+                            f: 0.0,
+                        }
+                    }
+                    4 => {
+                        let [g, a, b, c, d, e, f] = params;
+                        Self::Parametric {
+                            g,
+                            a,
+                            b,
+                            c,
+                            d,
+                            e,
+                            f,
+                        }
+                    }
+                    _ => unreachable!(),
+                };
+                Ok((curve, size))
+            }
+            _ => Err(Error::IccParseFailure("invalid curve type")),
+        }
+    }
+
+    /// Parses `count` curves stored consecutively, each aligned to 4 bytes.
+    pub(crate) fn parse_many(data: &[u8], count: usize) -> Result<Vec<Self>> {
+        let mut offset = 0usize;
+        let mut out = Vec::with_capacity(count);
+        for _ in 0..count {
+            let data = data
+                .get(offset..)
+                .ok_or(Error::IccParseFailure("curve data too short"))?;
+            let (curve, size) = Self::parse(data)?;
+            out.push(curve);
+            offset += (size + 3) & !3;
+        }
+        Ok(out)
+    }
+
+    #[inline]
+    pub(crate) fn is_identity(&self) -> bool {
+        matches!(self, Self::Identity)
+    }
+
+    pub(crate) fn eval(&self, x: f32) -> f32 {
+        match *self {
+            Self::Identity => x,
+            Self::Gamma(g) => x.max(0.0).powf(g),
+            Self::Parametric {
+                g,
+                a,
+                b,
+                c,
+                d,
+                e,
+                f,
+            } => {
+                if x >= d {
+                    (a * x + b).max(0.0).powf(g) + e
+                } else {
+                    c * x + f
+                }
+            }
+            Self::Table(ref lut) => {
+                let x = x.clamp(0.0, 1.0) * (lut.len() - 1) as f32;
+                let idx = (x as usize).min(lut.len() - 2);
+                let frac = x - idx as f32;
+                lut[idx] + (lut[idx + 1] - lut[idx]) * frac
+            }
+        }
+    }
+
+    /// Creates the inverse of the curve, assuming that the curve is monotonic in `[0, 1]`.
+    pub(crate) fn inverse(&self) -> Self {
+        const INVERSE_TABLE_SIZE: usize = 4096;
+
+        match *self {
+            Self::Identity => Self::Identity,
+            Self::Gamma(g) => Self::Gamma(1.0 / g),
+            _ => {
+                let start = self.eval(0.0);
+                let end = self.eval(1.0);
+                let increasing = start <= end;
+
+                let lut = (0..INVERSE_TABLE_SIZE)
+                    .map(|idx| {
+                        let y = idx as f32 / (INVERSE_TABLE_SIZE - 1) as f32;
+                        let mut lo = 0f32;
+                        let mut hi = 1f32;
+                        // Bisection is enough here; the table is built only once per transform.
+                        for _ in 0..24 {
+                            let mid = (lo + hi) / 2.0;
+                            if (self.eval(mid) < y) == increasing {
+                                lo = mid;
+                            } else {
+                                hi = mid;
+                            }
+                        }
+                        (lo + hi) / 2.0
+                    })
+                    .collect();
+                Self::Table(lut)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_para_inverse() {
+        let curve = Curve::Parametric {
+            g: 2.4,
+            a: 1.0 / 1.055,
+            b: 0.055 / 1.055,
+            c: 1.0 / 12.92,
+            d: 0.04045,
+            e: 0.0,
+            f: 0.0,
+        };
+        let inverse = curve.inverse();
+        for idx in 0..=100 {
+            let x = idx as f32 / 100.0;
+            let y = inverse.eval(curve.eval(x));
+            assert!((x - y).abs() < 1e-3, "x = {x}, y = {y}");
+        }
+    }
+
+    #[test]
+    fn parse_curv_gamma() {
+        let data = [b'c', b'u', b'r', b'v', 0, 0, 0, 0, 0, 0, 0, 1, 2, 0x33];
+        let (curve, size) = Curve::parse(&data).unwrap();
+        assert_eq!(size, 14);
+        let Curve::Gamma(g) = curve else { panic!() };
+        assert!((g - 2.2).abs() < 1e-2);
+    }
+}
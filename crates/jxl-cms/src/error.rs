@@ -0,0 +1,24 @@
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    IccParseFailure(&'static str),
+    UnsupportedIccProfile(&'static str),
+    NotEnoughChannels { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IccParseFailure(s) => write!(f, "parsing ICC profile failed: {s}"),
+            Error::UnsupportedIccProfile(s) => write!(f, "unsupported ICC profile: {s}"),
+            Error::NotEnoughChannels { expected, actual } => write!(
+                f,
+                "not enough channels for color transform: expected {expected}, got {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
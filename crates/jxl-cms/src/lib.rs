@@ -0,0 +1,386 @@
+//! This crate provides a color management system written in pure Rust, which can be used as a
+//! fallback when Little CMS 2 is not available.
+//!
+//! [`PureRustCms`] implements [`ColorManagementSystem`] and supports the following kinds of ICC
+//! profiles:
+//! - Matrix/TRC based RGB profiles, and grayscale profiles with `grayTRC` tag.
+//! - LUT based profiles with `lutAtoBType` (`mAB `), `lutBtoAType` (`mBA `), `lut8Type` (`mft1`)
+//!   and `lut16Type` (`mft2`) tags, including CMYK profiles. Color lookup tables are evaluated
+//!   using tetrahedral interpolation.
+//!
+//! Transforms are done in the profile connection space (PCS) using 32-bit floats. Absolute
+//! colorimetric intent is handled by scaling with media white points.
+
+use jxl_color::{ColorManagementSystem, PreparedTransform, RenderingIntent};
+
+mod clut;
+mod curve;
+mod error;
+mod pipeline;
+mod profile;
+
+pub use error::{Error, Result};
+
+use clut::MAX_CHANNELS;
+use pipeline::Pipeline;
+use profile::Profile;
+
+/// Number of pixels processed at once by the pipeline.
+const CHUNK_SIZE: usize = 1024;
+
+/// Color management system written in pure Rust.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PureRustCms;
+
+impl ColorManagementSystem for PureRustCms {
+    fn transform_impl(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+        channels: &mut [&mut [f32]],
+    ) -> std::result::Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let transform = Transform::new(from, to, intent)?;
+        Ok(transform.run(channels)?)
+    }
+
+    fn prepare_transform(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+    ) -> std::result::Result<
+        Option<Box<dyn PreparedTransform>>,
+        Box<dyn std::error::Error + Send + Sync + 'static>,
+    > {
+        let transform = Transform::new(from, to, intent)?;
+        Ok(Some(Box::new(transform)))
+    }
+}
+
+/// Color transform between two ICC profiles.
+struct Transform {
+    from_channels: usize,
+    to_channels: usize,
+    pipeline: Pipeline,
+}
+
+impl Transform {
+    fn new(from: &[u8], to: &[u8], intent: RenderingIntent) -> Result<Self> {
+        let from_profile = Profile::parse(from)?;
+        let to_profile = Profile::parse(to)?;
+        let from_channels = from_profile.channels()?;
+        let to_channels = to_profile.channels()?;
+
+        let mut pipeline = from_profile.device_to_pcs(intent)?;
+        pipeline.append(to_profile.pcs_to_device(intent)?);
+        tracing::trace!(?pipeline, "Created color transform");
+
+        Ok(Self {
+            from_channels,
+            to_channels,
+            pipeline,
+        })
+    }
+
+    fn run(&self, channels: &mut [&mut [f32]]) -> Result<usize> {
+        let max_channels = self.from_channels.max(self.to_channels);
+        if channels.len() < max_channels {
+            return Err(Error::NotEnoughChannels {
+                expected: max_channels,
+                actual: channels.len(),
+            });
+        }
+
+        let len = channels.iter().map(|x| x.len()).min().unwrap_or(0);
+        let mut buf = vec![0f32; CHUNK_SIZE * MAX_CHANNELS];
+        for idx in (0..len).step_by(CHUNK_SIZE) {
+            let chunk_len = (len - idx).min(CHUNK_SIZE);
+            let mut chunk = buf
+                .chunks_exact_mut(CHUNK_SIZE)
+                .map(|ch| &mut ch[..chunk_len])
+                .collect::<Vec<_>>();
+
+            for (buf, ch) in chunk.iter_mut().zip(&channels[..self.from_channels]) {
+                buf.copy_from_slice(&ch[idx..][..chunk_len]);
+            }
+            let num_channels = self.pipeline.run(&mut chunk, self.from_channels);
+            debug_assert_eq!(num_channels, self.to_channels);
+            for (buf, ch) in chunk.iter().zip(&mut channels[..self.to_channels]) {
+                ch[idx..][..chunk_len].copy_from_slice(buf);
+            }
+        }
+
+        Ok(self.to_channels)
+    }
+}
+
+impl PreparedTransform for Transform {
+    fn transform(
+        &self,
+        channels: &mut [&mut [f32]],
+    ) -> std::result::Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.run(channels)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jxl_color::{icc::colour_encoding_to_icc, EnumColourEncoding, TransferFunction};
+
+    use super::*;
+
+    /// Returns the colorant matrix of sRGB primaries, adapted to D50.
+    fn srgb_d50_matrix() -> [[f32; 3]; 3] {
+        [
+            [0.4360747, 0.3850649, 0.1430804],
+            [0.2225045, 0.7168786, 0.0606169],
+            [0.0139322, 0.0971045, 0.7141733],
+        ]
+    }
+
+    fn build_profile(color_space: &[u8; 4], pcs: &[u8; 4], tags: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut header = vec![0u8; 128];
+        header[8] = 4;
+        header[0x0c..0x10].copy_from_slice(b"mntr");
+        header[0x10..0x14].copy_from_slice(color_space);
+        header[0x14..0x18].copy_from_slice(pcs);
+        header[0x24..0x28].copy_from_slice(b"acsp");
+        header[0x43] = 1;
+
+        let mut tag_table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data = Vec::new();
+        let data_offset = 128 + 4 + tags.len() * 12;
+        for (tag, tag_data) in tags {
+            tag_table.extend_from_slice(tag);
+            tag_table.extend_from_slice(&((data_offset + data.len()) as u32).to_be_bytes());
+            tag_table.extend_from_slice(&(tag_data.len() as u32).to_be_bytes());
+            data.extend_from_slice(tag_data);
+            data.resize((data.len() + 3) & !3, 0);
+        }
+
+        let mut out = header;
+        out.extend(tag_table);
+        out.extend(data);
+        let len = out.len() as u32;
+        out[..4].copy_from_slice(&len.to_be_bytes());
+        out
+    }
+
+    fn identity_curves(count: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        for _ in 0..count {
+            out.extend_from_slice(&[b'c', b'u', b'r', b'v', 0, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        out
+    }
+
+    /// Creates `lutAtoBType` which maps linear sRGB into XYZ using A curves, CLUT and B curves.
+    fn srgb_linear_a2b() -> Vec<u8> {
+        let matrix = srgb_d50_matrix();
+
+        let curves = identity_curves(3);
+        let mut clut = vec![2u8; 3];
+        clut.resize(16, 0);
+        clut.extend_from_slice(&[2, 0, 0, 0]);
+        for r in 0..2 {
+            for g in 0..2 {
+                for b in 0..2 {
+                    let rgb = [r as f32, g as f32, b as f32];
+                    for row in matrix {
+                        let xyz = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+                        let encoded = (xyz * 32768.0).round() as u16;
+                        clut.extend_from_slice(&encoded.to_be_bytes());
+                    }
+                }
+            }
+        }
+
+        let b_offset = 32usize;
+        let clut_offset = b_offset + curves.len();
+        let a_offset = clut_offset + clut.len();
+        let mut out = vec![b'm', b'A', b'B', b' ', 0, 0, 0, 0, 3, 3, 0, 0];
+        for offset in [b_offset, 0, 0, clut_offset, a_offset] {
+            out.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+        out.extend_from_slice(&curves);
+        out.extend_from_slice(&clut);
+        out.extend_from_slice(&curves);
+        out
+    }
+
+    /// Creates `lut16Type` which maps linear sRGB into XYZ or legacy encoded Lab.
+    fn srgb_linear_lut16(lab: bool) -> Vec<u8> {
+        let matrix = srgb_d50_matrix();
+
+        let mut out = vec![b'm', b'f', b't', b'2', 0, 0, 0, 0, 3, 3, 2, 0];
+        for v in [1i32, 0, 0, 0, 1, 0, 0, 0, 1] {
+            out.extend_from_slice(&(v << 16).to_be_bytes());
+        }
+        out.extend_from_slice(&[0, 2, 0, 2]);
+        let identity_tables = [0u8, 0, 0xff, 0xff].repeat(3);
+        out.extend_from_slice(&identity_tables);
+        for r in 0..2 {
+            for g in 0..2 {
+                for b in 0..2 {
+                    let rgb = [r as f32, g as f32, b as f32];
+                    let xyz = matrix.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]);
+                    let encoded = if lab {
+                        let f = |t: f32| {
+                            if t > 216.0 / 24389.0 {
+                                t.cbrt()
+                            } else {
+                                (24389.0 / 27.0 * t + 16.0) / 116.0
+                            }
+                        };
+                        let [fx, fy, fz] = std::array::from_fn(|i| f(xyz[i] / pipeline::D50[i]));
+                        let l = 116.0 * fy - 16.0;
+                        let a = 500.0 * (fx - fy);
+                        let b = 200.0 * (fy - fz);
+                        [l * 652.8, (a + 128.0) * 256.0, (b + 128.0) * 256.0]
+                    } else {
+                        xyz.map(|v| v * 32768.0)
+                    };
+                    for v in encoded {
+                        out.extend_from_slice(&(v.round() as u16).to_be_bytes());
+                    }
+                }
+            }
+        }
+        out.extend_from_slice(&identity_tables);
+        out
+    }
+
+    /// Creates matrix/TRC profile of linear sRGB.
+    fn srgb_linear_matrix_profile() -> Vec<u8> {
+        let mut tags = vec![
+            (*b"rTRC", identity_curves(1)),
+            (*b"gTRC", identity_curves(1)),
+            (*b"bTRC", identity_curves(1)),
+        ];
+        let matrix = srgb_d50_matrix();
+        for (idx, tag) in [*b"rXYZ", *b"gXYZ", *b"bXYZ"].into_iter().enumerate() {
+            let mut data = vec![b'X', b'Y', b'Z', b' ', 0, 0, 0, 0];
+            for row in matrix {
+                data.extend_from_slice(&((row[idx] * 65536.0).round() as i32).to_be_bytes());
+            }
+            tags.push((tag, data));
+        }
+        build_profile(b"RGB ", b"XYZ ", &tags)
+    }
+
+    fn transform_and_compare(from: &[u8], to: &[u8], input: &[[f32; 3]], tolerance: f32) {
+        let mut r = input.iter().map(|v| v[0]).collect::<Vec<_>>();
+        let mut g = input.iter().map(|v| v[1]).collect::<Vec<_>>();
+        let mut b = input.iter().map(|v| v[2]).collect::<Vec<_>>();
+        PureRustCms
+            .transform(
+                from,
+                to,
+                RenderingIntent::Perceptual,
+                &mut [&mut r, &mut g, &mut b],
+            )
+            .unwrap();
+
+        for (idx, &expected) in input.iter().enumerate() {
+            let actual = [r[idx], g[idx], b[idx]];
+            for (a, e) in actual.into_iter().zip(expected) {
+                assert!((a - e).abs() < tolerance, "{actual:?} != {expected:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_to_linear() {
+        let srgb = colour_encoding_to_icc(&EnumColourEncoding::srgb(RenderingIntent::Relative));
+        let linear = colour_encoding_to_icc(&EnumColourEncoding {
+            tf: TransferFunction::Linear,
+            ..EnumColourEncoding::srgb(RenderingIntent::Relative)
+        });
+
+        let mut r = vec![0.0f32, 0.5, 1.0];
+        let mut g = vec![0.5f32, 0.04, 0.2];
+        let mut b = vec![1.0f32, 0.0, 0.7];
+        let expected = [&r, &g, &b].map(|ch| {
+            ch.iter()
+                .map(|&v| {
+                    if v <= 0.04045 {
+                        v / 12.92
+                    } else {
+                        ((v + 0.055) / 1.055).powf(2.4)
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let ret = PureRustCms
+            .transform(
+                &srgb,
+                &linear,
+                RenderingIntent::Relative,
+                &mut [&mut r, &mut g, &mut b],
+            )
+            .unwrap();
+        assert_eq!(ret, 3);
+        for (actual, expected) in [r, g, b].into_iter().zip(expected) {
+            for (a, e) in actual.into_iter().zip(expected) {
+                assert!((a - e).abs() < 2e-3, "{a} != {e}");
+            }
+        }
+    }
+
+    #[test]
+    fn lut_profile_matches_matrix_profile() {
+        let lut_profile = build_profile(b"RGB ", b"XYZ ", &[(*b"A2B0", srgb_linear_a2b())]);
+        let matrix_profile = srgb_linear_matrix_profile();
+
+        let input = [
+            [0.1f32, 0.2, 0.3],
+            [0.9, 0.5, 0.05],
+            [1.0, 1.0, 1.0],
+            [0.0, 0.7, 0.0],
+        ];
+        transform_and_compare(&lut_profile, &matrix_profile, &input, 1e-3);
+    }
+
+    #[test]
+    fn lut16_profile_matches_matrix_profile() {
+        let lut_profile = build_profile(b"RGB ", b"XYZ ", &[(*b"A2B0", srgb_linear_lut16(false))]);
+        let matrix_profile = srgb_linear_matrix_profile();
+
+        let input = [
+            [0.1f32, 0.2, 0.3],
+            [0.9, 0.5, 0.05],
+            [1.0, 1.0, 1.0],
+            [0.0, 0.7, 0.0],
+        ];
+        transform_and_compare(&lut_profile, &matrix_profile, &input, 1e-3);
+    }
+
+    #[test]
+    fn lut16_lab_profile() {
+        let lut_profile = build_profile(b"RGB ", b"Lab ", &[(*b"A2B0", srgb_linear_lut16(true))]);
+        let matrix_profile = srgb_linear_matrix_profile();
+
+        // Lab is not linear in RGB, so test only on the grid points.
+        let input = [
+            [0.0f32, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 1.0, 0.0],
+        ];
+        transform_and_compare(&lut_profile, &matrix_profile, &input, 2e-3);
+    }
+
+    #[test]
+    fn not_enough_channels() {
+        let srgb = colour_encoding_to_icc(&EnumColourEncoding::srgb(RenderingIntent::Relative));
+        let mut r = vec![0.0f32];
+        let err = PureRustCms
+            .transform(&srgb, &srgb, RenderingIntent::Relative, &mut [&mut r])
+            .unwrap_err();
+        assert!(matches!(err, jxl_color::Error::CmsFailure(_)));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::clut::{Clut, MAX_CHANNELS};
+use crate::curve::Curve;
+
+/// D50 illuminant in XYZ, which is the PCS illuminant of ICC profiles.
+pub(crate) const D50: [f32; 3] = [0.9642, 1.0, 0.8249];
+
+/// Single processing element of a transform.
+#[derive(Debug, Clone)]
+pub(crate) enum Stage {
+    Curves(Vec<Curve>),
+    /// 3x3 matrix in row-major order, followed by offsets.
+    Matrix {
+        matrix: [f32; 9],
+        offset: [f32; 3],
+    },
+    Clut(Clut),
+    /// Converts PCS-encoded CIELAB into XYZ.
+    LabToXyz,
+    /// Converts XYZ into PCS-encoded CIELAB.
+    XyzToLab,
+    GrayToXyz,
+    XyzToGray,
+}
+
+impl Stage {
+    pub(crate) fn scale(scale: [f32; 3]) -> Self {
+        let [x, y, z] = scale;
+        Self::Matrix {
+            matrix: [x, 0.0, 0.0, 0.0, y, 0.0, 0.0, 0.0, z],
+            offset: [0.0; 3],
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        match self {
+            Self::Curves(curves) => curves.iter().all(Curve::is_identity),
+            _ => false,
+        }
+    }
+
+    /// Applies the stage to a chunk of planar samples, and returns the number of output channels.
+    fn apply(&self, channels: &mut [&mut [f32]], num_channels: usize) -> usize {
+        match self {
+            Self::Curves(curves) => {
+                for (curve, ch) in curves.iter().zip(&mut *channels) {
+                    if curve.is_identity() {
+                        continue;
+                    }
+                    for v in ch.iter_mut() {
+                        *v = curve.eval(*v);
+                    }
+                }
+                num_channels
+            }
+            Self::Matrix { matrix, offset } => {
+                let [a, b, c, ..] = channels else {
+                    unreachable!()
+                };
+                for ((a, b), c) in a.iter_mut().zip(&mut **b).zip(&mut **c) {
+                    let [x, y, z] = [*a, *b, *c];
+                    *a = matrix[0] * x + matrix[1] * y + matrix[2] * z + offset[0];
+                    *b = matrix[3] * x + matrix[4] * y + matrix[5] * z + offset[1];
+                    *c = matrix[6] * x + matrix[7] * y + matrix[8] * z + offset[2];
+                }
+                3
+            }
+            Self::Clut(clut) => {
+                let in_channels = clut.in_channels();
+                let out_channels = clut.out_channels();
+                let len = channels[0].len();
+                let mut input = [0f32; MAX_CHANNELS];
+                let mut output = [0f32; MAX_CHANNELS];
+                for idx in 0..len {
+                    for (v, ch) in input.iter_mut().zip(&channels[..in_channels]) {
+                        *v = ch[idx];
+                    }
+                    clut.eval(&input[..in_channels], &mut output);
+                    for (v, ch) in output.iter().zip(&mut channels[..out_channels]) {
+                        ch[idx] = *v;
+                    }
+                }
+                out_channels
+            }
+            Self::LabToXyz => {
+                let [l, a, b, ..] = channels else {
+                    unreachable!()
+                };
+                for ((l, a), b) in l.iter_mut().zip(&mut **a).zip(&mut **b) {
+                    let xyz = lab_to_xyz([*l * 100.0, *a * 255.0 - 128.0, *b * 255.0 - 128.0]);
+                    [*l, *a, *b] = xyz;
+                }
+                3
+            }
+            Self::XyzToLab => {
+                let [x, y, z, ..] = channels else {
+                    unreachable!()
+                };
+                for ((x, y), z) in x.iter_mut().zip(&mut **y).zip(&mut **z) {
+                    let [l, a, b] = xyz_to_lab([*x, *y, *z]);
+                    *x = l / 100.0;
+                    *y = (a + 128.0) / 255.0;
+                    *z = (b + 128.0) / 255.0;
+                }
+                3
+            }
+            Self::GrayToXyz => {
+                let [gray, y, z, ..] = channels else {
+                    unreachable!()
+                };
+                for ((x, y), z) in gray.iter_mut().zip(&mut **y).zip(&mut **z) {
+                    let lum = *x;
+                    *x = lum * D50[0];
+                    *y = lum * D50[1];
+                    *z = lum * D50[2];
+                }
+                3
+            }
+            Self::XyzToGray => {
+                let [gray, y, ..] = channels else {
+                    unreachable!()
+                };
+                gray.copy_from_slice(y);
+                1
+            }
+        }
+    }
+}
+
+fn lab_to_xyz([l, a, b]: [f32; 3]) -> [f32; 3] {
+    const DELTA: f32 = 6.0 / 29.0;
+
+    let f_inv = |t: f32| {
+        if t > DELTA {
+            t * t * t
+        } else {
+            3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+        }
+    };
+
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    [D50[0] * f_inv(fx), D50[1] * f_inv(fy), D50[2] * f_inv(fz)]
+}
+
+fn xyz_to_lab([x, y, z]: [f32; 3]) -> [f32; 3] {
+    const DELTA: f32 = 6.0 / 29.0;
+
+    let f = |t: f32| {
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+
+    let fx = f(x / D50[0]);
+    let fy = f(y / D50[1]);
+    let fz = f(z / D50[2]);
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Sequence of stages which converts samples between two color spaces.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    pub(crate) fn push(&mut self, stage: Stage) {
+        if !stage.is_identity() {
+            self.stages.push(stage);
+        }
+    }
+
+    pub(crate) fn append(&mut self, other: Pipeline) {
+        self.stages.extend(other.stages);
+    }
+
+    /// Runs the pipeline on a chunk of planar samples.
+    ///
+    /// Every channel should have enough capacity to hold intermediate results.
+    pub(crate) fn run(&self, channels: &mut [&mut [f32]], mut num_channels: usize) -> usize {
+        for stage in &self.stages {
+            num_channels = stage.apply(channels, num_channels);
+        }
+        num_channels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lab_roundtrip() {
+        for xyz in [
+            [0.9642, 1.0, 0.8249],
+            [0.2, 0.3, 0.1],
+            [0.001, 0.002, 0.003],
+        ] {
+            let lab = xyz_to_lab(xyz);
+            let out = lab_to_xyz(lab);
+            for (a, b) in xyz.into_iter().zip(out) {
+                assert!((a - b).abs() < 1e-5, "{xyz:?} -> {lab:?} -> {out:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,499 @@
+use jxl_color::RenderingIntent;
+
+use crate::clut::{decode_samples, Clut};
+use crate::curve::Curve;
+use crate::pipeline::{Pipeline, Stage, D50};
+use crate::{Error, Result};
+
+/// Scale factor of XYZ values encoded in `lutAtoBType` and `lutBtoAType`.
+///
+/// XYZ PCS values are encoded as `u1Fixed15`, so 1.0 in normalized encoding corresponds to
+/// `65535 / 32768`.
+const XYZ_ENCODING_SCALE: f32 = 65535.0 / 32768.0;
+
+/// Scale factor which converts legacy 16-bit Lab encoding, used by `lut16Type`, into v4 Lab
+/// encoding.
+///
+/// Legacy encoding maps 100.0 of L* to `0xff00` instead of `0xffff`.
+const LEGACY_LAB_SCALE: f32 = 65535.0 / 65280.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pcs {
+    Xyz,
+    Lab,
+}
+
+/// Parsed ICC profile.
+pub(crate) struct Profile<'a> {
+    color_space: [u8; 4],
+    pcs: Pcs,
+    tags: Vec<([u8; 4], &'a [u8])>,
+}
+
+impl<'a> Profile<'a> {
+    pub(crate) fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 132 {
+            return Err(Error::IccParseFailure("profile is too short"));
+        }
+
+        let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        if data.len() < size {
+            return Err(Error::IccParseFailure("profile size mismatch"));
+        }
+        let data = &data[..size];
+
+        let color_space = [data[0x10], data[0x11], data[0x12], data[0x13]];
+        let pcs = match &data[0x14..0x18] {
+            b"XYZ " => Pcs::Xyz,
+            b"Lab " => Pcs::Lab,
+            _ => return Err(Error::IccParseFailure("invalid PCS")),
+        };
+
+        let tag_count = u32::from_be_bytes([data[0x80], data[0x81], data[0x82], data[0x83]]);
+        let tag_bytes = (tag_count as usize)
+            .checked_mul(12)
+            .and_then(|len| data[0x84..].get(..len))
+            .ok_or(Error::IccParseFailure(
+                "unexpected end of profile while reading tag list",
+            ))?;
+
+        let mut tags = Vec::with_capacity(tag_count as usize);
+        for raw_tag in tag_bytes.chunks_exact(12) {
+            let tag = [raw_tag[0], raw_tag[1], raw_tag[2], raw_tag[3]];
+            let offset = u32::from_be_bytes([raw_tag[4], raw_tag[5], raw_tag[6], raw_tag[7]]);
+            let len = u32::from_be_bytes([raw_tag[8], raw_tag[9], raw_tag[10], raw_tag[11]]);
+            let tag_data = data
+                .get(offset as usize..)
+                .and_then(|data| data.get(..len as usize))
+                .ok_or(Error::IccParseFailure(
+                    "unexpected end of profile while reading tag data",
+                ))?;
+            tags.push((tag, tag_data));
+        }
+
+        Ok(Self {
+            color_space,
+            pcs,
+            tags,
+        })
+    }
+
+    fn tag(&self, sig: &[u8; 4]) -> Option<&'a [u8]> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == sig)
+            .map(|&(_, data)| data)
+    }
+
+    /// Returns the number of device channels of the profile.
+    pub(crate) fn channels(&self) -> Result<usize> {
+        Ok(match &self.color_space {
+            b"GRAY" => 1,
+            b"RGB " | b"XYZ " | b"Lab " | b"Luv " | b"YCbr" | b"Yxy " | b"HSV " | b"HLS "
+            | b"CMY " => 3,
+            b"CMYK" => 4,
+            &[n @ b'2'..=b'9', b'C', b'L', b'R'] => (n - b'0') as usize,
+            &[n @ b'A'..=b'F', b'C', b'L', b'R'] => (n - b'A') as usize + 10,
+            _ => return Err(Error::UnsupportedIccProfile("unknown color space")),
+        })
+    }
+
+    fn media_white(&self) -> Result<[f32; 3]> {
+        let Some(data) = self.tag(b"wtpt") else {
+            return Ok(D50);
+        };
+        parse_xyz(data)
+    }
+
+    fn lut_tag(&self, prefix: [u8; 3], intent: RenderingIntent) -> Option<&'a [u8]> {
+        let intent_idx = match intent {
+            RenderingIntent::Perceptual => b'0',
+            RenderingIntent::Relative | RenderingIntent::Absolute => b'1',
+            RenderingIntent::Saturation => b'2',
+        };
+        let [a, b, c] = prefix;
+        self.tag(&[a, b, c, intent_idx])
+            .or_else(|| self.tag(&[a, b, c, b'0']))
+    }
+
+    /// Builds a pipeline which converts device samples into relative XYZ.
+    pub(crate) fn device_to_pcs(&self, intent: RenderingIntent) -> Result<Pipeline> {
+        let channels = self.channels()?;
+        let mut pipeline = Pipeline::default();
+
+        if let Some(data) = self.lut_tag(*b"A2B", intent) {
+            let is_lut16 = match data.get(..4) {
+                Some(b"mft1" | b"mft2") => {
+                    pipeline.append(parse_lut_8_16(data, channels, 3, false)?);
+                    &data[..4] == b"mft2"
+                }
+                _ => {
+                    pipeline.append(parse_lut_a_to_b(data, channels)?);
+                    false
+                }
+            };
+            match self.pcs {
+                Pcs::Xyz => pipeline.push(Stage::scale([XYZ_ENCODING_SCALE; 3])),
+                Pcs::Lab => {
+                    if is_lut16 {
+                        pipeline.push(Stage::scale([LEGACY_LAB_SCALE; 3]));
+                    }
+                    pipeline.push(Stage::LabToXyz);
+                }
+            }
+        } else if channels == 1 {
+            let trc = self
+                .tag(b"kTRC")
+                .ok_or(Error::UnsupportedIccProfile("grayTRC not found"))?;
+            let (curve, _) = Curve::parse(trc)?;
+            pipeline.push(Stage::Curves(vec![curve]));
+            pipeline.push(Stage::GrayToXyz);
+        } else if &self.color_space == b"RGB " {
+            let (curves, matrix) = self.matrix_trc()?;
+            pipeline.push(Stage::Curves(curves));
+            pipeline.push(Stage::Matrix {
+                matrix,
+                offset: [0.0; 3],
+            });
+        } else {
+            return Err(Error::UnsupportedIccProfile("AToB tag not found"));
+        }
+
+        if intent == RenderingIntent::Absolute {
+            let wtpt = self.media_white()?;
+            pipeline.push(Stage::scale(std::array::from_fn(|i| wtpt[i] / D50[i])));
+        }
+        Ok(pipeline)
+    }
+
+    /// Builds a pipeline which converts relative XYZ into device samples.
+    pub(crate) fn pcs_to_device(&self, intent: RenderingIntent) -> Result<Pipeline> {
+        let channels = self.channels()?;
+        let mut pipeline = Pipeline::default();
+
+        if intent == RenderingIntent::Absolute {
+            let wtpt = self.media_white()?;
+            pipeline.push(Stage::scale(std::array::from_fn(|i| D50[i] / wtpt[i])));
+        }
+
+        if let Some(data) = self.lut_tag(*b"B2A", intent) {
+            let ty = data.get(..4);
+            let is_legacy = matches!(ty, Some(b"mft1" | b"mft2"));
+            match self.pcs {
+                Pcs::Xyz => pipeline.push(Stage::scale([1.0 / XYZ_ENCODING_SCALE; 3])),
+                Pcs::Lab => {
+                    pipeline.push(Stage::XyzToLab);
+                    if ty == Some(b"mft2") {
+                        pipeline.push(Stage::scale([1.0 / LEGACY_LAB_SCALE; 3]));
+                    }
+                }
+            }
+            if is_legacy {
+                let apply_matrix = self.pcs == Pcs::Xyz;
+                pipeline.append(parse_lut_8_16(data, 3, channels, apply_matrix)?);
+            } else {
+                pipeline.append(parse_lut_b_to_a(data, channels)?);
+            }
+        } else if channels == 1 {
+            let trc = self
+                .tag(b"kTRC")
+                .ok_or(Error::UnsupportedIccProfile("grayTRC not found"))?;
+            let (curve, _) = Curve::parse(trc)?;
+            pipeline.push(Stage::XyzToGray);
+            pipeline.push(Stage::Curves(vec![curve.inverse()]));
+        } else if &self.color_space == b"RGB " {
+            let (curves, matrix) = self.matrix_trc()?;
+            let matrix = invert_matrix(matrix)
+                .ok_or(Error::IccParseFailure("colorant matrix is not invertible"))?;
+            pipeline.push(Stage::Matrix {
+                matrix,
+                offset: [0.0; 3],
+            });
+            pipeline.push(Stage::Curves(curves.iter().map(Curve::inverse).collect()));
+        } else {
+            return Err(Error::UnsupportedIccProfile("BToA tag not found"));
+        }
+
+        Ok(pipeline)
+    }
+
+    fn matrix_trc(&self) -> Result<(Vec<Curve>, [f32; 9])> {
+        let mut curves = Vec::with_capacity(3);
+        let mut columns = [[0f32; 3]; 3];
+        for (column, [trc_tag, xyz_tag]) in
+            columns
+                .iter_mut()
+                .zip([[b"rTRC", b"rXYZ"], [b"gTRC", b"gXYZ"], [b"bTRC", b"bXYZ"]])
+        {
+            let trc = self
+                .tag(trc_tag)
+                .ok_or(Error::UnsupportedIccProfile("TRC not found"))?;
+            let xyz = self
+                .tag(xyz_tag)
+                .ok_or(Error::UnsupportedIccProfile("colorant not found"))?;
+            curves.push(Curve::parse(trc)?.0);
+            *column = parse_xyz(xyz)?;
+        }
+
+        let matrix = std::array::from_fn(|idx| columns[idx % 3][idx / 3]);
+        Ok((curves, matrix))
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(Error::IccParseFailure("unexpected end of tag data"))?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_s15fixed16(data: &[u8], offset: usize) -> Result<f32> {
+    Ok(read_u32(data, offset)? as i32 as f32 / 65536.0)
+}
+
+fn parse_xyz(data: &[u8]) -> Result<[f32; 3]> {
+    if data.len() < 20 || &data[..4] != b"XYZ " {
+        return Err(Error::IccParseFailure("invalid XYZType"));
+    }
+    Ok([
+        read_s15fixed16(data, 8)?,
+        read_s15fixed16(data, 12)?,
+        read_s15fixed16(data, 16)?,
+    ])
+}
+
+fn invert_matrix(m: [f32; 9]) -> Option<[f32; 9]> {
+    let [a, b, c, d, e, f, g, h, i] = m.map(|v| v as f64);
+    let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv = [
+        e * i - f * h,
+        c * h - b * i,
+        b * f - c * e,
+        f * g - d * i,
+        a * i - c * g,
+        c * d - a * f,
+        d * h - e * g,
+        b * g - a * h,
+        a * e - b * d,
+    ];
+    Some(inv.map(|v| (v / det) as f32))
+}
+
+/// Offsets of processing elements in `lutAtoBType` and `lutBtoAType`.
+struct LutOffsets {
+    in_channels: usize,
+    out_channels: usize,
+    b: usize,
+    matrix: usize,
+    m: usize,
+    clut: usize,
+    a: usize,
+}
+
+impl LutOffsets {
+    fn parse(data: &[u8], ty: &[u8; 4]) -> Result<Self> {
+        if data.len() < 32 || &data[..4] != ty {
+            return Err(Error::IccParseFailure("invalid LUT tag"));
+        }
+
+        Ok(Self {
+            in_channels: data[8] as usize,
+            out_channels: data[9] as usize,
+            b: read_u32(data, 12)? as usize,
+            matrix: read_u32(data, 16)? as usize,
+            m: read_u32(data, 20)? as usize,
+            clut: read_u32(data, 24)? as usize,
+            a: read_u32(data, 28)? as usize,
+        })
+    }
+}
+
+fn element(data: &[u8], offset: usize) -> Result<&[u8]> {
+    data.get(offset..)
+        .ok_or(Error::IccParseFailure("invalid offset in LUT tag"))
+}
+
+fn parse_matrix(data: &[u8]) -> Result<Stage> {
+    let mut values = [0f32; 12];
+    for (idx, v) in values.iter_mut().enumerate() {
+        *v = read_s15fixed16(data, idx * 4)?;
+    }
+
+    let [m0, m1, m2, m3, m4, m5, m6, m7, m8, o0, o1, o2] = values;
+    Ok(Stage::Matrix {
+        matrix: [m0, m1, m2, m3, m4, m5, m6, m7, m8],
+        offset: [o0, o1, o2],
+    })
+}
+
+/// Parses `lutAtoBType`, which is applied in the order of A, CLUT, M, matrix and B.
+fn parse_lut_a_to_b(data: &[u8], device_channels: usize) -> Result<Pipeline> {
+    let offsets = LutOffsets::parse(data, b"mAB ")?;
+    if offsets.in_channels != device_channels || offsets.out_channels != 3 {
+        return Err(Error::IccParseFailure("channel count mismatch in AToB tag"));
+    }
+    if offsets.b == 0 {
+        return Err(Error::IccParseFailure("B curves not found in AToB tag"));
+    }
+    if (offsets.a == 0) != (offsets.clut == 0) || (offsets.m == 0) != (offsets.matrix == 0) {
+        return Err(Error::IccParseFailure(
+            "invalid combination of AToB elements",
+        ));
+    }
+    if offsets.clut == 0 && offsets.in_channels != offsets.out_channels {
+        return Err(Error::IccParseFailure("CLUT not found in AToB tag"));
+    }
+
+    let mut pipeline = Pipeline::default();
+    if offsets.a != 0 {
+        let curves = Curve::parse_many(element(data, offsets.a)?, offsets.in_channels)?;
+        pipeline.push(Stage::Curves(curves));
+        let clut = Clut::parse(
+            element(data, offsets.clut)?,
+            offsets.in_channels,
+            offsets.out_channels,
+        )?;
+        pipeline.push(Stage::Clut(clut));
+    }
+    if offsets.m != 0 {
+        let curves = Curve::parse_many(element(data, offsets.m)?, offsets.out_channels)?;
+        pipeline.push(Stage::Curves(curves));
+        pipeline.push(parse_matrix(element(data, offsets.matrix)?)?);
+    }
+    let curves = Curve::parse_many(element(data, offsets.b)?, offsets.out_channels)?;
+    pipeline.push(Stage::Curves(curves));
+    Ok(pipeline)
+}
+
+/// Parses `lutBtoAType`, which is applied in the order of B, matrix, M, CLUT and A.
+fn parse_lut_b_to_a(data: &[u8], device_channels: usize) -> Result<Pipeline> {
+    let offsets = LutOffsets::parse(data, b"mBA ")?;
+    if offsets.in_channels != 3 || offsets.out_channels != device_channels {
+        return Err(Error::IccParseFailure("channel count mismatch in BToA tag"));
+    }
+    if offsets.b == 0 {
+        return Err(Error::IccParseFailure("B curves not found in BToA tag"));
+    }
+    if (offsets.a == 0) != (offsets.clut == 0) || (offsets.m == 0) != (offsets.matrix == 0) {
+        return Err(Error::IccParseFailure(
+            "invalid combination of BToA elements",
+        ));
+    }
+    if offsets.clut == 0 && offsets.in_channels != offsets.out_channels {
+        return Err(Error::IccParseFailure("CLUT not found in BToA tag"));
+    }
+
+    let mut pipeline = Pipeline::default();
+    let curves = Curve::parse_many(element(data, offsets.b)?, offsets.in_channels)?;
+    pipeline.push(Stage::Curves(curves));
+    if offsets.m != 0 {
+        pipeline.push(parse_matrix(element(data, offsets.matrix)?)?);
+        let curves = Curve::parse_many(element(data, offsets.m)?, offsets.in_channels)?;
+        pipeline.push(Stage::Curves(curves));
+    }
+    if offsets.a != 0 {
+        let clut = Clut::parse(
+            element(data, offsets.clut)?,
+            offsets.in_channels,
+            offsets.out_channels,
+        )?;
+        pipeline.push(Stage::Clut(clut));
+        let curves = Curve::parse_many(element(data, offsets.a)?, offsets.out_channels)?;
+        pipeline.push(Stage::Curves(curves));
+    }
+    Ok(pipeline)
+}
+
+/// Parses `lut8Type` or `lut16Type`, which is applied in the order of matrix, input tables, CLUT
+/// and output tables.
+///
+/// The matrix is applied only if `apply_matrix` is set, as the specification allows it only for
+/// XYZ input.
+fn parse_lut_8_16(
+    data: &[u8],
+    in_channels: usize,
+    out_channels: usize,
+    apply_matrix: bool,
+) -> Result<Pipeline> {
+    if data.len() < 48 {
+        return Err(Error::IccParseFailure("invalid LUT tag"));
+    }
+    let precision = match &data[..4] {
+        b"mft1" => 1,
+        b"mft2" => 2,
+        _ => return Err(Error::IccParseFailure("invalid LUT tag")),
+    };
+    if data[8] as usize != in_channels || data[9] as usize != out_channels {
+        return Err(Error::IccParseFailure("channel count mismatch in LUT tag"));
+    }
+    let grid_points = data[10] as usize;
+    if grid_points < 2 {
+        return Err(Error::IccParseFailure("invalid number of CLUT grid points"));
+    }
+
+    let mut matrix = [0f32; 9];
+    for (idx, v) in matrix.iter_mut().enumerate() {
+        *v = read_s15fixed16(data, 12 + idx * 4)?;
+    }
+
+    let (in_entries, out_entries, mut offset) = if precision == 1 {
+        (256, 256, 48)
+    } else {
+        let entries = data
+            .get(48..52)
+            .ok_or(Error::IccParseFailure("unexpected end of tag data"))?;
+        let in_entries = u16::from_be_bytes([entries[0], entries[1]]) as usize;
+        let out_entries = u16::from_be_bytes([entries[2], entries[3]]) as usize;
+        if !(2..=4096).contains(&in_entries) || !(2..=4096).contains(&out_entries) {
+            return Err(Error::IccParseFailure(
+                "invalid number of LUT table entries",
+            ));
+        }
+        (in_entries, out_entries, 52)
+    };
+
+    let parse_tables = |offset: &mut usize, channels: usize, entries: usize| {
+        let mut curves = Vec::with_capacity(channels);
+        for _ in 0..channels {
+            let table = decode_samples(element(data, *offset)?, entries, precision)?;
+            *offset += entries * precision;
+            curves.push(Curve::Table(table));
+        }
+        Ok::<_, Error>(curves)
+    };
+
+    let mut pipeline = Pipeline::default();
+    let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    if apply_matrix && matrix != identity {
+        pipeline.push(Stage::Matrix {
+            matrix,
+            offset: [0.0; 3],
+        });
+    }
+    pipeline.push(Stage::Curves(parse_tables(
+        &mut offset,
+        in_channels,
+        in_entries,
+    )?));
+
+    let (clut, clut_len) = Clut::parse_legacy(
+        element(data, offset)?,
+        grid_points,
+        in_channels,
+        out_channels,
+        precision,
+    )?;
+    offset += clut_len;
+    pipeline.push(Stage::Clut(clut));
+
+    pipeline.push(Stage::Curves(parse_tables(
+        &mut offset,
+        out_channels,
+        out_entries,
+    )?));
+    Ok(pipeline)
+}
@@ -20,6 +20,10 @@ pub enum Error {
     },
     UnexpectedLz77Repeat,
     InvalidLz77Symbol,
+    Lz77DistanceLimitExceeded {
+        distance: u32,
+        limit: u32,
+    },
 }
 
 impl std::error::Error for Error {
@@ -56,6 +60,9 @@ impl std::fmt::Display for Error {
                 "LZ77 repeat symbol encountered without decoding any symbols"
             ),
             Self::InvalidLz77Symbol => write!(f, "Invalid LZ77 symbol"),
+            Self::Lz77DistanceLimitExceeded { distance, limit } => {
+                write!(f, "LZ77 distance {distance} exceeds the limit {limit}")
+            }
         }
     }
 }
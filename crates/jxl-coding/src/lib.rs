@@ -10,10 +10,12 @@ use jxl_bitstream::{Bitstream, U};
 
 mod ans;
 mod error;
+mod lz77;
 mod permutation;
 mod prefix;
 
 pub use error::Error;
+pub use lz77::Lz77Tracker;
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub use permutation::read_permutation;
@@ -100,18 +102,24 @@ impl Decoder {
     }
 
     pub fn as_rle(&mut self) -> Option<DecoderRleMode<'_>> {
-        let &Lz77::Enabled {
-            ref state,
+        let &mut Lz77::Enabled {
+            ref mut state,
             min_symbol,
             min_length,
-        } = &self.lz77
+        } = &mut self.lz77
         else {
             return None;
         };
         let lz_cluster = self.inner.lz_dist_cluster();
         let lz_conf = &self.inner.configs[lz_cluster as usize];
         let sym = self.inner.code.single_symbol(lz_cluster)?;
-        (sym == 1 && lz_conf.split_exponent == 0).then_some(DecoderRleMode {
+        if sym != 1 || lz_conf.split_exponent != 0 || state.distance_limit == 0 {
+            return None;
+        }
+
+        // RLE copies from the previous symbol.
+        state.max_distance = state.max_distance.max(1);
+        Some(DecoderRleMode {
             inner: &mut self.inner,
             min_symbol,
             min_length,
@@ -165,14 +173,44 @@ impl Decoder {
     /// checks if the final state matches expected state, which is specified in the specification.
     #[inline]
     pub fn finalize(&self) -> Result<()> {
+        if let Lz77::Enabled { state, .. } = &self.lz77 {
+            if let Some(tracker) = &state.tracker {
+                tracker.record_distance(state.max_distance);
+            }
+        }
         self.inner.code.finalize()
     }
 
+    /// Returns whether LZ77 is enabled for the decoder.
+    #[inline]
+    pub fn is_lz77_enabled(&self) -> bool {
+        matches!(self.lz77, Lz77::Enabled { .. })
+    }
+
+    /// Attaches the LZ77 tracker to the decoder.
+    ///
+    /// Clones of the decoder made afterwards share the tracker. LZ77 distances used by the decoder
+    /// are recorded on [`finalize`][Self::finalize]. If the tracker has a distance limit, the
+    /// decoder rejects LZ77 distances greater than the limit.
+    pub fn set_lz77_tracker(&mut self, tracker: &Lz77Tracker) {
+        if let Lz77::Enabled { state, .. } = &mut self.lz77 {
+            tracker.mark_used();
+            state.set_distance_limit(tracker.distance_limit());
+            state.tracker = Some(tracker.clone());
+        }
+    }
+
     /// Returns the cluster mapping of distributions.
     #[inline]
     pub fn cluster_map(&self) -> &[u8] {
         &self.inner.clusters
     }
+
+    /// Returns whether the decoder uses prefix codes instead of ANS.
+    #[inline]
+    pub fn is_prefix_code(&self) -> bool {
+        matches!(self.inner.code, Coder::PrefixCode(_))
+    }
 }
 
 /// An entropy decoder, in RLE mode.
@@ -293,6 +331,10 @@ impl Lz77 {
 struct Lz77State {
     lz_len_conf: IntegerConfig,
     window: Vec<u32>,
+    window_mask: u32,
+    distance_limit: u32,
+    max_distance: u32,
+    tracker: Option<Lz77Tracker>,
     num_to_copy: u32,
     copy_pos: u32,
     num_decoded: u32,
@@ -305,6 +347,8 @@ impl std::fmt::Debug for Lz77State {
             .field("num_to_copy", &self.num_to_copy)
             .field("copy_pos", &self.copy_pos)
             .field("num_decoded", &self.num_decoded)
+            .field("distance_limit", &self.distance_limit)
+            .field("max_distance", &self.max_distance)
             .finish_non_exhaustive()
     }
 }
@@ -314,11 +358,26 @@ impl Lz77State {
         Self {
             lz_len_conf,
             window: Vec::new(),
+            window_mask: Self::MAX_WINDOW_SIZE - 1,
+            distance_limit: Self::MAX_WINDOW_SIZE,
+            max_distance: 0,
+            tracker: None,
             num_to_copy: 0,
             copy_pos: 0,
             num_decoded: 0,
         }
     }
+
+    const MAX_WINDOW_SIZE: u32 = 1 << 20;
+
+    fn set_distance_limit(&mut self, limit: Option<u32>) {
+        let limit = limit.map_or(Self::MAX_WINDOW_SIZE, |limit| {
+            limit.min(Self::MAX_WINDOW_SIZE)
+        });
+        // Distances are within the limit, so the window doesn't need to be larger than that.
+        self.distance_limit = limit;
+        self.window_mask = limit.max(1).next_power_of_two() - 1;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -465,7 +524,7 @@ impl DecoderInner {
 
         let r;
         if state.num_to_copy > 0 {
-            r = state.window[(state.copy_pos & 0xfffff) as usize];
+            r = state.window[(state.copy_pos & state.window_mask) as usize];
             state.copy_pos += 1;
             state.num_to_copy -= 1;
         } else {
@@ -503,16 +562,28 @@ impl DecoderInner {
                 };
 
                 let distance = (((1 << 20) - 1).min(distance) + 1).min(state.num_decoded);
+                if distance > state.distance_limit {
+                    tracing::error!(
+                        distance,
+                        limit = state.distance_limit,
+                        "LZ77 distance exceeds the limit"
+                    );
+                    return Err(Error::Lz77DistanceLimitExceeded {
+                        distance,
+                        limit: state.distance_limit,
+                    });
+                }
+                state.max_distance = state.max_distance.max(distance);
                 state.copy_pos = state.num_decoded - distance;
 
-                r = state.window[(state.copy_pos & 0xfffff) as usize];
+                r = state.window[(state.copy_pos & state.window_mask) as usize];
                 state.copy_pos += 1;
                 state.num_to_copy -= 1;
             } else {
                 r = self.read_uint_prefilled(bitstream, &self.configs[cluster as usize], token);
             }
         }
-        let offset = (state.num_decoded & 0xfffff) as usize;
+        let offset = (state.num_decoded & state.window_mask) as usize;
         if state.window.len() <= offset {
             state.window.push(r);
         } else {
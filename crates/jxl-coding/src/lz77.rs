@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Tracker of LZ77 usage, shared between entropy decoders.
+///
+/// The tracker records whether any of the decoders attached to it has LZ77 enabled, and the
+/// largest LZ77 distance actually used. It can also be created with a distance limit; attached
+/// decoders reject distances greater than the limit, and keep smaller windows accordingly.
+///
+/// Cloning the tracker yields a handle to the same tracker.
+#[derive(Debug, Clone, Default)]
+pub struct Lz77Tracker {
+    inner: Arc<Lz77TrackerInner>,
+}
+
+#[derive(Debug, Default)]
+struct Lz77TrackerInner {
+    distance_limit: Option<u32>,
+    used: AtomicBool,
+    max_distance: AtomicU32,
+}
+
+impl Lz77Tracker {
+    /// Creates a tracker without distance limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a tracker which rejects LZ77 distances greater than `limit`.
+    pub fn with_distance_limit(limit: u32) -> Self {
+        Self {
+            inner: Arc::new(Lz77TrackerInner {
+                distance_limit: Some(limit),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Returns the distance limit of the tracker.
+    #[inline]
+    pub fn distance_limit(&self) -> Option<u32> {
+        self.inner.distance_limit
+    }
+
+    /// Returns whether any of the attached decoders has LZ77 enabled.
+    #[inline]
+    pub fn is_lz77_used(&self) -> bool {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the largest LZ77 distance used by the attached decoders.
+    ///
+    /// Distances are recorded when the stream is [finalized][crate::Decoder::finalize]. Returns 0
+    /// if no LZ77 copy has been decoded.
+    #[inline]
+    pub fn max_distance(&self) -> u32 {
+        self.inner.max_distance.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn mark_used(&self) {
+        self.inner.used.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_distance(&self, distance: u32) {
+        self.inner
+            .max_distance
+            .fetch_max(distance, Ordering::Relaxed);
+    }
+}
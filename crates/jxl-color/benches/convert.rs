@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jxl_color::header::{OpsinInverseMatrix, ToneMapping};
+use jxl_color::{
+    ColorEncodingWithProfile, ColorTransform, EnumColourEncoding, NullCms, RenderingIntent,
+};
+use jxl_oxide_common::BundleDefault;
+use jxl_threadpool::JxlThreadPool;
+
+/// Benchmarks color transforms of a 4K image, mapping HDR into SDR.
+fn convert(c: &mut Criterion) {
+    let width = 3840;
+    let height = 2160;
+    let pool = JxlThreadPool::rayon(None);
+
+    // Deterministic noise in the range of 12-bit samples.
+    let mut state = 0x2545f491u32;
+    let mut samples = vec![vec![0f32; width * height]; 3];
+    for channel in &mut samples {
+        for v in channel {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *v = (state % 4096) as f32 / 4095.0;
+        }
+    }
+
+    let oim = OpsinInverseMatrix::default_with_context(());
+    let mut tone_mapping = ToneMapping::default_with_context(());
+    tone_mapping.intensity_target = 4000.0;
+    let cases = [
+        (
+            "pq-to-srgb",
+            EnumColourEncoding::bt2100_pq(RenderingIntent::Relative),
+        ),
+        (
+            "hlg-to-srgb",
+            EnumColourEncoding::bt2100_hlg(RenderingIntent::Relative),
+        ),
+    ];
+
+    let mut buf = samples.clone();
+    for (name, from) in cases {
+        let transform = ColorTransform::new(
+            &ColorEncodingWithProfile::new(from),
+            &ColorEncodingWithProfile::new(EnumColourEncoding::srgb(RenderingIntent::Relative)),
+            &oim,
+            &tone_mapping,
+        )
+        .unwrap();
+
+        let mut g = c.benchmark_group(name);
+        g.warm_up_time(Duration::from_secs(3));
+        g.measurement_time(Duration::from_secs(10));
+        g.throughput(criterion::Throughput::Elements((width * height) as u64));
+        g.bench_function("threaded", |b| {
+            b.iter(|| {
+                for (buf, samples) in buf.iter_mut().zip(&samples) {
+                    buf.copy_from_slice(samples);
+                }
+                let mut channels = buf.iter_mut().map(|ch| &mut **ch).collect::<Vec<_>>();
+                transform
+                    .run_with_threads(black_box(&mut channels), &NullCms, &pool)
+                    .unwrap()
+            })
+        });
+        g.finish();
+    }
+}
+
+criterion_group!(group, convert);
+criterion_main!(group);
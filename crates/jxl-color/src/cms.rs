@@ -1,5 +1,10 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
 use crate::RenderingIntent;
 
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
 /// Color management system that handles ICCv4 profiles.
 ///
 /// Implementors can implement `transform_impl` to integrate into external color management system.
@@ -27,6 +32,21 @@ pub trait ColorManagementSystem {
             .map_err(crate::Error::CmsFailure)
     }
 
+    /// Prepares a reusable color transformation between two ICC profiles.
+    ///
+    /// Implementors can override this method to parse profiles and build transforms only once,
+    /// so that the transform can be reused across frames and threads. Returns `Ok(None)` if the
+    /// CMS doesn't support prepared transforms, in which case `transform_impl` is used instead.
+    fn prepare_transform(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+    ) -> Result<Option<Box<dyn PreparedTransform>>, BoxedError> {
+        let _ = (from, to, intent);
+        Ok(None)
+    }
+
     /// Returns whether the CMS supports linear transfer function.
     ///
     /// This method will return `false` if it doesn't support (or it lacks precision to handle)
@@ -36,6 +56,14 @@ pub trait ColorManagementSystem {
     }
 }
 
+/// Color transformation prepared by [`ColorManagementSystem::prepare_transform`].
+///
+/// Prepared transforms may be shared between threads.
+pub trait PreparedTransform: Send + Sync {
+    /// Performs the color transformation in place, returning the number of output channels.
+    fn transform(&self, channels: &mut [&mut [f32]]) -> Result<usize, BoxedError>;
+}
+
 /// "Null" color management system that fails on every operation.
 #[derive(Debug, Copy, Clone)]
 pub struct NullCms;
@@ -60,3 +88,215 @@ impl ColorManagementSystem for NullCms {
         Err(crate::Error::CmsNotAvailable)
     }
 }
+
+type TransformKey = (Vec<u8>, Vec<u8>, RenderingIntent);
+
+/// Maximum number of prepared transforms kept by [`CachingCms`].
+const MAX_CACHED_TRANSFORMS: usize = 16;
+
+/// Color management system wrapper which caches prepared transforms.
+///
+/// Transforms are prepared with [`ColorManagementSystem::prepare_transform`] on first use and
+/// reused for subsequent transformations between the same pair of profiles. If the inner CMS
+/// doesn't support prepared transforms, `transform_impl` of the inner CMS is called as is.
+pub struct CachingCms<C> {
+    inner: C,
+    cache: Mutex<HashMap<TransformKey, Option<Arc<dyn PreparedTransform>>>>,
+}
+
+impl<C> CachingCms<C> {
+    /// Wraps the given color management system.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a reference to the inner color management system.
+    #[inline]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Drops all cached transforms.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+impl<C: std::fmt::Debug> std::fmt::Debug for CachingCms<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingCms")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: ColorManagementSystem> CachingCms<C> {
+    fn get_or_prepare(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+    ) -> Result<Option<Arc<dyn PreparedTransform>>, BoxedError> {
+        let key = (from.to_vec(), to.to_vec(), intent);
+        if let Some(transform) = self.cache.lock().unwrap().get(&key) {
+            return Ok(transform.clone());
+        }
+
+        // Prepare without holding the lock, as it may take a while.
+        let transform = self
+            .inner
+            .prepare_transform(from, to, intent)?
+            .map(Arc::from);
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_TRANSFORMS {
+            cache.clear();
+        }
+        cache.insert(key, transform.clone());
+        Ok(transform)
+    }
+}
+
+impl<C: ColorManagementSystem> ColorManagementSystem for CachingCms<C> {
+    fn transform_impl(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+        channels: &mut [&mut [f32]],
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        match self.get_or_prepare(from, to, intent)? {
+            Some(transform) => transform.transform(channels),
+            None => self.inner.transform_impl(from, to, intent, channels),
+        }
+    }
+
+    fn transform(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+        channels: &mut [&mut [f32]],
+    ) -> Result<usize, crate::Error> {
+        let transform = self
+            .get_or_prepare(from, to, intent)
+            .map_err(crate::Error::CmsFailure)?;
+        match transform {
+            Some(transform) => transform
+                .transform(channels)
+                .map_err(crate::Error::CmsFailure),
+            // Let the inner CMS report its own errors.
+            None => self.inner.transform(from, to, intent, channels),
+        }
+    }
+
+    fn prepare_transform(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+    ) -> Result<Option<Box<dyn PreparedTransform>>, BoxedError> {
+        self.inner.prepare_transform(from, to, intent)
+    }
+
+    fn supports_linear_tf(&self) -> bool {
+        self.inner.supports_linear_tf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Scale(f32);
+
+    impl PreparedTransform for Scale {
+        fn transform(&self, channels: &mut [&mut [f32]]) -> Result<usize, BoxedError> {
+            for ch in channels.iter_mut() {
+                for v in ch.iter_mut() {
+                    *v *= self.0;
+                }
+            }
+            Ok(channels.len())
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingCms {
+        prepared: AtomicUsize,
+        transformed: AtomicUsize,
+    }
+
+    impl ColorManagementSystem for CountingCms {
+        fn transform_impl(
+            &self,
+            _: &[u8],
+            _: &[u8],
+            _: RenderingIntent,
+            channels: &mut [&mut [f32]],
+        ) -> Result<usize, BoxedError> {
+            self.transformed.fetch_add(1, Ordering::Relaxed);
+            Ok(channels.len())
+        }
+
+        fn prepare_transform(
+            &self,
+            from: &[u8],
+            _: &[u8],
+            _: RenderingIntent,
+        ) -> Result<Option<Box<dyn PreparedTransform>>, BoxedError> {
+            self.prepared.fetch_add(1, Ordering::Relaxed);
+            Ok(Some(Box::new(Scale(from[0] as f32))))
+        }
+    }
+
+    #[test]
+    fn caching_cms_reuses_transforms() {
+        let cms = CachingCms::new(CountingCms::default());
+        let mut data = [1f32; 4];
+
+        for _ in 0..3 {
+            cms.transform(&[2], &[0], RenderingIntent::Relative, &mut [&mut data])
+                .unwrap();
+        }
+        cms.transform(&[3], &[0], RenderingIntent::Relative, &mut [&mut data])
+            .unwrap();
+        cms.transform(&[3], &[0], RenderingIntent::Perceptual, &mut [&mut data])
+            .unwrap();
+
+        assert_eq!(data, [72f32; 4]);
+        assert_eq!(cms.inner().prepared.load(Ordering::Relaxed), 3);
+        assert_eq!(cms.inner().transformed.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn caching_cms_falls_back_without_prepare() {
+        struct Unprepared(AtomicUsize);
+
+        impl ColorManagementSystem for Unprepared {
+            fn transform_impl(
+                &self,
+                _: &[u8],
+                _: &[u8],
+                _: RenderingIntent,
+                channels: &mut [&mut [f32]],
+            ) -> Result<usize, BoxedError> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Ok(channels.len())
+            }
+        }
+
+        let cms = CachingCms::new(Unprepared(AtomicUsize::new(0)));
+        let mut data = [1f32; 4];
+        for _ in 0..2 {
+            cms.transform(&[0], &[0], RenderingIntent::Relative, &mut [&mut data])
+                .unwrap();
+        }
+        assert_eq!(cms.inner().0.load(Ordering::Relaxed), 2);
+    }
+}
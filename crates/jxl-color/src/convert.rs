@@ -86,7 +86,24 @@ impl ColorEncodingWithProfile {
 }
 
 impl ColorEncodingWithProfile {
-    fn is_equivalent(&self, other: &Self) -> bool {
+    /// Returns whether the color encoding can be handled without external CMS, i.e. it's
+    /// described by enum values of a known color space.
+    ///
+    /// Transformation between two such color encodings is done by jxl-color itself.
+    #[inline]
+    pub fn is_representable_without_cms(&self) -> bool {
+        matches!(
+            &self.encoding,
+            ColourEncoding::Enum(encoding) if encoding.colour_space != ColourSpace::Unknown
+        )
+    }
+
+    /// Returns whether two color encodings describe the same color space, so that color
+    /// transformation between them is no-op.
+    ///
+    /// Color encodings described by ICC profiles are equivalent only if the profiles are
+    /// byte-identical.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
         if self.encoding.want_icc() != other.encoding.want_icc() {
             return false;
         }
@@ -119,11 +136,104 @@ impl ColorEncodingWithProfile {
     }
 }
 
+/// Number of samples per channel processed by a task of [`ColorTransform::run_with_threads`].
+const TASK_CHUNK_SIZE: usize = 65536;
+
+/// Number of samples per channel run through the whole transformation at once, small enough for
+/// samples of all channels to fit in L1 cache.
+const PIPELINE_CHUNK_SIZE: usize = 1024;
+
+/// Tone mapping operator applied when HDR images are transformed to SDR color encodings.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum ToneMappingOptions {
+    /// Maps luminance using the EETF defined in Rec. ITU-R BT.2408, with peak luminance of 255
+    /// nits.
+    #[default]
+    Rec2408,
+    /// Maps luminance using the Rec. 2408 EETF, with the given peak luminance in nits.
+    ///
+    /// Sample value of 1.0 represents the given luminance.
+    TargetLuminance(f32),
+    /// Scales samples so that 255 nits maps to sample value of 1.0, and clips samples brighter
+    /// than that.
+    Clip,
+}
+
+impl ToneMappingOptions {
+    /// Returns the peak luminance of the target display in nits.
+    #[inline]
+    pub fn target_luminance(self) -> f32 {
+        match self {
+            Self::Rec2408 | Self::Clip => 255.0,
+            Self::TargetLuminance(luminance) => luminance,
+        }
+    }
+}
+
+/// Display parameters used to compute the system gamma of HLG OOTF.
+///
+/// HLG is scene-referred, and the system gamma of HLG OOTF depends on the nominal peak luminance
+/// of the display and the luminance of its surround.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HlgDisplayParams {
+    /// Nominal peak luminance of the display in nits. Intensity target of the image is used if
+    /// `None`.
+    pub peak_luminance: Option<f32>,
+    /// Luminance of the viewing environment in nits. Reference value is 5 nits.
+    pub surround_luminance: f32,
+}
+
+impl Default for HlgDisplayParams {
+    fn default() -> Self {
+        Self {
+            peak_luminance: None,
+            surround_luminance: 5.0,
+        }
+    }
+}
+
+/// Statistics of output samples which fall outside of the nominal range of the target color
+/// encoding, collected by [`ColorTransform::run_with_stats`].
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct GamutStats {
+    /// Number of pixels checked.
+    pub total_pixels: usize,
+    /// Number of pixels with at least one sample out of the range of `[0, 1]`, which are
+    /// clipped.
+    pub clipped_pixels: usize,
+    /// Maximum distance of a sample from the range of `[0, 1]`.
+    pub max_overshoot: f32,
+}
+
+impl GamutStats {
+    /// Returns the ratio of clipped pixels, in the range of `[0, 1]`.
+    pub fn clipped_ratio(&self) -> f32 {
+        if self.total_pixels == 0 {
+            0.0
+        } else {
+            self.clipped_pixels as f32 / self.total_pixels as f32
+        }
+    }
+
+    fn merge(&mut self, other: &GamutStats) {
+        self.total_pixels += other.total_pixels;
+        self.clipped_pixels += other.clipped_pixels;
+        self.max_overshoot = self.max_overshoot.max(other.max_overshoot);
+    }
+}
+
+/// Tolerance of out-of-range samples not counted as clipped, to ignore rounding errors.
+const GAMUT_TOLERANCE: f32 = 1e-4;
+
 #[derive(Debug)]
 pub struct ColorTransformBuilder {
     detect_peak: bool,
     srgb_icc: bool,
     from_pq: bool,
+    tone_mapping: ToneMappingOptions,
+    hlg_display: HlgDisplayParams,
+    clip_output: bool,
+    force_scalar: bool,
 }
 
 impl Default for ColorTransformBuilder {
@@ -139,6 +249,10 @@ impl ColorTransformBuilder {
             detect_peak: false,
             srgb_icc: false,
             from_pq: false,
+            tone_mapping: ToneMappingOptions::default(),
+            hlg_display: HlgDisplayParams::default(),
+            clip_output: false,
+            force_scalar: false,
         }
     }
 
@@ -157,6 +271,36 @@ impl ColorTransformBuilder {
         self
     }
 
+    /// Sets the tone mapping operator used when transforming HDR images to SDR.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMappingOptions) -> &mut Self {
+        self.tone_mapping = tone_mapping;
+        self
+    }
+
+    /// Sets the display parameters used when applying HLG OOTF.
+    pub fn set_hlg_display(&mut self, hlg_display: HlgDisplayParams) -> &mut Self {
+        self.hlg_display = hlg_display;
+        self
+    }
+
+    /// Sets whether to clip output samples to the range of `[0, 1]` as the final stage.
+    ///
+    /// Clipped samples are counted in [`GamutStats`] returned by
+    /// [`ColorTransform::run_with_stats`].
+    pub fn set_clip_output(&mut self, value: bool) -> &mut Self {
+        self.clip_output = value;
+        self
+    }
+
+    /// Sets whether to use portable scalar code instead of SIMD implementations selected at
+    /// runtime.
+    ///
+    /// This makes the output identical across CPUs with different feature sets.
+    pub fn set_force_scalar(&mut self, value: bool) -> &mut Self {
+        self.force_scalar = value;
+        self
+    }
+
     pub fn build(
         self,
         from: &ColorEncodingWithProfile,
@@ -164,7 +308,12 @@ impl ColorTransformBuilder {
         oim: &OpsinInverseMatrix,
         tone_mapping: &ToneMapping,
     ) -> Result<ColorTransform> {
-        ColorTransform::with_builder(self, from, to, oim, tone_mapping)
+        let clip_output = self.clip_output;
+        let mut transform = ColorTransform::with_builder(self, from, to, oim, tone_mapping)?;
+        if clip_output {
+            transform.ops.push(ColorTransformOp::ClipOutput);
+        }
+        Ok(transform)
     }
 }
 
@@ -172,6 +321,7 @@ impl ColorTransformBuilder {
 #[derive(Debug, Clone)]
 pub struct ColorTransform {
     begin_channels: usize,
+    force_scalar: bool,
     ops: Vec<ColorTransformOp>,
 }
 
@@ -211,6 +361,10 @@ impl ColorTransform {
             detect_peak,
             srgb_icc,
             from_pq,
+            tone_mapping: tone_mapping_options,
+            hlg_display,
+            clip_output: _,
+            force_scalar,
         } = builder;
         let connecting_tf = if srgb_icc {
             TransferFunction::Srgb
@@ -244,6 +398,7 @@ impl ColorTransform {
         if from.is_equivalent(to) {
             return Ok(Self {
                 begin_channels,
+                force_scalar,
                 ops: Vec::new(),
             });
         }
@@ -259,6 +414,7 @@ impl ColorTransform {
                     ColourEncoding::IccProfile(_) => {
                         return Ok(Self {
                             begin_channels,
+                            force_scalar,
                             ops: vec![ColorTransformOp::IccToIcc {
                                 inputs: 0,
                                 outputs: 0,
@@ -321,6 +477,7 @@ impl ColorTransform {
                         luminances,
                         intensity_target,
                         min_nits,
+                        hlg_display,
                     },
                     inverse: true,
                 });
@@ -348,6 +505,7 @@ impl ColorTransform {
                         luminances,
                         intensity_target,
                         min_nits,
+                        hlg_display,
                     },
                     inverse: true,
                 });
@@ -373,6 +531,7 @@ impl ColorTransform {
                 });
                 return Ok(Self {
                     begin_channels,
+                    force_scalar,
                     ops,
                 });
             }
@@ -387,6 +546,7 @@ impl ColorTransform {
                     luminances: [0.0, 0.0, 0.0],
                     intensity_target,
                     min_nits,
+                    hlg_display,
                 },
                 inverse: true,
             });
@@ -478,19 +638,26 @@ impl ColorTransform {
             luminances,
             intensity_target,
             min_nits,
+            hlg_display,
         };
 
-        if intensity_target > 255.0 && !target_encoding.is_hdr() {
-            if current_encoding.colour_space == ColourSpace::Grey {
+        let target_display_luminance = tone_mapping_options.target_luminance();
+        if intensity_target > target_display_luminance && !target_encoding.is_hdr() {
+            if tone_mapping_options == ToneMappingOptions::Clip {
+                ops.push(ColorTransformOp::Scale(
+                    intensity_target / target_display_luminance,
+                ));
+                ops.push(ColorTransformOp::Clip);
+            } else if current_encoding.colour_space == ColourSpace::Grey {
                 ops.push(ColorTransformOp::ToneMapLumaRec2408 {
                     hdr_params,
-                    target_display_luminance: 255.0,
+                    target_display_luminance,
                     detect_peak,
                 });
             } else {
                 ops.push(ColorTransformOp::ToneMapRec2408 {
                     hdr_params,
-                    target_display_luminance: 255.0,
+                    target_display_luminance,
                     detect_peak,
                 });
 
@@ -547,6 +714,7 @@ impl ColorTransform {
 
         let mut ret = Self {
             begin_channels,
+            force_scalar,
             ops,
         };
         ret.optimize();
@@ -576,6 +744,14 @@ impl ColorTransform {
         self.ops.is_empty()
     }
 
+    /// Returns whether the transformation includes operations done by external CMS.
+    #[inline]
+    pub fn requires_cms(&self) -> bool {
+        self.ops
+            .iter()
+            .any(|op| matches!(op, ColorTransformOp::IccToIcc { .. }))
+    }
+
     #[inline]
     pub fn input_channels(&self) -> usize {
         self.begin_channels
@@ -607,19 +783,15 @@ impl ColorTransform {
         cms: &Cms,
     ) -> Result<usize> {
         let _gurad = tracing::trace_span!("Run color transform ops").entered();
-
-        let mut num_channels = self.begin_channels;
-        for op in &self.ops {
-            num_channels = op.run(channels, num_channels, cms)?;
-        }
-        Ok(num_channels)
+        self.run_chunked(channels, cms, &mut GamutStats::default())
     }
 
     /// Performs the prepared color transformation on the samples with the thread pool.
     ///
     /// Returns the number of final channels after transformation.
     ///
-    /// `channels` are planar sample data, all having the same length.
+    /// `channels` are planar sample data, all having the same length. Samples are split into
+    /// fixed-size chunks, and each task runs the whole transformation on its chunk.
     ///
     /// # Errors
     /// This function will return an error if it encountered ICC to ICC operation and the provided
@@ -630,12 +802,38 @@ impl ColorTransform {
         cms: &Cms,
         pool: &jxl_threadpool::JxlThreadPool,
     ) -> Result<usize> {
+        self.run_with_stats(channels, cms, pool)
+            .map(|(num_channels, _)| num_channels)
+    }
+
+    /// Performs the prepared color transformation on the samples with the thread pool, and
+    /// returns the number of final channels along with statistics of clipped samples.
+    ///
+    /// Statistics are collected only if the transformation is built with
+    /// [`ColorTransformBuilder::set_clip_output`].
+    ///
+    /// # Errors
+    /// This function will return an error if it encountered ICC to ICC operation and the provided
+    /// CMS cannot handle it.
+    pub fn run_with_stats<Cms: ColorManagementSystem + Sync + ?Sized>(
+        &self,
+        channels: &mut [&mut [f32]],
+        cms: &Cms,
+        pool: &jxl_threadpool::JxlThreadPool,
+    ) -> Result<(usize, GamutStats)> {
         let _gurad = tracing::trace_span!("Run color transform ops").entered();
 
-        let mut chunks = Vec::new();
+        let len = channels.first().map(|ch| ch.len()).unwrap_or(0);
+        if len <= TASK_CHUNK_SIZE {
+            let mut stats = GamutStats::default();
+            let num_channels = self.run_chunked(channels, cms, &mut stats)?;
+            return Ok((num_channels, stats));
+        }
+
+        let mut chunks = Vec::with_capacity(len.div_ceil(TASK_CHUNK_SIZE));
         let mut it = channels
             .iter_mut()
-            .map(|ch| ch.chunks_mut(65536))
+            .map(|ch| ch.chunks_mut(TASK_CHUNK_SIZE))
             .collect::<Vec<_>>();
         loop {
             let Some(chunk) = it
@@ -648,25 +846,75 @@ impl ColorTransform {
             chunks.push(chunk);
         }
 
-        let ret = std::sync::Mutex::new(Ok(self.begin_channels));
+        let ret = std::sync::Mutex::new(Ok((self.begin_channels, GamutStats::default())));
         pool.for_each_vec(chunks, |mut channels| {
-            let mut num_channels = self.begin_channels;
-            for op in &self.ops {
-                match op.run(&mut channels, num_channels, cms) {
-                    Ok(x) => {
-                        num_channels = x;
-                    }
-                    err => {
-                        *ret.lock().unwrap() = err;
-                        return;
-                    }
+            let mut stats = GamutStats::default();
+            let result = self.run_chunked(&mut channels, cms, &mut stats);
+            let mut ret = ret.lock().unwrap();
+            // Keep the error if any of the tasks failed.
+            match (&mut *ret, result) {
+                (Ok((ret_channels, ret_stats)), Ok(num_channels)) => {
+                    *ret_channels = num_channels;
+                    ret_stats.merge(&stats);
                 }
+                (Ok(_), Err(e)) => *ret = Err(e),
+                (Err(_), _) => {}
             }
-            *ret.lock().unwrap() = Ok(num_channels);
         });
         ret.into_inner().unwrap()
     }
 
+    /// Runs the operations on small chunks of samples in turn, so that samples stay in cache
+    /// while going through the whole transformation.
+    fn run_chunked<Cms: ColorManagementSystem + ?Sized>(
+        &self,
+        channels: &mut [&mut [f32]],
+        cms: &Cms,
+        stats: &mut GamutStats,
+    ) -> Result<usize> {
+        let len = channels.first().map(|ch| ch.len()).unwrap_or(0);
+        // Peak detection and CMS transforms work on all of the given samples at once.
+        let splittable = self.ops.iter().all(|op| {
+            !matches!(
+                op,
+                ColorTransformOp::ToneMapRec2408 {
+                    detect_peak: true,
+                    ..
+                } | ColorTransformOp::ToneMapLumaRec2408 {
+                    detect_peak: true,
+                    ..
+                } | ColorTransformOp::IccToIcc { .. }
+            )
+        });
+        if !splittable || len <= PIPELINE_CHUNK_SIZE {
+            return self.run_ops(channels, cms, stats);
+        }
+
+        let mut num_channels = self.begin_channels;
+        for start in (0..len).step_by(PIPELINE_CHUNK_SIZE) {
+            let end = (start + PIPELINE_CHUNK_SIZE).min(len);
+            let mut chunk = channels
+                .iter_mut()
+                .map(|ch| &mut ch[start..end])
+                .collect::<Vec<_>>();
+            num_channels = self.run_ops(&mut chunk, cms, stats)?;
+        }
+        Ok(num_channels)
+    }
+
+    fn run_ops<Cms: ColorManagementSystem + ?Sized>(
+        &self,
+        channels: &mut [&mut [f32]],
+        cms: &Cms,
+        stats: &mut GamutStats,
+    ) -> Result<usize> {
+        let mut num_channels = self.begin_channels;
+        for op in &self.ops {
+            num_channels = op.run(channels, num_channels, cms, stats, self.force_scalar)?;
+        }
+        Ok(num_channels)
+    }
+
     fn optimize(&mut self) {
         let mut matrix_op_from = None;
         let mut matrix = [0f32; 9];
@@ -730,7 +978,9 @@ enum ColorTransformOp {
         luminances: [f32; 3],
         saturation_factor: f32,
     },
+    Scale(f32),
     Clip,
+    ClipOutput,
     IccToIcc {
         inputs: usize,
         outputs: usize,
@@ -798,7 +1048,9 @@ impl std::fmt::Debug for ColorTransformOp {
                 .field("luminances", luminances)
                 .field("saturation_factor", saturation_factor)
                 .finish(),
+            Self::Scale(factor) => f.debug_tuple("Scale").field(factor).finish(),
             Self::Clip => f.write_str("Clip"),
+            Self::ClipOutput => f.write_str("ClipOutput"),
             Self::IccToIcc {
                 inputs,
                 outputs,
@@ -833,7 +1085,9 @@ impl ColorTransformOp {
             ColorTransformOp::ToneMapRec2408 { .. } => Some(3),
             ColorTransformOp::ToneMapLumaRec2408 { .. } => Some(1),
             ColorTransformOp::GamutMap { .. } => Some(3),
-            ColorTransformOp::Clip => None,
+            ColorTransformOp::Scale(_) | ColorTransformOp::Clip | ColorTransformOp::ClipOutput => {
+                None
+            }
             ColorTransformOp::IccToIcc { inputs: 0, .. } => None,
             ColorTransformOp::IccToIcc { inputs, .. } => Some(inputs),
         }
@@ -854,7 +1108,9 @@ impl ColorTransformOp {
             ColorTransformOp::ToneMapRec2408 { .. } => Some(3),
             ColorTransformOp::ToneMapLumaRec2408 { .. } => Some(1),
             ColorTransformOp::GamutMap { .. } => Some(3),
-            ColorTransformOp::Clip => None,
+            ColorTransformOp::Scale(_) | ColorTransformOp::Clip | ColorTransformOp::ClipOutput => {
+                None
+            }
             ColorTransformOp::IccToIcc { outputs: 0, .. } => None,
             ColorTransformOp::IccToIcc { outputs, .. } => Some(outputs),
         }
@@ -865,6 +1121,8 @@ impl ColorTransformOp {
         channels: &mut [&mut [f32]],
         num_input_channels: usize,
         cms: &Cms,
+        stats: &mut GamutStats,
+        force_scalar: bool,
     ) -> Result<usize> {
         let channel_count = channels.len();
         if let Some(inputs) = self.inputs() {
@@ -883,7 +1141,7 @@ impl ColorTransformOp {
                     unreachable!()
                 };
                 let xyb = [&mut **x, &mut **y, &mut **b];
-                crate::xyb::run(xyb, *opsin_bias, *intensity_target);
+                crate::xyb::run(xyb, *opsin_bias, *intensity_target, force_scalar);
                 3
             }
             Self::LumaToXyz { illuminant } => {
@@ -923,7 +1181,12 @@ impl ColorTransformOp {
                 hdr_params,
                 inverse: false,
             } => {
-                apply_transfer_function(&mut channels[..num_input_channels], *tf, *hdr_params);
+                apply_transfer_function(
+                    &mut channels[..num_input_channels],
+                    *tf,
+                    *hdr_params,
+                    force_scalar,
+                );
                 num_input_channels
             }
             Self::TransferFunction {
@@ -935,6 +1198,7 @@ impl ColorTransformOp {
                     &mut channels[..num_input_channels],
                     *tf,
                     *hdr_params,
+                    force_scalar,
                 );
                 num_input_channels
             }
@@ -958,7 +1222,15 @@ impl ColorTransformOp {
                 let [r, g, b, ..] = channels else {
                     unreachable!()
                 };
-                tone_map::tone_map(r, g, b, hdr_params, *target_display_luminance, *detect_peak);
+                tone_map::tone_map(
+                    r,
+                    g,
+                    b,
+                    hdr_params,
+                    *target_display_luminance,
+                    *detect_peak,
+                    force_scalar,
+                );
                 3
             }
             Self::ToneMapLumaRec2408 {
@@ -967,7 +1239,13 @@ impl ColorTransformOp {
                 detect_peak,
             } => {
                 let [y, ..] = channels else { unreachable!() };
-                tone_map::tone_map_luma(y, hdr_params, *target_display_luminance, *detect_peak);
+                tone_map::tone_map_luma(
+                    y,
+                    hdr_params,
+                    *target_display_luminance,
+                    *detect_peak,
+                    force_scalar,
+                );
                 1
             }
             Self::GamutMap {
@@ -977,9 +1255,17 @@ impl ColorTransformOp {
                 let [r, g, b, ..] = channels else {
                     unreachable!()
                 };
-                gamut_map::gamut_map(r, g, b, *luminances, *saturation_factor);
+                gamut_map::gamut_map(r, g, b, *luminances, *saturation_factor, force_scalar);
                 3
             }
+            Self::Scale(factor) => {
+                for buf in &mut channels[..num_input_channels] {
+                    for v in buf.iter_mut() {
+                        *v *= factor;
+                    }
+                }
+                num_input_channels
+            }
             Self::Clip => {
                 for buf in &mut channels[..num_input_channels] {
                     for v in buf.iter_mut() {
@@ -988,6 +1274,24 @@ impl ColorTransformOp {
                 }
                 num_input_channels
             }
+            Self::ClipOutput => {
+                let channels = &mut channels[..num_input_channels];
+                let len = channels.first().map(|ch| ch.len()).unwrap_or(0);
+                for idx in 0..len {
+                    let mut overshoot = 0f32;
+                    for ch in channels.iter_mut() {
+                        let v = ch[idx];
+                        overshoot = overshoot.max(v - 1.0).max(-v);
+                        ch[idx] = v.clamp(0.0, 1.0);
+                    }
+                    if overshoot > GAMUT_TOLERANCE {
+                        stats.clipped_pixels += 1;
+                        stats.max_overshoot = stats.max_overshoot.max(overshoot);
+                    }
+                }
+                stats.total_pixels += len;
+                num_input_channels
+            }
             Self::IccToIcc { from, to, .. } => {
                 cms.transform(from, to, RenderingIntent::Relative, channels)?
             }
@@ -1000,12 +1304,14 @@ struct HdrParams {
     luminances: [f32; 3],
     intensity_target: f32,
     min_nits: f32,
+    hlg_display: HlgDisplayParams,
 }
 
 fn apply_transfer_function(
     channels: &mut [&mut [f32]],
     tf: TransferFunction,
     hdr_params: HdrParams,
+    force_scalar: bool,
 ) {
     match tf {
         TransferFunction::Gamma {
@@ -1014,7 +1320,7 @@ fn apply_transfer_function(
         } => {
             let gamma = 1e7 / gamma as f32;
             for ch in channels {
-                tf::apply_gamma(ch, gamma);
+                tf::apply_gamma(ch, gamma, force_scalar);
             }
         }
         TransferFunction::Gamma {
@@ -1023,31 +1329,31 @@ fn apply_transfer_function(
         } => {
             let gamma = gamma as f32 / 1e7;
             for ch in channels {
-                tf::apply_gamma(ch, gamma);
+                tf::apply_gamma(ch, gamma, force_scalar);
             }
         }
         TransferFunction::Bt709 => {
             for ch in channels {
-                tf::linear_to_bt709(ch);
+                tf::linear_to_bt709(ch, force_scalar);
             }
         }
         TransferFunction::Unknown => {}
         TransferFunction::Linear => {}
         TransferFunction::Srgb => {
             for ch in channels {
-                tf::linear_to_srgb(ch);
+                tf::linear_to_srgb(ch, force_scalar);
             }
         }
         TransferFunction::Pq => {
             let intensity_target = hdr_params.intensity_target;
             for ch in channels {
-                tf::linear_to_pq(ch, intensity_target);
+                tf::linear_to_pq(ch, intensity_target, force_scalar);
             }
         }
         TransferFunction::Dci => {
             let gamma = 1.0 / 2.6;
             for ch in channels {
-                tf::apply_gamma(ch, gamma);
+                tf::apply_gamma(ch, gamma, force_scalar);
             }
         }
         TransferFunction::Hlg => {
@@ -1070,6 +1376,7 @@ fn apply_inverse_transfer_function(
     channels: &mut [&mut [f32]],
     tf: TransferFunction,
     hdr_params: HdrParams,
+    force_scalar: bool,
 ) {
     match tf {
         TransferFunction::Gamma {
@@ -1078,7 +1385,7 @@ fn apply_inverse_transfer_function(
         } => {
             let gamma = gamma as f32 / 1e7;
             for ch in channels {
-                tf::apply_gamma(ch, gamma);
+                tf::apply_gamma(ch, gamma, force_scalar);
             }
         }
         TransferFunction::Gamma {
@@ -1087,7 +1394,7 @@ fn apply_inverse_transfer_function(
         } => {
             let gamma = 1e7 / gamma as f32;
             for ch in channels {
-                tf::apply_gamma(ch, gamma);
+                tf::apply_gamma(ch, gamma, force_scalar);
             }
         }
         TransferFunction::Bt709 => {
@@ -1105,13 +1412,13 @@ fn apply_inverse_transfer_function(
         TransferFunction::Pq => {
             let intensity_target = hdr_params.intensity_target;
             for ch in channels {
-                tf::pq_to_linear(ch, intensity_target);
+                tf::pq_to_linear(ch, intensity_target, force_scalar);
             }
         }
         TransferFunction::Dci => {
             let gamma = 2.6;
             for ch in channels {
-                tf::apply_gamma(ch, gamma);
+                tf::apply_gamma(ch, gamma, force_scalar);
             }
         }
         TransferFunction::Hlg => {
@@ -1120,12 +1427,124 @@ fn apply_inverse_transfer_function(
             let g = &mut **g;
             let b = &mut **b;
             let luminances = hdr_params.luminances;
-            let intensity_target = hdr_params.intensity_target;
+            let HlgDisplayParams {
+                peak_luminance,
+                surround_luminance,
+            } = hdr_params.hlg_display;
+            let peak_luminance = peak_luminance.unwrap_or(hdr_params.intensity_target);
 
             tf::hlg_to_linear(r);
             tf::hlg_to_linear(g);
             tf::hlg_to_linear(b);
-            tf::hlg_oo([r, g, b], luminances, intensity_target);
+            tf::hlg_oo([r, g, b], luminances, peak_luminance, surround_luminance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jxl_oxide_common::BundleDefault;
+
+    use super::*;
+
+    fn transform_pq_nits(nits: &[f32], tone_mapping_options: ToneMappingOptions) -> Vec<f32> {
+        let oim = OpsinInverseMatrix::default_with_context(());
+        let mut tone_mapping = ToneMapping::default_with_context(());
+        tone_mapping.intensity_target = 1000.0;
+
+        let from = EnumColourEncoding::bt2100_pq(RenderingIntent::Relative);
+        let to = EnumColourEncoding {
+            tf: TransferFunction::Linear,
+            ..from.clone()
+        };
+        let mut builder = ColorTransform::builder();
+        builder.set_tone_mapping(tone_mapping_options);
+        let transform = builder
+            .build(
+                &ColorEncodingWithProfile::new(from),
+                &ColorEncodingWithProfile::new(to),
+                &oim,
+                &tone_mapping,
+            )
+            .unwrap();
+
+        let mut samples = nits
+            .iter()
+            .map(|&v| tf::pq::linear_to_pq_generic(v / 1000.0, 1000.0))
+            .collect::<Vec<_>>();
+        let mut g = samples.clone();
+        let mut b = samples.clone();
+        transform
+            .run(&mut [&mut samples, &mut g, &mut b], &crate::NullCms)
+            .unwrap();
+        samples
+    }
+
+    #[test]
+    fn tone_map_clip() {
+        let out = transform_pq_nits(&[51.0, 127.5, 1000.0], ToneMappingOptions::Clip);
+        for (v, expected) in out.into_iter().zip([0.2, 0.5, 1.0]) {
+            assert!((v - expected).abs() < 1e-3, "{v} != {expected}");
+        }
+    }
+
+    #[test]
+    fn tone_map_target_luminance() {
+        // Peak luminance is within the target; tone mapping is not needed.
+        let out = transform_pq_nits(
+            &[100.0, 500.0, 1000.0],
+            ToneMappingOptions::TargetLuminance(1000.0),
+        );
+        for (v, expected) in out.into_iter().zip([0.1, 0.5, 1.0]) {
+            assert!((v - expected).abs() < 1e-3, "{v} != {expected}");
+        }
+
+        let rec2408 = transform_pq_nits(&[1000.0], ToneMappingOptions::Rec2408);
+        assert!((rec2408[0] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hlg_surround_luminance() {
+        let oim = OpsinInverseMatrix::default_with_context(());
+        let mut tone_mapping = ToneMapping::default_with_context(());
+        tone_mapping.intensity_target = 1000.0;
+
+        let from = EnumColourEncoding::bt2100_hlg(RenderingIntent::Relative);
+        let to = EnumColourEncoding {
+            tf: TransferFunction::Linear,
+            ..from.clone()
+        };
+
+        for (surround_luminance, gamma) in [(5.0, 1.2), (50.0, 1.1221)] {
+            let mut builder = ColorTransform::builder();
+            builder
+                .set_tone_mapping(ToneMappingOptions::TargetLuminance(1000.0))
+                .set_hlg_display(HlgDisplayParams {
+                    peak_luminance: None,
+                    surround_luminance,
+                });
+            let transform = builder
+                .build(
+                    &ColorEncodingWithProfile::new(from.clone()),
+                    &ColorEncodingWithProfile::new(to.clone()),
+                    &oim,
+                    &tone_mapping,
+                )
+                .unwrap();
+
+            let mut r = [0.25f32, 0.5, 0.75];
+            let mut g = r;
+            let mut b = r;
+            transform
+                .run(&mut [&mut r, &mut g, &mut b], &crate::NullCms)
+                .unwrap();
+
+            let mut expected = [0.25f32, 0.5, 0.75];
+            tf::hlg_to_linear(&mut expected);
+            for (v, expected) in r.into_iter().zip(expected) {
+                let expected = expected.powf(gamma);
+                assert!((v - expected).abs() < 1e-3, "{v} != {expected}");
+            }
         }
     }
 }
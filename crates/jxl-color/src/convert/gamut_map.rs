@@ -9,10 +9,15 @@ pub(super) fn gamut_map(
     b: &mut [f32],
     luminances: [f32; 3],
     saturation_factor: f32,
+    force_scalar: bool,
 ) {
     assert_eq!(r.len(), g.len());
     assert_eq!(g.len(), b.len());
 
+    if force_scalar {
+        return gamut_map_generic(r, g, b, luminances, saturation_factor);
+    }
+
     #[cfg(target_arch = "x86_64")]
     let (r, g, b) = if is_x86_feature_detected!("fma") && is_x86_feature_detected!("sse4.1") {
         if is_x86_feature_detected!("avx2") {
@@ -60,7 +65,16 @@ pub(super) fn gamut_map(
         (r, g, b)
     };
 
-    // generic
+    gamut_map_generic(r, g, b, luminances, saturation_factor);
+}
+
+fn gamut_map_generic(
+    r: &mut [f32],
+    g: &mut [f32],
+    b: &mut [f32],
+    luminances: [f32; 3],
+    saturation_factor: f32,
+) {
     for ((r, g), b) in r.iter_mut().zip(g).zip(b) {
         let mapped = crate::gamut::map_gamut_generic([*r, *g, *b], luminances, saturation_factor);
         *r = mapped[0];
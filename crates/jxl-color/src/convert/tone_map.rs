@@ -13,6 +13,7 @@ pub(super) fn tone_map(
     hdr_params: &HdrParams,
     target_display_luminance: f32,
     detect_peak: bool,
+    force_scalar: bool,
 ) {
     assert_eq!(r.len(), g.len());
     assert_eq!(g.len(), b.len());
@@ -21,7 +22,7 @@ pub(super) fn tone_map(
     let intensity_target = hdr_params.intensity_target;
     let min_nits = hdr_params.min_nits;
     let detected_peak_luminance = if detect_peak {
-        detect_peak_luminance(r, g, b, luminances) * intensity_target
+        detect_peak_luminance(r, g, b, luminances, force_scalar) * intensity_target
     } else {
         intensity_target
     };
@@ -30,6 +31,18 @@ pub(super) fn tone_map(
     let from_luminance_range = (min_nits, peak_luminance);
     let to_luminance_range = (0.0, target_display_luminance);
 
+    if force_scalar {
+        return tone_map_generic(
+            r,
+            g,
+            b,
+            luminances,
+            intensity_target,
+            from_luminance_range,
+            to_luminance_range,
+        );
+    }
+
     #[cfg(target_arch = "x86_64")]
     if is_x86_feature_detected!("fma") && is_x86_feature_detected!("sse4.1") {
         if is_x86_feature_detected!("avx2") {
@@ -104,6 +117,7 @@ pub(super) fn tone_map_luma(
     hdr_params: &HdrParams,
     target_display_luminance: f32,
     detect_peak: bool,
+    force_scalar: bool,
 ) {
     let intensity_target = hdr_params.intensity_target;
     let min_nits = hdr_params.min_nits;
@@ -122,6 +136,15 @@ pub(super) fn tone_map_luma(
     let from_luminance_range = (min_nits, peak_luminance);
     let to_luminance_range = (0.0, target_display_luminance);
 
+    if force_scalar {
+        return tone_map_luma_generic(
+            luma,
+            intensity_target,
+            from_luminance_range,
+            to_luminance_range,
+        );
+    }
+
     #[cfg(target_arch = "x86_64")]
     if is_x86_feature_detected!("fma") && is_x86_feature_detected!("sse4.1") {
         if is_x86_feature_detected!("avx2") {
@@ -609,7 +632,17 @@ unsafe fn tone_map_luma_aarch64_neon(
     );
 }
 
-fn detect_peak_luminance(r: &[f32], g: &[f32], b: &[f32], luminances: [f32; 3]) -> f32 {
+fn detect_peak_luminance(
+    r: &[f32],
+    g: &[f32],
+    b: &[f32],
+    luminances: [f32; 3],
+    force_scalar: bool,
+) -> f32 {
+    if force_scalar {
+        return detect_peak_luminance_generic(r, g, b, luminances);
+    }
+
     #[cfg(target_arch = "x86_64")]
     {
         if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
@@ -775,8 +808,9 @@ mod tests {
             luminances: [0.2126, 0.7152, 0.0722],
             intensity_target: 10000.0,
             min_nits: 0.0,
+            hlg_display: Default::default(),
         };
-        tone_map(&mut r, &mut g, &mut b, &hdr_params, 255.0, false);
+        tone_map(&mut r, &mut g, &mut b, &hdr_params, 255.0, false, false);
 
         dbg!(r);
         dbg!(g);
@@ -804,8 +838,9 @@ mod tests {
             luminances: [0.2126, 0.7152, 0.0722],
             intensity_target: 10000.0,
             min_nits: 0.0,
+            hlg_display: Default::default(),
         };
-        tone_map(&mut r, &mut g, &mut b, &hdr_params, 255.0, true);
+        tone_map(&mut r, &mut g, &mut b, &hdr_params, 255.0, true, false);
 
         dbg!(r);
         dbg!(g);
@@ -825,7 +860,7 @@ mod tests {
         let r = samples;
         let g = samples;
         let b = samples;
-        let peak = detect_peak_luminance(&r, &g, &b, [0.2126, 0.7152, 0.0722]);
+        let peak = detect_peak_luminance(&r, &g, &b, [0.2126, 0.7152, 0.0722], false);
         assert!((peak - 0.1).abs() < 1e-6);
     }
 
@@ -835,7 +870,7 @@ mod tests {
         let r = samples;
         let g = samples;
         let b = samples;
-        let peak = detect_peak_luminance(&r, &g, &b, [0.2126, 0.7152, 0.0722]);
+        let peak = detect_peak_luminance(&r, &g, &b, [0.2126, 0.7152, 0.0722], false);
         assert!(peak == 1.0);
     }
 }
@@ -3,6 +3,7 @@
 pub enum Error {
     Bitstream(jxl_bitstream::Error),
     Decoder(jxl_coding::Error),
+    Buffer(jxl_grid::Error),
     InvalidIccStream(&'static str),
     IccParseFailure(&'static str),
     UnsupportedColorEncoding,
@@ -25,6 +26,12 @@ impl From<jxl_coding::Error> for Error {
     }
 }
 
+impl From<jxl_grid::Error> for Error {
+    fn from(err: jxl_grid::Error) -> Self {
+        Self::Buffer(err)
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Error::*;
@@ -32,6 +39,7 @@ impl std::fmt::Display for Error {
         match self {
             Bitstream(err) => write!(f, "bitstream error: {}", err),
             Decoder(err) => write!(f, "entropy decoder error: {}", err),
+            Buffer(err) => write!(f, "{}", err),
             InvalidIccStream(s) => write!(f, "invalid ICC stream: {s}"),
             IccParseFailure(s) => write!(f, "parsing ICC profile failed: {s}"),
             UnsupportedColorEncoding => write!(f, "unsupported color encoding"),
@@ -51,6 +59,7 @@ impl std::error::Error for Error {
         match self {
             Bitstream(err) => Some(err),
             Decoder(err) => Some(err),
+            Buffer(err) => Some(err),
             CmsFailure(err) => Some(&**err),
             _ => None,
         }
@@ -111,7 +111,8 @@ impl ColourEncoding {
 }
 
 /// "Enum color encoding" represented by JPEG XL enum values.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnumColourEncoding {
     pub colour_space: ColourSpace,
     pub white_point: WhitePoint,
@@ -258,6 +259,27 @@ impl EnumColourEncoding {
             rendering_intent,
         }
     }
+
+    /// Creates an RGB color encoding with custom white point and primaries, given as
+    /// xy-chromaticity coordinates.
+    ///
+    /// This can be used to render in working color spaces which don't have predefined values,
+    /// such as ACEScg.
+    pub fn custom_rgb(
+        white_point: [f32; 2],
+        primaries: [[f32; 2]; 3],
+        tf: TransferFunction,
+        rendering_intent: RenderingIntent,
+    ) -> Self {
+        let [red, green, blue] = primaries.map(Customxy::from_float);
+        Self {
+            colour_space: ColourSpace::Rgb,
+            white_point: WhitePoint::Custom(Customxy::from_float(white_point)),
+            primaries: Primaries::Custom { red, green, blue },
+            tf,
+            rendering_intent,
+        }
+    }
 }
 
 impl EnumColourEncoding {
@@ -299,7 +321,12 @@ impl EnumColourEncoding {
 
     /// Returns the CICP tag which represents this color encoding.
     pub fn cicp(&self) -> Option<[u8; 4]> {
-        let primaries_cicp = self.primaries.cicp();
+        let primaries_cicp = match (self.primaries, self.white_point) {
+            (Primaries::P3, WhitePoint::D65) => Some(12),
+            (Primaries::P3, WhitePoint::Dci) => Some(11),
+            (Primaries::Srgb | Primaries::Bt2100, WhitePoint::D65) => self.primaries.cicp(),
+            _ => None,
+        };
         let tf_cicp = self.tf.cicp();
         if let (Some(primaries), Some(tf)) = (primaries_cicp, tf_cicp) {
             Some([primaries, tf, 0, 1])
@@ -314,13 +341,14 @@ define_bundle! {
     ///
     /// Coordinate values are scaled by `1e6` (`1_000_000`).
     #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Customxy {
         pub x: ty(U32(u(19), 524288 + u(19), 1048576 + u(20), 2097152 + u(21)); UnpackSigned),
         pub y: ty(U32(u(19), 524288 + u(19), 1048576 + u(20), 2097152 + u(21)); UnpackSigned),
     }
 
     /// HDR tone mapping metadata.
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct ToneMapping {
         all_default: ty(Bool) default(true),
         pub intensity_target: ty(F16) cond(!all_default) default(255.0),
@@ -331,6 +359,15 @@ define_bundle! {
 }
 
 impl Customxy {
+    /// Creates a custom xy-chromaticity coordinate from floating point values.
+    #[inline]
+    pub fn from_float([x, y]: [f32; 2]) -> Self {
+        Self {
+            x: (x * 1e6).round() as i32,
+            y: (y * 1e6).round() as i32,
+        }
+    }
+
     /// Returns the xy-chromaticity coordinate as floating point values.
     #[inline]
     pub fn as_float(self) -> [f32; 2] {
@@ -340,6 +377,7 @@ impl Customxy {
 
 /// Color space type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ColourSpace {
     /// Tristimulus RGB.
@@ -395,6 +433,7 @@ impl TryFrom<u32> for WhitePointDiscriminator {
 
 /// White point.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum WhitePoint {
     /// CIE Standard Illuminant D65.
@@ -464,6 +503,7 @@ impl TryFrom<u32> for PrimariesDiscriminator {
 
 /// RGB primaries.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Primaries {
     /// sRGB primaries (same as BT.709).
@@ -526,7 +566,8 @@ impl Primaries {
 }
 
 /// Rendering intent, defined by ICC specification.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum RenderingIntent {
     /// Perceptual; vendor-specific.
@@ -556,6 +597,7 @@ impl TryFrom<u32> for RenderingIntent {
 
 /// Transfer function (tone curve).
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum TransferFunction {
     /// Pure gamma curve.
@@ -621,6 +663,15 @@ impl<Ctx> Bundle<Ctx> for TransferFunction {
 }
 
 impl TransferFunction {
+    /// Creates a pure gamma transfer function, with the gamma value of the display (e.g. 2.2).
+    #[inline]
+    pub fn gamma(gamma: f32) -> Self {
+        Self::Gamma {
+            g: (gamma * 1e7).round() as u32,
+            inverted: false,
+        }
+    }
+
     /// Returns the CICP value of transfer function, if there is any.
     pub fn cicp(&self) -> Option<u8> {
         match self {
@@ -638,7 +689,7 @@ impl TransferFunction {
 
 define_bundle! {
     /// Opsin inverse metadata.
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct OpsinInverseMatrix {
         all_default: ty(Bool) default(true),
         pub inv_mat: ty(Array[Array[F16]; 3]; 3) cond(!all_default) default([
@@ -1,6 +1,8 @@
 //! Functions related to ICC profiles.
 //!
 //! - [`read_icc`] and [`decode_icc`] can be used to read embedded ICC profile from the bitstream.
+//!   [`read_icc_with_limit`] and [`decode_icc_with_limit`] additionally check buffer sizes against
+//!   the given memory limit.
 //! - [`colour_encoding_to_icc`] can be used to create an ICC profile to embed into the decoded
 //!   image file, or to be used by the color management system for various purposes.
 
@@ -8,7 +10,7 @@ mod decode;
 mod parse;
 mod synthesize;
 
-pub use decode::{decode_icc, read_icc};
+pub use decode::{decode_icc, decode_icc_with_limit, read_icc, read_icc_with_limit};
 pub use parse::icc_tf;
 pub(crate) use parse::parse_icc;
 pub(crate) use parse::parse_icc_raw;
@@ -2,11 +2,21 @@ use std::io::prelude::*;
 use std::io::Cursor;
 
 use jxl_bitstream::Bitstream;
+use jxl_grid::MemoryLimit;
 
 use crate::{Error, Result};
 
 /// Reads the encoded ICC profile stream from the given bitstream.
 pub fn read_icc(bitstream: &mut Bitstream) -> Result<Vec<u8>> {
+    read_icc_with_limit(bitstream, None)
+}
+
+/// Reads the encoded ICC profile stream from the given bitstream, checking the size of the stream
+/// against the memory limit.
+pub fn read_icc_with_limit(
+    bitstream: &mut Bitstream,
+    memory_limit: Option<&MemoryLimit>,
+) -> Result<Vec<u8>> {
     let enc_size = bitstream.read_u64()?;
     tracing::trace!(enc_size);
 
@@ -63,6 +73,9 @@ pub fn read_icc(bitstream: &mut Bitstream) -> Result<Vec<u8>> {
     }
 
     // Read remaining data
+    if let Some(memory_limit) = memory_limit {
+        memory_limit.check(enc_size as usize)?;
+    }
     encoded_icc.resize(enc_size as usize, 0);
     for (idx, b) in encoded_icc.iter_mut().enumerate().skip(max_size_header_len) {
         let sym = decoder.read_varint(bitstream, get_icc_ctx(idx, b1, b2))?;
@@ -189,6 +202,12 @@ fn shuffle4(bytes: &[u8]) -> Vec<u8> {
 
 /// Decodes the given ICC profile stream.
 pub fn decode_icc(stream: &[u8]) -> Result<Vec<u8>> {
+    decode_icc_with_limit(stream, None)
+}
+
+/// Decodes the given ICC profile stream, checking the size of the decoded profile against the
+/// memory limit.
+pub fn decode_icc_with_limit(stream: &[u8], memory_limit: Option<&MemoryLimit>) -> Result<Vec<u8>> {
     use std::num::Wrapping;
 
     const COMMON_TAGS: [&[u8]; 19] = [
@@ -219,6 +238,9 @@ pub fn decode_icc(stream: &[u8]) -> Result<Vec<u8>> {
     }
     let (header_data, mut data) = data.split_at(header_size);
     let mut commands_stream = Cursor::new(commands);
+    if let Some(memory_limit) = memory_limit {
+        memory_limit.check(output_size as usize)?;
+    }
     let mut out = Vec::with_capacity(output_size as usize);
 
     // Header
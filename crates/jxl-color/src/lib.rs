@@ -6,6 +6,10 @@
 //! external color management system, since it is required by the specification. Such
 //! transformations can be done by creating a [`ColorTransform`].
 //!
+//! Transformations involving arbitrary ICC profiles, including LUT-based profiles such as CMYK
+//! ones, need a [`ColorManagementSystem`]. `jxl-cms` provides one written in pure Rust, which
+//! jxl-render uses by default with its `jxl-cms` feature.
+//!
 //! # Modules
 //! - [`consts`] defines constants used by the various colorspaces.
 //! - [`icc`] provides functions related to ICC profiles.
@@ -27,4 +31,4 @@ pub use cms::*;
 pub use convert::*;
 pub use error::*;
 pub use header::*;
-pub use ycbcr::ycbcr_to_rgb;
+pub use ycbcr::{ycbcr_to_rgb, ycbcr_to_rgb_with};
@@ -8,7 +8,13 @@ pub use pq::*;
 pub use srgb::*;
 
 /// Applies gamma to samples.
-pub fn apply_gamma(samples: &mut [f32], gamma: f32) {
+///
+/// SIMD implementations are not used if `force_scalar` is `true`.
+pub fn apply_gamma(samples: &mut [f32], gamma: f32, force_scalar: bool) {
+    if force_scalar {
+        return apply_gamma_generic(samples, gamma);
+    }
+
     #[cfg(target_arch = "aarch64")]
     let samples = {
         if std::arch::is_aarch64_feature_detected!("neon") {
@@ -57,6 +63,10 @@ pub fn apply_gamma(samples: &mut [f32], gamma: f32) {
         }
     };
 
+    apply_gamma_generic(samples, gamma);
+}
+
+fn apply_gamma_generic(samples: &mut [f32], gamma: f32) {
     for x in samples {
         let a = *x;
         *x = if a <= 1e-7 {
@@ -96,14 +106,23 @@ unsafe fn linear_to_gamma_x86_64_avx2(samples: &mut [f32], gamma: f32) -> &mut [
     remainder
 }
 
+/// Computes the system gamma of HLG OOTF, for the display with given nominal peak luminance and
+/// surround luminance.
+fn hlg_system_gamma(peak_luminance: f32, surround_luminance: f32) -> f32 {
+    1.2f32
+        * 1.111f32.powf((peak_luminance / 1e3).log2())
+        * 0.98f32.powf((surround_luminance / 5.0).log2())
+}
+
 /// Converts scene luminance values to display luminance values using the hybrid log-gamma
 /// transfer function (HLG OOTF).
 pub fn hlg_oo(
     [samples_r, samples_g, samples_b]: [&mut [f32]; 3],
     [lr, lg, lb]: [f32; 3],
-    intensity_target: f32,
+    peak_luminance: f32,
+    surround_luminance: f32,
 ) {
-    let gamma = 1.2f32 * 1.111f32.powf((intensity_target / 1e3).log2());
+    let gamma = hlg_system_gamma(peak_luminance, surround_luminance);
     // 1/g - 1
     let exp = gamma - 1.0;
 
@@ -128,7 +147,7 @@ pub fn hlg_inverse_oo(
         return;
     }
 
-    let gamma = 1.2f32 * 1.111f32.powf((intensity_target / 1e3).log2());
+    let gamma = hlg_system_gamma(intensity_target, 5.0);
     // 1/g - 1
     let exp = (1.0 - gamma) / gamma;
 
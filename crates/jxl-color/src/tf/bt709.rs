@@ -1,5 +1,11 @@
 /// Converts the linear samples with the BT.709 transfer curve.
-pub fn linear_to_bt709(samples: &mut [f32]) {
+///
+/// SIMD implementations are not used if `force_scalar` is `true`.
+pub fn linear_to_bt709(samples: &mut [f32], force_scalar: bool) {
+    if force_scalar {
+        return linear_to_bt709_generic(samples);
+    }
+
     #[cfg(target_arch = "aarch64")]
     let samples = {
         if std::arch::is_aarch64_feature_detected!("neon") {
@@ -58,6 +64,10 @@ pub fn linear_to_bt709(samples: &mut [f32]) {
         }
     };
 
+    linear_to_bt709_generic(samples);
+}
+
+fn linear_to_bt709_generic(samples: &mut [f32]) {
     for x in samples {
         let a = *x;
         *x = if a <= 0.018 {
@@ -35,8 +35,10 @@ const INV_EOTF_Q_SMALL: [f32; 5] = [3.371868e1, 1.477719e3, 1.608477e4, -4.38988
 
 /// Converts the linear samples with the PQ transfer function, where linear sample value of 1.0
 /// represents `intensity_target` nits (PQ inverse EOTF).
+///
+/// SIMD implementations are not used if `force_scalar` is `true`.
 #[allow(unused_mut)]
-pub fn linear_to_pq(mut samples: &mut [f32], intensity_target: f32) {
+pub fn linear_to_pq(mut samples: &mut [f32], intensity_target: f32, force_scalar: bool) {
     #[cfg(target_arch = "x86_64")]
     #[target_feature(enable = "avx2")]
     #[target_feature(enable = "fma")]
@@ -82,6 +84,13 @@ pub fn linear_to_pq(mut samples: &mut [f32], intensity_target: f32) {
         it.into_remainder()
     }
 
+    if force_scalar {
+        for s in samples {
+            *s = linear_to_pq_generic(*s, intensity_target);
+        }
+        return;
+    }
+
     #[cfg(target_arch = "x86_64")]
     if is_x86_feature_detected!("fma") && is_x86_feature_detected!("sse4.1") {
         if is_x86_feature_detected!("avx2") {
@@ -245,8 +254,10 @@ pub(crate) unsafe fn linear_to_pq_aarch64_neon(
 
 /// Converts non-linear PQ signals to linear display luminance values, where luminance value of 1.0
 /// represents `intensity_target` nits (PQ EOTF).
+///
+/// SIMD implementations are not used if `force_scalar` is `true`.
 #[allow(unused_mut)]
-pub fn pq_to_linear(mut samples: &mut [f32], intensity_target: f32) {
+pub fn pq_to_linear(mut samples: &mut [f32], intensity_target: f32, force_scalar: bool) {
     #[cfg(target_arch = "x86_64")]
     #[target_feature(enable = "avx2")]
     #[target_feature(enable = "fma")]
@@ -292,6 +303,13 @@ pub fn pq_to_linear(mut samples: &mut [f32], intensity_target: f32) {
         it.into_remainder()
     }
 
+    if force_scalar {
+        for s in samples {
+            *s = pq_to_linear_generic(*s, intensity_target);
+        }
+        return;
+    }
+
     #[cfg(target_arch = "x86_64")]
     if is_x86_feature_detected!("fma") && is_x86_feature_detected!("sse4.1") {
         if is_x86_feature_detected!("avx2") {
@@ -484,7 +502,7 @@ mod tests {
             *v = idx as f32 * 1e-5;
         }
 
-        super::linear_to_pq(&mut input, 10000.0);
+        super::linear_to_pq(&mut input, 10000.0, false);
 
         for (idx, v) in input.iter().enumerate() {
             let linear = idx as f32 * 1e-5;
@@ -521,8 +539,8 @@ mod tests {
             *v = idx as f32 * 1e-5;
         }
 
-        super::linear_to_pq(&mut input, 10000.0);
-        super::pq_to_linear(&mut input, 1000.0);
+        super::linear_to_pq(&mut input, 10000.0, false);
+        super::pq_to_linear(&mut input, 1000.0, false);
 
         for (idx, v) in input.iter().enumerate() {
             let expected = idx as f32 * 1e-4;
@@ -9,8 +9,14 @@ const SRGB_POWTABLE_LOWER: Aligned = Aligned([
 ]);
 
 /// Converts the linear samples with the sRGB transfer curve.
+///
+/// SIMD implementations are not used if `force_scalar` is `true`.
 // Fast linear to sRGB conversion, ported from libjxl.
-pub fn linear_to_srgb(samples: &mut [f32]) {
+pub fn linear_to_srgb(samples: &mut [f32], force_scalar: bool) {
+    if force_scalar {
+        return linear_to_srgb_generic(samples);
+    }
+
     #[cfg(target_arch = "x86_64")]
     {
         if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
@@ -25,6 +31,10 @@ pub fn linear_to_srgb(samples: &mut [f32]) {
         }
     }
 
+    linear_to_srgb_generic(samples);
+}
+
+fn linear_to_srgb_generic(samples: &mut [f32]) {
     for s in samples {
         let v = s.to_bits() & 0x7fff_ffff;
         let v_adj = f32::from_bits((v | 0x3e80_0000) & 0x3eff_ffff);
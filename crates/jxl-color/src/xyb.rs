@@ -1,4 +1,8 @@
-pub(crate) fn run(xyb: [&mut [f32]; 3], ob: [f32; 3], intensity_target: f32) {
+pub(crate) fn run(xyb: [&mut [f32]; 3], ob: [f32; 3], intensity_target: f32, force_scalar: bool) {
+    if force_scalar {
+        return run_generic(xyb, ob, intensity_target);
+    }
+
     #[cfg(target_arch = "x86_64")]
     {
         if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
@@ -4,11 +4,23 @@ use jxl_grid::AlignedGrid;
 ///
 /// Channels are expected to be in CbYCr order.
 pub fn ycbcr_to_rgb(fb_cbycr: [&mut AlignedGrid<f32>; 3]) {
+    ycbcr_to_rgb_with(fb_cbycr, false)
+}
+
+/// Applies transform from YCbCr to RGB, optionally without SIMD implementations.
+///
+/// Channels are expected to be in CbYCr order. SIMD implementations are not used if
+/// `force_scalar` is `true`.
+pub fn ycbcr_to_rgb_with(fb_cbycr: [&mut AlignedGrid<f32>; 3], force_scalar: bool) {
     let [cb, y, cr] = fb_cbycr;
     let cb = cb.buf_mut();
     let y = y.buf_mut();
     let cr = cr.buf_mut();
 
+    if force_scalar {
+        return run_generic([cb, y, cr]);
+    }
+
     #[cfg(target_arch = "x86_64")]
     {
         if is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma") {
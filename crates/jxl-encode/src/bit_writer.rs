@@ -0,0 +1,211 @@
+/// `U32` distribution, represented as a pair of offset and the number of bits.
+///
+/// Constants are represented with zero bits.
+pub(crate) type U32Dist = (u32, usize);
+
+/// Bit writer which packs bits from the least significant bit, as JPEG XL bitstream does.
+#[derive(Debug, Default)]
+pub(crate) struct BitWriter {
+    buf: Vec<u8>,
+    acc: u64,
+    acc_bits: usize,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes lower `n` bits of `value`, where `n` is at most 32.
+    #[inline]
+    pub(crate) fn write_bits(&mut self, n: usize, value: u32) {
+        debug_assert!(n <= 32);
+        debug_assert!(n == 32 || value >> n == 0);
+        self.acc |= (value as u64) << self.acc_bits;
+        self.acc_bits += n;
+        while self.acc_bits >= 8 {
+            self.buf.push(self.acc as u8);
+            self.acc >>= 8;
+            self.acc_bits -= 8;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        self.write_bits(1, value as u32);
+    }
+
+    /// Writes an `U32` using the first distribution which can represent `value`.
+    ///
+    /// # Panics
+    /// Panics if none of the distributions can represent `value`.
+    pub(crate) fn write_u32(&mut self, value: u32, dist: [U32Dist; 4]) {
+        let (selector, (offset, n)) = dist
+            .into_iter()
+            .enumerate()
+            .find(|&(_, (offset, n))| {
+                value
+                    .checked_sub(offset)
+                    .map(|v| (v as u64) < (1u64 << n))
+                    .unwrap_or(false)
+            })
+            .expect("value cannot be represented with given distribution");
+        self.write_bits(2, selector as u32);
+        self.write_bits(n, value - offset);
+    }
+
+    /// Writes an `U64` as defined in the JPEG XL specification.
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        match value {
+            0 => self.write_bits(2, 0),
+            1..=16 => {
+                self.write_bits(2, 1);
+                self.write_bits(4, (value - 1) as u32);
+            }
+            17..=272 => {
+                self.write_bits(2, 2);
+                self.write_bits(8, (value - 17) as u32);
+            }
+            _ => {
+                self.write_bits(2, 3);
+                self.write_bits(12, (value & 0xfff) as u32);
+                let mut rest = value >> 12;
+                let mut shift = 12;
+                while rest != 0 {
+                    self.write_bool(true);
+                    if shift == 60 {
+                        self.write_bits(4, rest as u32);
+                        return;
+                    }
+                    self.write_bits(8, (rest & 0xff) as u32);
+                    rest >>= 8;
+                    shift += 8;
+                }
+                self.write_bool(false);
+            }
+        }
+    }
+
+    /// Writes an enum value, which is encoded as `U32(0, 1, 2 + u(4), 18 + u(6))`.
+    #[inline]
+    pub(crate) fn write_enum(&mut self, value: u32) {
+        self.write_u32(value, [(0, 0), (1, 0), (2, 4), (18, 6)]);
+    }
+
+    /// Writes an `F16`, rounding `value` to the nearest representable number.
+    ///
+    /// # Panics
+    /// Panics if `value` is not finite or is out of range of half precision floats.
+    pub(crate) fn write_f16(&mut self, value: f32) {
+        let bits = value.to_bits();
+        let sign = (bits >> 16) & 0x8000;
+        let abs = value.abs();
+        assert!(abs < 65520.0, "value cannot be represented with F16");
+        if abs < 1.0 / (1 << 24) as f32 {
+            self.write_bits(16, sign);
+            return;
+        }
+        if abs < 1.0 / 16384.0 {
+            // Subnormal
+            let mantissa = (abs * (1 << 24) as f32).round() as u32;
+            self.write_bits(16, sign | mantissa);
+            return;
+        }
+
+        let exponent = ((bits >> 23) & 0xff) - 112;
+        let mantissa = bits & 0x7fffff;
+        // Round to nearest; carry propagates to the exponent.
+        let rounded = ((exponent << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1);
+        self.write_bits(16, sign | rounded);
+    }
+
+    /// Returns the number of bits written so far.
+    #[inline]
+    pub(crate) fn bits_written(&self) -> usize {
+        self.buf.len() * 8 + self.acc_bits
+    }
+
+    /// Appends bits written to `other`, which doesn't need to be byte-aligned.
+    pub(crate) fn append_writer(&mut self, other: &BitWriter) {
+        for &byte in &other.buf {
+            self.write_bits(8, byte as u32);
+        }
+        self.write_bits(other.acc_bits, other.acc as u32);
+    }
+
+    pub(crate) fn zero_pad_to_byte(&mut self) {
+        let n = (8 - self.acc_bits % 8) % 8;
+        self.write_bits(n, 0);
+    }
+
+    /// Appends byte-aligned data.
+    ///
+    /// # Panics
+    /// Panics if the writer is not byte-aligned.
+    pub(crate) fn append_bytes(&mut self, bytes: &[u8]) {
+        assert_eq!(self.acc_bits, 0);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Pads the stream to byte boundary with zeros, and returns the written bytes.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.zero_pad_to_byte();
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jxl_bitstream::{Bitstream, U};
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let values = [0u64, 1, 16, 17, 272, 273, 4095, 4096, 1 << 40, u64::MAX];
+
+        let mut writer = BitWriter::new();
+        writer.write_bits(3, 5);
+        for &v in &values {
+            writer.write_u64(v);
+            writer.write_u32(v as u32 & 0xfffff, [(0, 0), (1, 0), (2, 4), (18, 20)]);
+        }
+        writer.write_enum(13);
+        let buf = writer.finish();
+
+        let mut bitstream = Bitstream::new(&buf);
+        assert_eq!(bitstream.read_bits(3).unwrap(), 5);
+        for &v in &values {
+            assert_eq!(bitstream.read_u64().unwrap(), v);
+            let v32 = bitstream.read_u32(0, 1, 2 + U(4), 18 + U(20)).unwrap();
+            assert_eq!(v32, v as u32 & 0xfffff);
+        }
+        assert_eq!(bitstream.read_u32(0, 1, 2 + U(4), 18 + U(6)).unwrap(), 13);
+    }
+
+    #[test]
+    fn f16() {
+        let values = [
+            0.0f32,
+            1.0,
+            -2.5,
+            1.0 / 2040.0,
+            65504.0,
+            1e-4,
+            128.0 / 2040.0 * 3.0,
+        ];
+
+        let mut writer = BitWriter::new();
+        for &v in &values {
+            writer.write_f16(v);
+        }
+        assert_eq!(writer.bits_written(), values.len() * 16);
+        let buf = writer.finish();
+
+        let mut bitstream = Bitstream::new(&buf);
+        for &v in &values {
+            let decoded = bitstream.read_f16_as_f32().unwrap();
+            assert!((decoded - v).abs() <= v.abs() / 1024.0, "{v} != {decoded}");
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! JPEG XL container writer.
+
+/// Signature box and `ftyp` box, which every container starts with.
+const CONTAINER_HEADER: [u8; 32] = [
+    0, 0, 0, 0xc, b'J', b'X', b'L', b' ', 0xd, 0xa, 0x87, 0xa, // JXL signature box
+    0, 0, 0, 0x14, b'f', b't', b'y', b'p', b'j', b'x', b'l', b' ', 0, 0, 0, 0, b'j', b'x', b'l',
+    b' ', // ftyp box
+];
+
+/// Writes a container with the given boxes, as `(box_type, data)`.
+pub(crate) fn write_container(boxes: &[([u8; 4], &[u8])]) -> Vec<u8> {
+    let len = CONTAINER_HEADER.len() + boxes.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+    let mut out = Vec::with_capacity(len);
+    out.extend_from_slice(&CONTAINER_HEADER);
+    for (ty, data) in boxes {
+        if let Ok(box_size) = u32::try_from(8 + data.len()) {
+            out.extend_from_slice(&box_size.to_be_bytes());
+            out.extend_from_slice(ty);
+        } else {
+            // Box with 64-bit size.
+            out.extend_from_slice(&1u32.to_be_bytes());
+            out.extend_from_slice(ty);
+            out.extend_from_slice(&(16 + data.len() as u64).to_be_bytes());
+        }
+        out.extend_from_slice(data);
+    }
+    out
+}
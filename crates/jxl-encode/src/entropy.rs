@@ -0,0 +1,688 @@
+//! Entropy encoder using prefix codes.
+//!
+//! Distributions are grouped into clusters, starting from the initial clustering given by the
+//! caller and then greedily merging clusters where it reduces the estimated cost. Histogram of
+//! each cluster is written as a Brotli-style canonical prefix code.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::bit_writer::BitWriter;
+
+const MAX_PREFIX_BITS: u8 = 15;
+const MAX_CODE_LENGTH_CODE_BITS: u8 = 5;
+const CODE_LENGTH_ORDER: [usize; 18] =
+    [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+const MAX_CLUSTERS: usize = 256;
+
+/// LZ77 parameters used when coding context maps, which are the smallest values representable.
+const LZ77_MIN_SYMBOL: u32 = 224;
+const LZ77_MIN_LENGTH: u32 = 3;
+
+/// Hybrid integer configuration.
+#[derive(Debug, Copy, Clone)]
+struct IntegerConfig {
+    split_exponent: u32,
+    msb_in_token: u32,
+    lsb_in_token: u32,
+}
+
+impl IntegerConfig {
+    const DEFAULT: Self = Self {
+        split_exponent: 4,
+        msb_in_token: 2,
+        lsb_in_token: 0,
+    };
+
+    fn encode(self, value: u32) -> Token {
+        let Self {
+            split_exponent,
+            msb_in_token,
+            lsb_in_token,
+        } = self;
+        let split = 1u32 << split_exponent;
+        if value < split {
+            return Token {
+                token: value,
+                num_extra_bits: 0,
+                extra_bits: 0,
+            };
+        }
+
+        let n = 31 - value.leading_zeros();
+        let in_token = msb_in_token + lsb_in_token;
+        let num_extra_bits = n - in_token;
+        let msb = (value >> (n - msb_in_token)) & ((1 << msb_in_token) - 1);
+        let lsb = value & ((1 << lsb_in_token) - 1);
+        let token = split + ((n - split_exponent) << in_token) + (msb << lsb_in_token) + lsb;
+        let extra_bits = (value >> lsb_in_token) & ((1u64 << num_extra_bits) - 1) as u32;
+        Token {
+            token,
+            num_extra_bits,
+            extra_bits,
+        }
+    }
+
+    fn write(self, writer: &mut BitWriter) {
+        // log_alphabet_size is 15 for prefix codes.
+        writer.write_bits(4, self.split_exponent);
+        writer.write_bits(
+            add_log2_ceil(self.split_exponent) as usize,
+            self.msb_in_token,
+        );
+        writer.write_bits(
+            add_log2_ceil(self.split_exponent - self.msb_in_token) as usize,
+            self.lsb_in_token,
+        );
+    }
+}
+
+fn add_log2_ceil(x: u32) -> u32 {
+    (x + 1).next_power_of_two().trailing_zeros()
+}
+
+/// Hybrid integer token with its raw bits.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Token {
+    token: u32,
+    num_extra_bits: u32,
+    extra_bits: u32,
+}
+
+impl Token {
+    /// Tokenizes the given integer.
+    #[inline]
+    pub(crate) fn new(value: u32) -> Self {
+        IntegerConfig::DEFAULT.encode(value)
+    }
+
+    /// Tokenizes LZ77 copy length.
+    fn lz77_length(length: u32) -> Self {
+        let mut token = IntegerConfig::DEFAULT.encode(length - LZ77_MIN_LENGTH);
+        token.token += LZ77_MIN_SYMBOL;
+        token
+    }
+}
+
+/// Entropy encoder which collects token statistics first, and then writes the tokens.
+#[derive(Debug)]
+pub(crate) struct EntropyEncoder {
+    lz77: bool,
+    /// Cluster index of each distribution, including the LZ77 distance distribution if enabled.
+    context_map: Vec<u8>,
+    /// Histograms of clusters; initial clusters before writing the header, and final clusters
+    /// after that.
+    histograms: Vec<Vec<u32>>,
+    codes: Vec<Vec<(u32, u8)>>,
+}
+
+impl EntropyEncoder {
+    /// Creates an encoder with all distributions mapped to a single cluster.
+    pub(crate) fn new(num_dist: u32) -> Self {
+        Self::with_context_map(vec![0; num_dist as usize])
+    }
+
+    /// Creates an encoder with the given initial clustering of distributions.
+    ///
+    /// Clusters may be merged further when writing the header.
+    pub(crate) fn with_context_map(context_map: Vec<u8>) -> Self {
+        let num_clusters = context_map
+            .iter()
+            .map(|&c| c as usize + 1)
+            .max()
+            .unwrap_or(1);
+        Self {
+            lz77: false,
+            context_map,
+            histograms: vec![Vec::new(); num_clusters],
+            codes: Vec::new(),
+        }
+    }
+
+    /// Creates an encoder for a single distribution with LZ77 enabled, which is used for
+    /// context maps.
+    fn with_lz77() -> Self {
+        let mut encoder = Self::with_context_map(vec![0, 1]);
+        encoder.lz77 = true;
+        encoder
+    }
+
+    /// Records occurrence of the token in the distribution `ctx`.
+    #[inline]
+    pub(crate) fn record(&mut self, ctx: u32, token: Token) {
+        let histogram = &mut self.histograms[self.context_map[ctx as usize] as usize];
+        let idx = token.token as usize;
+        if histogram.len() <= idx {
+            histogram.resize(idx + 1, 0);
+        }
+        histogram[idx] += 1;
+    }
+
+    /// Writes entropy coder configuration and the histograms of recorded tokens, and builds the
+    /// prefix code used by [`write_token`][Self::write_token].
+    pub(crate) fn write_header(&mut self, writer: &mut BitWriter) {
+        self.merge_clusters();
+
+        writer.write_bool(self.lz77);
+        if self.lz77 {
+            writer.write_u32(LZ77_MIN_SYMBOL, [(224, 0), (512, 0), (4096, 0), (8, 15)]);
+            writer.write_u32(LZ77_MIN_LENGTH, [(3, 0), (4, 0), (5, 2), (9, 8)]);
+            // log_alphabet_size of 8 uses the same number of bits for the configuration.
+            IntegerConfig::DEFAULT.write(writer);
+        }
+        if self.context_map.len() > 1 {
+            write_context_map(writer, &self.context_map);
+        }
+        // use_prefix_code
+        writer.write_bool(true);
+        for _ in &self.histograms {
+            IntegerConfig::DEFAULT.write(writer);
+        }
+
+        for histogram in &mut self.histograms {
+            let alphabet_size = histogram
+                .iter()
+                .rposition(|&count| count != 0)
+                .map(|idx| idx + 1)
+                .unwrap_or(1);
+            if alphabet_size == 1 {
+                writer.write_bool(false);
+            } else {
+                let v = (alphabet_size - 1) as u32;
+                let n = 31 - v.leading_zeros();
+                writer.write_bool(true);
+                writer.write_bits(4, n);
+                writer.write_bits(n as usize, v - (1 << n));
+            }
+            histogram.resize(alphabet_size, 0);
+        }
+
+        self.codes = self
+            .histograms
+            .iter()
+            .map(|histogram| canonical_codes(&write_histogram(writer, histogram)))
+            .collect();
+    }
+
+    /// Writes the token using the prefix code built by [`write_header`][Self::write_header].
+    #[inline]
+    pub(crate) fn write_token(&self, writer: &mut BitWriter, ctx: u32, token: Token) {
+        let cluster = self.context_map[ctx as usize];
+        let (code, len) = self.codes[cluster as usize][token.token as usize];
+        writer.write_bits(len as usize, code);
+        writer.write_bits(token.num_extra_bits as usize, token.extra_bits);
+    }
+
+    /// Merges clusters greedily, and renumbers clusters in the order of first appearance.
+    fn merge_clusters(&mut self) {
+        let num_initial_clusters = self.histograms.len();
+        let mut clusters = std::mem::take(&mut self.histograms)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, histogram)| histogram.iter().any(|&count| count != 0))
+            .map(|(idx, histogram)| {
+                let cost = histogram_cost(&histogram);
+                (vec![idx], histogram, cost)
+            })
+            .collect::<Vec<_>>();
+
+        while clusters.len() > 1 {
+            let mut best = None;
+            for (i, (_, a, cost_a)) in clusters.iter().enumerate() {
+                for (j, (_, b, cost_b)) in clusters.iter().enumerate().skip(i + 1) {
+                    let delta = histogram_cost(&merge_histograms(a, b)) - cost_a - cost_b;
+                    if best
+                        .map(|(_, _, best_delta)| delta < best_delta)
+                        .unwrap_or(true)
+                    {
+                        best = Some((i, j, delta));
+                    }
+                }
+            }
+
+            let (i, j, delta) = best.unwrap();
+            if delta >= 0.0 && clusters.len() <= MAX_CLUSTERS {
+                break;
+            }
+            let (members, histogram, _) = clusters.swap_remove(j);
+            let merged = &mut clusters[i];
+            merged.0.extend(members);
+            merged.1 = merge_histograms(&merged.1, &histogram);
+            merged.2 = histogram_cost(&merged.1);
+        }
+
+        // Map initial clusters to merged ones.
+        let mut merged_idx = vec![None; num_initial_clusters];
+        for (idx, (members, _, _)) in clusters.iter().enumerate() {
+            for &member in members {
+                merged_idx[member] = Some(idx);
+            }
+        }
+
+        // Renumber, so that the context map doesn't have holes. Distributions without any
+        // tokens follow the previous distribution, which makes the context map compress well.
+        let mut renumber = vec![None; clusters.len()];
+        let mut histograms = Vec::new();
+        let mut prev = 0u8;
+        for cluster in &mut self.context_map {
+            if let Some(merged) = merged_idx[*cluster as usize] {
+                let new_idx = *renumber[merged].get_or_insert_with(|| {
+                    histograms.push(std::mem::take(&mut clusters[merged].1));
+                    (histograms.len() - 1) as u8
+                });
+                prev = new_idx;
+            }
+            *cluster = prev;
+        }
+        if histograms.is_empty() {
+            histograms.push(Vec::new());
+        }
+        self.histograms = histograms;
+    }
+}
+
+fn merge_histograms(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut out = long.to_vec();
+    for (out, &count) in out.iter_mut().zip(short) {
+        *out += count;
+    }
+    out
+}
+
+/// Estimates the number of bits needed to code the histogram and the tokens.
+fn histogram_cost(histogram: &[u32]) -> f64 {
+    let total = histogram.iter().map(|&count| count as u64).sum::<u64>();
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    let data_bits = histogram
+        .iter()
+        .filter(|&&count| count != 0)
+        .map(|&count| count as f64 * (total / count as f64).log2())
+        .sum::<f64>();
+    let header_bits = 30.0 + 4.0 * histogram.len() as f64;
+    data_bits + header_bits
+}
+
+/// Writes the context map, either with the simple encoding or with an entropy coded stream with
+/// move-to-front transform and LZ77.
+fn write_context_map(writer: &mut BitWriter, context_map: &[u8]) {
+    let num_clusters = context_map.iter().map(|&c| c as u32 + 1).max().unwrap();
+    let nbits = add_log2_ceil(num_clusters - 1);
+
+    let mut nested = BitWriter::new();
+    if context_map.len() > 2 {
+        // is_simple = false, use_mtf = true
+        nested.write_bool(false);
+        nested.write_bool(true);
+        write_context_map_stream(&mut nested, context_map);
+    }
+
+    let simple_bits = 3 + nbits as usize * context_map.len();
+    if nbits <= 3 && (context_map.len() <= 2 || simple_bits <= nested.bits_written()) {
+        writer.write_bool(true);
+        writer.write_bits(2, nbits);
+        for &cluster in context_map {
+            writer.write_bits(nbits as usize, cluster as u32);
+        }
+    } else {
+        writer.append_writer(&nested);
+    }
+}
+
+fn write_context_map_stream(writer: &mut BitWriter, context_map: &[u8]) {
+    let mut mtf = std::array::from_fn::<u8, 256, _>(|idx| idx as u8);
+    let symbols = context_map
+        .iter()
+        .map(|&cluster| {
+            let idx = mtf.iter().position(|&v| v == cluster).unwrap();
+            mtf.copy_within(0..idx, 1);
+            mtf[0] = cluster;
+            idx as u32
+        })
+        .collect::<Vec<_>>();
+
+    // Repeats of the previous symbol are coded as LZ77 copies with distance 1.
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < symbols.len() {
+        let run = if idx > 0 {
+            symbols[idx..]
+                .iter()
+                .take_while(|&&sym| sym == symbols[idx - 1])
+                .count()
+        } else {
+            0
+        };
+        if run >= LZ77_MIN_LENGTH as usize {
+            tokens.push((0, Token::lz77_length(run as u32)));
+            tokens.push((1, Token::new(0)));
+            idx += run;
+        } else {
+            tokens.push((0, Token::new(symbols[idx])));
+            idx += 1;
+        }
+    }
+
+    let mut encoder = EntropyEncoder::with_lz77();
+    for &(ctx, token) in &tokens {
+        encoder.record(ctx, token);
+    }
+    encoder.write_header(writer);
+    for (ctx, token) in tokens {
+        encoder.write_token(writer, ctx, token);
+    }
+}
+
+/// Writes the histogram, and returns code lengths of symbols.
+fn write_histogram(writer: &mut BitWriter, counts: &[u32]) -> Vec<u8> {
+    let alphabet_size = counts.len();
+    if alphabet_size == 1 {
+        // Nothing is written; the only symbol is coded with zero bits.
+        return vec![0];
+    }
+
+    let mut nonzero = counts
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count != 0)
+        .map(|(sym, _)| sym);
+    let first = nonzero.next().unwrap();
+    if nonzero.next().is_none() {
+        // Simple prefix code with a single symbol.
+        let alphabet_bits = alphabet_size.next_power_of_two().trailing_zeros() as usize;
+        writer.write_bits(2, 1);
+        writer.write_bits(2, 0);
+        writer.write_bits(alphabet_bits, first as u32);
+        return vec![0; alphabet_size];
+    }
+
+    let lengths = build_code_lengths(counts, MAX_PREFIX_BITS);
+
+    let mut code_length_counts = [0u32; 18];
+    for &len in &lengths {
+        code_length_counts[len as usize] += 1;
+    }
+
+    // hskip = 0
+    writer.write_bits(2, 0);
+    let num_code_length_syms = code_length_counts.iter().filter(|&&c| c != 0).count();
+    if num_code_length_syms == 1 {
+        // Every symbol has the same code length, which is coded with zero bits.
+        let sym = lengths[0] as usize;
+        for idx in CODE_LENGTH_ORDER {
+            write_code_length_code_length(writer, if idx == sym { 1 } else { 0 });
+        }
+        return lengths;
+    }
+
+    let code_length_lengths = build_code_lengths(&code_length_counts, MAX_CODE_LENGTH_CODE_BITS);
+    let last = CODE_LENGTH_ORDER
+        .iter()
+        .rposition(|&idx| code_length_lengths[idx] != 0)
+        .unwrap();
+    for &idx in &CODE_LENGTH_ORDER[..=last] {
+        write_code_length_code_length(writer, code_length_lengths[idx]);
+    }
+
+    let code_length_codes = canonical_codes(&code_length_lengths);
+    for &len in &lengths {
+        let (code, code_len) = code_length_codes[len as usize];
+        writer.write_bits(code_len as usize, code);
+    }
+    lengths
+}
+
+fn write_code_length_code_length(writer: &mut BitWriter, len: u8) {
+    match len {
+        0 => writer.write_bits(2, 0),
+        4 => writer.write_bits(2, 1),
+        3 => writer.write_bits(2, 2),
+        2 => writer.write_bits(3, 0b011),
+        1 => writer.write_bits(4, 0b0111),
+        5 => writer.write_bits(4, 0b1111),
+        _ => unreachable!(),
+    }
+}
+
+/// Builds Huffman code lengths limited to `limit` bits.
+///
+/// The resulting code is always complete if there are two or more symbols with nonzero count.
+fn build_code_lengths(counts: &[u32], limit: u8) -> Vec<u8> {
+    let symbols = counts
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count != 0)
+        .map(|(sym, _)| sym)
+        .collect::<Vec<_>>();
+    let mut lengths = vec![0u8; counts.len()];
+    if symbols.len() < 2 {
+        return lengths;
+    }
+
+    // Flatten the distribution until the tree fits in the length limit.
+    let mut min_count = 1u32;
+    loop {
+        let mut parents = vec![usize::MAX; symbols.len() * 2 - 1];
+        let mut heap = symbols
+            .iter()
+            .enumerate()
+            .map(|(idx, &sym)| Reverse((counts[sym].max(min_count) as u64, idx)))
+            .collect::<BinaryHeap<_>>();
+        let mut next = symbols.len();
+        while heap.len() > 1 {
+            let Reverse((wa, a)) = heap.pop().unwrap();
+            let Reverse((wb, b)) = heap.pop().unwrap();
+            parents[a] = next;
+            parents[b] = next;
+            heap.push(Reverse((wa + wb, next)));
+            next += 1;
+        }
+
+        let mut depths = vec![0u8; parents.len()];
+        for idx in (0..parents.len() - 1).rev() {
+            depths[idx] = depths[parents[idx]] + 1;
+        }
+
+        if depths[..symbols.len()].iter().all(|&d| d <= limit) {
+            for (&sym, &depth) in symbols.iter().zip(&depths) {
+                lengths[sym] = depth;
+            }
+            return lengths;
+        }
+        min_count = min_count.saturating_mul(2);
+    }
+}
+
+/// Assigns canonical prefix codes to symbols, with bits reversed to be written LSB first.
+fn canonical_codes(lengths: &[u8]) -> Vec<(u32, u8)> {
+    let max_len = lengths.iter().copied().max().unwrap_or(0) as usize;
+    let mut len_counts = vec![0u32; max_len + 1];
+    for &len in lengths {
+        len_counts[len as usize] += 1;
+    }
+    len_counts[0] = 0;
+
+    let mut next_code = vec![0u32; max_len + 1];
+    let mut code = 0u32;
+    for len in 1..=max_len {
+        code = (code + len_counts[len - 1]) << 1;
+        next_code[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return (0, 0);
+            }
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            (code.reverse_bits() >> (32 - len), len)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use jxl_bitstream::Bitstream;
+    use jxl_coding::{Decoder, Lz77Tracker};
+
+    use super::*;
+
+    fn roundtrip(num_dist: u32, values: &[(u32, u32)]) {
+        roundtrip_with(EntropyEncoder::new(num_dist), num_dist, values);
+    }
+
+    fn roundtrip_with(mut encoder: EntropyEncoder, num_dist: u32, values: &[(u32, u32)]) {
+        for &(ctx, value) in values {
+            encoder.record(ctx, Token::new(value));
+        }
+        let mut writer = BitWriter::new();
+        encoder.write_header(&mut writer);
+        for &(ctx, value) in values {
+            encoder.write_token(&mut writer, ctx, Token::new(value));
+        }
+        let buf = writer.finish();
+
+        let mut bitstream = Bitstream::new(&buf);
+        let mut decoder = Decoder::parse(&mut bitstream, num_dist).unwrap();
+        decoder.begin(&mut bitstream).unwrap();
+        for &(ctx, value) in values {
+            assert_eq!(decoder.read_varint(&mut bitstream, ctx).unwrap(), value);
+        }
+        decoder.finalize().unwrap();
+    }
+
+    #[test]
+    fn single_symbol() {
+        roundtrip(1, &[(0, 0); 10]);
+        roundtrip(2, &[(1, 7); 10]);
+    }
+
+    #[test]
+    fn uniform_code_lengths() {
+        let values = (0..64).map(|v| (0, v % 4)).collect::<Vec<_>>();
+        roundtrip(1, &values);
+    }
+
+    #[test]
+    fn skewed_distribution() {
+        let mut values = Vec::new();
+        let mut state = 1u32;
+        for idx in 0..5000u32 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            let magnitude = (state >> 16) % 20;
+            let value = (state >> 8) & ((1 << magnitude) - 1);
+            values.push((idx % 6, value));
+        }
+        values.push((0, u32::MAX));
+        roundtrip(6, &values);
+    }
+
+    #[test]
+    fn clustered() {
+        // Contexts with distinct distributions, some of which are unused.
+        let num_dist = 600u32;
+        let context_map = (0..num_dist)
+            .map(|ctx| (ctx / 20) as u8)
+            .collect::<Vec<_>>();
+        let mut values = Vec::new();
+        let mut state = 7u32;
+        for idx in 0..20000u32 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            let ctx = (state >> 8) % num_dist;
+            if ctx % 7 == 3 {
+                continue;
+            }
+            let value = match (ctx / 20) % 3 {
+                0 => idx % 3,
+                1 => 100 + (state >> 20) % 50,
+                _ => (state >> 12) % 4000,
+            };
+            values.push((ctx, value));
+        }
+        roundtrip_with(
+            EntropyEncoder::with_context_map(context_map),
+            num_dist,
+            &values,
+        );
+
+        // Few clusters, which are written with simple clustering.
+        let values = (0..300)
+            .map(|v| (v % 3, v % (v % 3 + 2)))
+            .collect::<Vec<_>>();
+        roundtrip_with(EntropyEncoder::with_context_map(vec![0, 1, 2]), 3, &values);
+    }
+
+    #[test]
+    fn lz77_tracker() {
+        // Symbols 0 to 7, followed by a copy of 4 symbols from distance 8.
+        let mut tokens = (0..8).map(|v| (0, Token::new(v))).collect::<Vec<_>>();
+        tokens.push((0, Token::lz77_length(4)));
+        tokens.push((1, Token::new(7)));
+
+        let mut encoder = EntropyEncoder::with_lz77();
+        for &(ctx, token) in &tokens {
+            encoder.record(ctx, token);
+        }
+        let mut writer = BitWriter::new();
+        encoder.write_header(&mut writer);
+        for &(ctx, token) in &tokens {
+            encoder.write_token(&mut writer, ctx, token);
+        }
+        let buf = writer.finish();
+
+        let decode = |tracker: &Lz77Tracker| -> jxl_coding::Result<Vec<u32>> {
+            let mut bitstream = Bitstream::new(&buf);
+            let mut decoder = Decoder::parse(&mut bitstream, 1)?;
+            assert!(decoder.is_lz77_enabled());
+            decoder.set_lz77_tracker(tracker);
+            let values = (0..12)
+                .map(|_| decoder.read_varint(&mut bitstream, 0))
+                .collect::<jxl_coding::Result<Vec<_>>>()?;
+            decoder.finalize()?;
+            Ok(values)
+        };
+        let expected = [0, 1, 2, 3, 4, 5, 6, 7, 0, 1, 2, 3];
+
+        let tracker = Lz77Tracker::new();
+        assert_eq!(decode(&tracker).unwrap(), expected);
+        assert!(tracker.is_lz77_used());
+        assert_eq!(tracker.max_distance(), 8);
+
+        // The window is shrunk to the limit.
+        let tracker = Lz77Tracker::with_distance_limit(8);
+        assert_eq!(decode(&tracker).unwrap(), expected);
+
+        let tracker = Lz77Tracker::with_distance_limit(7);
+        let err = decode(&tracker).unwrap_err();
+        assert!(matches!(
+            err,
+            jxl_coding::Error::Lz77DistanceLimitExceeded {
+                distance: 8,
+                limit: 7
+            }
+        ));
+    }
+
+    #[test]
+    fn length_limit() {
+        // Fibonacci-like counts produce deep trees without length limiting.
+        let mut counts = vec![1u32, 1];
+        while counts.len() < 40 {
+            let n = counts.len();
+            counts.push(counts[n - 1].saturating_add(counts[n - 2]));
+        }
+        let lengths = build_code_lengths(&counts, MAX_PREFIX_BITS);
+        assert!(lengths
+            .iter()
+            .all(|&len| (1..=MAX_PREFIX_BITS).contains(&len)));
+        let kraft = lengths
+            .iter()
+            .map(|&len| 1u32 << (MAX_PREFIX_BITS - len))
+            .sum::<u32>();
+        assert_eq!(kraft, 1 << MAX_PREFIX_BITS);
+    }
+}
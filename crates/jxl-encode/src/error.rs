@@ -0,0 +1,45 @@
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    InvalidDimensions { width: usize, height: usize },
+    UnsupportedChannels(usize),
+    UnsupportedBitDepth(u32),
+    BitDepthTooLow { input: u32, requested: u32 },
+    BufferSizeMismatch { expected: usize, actual: usize },
+    InvalidJpeg(&'static str),
+    UnsupportedJpeg(&'static str),
+    InvalidPatch(&'static str),
+    InvalidOrientation(u32),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidDimensions { width, height } => {
+                write!(f, "invalid image dimensions: {width}x{height}")
+            }
+            Error::UnsupportedChannels(channels) => {
+                write!(f, "unsupported number of channels: {channels}")
+            }
+            Error::UnsupportedBitDepth(bits) => write!(f, "unsupported bit depth: {bits}"),
+            Error::BitDepthTooLow { input, requested } => write!(
+                f,
+                "bit depth {requested} is lower than bit depth of input samples ({input})"
+            ),
+            Error::BufferSizeMismatch { expected, actual } => write!(
+                f,
+                "buffer size mismatch: expected {expected} samples, got {actual}"
+            ),
+            Error::InvalidJpeg(msg) => write!(f, "invalid JPEG bitstream: {msg}"),
+            Error::UnsupportedJpeg(msg) => write!(f, "unsupported JPEG bitstream: {msg}"),
+            Error::InvalidPatch(msg) => write!(f, "invalid patch: {msg}"),
+            Error::InvalidOrientation(orientation) => {
+                write!(f, "invalid orientation: {orientation}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,240 @@
+//! Image header, frame header and TOC writers.
+use crate::bit_writer::{BitWriter, U32Dist};
+use crate::patch::{self, PatchRef};
+
+const SIZE_DIST: [U32Dist; 4] = [(1, 9), (1, 13), (1, 18), (1, 30)];
+const BIT_DEPTH_DIST: [U32Dist; 4] = [(8, 0), (10, 0), (12, 0), (1, 6)];
+const NAME_LEN_DIST: [U32Dist; 4] = [(0, 0), (0, 4), (16, 5), (48, 10)];
+const CROP_DIST: [U32Dist; 4] = [(0, 8), (256, 11), (2304, 14), (18688, 30)];
+const TOC_ENTRY_DIST: [U32Dist; 4] = [(0, 10), (1024, 14), (17408, 22), (4211712, 30)];
+
+/// Properties of the image being encoded.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ImageInfo {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) grayscale: bool,
+    pub(crate) has_alpha: bool,
+    pub(crate) bits_per_sample: u32,
+    /// Orientation signalled in the image header, from 1 to 8.
+    pub(crate) orientation: u32,
+}
+
+impl ImageInfo {
+    #[inline]
+    pub(crate) fn num_extra(&self) -> usize {
+        self.has_alpha as usize
+    }
+}
+
+/// Writes signature, `SizeHeader` and `ImageMetadata`, followed by zero padding.
+pub(crate) fn write_image_header(writer: &mut BitWriter, info: &ImageInfo) {
+    writer.write_bits(16, 0x0aff);
+
+    // SizeHeader
+    writer.write_bool(false); // div8
+    writer.write_u32(info.height, SIZE_DIST);
+    writer.write_bits(3, 0); // ratio
+    writer.write_u32(info.width, SIZE_DIST);
+
+    // ImageMetadata
+    let extra_fields = info.orientation != 1;
+    writer.write_bool(false); // all_default
+    writer.write_bool(extra_fields);
+    if extra_fields {
+        writer.write_bits(3, info.orientation - 1);
+        writer.write_bool(false); // have_intr_size
+        writer.write_bool(false); // have_preview
+        writer.write_bool(false); // have_animation
+    }
+    write_bit_depth(writer, info.bits_per_sample);
+    writer.write_bool(info.bits_per_sample <= 12); // modular_16bit_buffers
+    writer.write_u32(info.num_extra() as u32, [(0, 0), (1, 0), (2, 4), (1, 12)]);
+    if info.has_alpha {
+        if info.bits_per_sample == 8 {
+            writer.write_bool(true); // d_alpha
+        } else {
+            writer.write_bool(false);
+            writer.write_enum(0); // ExtraChannelType::Alpha
+            write_bit_depth(writer, info.bits_per_sample);
+            writer.write_u32(0, [(0, 0), (3, 0), (4, 0), (1, 3)]); // dim_shift
+            writer.write_u32(0, NAME_LEN_DIST); // name
+            writer.write_bool(false); // alpha_associated
+        }
+    }
+    writer.write_bool(false); // xyb_encoded
+
+    // ColourEncoding
+    if info.grayscale {
+        writer.write_bool(false); // all_default
+        writer.write_bool(false); // want_icc
+        writer.write_enum(1); // ColourSpace::Grey
+        writer.write_enum(1); // WhitePoint::D65
+        writer.write_bool(false); // has_gamma
+        writer.write_enum(13); // TransferFunction::Srgb
+        writer.write_enum(1); // RenderingIntent::Relative
+    } else {
+        // sRGB
+        writer.write_bool(true);
+    }
+
+    if extra_fields {
+        writer.write_bool(true); // ToneMapping.all_default
+    }
+    writer.write_u64(0); // extensions
+    writer.write_bool(true); // default_m
+    writer.zero_pad_to_byte();
+}
+
+fn write_bit_depth(writer: &mut BitWriter, bits_per_sample: u32) {
+    writer.write_bool(false); // float_sample
+    writer.write_u32(bits_per_sample, BIT_DEPTH_DIST);
+}
+
+/// Encoding of a frame, with encoding specific parameters.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum FrameEncoding {
+    Modular {
+        group_size_shift: u32,
+    },
+    /// VarDCT frame of YCbCr samples, with chroma subsampling mode of each channel in
+    /// Cb, Y, Cr order.
+    VarDctYcbcr {
+        jpeg_upsampling: [u32; 3],
+    },
+}
+
+/// Optional features of a frame, signalled in the frame header and `LfGlobal`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FrameOptions {
+    /// Noise LUT, quantized to 10 bits.
+    pub(crate) noise: Option<[u32; 8]>,
+    /// Whether Gabor-like filter is enabled, with default weights.
+    pub(crate) gabor: bool,
+    /// Number of edge-preserving filter iterations, with default parameters.
+    pub(crate) epf_iters: u32,
+    /// Patches copied from the reference frame saved in [`patch::PATCH_REF_SLOT`].
+    pub(crate) patches: Vec<PatchRef>,
+    /// Reference slot to save the frame in, if the frame is a reference-only frame which is not
+    /// displayed.
+    pub(crate) reference_only: Option<u32>,
+}
+
+impl FrameOptions {
+    fn flags(&self) -> u64 {
+        let mut flags = 0;
+        if self.noise.is_some() {
+            flags |= FLAG_NOISE;
+        }
+        if !self.patches.is_empty() {
+            flags |= FLAG_PATCHES;
+        }
+        flags
+    }
+
+    /// Writes the features in `LfGlobal`, which precede `LfChannelDequantization`.
+    pub(crate) fn write_lf_global(&self, writer: &mut BitWriter, info: &ImageInfo) {
+        if !self.patches.is_empty() {
+            patch::write_patches(writer, &self.patches, info.num_extra());
+        }
+        if let Some(lut) = self.noise {
+            for value in lut {
+                writer.write_bits(10, value);
+            }
+        }
+    }
+}
+
+const FLAG_NOISE: u64 = 0x1;
+const FLAG_PATCHES: u64 = 0x2;
+const FLAG_SKIP_ADAPTIVE_LF_SMOOTHING: u64 = 0x80;
+
+/// Writes the header of a frame, which is either the last, full-canvas frame of the image or a
+/// reference-only frame of the size in `info`.
+pub(crate) fn write_frame_header(
+    writer: &mut BitWriter,
+    info: &ImageInfo,
+    encoding: FrameEncoding,
+    options: &FrameOptions,
+) {
+    let reference_only = options.reference_only.is_some();
+    writer.write_bool(false); // all_default
+                              // FrameType::ReferenceOnly or FrameType::RegularFrame
+    writer.write_bits(2, if reference_only { 2 } else { 0 });
+    match encoding {
+        FrameEncoding::Modular { .. } => {
+            writer.write_bits(1, 1); // Encoding::Modular
+            writer.write_u64(options.flags());
+            writer.write_bool(false); // do_ycbcr
+        }
+        FrameEncoding::VarDctYcbcr { jpeg_upsampling } => {
+            writer.write_bits(1, 0); // Encoding::VarDct
+            writer.write_u64(options.flags() | FLAG_SKIP_ADAPTIVE_LF_SMOOTHING);
+            writer.write_bool(true); // do_ycbcr
+            for upsampling in jpeg_upsampling {
+                writer.write_bits(2, upsampling);
+            }
+        }
+    }
+    writer.write_u32(1, [(1, 0), (2, 0), (4, 0), (8, 0)]); // upsampling
+    for _ in 0..info.num_extra() {
+        writer.write_u32(1, [(1, 0), (2, 0), (4, 0), (8, 0)]); // ec_upsampling
+    }
+    if let FrameEncoding::Modular { group_size_shift } = encoding {
+        writer.write_bits(2, group_size_shift);
+    }
+    if let Some(slot) = options.reference_only {
+        // Reference-only frames don't have passes and blending info, and are never the last
+        // frame.
+        writer.write_bool(true); // have_crop
+        writer.write_u32(info.width, CROP_DIST);
+        writer.write_u32(info.height, CROP_DIST);
+        writer.write_bits(2, slot); // save_as_reference
+        writer.write_bool(true); // save_before_ct
+    } else {
+        writer.write_u32(1, [(1, 0), (2, 0), (3, 0), (4, 3)]); // num_passes
+        writer.write_bool(false); // have_crop
+
+        // BlendingInfo, BlendMode::Replace. Other fields are implied since the frame covers the
+        // whole canvas.
+        writer.write_u32(0, [(0, 0), (1, 0), (2, 0), (3, 2)]);
+        for _ in 0..info.num_extra() {
+            writer.write_u32(0, [(0, 0), (1, 0), (2, 0), (3, 2)]);
+        }
+
+        writer.write_bool(true); // is_last
+    }
+    writer.write_u32(0, NAME_LEN_DIST); // name
+
+    // RestorationFilter
+    writer.write_bool(false); // all_default
+    writer.write_bool(options.gabor);
+    if options.gabor {
+        writer.write_bool(false); // gab_custom
+    }
+    writer.write_bits(2, options.epf_iters);
+    if options.epf_iters > 0 {
+        let is_modular = matches!(encoding, FrameEncoding::Modular { .. });
+        if !is_modular {
+            writer.write_bool(false); // epf_sharp_custom
+        }
+        writer.write_bool(false); // epf_weight_custom
+        writer.write_bool(false); // epf_sigma_custom
+        if is_modular {
+            writer.write_f16(1.0); // epf_sigma_for_modular
+        }
+    }
+    writer.write_u64(0); // extensions
+
+    writer.write_u64(0); // extensions
+}
+
+/// Writes TOC without permutation.
+pub(crate) fn write_toc(writer: &mut BitWriter, section_sizes: &[u32]) {
+    writer.write_bool(false); // permuted
+    writer.zero_pad_to_byte();
+    for &size in section_sizes {
+        writer.write_u32(size, TOC_ENTRY_DIST);
+    }
+    writer.zero_pad_to_byte();
+}
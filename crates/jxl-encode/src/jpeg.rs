@@ -0,0 +1,43 @@
+//! Lossless JPEG transcoding.
+//!
+//! Quantized coefficients of the JPEG image are stored in a VarDCT frame, and the remaining
+//! information needed to reconstruct the original bitstream is stored in a `jbrd` box, which is
+//! read by jxl-jbr.
+mod jbrd;
+mod parse;
+mod vardct;
+
+use crate::bit_writer::BitWriter;
+use crate::container;
+use crate::header::{self, ImageInfo};
+use crate::Result;
+
+/// Transcodes the JPEG bitstream into a JPEG XL container.
+pub(crate) fn transcode(jpeg: &[u8]) -> Result<Vec<u8>> {
+    let jpeg = parse::parse(jpeg)?;
+    let info = ImageInfo {
+        width: jpeg.width,
+        height: jpeg.height,
+        grayscale: jpeg.is_gray(),
+        has_alpha: false,
+        bits_per_sample: 8,
+        orientation: 1,
+    };
+    tracing::debug!(
+        ?info,
+        num_markers = jpeg.markers.len(),
+        restart_interval = jpeg.restart_interval,
+        "Transcoding JPEG"
+    );
+
+    let mut writer = BitWriter::new();
+    header::write_image_header(&mut writer, &info);
+    vardct::write_frame(&mut writer, &info, &jpeg);
+    let codestream = writer.finish();
+    let jbrd = jbrd::write_jbrd(&jpeg);
+
+    Ok(container::write_container(&[
+        (*b"jbrd", &jbrd),
+        (*b"jxlc", &codestream),
+    ]))
+}
@@ -0,0 +1,161 @@
+//! `jbrd` box writer.
+use super::parse::JpegData;
+use crate::bit_writer::{BitWriter, U32Dist};
+
+const BLOCK_IDX_DIST: [U32Dist; 4] = [(0, 0), (1, 3), (9, 5), (41, 28)];
+const NUM_POINTS_DIST: [U32Dist; 4] = [(0, 0), (1, 2), (4, 4), (20, 16)];
+
+/// Maximum length of a metablock with uncompressed data in Brotli stream.
+const MAX_BROTLI_METABLOCK: usize = 1 << 24;
+
+/// Writes the contents of `jbrd` box describing reconstruction of `jpeg`.
+pub(crate) fn write_jbrd(jpeg: &JpegData) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bool(jpeg.is_gray());
+
+    for &marker in &jpeg.markers {
+        writer.write_bits(6, (marker - 0xc0) as u32);
+    }
+    for &length in &jpeg.app_lengths {
+        writer.write_u32(0, [(0, 0), (1, 0), (2, 1), (4, 2)]); // type, stored as is
+        writer.write_bits(16, length - 1);
+    }
+    for &length in &jpeg.com_lengths {
+        writer.write_bits(16, length - 1);
+    }
+
+    writer.write_bits(2, jpeg.quant_tables.len() as u32 - 1);
+    for table in &jpeg.quant_tables {
+        writer.write_bits(1, table.precision as u32);
+        writer.write_bits(2, table.index as u32);
+        writer.write_bool(table.is_last);
+    }
+
+    let ids = jpeg.components.iter().map(|c| c.id).collect::<Vec<_>>();
+    match &*ids {
+        [1] => writer.write_bits(2, 0),
+        [1, 2, 3] => writer.write_bits(2, 1),
+        _ => {
+            writer.write_bits(2, 3);
+            writer.write_bits(2, ids.len() as u32 - 1);
+            for &id in &ids {
+                writer.write_bits(8, id as u32);
+            }
+        }
+    }
+    for comp in &jpeg.components {
+        writer.write_bits(2, comp.q_idx as u32);
+    }
+
+    writer.write_u32(
+        jpeg.huffman_tables.len() as u32,
+        [(4, 0), (2, 3), (10, 4), (26, 6)],
+    );
+    for table in &jpeg.huffman_tables {
+        writer.write_bool(table.is_ac);
+        writer.write_bits(2, table.id as u32);
+        writer.write_bool(table.is_last);
+
+        // A sentinel symbol is added to the longest code length.
+        let mut counts = [0u32; 17];
+        for (count, &c) in counts[1..].iter_mut().zip(&table.counts) {
+            *count = c as u32;
+        }
+        *counts.iter_mut().rev().find(|c| **c != 0).unwrap() += 1;
+        for count in counts {
+            writer.write_u32(count, [(0, 0), (1, 0), (2, 3), (0, 8)]);
+        }
+        for value in table.values.iter().map(|&v| v as u32).chain([256]) {
+            writer.write_u32(value, [(0, 2), (4, 2), (8, 4), (1, 8)]);
+        }
+    }
+
+    // ScanInfo
+    writer.write_bits(2, jpeg.scan_components.len() as u32 - 1);
+    writer.write_bits(6, 0); // Ss
+    writer.write_bits(6, 63); // Se
+    writer.write_bits(4, 0); // Al
+    writer.write_bits(4, 0); // Ah
+    for comp in &jpeg.scan_components {
+        writer.write_bits(2, comp.comp_idx as u32);
+        writer.write_bits(2, comp.ac_tbl as u32);
+        writer.write_bits(2, comp.dc_tbl as u32);
+    }
+    writer.write_u32(0, [(0, 0), (1, 0), (2, 0), (3, 3)]); // last_needed_pass
+
+    if jpeg.markers.contains(&0xdd) {
+        writer.write_bits(16, jpeg.restart_interval.unwrap_or(0) as u32);
+    }
+
+    // ScanMoreInfo
+    writer.write_u32(0, NUM_POINTS_DIST); // reset_points
+    writer.write_u32(jpeg.extra_zero_runs.len() as u32, NUM_POINTS_DIST);
+    let mut last_block_idx = None;
+    for &(block_idx, num_runs) in &jpeg.extra_zero_runs {
+        writer.write_u32(num_runs, [(1, 0), (2, 2), (5, 4), (20, 8)]);
+        let diff = match last_block_idx {
+            Some(last) => block_idx - last - 1,
+            None => block_idx,
+        };
+        writer.write_u32(diff, BLOCK_IDX_DIST);
+        last_block_idx = Some(block_idx);
+    }
+
+    writer.write_u32(
+        jpeg.tail.len() as u32,
+        [(0, 0), (1, 8), (257, 16), (65793, 22)],
+    );
+
+    let has_padding = !jpeg.is_padding_all_ones();
+    writer.write_bool(has_padding);
+    if has_padding {
+        let num_bits = jpeg.padding_bits.iter().map(|&(n, _)| n).sum::<usize>();
+        writer.write_bits(24, num_bits as u32);
+        for &(num_bits, bits) in &jpeg.padding_bits {
+            writer.write_bits(num_bits, bits);
+        }
+    }
+    writer.zero_pad_to_byte();
+
+    let data = [&jpeg.app_data[..], &jpeg.com_data, &jpeg.tail];
+    write_stored_brotli(&mut writer, &data.concat());
+    writer.finish()
+}
+
+/// Writes Brotli stream which stores data without compression.
+fn write_stored_brotli(writer: &mut BitWriter, data: &[u8]) {
+    writer.write_bits(1, 0); // WBITS = 16
+    for chunk in data.chunks(MAX_BROTLI_METABLOCK) {
+        let mlen = chunk.len() as u32 - 1;
+        let num_nibbles = (32 - mlen.leading_zeros()).div_ceil(4).max(4);
+        writer.write_bool(false); // ISLAST
+        writer.write_bits(2, num_nibbles - 4);
+        writer.write_bits(num_nibbles as usize * 4, mlen);
+        writer.write_bool(true); // ISUNCOMPRESSED
+        writer.zero_pad_to_byte();
+        writer.append_bytes(chunk);
+    }
+    writer.write_bool(true); // ISLAST
+    writer.write_bool(true); // ISLASTEMPTY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_brotli_metablock_size() {
+        let data = vec![0x5au8; 70000];
+        let mut writer = BitWriter::new();
+        write_stored_brotli(&mut writer, &data);
+        let out = writer.finish();
+
+        // WBITS, ISLAST, MNIBBLES = 5, then MLEN - 1 = 69999 in 20 bits and ISUNCOMPRESSED.
+        let header = u32::from_le_bytes([out[0], out[1], out[2], out[3]]);
+        assert_eq!(header & 0b1111, 0b0100);
+        assert_eq!((header >> 4) & 0xfffff, 69999);
+        assert_eq!((header >> 24) & 1, 1);
+        assert_eq!(&out[4..][..data.len()], &data[..]);
+        assert_eq!(out.len(), 4 + data.len() + 1);
+    }
+}
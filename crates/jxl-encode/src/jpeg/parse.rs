@@ -0,0 +1,715 @@
+//! JPEG bitstream parser.
+//!
+//! The parser decodes quantized coefficients of sequential, Huffman coded JPEG images, and records
+//! everything else needed to reconstruct the bitstream exactly. Bitstreams whose reconstruction
+//! can't be described in a `jbrd` box are rejected.
+use crate::{Error, Result};
+
+/// Natural (raster) order index of each coefficient in zigzag order.
+pub(crate) const JPEG_NATURAL_ORDER: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Maximum length of trailing data after EOI marker which `jbrd` box can represent.
+const MAX_TAIL_LEN: usize = 65793 + (1 << 22) - 1;
+
+/// Component of a JPEG frame, with its quantized coefficients.
+#[derive(Debug)]
+pub(crate) struct Component {
+    pub(crate) id: u8,
+    pub(crate) h_samp: u32,
+    pub(crate) v_samp: u32,
+    pub(crate) q_idx: u8,
+    /// Width of the coefficient grid, in blocks, including blocks padding the last MCU.
+    pub(crate) width_in_blocks: usize,
+    pub(crate) height_in_blocks: usize,
+    /// Quantized coefficients of each block in zigzag order, with blocks in raster order.
+    pub(crate) coeffs: Vec<[i16; 64]>,
+}
+
+/// Quantization table in a DQT marker.
+#[derive(Debug)]
+pub(crate) struct QuantTable {
+    pub(crate) precision: u8,
+    pub(crate) index: u8,
+    pub(crate) is_last: bool,
+    /// Quantization values in zigzag order.
+    pub(crate) values: [u16; 64],
+}
+
+/// Huffman table in a DHT marker.
+#[derive(Debug)]
+pub(crate) struct HuffmanTable {
+    pub(crate) is_ac: bool,
+    pub(crate) id: u8,
+    pub(crate) is_last: bool,
+    /// Number of codes of each length, from 1 to 16.
+    pub(crate) counts: [u8; 16],
+    pub(crate) values: Vec<u8>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ScanComponent {
+    pub(crate) comp_idx: u8,
+    pub(crate) dc_tbl: u8,
+    pub(crate) ac_tbl: u8,
+}
+
+/// Parsed JPEG bitstream.
+#[derive(Debug, Default)]
+pub(crate) struct JpegData {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) components: Vec<Component>,
+    /// Markers in the order of appearance, excluding SOI and RSTn.
+    pub(crate) markers: Vec<u8>,
+    /// Length of each APPn marker in `app_data`, including the marker byte and the length field.
+    pub(crate) app_lengths: Vec<u32>,
+    pub(crate) app_data: Vec<u8>,
+    /// Length of each COM marker in `com_data`, including the length field.
+    pub(crate) com_lengths: Vec<u32>,
+    pub(crate) com_data: Vec<u8>,
+    pub(crate) quant_tables: Vec<QuantTable>,
+    pub(crate) huffman_tables: Vec<HuffmanTable>,
+    pub(crate) scan_components: Vec<ScanComponent>,
+    pub(crate) restart_interval: Option<u16>,
+    /// Blocks which end with ZRL symbols instead of an EOB, as `(block_idx, num_zrl)`.
+    pub(crate) extra_zero_runs: Vec<(u32, u32)>,
+    /// Padding bits at the end of each entropy-coded segment, as `(num_bits, bits)`.
+    pub(crate) padding_bits: Vec<(usize, u32)>,
+    pub(crate) tail: Vec<u8>,
+}
+
+impl JpegData {
+    #[inline]
+    pub(crate) fn is_gray(&self) -> bool {
+        self.components.len() == 1
+    }
+
+    /// Returns the quantization table used by the component.
+    pub(crate) fn quant_table(&self, comp_idx: usize) -> &[u16; 64] {
+        let q_idx = self.components[comp_idx].q_idx;
+        let table = self
+            .quant_tables
+            .iter()
+            .find(|table| table.index == q_idx)
+            .expect("quantization table of a component should exist");
+        &table.values
+    }
+
+    /// Returns whether all padding bits are ones, which is the default of `jbrd` box.
+    pub(crate) fn is_padding_all_ones(&self) -> bool {
+        self.padding_bits
+            .iter()
+            .all(|&(num_bits, bits)| bits == (1u32 << num_bits) - 1)
+    }
+}
+
+/// Huffman decoding table, built as described in Annex F of the JPEG specification.
+#[derive(Debug, Clone)]
+struct DecodeTable {
+    max_code: [i32; 17],
+    val_offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl DecodeTable {
+    fn new(counts: &[u8; 16], values: &[u8]) -> Result<Self> {
+        let mut max_code = [-1i32; 17];
+        let mut val_offset = [0i32; 17];
+        let mut code = 0i32;
+        let mut idx = 0i32;
+        for (len, &count) in (1..=16).zip(counts) {
+            let count = count as i32;
+            val_offset[len] = idx - code;
+            code += count;
+            idx += count;
+            if code > (1 << len) {
+                return Err(Error::InvalidJpeg("oversubscribed Huffman table"));
+            }
+            if count > 0 {
+                max_code[len] = code - 1;
+            }
+            code <<= 1;
+        }
+
+        Ok(Self {
+            max_code,
+            val_offset,
+            values: values.to_vec(),
+        })
+    }
+}
+
+/// Reader of entropy-coded segment, which removes stuffed zero bytes.
+struct ScanReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    byte: u32,
+    bits_left: usize,
+}
+
+impl<'a> ScanReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            data,
+            pos,
+            byte: 0,
+            bits_left: 0,
+        }
+    }
+
+    fn read_bit(&mut self) -> Result<u32> {
+        if self.bits_left == 0 {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or(Error::InvalidJpeg("unexpected end of entropy-coded data"))?;
+            if byte == 0xff {
+                if self.data.get(self.pos + 1) != Some(&0) {
+                    return Err(Error::InvalidJpeg(
+                        "unexpected marker in entropy-coded data",
+                    ));
+                }
+                self.pos += 2;
+            } else {
+                self.pos += 1;
+            }
+            self.byte = byte as u32;
+            self.bits_left = 8;
+        }
+        self.bits_left -= 1;
+        Ok((self.byte >> self.bits_left) & 1)
+    }
+
+    fn read_bits(&mut self, n: usize) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..n {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+
+    fn decode(&mut self, table: &DecodeTable) -> Result<u8> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = (code << 1) | self.read_bit()? as i32;
+            if code <= table.max_code[len] {
+                return Ok(table.values[(code + table.val_offset[len]) as usize]);
+            }
+        }
+        Err(Error::InvalidJpeg("invalid Huffman code"))
+    }
+
+    /// Reads an additional value of `category` bits, and extends it to a signed value.
+    fn read_extended(&mut self, category: u8) -> Result<i32> {
+        if category == 0 {
+            return Ok(0);
+        }
+        let category = category as usize;
+        let bits = self.read_bits(category)? as i32;
+        Ok(if bits < 1 << (category - 1) {
+            bits - (1 << category) + 1
+        } else {
+            bits
+        })
+    }
+
+    /// Consumes the remaining bits of the last byte, returning them as `(num_bits, bits)`.
+    fn take_padding(&mut self) -> (usize, u32) {
+        let num_bits = self.bits_left;
+        let bits = self.byte & ((1 << num_bits) - 1);
+        self.bits_left = 0;
+        (num_bits, bits)
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    out: JpegData,
+    dc_tables: [Option<DecodeTable>; 4],
+    ac_tables: [Option<DecodeTable>; 4],
+    has_adobe_rgb: bool,
+}
+
+/// Parses JPEG bitstream.
+pub(crate) fn parse(jpeg: &[u8]) -> Result<JpegData> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err(Error::InvalidJpeg("missing SOI marker"));
+    }
+
+    let mut parser = Parser::default();
+    let mut pos = 2;
+    loop {
+        let marker = match jpeg.get(pos..pos + 2) {
+            Some(&[0xff, marker]) => marker,
+            Some(_) => return Err(Error::UnsupportedJpeg("data between markers")),
+            None => return Err(Error::InvalidJpeg("missing EOI marker")),
+        };
+        pos += 2;
+
+        match marker {
+            0xd9 => {
+                parser.out.markers.push(marker);
+                let tail = &jpeg[pos..];
+                if tail.len() > MAX_TAIL_LEN {
+                    return Err(Error::UnsupportedJpeg("trailing data too large"));
+                }
+                parser.out.tail = tail.to_vec();
+                break;
+            }
+            0xd0..=0xd7 => return Err(Error::InvalidJpeg("restart marker outside of scan")),
+            0x00 | 0x01 | 0xd8 | 0xff => return Err(Error::UnsupportedJpeg("unexpected marker")),
+            _ => {}
+        }
+
+        let len = jpeg
+            .get(pos..pos + 2)
+            .map(|len| u16::from_be_bytes([len[0], len[1]]) as usize)
+            .ok_or(Error::InvalidJpeg("unexpected end of data"))?;
+        if len < 2 {
+            return Err(Error::InvalidJpeg("invalid marker segment length"));
+        }
+        let segment = jpeg
+            .get(pos..pos + len)
+            .ok_or(Error::InvalidJpeg("unexpected end of data"))?;
+        let payload = &segment[2..];
+        pos += len;
+
+        match marker {
+            0xc0 | 0xc1 => parser.parse_sof(payload)?,
+            0xc4 => parser.parse_dht(payload)?,
+            0xdb => parser.parse_dqt(payload)?,
+            0xdd => parser.parse_dri(payload)?,
+            0xda => {
+                parser.parse_sos(payload)?;
+                pos = parser.decode_scan(jpeg, pos)?;
+            }
+            0xe0..=0xef => {
+                if marker == 0xee && payload.starts_with(b"Adobe") && payload.len() >= 12 {
+                    parser.has_adobe_rgb = payload[11] == 0;
+                }
+                parser.out.app_lengths.push(segment.len() as u32 + 1);
+                parser.out.app_data.push(marker);
+                parser.out.app_data.extend_from_slice(segment);
+            }
+            0xfe => {
+                parser.out.com_lengths.push(segment.len() as u32);
+                parser.out.com_data.extend_from_slice(segment);
+            }
+            0xc2 | 0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => {
+                return Err(Error::UnsupportedJpeg(
+                    "progressive, lossless or arithmetic coded JPEG",
+                ));
+            }
+            _ => return Err(Error::UnsupportedJpeg("unsupported marker")),
+        }
+        parser.out.markers.push(marker);
+    }
+
+    parser.finish()
+}
+
+impl Parser {
+    fn parse_sof(&mut self, payload: &[u8]) -> Result<()> {
+        if !self.out.components.is_empty() {
+            return Err(Error::UnsupportedJpeg("multiple frames"));
+        }
+        let [precision, h0, h1, w0, w1, num_comps, comps @ ..] = payload else {
+            return Err(Error::InvalidJpeg("SOF marker too short"));
+        };
+        if *precision != 8 {
+            return Err(Error::UnsupportedJpeg("sample precision other than 8 bits"));
+        }
+        let height = u16::from_be_bytes([*h0, *h1]) as u32;
+        let width = u16::from_be_bytes([*w0, *w1]) as u32;
+        if height == 0 {
+            return Err(Error::UnsupportedJpeg("height defined by DNL marker"));
+        }
+        if width == 0 {
+            return Err(Error::InvalidJpeg("zero image width"));
+        }
+        let num_comps = *num_comps as usize;
+        if num_comps != 1 && num_comps != 3 {
+            return Err(Error::UnsupportedJpeg(
+                "number of components other than 1 or 3",
+            ));
+        }
+        if comps.len() != num_comps * 3 {
+            return Err(Error::InvalidJpeg("SOF marker length mismatch"));
+        }
+
+        let mut components = Vec::with_capacity(num_comps);
+        for comp in comps.chunks_exact(3) {
+            let &[id, samp, q_idx] = comp else {
+                unreachable!()
+            };
+            let h_samp = (samp >> 4) as u32;
+            let v_samp = (samp & 0xf) as u32;
+            if !(1..=2).contains(&h_samp) || !(1..=2).contains(&v_samp) {
+                return Err(Error::UnsupportedJpeg("unsupported sampling factor"));
+            }
+            if q_idx >= 4 {
+                return Err(Error::InvalidJpeg("invalid quantization table index"));
+            }
+            if components.iter().any(|c: &Component| c.id == id) {
+                return Err(Error::InvalidJpeg("duplicate component ID"));
+            }
+            components.push(Component {
+                id,
+                h_samp,
+                v_samp,
+                q_idx,
+                width_in_blocks: 0,
+                height_in_blocks: 0,
+                coeffs: Vec::new(),
+            });
+        }
+
+        let ids = components.iter().map(|c| c.id).collect::<Vec<_>>();
+        if ids == b"RGB" {
+            return Err(Error::UnsupportedJpeg("RGB JPEG"));
+        }
+
+        // Allocate coefficients of every block covered by MCUs.
+        let max_h = components.iter().map(|c| c.h_samp).max().unwrap();
+        let max_v = components.iter().map(|c| c.v_samp).max().unwrap();
+        let is_gray = num_comps == 1;
+        for comp in &mut components {
+            let (w, h) = if is_gray {
+                (width.div_ceil(8), height.div_ceil(8))
+            } else {
+                (
+                    width.div_ceil(8 * max_h) * comp.h_samp,
+                    height.div_ceil(8 * max_v) * comp.v_samp,
+                )
+            };
+            comp.width_in_blocks = w as usize;
+            comp.height_in_blocks = h as usize;
+            comp.coeffs = vec![[0i16; 64]; (w * h) as usize];
+        }
+
+        self.out.width = width;
+        self.out.height = height;
+        self.out.components = components;
+        Ok(())
+    }
+
+    fn parse_dht(&mut self, mut payload: &[u8]) -> Result<()> {
+        if payload.is_empty() {
+            return Err(Error::UnsupportedJpeg("empty DHT marker"));
+        }
+        while let [tc_th, rest @ ..] = payload {
+            let is_ac = match tc_th >> 4 {
+                0 => false,
+                1 => true,
+                _ => return Err(Error::InvalidJpeg("invalid Huffman table class")),
+            };
+            let id = tc_th & 0xf;
+            if id >= 4 {
+                return Err(Error::InvalidJpeg("invalid Huffman table index"));
+            }
+
+            let (counts, rest) = rest
+                .split_first_chunk::<16>()
+                .ok_or(Error::InvalidJpeg("DHT marker too short"))?;
+            let num_values = counts.iter().map(|&c| c as usize).sum::<usize>();
+            if num_values == 0 {
+                return Err(Error::UnsupportedJpeg("empty Huffman table"));
+            }
+            // jbrd stores codes with a sentinel symbol appended to the longest code length.
+            let max_len = counts.iter().rposition(|&c| c != 0).unwrap();
+            if counts[max_len] == 255 || num_values > 255 {
+                return Err(Error::UnsupportedJpeg("too many Huffman codes"));
+            }
+            let (values, rest) = rest
+                .split_at_checked(num_values)
+                .ok_or(Error::InvalidJpeg("DHT marker too short"))?;
+
+            let table = DecodeTable::new(counts, values)?;
+            if is_ac {
+                self.ac_tables[id as usize] = Some(table);
+            } else {
+                self.dc_tables[id as usize] = Some(table);
+            }
+            self.out.huffman_tables.push(HuffmanTable {
+                is_ac,
+                id,
+                is_last: false,
+                counts: *counts,
+                values: values.to_vec(),
+            });
+            payload = rest;
+        }
+        self.out.huffman_tables.last_mut().unwrap().is_last = true;
+        Ok(())
+    }
+
+    fn parse_dqt(&mut self, mut payload: &[u8]) -> Result<()> {
+        if payload.is_empty() {
+            return Err(Error::UnsupportedJpeg("empty DQT marker"));
+        }
+        while let [pq_tq, rest @ ..] = payload {
+            let precision = pq_tq >> 4;
+            let index = pq_tq & 0xf;
+            if precision >= 2 || index >= 4 {
+                return Err(Error::InvalidJpeg("invalid quantization table"));
+            }
+
+            let mut values = [0u16; 64];
+            let rest = if precision == 0 {
+                let (data, rest) = rest
+                    .split_first_chunk::<64>()
+                    .ok_or(Error::InvalidJpeg("DQT marker too short"))?;
+                for (value, &v) in values.iter_mut().zip(data) {
+                    *value = v as u16;
+                }
+                rest
+            } else {
+                let (data, rest) = rest
+                    .split_first_chunk::<128>()
+                    .ok_or(Error::InvalidJpeg("DQT marker too short"))?;
+                for (value, v) in values.iter_mut().zip(data.chunks_exact(2)) {
+                    *value = u16::from_be_bytes([v[0], v[1]]);
+                }
+                rest
+            };
+            if values.contains(&0) {
+                return Err(Error::InvalidJpeg("zero quantization value"));
+            }
+
+            // JPEG XL stores one table per channel, so tables can't be redefined.
+            if let Some(prev) = self.out.quant_tables.iter().find(|t| t.index == index) {
+                if prev.values != values {
+                    return Err(Error::UnsupportedJpeg("redefined quantization table"));
+                }
+            }
+            if self.out.quant_tables.len() >= 4 {
+                return Err(Error::UnsupportedJpeg("too many quantization tables"));
+            }
+            self.out.quant_tables.push(QuantTable {
+                precision,
+                index,
+                is_last: false,
+                values,
+            });
+            payload = rest;
+        }
+        self.out.quant_tables.last_mut().unwrap().is_last = true;
+        Ok(())
+    }
+
+    fn parse_dri(&mut self, payload: &[u8]) -> Result<()> {
+        let &[hi, lo] = payload else {
+            return Err(Error::InvalidJpeg("DRI marker length mismatch"));
+        };
+        let interval = u16::from_be_bytes([hi, lo]);
+        match self.out.restart_interval {
+            Some(prev) if prev != interval => {
+                Err(Error::UnsupportedJpeg("multiple restart intervals"))
+            }
+            _ => {
+                self.out.restart_interval = Some(interval);
+                Ok(())
+            }
+        }
+    }
+
+    fn parse_sos(&mut self, payload: &[u8]) -> Result<()> {
+        if self.out.components.is_empty() {
+            return Err(Error::InvalidJpeg("SOS marker before SOF marker"));
+        }
+        if !self.out.scan_components.is_empty() {
+            return Err(Error::UnsupportedJpeg("multiple scans"));
+        }
+        let [num_comps, rest @ ..] = payload else {
+            return Err(Error::InvalidJpeg("SOS marker too short"));
+        };
+        let num_comps = *num_comps as usize;
+        if rest.len() != num_comps * 2 + 3 {
+            return Err(Error::InvalidJpeg("SOS marker length mismatch"));
+        }
+        if num_comps != self.out.components.len() {
+            return Err(Error::UnsupportedJpeg("scan without all components"));
+        }
+
+        let (comps, params) = rest.split_at(num_comps * 2);
+        if params != [0, 63, 0] {
+            return Err(Error::InvalidJpeg(
+                "invalid spectral selection for sequential JPEG",
+            ));
+        }
+
+        let mut scan_components = Vec::with_capacity(num_comps);
+        for comp in comps.chunks_exact(2) {
+            let comp_idx = self
+                .out
+                .components
+                .iter()
+                .position(|c| c.id == comp[0])
+                .ok_or(Error::InvalidJpeg("unknown component in scan"))?;
+            if scan_components
+                .iter()
+                .any(|c: &ScanComponent| c.comp_idx as usize == comp_idx)
+            {
+                return Err(Error::InvalidJpeg("duplicate component in scan"));
+            }
+            let dc_tbl = comp[1] >> 4;
+            let ac_tbl = comp[1] & 0xf;
+            if dc_tbl >= 4 || ac_tbl >= 4 {
+                return Err(Error::InvalidJpeg("invalid Huffman table index"));
+            }
+            if self.dc_tables[dc_tbl as usize].is_none()
+                || self.ac_tables[ac_tbl as usize].is_none()
+            {
+                return Err(Error::InvalidJpeg("undefined Huffman table"));
+            }
+            scan_components.push(ScanComponent {
+                comp_idx: comp_idx as u8,
+                dc_tbl,
+                ac_tbl,
+            });
+        }
+        self.out.scan_components = scan_components;
+        Ok(())
+    }
+
+    /// Decodes entropy-coded data starting at `pos`, and returns the position of next marker.
+    fn decode_scan(&mut self, jpeg: &[u8], pos: usize) -> Result<usize> {
+        let width = self.out.width;
+        let height = self.out.height;
+        let scan_components = self.out.scan_components.clone();
+        let is_gray = self.out.is_gray();
+        let max_h = self.out.components.iter().map(|c| c.h_samp).max().unwrap();
+        let max_v = self.out.components.iter().map(|c| c.v_samp).max().unwrap();
+        let (mcus_x, mcus_y) = if is_gray {
+            (width.div_ceil(8), height.div_ceil(8))
+        } else {
+            (width.div_ceil(8 * max_h), height.div_ceil(8 * max_v))
+        };
+        let restart_interval = self.out.restart_interval.unwrap_or(0) as u32;
+
+        let mut reader = ScanReader::new(jpeg, pos);
+        let mut dc_pred = vec![0i32; scan_components.len()];
+        let mut rst_m = 0u8;
+        let mut block_idx = 0u32;
+        for mcu_y in 0..mcus_y {
+            for mcu_x in 0..mcus_x {
+                let mcu_idx = mcu_x + mcus_x * mcu_y;
+                if restart_interval != 0 && mcu_idx != 0 && mcu_idx % restart_interval == 0 {
+                    self.out.padding_bits.push(reader.take_padding());
+                    if jpeg.get(reader.pos..reader.pos + 2) != Some(&[0xff, 0xd0 + rst_m]) {
+                        return Err(Error::UnsupportedJpeg(
+                            "missing or misplaced restart marker",
+                        ));
+                    }
+                    reader.pos += 2;
+                    rst_m = (rst_m + 1) % 8;
+                    dc_pred.fill(0);
+                }
+
+                for (scan_comp, dc_pred) in scan_components.iter().zip(&mut dc_pred) {
+                    let dc_table = self.dc_tables[scan_comp.dc_tbl as usize].as_ref().unwrap();
+                    let ac_table = self.ac_tables[scan_comp.ac_tbl as usize].as_ref().unwrap();
+                    let comp = &mut self.out.components[scan_comp.comp_idx as usize];
+                    let (h_samp, v_samp) = if is_gray {
+                        (1, 1)
+                    } else {
+                        (comp.h_samp, comp.v_samp)
+                    };
+
+                    for dy in 0..v_samp {
+                        for dx in 0..h_samp {
+                            let x = (mcu_x * h_samp + dx) as usize;
+                            let y = (mcu_y * v_samp + dy) as usize;
+                            let block = &mut comp.coeffs[y * comp.width_in_blocks + x];
+                            let num_zrl =
+                                decode_block(&mut reader, dc_table, ac_table, dc_pred, block)?;
+                            if num_zrl > 0 {
+                                self.out.extra_zero_runs.push((block_idx, num_zrl));
+                            }
+                            block_idx += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.out.padding_bits.push(reader.take_padding());
+        Ok(reader.pos)
+    }
+
+    fn finish(mut self) -> Result<JpegData> {
+        let out = &mut self.out;
+        if out.scan_components.is_empty() {
+            return Err(Error::InvalidJpeg("missing scan"));
+        }
+        if self.has_adobe_rgb && !out.is_gray() {
+            return Err(Error::UnsupportedJpeg("RGB JPEG"));
+        }
+        for comp in &out.components {
+            if !out.quant_tables.iter().any(|t| t.index == comp.q_idx) {
+                return Err(Error::InvalidJpeg("undefined quantization table"));
+            }
+        }
+        for table in &out.quant_tables {
+            if !out.components.iter().any(|c| c.q_idx == table.index) {
+                return Err(Error::UnsupportedJpeg("unused quantization table"));
+            }
+        }
+        out.padding_bits.retain(|&(num_bits, _)| num_bits > 0);
+        Ok(self.out)
+    }
+}
+
+/// Decodes a block, and returns the number of trailing ZRL symbols not followed by EOB.
+fn decode_block(
+    reader: &mut ScanReader,
+    dc_table: &DecodeTable,
+    ac_table: &DecodeTable,
+    dc_pred: &mut i32,
+    block: &mut [i16; 64],
+) -> Result<u32> {
+    let category = reader.decode(dc_table)?;
+    if category > 15 {
+        return Err(Error::InvalidJpeg("invalid DC coefficient category"));
+    }
+    let dc = *dc_pred + reader.read_extended(category)?;
+    if !(-2047..=2047).contains(&dc) {
+        return Err(Error::UnsupportedJpeg("DC coefficient out of range"));
+    }
+    *dc_pred = dc;
+    block[0] = dc as i16;
+
+    let mut k = 1usize;
+    let mut num_zrl = 0u32;
+    while k < 64 {
+        let symbol = reader.decode(ac_table)?;
+        let run = (symbol >> 4) as usize;
+        let category = symbol & 0xf;
+        if category == 0 {
+            match run {
+                0 => return Ok(num_zrl),
+                15 => {
+                    num_zrl += 1;
+                    k += 16;
+                    if k > 64 {
+                        return Err(Error::InvalidJpeg("AC coefficient index out of range"));
+                    }
+                }
+                _ => return Err(Error::UnsupportedJpeg("AC symbol with zero category")),
+            }
+        } else {
+            k += run;
+            if k >= 64 {
+                return Err(Error::InvalidJpeg("AC coefficient index out of range"));
+            }
+            block[k] = reader.read_extended(category)? as i16;
+            k += 1;
+            num_zrl = 0;
+        }
+    }
+    Ok(num_zrl)
+}
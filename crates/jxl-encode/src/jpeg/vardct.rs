@@ -0,0 +1,509 @@
+//! VarDCT frame writer for JPEG transcoding.
+//!
+//! Every varblock is a DCT8 block holding quantized JPEG coefficients as is. Quantization tables
+//! are stored as raw DCT8 dequantization matrices with the denominator of 1/2040, so that the
+//! decoder can recognize the frame as JPEG-compatible.
+use super::parse::{JpegData, JPEG_NATURAL_ORDER};
+use crate::bit_writer::BitWriter;
+use crate::entropy::{EntropyEncoder, Token};
+use crate::header::{write_frame_header, write_toc, FrameEncoding, FrameOptions, ImageInfo};
+use crate::modular::{
+    pack_signed, tokenize_channels, write_ma_tree, write_modular_header, Channel,
+};
+use crate::Predictor;
+
+const GROUP_DIM_BLOCKS: usize = 32;
+const LF_GROUP_DIM_BLOCKS: usize = GROUP_DIM_BLOCKS * 8;
+
+/// Block context of DCT8 varblocks of each channel in Y, X, B order, from the default block
+/// context map.
+const BLOCK_CTX: [u32; 3] = [0, 7, 7];
+const NUM_BLOCK_CLUSTERS: u32 = 15;
+const NUM_HF_CONTEXTS: u32 = 495 * NUM_BLOCK_CLUSTERS;
+/// Offset of coefficient contexts, placed after non-zero count contexts.
+const COEFF_CTX_OFFSET: u32 = 37 * NUM_BLOCK_CLUSTERS;
+
+const COEFF_FREQ_CONTEXT: [u32; 63] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20,
+    20, 21, 21, 22, 22, 23, 23, 23, 23, 24, 24, 24, 24, 25, 25, 25, 25, 26, 26, 26, 26, 27, 27, 27,
+    27, 28, 28, 28, 28, 29, 29, 29, 29, 30, 30, 30, 30,
+];
+const COEFF_NUM_NONZERO_CONTEXT: [u32; 63] = [
+    0, 31, 62, 62, 93, 93, 93, 93, 123, 123, 123, 123, 152, 152, 152, 152, 152, 152, 152, 152, 180,
+    180, 180, 180, 180, 180, 180, 180, 180, 180, 180, 180, 206, 206, 206, 206, 206, 206, 206, 206,
+    206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206, 206,
+    206, 206, 206, 206,
+];
+
+/// Chroma subsampling of a channel, mirroring `ChannelShift::JpegUpsampling` of jxl-modular.
+#[derive(Debug, Copy, Clone)]
+struct ChannelShift {
+    has_h_subsample: bool,
+    h_subsample: bool,
+    has_v_subsample: bool,
+    v_subsample: bool,
+}
+
+impl ChannelShift {
+    fn from_jpeg_upsampling(jpeg_upsampling: [u32; 3], idx: usize) -> Self {
+        let hscale = jpeg_upsampling.into_iter().any(|v| v == 1 || v == 2);
+        let vscale = jpeg_upsampling.into_iter().any(|v| v == 1 || v == 3);
+        let (h, v) = match jpeg_upsampling[idx] {
+            0 => (hscale, vscale),
+            1 => (false, false),
+            2 => (false, vscale),
+            3 => (hscale, false),
+            _ => unreachable!(),
+        };
+        Self {
+            has_h_subsample: hscale,
+            h_subsample: h,
+            has_v_subsample: vscale,
+            v_subsample: v,
+        }
+    }
+
+    #[inline]
+    fn hshift(&self) -> usize {
+        self.h_subsample as usize
+    }
+
+    #[inline]
+    fn vshift(&self) -> usize {
+        self.v_subsample as usize
+    }
+
+    fn shift_size(&self, (width, height): (usize, usize)) -> (usize, usize) {
+        let shift = |size: usize, has_subsample: bool, subsample: bool| {
+            if !has_subsample {
+                size
+            } else if subsample {
+                size.div_ceil(2)
+            } else {
+                size.div_ceil(2) * 2
+            }
+        };
+        (
+            shift(width, self.has_h_subsample, self.h_subsample),
+            shift(height, self.has_v_subsample, self.v_subsample),
+        )
+    }
+}
+
+/// Returns `jpeg_upsampling` value of the given sampling factor.
+fn jpeg_upsampling_of(h_samp: u32, v_samp: u32) -> u32 {
+    match (h_samp, v_samp) {
+        (1, 1) => 0,
+        (2, 2) => 1,
+        (2, 1) => 2,
+        (1, 2) => 3,
+        _ => unreachable!(),
+    }
+}
+
+/// Coefficients and quantization table of a channel.
+struct Plane {
+    shift: ChannelShift,
+    width: usize,
+    /// Quantized coefficients in the natural coefficient order of DCT8.
+    blocks: Vec<[i16; 64]>,
+    /// Quantization table in zigzag order.
+    quant: [u16; 64],
+}
+
+impl Plane {
+    #[inline]
+    fn block(&self, x: usize, y: usize) -> &[i16; 64] {
+        &self.blocks[y * self.width + x]
+    }
+
+    /// Returns LF (DC) coefficients of the region given in full resolution blocks.
+    fn lf_channel(&self, left: usize, top: usize, size: (usize, usize)) -> Channel {
+        let left = left >> self.shift.hshift();
+        let top = top >> self.shift.vshift();
+        let (width, height) = self.shift.shift_size(size);
+        let mut data = Vec::with_capacity(width * height);
+        for y in top..top + height {
+            for x in left..left + width {
+                data.push(self.block(x, y)[0] as i32);
+            }
+        }
+        Channel::new(width, height, data)
+    }
+}
+
+/// Rectangular region of the frame, in full resolution blocks.
+#[derive(Debug, Copy, Clone)]
+struct BlockRect {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+impl BlockRect {
+    /// Splits `(width, height)` into rectangles of `dim` blocks in raster order.
+    fn split(width: usize, height: usize, dim: usize) -> Vec<Self> {
+        let mut out = Vec::new();
+        for top in (0..height).step_by(dim) {
+            for left in (0..width).step_by(dim) {
+                out.push(Self {
+                    left,
+                    top,
+                    width: dim.min(width - left),
+                    height: dim.min(height - top),
+                });
+            }
+        }
+        out
+    }
+}
+
+/// Reorders coefficients in JPEG zigzag order to the natural order of DCT8, which visits the
+/// transposed positions of the zigzag order.
+fn transpose_block(block: &[i16; 64]) -> [i16; 64] {
+    let mut natural = [0i16; 64];
+    for (&coeff, &pos) in block.iter().zip(&JPEG_NATURAL_ORDER) {
+        natural[(pos % 8) * 8 + pos / 8] = coeff;
+    }
+    std::array::from_fn(|k| natural[JPEG_NATURAL_ORDER[k]])
+}
+
+/// Arranges coefficients of JPEG components into channels of the frame, padded to `size` blocks.
+fn build_planes(jpeg: &JpegData, jpeg_upsampling: [u32; 3], size: (usize, usize)) -> [Plane; 3] {
+    // JPEG XL channel order (X, Y, B) corresponds to (Cb, Y, Cr).
+    let comp_of_channel = if jpeg.is_gray() { [0, 0, 0] } else { [1, 0, 2] };
+    std::array::from_fn(|c| {
+        let shift = ChannelShift::from_jpeg_upsampling(jpeg_upsampling, c);
+        let (width, height) = shift.shift_size(size);
+        let comp_idx = comp_of_channel[c];
+        let mut blocks = vec![[0i16; 64]; width * height];
+        if !jpeg.is_gray() || c == 1 {
+            let comp = &jpeg.components[comp_idx];
+            debug_assert!(comp.width_in_blocks <= width && comp.height_in_blocks <= height);
+            for (y, row) in comp.coeffs.chunks_exact(comp.width_in_blocks).enumerate() {
+                for (out, block) in blocks[y * width..].iter_mut().zip(row) {
+                    *out = transpose_block(block);
+                }
+            }
+        }
+        Plane {
+            shift,
+            width,
+            blocks,
+            quant: *jpeg.quant_table(comp_idx),
+        }
+    })
+}
+
+/// Returns the initial cluster of the HF coefficient context, grouping similar contexts
+/// together.
+fn initial_hf_cluster(ctx: u32) -> u8 {
+    fn bucket(value: u32, bounds: &[u32]) -> u32 {
+        bounds.iter().filter(|&&bound| value >= bound).count() as u32
+    }
+
+    if ctx < COEFF_CTX_OFFSET {
+        let is_chroma = (!ctx.is_multiple_of(NUM_BLOCK_CLUSTERS)) as u32;
+        let idx = ctx / NUM_BLOCK_CLUSTERS;
+        let class = bucket(idx, &[1, 3, 6, 10, 16]);
+        (is_chroma * 6 + class) as u8
+    } else {
+        let ctx = ctx - COEFF_CTX_OFFSET;
+        let is_chroma = (ctx / 458 != 0) as u32;
+        let is_prev_nonzero = ctx % 2;
+        let ctx = (ctx % 458) / 2;
+        let nz_idx = bucket(ctx, &COEFF_NUM_NONZERO_CONTEXT_BASES[1..]);
+        let freq = ctx - COEFF_NUM_NONZERO_CONTEXT_BASES[nz_idx as usize];
+        let freq_class = bucket(freq, &[1, 3, 6, 11, 18]);
+        let class = ((nz_idx / 2) * 6 + freq_class) * 2 + is_prev_nonzero;
+        (12 + is_chroma * 48 + class) as u8
+    }
+}
+
+/// Distinct values of `COEFF_NUM_NONZERO_CONTEXT`.
+const COEFF_NUM_NONZERO_CONTEXT_BASES: [u32; 8] = [0, 31, 62, 93, 123, 152, 180, 206];
+
+/// Tokenizes HF coefficients of a group, as `(ctx, token)`.
+fn tokenize_hf_group(planes: &[Plane; 3], rect: BlockRect, out: &mut Vec<(u32, Token)>) {
+    let mut non_zeros_rows = [[0u32; GROUP_DIM_BLOCKS]; 3];
+    for y in 0..rect.height {
+        for x in 0..rect.width {
+            for (ci, c) in [1, 0, 2].into_iter().enumerate() {
+                let plane = &planes[c];
+                let hshift = plane.shift.hshift();
+                let vshift = plane.shift.vshift();
+                let sx = x >> hshift;
+                let sy = y >> vshift;
+                if sx << hshift != x || sy << vshift != y {
+                    continue;
+                }
+
+                let block = plane.block((rect.left >> hshift) + sx, (rect.top >> vshift) + sy);
+                let block_ctx = BLOCK_CTX[ci];
+                let non_zeros_row = &mut non_zeros_rows[c];
+                let predicted = match (sx, sy) {
+                    (0, 0) => 32,
+                    (_, 0) => non_zeros_row[sx - 1],
+                    (0, _) => non_zeros_row[sx],
+                    _ => (non_zeros_row[sx] + non_zeros_row[sx - 1] + 1) >> 1,
+                };
+                let idx = if predicted >= 8 {
+                    4 + predicted / 2
+                } else {
+                    predicted
+                };
+
+                let non_zeros = block[1..].iter().filter(|&&coeff| coeff != 0).count() as u32;
+                out.push((block_ctx + idx * NUM_BLOCK_CLUSTERS, Token::new(non_zeros)));
+                non_zeros_row[sx] = non_zeros;
+
+                let mut remaining = non_zeros;
+                let mut is_prev_nonzero = (non_zeros <= 4) as u32;
+                let coeff_ctx_base = COEFF_CTX_OFFSET + block_ctx * 458;
+                for (k, &coeff) in block.iter().enumerate().skip(1) {
+                    if remaining == 0 {
+                        break;
+                    }
+                    let ctx = coeff_ctx_base
+                        + (COEFF_NUM_NONZERO_CONTEXT[remaining as usize - 1]
+                            + COEFF_FREQ_CONTEXT[k - 1])
+                            * 2
+                        + is_prev_nonzero;
+                    out.push((ctx, Token::new(pack_signed(coeff as i32))));
+                    is_prev_nonzero = (coeff != 0) as u32;
+                    remaining -= is_prev_nonzero;
+                }
+            }
+        }
+    }
+}
+
+/// Writes a VarDCT frame containing coefficients of the JPEG image, including frame header and
+/// TOC.
+pub(crate) fn write_frame(writer: &mut BitWriter, info: &ImageInfo, jpeg: &JpegData) {
+    let jpeg_upsampling = if jpeg.is_gray() {
+        let comp = &jpeg.components[0];
+        [jpeg_upsampling_of(comp.h_samp, comp.v_samp); 3]
+    } else {
+        let [y, cb, cr] = [0, 1, 2].map(|idx| {
+            let comp = &jpeg.components[idx];
+            jpeg_upsampling_of(comp.h_samp, comp.v_samp)
+        });
+        [cb, y, cr]
+    };
+
+    // Size of the frame in blocks, padded to the MCU size if chroma is subsampled.
+    let shift = ChannelShift::from_jpeg_upsampling(jpeg_upsampling, 0);
+    let width_in_blocks = (info.width as usize).div_ceil(8);
+    let height_in_blocks = (info.height as usize).div_ceil(8);
+    let padded_width = if shift.has_h_subsample {
+        width_in_blocks.div_ceil(2) * 2
+    } else {
+        width_in_blocks
+    };
+    let padded_height = if shift.has_v_subsample {
+        height_in_blocks.div_ceil(2) * 2
+    } else {
+        height_in_blocks
+    };
+    let planes = build_planes(jpeg, jpeg_upsampling, (padded_width, padded_height));
+
+    let lf_group_rects = BlockRect::split(padded_width, padded_height, LF_GROUP_DIM_BLOCKS);
+    let group_rects = BlockRect::split(padded_width, padded_height, GROUP_DIM_BLOCKS);
+    let num_lf_groups = lf_group_rects.len();
+    let num_groups = group_rects.len();
+    let single_group = num_groups == 1;
+    tracing::debug!(
+        num_groups,
+        num_lf_groups,
+        ?jpeg_upsampling,
+        "Encoding VarDCT frame"
+    );
+
+    // Modular sub-bitstreams share the global MA tree and the entropy code.
+    let mut modular_encoder = EntropyEncoder::new(1);
+    let mut tokenize_modular = |channels: &[Channel]| {
+        let mut tokens = Vec::new();
+        tokenize_channels(channels, Predictor::Gradient, &mut tokens);
+        for &token in &tokens {
+            modular_encoder.record(0, token);
+        }
+        tokens
+    };
+
+    let lf_group_tokens = lf_group_rects
+        .iter()
+        .map(|rect| {
+            // Full resolution size of the LF group, which is not padded to the MCU size.
+            let size = (
+                rect.width.min(width_in_blocks - rect.left),
+                rect.height.min(height_in_blocks - rect.top),
+            );
+            let lf_coeff = tokenize_modular(
+                &[1, 0, 2].map(|c| planes[c].lf_channel(rect.left, rect.top, size)),
+            );
+
+            // All varblocks are DCT8 with HfMul of 1, and chroma-from-luma is disabled.
+            let num_blocks = rect.width * rect.height;
+            let cfl_size = (size.0.div_ceil(8), size.1.div_ceil(8));
+            let hf_meta = tokenize_modular(&[
+                Channel::new(cfl_size.0, cfl_size.1, vec![0; cfl_size.0 * cfl_size.1]),
+                Channel::new(cfl_size.0, cfl_size.1, vec![0; cfl_size.0 * cfl_size.1]),
+                Channel::new(num_blocks, 2, vec![0; num_blocks * 2]),
+                Channel::new(rect.width, rect.height, vec![0; num_blocks]),
+            ]);
+            (num_blocks, lf_coeff, hf_meta)
+        })
+        .collect::<Vec<_>>();
+
+    // Quantization tables are transposed, as the decoder does with the coefficients.
+    let quant_tokens = tokenize_modular(&planes.each_ref().map(|plane| {
+        let mut data = vec![0i32; 64];
+        for (&q, &pos) in plane.quant.iter().zip(&JPEG_NATURAL_ORDER) {
+            data[(pos % 8) * 8 + pos / 8] = q as i32;
+        }
+        Channel::new(8, 8, data)
+    }));
+
+    let context_map = (0..NUM_HF_CONTEXTS).map(initial_hf_cluster).collect();
+    let mut hf_encoder = EntropyEncoder::with_context_map(context_map);
+    let group_tokens = group_rects
+        .iter()
+        .map(|&rect| {
+            let mut tokens = Vec::new();
+            tokenize_hf_group(&planes, rect, &mut tokens);
+            for &(ctx, token) in &tokens {
+                hf_encoder.record(ctx, token);
+            }
+            tokens
+        })
+        .collect::<Vec<_>>();
+
+    let mut lf_global = BitWriter::new();
+    // LfChannelDequantization, scaled to match JPEG DC quantization.
+    lf_global.write_bool(false);
+    for plane in &planes {
+        lf_global.write_f16(plane.quant[0] as f32 * 128.0 / 2040.0);
+    }
+    // Quantizer
+    lf_global.write_u32(65536, [(1, 11), (2049, 11), (4097, 12), (8193, 16)]);
+    lf_global.write_u32(1, [(16, 0), (1, 5), (1, 8), (1, 16)]);
+    lf_global.write_bool(true); // HfBlockContext, default
+                                // LfChannelCorrelation, with zero base correlation
+    lf_global.write_bool(false);
+    lf_global.write_u32(84, [(84, 0), (256, 0), (2, 8), (258, 16)]);
+    lf_global.write_f16(0.0);
+    lf_global.write_f16(0.0);
+    lf_global.write_bits(8, 128);
+    lf_global.write_bits(8, 128);
+    // GlobalModular, with no channels
+    lf_global.write_bool(true);
+    write_ma_tree(&mut lf_global, Predictor::Gradient);
+    modular_encoder.write_header(&mut lf_global);
+
+    let mut sections = vec![lf_global];
+    for (num_blocks, lf_coeff, hf_meta) in lf_group_tokens {
+        let mut lf_group = BitWriter::new();
+        lf_group.write_bits(2, 0); // extra_precision
+        write_modular_header(&mut lf_group, false);
+        for token in lf_coeff {
+            modular_encoder.write_token(&mut lf_group, 0, token);
+        }
+        let nb_blocks_bits = num_blocks.next_power_of_two().trailing_zeros();
+        lf_group.write_bits(nb_blocks_bits as usize, num_blocks as u32 - 1);
+        write_modular_header(&mut lf_group, false);
+        for token in hf_meta {
+            modular_encoder.write_token(&mut lf_group, 0, token);
+        }
+        sections.push(lf_group);
+    }
+
+    let mut hf_global = BitWriter::new();
+    // DequantMatrixSet, with raw DCT8 matrix and defaults for the others
+    hf_global.write_bool(false);
+    hf_global.write_bits(3, 7);
+    hf_global.write_f16(1.0 / 2040.0);
+    write_modular_header(&mut hf_global, false);
+    for token in quant_tokens {
+        modular_encoder.write_token(&mut hf_global, 0, token);
+    }
+    for _ in 1..17 {
+        hf_global.write_bits(3, 0);
+    }
+    let num_hf_presets_bits = num_groups.next_power_of_two().trailing_zeros();
+    hf_global.write_bits(num_hf_presets_bits as usize, 0);
+    // HfPass, with natural coefficient orders
+    hf_global.write_u32(0, [(0x5f, 0), (0x13, 0), (0, 0), (0, 13)]);
+    hf_encoder.write_header(&mut hf_global);
+    sections.push(hf_global);
+
+    for tokens in group_tokens {
+        let mut pass_group = BitWriter::new();
+        for (ctx, token) in tokens {
+            hf_encoder.write_token(&mut pass_group, ctx, token);
+        }
+        sections.push(pass_group);
+    }
+
+    let sections = if single_group {
+        // Every section is in a single TOC entry, without padding between them.
+        let mut merged = BitWriter::new();
+        for section in &sections {
+            merged.append_writer(section);
+        }
+        vec![merged.finish()]
+    } else {
+        sections.into_iter().map(BitWriter::finish).collect()
+    };
+
+    write_frame_header(
+        writer,
+        info,
+        FrameEncoding::VarDctYcbcr { jpeg_upsampling },
+        &FrameOptions::default(),
+    );
+    let section_sizes = sections
+        .iter()
+        .map(|section| section.len() as u32)
+        .collect::<Vec<_>>();
+    write_toc(writer, &section_sizes);
+    for section in sections {
+        writer.append_bytes(&section);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_clusters_fit() {
+        let clusters = (0..NUM_HF_CONTEXTS)
+            .map(initial_hf_cluster)
+            .collect::<Vec<_>>();
+        assert_eq!(clusters.iter().copied().max(), Some(12 + 48 * 2 - 1));
+        // Luma and chroma contexts of the same kind are in different clusters.
+        assert_ne!(initial_hf_cluster(0), initial_hf_cluster(7));
+        assert_ne!(
+            initial_hf_cluster(COEFF_CTX_OFFSET),
+            initial_hf_cluster(COEFF_CTX_OFFSET + 7 * 458)
+        );
+    }
+
+    #[test]
+    fn channel_shift_size() {
+        // 4:2:0; Y is not subsampled, Cb and Cr are.
+        let jpeg_upsampling = [0, 1, 0];
+        let y = ChannelShift::from_jpeg_upsampling(jpeg_upsampling, 1);
+        let cb = ChannelShift::from_jpeg_upsampling(jpeg_upsampling, 0);
+        assert_eq!((y.hshift(), y.vshift()), (0, 0));
+        assert_eq!((cb.hshift(), cb.vshift()), (1, 1));
+        assert_eq!(y.shift_size((3, 5)), (4, 6));
+        assert_eq!(cb.shift_size((3, 5)), (2, 3));
+
+        // 4:4:4
+        let shift = ChannelShift::from_jpeg_upsampling([0, 0, 0], 1);
+        assert_eq!(shift.shift_size((3, 5)), (3, 5));
+    }
+}
@@ -0,0 +1,479 @@
+//! This crate provides a simple lossless JPEG XL encoder, used by jxl-oxide.
+//!
+//! [`JxlEncoder`] encodes 8-bit or 16-bit grayscale and RGB images, optionally with alpha, into a
+//! bare JPEG XL codestream. Images are encoded as a single Modular frame with a gradient predictor
+//! (configurable with [`JxlEncoder::predictor`]) and prefix codes, so decoding the result yields
+//! exactly the same samples as the input. Repeated rectangles can be encoded as patches with
+//! [`JxlEncoder::patch`], which adds a reference-only frame before the image.
+//!
+//! [`transcode_jpeg`] losslessly transcodes a JPEG image into a JPEG XL container, from which
+//! the original JPEG bitstream can be reconstructed bit-exactly.
+//!
+//! # Example
+//! ```
+//! use jxl_encode::{ImageRef, JxlEncoder};
+//!
+//! let pixels = vec![0u8; 4 * 4 * 3];
+//! let image = ImageRef::from_u8(4, 4, 3, &pixels);
+//! let codestream = JxlEncoder::new().encode(image)?;
+//! assert_eq!(&codestream[..2], &[0xff, 0x0a]);
+//! # Ok::<_, jxl_encode::Error>(())
+//! ```
+mod bit_writer;
+mod container;
+mod entropy;
+mod error;
+mod header;
+mod jpeg;
+mod modular;
+mod patch;
+
+pub use error::{Error, Result};
+
+use bit_writer::BitWriter;
+use header::{FrameOptions, ImageInfo};
+use modular::Channel;
+
+/// Maximum width or height of an image that can be encoded.
+const MAX_DIMENSION: usize = 1 << 30;
+
+/// Borrowed interleaved image to be encoded.
+///
+/// Number of channels determines how samples are interpreted:
+/// - 1: grayscale
+/// - 2: grayscale and alpha
+/// - 3: RGB
+/// - 4: RGB and alpha
+///
+/// Color samples are assumed to be in sRGB color space.
+#[derive(Debug, Copy, Clone)]
+pub struct ImageRef<'a> {
+    width: usize,
+    height: usize,
+    channels: usize,
+    samples: Samples<'a>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Samples<'a> {
+    F32(&'a [f32]),
+    U8(&'a [u8]),
+    U16(&'a [u16]),
+}
+
+impl Samples<'_> {
+    /// Returns the bit depth of integer samples.
+    fn bits_per_sample(&self) -> Option<u32> {
+        match self {
+            Self::F32(_) => None,
+            Self::U8(_) => Some(8),
+            Self::U16(_) => Some(16),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::F32(buf) => buf.len(),
+            Self::U8(buf) => buf.len(),
+            Self::U16(buf) => buf.len(),
+        }
+    }
+}
+
+impl<'a> ImageRef<'a> {
+    /// Creates an image from floating point samples, in range of `[0, 1]`.
+    ///
+    /// Samples are quantized to the bit depth of the encoder; values out of range are clamped.
+    pub fn from_f32(width: usize, height: usize, channels: usize, buf: &'a [f32]) -> Self {
+        Self {
+            width,
+            height,
+            channels,
+            samples: Samples::F32(buf),
+        }
+    }
+
+    /// Creates an image from 8-bit samples.
+    pub fn from_u8(width: usize, height: usize, channels: usize, buf: &'a [u8]) -> Self {
+        Self {
+            width,
+            height,
+            channels,
+            samples: Samples::U8(buf),
+        }
+    }
+
+    /// Creates an image from 16-bit samples.
+    pub fn from_u16(width: usize, height: usize, channels: usize, buf: &'a [u16]) -> Self {
+        Self {
+            width,
+            height,
+            channels,
+            samples: Samples::U16(buf),
+        }
+    }
+
+    /// Returns the width of the image.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of channels of the image.
+    #[inline]
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.width == 0
+            || self.height == 0
+            || self.width > MAX_DIMENSION
+            || self.height > MAX_DIMENSION
+        {
+            return Err(Error::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        if !(1..=4).contains(&self.channels) {
+            return Err(Error::UnsupportedChannels(self.channels));
+        }
+
+        let expected = self
+            .width
+            .checked_mul(self.height)
+            .and_then(|x| x.checked_mul(self.channels))
+            .ok_or(Error::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            })?;
+        let actual = self.samples.len();
+        if expected != actual {
+            return Err(Error::BufferSizeMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Deinterleaves samples into Modular channels, quantizing them to `bits_per_sample`.
+    fn to_channels(self, bits_per_sample: u32) -> Vec<Channel> {
+        let max = (1u32 << bits_per_sample) - 1;
+        let num_pixels = self.width * self.height;
+        let mut planes = vec![Vec::with_capacity(num_pixels); self.channels];
+
+        match self.samples {
+            Samples::F32(buf) => {
+                let maxf = max as f32;
+                for pixel in buf.chunks_exact(self.channels) {
+                    for (plane, &v) in planes.iter_mut().zip(pixel) {
+                        plane.push((v * maxf + 0.5).clamp(0.0, maxf) as i32);
+                    }
+                }
+            }
+            Samples::U8(buf) => {
+                for pixel in buf.chunks_exact(self.channels) {
+                    for (plane, &v) in planes.iter_mut().zip(pixel) {
+                        plane.push(rescale(v as u32, 255, max));
+                    }
+                }
+            }
+            Samples::U16(buf) => {
+                for pixel in buf.chunks_exact(self.channels) {
+                    for (plane, &v) in planes.iter_mut().zip(pixel) {
+                        plane.push(rescale(v as u32, 65535, max));
+                    }
+                }
+            }
+        }
+
+        planes
+            .into_iter()
+            .map(|data| Channel::new(self.width, self.height, data))
+            .collect()
+    }
+}
+
+#[inline]
+fn rescale(value: u32, from_max: u32, to_max: u32) -> i32 {
+    if from_max == to_max {
+        value as i32
+    } else {
+        ((value as u64 * to_max as u64 + from_max as u64 / 2) / from_max as u64) as i32
+    }
+}
+
+/// Modular predictor used by the encoder.
+///
+/// Every sample of the image is predicted with the same predictor.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Predictor {
+    /// Predicts zero.
+    Zero = 0,
+    /// Predicts the sample above.
+    North = 2,
+    /// Predicts clamped gradient of the samples to the left, above, and to the top-left.
+    #[default]
+    Gradient = 5,
+}
+
+/// Patch which copies a rectangle of the image to other positions of the image.
+///
+/// Source rectangles of patches are stored in a reference-only frame, which is decoded before
+/// the image, and copied to every target while decoding the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// Left coordinate of the source rectangle.
+    pub left: u32,
+    /// Top coordinate of the source rectangle.
+    pub top: u32,
+    /// Width of the rectangle.
+    pub width: u32,
+    /// Height of the rectangle.
+    pub height: u32,
+    /// Top-left coordinates of the targets the rectangle is copied to.
+    pub targets: Vec<(u32, u32)>,
+}
+
+/// Lossless JPEG XL encoder.
+#[derive(Debug, Clone)]
+pub struct JxlEncoder {
+    bits_per_sample: Option<u32>,
+    predictor: Predictor,
+    frame_options: FrameOptions,
+    patches: Vec<Patch>,
+    orientation: u32,
+}
+
+impl Default for JxlEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JxlEncoder {
+    /// Creates a new encoder which encodes samples with the bit depth of the input.
+    ///
+    /// 8-bit and 16-bit inputs are encoded with the same bit depth, and floating point inputs
+    /// are quantized to 8 bits.
+    pub fn new() -> Self {
+        Self {
+            bits_per_sample: None,
+            predictor: Predictor::default(),
+            frame_options: FrameOptions::default(),
+            patches: Vec::new(),
+            orientation: 1,
+        }
+    }
+
+    /// Sets the bit depth of encoded samples, from 1 to 16.
+    ///
+    /// Integer input samples are rescaled to this bit depth. Encoding fails with
+    /// [`Error::BitDepthTooLow`] if it's lower than the bit depth of integer input, as samples
+    /// would lose precision.
+    pub fn bit_depth(mut self, bits_per_sample: u32) -> Self {
+        self.bits_per_sample = Some(bits_per_sample);
+        self
+    }
+
+    /// Sets the predictor of the samples, [`Predictor::Gradient`] by default.
+    ///
+    /// Gradient predictor compresses best for most images; other predictors exercise different
+    /// decoding paths.
+    pub fn predictor(mut self, predictor: Predictor) -> Self {
+        self.predictor = predictor;
+        self
+    }
+
+    /// Signals noise synthesis with the given LUT, which maps intensity to noise strength.
+    ///
+    /// Decoders add noise on top of the decoded samples, so the image no longer decodes to the
+    /// input unless noise synthesis is disabled. LUT values are quantized to multiples of 1/1024,
+    /// from 0 to 1023/1024.
+    pub fn noise(mut self, lut: [f32; 8]) -> Self {
+        let lut = lut.map(|v| (v * 1024.0).round().clamp(0.0, 1023.0) as u32);
+        self.frame_options.noise = Some(lut);
+        self
+    }
+
+    /// Sets whether Gabor-like filter is applied to decoded samples, disabled by default.
+    ///
+    /// Decoded samples are smoothed by the filter, so the image no longer decodes to the input
+    /// unless restoration filters are skipped.
+    pub fn gabor(mut self, enabled: bool) -> Self {
+        self.frame_options.gabor = enabled;
+        self
+    }
+
+    /// Sets the number of edge-preserving filter iterations applied to decoded samples, from 0
+    /// to 3. Defaults to 0, which disables the filter.
+    ///
+    /// As with [`gabor`](Self::gabor), the image no longer decodes to the input unless
+    /// restoration filters are skipped. Values larger than 3 are clamped.
+    pub fn epf_iterations(mut self, iters: u32) -> Self {
+        self.frame_options.epf_iters = iters.min(3);
+        self
+    }
+
+    /// Adds a patch, which is decoded by copying the source rectangle to its targets.
+    ///
+    /// Every target should have the same samples as the source rectangle, so that the image
+    /// decodes to the input. Encoding fails with [`Error::InvalidPatch`] if it doesn't, or if
+    /// any of the rectangles is out of bounds.
+    pub fn patch(mut self, patch: Patch) -> Self {
+        self.patches.push(patch);
+        self
+    }
+
+    /// Sets the orientation signalled in the image header, from 1 to 8 as in Exif. Defaults to 1,
+    /// which is the identity.
+    ///
+    /// Samples are encoded as given, and decoders apply the orientation when rendering. Encoding
+    /// fails with [`Error::InvalidOrientation`] if it's out of range.
+    pub fn orientation(mut self, orientation: u32) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Encodes the image into a JPEG XL codestream.
+    pub fn encode<'a>(&self, image: impl Into<ImageRef<'a>>) -> Result<Vec<u8>> {
+        let image = image.into();
+        let input_bits = image.samples.bits_per_sample();
+        let bits_per_sample = self.bits_per_sample.or(input_bits).unwrap_or(8);
+        if !(1..=16).contains(&bits_per_sample) {
+            return Err(Error::UnsupportedBitDepth(bits_per_sample));
+        }
+        if let Some(input) = input_bits.filter(|&input| input > bits_per_sample) {
+            return Err(Error::BitDepthTooLow {
+                input,
+                requested: bits_per_sample,
+            });
+        }
+        image.validate()?;
+        if !(1..=8).contains(&self.orientation) {
+            return Err(Error::InvalidOrientation(self.orientation));
+        }
+
+        let info = ImageInfo {
+            width: image.width as u32,
+            height: image.height as u32,
+            grayscale: image.channels <= 2,
+            has_alpha: image.channels % 2 == 0,
+            bits_per_sample,
+            orientation: self.orientation,
+        };
+        tracing::debug!(?info, "Encoding image");
+        let mut channels = image.to_channels(bits_per_sample);
+
+        let mut writer = BitWriter::new();
+        header::write_image_header(&mut writer, &info);
+
+        let mut frame_options = self.frame_options.clone();
+        if !self.patches.is_empty() {
+            patch::validate(&self.patches, &channels)?;
+            let (width, height, ref_channels, patches) =
+                patch::build_reference(&self.patches, &channels);
+            let ref_info = ImageInfo {
+                width: width as u32,
+                height: height as u32,
+                ..info
+            };
+            let ref_options = FrameOptions {
+                reference_only: Some(patch::PATCH_REF_SLOT),
+                ..Default::default()
+            };
+            modular::write_frame(
+                &mut writer,
+                &ref_info,
+                ref_channels,
+                self.predictor,
+                &ref_options,
+            );
+            patch::clear_targets(&self.patches, &mut channels);
+            frame_options.patches = patches;
+        }
+
+        modular::write_frame(&mut writer, &info, channels, self.predictor, &frame_options);
+        Ok(writer.finish())
+    }
+}
+
+/// Losslessly transcodes a JPEG image into a JPEG XL container.
+///
+/// The container holds a VarDCT frame with the quantized coefficients of the JPEG image, and a
+/// `jbrd` box so that the original JPEG bitstream can be reconstructed bit-exactly, e.g. with
+/// `JxlImage::reconstruct_jpeg` of jxl-oxide.
+///
+/// Only sequential, Huffman coded JPEG images with 8-bit samples are supported, with either one
+/// component or three YCbCr components coded in a single scan. APP markers, including ICC
+/// profiles and Exif metadata, are kept as is in the `jbrd` box; the image is signalled as sRGB
+/// or grayscale regardless of the embedded ICC profile.
+pub fn transcode_jpeg(jpeg: &[u8]) -> Result<Vec<u8>> {
+    jpeg::transcode(jpeg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rescale_samples() {
+        assert_eq!(rescale(255, 255, 65535), 65535);
+        assert_eq!(rescale(128, 255, 65535), 32896);
+        assert_eq!(rescale(65535, 65535, 255), 255);
+        assert_eq!(rescale(32896, 65535, 255), 128);
+        assert_eq!(rescale(200, 255, 255), 200);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let buf = [0u8; 12];
+        let encoder = JxlEncoder::new();
+        assert!(matches!(
+            encoder.encode(ImageRef::from_u8(2, 2, 2, &buf)),
+            Err(Error::BufferSizeMismatch {
+                expected: 8,
+                actual: 12
+            })
+        ));
+        assert!(matches!(
+            encoder.encode(ImageRef::from_u8(0, 2, 3, &buf)),
+            Err(Error::InvalidDimensions { .. })
+        ));
+        assert!(matches!(
+            encoder.encode(ImageRef::from_u8(1, 1, 5, &buf)),
+            Err(Error::UnsupportedChannels(5))
+        ));
+        assert!(matches!(
+            encoder
+                .clone()
+                .bit_depth(17)
+                .encode(ImageRef::from_u8(2, 2, 3, &buf)),
+            Err(Error::UnsupportedBitDepth(17))
+        ));
+    }
+
+    #[test]
+    fn bit_depth_from_input() {
+        let buf = [0x1234u16; 12];
+        let encoder = JxlEncoder::new();
+        let u16_image = ImageRef::from_u16(2, 2, 3, &buf);
+        assert!(matches!(
+            encoder.clone().bit_depth(8).encode(u16_image),
+            Err(Error::BitDepthTooLow {
+                input: 16,
+                requested: 8
+            })
+        ));
+        assert_eq!(
+            encoder.encode(u16_image).unwrap(),
+            encoder.clone().bit_depth(16).encode(u16_image).unwrap()
+        );
+    }
+}
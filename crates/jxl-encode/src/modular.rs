@@ -0,0 +1,276 @@
+//! Lossless Modular frame encoder.
+//!
+//! The encoder uses a global MA tree with a single leaf node, with gradient predictor by default.
+//! RGB images are decorrelated with YCoCg reversible color transform before prediction.
+use crate::bit_writer::{BitWriter, U32Dist};
+use crate::entropy::{EntropyEncoder, Token};
+use crate::header::{write_frame_header, write_toc, FrameEncoding, FrameOptions, ImageInfo};
+use crate::Predictor;
+
+/// `group_size_shift` of the frame, which makes groups of 256x256 pixels.
+const GROUP_SIZE_SHIFT: u32 = 1;
+const GROUP_DIM: usize = 128 << GROUP_SIZE_SHIFT;
+const LF_GROUP_DIM: usize = GROUP_DIM * 8;
+
+const RCT_YCOCG: u32 = 6;
+const NB_TRANSFORMS_DIST: [U32Dist; 4] = [(0, 0), (1, 0), (2, 4), (18, 8)];
+
+/// Single channel of a Modular image.
+#[derive(Debug)]
+pub(crate) struct Channel {
+    pub(crate) width: usize,
+    pub(crate) data: Vec<i32>,
+}
+
+impl Channel {
+    pub(crate) fn new(width: usize, height: usize, data: Vec<i32>) -> Self {
+        debug_assert_eq!(data.len(), width * height);
+        Self { width, data }
+    }
+}
+
+/// Rectangular region of the image.
+#[derive(Debug, Copy, Clone)]
+struct Rect {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Applies forward YCoCg transform to the first three channels.
+fn forward_ycocg(channels: &mut [Channel]) {
+    let [r, g, b, ..] = channels else {
+        unreachable!()
+    };
+    for ((r, g), b) in r.data.iter_mut().zip(&mut g.data).zip(&mut b.data) {
+        let co = *r - *b;
+        let tmp = *b + (co >> 1);
+        let cg = *g - tmp;
+        let y = tmp + (cg >> 1);
+        *r = y;
+        *g = co;
+        *b = cg;
+    }
+}
+
+#[inline]
+pub(crate) fn pack_signed(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Computes predictor residuals of the region, treating it as a separate image.
+fn tokenize_region(channel: &Channel, rect: Rect, predictor: Predictor, out: &mut Vec<Token>) {
+    let stride = channel.width;
+    let data = &channel.data;
+    for y in 0..rect.height {
+        let row_start = (rect.top + y) * stride + rect.left;
+        let row = &data[row_start..][..rect.width];
+        let prev_row = (y > 0).then(|| &data[row_start - stride..][..rect.width]);
+
+        for (x, &value) in row.iter().enumerate() {
+            let prediction = match (predictor, prev_row, x) {
+                (Predictor::Zero, _, _) => 0,
+                (_, None, 0) => 0,
+                (_, None, _) => row[x - 1],
+                (Predictor::North, Some(prev), _) | (_, Some(prev), 0) => prev[x],
+                (Predictor::Gradient, Some(prev), _) => {
+                    let n = prev[x] as i64;
+                    let w = row[x - 1] as i64;
+                    let nw = prev[x - 1] as i64;
+                    (n + w - nw).clamp(w.min(n), w.max(n)) as i32
+                }
+            };
+            out.push(Token::new(pack_signed(value.wrapping_sub(prediction))));
+        }
+    }
+}
+
+pub(crate) fn write_modular_header(writer: &mut BitWriter, use_rct: bool) {
+    writer.write_bool(true); // use_global_tree
+    writer.write_bool(true); // default_wp
+    if use_rct {
+        writer.write_u32(1, NB_TRANSFORMS_DIST);
+        writer.write_bits(2, 0); // TransformId::Rct
+        writer.write_u32(0, [(0, 3), (8, 6), (72, 10), (1096, 13)]); // begin_c
+        writer.write_u32(RCT_YCOCG, [(6, 0), (0, 2), (2, 4), (10, 6)]);
+    } else {
+        writer.write_u32(0, NB_TRANSFORMS_DIST);
+    }
+}
+
+/// Tokenizes channels of a Modular image which doesn't need to be split into groups.
+pub(crate) fn tokenize_channels(channels: &[Channel], predictor: Predictor, out: &mut Vec<Token>) {
+    for channel in channels {
+        let rect = Rect {
+            left: 0,
+            top: 0,
+            width: channel.width,
+            height: channel.data.len() / channel.width,
+        };
+        tokenize_region(channel, rect, predictor, out);
+    }
+}
+
+/// Writes an MA tree consisting of a single leaf with the predictor.
+pub(crate) fn write_ma_tree(writer: &mut BitWriter, predictor: Predictor) {
+    // [property + 1 (leaf), predictor, offset, mul_log, mul_bits]
+    let tokens = [0, predictor as u32, 0, 0, 0].map(Token::new);
+    let mut encoder = EntropyEncoder::new(6);
+    for token in tokens {
+        encoder.record(0, token);
+    }
+    encoder.write_header(writer);
+    for token in tokens {
+        encoder.write_token(writer, 0, token);
+    }
+}
+
+/// Writes a Modular frame, including frame header and TOC.
+pub(crate) fn write_frame(
+    writer: &mut BitWriter,
+    info: &ImageInfo,
+    mut channels: Vec<Channel>,
+    predictor: Predictor,
+    options: &FrameOptions,
+) {
+    let width = info.width as usize;
+    let height = info.height as usize;
+    let use_rct = !info.grayscale;
+    if use_rct {
+        forward_ycocg(&mut channels);
+    }
+
+    let groups_per_row = width.div_ceil(GROUP_DIM);
+    let num_groups = groups_per_row * height.div_ceil(GROUP_DIM);
+    let num_lf_groups = width.div_ceil(LF_GROUP_DIM) * height.div_ceil(LF_GROUP_DIM);
+    let single_group = num_groups == 1;
+
+    let group_rects = if single_group {
+        vec![Rect {
+            left: 0,
+            top: 0,
+            width,
+            height,
+        }]
+    } else {
+        (0..num_groups)
+            .map(|group_idx| {
+                let left = (group_idx % groups_per_row) * GROUP_DIM;
+                let top = (group_idx / groups_per_row) * GROUP_DIM;
+                Rect {
+                    left,
+                    top,
+                    width: GROUP_DIM.min(width - left),
+                    height: GROUP_DIM.min(height - top),
+                }
+            })
+            .collect()
+    };
+
+    let mut encoder = EntropyEncoder::new(1);
+    let group_tokens = group_rects
+        .into_iter()
+        .map(|rect| {
+            let mut tokens = Vec::with_capacity(rect.width * rect.height * channels.len());
+            for channel in &channels {
+                tokenize_region(channel, rect, predictor, &mut tokens);
+            }
+            for &token in &tokens {
+                encoder.record(0, token);
+            }
+            tokens
+        })
+        .collect::<Vec<_>>();
+    tracing::debug!(num_groups, single_group, "Encoding Modular frame");
+
+    let mut lf_global = BitWriter::new();
+    options.write_lf_global(&mut lf_global, info);
+    lf_global.write_bool(true); // LfChannelDequantization.all_default
+    lf_global.write_bool(true); // global MA tree
+    write_ma_tree(&mut lf_global, predictor);
+    encoder.write_header(&mut lf_global);
+    write_modular_header(&mut lf_global, use_rct);
+
+    let mut sections = Vec::new();
+    if single_group {
+        // Every channel fits in a group; all samples are in the global section.
+        for &token in &group_tokens[0] {
+            encoder.write_token(&mut lf_global, 0, token);
+        }
+        sections.push(lf_global.finish());
+    } else {
+        sections.push(lf_global.finish());
+        // LfGroups and HfGlobal are empty for lossless Modular frames without squeeze.
+        for _ in 0..num_lf_groups {
+            sections.push(Vec::new());
+        }
+        sections.push(Vec::new());
+        for tokens in group_tokens {
+            let mut pass_group = BitWriter::new();
+            write_modular_header(&mut pass_group, false);
+            for token in tokens {
+                encoder.write_token(&mut pass_group, 0, token);
+            }
+            sections.push(pass_group.finish());
+        }
+    }
+
+    write_frame_header(
+        writer,
+        info,
+        FrameEncoding::Modular {
+            group_size_shift: GROUP_SIZE_SHIFT,
+        },
+        options,
+    );
+    let section_sizes = sections
+        .iter()
+        .map(|section| section.len() as u32)
+        .collect::<Vec<_>>();
+    write_toc(writer, &section_sizes);
+    for section in sections {
+        writer.append_bytes(&section);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ycocg_matches_inverse() {
+        let mut channels = [[0, 0, 0], [255, 0, 128], [1, 254, 3], [65535, 0, 65535]]
+            .iter()
+            .map(|&[r, g, b]| {
+                [
+                    Channel::new(1, 1, vec![r]),
+                    Channel::new(1, 1, vec![g]),
+                    Channel::new(1, 1, vec![b]),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        for channels in &mut channels {
+            let original = channels.each_ref().map(|c| c.data[0]);
+            forward_ycocg(channels);
+            let [y, co, cg] = channels.each_ref().map(|c| c.data[0]);
+
+            // Inverse transform as defined in the specification.
+            let tmp = y - (cg >> 1);
+            let g = cg + tmp;
+            let b = tmp - (co >> 1);
+            let r = b + co;
+            assert_eq!([r, g, b], original);
+        }
+    }
+
+    #[test]
+    fn pack_signed_values() {
+        assert_eq!(pack_signed(0), 0);
+        assert_eq!(pack_signed(1), 2);
+        assert_eq!(pack_signed(-1), 1);
+        assert_eq!(pack_signed(-2), 3);
+        assert_eq!(pack_signed(i32::MIN), u32::MAX);
+    }
+}
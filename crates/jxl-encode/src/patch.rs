@@ -0,0 +1,154 @@
+//! Patches, which copy rectangles from a reference frame to the frame being decoded.
+//!
+//! Source rectangles are packed side by side into a reference-only frame, and each target is
+//! blended with `Replace` mode, so the decoded image has the same samples as the input. Targets
+//! are cleared in the frame itself, since the samples are overwritten anyway.
+use crate::bit_writer::BitWriter;
+use crate::entropy::{EntropyEncoder, Token};
+use crate::modular::{pack_signed, Channel};
+use crate::{Error, Patch, Result};
+
+/// Reference slot the reference frame of patches is saved in.
+pub(crate) const PATCH_REF_SLOT: u32 = 0;
+
+const PATCH_BLEND_MODE_REPLACE: u32 = 1;
+
+/// Patch with the position of the source rectangle in the reference frame.
+#[derive(Debug, Clone)]
+pub(crate) struct PatchRef {
+    ref_x0: u32,
+    width: u32,
+    height: u32,
+    targets: Vec<(u32, u32)>,
+}
+
+/// Checks that rectangles of the patches are within the image, and that every target has the
+/// same samples as its source.
+pub(crate) fn validate(patches: &[Patch], channels: &[Channel]) -> Result<()> {
+    let width = channels[0].width;
+    let height = channels[0].data.len() / width;
+    let in_bounds = |left: u32, top: u32, patch: &Patch| {
+        patch.width > 0
+            && patch.height > 0
+            && (left as usize).saturating_add(patch.width as usize) <= width
+            && (top as usize).saturating_add(patch.height as usize) <= height
+    };
+
+    for patch in patches {
+        if patch.targets.is_empty() || !in_bounds(patch.left, patch.top, patch) {
+            return Err(Error::InvalidPatch("patch source is out of bounds"));
+        }
+        for &(x, y) in &patch.targets {
+            if !in_bounds(x, y, patch) {
+                return Err(Error::InvalidPatch("patch target is out of bounds"));
+            }
+
+            let row_len = patch.width as usize;
+            let same = channels.iter().all(|channel| {
+                (0..patch.height as usize).all(|dy| {
+                    let source = (patch.top as usize + dy) * width + patch.left as usize;
+                    let target = (y as usize + dy) * width + x as usize;
+                    channel.data[source..][..row_len] == channel.data[target..][..row_len]
+                })
+            });
+            if !same {
+                return Err(Error::InvalidPatch("patch target differs from source"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Packs source rectangles of the patches side by side, and returns the channels of the
+/// reference frame with the patches pointing to it.
+pub(crate) fn build_reference(
+    patches: &[Patch],
+    channels: &[Channel],
+) -> (usize, usize, Vec<Channel>, Vec<PatchRef>) {
+    let image_width = channels[0].width;
+    let ref_width = patches.iter().map(|p| p.width as usize).sum::<usize>();
+    let ref_height = patches.iter().map(|p| p.height as usize).max().unwrap_or(0);
+
+    let mut ref_data = vec![vec![0i32; ref_width * ref_height]; channels.len()];
+    let mut patch_refs = Vec::with_capacity(patches.len());
+    let mut ref_x0 = 0usize;
+    for patch in patches {
+        let row_len = patch.width as usize;
+        for (data, channel) in ref_data.iter_mut().zip(channels) {
+            for dy in 0..patch.height as usize {
+                let source = (patch.top as usize + dy) * image_width + patch.left as usize;
+                let target = dy * ref_width + ref_x0;
+                data[target..][..row_len].copy_from_slice(&channel.data[source..][..row_len]);
+            }
+        }
+
+        patch_refs.push(PatchRef {
+            ref_x0: ref_x0 as u32,
+            width: patch.width,
+            height: patch.height,
+            targets: patch.targets.clone(),
+        });
+        ref_x0 += row_len;
+    }
+
+    let ref_channels = ref_data
+        .into_iter()
+        .map(|data| Channel::new(ref_width, ref_height, data))
+        .collect();
+    (ref_width, ref_height, ref_channels, patch_refs)
+}
+
+/// Fills targets of the patches with zeros, as they're replaced by the patches while decoding.
+pub(crate) fn clear_targets(patches: &[Patch], channels: &mut [Channel]) {
+    for channel in channels {
+        let width = channel.width;
+        for patch in patches {
+            for &(x, y) in &patch.targets {
+                for dy in 0..patch.height as usize {
+                    let start = (y as usize + dy) * width + x as usize;
+                    channel.data[start..][..patch.width as usize].fill(0);
+                }
+            }
+        }
+    }
+}
+
+/// Writes the patch dictionary of `LfGlobal`.
+pub(crate) fn write_patches(writer: &mut BitWriter, patches: &[PatchRef], num_extra: usize) {
+    // Pairs of context and value, in the order the decoder reads them.
+    let mut tokens = vec![(0, patches.len() as u32)];
+    for patch in patches {
+        tokens.extend([
+            (1, PATCH_REF_SLOT),
+            (3, patch.ref_x0),
+            (3, 0),
+            (2, patch.width - 1),
+            (2, patch.height - 1),
+            (7, patch.targets.len() as u32 - 1),
+        ]);
+
+        let mut prev = None;
+        for &(x, y) in &patch.targets {
+            match prev {
+                None => tokens.extend([(4, x), (4, y)]),
+                Some((px, py)) => tokens.extend([
+                    (6, pack_signed(x as i32 - px as i32)),
+                    (6, pack_signed(y as i32 - py as i32)),
+                ]),
+            }
+            prev = Some((x, y));
+            for _ in 0..=num_extra {
+                tokens.push((5, PATCH_BLEND_MODE_REPLACE));
+            }
+        }
+    }
+
+    let mut encoder = EntropyEncoder::new(10);
+    for &(ctx, value) in &tokens {
+        encoder.record(ctx, Token::new(value));
+    }
+    encoder.write_header(writer);
+    for (ctx, value) in tokens {
+        encoder.write_token(writer, ctx, Token::new(value));
+    }
+}
@@ -17,6 +17,7 @@ pub struct HfGlobalParams<'a, 'b> {
     hf_block_ctx: &'a HfBlockContext,
     tracker: Option<&'b AllocTracker>,
     pool: &'a JxlThreadPool,
+    force_scalar: bool,
 }
 
 impl<'a, 'b> HfGlobalParams<'a, 'b> {
@@ -37,8 +38,16 @@ impl<'a, 'b> HfGlobalParams<'a, 'b> {
             hf_block_ctx: &lf_vardct.hf_block_ctx,
             tracker,
             pool,
+            force_scalar: false,
         }
     }
+
+    /// Sets whether to use portable scalar code instead of SIMD implementations selected at
+    /// runtime.
+    pub fn force_scalar(mut self, force_scalar: bool) -> Self {
+        self.force_scalar = force_scalar;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -59,6 +68,7 @@ impl Bundle<HfGlobalParams<'_, '_>> for HfGlobal {
             hf_block_ctx,
             tracker,
             pool,
+            force_scalar,
         } = params;
         let dequant_matrix_params = DequantMatrixSetParams::new(
             metadata.bit_depth.bits_per_sample(),
@@ -66,7 +76,8 @@ impl Bundle<HfGlobalParams<'_, '_>> for HfGlobal {
             ma_config,
             tracker,
             pool,
-        );
+        )
+        .force_scalar(force_scalar);
         let dequant_matrices = DequantMatrixSet::parse(bitstream, dequant_matrix_params)?;
 
         let num_groups = frame_header.num_groups();
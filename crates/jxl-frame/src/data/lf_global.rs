@@ -1,4 +1,5 @@
 use jxl_bitstream::Bitstream;
+use jxl_coding::Lz77Tracker;
 use jxl_grid::AllocTracker;
 use jxl_image::ImageHeader;
 use jxl_modular::{
@@ -26,7 +27,10 @@ pub struct LfGlobalParams<'a, 'b> {
     pub image_header: &'a ImageHeader,
     pub frame_header: &'a FrameHeader,
     pub tracker: Option<&'b AllocTracker>,
+    pub lz77_tracker: Option<&'b Lz77Tracker>,
     pub allow_partial: bool,
+    /// Use portable scalar code instead of SIMD implementations selected at runtime.
+    pub force_scalar: bool,
 }
 
 impl<'a, 'b> LfGlobalParams<'a, 'b> {
@@ -40,7 +44,9 @@ impl<'a, 'b> LfGlobalParams<'a, 'b> {
             image_header,
             frame_header,
             tracker,
+            lz77_tracker: None,
             allow_partial,
+            force_scalar: false,
         }
     }
 }
@@ -209,7 +215,9 @@ impl<S: Sample> Bundle<LfGlobalParams<'_, '_>> for GlobalModular<S> {
             image_header,
             frame_header: header,
             tracker,
+            lz77_tracker,
             allow_partial,
+            force_scalar,
         } = params;
         let span = tracing::span!(tracing::Level::TRACE, "Decode GlobalModular");
         let _guard = span.enter();
@@ -221,6 +229,7 @@ impl<S: Sample> Bundle<LfGlobalParams<'_, '_>> for GlobalModular<S> {
         let max_global_ma_nodes = (1 << 22).min(max_global_ma_nodes) as usize;
         let ma_config_params = MaConfigParams {
             tracker: params.tracker,
+            lz77_tracker,
             node_limit: max_global_ma_nodes,
             depth_limit: 2048,
         };
@@ -282,13 +291,15 @@ impl<S: Sample> Bundle<LfGlobalParams<'_, '_>> for GlobalModular<S> {
         }
 
         let group_dim = header.group_dim();
-        let modular_params = ModularParams::with_channels(
+        let mut modular_params = ModularParams::with_channels(
             group_dim,
             image_header.metadata.bit_depth.bits_per_sample(),
             shifts,
             ma_config.as_ref(),
             tracker,
         );
+        modular_params.lz77_tracker = lz77_tracker;
+        modular_params.force_scalar = force_scalar;
         let mut modular = Modular::<S>::parse(bitstream, modular_params)?;
         if let Some(image) = modular.image_mut() {
             let mut gmodular = image.prepare_gmodular()?;
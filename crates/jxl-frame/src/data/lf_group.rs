@@ -20,6 +20,8 @@ pub struct LfGroupParams<'a, 'dest, 'tracker, S: Sample> {
     pub allow_partial: bool,
     pub tracker: Option<&'tracker AllocTracker>,
     pub pool: &'a jxl_threadpool::JxlThreadPool,
+    /// Use portable scalar code instead of SIMD implementations selected at runtime.
+    pub force_scalar: bool,
 }
 
 #[derive(Debug)]
@@ -41,6 +43,7 @@ impl<S: Sample> Bundle<LfGroupParams<'_, '_, '_, S>> for LfGroup<S> {
             allow_partial,
             tracker,
             pool,
+            force_scalar,
             ..
         } = params;
         let (lf_width, lf_height) = frame_header.lf_group_size_for(lf_group_idx);
@@ -58,6 +61,7 @@ impl<S: Sample> Bundle<LfGroupParams<'_, '_, '_, S>> for LfGroup<S> {
                 allow_partial,
                 tracker,
                 pool,
+                force_scalar,
             };
             LfCoeff::parse(bitstream, lf_coeff_params)
         })
@@ -106,6 +110,7 @@ impl<S: Sample> Bundle<LfGroupParams<'_, '_, '_, S>> for LfGroup<S> {
                     quantizer_global_scale: params.quantizer.unwrap().global_scale,
                     tracker,
                     pool,
+                    force_scalar,
                 };
                 HfMetadata::parse(bitstream, hf_meta_params)
             })
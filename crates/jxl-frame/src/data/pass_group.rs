@@ -46,6 +46,8 @@ pub fn decode_pass_group<S: Sample>(
         tracker,
         pool,
     } = params;
+    let _guard =
+        tracing::trace_span!("Decode group", kind = "PassGroup", pass_idx, group_idx).entered();
 
     if let (
         Some(PassGroupParamsVardct {
@@ -153,6 +155,8 @@ pub fn decode_pass_group_modular<S: Sample>(
         return Ok(());
     }
 
+    let _guard =
+        tracing::trace_span!("Decode group", kind = "PassGroup", pass_idx, group_idx).entered();
     let mut modular = modular.recursive(bitstream, global_ma_config, tracker)?;
     let mut subimage = modular.prepare_subimage()?;
     subimage.decode(
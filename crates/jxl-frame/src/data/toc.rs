@@ -2,10 +2,14 @@ use crate::Result;
 use jxl_bitstream::{Bitstream, U};
 use jxl_oxide_common::Bundle;
 
+/// Maximum number of TOC entries, which bounds the number of groups of a frame.
+const MAX_TOC_ENTRIES: u64 = 65536;
+
 /// Table of contents of a frame.
 ///
 /// Frame data are organized in groups. TOC specified the size and order of each group, and it is
 /// decoded after the frame header.
+#[derive(Clone)]
 pub struct Toc {
     num_lf_groups: usize,
     num_groups: usize,
@@ -68,6 +72,21 @@ pub enum TocGroupKind {
     GroupPass { pass_idx: u32, group_idx: u32 },
 }
 
+impl std::fmt::Display for TocGroupKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::All => write!(f, "single group"),
+            Self::LfGlobal => write!(f, "LfGlobal"),
+            Self::LfGroup(idx) => write!(f, "LfGroup #{idx}"),
+            Self::HfGlobal => write!(f, "HfGlobal"),
+            Self::GroupPass {
+                pass_idx,
+                group_idx,
+            } => write!(f, "pass #{pass_idx} group #{group_idx}"),
+        }
+    }
+}
+
 impl Ord for TocGroupKind {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         match (self, other) {
@@ -182,15 +201,27 @@ impl Bundle<&crate::FrameHeader> for Toc {
         let num_groups = ctx.num_groups();
         let num_passes = ctx.passes.num_passes;
 
+        // Computed in u64 so that group counts of large frames don't overflow, and validated
+        // before allocating anything based on the count.
         let entry_count = if num_groups == 1 && num_passes == 1 {
             1
         } else {
-            1 + ctx.num_lf_groups() + 1 + num_groups * num_passes
+            2 + ctx.num_lf_groups() as u64 + num_groups as u64 * num_passes as u64
         };
-
-        if entry_count > 65536 {
-            return Err(jxl_bitstream::Error::ValidationFailed("Too many TOC entries").into());
+        if entry_count > MAX_TOC_ENTRIES {
+            tracing::error!(
+                entry_count,
+                num_groups,
+                num_passes,
+                "Too many TOC entries; limit is {MAX_TOC_ENTRIES}"
+            );
+            return Err(jxl_bitstream::Error::TooManyTocEntries {
+                entry_count,
+                limit: MAX_TOC_ENTRIES,
+            }
+            .into());
         }
+        let entry_count = entry_count as u32;
 
         let permutated_toc = bitstream.read_bool()?;
         let permutation = if permutated_toc {
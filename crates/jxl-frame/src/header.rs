@@ -5,7 +5,7 @@ use jxl_oxide_common::{define_bundle, Bundle, Name};
 
 define_bundle! {
     /// Frame header.
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct FrameHeader ctx(headers: &ImageHeader) error(crate::Error) {
         all_default: ty(Bool) default(true),
         pub frame_type: ty(Bundle(FrameType)) cond(!all_default) default(FrameType::RegularFrame),
@@ -133,7 +133,7 @@ define_bundle! {
         pub bit_depth: ty(Bundle(BitDepth)) cond(false) default(headers.metadata.bit_depth),
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Passes error(crate::Error) {
         pub num_passes: ty(U32(1, 2, 3, 4 + u(3))) default(1),
         pub num_ds: ty(U32(0, 1, 2, 3 + u(1))) cond(num_passes != 1) default(0),
@@ -142,7 +142,7 @@ define_bundle! {
         pub last_pass: ty(Vec[U32(0, 1, 2, u(3))]; num_ds) cond(num_passes != 1) default(vec![0; num_ds as usize]),
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct BlendingInfo ctx(context: (bool, Option<BlendMode>, CanvasSizeParams<'_>)) error(crate::Error) {
         pub mode: ty(Bundle(BlendMode)),
         pub alpha_channel:
@@ -159,7 +159,7 @@ define_bundle! {
             default(0),
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct RestorationFilter ctx(encoding: Encoding) error(crate::Error) {
         all_default: ty(Bool) default(true),
         pub gab: ty(Bundle(crate::filter::Gabor)) cond(!all_default),
@@ -21,7 +21,10 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use jxl_bitstream::Bitstream;
-use jxl_grid::{AllocHandle, AllocTracker};
+use jxl_coding::Lz77Tracker;
+use jxl_grid::{
+    AllocCategory, AllocHandle, AllocTracker, MemoryLimit, MemoryReservation, MutableSubgrid,
+};
 use jxl_image::ImageHeader;
 use jxl_oxide_common::Bundle;
 
@@ -45,6 +48,9 @@ use crate::data::*;
 pub struct Frame {
     pool: JxlThreadPool,
     tracker: Option<AllocTracker>,
+    memory_limit: Option<MemoryLimit>,
+    lz77_tracker: Lz77Tracker,
+    force_scalar: bool,
     image_header: Arc<ImageHeader>,
     header: FrameHeader,
     toc: Toc,
@@ -67,6 +73,7 @@ struct GroupData {
     toc_group: TocGroup,
     bytes: Vec<u8>,
     handle: Option<AllocHandle>,
+    reservation: Option<MemoryReservation>,
 }
 
 impl From<TocGroup> for GroupData {
@@ -75,26 +82,40 @@ impl From<TocGroup> for GroupData {
             toc_group: value,
             bytes: Vec::new(),
             handle: None,
+            reservation: None,
         }
     }
 }
 
 impl GroupData {
-    fn ensure_allocated(&mut self, tracker: Option<&AllocTracker>) -> Result<()> {
+    /// Reserves buffer for the data of the group, with room for `available` more bytes.
+    ///
+    /// Group sizes in TOC may be much larger than the actual data, so the buffer grows as the data
+    /// arrives instead of allocating the declared size up front. Memory limit, if any, is applied
+    /// to the bytes actually stored.
+    fn ensure_allocated(
+        &mut self,
+        tracker: Option<&AllocTracker>,
+        memory_limit: Option<&MemoryLimit>,
+        available: usize,
+    ) -> Result<()> {
+        let size = self.toc_group.size as usize;
         if let Some(tracker) = tracker {
-            if self.handle.is_some() {
-                return Ok(());
+            if self.handle.is_none() {
+                let tracker = tracker.with_category(AllocCategory::Bitstream);
+                self.handle = Some(tracker.alloc::<u8>(size)?);
             }
-
-            let size = self.toc_group.size as usize;
-            let handle = tracker.alloc::<u8>(size)?;
-            self.bytes.try_reserve(size)?;
-            self.handle = Some(handle);
-        } else {
-            let additional = (self.toc_group.size as usize).saturating_sub(self.bytes.capacity());
-            self.bytes.try_reserve(additional)?;
         }
 
+        let bytes_left = size - self.bytes.len();
+        let additional = bytes_left.min(available);
+        if let Some(memory_limit) = memory_limit {
+            match &mut self.reservation {
+                Some(reservation) => reservation.grow(additional)?,
+                None => self.reservation = Some(memory_limit.reserve(additional)?),
+            }
+        }
+        self.bytes.try_reserve(additional)?;
         Ok(())
     }
 }
@@ -103,7 +124,12 @@ impl GroupData {
 pub struct FrameContext<'a> {
     pub image_header: Arc<ImageHeader>,
     pub tracker: Option<&'a AllocTracker>,
+    pub memory_limit: Option<&'a MemoryLimit>,
+    /// Maximum LZ77 distance allowed in Modular streams of the frame.
+    pub lz77_distance_limit: Option<u32>,
     pub pool: JxlThreadPool,
+    /// Use portable scalar code instead of SIMD implementations selected at runtime.
+    pub force_scalar: bool,
 }
 
 impl Bundle<FrameContext<'_>> for Frame {
@@ -113,9 +139,17 @@ impl Bundle<FrameContext<'_>> for Frame {
         let FrameContext {
             image_header,
             tracker,
+            memory_limit,
+            lz77_distance_limit,
             pool,
+            force_scalar,
         } = ctx;
         let tracker = tracker.cloned();
+        let memory_limit = memory_limit.cloned();
+        let lz77_tracker = match lz77_distance_limit {
+            Some(limit) => Lz77Tracker::with_distance_limit(limit),
+            None => Lz77Tracker::new(),
+        };
 
         bitstream.zero_pad_to_byte()?;
         let base_offset = bitstream.num_read_bits() / 8;
@@ -228,6 +262,9 @@ impl Bundle<FrameContext<'_>> for Frame {
         Ok(Self {
             pool,
             tracker,
+            memory_limit,
+            lz77_tracker,
+            force_scalar,
             image_header,
             header,
             toc,
@@ -245,6 +282,21 @@ impl Frame {
         self.tracker.as_ref()
     }
 
+    /// Returns whether portable scalar code is used instead of SIMD implementations.
+    #[inline]
+    pub fn force_scalar(&self) -> bool {
+        self.force_scalar
+    }
+
+    /// Returns the LZ77 tracker of the frame.
+    ///
+    /// The tracker records LZ77 usage of Modular streams which use the global MA tree, or are
+    /// nested in the global Modular stream.
+    #[inline]
+    pub fn lz77_tracker(&self) -> &Lz77Tracker {
+        &self.lz77_tracker
+    }
+
     pub fn image_header(&self) -> &ImageHeader {
         &self.image_header
     }
@@ -258,6 +310,11 @@ impl Frame {
         &self.header
     }
 
+    /// Replaces the frame name with the one interned in the given pool.
+    pub fn intern_name(&mut self, pool: &mut jxl_oxide_common::NamePool) {
+        self.header.name = pool.intern(&self.header.name);
+    }
+
     /// Returns the TOC.
     ///
     /// See the documentation of [`Toc`] for details.
@@ -273,12 +330,43 @@ impl Frame {
         let idx = self.toc.group_index_bitstream_order(group);
         self.data.get(idx).map(|b| &*b.bytes)
     }
+
+    /// Returns a copy of the frame with compressed data of groups discarded.
+    ///
+    /// The copy keeps the frame header and TOC, but groups of it cannot be decoded anymore.
+    pub fn without_data(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            tracker: self.tracker.clone(),
+            memory_limit: self.memory_limit.clone(),
+            lz77_tracker: self.lz77_tracker.clone(),
+            force_scalar: self.force_scalar,
+            image_header: Arc::clone(&self.image_header),
+            header: self.header.clone(),
+            toc: self.toc.clone(),
+            data: Vec::new(),
+            all_group_offsets: AllGroupOffsets::default(),
+            reading_data_index: 0,
+            pass_shifts: self.pass_shifts.clone(),
+        }
+    }
+
+    /// Returns whether compressed data of the frame is discarded by
+    /// [`without_data`][Self::without_data].
+    #[inline]
+    pub fn is_data_released(&self) -> bool {
+        self.data.is_empty()
+    }
 }
 
 impl Frame {
     pub fn feed_bytes<'buf>(&mut self, mut buf: &'buf [u8]) -> Result<&'buf [u8]> {
         while let Some(group_data) = self.data.get_mut(self.reading_data_index) {
-            group_data.ensure_allocated(self.tracker.as_ref())?;
+            group_data.ensure_allocated(
+                self.tracker.as_ref(),
+                self.memory_limit.as_ref(),
+                buf.len(),
+            )?;
             let bytes_left = group_data.toc_group.size as usize - group_data.bytes.len();
             if buf.len() < bytes_left {
                 group_data.bytes.extend_from_slice(buf);
@@ -301,10 +389,23 @@ impl Frame {
     pub fn is_loading_done(&self) -> bool {
         self.reading_data_index >= self.data.len()
     }
+
+    /// Returns the number of groups loaded completely, counted in bitstream order.
+    #[inline]
+    pub fn num_loaded_groups(&self) -> usize {
+        self.reading_data_index.min(self.data.len())
+    }
+
+    /// Returns the number of bytes of group data loaded so far, including partially loaded
+    /// groups.
+    pub fn loaded_bytes(&self) -> usize {
+        self.data.iter().map(|group| group.bytes.len()).sum()
+    }
 }
 
 impl Frame {
     pub fn try_parse_lf_global<S: Sample>(&self) -> Option<Result<LfGlobal<S>>> {
+        let _guard = tracing::trace_span!("Decode group", kind = "LfGlobal").entered();
         Some(if self.toc.is_single_entry() {
             if self.all_group_offsets.has_error.load(Ordering::Relaxed) != 0 {
                 return Some(Err(Error::HadError));
@@ -315,12 +416,16 @@ impl Frame {
             let mut bitstream = Bitstream::new(&group.bytes);
             let lf_global = LfGlobal::parse(
                 &mut bitstream,
-                LfGlobalParams::new(
-                    &self.image_header,
-                    &self.header,
-                    self.tracker.as_ref(),
-                    false,
-                ),
+                LfGlobalParams {
+                    lz77_tracker: Some(&self.lz77_tracker),
+                    force_scalar: self.force_scalar,
+                    ..LfGlobalParams::new(
+                        &self.image_header,
+                        &self.header,
+                        self.tracker.as_ref(),
+                        false,
+                    )
+                },
             );
             match lf_global {
                 Ok(lf_global) => {
@@ -344,12 +449,16 @@ impl Frame {
             let mut bitstream = Bitstream::new(&group.bytes);
             LfGlobal::parse(
                 &mut bitstream,
-                LfGlobalParams::new(
-                    &self.image_header,
-                    &self.header,
-                    self.tracker.as_ref(),
-                    allow_partial,
-                ),
+                LfGlobalParams {
+                    lz77_tracker: Some(&self.lz77_tracker),
+                    force_scalar: self.force_scalar,
+                    ..LfGlobalParams::new(
+                        &self.image_header,
+                        &self.header,
+                        self.tracker.as_ref(),
+                        allow_partial,
+                    )
+                },
             )
         })
     }
@@ -361,6 +470,7 @@ impl Frame {
         mlf_group: Option<TransformedModularSubimage<S>>,
         lf_group_idx: u32,
     ) -> Option<Result<LfGroup<S>>> {
+        let _guard = tracing::trace_span!("Decode group", kind = "LfGroup", lf_group_idx).entered();
         if self.toc.is_single_entry() {
             if self.all_group_offsets.has_error.load(Ordering::Relaxed) != 0 {
                 return Some(Err(Error::HadError));
@@ -394,6 +504,7 @@ impl Frame {
                     allow_partial: !loaded,
                     tracker: self.tracker.as_ref(),
                     pool: &self.pool,
+                    force_scalar: self.force_scalar,
                 },
             );
 
@@ -430,6 +541,7 @@ impl Frame {
                     allow_partial,
                     tracker: self.tracker.as_ref(),
                     pool: &self.pool,
+                    force_scalar: self.force_scalar,
                 },
             );
             if allow_partial && result.is_err() {
@@ -443,6 +555,7 @@ impl Frame {
         &self,
         cached_lf_global: Option<&LfGlobal<S>>,
     ) -> Option<Result<HfGlobal>> {
+        let _guard = tracing::trace_span!("Decode group", kind = "HfGlobal").entered();
         let is_modular = self.header.encoding == header::Encoding::Modular;
 
         if self.toc.is_single_entry() {
@@ -514,7 +627,8 @@ impl Frame {
                     lf_global,
                     self.tracker.as_ref(),
                     &self.pool,
-                ),
+                )
+                .force_scalar(self.force_scalar),
             );
 
             Some(match result {
@@ -557,7 +671,8 @@ impl Frame {
                 lf_global,
                 self.tracker.as_ref(),
                 &self.pool,
-            );
+            )
+            .force_scalar(self.force_scalar);
             Some(HfGlobal::parse(&mut bitstream, params))
         }
     }
@@ -607,6 +722,108 @@ impl Frame {
             Ok(PassGroupBitstream { bitstream, partial })
         })
     }
+
+    /// Decodes HF coefficients of a VarDCT pass group into the given buffers.
+    ///
+    /// `coeff_output` holds quantized HF coefficients of X, Y and B channels of the group, or Cb,
+    /// Y and Cr channels if the frame is YCbCr encoded, with each varblock laid out in its own
+    /// position. Decoded coefficients are *added* to the buffers, with the shift of the pass
+    /// applied, so that every pass can be decoded into the same buffers; zero the buffers before
+    /// decoding the first pass. Grids of [`group_dim`] by `group_dim` samples, shifted by chroma
+    /// subsampling, are always large enough. If `modular` is given, Modular image of the pass
+    /// group, such as extra channels, is decoded into it.
+    ///
+    /// Returns `None` if the group is not loaded yet. Partially loaded groups are decoded as far
+    /// as possible.
+    ///
+    /// # Panics
+    /// Panics if the frame is not a VarDCT frame, or the buffers are too small for the group.
+    ///
+    /// [`group_dim`]: FrameHeader::group_dim
+    #[allow(clippy::too_many_arguments)]
+    pub fn decode_pass_group_vardct<S: Sample>(
+        &self,
+        pass_idx: u32,
+        group_idx: u32,
+        lf_global: &LfGlobal<S>,
+        lf_group: &LfGroup<S>,
+        hf_global: &HfGlobal,
+        coeff_output: &mut [MutableSubgrid<'_, i32>; 3],
+        modular: Option<TransformedModularSubimage<S>>,
+    ) -> Option<Result<()>> {
+        let lf_vardct = lf_global
+            .vardct
+            .as_ref()
+            .expect("frame is not a VarDCT frame");
+        let bitstream = match self.pass_group_bitstream(pass_idx, group_idx)? {
+            Ok(bitstream) => bitstream,
+            Err(e) => return Some(Err(e)),
+        };
+        let PassGroupBitstream {
+            mut bitstream,
+            partial,
+        } = bitstream;
+
+        // SAFETY: Buffers are borrowed mutably, so they are accessed only through the atomic views
+        // while decoding.
+        let hf_coeff_output = coeff_output
+            .each_ref()
+            .map(|grid| unsafe { grid.as_shared().as_atomic_i32() });
+        let params = PassGroupParams {
+            frame_header: &self.header,
+            lf_group,
+            pass_idx,
+            group_idx,
+            global_ma_config: lf_global.gmodular.ma_config(),
+            modular,
+            vardct: Some(PassGroupParamsVardct {
+                lf_vardct,
+                hf_global,
+                hf_coeff_output: &hf_coeff_output,
+            }),
+            allow_partial: partial,
+            tracker: self.tracker.as_ref(),
+            pool: &self.pool,
+        };
+        Some(decode_pass_group(&mut bitstream, params))
+    }
+
+    /// Decodes Modular image of a pass group into the given subimage.
+    ///
+    /// `modular` is the subimage of the pass group, prepared from the global Modular image with
+    /// [`prepare_groups`][jxl_modular::image::ModularImageDestination::prepare_groups] using
+    /// [`pass_shifts`][Self::pass_shifts].
+    ///
+    /// Returns `None` if the group is not loaded yet. Partially loaded groups are decoded as far
+    /// as possible.
+    pub fn decode_pass_group_modular<S: Sample>(
+        &self,
+        pass_idx: u32,
+        group_idx: u32,
+        lf_global: &LfGlobal<S>,
+        modular: TransformedModularSubimage<S>,
+    ) -> Option<Result<()>> {
+        let bitstream = match self.pass_group_bitstream(pass_idx, group_idx)? {
+            Ok(bitstream) => bitstream,
+            Err(e) => return Some(Err(e)),
+        };
+        let PassGroupBitstream {
+            mut bitstream,
+            partial,
+        } = bitstream;
+
+        Some(decode_pass_group_modular(
+            &mut bitstream,
+            &self.header,
+            lf_global.gmodular.ma_config(),
+            pass_idx,
+            group_idx,
+            modular,
+            partial,
+            self.tracker.as_ref(),
+            &self.pool,
+        ))
+    }
 }
 
 #[derive(Debug)]
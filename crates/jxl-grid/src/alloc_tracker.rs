@@ -4,14 +4,114 @@ use std::sync::{
 };
 
 /// Allocation tracker with total memory limit.
+///
+/// Allocations are attributed to the [category][AllocCategory] of the tracker, which can be set
+/// with [`with_category`][Self::with_category]. Current and peak usage are available from
+/// [`stats`][Self::stats].
 #[derive(Debug, Clone)]
 pub struct AllocTracker {
     inner: Arc<AllocTrackerInner>,
+    category: AllocCategory,
 }
 
 #[derive(Debug)]
 struct AllocTrackerInner {
     bytes_left: AtomicUsize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    categories: [CategoryUsage; AllocCategory::COUNT],
+}
+
+#[derive(Debug, Default)]
+struct CategoryUsage {
+    used: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl AllocTrackerInner {
+    fn record_alloc(&self, category: AllocCategory, bytes: usize) {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(used, Ordering::Relaxed);
+
+        let usage = &self.categories[category as usize];
+        let used = usage.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        usage.peak.fetch_max(used, Ordering::Relaxed);
+    }
+
+    fn record_free(&self, category: AllocCategory, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+        self.categories[category as usize]
+            .used
+            .fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Subsystem an allocation is attributed to.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AllocCategory {
+    /// Compressed data of frames.
+    Bitstream,
+    /// Modular sample grids and MA trees.
+    Modular,
+    /// VarDCT coefficients and metadata.
+    VarDct,
+    /// Render buffers, such as reconstructed frames and intermediate images of filters.
+    Render,
+    /// Allocations not attributed to any of the subsystems.
+    #[default]
+    Other,
+}
+
+impl AllocCategory {
+    const COUNT: usize = 5;
+
+    /// All categories, in the order of declaration.
+    pub const ALL: [AllocCategory; Self::COUNT] = [
+        Self::Bitstream,
+        Self::Modular,
+        Self::VarDct,
+        Self::Render,
+        Self::Other,
+    ];
+}
+
+/// Memory usage recorded by [`AllocTracker`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AllocStats {
+    current_bytes: usize,
+    peak_bytes: usize,
+    categories: [CategoryStats; AllocCategory::COUNT],
+}
+
+impl AllocStats {
+    /// Returns the number of bytes currently allocated.
+    #[inline]
+    pub fn current_bytes(&self) -> usize {
+        self.current_bytes
+    }
+
+    /// Returns the highest number of bytes allocated at the same time.
+    #[inline]
+    pub fn peak_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    /// Returns the usage of allocations attributed to the category.
+    ///
+    /// Peaks of categories are recorded separately, so they may not add up to the total peak.
+    #[inline]
+    pub fn category(&self, category: AllocCategory) -> CategoryStats {
+        self.categories[category as usize]
+    }
+}
+
+/// Memory usage of a single [`AllocCategory`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CategoryStats {
+    /// Number of bytes currently allocated.
+    pub current_bytes: usize,
+    /// Highest number of bytes allocated at the same time.
+    pub peak_bytes: usize,
 }
 
 impl AllocTracker {
@@ -20,6 +120,44 @@ impl AllocTracker {
         Self {
             inner: Arc::new(AllocTrackerInner {
                 bytes_left: AtomicUsize::new(bytes_left),
+                used: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                categories: Default::default(),
+            }),
+            category: AllocCategory::Other,
+        }
+    }
+
+    /// Returns a tracker sharing the limit and statistics, which attributes allocations to the
+    /// given category.
+    #[inline]
+    pub fn with_category(&self, category: AllocCategory) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            category,
+        }
+    }
+
+    /// Returns the category allocations are attributed to.
+    #[inline]
+    pub fn category(&self) -> AllocCategory {
+        self.category
+    }
+
+    /// Returns current and peak memory usage recorded by the tracker.
+    ///
+    /// Statistics are shared between trackers created with [`with_category`][Self::with_category].
+    pub fn stats(&self) -> AllocStats {
+        let inner = &*self.inner;
+        AllocStats {
+            current_bytes: inner.used.load(Ordering::Relaxed),
+            peak_bytes: inner.peak.load(Ordering::Relaxed),
+            categories: std::array::from_fn(|idx| {
+                let usage = &inner.categories[idx];
+                CategoryStats {
+                    current_bytes: usage.used.load(Ordering::Relaxed),
+                    peak_bytes: usage.peak.load(Ordering::Relaxed),
+                }
             }),
         }
     }
@@ -38,9 +176,11 @@ impl AllocTracker {
         match result {
             Ok(prev) => {
                 tracing::trace!(bytes, left = prev - bytes, "Created allocation handle");
+                self.inner.record_alloc(self.category, bytes);
                 Ok(AllocHandle {
                     bytes,
                     inner: Arc::clone(&self.inner),
+                    category: self.category,
                 })
             }
             Err(left) => {
@@ -79,22 +219,25 @@ impl AllocTracker {
 pub struct AllocHandle {
     bytes: usize,
     inner: Arc<AllocTrackerInner>,
+    category: AllocCategory,
 }
 
 impl Drop for AllocHandle {
     fn drop(&mut self) {
         let bytes = self.bytes;
         let prev = self.inner.bytes_left.fetch_add(bytes, Ordering::Relaxed);
+        self.inner.record_free(self.category, bytes);
         tracing::trace!(bytes, left = prev + bytes, "Released allocation handle");
         self.bytes = 0;
     }
 }
 
 impl AllocHandle {
-    /// Returns the tracker the handle belongs to.
+    /// Returns the tracker the handle belongs to, with the category of the allocation.
     pub fn tracker(&self) -> AllocTracker {
         AllocTracker {
             inner: Arc::clone(&self.inner),
+            category: self.category,
         }
     }
 }
@@ -1,10 +1,12 @@
 //! This crate provides [`AlignedGrid`] and [`PaddedGrid`], used in various places involving
 //! images.
 mod alloc_tracker;
+mod memory_limit;
 mod mutable_subgrid;
 mod shared_subgrid;
 mod simd;
 pub use alloc_tracker::*;
+pub use memory_limit::*;
 pub use mutable_subgrid::*;
 pub use shared_subgrid::*;
 pub use simd::SimdVector;
@@ -12,6 +14,7 @@ pub use simd::SimdVector;
 #[derive(Debug)]
 pub enum Error {
     OutOfMemory(usize),
+    MemoryLimitExceeded(usize),
 }
 
 impl std::error::Error for Error {}
@@ -20,6 +23,9 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::OutOfMemory(bytes) => write!(f, "failed to allocate {bytes} byte(s)"),
+            Self::MemoryLimitExceeded(bytes) => {
+                write!(f, "memory limit exceeded while reserving {bytes} byte(s)")
+            }
         }
     }
 }
@@ -0,0 +1,123 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Memory limit for buffers other than sample grids.
+///
+/// [`AllocTracker`][crate::AllocTracker] tracks sample grids only. `MemoryLimit` bounds other
+/// buffers whose sizes are controlled by the bitstream, such as compressed frame data and ICC
+/// profiles, so that malformed images cannot make the decoder reserve huge amount of memory.
+#[derive(Debug, Clone)]
+pub struct MemoryLimit {
+    inner: Arc<MemoryLimitInner>,
+}
+
+#[derive(Debug)]
+struct MemoryLimitInner {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryLimit {
+    /// Creates a memory limit of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(MemoryLimitInner {
+                limit,
+                used: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns the limit in bytes.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// Returns the number of bytes currently reserved.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether `bytes` more bytes can be reserved, without actually reserving them.
+    ///
+    /// This is used for temporary buffers which are released soon after allocation.
+    pub fn check(&self, bytes: usize) -> Result<(), crate::Error> {
+        let used = self.used();
+        if used
+            .checked_add(bytes)
+            .is_some_and(|x| x <= self.inner.limit)
+        {
+            Ok(())
+        } else {
+            tracing::trace!(
+                bytes,
+                used,
+                limit = self.inner.limit,
+                "Memory limit exceeded"
+            );
+            Err(crate::Error::MemoryLimitExceeded(bytes))
+        }
+    }
+
+    /// Reserves `bytes` bytes, and returns the reservation which releases the bytes on drop.
+    pub fn reserve(&self, bytes: usize) -> Result<MemoryReservation, crate::Error> {
+        let mut reservation = MemoryReservation {
+            bytes: 0,
+            inner: Arc::clone(&self.inner),
+        };
+        reservation.grow(bytes)?;
+        Ok(reservation)
+    }
+}
+
+/// Reservation of memory made from [`MemoryLimit`].
+///
+/// Reserved bytes are released when the reservation is dropped.
+#[derive(Debug)]
+pub struct MemoryReservation {
+    bytes: usize,
+    inner: Arc<MemoryLimitInner>,
+}
+
+impl MemoryReservation {
+    /// Returns the number of reserved bytes.
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Reserves `bytes` more bytes.
+    ///
+    /// Returns an error if the reservation exceeds the limit, leaving the reservation unchanged.
+    pub fn grow(&mut self, bytes: usize) -> Result<(), crate::Error> {
+        let limit = self.inner.limit;
+        let result = self
+            .inner
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&x| x <= limit)
+            });
+
+        match result {
+            Ok(_) => {
+                self.bytes += bytes;
+                Ok(())
+            }
+            Err(used) => {
+                tracing::trace!(bytes, used, limit, "Memory limit exceeded");
+                Err(crate::Error::MemoryLimitExceeded(bytes))
+            }
+        }
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.inner.used.fetch_sub(self.bytes, Ordering::Relaxed);
+        self.bytes = 0;
+    }
+}
@@ -12,7 +12,7 @@ use jxl_oxide_common::{define_bundle, Bundle, Name};
 /// JPEG XL image header.
 ///
 /// Use [`Bundle::parse`] to parse the header.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ImageHeader {
     /// Image size information.
     pub size: SizeHeader,
@@ -84,7 +84,7 @@ impl ImageHeader {
 
 define_bundle! {
     /// Image size information.
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct SizeHeader {
         div8: ty(Bool) default(false),
         h_div8: ty(1 + u(5)) cond(div8) default(0),
@@ -121,7 +121,7 @@ impl SizeHeader {
 
 define_bundle! {
     /// Image metadata.
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct ImageMetadata {
         all_default: ty(Bool) default(true),
         extra_fields: ty(Bool) cond(!all_default) default(false),
@@ -166,7 +166,7 @@ define_bundle! {
         pub up8_weight: ty(Array[F16]; 210) cond(cw_mask & 4 != 0) default(Self::D_UP8),
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct PreviewHeader {
         div8: ty(Bool),
         h_div8: ty(U32(16, 32, 1 + u(5), 33 + u(9))) cond(div8) default(1),
@@ -186,7 +186,7 @@ define_bundle! {
     ///
     /// TPS (ticks per second) is computed as `tps_numerator / tps_denominator`, which means
     /// `tps_denominator / tps_numerator` seconds per tick.
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct AnimationHeader {
         /// TPS numerator.
         pub tps_numerator: ty(U32(100, 1000, 1 + u(10), 1 + u(30))) default(0),
@@ -199,7 +199,7 @@ define_bundle! {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 #[allow(unused)]
 pub struct Extensions {
     extension_bits: u64,
@@ -353,6 +353,20 @@ impl ExtraChannelInfo {
     pub fn is_black(&self) -> bool {
         self.ty == ExtraChannelType::Black
     }
+
+    /// Returns whether this channel carries auxiliary data which doesn't affect how the image
+    /// looks, such as depth, thermal or CFA channels.
+    #[inline]
+    pub fn is_auxiliary(&self) -> bool {
+        matches!(
+            self.ty,
+            ExtraChannelType::Depth
+                | ExtraChannelType::SelectionMask
+                | ExtraChannelType::Cfa { .. }
+                | ExtraChannelType::Thermal
+                | ExtraChannelType::Optional
+        )
+    }
 }
 
 /// Type of an extra channel.
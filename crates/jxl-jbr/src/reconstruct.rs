@@ -198,11 +198,13 @@ impl<'jbrd, 'frame, 'meta> JpegBitstreamReconstructor<'jbrd, 'frame, 'meta> {
         for group_idx in 0..num_groups {
             let (w, h) = frame_header.group_size_for(group_idx);
 
+            // Sizes are computed in blocks, so that groups of subsampled images are padded to
+            // the MCU boundary.
+            let (bw, bh) = (w.div_ceil(8), h.div_ceil(8));
             pass_groups.push(upsampling_shifts_ycbcr.map(|shift| {
-                let (w, h) = shift.shift_size((w, h));
-                let w = (w + 7) & !7;
-                let h = (h + 7) & !7;
-                AlignedGrid::<i32>::with_alloc_tracker(w as usize, h as usize, None).unwrap()
+                let (bw, bh) = shift.shift_size((bw, bh));
+                AlignedGrid::<i32>::with_alloc_tracker(bw as usize * 8, bh as usize * 8, None)
+                    .unwrap()
             }));
         }
 
@@ -1,12 +1,17 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use jxl_bitstream::Bitstream;
 use jxl_coding::{Decoder, DecoderRleMode, RleToken};
-use jxl_grid::{AlignedGrid, AllocTracker, MutableSubgrid};
+use jxl_grid::{AlignedGrid, AllocCategory, AllocTracker, MutableSubgrid};
 
 use crate::{
     ma::{FlatMaTree, MaTreeLeafClustered, SimpleMaTable},
-    predictor::{Predictor, PredictorState, Properties, WpHeader},
+    predictor::{
+        evaluate_leaf_row_base, evaluate_leaf_row_i32, Predictor, PredictorState, Properties,
+        WpHeader,
+    },
     sample::Sample,
     MaConfig, ModularChannelInfo, ModularChannels, ModularHeader, Result,
 };
@@ -102,6 +107,8 @@ pub struct ModularImageDestination<S: Sample> {
     channels: ModularChannels,
     meta_channels: Vec<AlignedGrid<S>>,
     image_channels: Vec<AlignedGrid<S>>,
+    clamped_samples: Arc<AtomicUsize>,
+    force_scalar: bool,
 }
 
 impl<S: Sample> ModularImageDestination<S> {
@@ -112,7 +119,10 @@ impl<S: Sample> ModularImageDestination<S> {
         bit_depth: u32,
         channels: ModularChannels,
         tracker: Option<&AllocTracker>,
+        force_scalar: bool,
     ) -> Result<Self> {
+        let tracker = tracker.map(|tracker| tracker.with_category(AllocCategory::Modular));
+        let tracker = tracker.as_ref();
         let mut meta_channels = Vec::new();
         for tr in &header.transform {
             tr.prepare_meta_channels(&mut meta_channels, tracker)?;
@@ -134,6 +144,8 @@ impl<S: Sample> ModularImageDestination<S> {
             channels,
             meta_channels,
             image_channels,
+            clamped_samples: Arc::new(AtomicUsize::new(0)),
+            force_scalar,
         })
     }
 
@@ -154,6 +166,10 @@ impl<S: Sample> ModularImageDestination<S> {
                 .iter()
                 .map(|x| x.try_clone())
                 .collect::<std::result::Result<_, _>>()?,
+            clamped_samples: Arc::new(AtomicUsize::new(
+                self.clamped_samples.load(Ordering::Relaxed),
+            )),
+            force_scalar: self.force_scalar,
         })
     }
 
@@ -178,6 +194,12 @@ impl<S: Sample> ModularImageDestination<S> {
     pub fn has_squeeze(&self) -> bool {
         self.header.transform.iter().any(|tr| tr.is_squeeze())
     }
+
+    /// Returns the number of samples clamped due to overflow while applying inverse transforms
+    /// of this image and its groups.
+    pub fn clamped_samples(&self) -> usize {
+        self.clamped_samples.load(Ordering::Relaxed)
+    }
 }
 
 impl<S: Sample> ModularImageDestination<S> {
@@ -306,7 +328,13 @@ impl<S: Sample> ModularImageDestination<S> {
 
             if groups.is_empty() {
                 groups.resize_with(grids.len(), || {
-                    TransformedModularSubimage::empty(&subimage.header, &subimage.ma_ctx, bit_depth)
+                    TransformedModularSubimage::empty(
+                        &subimage.header,
+                        &subimage.ma_ctx,
+                        bit_depth,
+                        &subimage.clamped_samples,
+                        subimage.force_scalar,
+                    )
                 });
             } else if groups.len() != grids.len() {
                 panic!();
@@ -367,6 +395,8 @@ impl<S: Sample> ModularImageDestination<S> {
             channel_indices,
             grid: grids,
             partial: true,
+            clamped_samples: Arc::clone(&self.clamped_samples),
+            force_scalar: self.force_scalar,
         })
     }
 }
@@ -387,10 +417,18 @@ pub struct TransformedModularSubimage<'dest, S: Sample> {
     channel_indices: Vec<usize>,
     grid: Vec<TransformedGrid<'dest, S>>,
     partial: bool,
+    clamped_samples: Arc<AtomicUsize>,
+    force_scalar: bool,
 }
 
 impl<S: Sample> TransformedModularSubimage<'_, S> {
-    fn empty(header: &ModularHeader, ma_ctx: &MaConfig, bit_depth: u32) -> Self {
+    fn empty(
+        header: &ModularHeader,
+        ma_ctx: &MaConfig,
+        bit_depth: u32,
+        clamped_samples: &Arc<AtomicUsize>,
+        force_scalar: bool,
+    ) -> Self {
         Self {
             header: header.clone(),
             ma_ctx: ma_ctx.clone(),
@@ -400,6 +438,8 @@ impl<S: Sample> TransformedModularSubimage<'_, S> {
             channel_indices: Vec::new(),
             grid: Vec::new(),
             partial: false,
+            clamped_samples: Arc::clone(clamped_samples),
+            force_scalar,
         }
     }
 }
@@ -424,6 +464,7 @@ impl<'dest, S: Sample> TransformedModularSubimage<'dest, S> {
             &channels,
             global_ma_config,
             tracker,
+            self.ma_ctx.lz77_tracker(),
         )?;
 
         let mut image = RecursiveModularImage {
@@ -433,6 +474,8 @@ impl<'dest, S: Sample> TransformedModularSubimage<'dest, S> {
             channels,
             meta_channels: Vec::new(),
             image_channels: self.grid,
+            clamped_samples: self.clamped_samples,
+            force_scalar: self.force_scalar,
         };
         for tr in &image.header.transform {
             tr.prepare_meta_channels(&mut image.meta_channels, tracker)?;
@@ -440,9 +483,17 @@ impl<'dest, S: Sample> TransformedModularSubimage<'dest, S> {
         Ok(image)
     }
 
+    /// Applies inverse transforms, and returns whether the subimage was fully decoded.
+    ///
+    /// Samples clamped due to overflow are counted in
+    /// [`ModularImageDestination::clamped_samples`] of the image this subimage belongs to.
     pub fn finish(mut self, pool: &jxl_threadpool::JxlThreadPool) -> bool {
+        let mut clamped = 0usize;
         for tr in self.header.transform.iter().rev() {
-            tr.inverse(&mut self.grid, self.bit_depth, pool);
+            clamped += tr.inverse(&mut self.grid, self.bit_depth, pool, self.force_scalar);
+        }
+        if clamped != 0 {
+            self.clamped_samples.fetch_add(clamped, Ordering::Relaxed);
         }
         !self.partial
     }
@@ -531,7 +582,7 @@ impl<S: Sample> TransformedModularSubimage<'_, S> {
         }
 
         let wp_header = &self.header.wp_params;
-        let mut predictor = PredictorState::new();
+        let mut predictor = PredictorState::new(self.force_scalar);
         let mut prev_map = HashMap::new();
         for ((info, ma_tree), grid) in self
             .channel_info
@@ -600,6 +651,12 @@ impl<S: Sample> TransformedModularSubimage<'_, S> {
             }
             Err(e) => return Err(e),
             Ok(_) => {
+                let num_coeffs = self
+                    .channel_info
+                    .iter()
+                    .map(|info| info.width as u64 * info.height as u64)
+                    .sum::<u64>();
+                tracing::trace!(num_coeffs, "Modular subimage");
                 self.partial = false;
             }
         }
@@ -615,6 +672,8 @@ pub struct RecursiveModularImage<'dest, S: Sample> {
     channels: ModularChannels,
     meta_channels: Vec<AlignedGrid<S>>,
     image_channels: Vec<TransformedGrid<'dest, S>>,
+    clamped_samples: Arc<AtomicUsize>,
+    force_scalar: bool,
 }
 
 impl<S: Sample> RecursiveModularImage<'_, S> {
@@ -649,6 +708,8 @@ impl<S: Sample> RecursiveModularImage<'_, S> {
             channel_indices,
             grid: grids,
             partial: true,
+            clamped_samples: Arc::clone(&self.clamped_samples),
+            force_scalar: self.force_scalar,
         })
     }
 }
@@ -737,21 +798,28 @@ fn decode_single_node<S: Sample>(
             }
             Ok(())
         }
-        (Predictor::Zero, None) => {
-            tracing::trace!("Zero predictor: fast path");
-            for y in 0..height {
-                let row = grid.get_row_mut(y);
-                for out in row {
-                    let token = decoder.read_varint_with_multiplier_clustered(
-                        bitstream,
-                        cluster,
-                        dist_multiplier,
-                    )?;
-                    *out =
-                        S::unpack_signed_u32(token).wrapping_muladd_i32(multiplier as i32, offset);
-                }
+        (Predictor::Zero, None) | (Predictor::North, _) => {
+            tracing::trace!(?predictor, "Prediction from previous row: fast path");
+            let simd = predictor_state.simd_level();
+            if let Some(grid) = S::try_as_mutable_subgrid_i32(grid) {
+                decode_rowwise(
+                    bitstream,
+                    decoder,
+                    dist_multiplier,
+                    grid,
+                    node,
+                    |t, n, out| evaluate_leaf_row_i32(simd, t, n, multiplier as i32, offset, out),
+                )
+            } else {
+                decode_rowwise(
+                    bitstream,
+                    decoder,
+                    dist_multiplier,
+                    grid,
+                    node,
+                    |t, n, out| evaluate_leaf_row_base(t, n, multiplier as i32, offset, out),
+                )
             }
-            Ok(())
         }
         (Predictor::Gradient, _) if offset == 0 && multiplier == 1 => {
             tracing::trace!("Simple gradient: quite fast path");
@@ -772,6 +840,59 @@ fn decode_single_node<S: Sample>(
     }
 }
 
+/// Decodes a channel with Zero or North predictor, a row at a time.
+///
+/// Tokens of a row are read first, and then evaluated by `evaluate_row`, which can be vectorized
+/// since the prediction doesn't depend on the samples in the same row.
+#[inline(never)]
+fn decode_rowwise<S: Sample>(
+    bitstream: &mut Bitstream,
+    decoder: &mut Decoder,
+    dist_multiplier: u32,
+    grid: &mut MutableSubgrid<S>,
+    node: &MaTreeLeafClustered,
+    evaluate_row: impl Fn(&[u32], Option<&[S]>, &mut [S]),
+) -> Result<()> {
+    let &MaTreeLeafClustered {
+        cluster,
+        predictor,
+        offset,
+        multiplier,
+    } = node;
+
+    let width = grid.width();
+    let height = grid.height();
+    let mut tokens = vec![0u32; width];
+    for y in 0..height {
+        for token in &mut tokens {
+            *token = decoder.read_varint_with_multiplier_clustered(
+                bitstream,
+                cluster,
+                dist_multiplier,
+            )?;
+        }
+
+        let (u, mut d) = grid.split_vertical(y);
+        let out_row = d.get_row_mut(0);
+        if predictor == Predictor::Zero {
+            evaluate_row(&tokens, None, out_row);
+        } else if y == 0 {
+            // North predictor of the first row is West predictor.
+            let mut w = S::default();
+            for (out, &token) in out_row.iter_mut().zip(&tokens) {
+                w = S::unpack_signed_u32(token)
+                    .wrapping_muladd_i32(multiplier as i32, offset)
+                    .add(w);
+                *out = w;
+            }
+        } else {
+            evaluate_row(&tokens, Some(u.get_row(y - 1)), out_row);
+        }
+    }
+
+    Ok(())
+}
+
 #[inline(never)]
 fn decode_fast_lossless<S: Sample>(
     bitstream: &mut Bitstream,
@@ -14,6 +14,7 @@ mod predictor;
 mod sample;
 mod transform;
 pub use error::{Error, Result};
+use jxl_coding::Lz77Tracker;
 use jxl_grid::AllocTracker;
 pub use ma::{FlatMaTree, MaConfig, MaConfigParams};
 pub use param::*;
@@ -104,11 +105,15 @@ impl<S: Sample> Bundle<ModularParams<'_, '_>> for ModularData<S> {
 
     fn parse(bitstream: &mut Bitstream, params: ModularParams) -> Result<Self> {
         let channels = ModularChannels::from_params(&params);
+        let lz77_tracker = params
+            .lz77_tracker
+            .or_else(|| params.ma_config.and_then(MaConfig::lz77_tracker));
         let (header, ma_ctx) = read_and_validate_local_modular_header(
             bitstream,
             &channels,
             params.ma_config,
             params.tracker,
+            lz77_tracker,
         )?;
         Ok(Self {
             image: image::ModularImageDestination::new(
@@ -118,6 +123,7 @@ impl<S: Sample> Bundle<ModularParams<'_, '_>> for ModularData<S> {
                 params.bit_depth,
                 channels,
                 params.tracker,
+                params.force_scalar,
             )?,
         })
     }
@@ -204,6 +210,7 @@ fn read_and_validate_local_modular_header(
     channels: &ModularChannels,
     global_ma_config: Option<&MaConfig>,
     tracker: Option<&AllocTracker>,
+    lz77_tracker: Option<&Lz77Tracker>,
 ) -> Result<(ModularHeader, MaConfig)> {
     let mut header = ModularHeader::parse(bitstream, ())?;
     if header.nb_transforms > 512 {
@@ -236,6 +243,7 @@ fn read_and_validate_local_modular_header(
             .fold(0u64, |acc, ch| acc + (ch.width as u64 * ch.height as u64));
         let params = MaConfigParams {
             tracker,
+            lz77_tracker,
             node_limit: (1024 + local_samples).min(1 << 20) as usize,
             depth_limit: 2048,
         };
@@ -2,8 +2,8 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 
 use jxl_bitstream::{unpack_signed, Bitstream};
-use jxl_coding::Decoder;
-use jxl_grid::{AllocHandle, AllocTracker};
+use jxl_coding::{Decoder, Lz77Tracker};
+use jxl_grid::{AllocCategory, AllocHandle, AllocTracker};
 use jxl_oxide_common::Bundle;
 
 use super::predictor::{Predictor, Properties};
@@ -20,6 +20,7 @@ pub struct MaConfig {
     tree_depth: usize,
     tree: Arc<(MaTreeNode, Option<AllocHandle>)>,
     decoder: Decoder,
+    lz77_tracker: Option<Lz77Tracker>,
 }
 
 impl MaConfig {
@@ -53,6 +54,12 @@ impl MaConfig {
     pub fn tree_depth(&self) -> usize {
         self.tree_depth
     }
+
+    /// Returns the LZ77 tracker attached to the entropy decoders.
+    #[inline]
+    pub fn lz77_tracker(&self) -> Option<&Lz77Tracker> {
+        self.lz77_tracker.as_ref()
+    }
 }
 
 /// Parameters for decoding [`MaConfig`].
@@ -60,6 +67,8 @@ impl MaConfig {
 pub struct MaConfigParams<'a> {
     /// Allocation tracker.
     pub tracker: Option<&'a AllocTracker>,
+    /// LZ77 tracker, attached to the entropy decoders.
+    pub lz77_tracker: Option<&'a Lz77Tracker>,
     /// Maximum number of meta-adaptive tree nodes.
     pub node_limit: usize,
     pub depth_limit: usize,
@@ -83,11 +92,17 @@ impl Bundle<MaConfigParams<'_>> for MaConfig {
 
         let MaConfigParams {
             tracker,
+            lz77_tracker,
             node_limit,
             depth_limit,
         } = params;
+        let tracker = tracker.map(|tracker| tracker.with_category(AllocCategory::Modular));
+        let tracker = tracker.as_ref();
 
         let mut tree_decoder = Decoder::parse(bitstream, 6)?;
+        if let Some(lz77_tracker) = lz77_tracker {
+            tree_decoder.set_lz77_tracker(lz77_tracker);
+        }
         if is_infinite_tree_dist(&tree_decoder) {
             tracing::error!("Infinite MA tree");
             return Err(crate::Error::InvalidMaTree);
@@ -165,7 +180,10 @@ impl Bundle<MaConfigParams<'_>> for MaConfig {
         }
         tree_decoder.finalize()?;
         let num_tree_nodes = nodes.len();
-        let decoder = Decoder::parse(bitstream, ctx)?;
+        let mut decoder = Decoder::parse(bitstream, ctx)?;
+        if let Some(lz77_tracker) = lz77_tracker {
+            decoder.set_lz77_tracker(lz77_tracker);
+        }
         let cluster_map = decoder.cluster_map();
 
         let tree_alloc_handle = tracker
@@ -221,6 +239,7 @@ impl Bundle<MaConfigParams<'_>> for MaConfig {
             tree_depth,
             tree: Arc::new((tree, tree_alloc_handle)),
             decoder,
+            lz77_tracker: lz77_tracker.cloned(),
         })
     }
 }
@@ -1,3 +1,4 @@
+use jxl_coding::Lz77Tracker;
 use jxl_grid::AllocTracker;
 
 use super::MaConfig;
@@ -9,7 +10,11 @@ pub struct ModularParams<'a, 'b> {
     pub channels: Vec<ModularChannelParams>,
     pub ma_config: Option<&'a MaConfig>,
     pub tracker: Option<&'b AllocTracker>,
+    /// LZ77 tracker for local MA trees. Defaults to the one of `ma_config`.
+    pub lz77_tracker: Option<&'b Lz77Tracker>,
     pub narrow_buffer: bool,
+    /// Use portable scalar code instead of SIMD implementations selected at runtime.
+    pub force_scalar: bool,
 }
 
 impl<'a, 'b> ModularParams<'a, 'b> {
@@ -46,7 +51,9 @@ impl<'a, 'b> ModularParams<'a, 'b> {
             channels,
             ma_config,
             tracker,
+            lz77_tracker: None,
             narrow_buffer: false,
+            force_scalar: false,
         }
     }
 }
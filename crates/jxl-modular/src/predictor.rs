@@ -1,3 +1,8 @@
+#[cfg(target_arch = "aarch64")]
+use std::arch::is_aarch64_feature_detected;
+#[cfg(target_arch = "x86_64")]
+use std::arch::is_x86_feature_detected;
+
 use jxl_grid::MutableSubgrid;
 use jxl_oxide_common::define_bundle;
 
@@ -145,6 +150,7 @@ pub struct PredictorState<'prev, 'a, S: Sample> {
     n: i32,
     nw: i32,
     prev_grad: i32,
+    force_scalar: bool,
 }
 
 const DIV_LOOKUP: [u32; 65] = compute_div_lookup();
@@ -160,7 +166,7 @@ const fn compute_div_lookup() -> [u32; 65] {
 }
 
 impl<'prev, 'a, S: Sample> PredictorState<'prev, 'a, S> {
-    pub fn new() -> Self {
+    pub fn new(force_scalar: bool) -> Self {
         Self {
             width: 0,
             prev_row: Vec::new(),
@@ -173,6 +179,7 @@ impl<'prev, 'a, S: Sample> PredictorState<'prev, 'a, S> {
             n: 0,
             nw: 0,
             prev_grad: 0,
+            force_scalar,
         }
     }
 
@@ -182,8 +189,9 @@ impl<'prev, 'a, S: Sample> PredictorState<'prev, 'a, S> {
         prev_channels_rev: &[&'a MutableSubgrid<'prev, S>],
         wp_header: Option<&WpHeader>,
     ) {
+        let simd = self.simd_level();
         self.self_correcting =
-            wp_header.map(|wp_header| SelfCorrectingPredictor::new(width, wp_header.clone()));
+            wp_header.map(|wp_header| SelfCorrectingPredictor::new(width, wp_header.clone(), simd));
         self.prev_row.clear();
         self.curr_row.clear();
         if let Some(additional) = (width as usize).checked_sub(self.prev_row.capacity()) {
@@ -202,6 +210,15 @@ impl<'prev, 'a, S: Sample> PredictorState<'prev, 'a, S> {
         self.prev_grad = 0;
     }
 
+    /// Returns the SIMD instruction set to be used by the predictor.
+    pub(crate) fn simd_level(&self) -> SimdLevel {
+        if self.force_scalar {
+            SimdLevel::Scalar
+        } else {
+            detect_simd()
+        }
+    }
+
     #[inline(always)]
     pub fn properties<'p, const EDGE: bool>(&'p mut self) -> Properties<'p, 'prev, 'a, S> {
         let prediction = self
@@ -286,11 +303,12 @@ struct SelfCorrectingPredictor {
     subpred_err_nw_ww: [u32; 4],
     subpred_err_n_w: [u32; 4],
     subpred_err_ne: [u32; 4],
+    simd: SimdLevel,
 }
 
 impl SelfCorrectingPredictor {
     #[inline(always)]
-    fn new(width: u32, wp_header: WpHeader) -> Self {
+    fn new(width: u32, wp_header: WpHeader, simd: SimdLevel) -> Self {
         Self {
             width,
             x: 0,
@@ -305,6 +323,7 @@ impl SelfCorrectingPredictor {
             subpred_err_nw_ww: [0; 4],
             subpred_err_n_w: [0; 4],
             subpred_err_ne: [0; 4],
+            simd,
         }
     }
 
@@ -344,19 +363,9 @@ impl SelfCorrectingPredictor {
                 >> 5),
         ];
 
-        let mut subpred_err_sum = [0u32; 4];
-        for (i, sum) in subpred_err_sum.iter_mut().enumerate() {
-            *sum = subpred_err_nw_ww[i]
-                .wrapping_add(subpred_err_n_w[i])
-                .wrapping_add(subpred_err_ne[i]);
-        }
-
         let wp_wn = [wp.wp_w0, wp.wp_w1, wp.wp_w2, wp.wp_w3];
-        let mut weight = [0u32; 4];
-        for ((w, err_sum), maxweight) in weight.iter_mut().zip(subpred_err_sum).zip(wp_wn) {
-            let shift = ((err_sum as u64 + 1) >> 5).checked_ilog2().unwrap_or(0);
-            *w = 4 + ((maxweight * DIV_LOOKUP[(err_sum >> shift) as usize + 1]) >> shift);
-        }
+        let mut weight =
+            self.compute_weights(subpred_err_nw_ww, subpred_err_n_w, subpred_err_ne, wp_wn);
 
         let sum_weights: u32 = weight.iter().copied().sum();
         let log_weight = (sum_weights as u64 >> 4).ilog2();
@@ -394,10 +403,7 @@ impl SelfCorrectingPredictor {
     fn record(&mut self, pred: PredictionResult, sample: i32) {
         let sample = sample as i64;
         let true_err = pred.prediction - (sample << 3);
-        let mut subpred_err = [0u32; 4];
-        for (err, subpred) in subpred_err.iter_mut().zip(pred.subpred) {
-            *err = ((subpred.abs_diff(sample << 3) + 3) >> 3) as u32;
-        }
+        let subpred_err = self.compute_subpred_errors(pred.subpred, sample << 3);
 
         self.true_err_row[self.x as usize] = true_err as i32;
         self.subpred_err_row[self.x as usize] = subpred_err;
@@ -441,6 +447,511 @@ impl SelfCorrectingPredictor {
     }
 }
 
+impl SelfCorrectingPredictor {
+    #[inline(always)]
+    fn compute_weights(
+        &self,
+        err_nw_ww: [u32; 4],
+        err_n_w: [u32; 4],
+        err_ne: [u32; 4],
+        maxweight: [u32; 4],
+    ) -> [u32; 4] {
+        match self.simd {
+            // SAFETY: AVX2 is available.
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2 => unsafe {
+                compute_weights_x86_64_avx2(err_nw_ww, err_n_w, err_ne, maxweight)
+            },
+            // SAFETY: SSE4.1 is available.
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse41 => unsafe {
+                compute_weights_x86_64_sse41(err_nw_ww, err_n_w, err_ne, maxweight)
+            },
+            // SAFETY: NEON is available.
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => unsafe {
+                compute_weights_aarch64_neon(err_nw_ww, err_n_w, err_ne, maxweight)
+            },
+            SimdLevel::Scalar => compute_weights_base(err_nw_ww, err_n_w, err_ne, maxweight),
+        }
+    }
+
+    #[inline(always)]
+    fn compute_subpred_errors(&self, subpred: [i64; 4], sample3: i64) -> [u32; 4] {
+        match self.simd {
+            // SAFETY: AVX2 is available.
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2 => unsafe { compute_subpred_errors_x86_64_avx2(subpred, sample3) },
+            // SAFETY: SSE4.1 is available.
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse41 => unsafe { compute_subpred_errors_x86_64_sse41(subpred, sample3) },
+            // SAFETY: NEON is available.
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => unsafe { compute_subpred_errors_aarch64_neon(subpred, sample3) },
+            SimdLevel::Scalar => compute_subpred_errors_base(subpred, sample3),
+        }
+    }
+}
+
+/// SIMD instruction set used by the predictor, detected at runtime.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SimdLevel {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse41,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+fn detect_simd() -> SimdLevel {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return SimdLevel::Avx2;
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return SimdLevel::Sse41;
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if is_aarch64_feature_detected!("neon") {
+        return SimdLevel::Neon;
+    }
+
+    SimdLevel::Scalar
+}
+
+#[inline(always)]
+fn compute_weights_base(
+    err_nw_ww: [u32; 4],
+    err_n_w: [u32; 4],
+    err_ne: [u32; 4],
+    maxweight: [u32; 4],
+) -> [u32; 4] {
+    let mut weight = [0u32; 4];
+    for i in 0..4 {
+        let err_sum = err_nw_ww[i]
+            .wrapping_add(err_n_w[i])
+            .wrapping_add(err_ne[i]);
+        let shift = ((err_sum as u64 + 1) >> 5).checked_ilog2().unwrap_or(0);
+        weight[i] = 4 + ((maxweight[i] * DIV_LOOKUP[(err_sum >> shift) as usize + 1]) >> shift);
+    }
+    weight
+}
+
+#[inline(always)]
+fn compute_subpred_errors_base(subpred: [i64; 4], sample3: i64) -> [u32; 4] {
+    subpred.map(|subpred| ((subpred.abs_diff(sample3) + 3) >> 3) as u32)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn compute_weights_x86_64_avx2(
+    err_nw_ww: [u32; 4],
+    err_n_w: [u32; 4],
+    err_ne: [u32; 4],
+    maxweight: [u32; 4],
+) -> [u32; 4] {
+    use std::arch::x86_64::*;
+
+    let err_nw_ww = _mm_loadu_si128(err_nw_ww.as_ptr() as *const _);
+    let err_n_w = _mm_loadu_si128(err_n_w.as_ptr() as *const _);
+    let err_ne = _mm_loadu_si128(err_ne.as_ptr() as *const _);
+    let maxweight = _mm_loadu_si128(maxweight.as_ptr() as *const _);
+
+    let err_sum = _mm_add_epi32(_mm_add_epi32(err_nw_ww, err_n_w), err_ne);
+    // `(err_sum + 1) >> 5` without overflowing 32 bits.
+    let carry = _mm_add_epi32(
+        _mm_and_si128(err_sum, _mm_set1_epi32(31)),
+        _mm_set1_epi32(1),
+    );
+    let t = _mm_add_epi32(_mm_srli_epi32::<5>(err_sum), _mm_srli_epi32::<5>(carry));
+
+    // `t` has at most 27 bits, so converting it to f64 is exact, and the exponent is `ilog2(t)`.
+    let exp = _mm256_srli_epi64::<52>(_mm256_castpd_si256(_mm256_cvtepi32_pd(t)));
+    let exp = _mm256_castsi256_si128(_mm256_permutevar8x32_epi32(
+        exp,
+        _mm256_setr_epi32(0, 2, 4, 6, 0, 2, 4, 6),
+    ));
+    // Biased exponent of zero is zero, which is clamped to shift of zero.
+    let shift = _mm_max_epi32(
+        _mm_sub_epi32(exp, _mm_set1_epi32(1023)),
+        _mm_setzero_si128(),
+    );
+
+    let idx = _mm_add_epi32(_mm_srlv_epi32(err_sum, shift), _mm_set1_epi32(1));
+    let lookup = &DIV_LOOKUP;
+    let div = _mm_i32gather_epi32::<4>(lookup.as_ptr() as *const i32, idx);
+    let weight = _mm_srlv_epi32(_mm_mullo_epi32(maxweight, div), shift);
+    let weight = _mm_add_epi32(weight, _mm_set1_epi32(4));
+
+    let mut out = [0u32; 4];
+    _mm_storeu_si128(out.as_mut_ptr() as *mut _, weight);
+    out
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn compute_subpred_errors_x86_64_avx2(subpred: [i64; 4], sample3: i64) -> [u32; 4] {
+    use std::arch::x86_64::*;
+
+    let subpred = _mm256_loadu_si256(subpred.as_ptr() as *const _);
+    let diff = _mm256_sub_epi64(subpred, _mm256_set1_epi64x(sample3));
+    let neg = _mm256_cmpgt_epi64(_mm256_setzero_si256(), diff);
+    let abs = _mm256_sub_epi64(_mm256_xor_si256(diff, neg), neg);
+    let err = _mm256_srli_epi64::<3>(_mm256_add_epi64(abs, _mm256_set1_epi64x(3)));
+    let err = _mm256_castsi256_si128(_mm256_permutevar8x32_epi32(
+        err,
+        _mm256_setr_epi32(0, 2, 4, 6, 0, 2, 4, 6),
+    ));
+
+    let mut out = [0u32; 4];
+    _mm_storeu_si128(out.as_mut_ptr() as *mut _, err);
+    out
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+#[inline]
+unsafe fn compute_weights_x86_64_sse41(
+    err_nw_ww: [u32; 4],
+    err_n_w: [u32; 4],
+    err_ne: [u32; 4],
+    maxweight: [u32; 4],
+) -> [u32; 4] {
+    use std::arch::x86_64::*;
+
+    let err_nw_ww = _mm_loadu_si128(err_nw_ww.as_ptr() as *const _);
+    let err_n_w = _mm_loadu_si128(err_n_w.as_ptr() as *const _);
+    let err_ne = _mm_loadu_si128(err_ne.as_ptr() as *const _);
+    let maxweight = _mm_loadu_si128(maxweight.as_ptr() as *const _);
+
+    let err_sum = _mm_add_epi32(_mm_add_epi32(err_nw_ww, err_n_w), err_ne);
+    // `(err_sum + 1) >> 5` without overflowing 32 bits.
+    let carry = _mm_add_epi32(
+        _mm_and_si128(err_sum, _mm_set1_epi32(31)),
+        _mm_set1_epi32(1),
+    );
+    let t = _mm_add_epi32(_mm_srli_epi32::<5>(err_sum), _mm_srli_epi32::<5>(carry));
+
+    // `t` has at most 27 bits, so converting it to f64 is exact, and the exponent is `ilog2(t)`.
+    let exp_lo = _mm_srli_epi64::<52>(_mm_castpd_si128(_mm_cvtepi32_pd(t)));
+    let exp_hi = _mm_srli_epi64::<52>(_mm_castpd_si128(_mm_cvtepi32_pd(_mm_unpackhi_epi64(t, t))));
+    let exp = _mm_castps_si128(_mm_shuffle_ps::<0b10_00_10_00>(
+        _mm_castsi128_ps(exp_lo),
+        _mm_castsi128_ps(exp_hi),
+    ));
+    // Biased exponent of zero is zero, which is clamped to shift of zero.
+    let shift = _mm_max_epi32(
+        _mm_sub_epi32(exp, _mm_set1_epi32(1023)),
+        _mm_setzero_si128(),
+    );
+
+    let idx = _mm_add_epi32(srlv_epi32_sse41(err_sum, shift), _mm_set1_epi32(1));
+    let mut idx_arr = [0u32; 4];
+    _mm_storeu_si128(idx_arr.as_mut_ptr() as *mut _, idx);
+    let div = idx_arr.map(|idx| DIV_LOOKUP[idx as usize]);
+    let div = _mm_loadu_si128(div.as_ptr() as *const _);
+    let weight = srlv_epi32_sse41(_mm_mullo_epi32(maxweight, div), shift);
+    let weight = _mm_add_epi32(weight, _mm_set1_epi32(4));
+
+    let mut out = [0u32; 4];
+    _mm_storeu_si128(out.as_mut_ptr() as *mut _, weight);
+    out
+}
+
+/// Shifts each lane of `a` right by the corresponding lane of `shift`, which SSE lacks.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+#[inline]
+unsafe fn srlv_epi32_sse41(
+    a: std::arch::x86_64::__m128i,
+    shift: std::arch::x86_64::__m128i,
+) -> std::arch::x86_64::__m128i {
+    use std::arch::x86_64::*;
+
+    let r0 = _mm_srl_epi32(a, _mm_cvtsi32_si128(_mm_extract_epi32::<0>(shift)));
+    let r1 = _mm_srl_epi32(a, _mm_cvtsi32_si128(_mm_extract_epi32::<1>(shift)));
+    let r2 = _mm_srl_epi32(a, _mm_cvtsi32_si128(_mm_extract_epi32::<2>(shift)));
+    let r3 = _mm_srl_epi32(a, _mm_cvtsi32_si128(_mm_extract_epi32::<3>(shift)));
+    let r01 = _mm_blend_epi16::<0b0000_1100>(r0, r1);
+    let r23 = _mm_blend_epi16::<0b1100_0000>(r2, r3);
+    _mm_blend_epi16::<0b1111_0000>(r01, r23)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+#[inline]
+unsafe fn compute_subpred_errors_x86_64_sse41(subpred: [i64; 4], sample3: i64) -> [u32; 4] {
+    use std::arch::x86_64::*;
+
+    #[inline(always)]
+    unsafe fn err(subpred: __m128i, sample3: __m128i) -> __m128i {
+        let diff = _mm_sub_epi64(subpred, sample3);
+        // Sign of 64-bit lanes, taken from the high halves.
+        let neg = _mm_shuffle_epi32::<0b11_11_01_01>(_mm_srai_epi32::<31>(diff));
+        let abs = _mm_sub_epi64(_mm_xor_si128(diff, neg), neg);
+        _mm_srli_epi64::<3>(_mm_add_epi64(abs, _mm_set1_epi64x(3)))
+    }
+
+    let sample3 = _mm_set1_epi64x(sample3);
+    let lo = err(_mm_loadu_si128(subpred.as_ptr() as *const _), sample3);
+    let hi = err(
+        _mm_loadu_si128(subpred.as_ptr().add(2) as *const _),
+        sample3,
+    );
+    let err = _mm_castps_si128(_mm_shuffle_ps::<0b10_00_10_00>(
+        _mm_castsi128_ps(lo),
+        _mm_castsi128_ps(hi),
+    ));
+
+    let mut out = [0u32; 4];
+    _mm_storeu_si128(out.as_mut_ptr() as *mut _, err);
+    out
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+#[inline]
+unsafe fn compute_weights_aarch64_neon(
+    err_nw_ww: [u32; 4],
+    err_n_w: [u32; 4],
+    err_ne: [u32; 4],
+    maxweight: [u32; 4],
+) -> [u32; 4] {
+    use std::arch::aarch64::*;
+
+    let err_nw_ww = vld1q_u32(err_nw_ww.as_ptr());
+    let err_n_w = vld1q_u32(err_n_w.as_ptr());
+    let err_ne = vld1q_u32(err_ne.as_ptr());
+    let maxweight = vld1q_u32(maxweight.as_ptr());
+
+    let err_sum = vaddq_u32(vaddq_u32(err_nw_ww, err_n_w), err_ne);
+    // `(err_sum + 1) >> 5` without overflowing 32 bits.
+    let carry = vaddq_u32(vandq_u32(err_sum, vdupq_n_u32(31)), vdupq_n_u32(1));
+    let t = vaddq_u32(vshrq_n_u32::<5>(err_sum), vshrq_n_u32::<5>(carry));
+
+    // `31 - clz(t)` is `ilog2(t)`; zero yields -1, which is clamped to shift of zero.
+    let shift = vmaxq_s32(
+        vsubq_s32(vdupq_n_s32(31), vreinterpretq_s32_u32(vclzq_u32(t))),
+        vdupq_n_s32(0),
+    );
+    let neg_shift = vnegq_s32(shift);
+
+    let idx = vaddq_u32(vshlq_u32(err_sum, neg_shift), vdupq_n_u32(1));
+    let mut idx_arr = [0u32; 4];
+    vst1q_u32(idx_arr.as_mut_ptr(), idx);
+    let div = idx_arr.map(|idx| DIV_LOOKUP[idx as usize]);
+    let weight = vshlq_u32(vmulq_u32(maxweight, vld1q_u32(div.as_ptr())), neg_shift);
+    let weight = vaddq_u32(weight, vdupq_n_u32(4));
+
+    let mut out = [0u32; 4];
+    vst1q_u32(out.as_mut_ptr(), weight);
+    out
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+#[inline]
+unsafe fn compute_subpred_errors_aarch64_neon(subpred: [i64; 4], sample3: i64) -> [u32; 4] {
+    use std::arch::aarch64::*;
+
+    let sample3 = vdupq_n_s64(sample3);
+    let lo = vabsq_s64(vsubq_s64(vld1q_s64(subpred.as_ptr()), sample3));
+    let hi = vabsq_s64(vsubq_s64(vld1q_s64(subpred.as_ptr().add(2)), sample3));
+    let lo = vshrq_n_u64::<3>(vaddq_u64(vreinterpretq_u64_s64(lo), vdupq_n_u64(3)));
+    let hi = vshrq_n_u64::<3>(vaddq_u64(vreinterpretq_u64_s64(hi), vdupq_n_u64(3)));
+    let err = vcombine_u32(vmovn_u64(lo), vmovn_u64(hi));
+
+    let mut out = [0u32; 4];
+    vst1q_u32(out.as_mut_ptr(), err);
+    out
+}
+
+/// Evaluates MA tree leaves for a row of tokens, of which prediction doesn't depend on the samples
+/// in the same row.
+///
+/// Computes `unpack_signed(token) * multiplier + offset + north` with wrapping arithmetic, where
+/// `north` is zero if it's `None`. This covers Zero predictor and North predictor except for the
+/// first row.
+pub(crate) fn evaluate_leaf_row_i32(
+    simd: SimdLevel,
+    tokens: &[u32],
+    north: Option<&[i32]>,
+    multiplier: i32,
+    offset: i32,
+    out: &mut [i32],
+) {
+    let len = out.len();
+    let tokens = &tokens[..len];
+    let north = north.map(|north| &north[..len]);
+
+    match simd {
+        // SAFETY: AVX2 is available, and slices have the same length.
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe {
+            evaluate_leaf_row_x86_64_avx2(tokens, north, multiplier, offset, out)
+        },
+        // SAFETY: SSE4.1 is available, and slices have the same length.
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse41 => unsafe {
+            evaluate_leaf_row_x86_64_sse41(tokens, north, multiplier, offset, out)
+        },
+        // SAFETY: NEON is available, and slices have the same length.
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe {
+            evaluate_leaf_row_aarch64_neon(tokens, north, multiplier, offset, out)
+        },
+        SimdLevel::Scalar => evaluate_leaf_row_base(tokens, north, multiplier, offset, out),
+    }
+}
+
+/// Scalar version of [`evaluate_leaf_row_i32`], which also handles `i16` samples.
+#[inline]
+pub(crate) fn evaluate_leaf_row_base<S: Sample>(
+    tokens: &[u32],
+    north: Option<&[S]>,
+    multiplier: i32,
+    offset: i32,
+    out: &mut [S],
+) {
+    if let Some(north) = north {
+        for ((out, &token), &n) in out.iter_mut().zip(tokens).zip(north) {
+            *out = S::unpack_signed_u32(token)
+                .wrapping_muladd_i32(multiplier, offset)
+                .add(n);
+        }
+    } else {
+        for (out, &token) in out.iter_mut().zip(tokens) {
+            *out = S::unpack_signed_u32(token).wrapping_muladd_i32(multiplier, offset);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn evaluate_leaf_row_x86_64_avx2(
+    tokens: &[u32],
+    north: Option<&[i32]>,
+    multiplier: i32,
+    offset: i32,
+    out: &mut [i32],
+) {
+    use std::arch::x86_64::*;
+
+    let len = out.len();
+    let mul = _mm256_set1_epi32(multiplier);
+    let add = _mm256_set1_epi32(offset);
+    let one = _mm256_set1_epi32(1);
+
+    let mut x = 0usize;
+    while x + 8 <= len {
+        let token = _mm256_loadu_si256(tokens.as_ptr().add(x) as *const _);
+        // `unpack_signed(token)` is `(token >> 1) ^ -(token & 1)`.
+        let neg = _mm256_sub_epi32(_mm256_setzero_si256(), _mm256_and_si256(token, one));
+        let diff = _mm256_xor_si256(_mm256_srli_epi32::<1>(token), neg);
+        let mut v = _mm256_add_epi32(_mm256_mullo_epi32(diff, mul), add);
+        if let Some(north) = north {
+            let n = _mm256_loadu_si256(north.as_ptr().add(x) as *const _);
+            v = _mm256_add_epi32(v, n);
+        }
+        _mm256_storeu_si256(out.as_mut_ptr().add(x) as *mut _, v);
+        x += 8;
+    }
+
+    evaluate_leaf_row_base(
+        &tokens[x..],
+        north.map(|north| &north[x..]),
+        multiplier,
+        offset,
+        &mut out[x..],
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn evaluate_leaf_row_x86_64_sse41(
+    tokens: &[u32],
+    north: Option<&[i32]>,
+    multiplier: i32,
+    offset: i32,
+    out: &mut [i32],
+) {
+    use std::arch::x86_64::*;
+
+    let len = out.len();
+    let mul = _mm_set1_epi32(multiplier);
+    let add = _mm_set1_epi32(offset);
+    let one = _mm_set1_epi32(1);
+
+    let mut x = 0usize;
+    while x + 4 <= len {
+        let token = _mm_loadu_si128(tokens.as_ptr().add(x) as *const _);
+        // `unpack_signed(token)` is `(token >> 1) ^ -(token & 1)`.
+        let neg = _mm_sub_epi32(_mm_setzero_si128(), _mm_and_si128(token, one));
+        let diff = _mm_xor_si128(_mm_srli_epi32::<1>(token), neg);
+        let mut v = _mm_add_epi32(_mm_mullo_epi32(diff, mul), add);
+        if let Some(north) = north {
+            let n = _mm_loadu_si128(north.as_ptr().add(x) as *const _);
+            v = _mm_add_epi32(v, n);
+        }
+        _mm_storeu_si128(out.as_mut_ptr().add(x) as *mut _, v);
+        x += 4;
+    }
+
+    evaluate_leaf_row_base(
+        &tokens[x..],
+        north.map(|north| &north[x..]),
+        multiplier,
+        offset,
+        &mut out[x..],
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn evaluate_leaf_row_aarch64_neon(
+    tokens: &[u32],
+    north: Option<&[i32]>,
+    multiplier: i32,
+    offset: i32,
+    out: &mut [i32],
+) {
+    use std::arch::aarch64::*;
+
+    let len = out.len();
+    let mul = vdupq_n_s32(multiplier);
+    let add = vdupq_n_s32(offset);
+    let one = vdupq_n_u32(1);
+
+    let mut x = 0usize;
+    while x + 4 <= len {
+        let token = vld1q_u32(tokens.as_ptr().add(x));
+        // `unpack_signed(token)` is `(token >> 1) ^ -(token & 1)`.
+        let neg = vnegq_s32(vreinterpretq_s32_u32(vandq_u32(token, one)));
+        let diff = veorq_s32(vreinterpretq_s32_u32(vshrq_n_u32::<1>(token)), neg);
+        let mut v = vmlaq_s32(add, diff, mul);
+        if let Some(north) = north {
+            v = vaddq_s32(v, vld1q_s32(north.as_ptr().add(x)));
+        }
+        vst1q_s32(out.as_mut_ptr().add(x), v);
+        x += 4;
+    }
+
+    evaluate_leaf_row_base(
+        &tokens[x..],
+        north.map(|north| &north[x..]),
+        multiplier,
+        offset,
+        &mut out[x..],
+    );
+}
+
 #[derive(Debug)]
 pub struct Properties<'p, 'prev, 'a, S: Sample> {
     predictor: &'p mut PredictorState<'prev, 'a, S>,
@@ -576,3 +1087,96 @@ impl<S: Sample> Properties<'_, '_, '_, S> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_oxide_common::BundleDefault;
+
+    use super::*;
+
+    /// Returns SIMD levels supported by the CPU, so that every SIMD path is tested against the
+    /// scalar one.
+    fn supported_simd_levels() -> Vec<SimdLevel> {
+        #[allow(unused_mut)]
+        let mut levels = Vec::new();
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("sse4.1") {
+                levels.push(SimdLevel::Sse41);
+            }
+            if is_x86_feature_detected!("avx2") {
+                levels.push(SimdLevel::Avx2);
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        if is_aarch64_feature_detected!("neon") {
+            levels.push(SimdLevel::Neon);
+        }
+        levels
+    }
+
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    #[test]
+    fn simd_weights_match_scalar() {
+        for simd in supported_simd_levels() {
+            let mut state = 0x2545f491u32;
+            let mut next = || {
+                let state = xorshift(&mut state);
+                // Mix small errors with large ones near 32-bit overflow.
+                match state % 4 {
+                    0 => state % 64,
+                    1 => state % 4096,
+                    2 => state,
+                    _ => u32::MAX - state % 64,
+                }
+            };
+            let sc = SelfCorrectingPredictor::new(1, WpHeader::default_with_context(()), simd);
+            for _ in 0..10000 {
+                let errs: [[u32; 4]; 3] = std::array::from_fn(|_| std::array::from_fn(|_| next()));
+                let maxweight = std::array::from_fn(|_| next() % 16);
+                assert_eq!(
+                    sc.compute_weights(errs[0], errs[1], errs[2], maxweight),
+                    compute_weights_base(errs[0], errs[1], errs[2], maxweight),
+                    "{simd:?}",
+                );
+
+                let subpred = std::array::from_fn(|_| (next() as i32 as i64) << 3);
+                let sample3 = (next() as i32 as i64) << 3;
+                assert_eq!(
+                    sc.compute_subpred_errors(subpred, sample3),
+                    compute_subpred_errors_base(subpred, sample3),
+                    "{simd:?}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn simd_leaf_row_matches_scalar() {
+        let mut state = 0x2545f491u32;
+        // Lengths not multiple of vector width, to cover the remainder.
+        let len = 1003;
+        let tokens = (0..len).map(|_| xorshift(&mut state)).collect::<Vec<_>>();
+        let north = (0..len)
+            .map(|_| xorshift(&mut state) as i32)
+            .collect::<Vec<_>>();
+
+        for simd in supported_simd_levels() {
+            for (multiplier, offset) in [(1, 0), (3, -5), (-7, i32::MAX)] {
+                for north in [None, Some(&*north)] {
+                    let mut expected = vec![0i32; len];
+                    evaluate_leaf_row_base(&tokens, north, multiplier, offset, &mut expected);
+                    let mut actual = vec![0i32; len];
+                    evaluate_leaf_row_i32(simd, &tokens, north, multiplier, offset, &mut actual);
+                    assert_eq!(actual, expected, "{simd:?}");
+                }
+            }
+        }
+    }
+}
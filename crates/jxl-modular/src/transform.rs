@@ -1,5 +1,7 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use jxl_bitstream::{Bitstream, U};
-use jxl_grid::{AlignedGrid, AllocTracker, MutableSubgrid};
+use jxl_grid::{AlignedGrid, AllocCategory, AllocTracker, MutableSubgrid};
 use jxl_oxide_common::{define_bundle, Bundle};
 use jxl_threadpool::JxlThreadPool;
 
@@ -43,12 +45,13 @@ impl TransformInfo {
         tracker: Option<&AllocTracker>,
     ) -> Result<()> {
         if let Self::Palette(pal) = self {
+            let tracker = tracker.map(|tracker| tracker.with_category(AllocCategory::Modular));
             meta_channels.insert(
                 0,
                 AlignedGrid::with_alloc_tracker(
                     pal.nb_colours as usize,
                     pal.num_c as usize,
-                    tracker,
+                    tracker.as_ref(),
                 )?,
             );
         }
@@ -70,16 +73,24 @@ impl TransformInfo {
         }
     }
 
+    /// Applies the inverse transform, and returns the number of samples clamped due to overflow.
     pub(super) fn inverse<S: Sample>(
         &self,
         grids: &mut Vec<TransformedGrid<'_, S>>,
         bit_depth: u32,
         pool: &JxlThreadPool,
-    ) {
+        force_scalar: bool,
+    ) -> usize {
         match self {
-            Self::Rct(rct) => rct.inverse(grids, pool),
-            Self::Palette(pal) => pal.inverse(grids, bit_depth),
-            Self::Squeeze(sq) => sq.inverse(grids, pool),
+            Self::Rct(rct) => {
+                rct.inverse(grids, pool, force_scalar);
+                0
+            }
+            Self::Palette(pal) => {
+                pal.inverse(grids, bit_depth, pool, force_scalar);
+                0
+            }
+            Self::Squeeze(sq) => sq.inverse(grids, pool, force_scalar),
         }
     }
 
@@ -183,7 +194,12 @@ impl Rct {
         Ok(())
     }
 
-    fn inverse<S: Sample>(&self, grids: &mut [TransformedGrid<'_, S>], pool: &JxlThreadPool) {
+    fn inverse<S: Sample>(
+        &self,
+        grids: &mut [TransformedGrid<'_, S>],
+        pool: &JxlThreadPool,
+        force_scalar: bool,
+    ) {
         let permutation = self.rct_type / 7;
         let ty = self.rct_type % 7;
 
@@ -195,13 +211,13 @@ impl Rct {
         let grids = [a, b, c];
 
         match ty {
-            0 => rct::inverse_rct::<_, 0>(permutation, grids, pool),
-            1 => rct::inverse_rct::<_, 1>(permutation, grids, pool),
-            2 => rct::inverse_rct::<_, 2>(permutation, grids, pool),
-            3 => rct::inverse_rct::<_, 3>(permutation, grids, pool),
-            4 => rct::inverse_rct::<_, 4>(permutation, grids, pool),
-            5 => rct::inverse_rct::<_, 5>(permutation, grids, pool),
-            6 => rct::inverse_rct::<_, 6>(permutation, grids, pool),
+            0 => rct::inverse_rct::<_, 0>(permutation, grids, pool, force_scalar),
+            1 => rct::inverse_rct::<_, 1>(permutation, grids, pool, force_scalar),
+            2 => rct::inverse_rct::<_, 2>(permutation, grids, pool, force_scalar),
+            3 => rct::inverse_rct::<_, 3>(permutation, grids, pool, force_scalar),
+            4 => rct::inverse_rct::<_, 4>(permutation, grids, pool, force_scalar),
+            5 => rct::inverse_rct::<_, 5>(permutation, grids, pool, force_scalar),
+            6 => rct::inverse_rct::<_, 6>(permutation, grids, pool, force_scalar),
             _ => unreachable!(),
         }
     }
@@ -255,7 +271,13 @@ impl Palette {
         Ok(())
     }
 
-    fn inverse<S: Sample>(&self, grids: &mut Vec<TransformedGrid<'_, S>>, bit_depth: u32) {
+    fn inverse<S: Sample>(
+        &self,
+        grids: &mut Vec<TransformedGrid<'_, S>>,
+        bit_depth: u32,
+        pool: &JxlThreadPool,
+        force_scalar: bool,
+    ) {
         let begin_c = self.begin_c as usize;
         let num_c = self.num_c as usize;
 
@@ -271,7 +293,7 @@ impl Palette {
             targets.push(member.grid_mut().borrow_mut());
         }
 
-        self.inverse_inner(palette, targets, bit_depth);
+        self.inverse_inner(palette, targets, bit_depth, pool, force_scalar);
 
         for (i, grid) in members.into_iter().enumerate() {
             grids.insert(begin_c + 1 + i, grid);
@@ -434,7 +456,13 @@ impl Squeeze {
         Ok(())
     }
 
-    fn inverse<S: Sample>(&self, grids: &mut Vec<TransformedGrid<'_, S>>, pool: &JxlThreadPool) {
+    fn inverse<S: Sample>(
+        &self,
+        grids: &mut Vec<TransformedGrid<'_, S>>,
+        pool: &JxlThreadPool,
+        force_scalar: bool,
+    ) -> usize {
+        let mut clamped = 0usize;
         for sp in self.sp.iter().rev() {
             let begin = sp.begin_c as usize;
             let channel_count = sp.num_c as usize;
@@ -446,9 +474,10 @@ impl Squeeze {
             };
 
             for (ch, residu) in grids[begin..end].iter_mut().zip(residual_channels) {
-                sp.inverse(ch, residu, pool);
+                clamped += sp.inverse(ch, residu, pool, force_scalar);
             }
         }
+        clamped
     }
 }
 
@@ -458,11 +487,13 @@ impl SqueezeParams {
         i0: &mut TransformedGrid<'dest, S>,
         i1: TransformedGrid<'dest, S>,
         pool: &jxl_threadpool::JxlThreadPool,
-    ) {
+        force_scalar: bool,
+    ) -> usize {
         let i0 = i0.grid_mut();
         let TransformedGrid::Single(i1) = i1 else {
             panic!("residual channel should be Single channel")
         };
+        let mut clamped = AtomicUsize::new(0);
         if self.horizontal {
             i0.merge_horizontal_in_place(i1);
             let width = i0.width();
@@ -470,10 +501,11 @@ impl SqueezeParams {
             if height > 16 {
                 let remaining = i0.split_vertical(0).1;
                 pool.for_each_vec(remaining.into_groups(width, 16), |mut group| {
-                    squeeze::inverse_h(&mut group)
+                    let count = squeeze::inverse_h(&mut group, force_scalar);
+                    clamped.fetch_add(count, Ordering::Relaxed);
                 });
             } else {
-                squeeze::inverse_h(i0);
+                *clamped.get_mut() = squeeze::inverse_h(i0, force_scalar);
             }
         } else {
             i0.merge_vertical_in_place(i1);
@@ -482,11 +514,54 @@ impl SqueezeParams {
             if width > 16 {
                 let remaining = i0.split_horizontal(0).1;
                 pool.for_each_vec(remaining.into_groups(16, height), |mut group| {
-                    squeeze::inverse_v(&mut group)
+                    let count = squeeze::inverse_v(&mut group, force_scalar);
+                    clamped.fetch_add(count, Ordering::Relaxed);
                 });
             } else {
-                squeeze::inverse_v(i0);
+                *clamped.get_mut() = squeeze::inverse_v(i0, force_scalar);
             }
         }
+
+        let clamped = clamped.into_inner();
+        if clamped != 0 {
+            tracing::warn!(
+                clamped,
+                horizontal = self.horizontal,
+                "Inverse squeeze overflowed, samples are clamped"
+            );
+        }
+        clamped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squeeze_counts_clamped_samples() {
+        let squeeze = TransformInfo::Squeeze(Squeeze {
+            num_sq: 1,
+            sp: vec![SqueezeParams {
+                horizontal: true,
+                in_place: true,
+                begin_c: 0,
+                num_c: 1,
+            }],
+        });
+
+        let mut buf = vec![i32::MAX, i32::MAX, i32::MAX, i32::MIN];
+        let mut avg = MutableSubgrid::from_buf(&mut buf, 4, 1, 4);
+        let residu = avg.split_horizontal_in_place(2);
+        let mut grids = vec![TransformedGrid::from(avg), TransformedGrid::from(residu)];
+        let clamped = squeeze.inverse(&mut grids, 32, &JxlThreadPool::none(), false);
+        assert_ne!(clamped, 0);
+
+        let mut buf = vec![1i32, 2, 0, 0];
+        let mut avg = MutableSubgrid::from_buf(&mut buf, 4, 1, 4);
+        let residu = avg.split_horizontal_in_place(2);
+        let mut grids = vec![TransformedGrid::from(avg), TransformedGrid::from(residu)];
+        let clamped = squeeze.inverse(&mut grids, 32, &JxlThreadPool::none(), false);
+        assert_eq!(clamped, 0);
     }
 }
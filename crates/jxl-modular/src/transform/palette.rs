@@ -1,4 +1,7 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use jxl_grid::{MutableSubgrid, SharedSubgrid};
+use jxl_threadpool::JxlThreadPool;
 
 use crate::{
     predictor::{Predictor, PredictorState},
@@ -29,22 +32,41 @@ impl Palette {
         palette: SharedSubgrid<S>,
         mut targets: Vec<MutableSubgrid<S>>,
         bit_depth: u32,
+        pool: &JxlThreadPool,
+        force_scalar: bool,
     ) {
         let nb_deltas = self.nb_deltas as i32;
         let nb_colors = self.nb_colours as i32;
 
         let is_simple = {
             let index_grid = targets[0].as_shared();
+            let width = index_grid.width();
             let height = index_grid.height();
-            (0..height).all(|y| {
-                let row = index_grid.get_row(y);
-                row.iter()
-                    .all(|&index| (0..nb_colors).contains(&index.to_i32()))
-            })
+            let has_complex = AtomicBool::new(false);
+            let bands = if width == 0 {
+                Vec::new()
+            } else {
+                (0..height).step_by(BAND_HEIGHT).collect::<Vec<_>>()
+            };
+            pool.for_each_vec(bands, |y0| {
+                if has_complex.load(Ordering::Relaxed) {
+                    return;
+                }
+                let band = index_grid.subgrid(0..width, y0..(y0 + BAND_HEIGHT).min(height));
+                let is_band_simple = (0..band.height()).all(|y| {
+                    band.get_row(y)
+                        .iter()
+                        .all(|&index| (0..nb_colors).contains(&index.to_i32()))
+                });
+                if !is_band_simple {
+                    has_complex.store(true, Ordering::Relaxed);
+                }
+            });
+            !has_complex.into_inner()
         };
 
         if is_simple {
-            return inverse_simple(palette, targets);
+            return inverse_simple(palette, targets, pool);
         }
 
         tracing::trace!("Inverse palette, slow path");
@@ -115,7 +137,7 @@ impl Palette {
         } else {
             None
         };
-        let mut predictor = PredictorState::<S>::new();
+        let mut predictor = PredictorState::<S>::new(force_scalar);
 
         'outer: for mut grid in targets {
             predictor.reset(width as u32, &[], wp_header);
@@ -142,32 +164,96 @@ impl Palette {
     }
 }
 
+/// Height of row bands processed in parallel by the fast path.
+const BAND_HEIGHT: usize = 16;
+
 #[inline(never)]
-fn inverse_simple<S: Sample>(palette: SharedSubgrid<S>, targets: Vec<MutableSubgrid<S>>) {
+fn inverse_simple<S: Sample>(
+    palette: SharedSubgrid<S>,
+    targets: Vec<MutableSubgrid<S>>,
+    pool: &JxlThreadPool,
+) {
+    let width = targets[0].width();
     let height = targets[0].height();
     let channels = targets.len();
     assert_eq!(channels, palette.height());
+    if width == 0 || height == 0 {
+        return;
+    }
 
     tracing::trace!("Inverse palette, fast path");
 
-    let mut targets_it = targets.into_iter().enumerate();
-    let (_, mut index_grid) = targets_it.next().unwrap();
-    for (c, mut grid) in targets_it {
-        let palette = palette.get_row(c);
+    // Split every channel into row bands, and collect bands at the same position into a job.
+    let mut bands = targets
+        .into_iter()
+        .map(|g| g.into_groups(width, BAND_HEIGHT).into_iter())
+        .collect::<Vec<_>>();
+    let num_bands = height.div_ceil(BAND_HEIGHT);
+    let jobs = (0..num_bands)
+        .map(|_| {
+            bands
+                .iter_mut()
+                .map(|it| it.next().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    pool.for_each_vec(jobs, |mut job| {
+        let height = job[0].height();
+        let (index_grid, grids) = job.split_first_mut().unwrap();
         for y in 0..height {
             let index_row = index_grid.get_row(y);
-            let grid_row = grid.get_row_mut(y);
-            for (index, sample) in index_row.iter().zip(grid_row) {
-                *sample = palette[index.to_i32() as usize];
+            for (c, grid) in grids.iter_mut().enumerate() {
+                let palette = palette.get_row(c + 1);
+                let grid_row = grid.get_row_mut(y);
+                for (index, sample) in index_row.iter().zip(grid_row) {
+                    *sample = palette[index.to_i32() as usize];
+                }
+            }
+
+            // Index channel is overwritten last.
+            let palette = palette.get_row(0);
+            for sample in index_grid.get_row_mut(y) {
+                *sample = palette[sample.to_i32() as usize];
             }
         }
-    }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let palette = palette.get_row(0);
-    for y in 0..height {
-        let grid_row = index_grid.get_row_mut(y);
-        for sample in grid_row {
-            *sample = palette[sample.to_i32() as usize];
+    #[test]
+    fn simple_multiple_bands() {
+        let width = 7;
+        let height = BAND_HEIGHT * 2 + 3;
+        let nb_colors = 5;
+        // Palette of three channels, where channel `c` of color `i` is `i * 10 + c`.
+        let palette = (0..3)
+            .flat_map(|c| (0..nb_colors).map(move |i| i * 10 + c))
+            .collect::<Vec<i32>>();
+        let palette = SharedSubgrid::from_buf(&palette, nb_colors as usize, 3, nb_colors as usize);
+
+        let indices = (0..width * height)
+            .map(|i| (i * 7 % nb_colors as usize) as i32)
+            .collect::<Vec<_>>();
+        let mut bufs = [
+            indices.clone(),
+            vec![0; width * height],
+            vec![0; width * height],
+        ];
+        let targets = bufs
+            .iter_mut()
+            .map(|buf| MutableSubgrid::from_buf(buf, width, height, width))
+            .collect();
+
+        inverse_simple(palette, targets, &JxlThreadPool::none());
+
+        for (c, buf) in bufs.iter().enumerate() {
+            for (&index, &sample) in indices.iter().zip(buf) {
+                assert_eq!(sample, index * 10 + c as i32);
+            }
         }
     }
 }
@@ -16,13 +16,14 @@ pub fn inverse_rct<S: Sample, const TYPE: u32>(
     permutation: u32,
     mut grids: [&mut MutableSubgrid<S>; 3],
     pool: &JxlThreadPool,
+    force_scalar: bool,
 ) {
     let grid16 = grids.each_mut().map(|g| S::try_as_mutable_subgrid_i16(g));
     if let [Some(a), Some(b), Some(c)] = grid16 {
         let grids = [a, b, c];
 
         #[cfg(target_arch = "x86_64")]
-        if is_x86_feature_detected!("avx2") {
+        if !force_scalar && is_x86_feature_detected!("avx2") {
             unsafe {
                 run_rows_unsafe(
                     permutation,
@@ -35,7 +36,7 @@ pub fn inverse_rct<S: Sample, const TYPE: u32>(
         }
 
         #[cfg(target_arch = "aarch64")]
-        if is_aarch64_feature_detected!("neon") {
+        if !force_scalar && is_aarch64_feature_detected!("neon") {
             unsafe {
                 run_rows_unsafe(
                     permutation,
@@ -56,7 +57,7 @@ pub fn inverse_rct<S: Sample, const TYPE: u32>(
         let grids = [a, b, c];
 
         #[cfg(target_arch = "x86_64")]
-        if is_x86_feature_detected!("avx2") {
+        if !force_scalar && is_x86_feature_detected!("avx2") {
             unsafe {
                 run_rows_unsafe(
                     permutation,
@@ -69,7 +70,7 @@ pub fn inverse_rct<S: Sample, const TYPE: u32>(
         }
 
         #[cfg(target_arch = "aarch64")]
-        if is_aarch64_feature_detected!("neon") {
+        if !force_scalar && is_aarch64_feature_detected!("neon") {
             unsafe {
                 run_rows_unsafe(
                     permutation,
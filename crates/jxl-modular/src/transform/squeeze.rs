@@ -8,23 +8,27 @@ use jxl_grid::MutableSubgrid;
 
 use crate::Sample;
 
-pub fn inverse_h<S: Sample>(merged: &mut MutableSubgrid<'_, S>) {
+/// Applies inverse horizontal squeeze, returning the number of samples clamped due to overflow.
+pub fn inverse_h<S: Sample>(merged: &mut MutableSubgrid<'_, S>, force_scalar: bool) -> usize {
     if let Some(merged) = S::try_as_mutable_subgrid_i16(merged) {
-        inverse_h_i16(merged)
+        inverse_h_i16(merged, force_scalar);
+        0
     } else if let Some(merged) = S::try_as_mutable_subgrid_i32(merged) {
         inverse_h_i32(merged)
+    } else {
+        0
     }
 }
 
-fn inverse_h_i32(merged: &mut MutableSubgrid<i32>) {
+fn inverse_h_i32(merged: &mut MutableSubgrid<i32>) -> usize {
     inverse_h_i32_base(merged)
 }
 
 #[allow(unreachable_code)]
-fn inverse_h_i16(merged: &mut MutableSubgrid<i16>) {
+fn inverse_h_i16(merged: &mut MutableSubgrid<i16>, force_scalar: bool) {
     #[cfg(target_arch = "x86_64")]
-    if is_x86_feature_detected!("sse4.1") && is_x86_feature_detected!("sse2") {
-        if is_x86_feature_detected!("avx2") {
+    if !force_scalar && is_x86_feature_detected!("sse4.1") && is_x86_feature_detected!("sse2") {
+        if !force_scalar && is_x86_feature_detected!("avx2") {
             unsafe {
                 inverse_h_i16_x86_64_avx2(merged);
                 return;
@@ -38,7 +42,7 @@ fn inverse_h_i16(merged: &mut MutableSubgrid<i16>) {
     }
 
     #[cfg(target_arch = "aarch64")]
-    if is_aarch64_feature_detected!("neon") {
+    if !force_scalar && is_aarch64_feature_detected!("neon") {
         unsafe {
             inverse_h_i16_aarch64_neon(merged);
             return;
@@ -46,7 +50,7 @@ fn inverse_h_i16(merged: &mut MutableSubgrid<i16>) {
     }
 
     #[cfg(all(target_family = "wasm", target_feature = "simd128"))]
-    {
+    if !force_scalar {
         unsafe {
             inverse_h_i16_wasm32_simd128(merged);
             return;
@@ -56,11 +60,12 @@ fn inverse_h_i16(merged: &mut MutableSubgrid<i16>) {
     inverse_h_i16_base(merged)
 }
 
-fn inverse_h_i32_base(merged: &mut MutableSubgrid<'_, i32>) {
+fn inverse_h_i32_base(merged: &mut MutableSubgrid<'_, i32>) -> usize {
     let height = merged.height();
     let width = merged.width();
     let mut scratch = vec![0i32; width];
     let avg_width = (width + 1) / 2;
+    let mut clamped = 0usize;
     for y in 0..height {
         let row_out = merged.get_row_mut(y);
         scratch.copy_from_slice(row_out);
@@ -72,9 +77,7 @@ fn inverse_h_i32_base(merged: &mut MutableSubgrid<'_, i32>) {
         for (x, pair) in (&mut row_out_it).enumerate() {
             let residu = residu_row[x];
             let next_avg = avg_row.get(x + 1).copied().unwrap_or(avg);
-            let diff = residu.wrapping_add(tendency_i32(left, avg, next_avg));
-            let first = avg.wrapping_add(diff / 2);
-            let second = first.wrapping_sub(diff);
+            let (first, second) = unsqueeze_i32(avg, residu, left, next_avg, &mut clamped);
             pair[0] = first;
             pair[1] = second;
             avg = next_avg;
@@ -85,6 +88,7 @@ fn inverse_h_i32_base(merged: &mut MutableSubgrid<'_, i32>) {
             *v = avg_row[avg_width - 1];
         }
     }
+    clamped
 }
 
 #[inline(never)]
@@ -752,23 +756,27 @@ unsafe fn inverse_h_i16_wasm32_simd128(merged: &mut MutableSubgrid<'_, i16>) {
     }
 }
 
-pub fn inverse_v<S: Sample>(merged: &mut MutableSubgrid<'_, S>) {
+/// Applies inverse vertical squeeze, returning the number of samples clamped due to overflow.
+pub fn inverse_v<S: Sample>(merged: &mut MutableSubgrid<'_, S>, force_scalar: bool) -> usize {
     if let Some(merged) = S::try_as_mutable_subgrid_i16(merged) {
-        inverse_v_i16(merged)
+        inverse_v_i16(merged, force_scalar);
+        0
     } else if let Some(merged) = S::try_as_mutable_subgrid_i32(merged) {
         inverse_v_i32(merged)
+    } else {
+        0
     }
 }
 
-fn inverse_v_i32(merged: &mut MutableSubgrid<i32>) {
+fn inverse_v_i32(merged: &mut MutableSubgrid<i32>) -> usize {
     inverse_v_i32_base(merged)
 }
 
 #[allow(unreachable_code)]
-fn inverse_v_i16(merged: &mut MutableSubgrid<i16>) {
+fn inverse_v_i16(merged: &mut MutableSubgrid<i16>, force_scalar: bool) {
     #[cfg(target_arch = "x86_64")]
-    if is_x86_feature_detected!("sse4.1") && is_x86_feature_detected!("sse2") {
-        if is_x86_feature_detected!("avx2") {
+    if !force_scalar && is_x86_feature_detected!("sse4.1") && is_x86_feature_detected!("sse2") {
+        if !force_scalar && is_x86_feature_detected!("avx2") {
             unsafe {
                 inverse_v_i16_x86_64_avx2(merged);
                 return;
@@ -782,7 +790,7 @@ fn inverse_v_i16(merged: &mut MutableSubgrid<i16>) {
     }
 
     #[cfg(target_arch = "aarch64")]
-    if is_aarch64_feature_detected!("neon") {
+    if !force_scalar && is_aarch64_feature_detected!("neon") {
         unsafe {
             inverse_v_i16_aarch64_neon(merged);
             return;
@@ -790,7 +798,7 @@ fn inverse_v_i16(merged: &mut MutableSubgrid<i16>) {
     }
 
     #[cfg(all(target_family = "wasm", target_feature = "simd128"))]
-    {
+    if !force_scalar {
         unsafe {
             inverse_v_i16_wasm32_simd128(merged);
             return;
@@ -800,11 +808,12 @@ fn inverse_v_i16(merged: &mut MutableSubgrid<i16>) {
     inverse_v_i16_base(merged)
 }
 
-fn inverse_v_i32_base(merged: &mut MutableSubgrid<'_, i32>) {
+fn inverse_v_i32_base(merged: &mut MutableSubgrid<'_, i32>) -> usize {
     let width = merged.width();
     let height = merged.height();
     let mut scratch = vec![0i32; height];
     let avg_height = (height + 1) / 2;
+    let mut clamped = 0usize;
     for x in 0..width {
         for (y, v) in scratch.iter_mut().enumerate() {
             *v = merged.get(x, y);
@@ -815,9 +824,7 @@ fn inverse_v_i32_base(merged: &mut MutableSubgrid<'_, i32>) {
         let mut top = avg;
         for (y, &residu) in residu_col.iter().enumerate() {
             let next_avg = avg_col.get(y + 1).copied().unwrap_or(avg);
-            let diff = residu.wrapping_add(tendency_i32(top, avg, next_avg));
-            let first = avg.wrapping_add(diff / 2);
-            let second = first.wrapping_sub(diff);
+            let (first, second) = unsqueeze_i32(avg, residu, top, next_avg, &mut clamped);
             *merged.get_mut(x, 2 * y) = first;
             *merged.get_mut(x, 2 * y + 1) = second;
             avg = next_avg;
@@ -828,6 +835,7 @@ fn inverse_v_i32_base(merged: &mut MutableSubgrid<'_, i32>) {
             *merged.get_mut(x, height - 1) = avg_col[avg_height - 1];
         }
     }
+    clamped
 }
 
 #[inline(never)]
@@ -1101,36 +1109,55 @@ unsafe fn inverse_v_i16_wasm32_simd128(merged: &mut MutableSubgrid<'_, i16>) {
     }
 }
 
-fn tendency_i32(a: i32, b: i32, c: i32) -> i32 {
-    let a = Wrapping(a);
-    let b = Wrapping(b);
-    let c = Wrapping(c);
+/// Reconstructs a pair of samples from the average and the residual.
+///
+/// Computation is done in 64-bit integers, which cannot overflow with 32-bit inputs. Results
+/// which don't fit in 32 bits are clamped, incrementing `clamped`.
+#[inline]
+fn unsqueeze_i32(
+    avg: i32,
+    residu: i32,
+    prev: i32,
+    next_avg: i32,
+    clamped: &mut usize,
+) -> (i32, i32) {
+    let avg = avg as i64;
+    let diff = residu as i64 + tendency_i64(prev as i64, avg, next_avg as i64);
+    let first = avg + diff / 2;
+    let second = first - diff;
+    (clamp_i32(first, clamped), clamp_i32(second, clamped))
+}
 
-    let n1 = Wrapping(1);
-    let n2 = Wrapping(2);
-    let n3 = Wrapping(3);
-    let n4 = Wrapping(4);
-    let n6 = Wrapping(6);
-    let n12 = Wrapping(12);
+#[inline]
+fn clamp_i32(v: i64, clamped: &mut usize) -> i32 {
+    match i32::try_from(v) {
+        Ok(v) => v,
+        Err(_) => {
+            *clamped += 1;
+            v.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+        }
+    }
+}
 
+fn tendency_i64(a: i64, b: i64, c: i64) -> i64 {
     if a >= b && b >= c {
-        let mut x = (n4 * a - n3 * c - b + n6) / n12;
-        if x - (x & n1) > n2 * (a - b) {
-            x = n2 * (a - b) + n1;
+        let mut x = (4 * a - 3 * c - b + 6) / 12;
+        if x - (x & 1) > 2 * (a - b) {
+            x = 2 * (a - b) + 1;
         }
-        if x + (x & n1) > n2 * (b - c) {
-            x = n2 * (b - c);
+        if x + (x & 1) > 2 * (b - c) {
+            x = 2 * (b - c);
         }
-        x.0
+        x
     } else if a <= b && b <= c {
-        let mut x = (n4 * a - n3 * c - b - n6) / n12;
-        if x + (x & n1) < n2 * (a - b) {
-            x = n2 * (a - b) - n1;
+        let mut x = (4 * a - 3 * c - b - 6) / 12;
+        if x + (x & 1) < 2 * (a - b) {
+            x = 2 * (a - b) - 1;
         }
-        if x - (x & n1) < n2 * (b - c) {
-            x = n2 * (b - c);
+        if x - (x & 1) < 2 * (b - c) {
+            x = 2 * (b - c);
         }
-        x.0
+        x
     } else {
         0
     }
@@ -1371,3 +1398,90 @@ fn tendency_i16_wasm32_simd128(
     let x = v128_bitselect(neg_x, x, need_neg);
     v128_and(no_skip, x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference implementation of horizontal inverse squeeze on single row, without overflow.
+    fn reference_h(row: &[i64]) -> Vec<i64> {
+        let avg_width = row.len().div_ceil(2);
+        let (avg_row, residu_row) = row.split_at(avg_width);
+        let mut out = Vec::with_capacity(row.len());
+        let mut left = avg_row[0];
+        for (x, &residu) in residu_row.iter().enumerate() {
+            let avg = avg_row[x];
+            let next_avg = avg_row.get(x + 1).copied().unwrap_or(avg);
+            let diff = residu + tendency_i64(left, avg, next_avg);
+            let first = avg + diff / 2;
+            let second = first - diff;
+            out.extend([first, second]);
+            left = second;
+        }
+        if row.len() % 2 == 1 {
+            out.push(avg_row[avg_width - 1]);
+        }
+        out
+    }
+
+    #[test]
+    fn i32_16bit_boundary() {
+        let mut row = vec![65535i32, 0, 65535, -65536, 65535, 65535, -131070, 131071, 1];
+        let expected = reference_h(&row.iter().map(|&v| v as i64).collect::<Vec<_>>());
+        let width = row.len();
+        let clamped = inverse_h_i32(&mut MutableSubgrid::from_buf(&mut row, width, 1, width));
+        assert_eq!(clamped, 0);
+        assert_eq!(row.iter().map(|&v| v as i64).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn i32_overflow_clamped() {
+        let mut row = vec![i32::MAX, i32::MAX, i32::MAX, i32::MIN];
+        let width = row.len();
+        let clamped = inverse_h_i32(&mut MutableSubgrid::from_buf(&mut row, width, 1, width));
+        assert_ne!(clamped, 0);
+        // Values saturate instead of wrapping around.
+        assert!(row.iter().all(|&v| v > 0), "{row:?}");
+
+        let mut col = vec![i32::MIN, i32::MIN, i32::MIN, i32::MAX];
+        let clamped = inverse_v_i32(&mut MutableSubgrid::from_buf(&mut col, 1, 4, 1));
+        assert_ne!(clamped, 0);
+        assert!(col.iter().all(|&v| v < 0), "{col:?}");
+    }
+
+    #[test]
+    fn i16_matches_i32() {
+        let width = 34;
+        let height = 20;
+        let mut narrow = (0..width * height)
+            .map(|i| match i % 5 {
+                0 => 2000,
+                1 => -2000,
+                2 => 1990 - (i as i16 % 7),
+                3 => -1990 + (i as i16 % 11),
+                _ => (i as i16 % 13) - 6,
+            })
+            .collect::<Vec<_>>();
+        let mut wide = narrow.iter().map(|&v| v as i32).collect::<Vec<_>>();
+
+        inverse_h(
+            &mut MutableSubgrid::from_buf(&mut narrow, width, height, width),
+            false,
+        );
+        inverse_h(
+            &mut MutableSubgrid::from_buf(&mut wide, width, height, width),
+            false,
+        );
+        inverse_v(
+            &mut MutableSubgrid::from_buf(&mut narrow, width, height, width),
+            false,
+        );
+        inverse_v(
+            &mut MutableSubgrid::from_buf(&mut wide, width, height, width),
+            false,
+        );
+
+        let narrow = narrow.iter().map(|&v| v as i32).collect::<Vec<_>>();
+        assert_eq!(narrow, wide);
+    }
+}
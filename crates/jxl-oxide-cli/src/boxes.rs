@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+use crate::commands::boxes::*;
+use crate::{Error, Result};
+
+/// Signature box of JPEG XL containers.
+const CONTAINER_SIGNATURE: [u8; 12] = [0, 0, 0, 0xc, b'J', b'X', b'L', b' ', 0xd, 0xa, 0x87, 0xa];
+
+/// Box in a container, with its position in the file.
+struct BoxInfo {
+    offset: usize,
+    size: usize,
+    ty: [u8; 4],
+    payload: Range<usize>,
+    truncated: bool,
+}
+
+pub fn handle_boxes(args: BoxesArgs) -> Result<()> {
+    let _guard = tracing::trace_span!("Handle boxes subcommand").entered();
+
+    let data = std::fs::read(&args.input).map_err(|e| Error::ReadJxl(e.into()))?;
+    if data.starts_with(&[0xff, 0x0a]) {
+        let Some(ty) = args.extract else {
+            println!("Bare codestream ({} bytes)", data.len());
+            return Ok(());
+        };
+        if &ty != b"jxlc" {
+            return Err(Error::BoxNotFound(box_type_name(ty)));
+        }
+        return write_payload(&args, &data);
+    }
+    if !data.starts_with(&CONTAINER_SIGNATURE) {
+        return Err(Error::ReadJxl("not a JPEG XL image".into()));
+    }
+
+    let boxes = parse_boxes(&data)?;
+    let Some(ty) = args.extract else {
+        print_boxes(&data, &boxes);
+        return Ok(());
+    };
+
+    if &ty == b"jxlc" {
+        if let Some(jxlc) = boxes.iter().find(|b| &b.ty == b"jxlc") {
+            warn_truncated(jxlc);
+            return write_payload(&args, &data[jxlc.payload.clone()]);
+        }
+
+        let mut codestream = Vec::new();
+        for jxlp in boxes.iter().filter(|b| &b.ty == b"jxlp") {
+            warn_truncated(jxlp);
+            if let Some(payload) = data[jxlp.payload.clone()].get(4..) {
+                codestream.extend_from_slice(payload);
+            }
+        }
+        if codestream.is_empty() {
+            return Err(Error::BoxNotFound(box_type_name(ty)));
+        }
+        tracing::info!("Concatenating partial codestream boxes");
+        return write_payload(&args, &codestream);
+    }
+
+    let mut matches = boxes.iter().filter(|b| b.ty == ty).peekable();
+    if let Some(b) = matches.next() {
+        if matches.peek().is_some() {
+            tracing::warn!("Multiple boxes found, extracting the first one");
+        }
+        warn_truncated(b);
+        return write_payload(&args, &data[b.payload.clone()]);
+    }
+
+    let brob = boxes
+        .iter()
+        .find(|b| &b.ty == b"brob" && data[b.payload.clone()].get(..4) == Some(&ty[..]));
+    if let Some(b) = brob {
+        tracing::warn!("Box is Brotli-compressed, writing compressed payload");
+        warn_truncated(b);
+        return write_payload(&args, &data[b.payload.start + 4..b.payload.end]);
+    }
+
+    Err(Error::BoxNotFound(box_type_name(ty)))
+}
+
+/// Splits container data into boxes.
+///
+/// Parsing stops at the first truncated box, which is included in the list.
+fn parse_boxes(data: &[u8]) -> Result<Vec<BoxInfo>> {
+    let mut boxes = Vec::new();
+    let mut offset = 0usize;
+    while offset < data.len() {
+        let buf = &data[offset..];
+        let invalid = || Error::ReadJxl(format!("invalid box header at offset {offset}").into());
+
+        let Some(header) = buf.get(..8) else {
+            return Err(invalid());
+        };
+        let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let ty = [header[4], header[5], header[6], header[7]];
+        let (header_size, size) = match size {
+            0 => (8, buf.len() as u64),
+            1 => {
+                let Some(xlbox) = buf.get(8..16) else {
+                    return Err(invalid());
+                };
+                (16, u64::from_be_bytes(xlbox.try_into().unwrap()))
+            }
+            size => (8, size as u64),
+        };
+        if size < header_size as u64 {
+            return Err(invalid());
+        }
+
+        let truncated = size > buf.len() as u64;
+        let size = size.min(buf.len() as u64) as usize;
+        boxes.push(BoxInfo {
+            offset,
+            size,
+            ty,
+            payload: offset + header_size.min(size)..offset + size,
+            truncated,
+        });
+        if truncated {
+            break;
+        }
+        offset += size;
+    }
+    Ok(boxes)
+}
+
+fn print_boxes(data: &[u8], boxes: &[BoxInfo]) {
+    println!(
+        "JPEG XL container ({} box{})",
+        boxes.len(),
+        if boxes.len() == 1 { "" } else { "es" },
+    );
+    println!("  {:>5}  {:>10}  {:>10}  Type", "Index", "Offset", "Size");
+    for (idx, b) in boxes.iter().enumerate() {
+        let payload = &data[b.payload.clone()];
+        print!(
+            "  {idx:>5}  {:>#10x}  {:>10}  {}",
+            b.offset,
+            b.size,
+            box_type_name(b.ty)
+        );
+        match &b.ty {
+            b"brob" if payload.len() >= 4 => {
+                let inner = [payload[0], payload[1], payload[2], payload[3]];
+                print!(" (Brotli-compressed {})", box_type_name(inner));
+            }
+            b"jxlp" if payload.len() >= 4 => {
+                let index = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                print!(" (index {}", index & 0x7fffffff);
+                if index & 0x80000000 != 0 {
+                    print!(", last");
+                }
+                print!(")");
+            }
+            _ => {}
+        }
+        if b.truncated {
+            print!(" (truncated)");
+        }
+        println!();
+    }
+}
+
+fn box_type_name(ty: [u8; 4]) -> String {
+    ty.iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '?'
+            }
+        })
+        .collect::<String>()
+        .trim_end()
+        .to_owned()
+}
+
+fn warn_truncated(b: &BoxInfo) {
+    if b.truncated {
+        tracing::warn!("Box is truncated, writing partial payload");
+    }
+}
+
+fn write_payload(args: &BoxesArgs, payload: &[u8]) -> Result<()> {
+    let output = args.output.as_ref().expect("output path is required");
+    std::fs::write(output, payload).map_err(Error::WriteImage)?;
+    tracing::info!("Wrote {} byte(s)", payload.len());
+    Ok(())
+}
@@ -1,6 +1,10 @@
+pub mod boxes;
 pub mod color_encoding;
+pub mod compare;
 pub mod decode;
 #[cfg(feature = "__devtools")]
+pub mod dump_groups;
+#[cfg(feature = "__devtools")]
 pub mod dump_jbrd;
 #[cfg(feature = "__devtools")]
 pub mod generate_fixture;
@@ -12,9 +16,13 @@ pub mod slow_motion;
 #[cfg(test)]
 pub mod tests;
 
+pub use boxes::BoxesArgs;
 pub use color_encoding::parse_color_encoding;
+pub use compare::CompareArgs;
 pub use decode::DecodeArgs;
 #[cfg(feature = "__devtools")]
+pub use dump_groups::DumpGroups;
+#[cfg(feature = "__devtools")]
 pub use dump_jbrd::DumpJbrd;
 #[cfg(feature = "__devtools")]
 pub use generate_fixture::GenerateFixtureArgs;
@@ -55,6 +63,10 @@ pub enum Subcommands {
     /// Print information about JPEG XL image.
     #[command(short_flag = 'I')]
     Info(InfoArgs),
+    /// Compare decoded JPEG XL image with a reference image.
+    Compare(CompareArgs),
+    /// List boxes of JPEG XL container, or extract their payloads.
+    Boxes(BoxesArgs),
     /// (devtools) Generate frames for progressive decoding animation.
     #[cfg(feature = "__devtools")]
     Progressive(ProgressiveArgs),
@@ -64,6 +76,9 @@ pub enum Subcommands {
     /// (devtools) Dump JPEG bitstream reconstruction data.
     #[cfg(feature = "__devtools")]
     DumpJbrd(DumpJbrd),
+    /// (devtools) Dump size, decode time and coefficient count of each group in CSV.
+    #[cfg(feature = "__devtools")]
+    DumpGroups(DumpGroups),
     /// (devtools, ffmpeg) Load an image byte-by-byte.
     #[cfg(feature = "__ffmpeg")]
     SlowMotion(SlowMotionArgs),
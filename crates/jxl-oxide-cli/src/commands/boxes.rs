@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// List boxes of JPEG XL container, or extract their payloads.
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct BoxesArgs {
+    /// Input file
+    pub input: PathBuf,
+    /// Type of the box to extract, such as `jxlc`, `Exif`, `xml` or `jbrd`
+    ///
+    /// Types shorter than four characters are padded with spaces. `jxlc` extracts the codestream,
+    /// concatenating `jxlp` boxes if the codestream is split. Brotli-compressed boxes are written
+    /// as is, without decompression.
+    #[arg(long, value_parser = parse_box_type, requires = "output")]
+    pub extract: Option<[u8; 4]>,
+    /// Output file of the extracted payload
+    #[arg(short, long, requires = "extract")]
+    pub output: Option<PathBuf>,
+}
+
+fn parse_box_type(s: &str) -> Result<[u8; 4], String> {
+    if s.is_empty() || s.len() > 4 || !s.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(String::from(
+            "box type should consist of one to four printable ASCII characters",
+        ));
+    }
+
+    let mut ty = *b"    ";
+    ty[..s.len()].copy_from_slice(s.as_bytes());
+    Ok(ty)
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// Compare decoded JPEG XL image with a reference image.
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct CompareArgs {
+    /// Input JPEG XL file
+    pub input: PathBuf,
+    /// Reference image, in PNG or Numpy format
+    ///
+    /// PNG references are compared with color and alpha channels of the first keyframe. Numpy
+    /// references, such as the ones written by `decode --output-format npy`, are compared with all
+    /// channels of every keyframe.
+    pub reference: PathBuf,
+    /// ICC profile of the reference image
+    ///
+    /// Embedded ICC profile of PNG references is used if not specified; PNG references without
+    /// one are assumed to be in sRGB.
+    #[arg(long)]
+    pub target_icc: Option<PathBuf>,
+    /// Fail if peak error of any channel exceeds the value
+    #[arg(long)]
+    pub peak_error: Option<f32>,
+    /// Fail if RMSE of any channel exceeds the value
+    #[arg(long)]
+    pub rmse: Option<f32>,
+}
@@ -7,13 +7,21 @@ use jxl_oxide::{CropInfo, EnumColourEncoding};
 #[non_exhaustive]
 pub struct DecodeArgs {
     /// Output file
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "output_dir")]
     pub output: Option<PathBuf>,
-    /// Output ICC file
+    /// Output directory, used when decoding multiple files
+    ///
+    /// Output files are named after the input files, with extensions of the output format.
     #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+    /// Output ICC file
+    #[arg(long, conflicts_with = "output_dir")]
     pub icc_output: Option<PathBuf>,
-    /// Input file
-    pub input: PathBuf,
+    /// Input files, or directories containing `.jxl` files
+    ///
+    /// Multiple files are decoded concurrently, sharing the thread pool.
+    #[arg(required = true)]
+    pub input: Vec<PathBuf>,
     /// (unstable) Region to render, in format of 'width height left top'
     #[arg(long, value_parser = parse_crop_info)]
     pub crop: Option<CropInfo>,
@@ -21,8 +29,14 @@ pub struct DecodeArgs {
     #[arg(long, default_value_t = 0)]
     pub approx_memory_limit: usize,
     /// Format to output
-    #[arg(value_enum, short = 'f', long)]
+    #[arg(value_enum, short = 'f', long, alias = "format")]
     pub output_format: Option<OutputFormat>,
+    /// Quality of encoded JPEG output, from 1 to 100 [default: 90]
+    ///
+    /// Chroma is subsampled below quality 90. Not supported for WebP output, which is always
+    /// lossless.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub quality: Option<u8>,
     /// (unstable) Target colorspace specification
     ///
     /// Specification string consists of (optional) preset and a sequence of parameters delimited by commas.
@@ -70,6 +84,22 @@ pub struct DecodeArgs {
     #[cfg(feature = "rayon")]
     #[arg(short = 'j', long)]
     pub num_threads: Option<usize>,
+    /// Decode sequentially using portable scalar code paths
+    ///
+    /// SIMD code paths may produce slightly different output depending on CPU features, such as
+    /// availability of FMA. Use this flag to produce canonical reference images, e.g. for bug
+    /// reports. Threading doesn't affect the output, but decoding is done in a single thread
+    /// to rule it out; `--num-threads` is ignored.
+    #[arg(long)]
+    pub deterministic: bool,
+    /// (unstable) Path to decode options in JSON format
+    ///
+    /// Options given with other arguments take precedence over the ones in the file.
+    #[arg(long)]
+    pub options: Option<PathBuf>,
+    /// (unstable) Print decode options in JSON format to stderr, e.g. to attach to bug reports
+    #[arg(long)]
+    pub print_options: bool,
     /// Number of repeated decoding, used for benchmarking
     #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
     pub num_reps: Option<u32>,
@@ -83,13 +113,37 @@ pub enum OutputFormat {
     Png8,
     /// PNG, always 16-bit.
     Png16,
-    /// JPEG bitstream reconstruction.
+    /// JPEG, reconstructed losslessly if the image has JPEG bitstream reconstruction data, or
+    /// encoded from pixels otherwise.
     #[value(name = "jpeg", alias("jpg"))]
+    Jpeg,
+    /// JPEG bitstream reconstruction only.
     JpegReconstruct,
+    /// WebP, lossless 8-bit.
+    Webp,
+    /// OpenEXR, 32-bit float in linear light.
+    Exr,
+    /// TIFF, 32-bit float.
+    #[value(alias("tif"))]
+    Tiff,
     /// Numpy, used for conformance test.
     Npy,
 }
 
+impl OutputFormat {
+    /// Returns the file extension used for the output format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png | Self::Png8 | Self::Png16 => "png",
+            Self::Jpeg | Self::JpegReconstruct => "jpg",
+            Self::Webp => "webp",
+            Self::Exr => "exr",
+            Self::Tiff => "tiff",
+            Self::Npy => "npy",
+        }
+    }
+}
+
 fn parse_crop_info(s: &str) -> Result<CropInfo, std::num::ParseIntError> {
     let s = s.trim();
     let mut it = s.split_whitespace().map(|s| s.parse::<u32>());
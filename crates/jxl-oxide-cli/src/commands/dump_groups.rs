@@ -0,0 +1,10 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+#[non_exhaustive]
+pub struct DumpGroups {
+    /// Input file
+    pub input: PathBuf,
+}
@@ -14,4 +14,16 @@ pub struct InfoArgs {
     /// Output group sizes and offsets
     #[arg(long)]
     pub with_offset: bool,
+    /// Output TOC of frames as a table, with group order, sizes and offsets
+    #[arg(long)]
+    pub toc: bool,
+    /// Output splines, patches and noise parameters used by frames
+    #[arg(long)]
+    pub features: bool,
+    /// Output information in JSON format, including every frame and its TOC
+    #[arg(long, conflicts_with_all = ["all_frames", "with_offset", "toc", "features"])]
+    pub json: bool,
+    /// Write an SVG file which visualizes group layout and order of frames
+    #[arg(long, value_name = "FILE")]
+    pub toc_svg: Option<PathBuf>,
 }
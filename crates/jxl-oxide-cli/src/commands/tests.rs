@@ -14,10 +14,151 @@ mod clap {
         };
         assert!(args.decode.is_none());
         assert_eq!(args.globals.verbose, 0);
-        assert_eq!(decode_args.input, Path::new("input.jxl"));
+        assert_eq!(decode_args.input, [Path::new("input.jxl")]);
         assert_eq!(decode_args.output.as_deref(), Some(Path::new("output.png")));
     }
 
+    #[test]
+    fn deterministic_decode() {
+        let args = Args::try_parse_from(["jxl-oxide", "input.jxl", "--deterministic"]).unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert!(decode_args.deterministic);
+
+        let args = Args::try_parse_from(["jxl-oxide", "input.jxl"]).unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert!(!decode_args.deterministic);
+    }
+
+    #[test]
+    fn decode_options() {
+        let args = Args::try_parse_from([
+            "jxl-oxide",
+            "input.jxl",
+            "--options",
+            "options.json",
+            "--print-options",
+        ])
+        .unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(
+            decode_args.options.as_deref(),
+            Some(Path::new("options.json"))
+        );
+        assert!(decode_args.print_options);
+    }
+
+    #[test]
+    fn output_formats() {
+        use super::super::decode::OutputFormat;
+
+        let args = Args::try_parse_from([
+            "jxl-oxide",
+            "input.jxl",
+            "-o",
+            "output.jpg",
+            "--format",
+            "jpg",
+            "--quality",
+            "75",
+        ])
+        .unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(decode_args.output_format, Some(OutputFormat::Jpeg));
+        assert_eq!(decode_args.quality, Some(75));
+
+        let args = Args::try_parse_from(["jxl-oxide", "input.jxl", "-f", "webp"]).unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(decode_args.output_format, Some(OutputFormat::Webp));
+        assert_eq!(decode_args.quality, None);
+
+        let args =
+            Args::try_parse_from(["jxl-oxide", "input.jxl", "-f", "jpeg-reconstruct"]).unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(
+            decode_args.output_format,
+            Some(OutputFormat::JpegReconstruct)
+        );
+
+        let args = Args::try_parse_from(["jxl-oxide", "input.jxl", "-f", "exr"]).unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(decode_args.output_format, Some(OutputFormat::Exr));
+
+        let args = Args::try_parse_from(["jxl-oxide", "input.jxl", "-f", "tif"]).unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(decode_args.output_format, Some(OutputFormat::Tiff));
+
+        Args::try_parse_from(["jxl-oxide", "input.jxl", "--quality", "0"]).unwrap_err();
+        Args::try_parse_from(["jxl-oxide", "input.jxl", "--quality", "101"]).unwrap_err();
+    }
+
+    #[test]
+    fn batch_decode() {
+        let args = Args::try_parse_from([
+            "jxl-oxide",
+            "a.jxl",
+            "b.jxl",
+            "images",
+            "--output-dir",
+            "out",
+            "-f",
+            "webp",
+        ])
+        .unwrap();
+        let Some(decode_args) = args.decode else {
+            panic!();
+        };
+        assert_eq!(
+            decode_args.input,
+            [Path::new("a.jxl"), Path::new("b.jxl"), Path::new("images")]
+        );
+        assert_eq!(decode_args.output_dir.as_deref(), Some(Path::new("out")));
+
+        Args::try_parse_from(["jxl-oxide", "decode"]).unwrap_err();
+        Args::try_parse_from(["jxl-oxide", "a.jxl", "-o", "a.png", "--output-dir", "out"])
+            .unwrap_err();
+    }
+
+    #[test]
+    fn compare() {
+        let args = Args::try_parse_from([
+            "jxl-oxide",
+            "compare",
+            "input.jxl",
+            "reference.npy",
+            "--peak-error",
+            "0.004",
+            "--rmse",
+            "0.0001",
+        ])
+        .unwrap();
+        let Some(Subcommands::Compare(compare_args)) = args.subcommand else {
+            panic!();
+        };
+        assert_eq!(compare_args.input, Path::new("input.jxl"));
+        assert_eq!(compare_args.reference, Path::new("reference.npy"));
+        assert!(compare_args.target_icc.is_none());
+        assert_eq!(compare_args.peak_error, Some(0.004));
+        assert_eq!(compare_args.rmse, Some(0.0001));
+
+        Args::try_parse_from(["jxl-oxide", "compare", "input.jxl"]).unwrap_err();
+    }
+
     #[test]
     fn basic_info() {
         let args = Args::try_parse_from(["jxl-oxide", "info", "input.jxl"]).unwrap();
@@ -37,7 +178,7 @@ mod clap {
         };
         assert!(args.subcommand.is_none());
         assert_eq!(args.globals.verbose, 0);
-        assert_eq!(decode_args.input, Path::new("input.jxl"));
+        assert_eq!(decode_args.input, [Path::new("input.jxl")]);
         assert_eq!(decode_args.output.as_deref(), Some(Path::new("output.png")));
     }
 
@@ -52,6 +193,76 @@ mod clap {
         assert_eq!(info_args.input, Path::new("input.jxl"));
     }
 
+    #[test]
+    fn info_toc() {
+        let args = Args::try_parse_from([
+            "jxl-oxide",
+            "info",
+            "input.jxl",
+            "--toc",
+            "--toc-svg",
+            "toc.svg",
+        ])
+        .unwrap();
+        let Some(Subcommands::Info(info_args)) = args.subcommand else {
+            panic!();
+        };
+        assert!(info_args.toc);
+        assert_eq!(info_args.toc_svg.as_deref(), Some(Path::new("toc.svg")));
+    }
+
+    #[test]
+    fn info_json() {
+        let args = Args::try_parse_from(["jxl-oxide", "info", "input.jxl", "--json"]).unwrap();
+        let Some(Subcommands::Info(info_args)) = args.subcommand else {
+            panic!();
+        };
+        assert!(info_args.json);
+
+        Args::try_parse_from(["jxl-oxide", "info", "input.jxl", "--json", "--toc"]).unwrap_err();
+    }
+
+    #[test]
+    fn boxes() {
+        let args = Args::try_parse_from(["jxl-oxide", "boxes", "input.jxl"]).unwrap();
+        let Some(Subcommands::Boxes(boxes_args)) = args.subcommand else {
+            panic!();
+        };
+        assert_eq!(boxes_args.input, Path::new("input.jxl"));
+        assert!(boxes_args.extract.is_none());
+
+        let args = Args::try_parse_from([
+            "jxl-oxide",
+            "boxes",
+            "input.jxl",
+            "--extract",
+            "xml",
+            "-o",
+            "metadata.xml",
+        ])
+        .unwrap();
+        let Some(Subcommands::Boxes(boxes_args)) = args.subcommand else {
+            panic!();
+        };
+        assert_eq!(boxes_args.extract, Some(*b"xml "));
+        assert_eq!(
+            boxes_args.output.as_deref(),
+            Some(Path::new("metadata.xml"))
+        );
+
+        Args::try_parse_from(["jxl-oxide", "boxes", "input.jxl", "--extract", "jxlc"]).unwrap_err();
+        Args::try_parse_from([
+            "jxl-oxide",
+            "boxes",
+            "input.jxl",
+            "--extract",
+            "jxlcc",
+            "-o",
+            "out",
+        ])
+        .unwrap_err();
+    }
+
     #[test]
     fn verbose() {
         let args = Args::try_parse_from(["jxl-oxide", "input.jxl", "-v"]).unwrap();
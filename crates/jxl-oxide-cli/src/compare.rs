@@ -0,0 +1,367 @@
+use std::io::prelude::*;
+use std::path::Path;
+
+use jxl_oxide::{DecodeOptions, EnumColourEncoding, JxlImage, RenderingIntent};
+
+use crate::commands::compare::*;
+use crate::{Error, Result};
+
+/// Opsin absorbance matrix of XYB color space.
+const OPSIN_ABSORBANCE: [[f32; 3]; 3] = [
+    [0.30, 0.622, 0.078],
+    [0.23, 0.692, 0.078],
+    [0.243_422_69, 0.204_767_44, 0.551_809_87],
+];
+const OPSIN_BIAS: f32 = 0.003_793_073_3;
+
+/// Weights of X, Y and B-Y differences in XYB distance, which roughly equalize their ranges.
+const XYB_WEIGHTS: [f32; 3] = [24.0, 1.0, 0.5];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ReferenceKind {
+    Png,
+    Npy,
+}
+
+/// Reference image with interleaved samples of nominal range `[0, 1]`.
+struct Reference {
+    kind: ReferenceKind,
+    width: usize,
+    height: usize,
+    channels: usize,
+    frames: Vec<Vec<f32>>,
+    icc: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default)]
+struct ChannelStats {
+    peak_error: f32,
+    sum_se: f64,
+}
+
+pub fn handle_compare(args: CompareArgs) -> Result<()> {
+    let _guard = tracing::trace_span!("Handle compare subcommand").entered();
+
+    let reference = read_reference(&args.reference)?;
+    tracing::debug!(
+        kind = ?reference.kind,
+        width = reference.width,
+        height = reference.height,
+        channels = reference.channels,
+        frames = reference.frames.len(),
+    );
+
+    let mut options = DecodeOptions::default();
+    match reference.kind {
+        ReferenceKind::Png => {
+            options.cmyk_to_srgb = true;
+        }
+        ReferenceKind::Npy => {
+            // Same as `decode --output-format npy`.
+            options.render_spot_color = false;
+        }
+    }
+    if let Some(icc_path) = &args.target_icc {
+        tracing::debug!("Reading target ICC profile");
+        options.target_icc = Some(std::fs::read(icc_path).map_err(Error::ReadIcc)?);
+    } else if let Some(icc) = &reference.icc {
+        options.target_icc = Some(icc.clone());
+    } else if reference.kind == ReferenceKind::Png {
+        let encoding = if reference.channels < 3 {
+            EnumColourEncoding::gray_srgb(RenderingIntent::Relative)
+        } else {
+            EnumColourEncoding::srgb(RenderingIntent::Relative)
+        };
+        options.target_color_encoding = Some(encoding);
+    }
+
+    let image = JxlImage::builder()
+        .options(options)
+        .open(&args.input)
+        .map_err(Error::ReadJxl)?;
+    if !image.is_loading_done() {
+        tracing::warn!("Partial image");
+    }
+
+    let num_keyframes = image.num_loaded_keyframes();
+    let num_frames = match reference.kind {
+        ReferenceKind::Png => {
+            if num_keyframes > 1 {
+                tracing::warn!("Comparing the first keyframe only");
+            }
+            1
+        }
+        ReferenceKind::Npy => {
+            if reference.frames.len() != num_keyframes {
+                return Err(Error::Compare(format!(
+                    "reference has {} frames, but image has {num_keyframes} keyframes",
+                    reference.frames.len(),
+                )));
+            }
+            num_keyframes
+        }
+    };
+    let srgb = image
+        .rendered_color_encoding()
+        .is_some_and(|encoding| encoding.is_srgb());
+
+    let mut passed = true;
+    for (keyframe_idx, expected) in reference.frames.iter().enumerate().take(num_frames) {
+        let render = image.render_frame(keyframe_idx).map_err(Error::Render)?;
+        let (width, height, channels, actual) = match reference.kind {
+            ReferenceKind::Png => {
+                let mut stream = render.stream();
+                let width = stream.width() as usize;
+                let height = stream.height() as usize;
+                let channels = stream.channels() as usize;
+                let mut buf = vec![0f32; width * height * channels];
+                stream.write_to_buffer(&mut buf);
+                (width, height, channels, buf)
+            }
+            ReferenceKind::Npy => {
+                let fb = render.image_all_channels();
+                (fb.width(), fb.height(), fb.channels(), fb.buf().to_vec())
+            }
+        };
+
+        let actual_dim = (width, height, channels);
+        let expected_dim = (reference.width, reference.height, reference.channels);
+        if actual_dim != expected_dim {
+            return Err(Error::Compare(format!(
+                "dimension mismatch; image is {}x{} with {} channels, reference is {}x{} with {} \
+                 channels",
+                width, height, channels, reference.width, reference.height, reference.channels,
+            )));
+        }
+
+        let mut stats = (0..channels)
+            .map(|_| ChannelStats::default())
+            .collect::<Vec<_>>();
+        for (a, e) in actual
+            .chunks_exact(channels)
+            .zip(expected.chunks_exact(channels))
+        {
+            for ((stats, &a), &e) in stats.iter_mut().zip(a).zip(e) {
+                let abs_error = (a - e).abs();
+                stats.peak_error = stats.peak_error.max(abs_error);
+                stats.sum_se += (abs_error as f64) * (abs_error as f64);
+            }
+        }
+
+        let num_pixels = (width * height) as f64;
+        println!("Keyframe #{keyframe_idx}");
+        for (c, stats) in stats.iter().enumerate() {
+            let rmse = (stats.sum_se / num_pixels).sqrt() as f32;
+            println!(
+                "  Channel {c}: peak error {:.6}, RMSE {:.6}, PSNR {:.2} dB",
+                stats.peak_error,
+                rmse,
+                psnr(stats.sum_se / num_pixels),
+            );
+
+            if let Some(max) = args.peak_error {
+                passed &= stats.peak_error <= max;
+            }
+            if let Some(max) = args.rmse {
+                passed &= rmse <= max;
+            }
+        }
+
+        let color_channels = if channels >= 3 { 3 } else { 1 };
+        let color_mse = stats[..color_channels]
+            .iter()
+            .map(|stats| stats.sum_se)
+            .sum::<f64>()
+            / (num_pixels * color_channels as f64);
+        println!("  PSNR of color channels: {:.2} dB", psnr(color_mse));
+
+        if srgb && channels >= 3 {
+            let (max, pnorm) = xyb_distance(&actual, expected, channels);
+            println!("  XYB distance: max {max:.6}, 3-norm {pnorm:.6}");
+        }
+    }
+
+    if !passed {
+        return Err(Error::Compare(String::from(
+            "peak error or RMSE exceeds the threshold",
+        )));
+    }
+    Ok(())
+}
+
+fn psnr(mse: f64) -> f64 {
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        -10.0 * mse.log10()
+    }
+}
+
+/// Computes maximum and 3-norm of weighted Euclidean distances between pixels in XYB color
+/// space.
+///
+/// This is a cheap approximation of perceptual distance, not calibrated to Butteraugli. Samples
+/// are assumed to be in sRGB.
+fn xyb_distance(actual: &[f32], expected: &[f32], channels: usize) -> (f32, f32) {
+    let mut max = 0f32;
+    let mut sum_cubed = 0f64;
+    let mut count = 0usize;
+    for (a, e) in actual
+        .chunks_exact(channels)
+        .zip(expected.chunks_exact(channels))
+    {
+        let a = srgb_to_xyb([a[0], a[1], a[2]]);
+        let e = srgb_to_xyb([e[0], e[1], e[2]]);
+        let dist = (0..3)
+            .map(|c| {
+                let d = (a[c] - e[c]) * XYB_WEIGHTS[c];
+                d * d
+            })
+            .sum::<f32>()
+            .sqrt();
+        max = max.max(dist);
+        sum_cubed += (dist as f64).powi(3);
+        count += 1;
+    }
+    let pnorm = (sum_cubed / count.max(1) as f64).cbrt() as f32;
+    (max, pnorm)
+}
+
+/// Converts an sRGB sample to XYB, with B replaced by B-Y.
+fn srgb_to_xyb(rgb: [f32; 3]) -> [f32; 3] {
+    let linear = rgb.map(|v| {
+        let v = v.clamp(0.0, 1.0);
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let bias_cbrt = OPSIN_BIAS.cbrt();
+    let [l, m, s] = OPSIN_ABSORBANCE.map(|row| {
+        let mixed = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2] + OPSIN_BIAS;
+        mixed.cbrt() - bias_cbrt
+    });
+    let y = (l + m) / 2.0;
+    [(l - m) / 2.0, y, s - y]
+}
+
+fn read_reference(path: &Path) -> Result<Reference> {
+    let mut file = std::fs::File::open(path).map_err(|e| Error::ReadReference(e.into()))?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)
+        .map_err(|e| Error::ReadReference(e.into()))?;
+    file.rewind().map_err(|e| Error::ReadReference(e.into()))?;
+    let file = std::io::BufReader::new(file);
+
+    if &magic == b"\x89PNG\r\n\x1a\n" {
+        read_png(file)
+    } else if magic.starts_with(b"\x93NUMPY") {
+        read_npy(file)
+    } else {
+        Err(Error::ReadReference("unknown image format".into()))
+    }
+}
+
+fn read_png(reader: impl Read) -> Result<Reference> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| Error::ReadReference(e.into()))?;
+    let icc = reader.info().icc_profile.as_ref().map(|icc| icc.to_vec());
+
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| Error::ReadReference(e.into()))?;
+    buf.truncate(info.buffer_size());
+
+    let frame = match info.bit_depth {
+        png::BitDepth::Sixteen => buf
+            .chunks_exact(2)
+            .map(|v| u16::from_be_bytes([v[0], v[1]]) as f32 / 65535.0)
+            .collect(),
+        _ => buf.iter().map(|&v| v as f32 / 255.0).collect(),
+    };
+    Ok(Reference {
+        kind: ReferenceKind::Png,
+        width: info.width as usize,
+        height: info.height as usize,
+        channels: info.color_type.samples(),
+        frames: vec![frame],
+        icc,
+    })
+}
+
+fn read_npy(mut reader: impl Read) -> Result<Reference> {
+    let mut header = [0u8; 10];
+    reader
+        .read_exact(&mut header)
+        .map_err(|e| Error::ReadReference(e.into()))?;
+    let meta_len = u16::from_le_bytes([header[8], header[9]]) as usize;
+    let mut meta = vec![0u8; meta_len];
+    reader
+        .read_exact(&mut meta)
+        .map_err(|e| Error::ReadReference(e.into()))?;
+    let meta = String::from_utf8_lossy(&meta);
+
+    if !meta.contains("'descr': '<f4'") || meta.contains("'fortran_order': True") {
+        return Err(Error::ReadReference(
+            "only little-endian f32 arrays in C order are supported".into(),
+        ));
+    }
+    let shape = meta
+        .split_once("'shape': (")
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(shape, _)| {
+            shape
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::parse::<usize>)
+                .collect::<std::result::Result<Vec<_>, _>>()
+        });
+    let (frames, height, width, channels) = match shape {
+        Some(Ok(shape)) => match *shape {
+            [frames, height, width, channels] => (frames, height, width, channels),
+            [height, width, channels] => (1, height, width, channels),
+            _ => {
+                return Err(Error::ReadReference(
+                    "array should have shape of (frames, height, width, channels)".into(),
+                ))
+            }
+        },
+        _ => return Err(Error::ReadReference("invalid Numpy header".into())),
+    };
+
+    let frame_len = width * height * channels;
+    let mut buf = Vec::new();
+    reader
+        .read_to_end(&mut buf)
+        .map_err(|e| Error::ReadReference(e.into()))?;
+    if buf.len() != frame_len * frames * 4 {
+        return Err(Error::ReadReference("unexpected end of Numpy data".into()));
+    }
+    let samples = buf
+        .chunks_exact(4)
+        .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+        .collect::<Vec<_>>();
+    let frames = if frame_len == 0 {
+        vec![Vec::new(); frames]
+    } else {
+        samples
+            .chunks_exact(frame_len)
+            .map(|f| f.to_vec())
+            .collect()
+    };
+
+    Ok(Reference {
+        kind: ReferenceKind::Npy,
+        width,
+        height,
+        channels,
+        frames,
+        icc: None,
+    })
+}
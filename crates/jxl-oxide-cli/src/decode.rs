@@ -1,6 +1,12 @@
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::time::Duration;
 
-use jxl_oxide::{AllocTracker, CropInfo, EnumColourEncoding, JxlImage, JxlThreadPool, Render};
+use jxl_oxide::export::{self, ExportOptions};
+use jxl_oxide::{
+    CropInfo, DecodeOptions, JpegReconstructionStatus, JxlImage, JxlThreadPool, Render,
+};
 
 use crate::commands::decode::*;
 use crate::{output, Error, Result};
@@ -8,39 +14,268 @@ use crate::{output, Error, Result};
 pub fn handle_decode(args: DecodeArgs) -> Result<()> {
     let _guard = tracing::trace_span!("Handle decode subcommand").entered();
 
+    let inputs = collect_inputs(&args.input)?;
+    let batch =
+        args.output_dir.is_some() || inputs.len() > 1 || args.input.iter().any(|p| p.is_dir());
+    if batch && (args.output.is_some() || args.icc_output.is_some()) {
+        return Err(Error::InvalidArgs(
+            "use --output-dir to decode multiple files".into(),
+        ));
+    }
+
+    let output_format = args.output_format.unwrap_or_else(|| {
+        let extension = args.output.as_ref().and_then(|path| path.extension());
+        match extension.and_then(|x| x.to_str()) {
+            Some("jpg" | "jpeg") => OutputFormat::Jpeg,
+            Some("webp") => OutputFormat::Webp,
+            Some("exr") => OutputFormat::Exr,
+            Some("tif" | "tiff") => OutputFormat::Tiff,
+            _ => OutputFormat::Png,
+        }
+    });
+
+    if output_format == OutputFormat::Webp && args.quality.is_some() {
+        return Err(Error::InvalidArgs(
+            "--quality is not supported for WebP output, which is always lossless".into(),
+        ));
+    }
+
+    let has_output = args.output.is_some() || args.output_dir.is_some();
+    let options = decode_options(&args, output_format, has_output)?;
+    if args.print_options {
+        eprintln!(
+            "{}",
+            serde_json::to_string_pretty(&options).expect("failed to serialize options")
+        );
+    }
+    tracing::debug!(?options);
+
+    if !batch {
+        let job = DecodeJob {
+            input: inputs.into_iter().next().unwrap(),
+            output: args.output.clone(),
+            icc_output: args.icc_output.clone(),
+        };
+        return decode_file(&args, &job, &options, output_format, None);
+    }
+
+    let jobs = if let Some(output_dir) = &args.output_dir {
+        std::fs::create_dir_all(output_dir).map_err(Error::WriteImage)?;
+        let mut outputs = std::collections::HashSet::new();
+        inputs
+            .into_iter()
+            .map(|input| {
+                let mut output = output_dir.join(input.file_stem().unwrap_or_default());
+                output.set_extension(output_format.extension());
+                if !outputs.insert(output.clone()) {
+                    tracing::warn!(output = %output.display(), "Multiple inputs write to the same file");
+                }
+                DecodeJob {
+                    input,
+                    output: Some(output),
+                    icc_output: None,
+                }
+            })
+            .collect::<Vec<_>>()
+    } else {
+        inputs
+            .into_iter()
+            .map(|input| DecodeJob {
+                input,
+                output: None,
+                icc_output: None,
+            })
+            .collect()
+    };
+    decode_batch(&args, &jobs, &options, output_format)
+}
+
+/// Single image to decode.
+struct DecodeJob {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    icc_output: Option<PathBuf>,
+}
+
+/// Expands directories in the input list into `.jxl` files in them, sorted by file name.
+fn collect_inputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            inputs.push(path.clone());
+            continue;
+        }
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path).map_err(|e| Error::ReadJxl(e.into()))? {
+            let entry_path = entry.map_err(|e| Error::ReadJxl(e.into()))?.path();
+            let is_jxl = entry_path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("jxl"));
+            if is_jxl && entry_path.is_file() {
+                files.push(entry_path);
+            }
+        }
+        if files.is_empty() {
+            tracing::warn!(path = %path.display(), "No JPEG XL files in directory");
+        }
+        files.sort();
+        inputs.extend(files);
+    }
+    Ok(inputs)
+}
+
+/// Decodes multiple images concurrently on a shared thread pool.
+///
+/// Failures are logged and don't stop decoding the remaining images.
+fn decode_batch(
+    args: &DecodeArgs,
+    jobs: &[DecodeJob],
+    options: &DecodeOptions,
+    output_format: OutputFormat,
+) -> Result<()> {
     #[cfg(feature = "rayon")]
-    let pool = JxlThreadPool::rayon(args.num_threads);
+    let pool = if options.deterministic {
+        JxlThreadPool::none()
+    } else {
+        JxlThreadPool::rayon(options.num_threads)
+    };
     #[cfg(not(feature = "rayon"))]
     let pool = JxlThreadPool::none();
 
-    let mut image_builder = JxlImage::builder().pool(pool.clone());
-    if args.approx_memory_limit != 0 {
-        let tracker = AllocTracker::with_limit(args.approx_memory_limit);
-        image_builder = image_builder.alloc_tracker(tracker);
+    tracing::info!("Decoding {} files", jobs.len());
+    let progress = BatchProgress::new(jobs.len());
+    let run_job = |job: &DecodeJob| {
+        let _guard = tracing::info_span!("Decode", input = %job.input.display()).entered();
+        let result = decode_file(args, job, options, output_format, Some(&pool));
+        if let Err(e) = &result {
+            tracing::error!("{e}");
+        }
+        progress.finish_one(result.is_ok());
+        result.is_ok()
+    };
+
+    #[allow(unused_mut)]
+    let mut results = None;
+    #[cfg(feature = "rayon")]
+    if let Some(rayon_pool) = pool.as_rayon_pool() {
+        results = Some(rayon_pool.install(|| {
+            use rayon::prelude::*;
+
+            jobs.par_iter().map(run_job).collect::<Vec<_>>()
+        }));
+    }
+    let results = results.unwrap_or_else(|| jobs.iter().map(run_job).collect());
+    progress.finish();
+
+    let failed = results.into_iter().filter(|ok| !ok).count();
+    if failed > 0 {
+        return Err(Error::Batch {
+            failed,
+            total: jobs.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Progress bar of batch decoding, drawn to stderr if it's a terminal.
+struct BatchProgress {
+    total: usize,
+    enabled: bool,
+    state: Mutex<(usize, usize)>,
+}
+
+impl BatchProgress {
+    const WIDTH: usize = 30;
+
+    fn new(total: usize) -> Self {
+        let progress = Self {
+            total,
+            enabled: std::io::stderr().is_terminal(),
+            state: Mutex::new((0, 0)),
+        };
+        progress.draw(0, 0);
+        progress
+    }
+
+    fn finish_one(&self, ok: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        if !ok {
+            state.1 += 1;
+        }
+        self.draw(state.0, state.1);
+    }
+
+    fn finish(&self) {
+        if self.enabled {
+            eprintln!();
+        }
+    }
+
+    fn draw(&self, done: usize, failed: usize) {
+        if !self.enabled {
+            return;
+        }
+
+        let filled = done * Self::WIDTH / self.total.max(1);
+        let mut line = format!(
+            "[{}{}] {done}/{}",
+            "=".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            self.total,
+        );
+        if failed > 0 {
+            line += &format!(" ({failed} failed)");
+        }
+        // Move the cursor back, so that log lines overwrite the progress bar.
+        let mut stderr = std::io::stderr().lock();
+        write!(stderr, "\r\x1b[2K{line}\r").ok();
+        stderr.flush().ok();
+    }
+}
+
+fn decode_file(
+    args: &DecodeArgs,
+    job: &DecodeJob,
+    options: &DecodeOptions,
+    mut output_format: OutputFormat,
+    pool: Option<&JxlThreadPool>,
+) -> Result<()> {
+    let explicit_jpeg = output_format == OutputFormat::JpegReconstruct;
+
+    // Malformed target ICC profile is reported, and the image is rendered in its original color
+    // encoding.
+    let mut image_options = options.clone();
+    let target_icc = image_options.target_icc.take();
+    if target_icc.is_some() {
+        image_options.target_color_encoding = None;
+        image_options.cmyk_to_srgb = false;
     }
-    let mut image = image_builder.open(&args.input).map_err(Error::ReadJxl)?;
+
+    let mut builder = JxlImage::builder().options(image_options);
+    if let Some(pool) = pool {
+        builder = builder.pool(pool.clone());
+    }
+    let mut image = builder.open(&job.input).map_err(Error::ReadJxl)?;
     if !image.is_loading_done() {
         tracing::warn!("Partial image");
     }
-
-    let explicit_jpeg = args.output_format == Some(OutputFormat::JpegReconstruct);
-    let mut output_format = args.output_format.unwrap_or_else(|| {
-        if let Some(output_path) = &args.output {
-            if output_path
-                .extension()
-                .map(|x| x == "jpg" || x == "jpeg")
-                .unwrap_or(false)
-            {
-                OutputFormat::JpegReconstruct
-            } else {
-                OutputFormat::Png
-            }
-        } else {
-            OutputFormat::Png
+    if let Some(icc_profile) = &target_icc {
+        if let Err(e) = image.request_icc(icc_profile) {
+            tracing::error!(%e, "Target ICC profile is malformed");
         }
-    });
+    }
+    if output_format == OutputFormat::Exr {
+        // OpenEXR stores samples in linear light.
+        image.set_render_linear(true);
+    }
 
-    if output_format == OutputFormat::JpegReconstruct {
+    if matches!(
+        output_format,
+        OutputFormat::Jpeg | OutputFormat::JpegReconstruct
+    ) && image.jpeg_reconstruction_status() != JpegReconstructionStatus::Unavailable
+    {
         tracing::info!("Reconstructing to JPEG");
 
         let mut buf = Vec::new();
@@ -55,7 +290,9 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
             }
 
             tracing::warn!("Falling back to decode-to-pixels");
-            output_format = OutputFormat::Png
+            if output_format == OutputFormat::JpegReconstruct {
+                output_format = OutputFormat::Png;
+            }
         } else {
             let total_pixels = image.width() * image.height();
             let mps = total_pixels as f64 / 1e6;
@@ -66,7 +303,7 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
                 mps / elapsed_seconds
             );
 
-            if let Some(output_path) = args.output {
+            if let Some(output_path) = &job.output {
                 std::fs::write(output_path, buf).map_err(Error::WriteImage)?;
             } else {
                 tracing::info!("No output path specified, skipping output encoding");
@@ -76,35 +313,20 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
         }
     }
 
-    let output_png = args.output.is_some()
-        && matches!(
-            output_format,
-            OutputFormat::Png | OutputFormat::Png8 | OutputFormat::Png16
-        );
-    if let Some(icc_path) = &args.target_icc {
-        tracing::debug!("Setting target ICC profile");
-        let icc_profile = std::fs::read(icc_path).map_err(Error::ReadIcc)?;
-        match image.request_icc(&icc_profile) {
-            Ok(_) => {}
-            Err(e) => {
-                tracing::error!(%e, "Target ICC profile is malformed");
-            }
+    if output_format == OutputFormat::JpegReconstruct {
+        if explicit_jpeg {
+            return Err(Error::Reconstruct(
+                "JPEG bitstream reconstruction data is not available".into(),
+            ));
         }
-    } else if let Some(encoding) = &args.target_colorspace {
-        tracing::debug!(?encoding, "Setting target color space");
-        image.request_color_encoding(encoding.clone());
-    } else if output_png && image.pixel_format().has_black() {
-        tracing::debug!("Input is CMYK; setting target color encoding to sRGB");
-        image.request_color_encoding(EnumColourEncoding::srgb(
-            jxl_oxide::color::RenderingIntent::Relative,
-        ));
+        output_format = OutputFormat::Png;
     }
 
     let image_meta = &image.image_header().metadata;
     tracing::info!("Image dimension: {}x{}", image.width(), image.height());
     tracing::debug!(colour_encoding = format_args!("{:?}", image_meta.colour_encoding));
 
-    if let Some(icc_path) = &args.icc_output {
+    if let Some(icc_path) = &job.icc_output {
         if let Some(icc) = image.original_icc() {
             tracing::debug!("Writing ICC profile");
             std::fs::write(icc_path, icc).map_err(Error::WriteIcc)?;
@@ -113,28 +335,7 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
         }
     }
 
-    let crop = args.crop.and_then(|crop| {
-        if crop.width == 0 && crop.height == 0 {
-            None
-        } else if crop.width == 0 {
-            Some(CropInfo {
-                width: image.width(),
-                ..crop
-            })
-        } else if crop.height == 0 {
-            Some(CropInfo {
-                height: image.height(),
-                ..crop
-            })
-        } else {
-            Some(crop)
-        }
-    });
-
-    if let Some(crop) = crop {
-        tracing::debug!(crop = format_args!("{:?}", crop), "Cropped decoding");
-    }
-
+    let crop = options.resolve_crop(image.width(), image.height());
     let crop_region = crop.unwrap_or(CropInfo {
         width: image.width(),
         height: image.height(),
@@ -145,10 +346,6 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
     let total_pixels = width * height;
     let mps = total_pixels as f64 / 1e6;
 
-    if output_format == OutputFormat::Npy {
-        image.set_render_spot_color(false);
-    }
-
     let keyframes = if let Some(num_reps @ 2..) = args.num_reps {
         tracing::info!("Running {num_reps} repetitions");
 
@@ -196,69 +393,61 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
         keyframes
     };
 
-    if let Some(output) = &args.output {
+    if let Some(output) = &job.output {
         if keyframes.is_empty() {
             tracing::warn!("No keyframes are decoded");
             return Ok(());
         }
 
         tracing::debug!(?output_format);
-        let pixel_format = image.pixel_format();
         let output = std::fs::File::create(output).map_err(Error::WriteImage)?;
         match output_format {
-            OutputFormat::Png => {
-                let force_bit_depth = if let Some(encoding) = &args.target_colorspace {
-                    if encoding.is_srgb_gamut() {
-                        Some(png::BitDepth::Eight)
-                    } else {
-                        None
+            OutputFormat::Png | OutputFormat::Png8 | OutputFormat::Png16 => {
+                let mut options = ExportOptions::from_image(&image);
+                match output_format {
+                    OutputFormat::Png8 => options.sixteen_bits = false,
+                    OutputFormat::Png16 => options.sixteen_bits = true,
+                    _ => {
+                        if let Some(encoding) = &args.target_colorspace {
+                            if encoding.is_srgb_gamut() {
+                                options.sixteen_bits = false;
+                            }
+                        }
                     }
-                } else {
-                    None
-                };
-
-                output::write_png(
-                    output,
-                    &image,
-                    &keyframes,
-                    pixel_format,
-                    force_bit_depth,
-                    width,
-                    height,
-                )
-                .map_err(Error::WriteImage)?;
-            }
-            OutputFormat::Png8 => {
-                output::write_png(
-                    output,
-                    &image,
-                    &keyframes,
-                    pixel_format,
-                    Some(png::BitDepth::Eight),
-                    width,
-                    height,
-                )
-                .map_err(Error::WriteImage)?;
-            }
-            OutputFormat::Png16 => {
-                output::write_png(
-                    output,
-                    &image,
-                    &keyframes,
-                    pixel_format,
-                    Some(png::BitDepth::Sixteen),
-                    width,
-                    height,
-                )
-                .map_err(Error::WriteImage)?;
+                }
+
+                export::write_png(output, &keyframes, &options).map_err(Error::WriteImage)?;
             }
             OutputFormat::Npy => {
-                if args.icc_output.is_none() {
+                if job.icc_output.is_none() {
                     tracing::warn!("--icc-output is not set. Numpy buffer alone cannot be used to display image as its colorspace is unknown.");
                 }
 
                 output::write_npy(output, &keyframes, width, height).map_err(Error::WriteImage)?;
             }
+            OutputFormat::Jpeg => {
+                let mut options = ExportOptions::from_image(&image);
+                if let Some(quality) = args.quality {
+                    options.jpeg_quality = quality;
+                }
+                export::write_jpeg(output, &keyframes, &options).map_err(Error::WriteImage)?;
+            }
+            OutputFormat::Webp => {
+                let options = ExportOptions::from_image(&image);
+                export::write_webp(output, &keyframes, &options).map_err(Error::WriteImage)?;
+            }
+            OutputFormat::Exr => {
+                if keyframes.len() > 1 {
+                    tracing::warn!("Writing the first keyframe only");
+                }
+                output::write_exr(output, &image, &keyframes[0]).map_err(Error::WriteImage)?;
+            }
+            OutputFormat::Tiff => {
+                if keyframes.len() > 1 {
+                    tracing::warn!("Writing the first keyframe only");
+                }
+                output::write_tiff(output, &image, &keyframes[0]).map_err(Error::WriteImage)?;
+            }
             OutputFormat::JpegReconstruct => unreachable!("should have been processed before"),
         }
     } else {
@@ -306,3 +495,54 @@ fn run_once(image: &mut JxlImage) -> Result<(Vec<Render>, Duration)> {
     let elapsed = decode_start.elapsed();
     Ok((keyframes, elapsed))
 }
+
+/// Collects decode options from the options file and command line arguments.
+fn decode_options(
+    args: &DecodeArgs,
+    output_format: OutputFormat,
+    has_output: bool,
+) -> Result<DecodeOptions> {
+    let mut options = if let Some(path) = &args.options {
+        let json = std::fs::read(path).map_err(Error::ReadOptions)?;
+        serde_json::from_slice(&json).map_err(Error::ParseOptions)?
+    } else {
+        DecodeOptions::default()
+    };
+
+    if args.crop.is_some() {
+        options.crop = args.crop;
+    }
+    if let Some(icc_path) = &args.target_icc {
+        tracing::debug!("Reading target ICC profile");
+        options.target_icc = Some(std::fs::read(icc_path).map_err(Error::ReadIcc)?);
+    }
+    if let Some(encoding) = &args.target_colorspace {
+        options.target_color_encoding = Some(encoding.clone());
+    }
+    options.cmyk_to_srgb = has_output
+        && matches!(
+            output_format,
+            OutputFormat::Png
+                | OutputFormat::Png8
+                | OutputFormat::Png16
+                | OutputFormat::Jpeg
+                | OutputFormat::Webp
+                | OutputFormat::Exr
+                | OutputFormat::Tiff
+        );
+    if output_format == OutputFormat::Npy {
+        options.render_spot_color = false;
+    }
+    if args.approx_memory_limit != 0 {
+        options.approx_memory_limit = Some(args.approx_memory_limit);
+    }
+    #[cfg(feature = "rayon")]
+    if args.num_threads.is_some() {
+        options.num_threads = args.num_threads;
+    }
+    if args.deterministic {
+        options.deterministic = true;
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use jxl_frame::data::TocGroupKind;
+use jxl_oxide::{JxlImage, JxlThreadPool};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::commands::dump_groups::*;
+use crate::{Error, Result};
+
+/// Decode time and number of decoded coefficients of a group.
+#[derive(Debug, Default, Copy, Clone)]
+struct GroupStats {
+    time: Duration,
+    num_coeffs: u64,
+}
+
+type StatsMap = HashMap<(usize, TocGroupKind), GroupStats>;
+
+/// Tracing layer which collects statistics from group decoding spans emitted by `jxl-frame`.
+///
+/// Group decoding spans are attributed to the frame of the enclosing render span. Only the
+/// outermost group span is recorded, as a group may decode other groups it depends on.
+struct GroupStatsLayer {
+    stats: Arc<Mutex<StatsMap>>,
+}
+
+/// Span extension of group decoding spans.
+struct GroupSpan {
+    frame_idx: usize,
+    kind: TocGroupKind,
+    entered_at: Option<Instant>,
+    stats: GroupStats,
+}
+
+/// Span extension of frame render spans.
+struct FrameSpan(usize);
+
+#[derive(Default)]
+struct FieldVisitor {
+    kind: Option<String>,
+    values: HashMap<&'static str, u64>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.values.insert(field.name(), value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.values.insert(field.name(), value as u64);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "kind" {
+            self.kind = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+}
+
+impl FieldVisitor {
+    fn group_kind(&self) -> Option<TocGroupKind> {
+        let value = |name| self.values.get(name).map(|&v| v as u32);
+        Some(match self.kind.as_deref()? {
+            "LfGlobal" => TocGroupKind::LfGlobal,
+            "LfGroup" => TocGroupKind::LfGroup(value("lf_group_idx")?),
+            "HfGlobal" => TocGroupKind::HfGlobal,
+            "PassGroup" => TocGroupKind::GroupPass {
+                pass_idx: value("pass_idx")?,
+                group_idx: value("group_idx")?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl<S> Layer<S> for GroupStatsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let name = attrs.metadata().name();
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+
+        if name == "Run" || name == "Run with image" {
+            if let Some(&idx) = visitor.values.get("index") {
+                span.extensions_mut().insert(FrameSpan(idx as usize));
+            }
+            return;
+        }
+        if name != "Decode group" {
+            return;
+        }
+
+        let mut frame_idx = None;
+        for parent in span.scope().skip(1) {
+            let ext = parent.extensions();
+            if ext.get::<GroupSpan>().is_some() {
+                // Nested group; decoding time is accounted for by the outer group.
+                return;
+            }
+            if let Some(FrameSpan(idx)) = ext.get::<FrameSpan>() {
+                frame_idx = Some(*idx);
+                break;
+            }
+        }
+        let (Some(frame_idx), Some(kind)) = (frame_idx, visitor.group_kind()) else {
+            return;
+        };
+        span.extensions_mut().insert(GroupSpan {
+            frame_idx,
+            kind,
+            entered_at: None,
+            stats: GroupStats::default(),
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut ext = span.extensions_mut();
+        if let Some(group) = ext.get_mut::<GroupSpan>() {
+            group.entered_at = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut ext = span.extensions_mut();
+        if let Some(group) = ext.get_mut::<GroupSpan>() {
+            if let Some(entered_at) = group.entered_at.take() {
+                group.stats.time += entered_at.elapsed();
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let Some(&num_coeffs) = visitor.values.get("num_coeffs") else {
+            return;
+        };
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+        for span in scope {
+            if let Some(group) = span.extensions_mut().get_mut::<GroupSpan>() {
+                group.stats.num_coeffs += num_coeffs;
+                return;
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(group) = span.extensions_mut().remove::<GroupSpan>() else {
+            return;
+        };
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry((group.frame_idx, group.kind)).or_default();
+        entry.time += group.stats.time;
+        entry.num_coeffs += group.stats.num_coeffs;
+    }
+}
+
+pub fn handle_dump_groups(args: DumpGroups) -> Result<()> {
+    let _guard = tracing::trace_span!("Handle dump-groups subcommand").entered();
+
+    let stats = Arc::new(Mutex::new(StatsMap::new()));
+    let subscriber = tracing_subscriber::registry().with(GroupStatsLayer {
+        stats: stats.clone(),
+    });
+
+    // Decode in the current thread, so that every group is decoded under the subscriber.
+    let image = tracing::subscriber::with_default(subscriber, || -> Result<_> {
+        let image = JxlImage::builder()
+            .pool(JxlThreadPool::none())
+            .open(&args.input)
+            .map_err(Error::ReadJxl)?;
+        for idx in 0..image.num_loaded_keyframes() {
+            image.render_frame(idx).map_err(Error::Render)?;
+        }
+        Ok(image)
+    })?;
+
+    let stats = stats.lock().unwrap();
+    println!("frame,kind,pass,group,bytes,time_us,coeffs");
+    for frame_idx in 0..image.num_loaded_frames() {
+        let frame = image.frame(frame_idx).unwrap();
+        let toc = frame.toc();
+        for group in toc.iter_bitstream_order() {
+            let group_stats = if toc.is_single_entry() {
+                // Every group is in a single section.
+                stats.iter().filter(|((idx, _), _)| *idx == frame_idx).fold(
+                    GroupStats::default(),
+                    |acc, (_, s)| GroupStats {
+                        time: acc.time + s.time,
+                        num_coeffs: acc.num_coeffs + s.num_coeffs,
+                    },
+                )
+            } else {
+                stats
+                    .get(&(frame_idx, group.kind))
+                    .copied()
+                    .unwrap_or_default()
+            };
+
+            let (kind, pass, group_idx) = match group.kind {
+                TocGroupKind::All => ("All", None, None),
+                TocGroupKind::LfGlobal => ("LfGlobal", None, None),
+                TocGroupKind::LfGroup(idx) => ("LfGroup", None, Some(idx)),
+                TocGroupKind::HfGlobal => ("HfGlobal", None, None),
+                TocGroupKind::GroupPass {
+                    pass_idx,
+                    group_idx,
+                } => ("PassGroup", Some(pass_idx), Some(group_idx)),
+            };
+            let pass = pass.map(|x| x.to_string()).unwrap_or_default();
+            let group_idx = group_idx.map(|x| x.to_string()).unwrap_or_default();
+            println!(
+                "{frame_idx},{kind},{pass},{group_idx},{},{},{}",
+                group.size,
+                group_stats.time.as_micros(),
+                group_stats.num_coeffs,
+            );
+        }
+    }
+
+    Ok(())
+}
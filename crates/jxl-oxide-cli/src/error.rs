@@ -1,12 +1,22 @@
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
-    ReadJxl(Box<dyn std::error::Error + Send + Sync + 'static>),
+    ReadJxl(jxl_oxide::Error),
     ReadIcc(std::io::Error),
+    ReadReference(Box<dyn std::error::Error + Send + Sync + 'static>),
+    ReadOptions(std::io::Error),
+    ParseOptions(serde_json::Error),
     WriteIcc(std::io::Error),
     WriteImage(std::io::Error),
-    Render(Box<dyn std::error::Error + Send + Sync + 'static>),
-    Reconstruct(Box<dyn std::error::Error + Send + Sync + 'static>),
+    Render(jxl_oxide::Error),
+    Reconstruct(jxl_oxide::Error),
+    Compare(String),
+    InvalidArgs(String),
+    BoxNotFound(String),
+    Batch {
+        failed: usize,
+        total: usize,
+    },
     #[cfg(feature = "__ffmpeg")]
     Ffmpeg {
         msg: Option<&'static str>,
@@ -39,10 +49,19 @@ impl std::fmt::Display for Error {
         match self {
             Error::ReadJxl(e) => write!(f, "failed reading JPEG XL image: {e}"),
             Error::ReadIcc(e) => write!(f, "failed reading ICC profile: {e}"),
+            Error::ReadReference(e) => write!(f, "failed reading reference image: {e}"),
+            Error::ReadOptions(e) => write!(f, "failed reading decode options: {e}"),
+            Error::ParseOptions(e) => write!(f, "failed parsing decode options: {e}"),
             Error::WriteIcc(e) => write!(f, "failed writing ICC profile: {e}"),
             Error::WriteImage(e) => write!(f, "failed writing output image: {e}"),
             Error::Render(e) => write!(f, "failed to render image: {e}"),
             Error::Reconstruct(e) => write!(f, "failed to reconstruct: {e}"),
+            Error::Compare(msg) => write!(f, "comparison failed: {msg}"),
+            Error::InvalidArgs(msg) => write!(f, "invalid arguments: {msg}"),
+            Error::BoxNotFound(ty) => write!(f, "box not found: {ty}"),
+            Error::Batch { failed, total } => {
+                write!(f, "failed to decode {failed} of {total} images")
+            }
             #[cfg(feature = "__ffmpeg")]
             Error::Ffmpeg { msg, averror } => {
                 write!(f, "FFmpeg error")?;
@@ -69,12 +88,19 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Error::ReadJxl(e) => Some(&**e),
+            Error::ReadJxl(e) => Some(e),
             Error::ReadIcc(e) => Some(e),
+            Error::ReadReference(e) => Some(&**e),
+            Error::ReadOptions(e) => Some(e),
+            Error::ParseOptions(e) => Some(e),
             Error::WriteIcc(e) => Some(e),
             Error::WriteImage(e) => Some(e),
-            Error::Render(e) => Some(&**e),
-            Error::Reconstruct(e) => Some(&**e),
+            Error::Render(e) => Some(e),
+            Error::Reconstruct(e) => Some(e),
+            Error::Compare(_)
+            | Error::InvalidArgs(_)
+            | Error::BoxNotFound(_)
+            | Error::Batch { .. } => None,
             #[cfg(feature = "__ffmpeg")]
             Error::Ffmpeg { .. } => None,
         }
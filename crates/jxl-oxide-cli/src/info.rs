@@ -1,9 +1,13 @@
+use std::fmt::Write;
+
 use jxl_oxide::color::*;
 use jxl_oxide::frame::*;
 use jxl_oxide::image::BitDepth;
 use jxl_oxide::{
     AuxBoxData, ColorEncodingWithProfile, ExtraChannelType, JpegReconstructionStatus, JxlImage,
+    Toc, TocGroupKind,
 };
+use serde_json::{json, Value};
 
 use crate::{commands::info::*, Error, Result};
 
@@ -13,6 +17,24 @@ pub fn handle_info(args: InfoArgs) -> Result<()> {
     let image = JxlImage::builder()
         .open(&args.input)
         .map_err(Error::ReadJxl)?;
+    if args.json {
+        let info = image_info_json(&image);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&info).expect("failed to serialize image information")
+        );
+
+        if let Some(path) = &args.toc_svg {
+            let svg_frames = (0..image.num_loaded_frames() + 1)
+                .map_while(|idx| image.frame(idx).map(|frame| (idx, frame)))
+                .filter(|(_, frame)| frame.header().is_keyframe())
+                .map(|(idx, frame)| toc_svg_frame(idx, frame.header(), frame.toc()))
+                .collect::<Vec<_>>();
+            std::fs::write(path, toc_svg(&svg_frames)).map_err(Error::WriteImage)?;
+        }
+        return Ok(());
+    }
+
     let image_size = &image.image_header().size;
     let image_meta = &image.image_header().metadata;
     let image_reader = image.reader();
@@ -149,6 +171,7 @@ pub fn handle_info(args: InfoArgs) -> Result<()> {
     }
 
     let animated = image_meta.animation.is_some();
+    let mut svg_frames = Vec::new();
     for idx in 0..image.num_loaded_frames() + 1 {
         let Some(frame) = image.frame(idx) else {
             break;
@@ -243,15 +266,437 @@ pub fn handle_info(args: InfoArgs) -> Result<()> {
                 );
             }
         }
+
+        if args.features {
+            print_features(&image, idx, frame_header)?;
+        }
+        if args.toc {
+            print_toc(frame_header, frame.toc(), image.frame_offset(idx).unwrap());
+        }
+        if args.toc_svg.is_some() {
+            svg_frames.push(toc_svg_frame(idx, frame_header, frame.toc()));
+        }
     }
 
     if !image.is_loading_done() {
         println!("Partial file");
     }
 
+    if let Some(path) = &args.toc_svg {
+        let svg = toc_svg(&svg_frames);
+        std::fs::write(path, svg).map_err(Error::WriteImage)?;
+    }
+
+    Ok(())
+}
+
+/// Collects image and frame information into a JSON value.
+fn image_info_json(image: &JxlImage) -> Value {
+    let image_size = &image.image_header().size;
+    let image_meta = &image.image_header().metadata;
+
+    let color_encoding = match &image_meta.colour_encoding {
+        ColourEncoding::Enum(encoding) => json!({
+            "type": "enum",
+            "encoding": encoding,
+        }),
+        ColourEncoding::IccProfile(colour_space) => {
+            let icc = image.original_icc().unwrap();
+            let encoding = ColorEncodingWithProfile::with_icc(icc)
+                .ok()
+                .and_then(|encoding| match encoding.encoding() {
+                    ColourEncoding::Enum(encoding) => Some(encoding.clone()),
+                    _ => None,
+                });
+            json!({
+                "type": "icc",
+                "grayscale": *colour_space == ColourSpace::Grey,
+                "icc_size": icc.len(),
+                "encoding": encoding,
+            })
+        }
+    };
+
+    let extra_channels = image_meta
+        .ec_info
+        .iter()
+        .enumerate()
+        .map(|(ec_idx, ec)| {
+            let mut info = json!({
+                "index": ec_idx,
+                "name": &*ec.name,
+                "bit_depth": bit_depth_json(ec.bit_depth),
+                "dim_shift": ec.dim_shift,
+            });
+            let (ty, extra) = match ec.ty {
+                ExtraChannelType::Alpha { alpha_associated } => {
+                    ("alpha", json!({ "alpha_associated": alpha_associated }))
+                }
+                ExtraChannelType::Depth => ("depth", json!({})),
+                ExtraChannelType::SpotColour {
+                    red,
+                    green,
+                    blue,
+                    solidity,
+                } => (
+                    "spot_color",
+                    json!({ "color": [red, green, blue], "solidity": solidity }),
+                ),
+                ExtraChannelType::SelectionMask => ("selection_mask", json!({})),
+                ExtraChannelType::Black => ("black", json!({})),
+                ExtraChannelType::Cfa { cfa_channel } => {
+                    ("cfa", json!({ "cfa_channel": cfa_channel }))
+                }
+                ExtraChannelType::Thermal => ("thermal", json!({})),
+                ExtraChannelType::NonOptional => ("non_optional", json!({})),
+                ExtraChannelType::Optional => ("optional", json!({})),
+            };
+            info["type"] = ty.into();
+            if let (Value::Object(info), Value::Object(extra)) = (&mut info, extra) {
+                info.extend(extra);
+            }
+            info
+        })
+        .collect::<Vec<_>>();
+
+    let jpeg_reconstruction = match image.jpeg_reconstruction_status() {
+        JpegReconstructionStatus::Available => Some("available"),
+        JpegReconstructionStatus::Invalid => Some("invalid"),
+        JpegReconstructionStatus::Unavailable => None,
+        JpegReconstructionStatus::NeedMoreData => Some("partial"),
+    };
+
+    let aux_boxes = image
+        .aux_boxes()
+        .iter()
+        .map(|(ty, data)| {
+            let size = match data {
+                AuxBoxData::Data(data) => Some(data.len()),
+                _ => None,
+            };
+            json!({
+                "type": String::from_utf8_lossy(&ty),
+                "size": size,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let animated = image_meta.animation.is_some();
+    let mut frames = Vec::new();
+    for idx in 0..image.num_loaded_frames() + 1 {
+        let Some(frame) = image.frame(idx) else {
+            break;
+        };
+        let frame_header = frame.header();
+        let is_keyframe = frame_header.is_keyframe();
+        let frame_offset = image.frame_offset(idx).unwrap();
+        let toc = frame.toc();
+
+        let frame_type = match frame_header.frame_type {
+            FrameType::RegularFrame => "regular",
+            FrameType::LfFrame => "lf",
+            FrameType::ReferenceOnly => "reference_only",
+            FrameType::SkipProgressive => "skip_progressive",
+        };
+        let groups = toc
+            .iter_bitstream_order()
+            .map(|group| {
+                json!({
+                    "group": group_name(frame_header, group.kind),
+                    "size": group.size,
+                    "offset": frame_offset + group.offset,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut info = json!({
+            "index": idx,
+            "keyframe": is_keyframe,
+            "partial": !frame.is_loading_done(),
+            "name": &*frame_header.name,
+            "encoding": match frame_header.encoding {
+                Encoding::VarDct => "vardct",
+                Encoding::Modular => "modular",
+            },
+            "frame_type": frame_type,
+            "width": frame_header.color_sample_width(),
+            "height": frame_header.color_sample_height(),
+            "offset": frame_offset,
+            "header_size": toc.bookmark(),
+            "toc": groups,
+        });
+        if frame_header.frame_type == FrameType::LfFrame {
+            info["lf_level"] = frame_header.lf_level.into();
+        }
+        if frame_header.frame_type == FrameType::ReferenceOnly {
+            info["save_as_reference"] = frame_header.save_as_reference.into();
+        }
+        if frame_header.frame_type.is_normal_frame() {
+            info["x0"] = frame_header.x0.into();
+            info["y0"] = frame_header.y0.into();
+        }
+        if frame_header.do_ycbcr {
+            info["jpeg_upsampling"] = json!(frame_header.jpeg_upsampling);
+        }
+        if animated && is_keyframe {
+            info["duration"] = frame_header.duration.into();
+        }
+        frames.push(info);
+    }
+
+    json!({
+        "kind": format!("{:?}", image.reader().kind()),
+        "width": image.width(),
+        "height": image.height(),
+        "encoded_width": image_size.width,
+        "encoded_height": image_size.height,
+        "orientation": image_meta.orientation,
+        "bit_depth": bit_depth_json(image_meta.bit_depth),
+        "xyb_encoded": image_meta.xyb_encoded,
+        "color_encoding": color_encoding,
+        "animation": image_meta.animation.as_ref().map(|animation| json!({
+            "tps_numerator": animation.tps_numerator,
+            "tps_denominator": animation.tps_denominator,
+        })),
+        "extra_channels": extra_channels,
+        "jpeg_reconstruction": jpeg_reconstruction,
+        "aux_boxes": aux_boxes,
+        "frames": frames,
+        "partial": !image.is_loading_done(),
+    })
+}
+
+fn bit_depth_json(bit_depth: BitDepth) -> Value {
+    match bit_depth {
+        BitDepth::IntegerSample { bits_per_sample } => json!({
+            "bits_per_sample": bits_per_sample,
+            "float": false,
+        }),
+        BitDepth::FloatSample {
+            bits_per_sample,
+            exp_bits,
+        } => json!({
+            "bits_per_sample": bits_per_sample,
+            "float": true,
+            "exponent_bits": exp_bits,
+        }),
+    }
+}
+
+/// Returns the human-readable name of a group, including its position in the group grid.
+fn group_name(frame_header: &FrameHeader, kind: TocGroupKind) -> String {
+    match kind {
+        TocGroupKind::All => String::from("All"),
+        TocGroupKind::LfGlobal => String::from("LfGlobal"),
+        TocGroupKind::LfGroup(lf_group_idx) => {
+            let per_row = frame_header.lf_groups_per_row();
+            format!(
+                "LfGroup {lf_group_idx} ({}, {})",
+                lf_group_idx % per_row,
+                lf_group_idx / per_row
+            )
+        }
+        TocGroupKind::HfGlobal => String::from("HfGlobal"),
+        TocGroupKind::GroupPass {
+            pass_idx,
+            group_idx,
+        } => {
+            let per_row = frame_header.groups_per_row();
+            format!(
+                "Pass {pass_idx} Group {group_idx} ({}, {})",
+                group_idx % per_row,
+                group_idx / per_row
+            )
+        }
+    }
+}
+
+fn print_features(image: &JxlImage, frame_idx: usize, frame_header: &FrameHeader) -> Result<()> {
+    let flags = frame_header.flags;
+    if !flags.splines() && !flags.patches() && !flags.noise() {
+        return Ok(());
+    }
+    let Some(features) = image.frame_features(frame_idx).map_err(Error::ReadJxl)? else {
+        return Ok(());
+    };
+
+    let splines = features.splines();
+    if !splines.is_empty() {
+        let num_points = splines
+            .iter()
+            .map(|spline| spline.control_points().count())
+            .sum::<usize>();
+        println!(
+            "  Splines: {} ({num_points} control point{})",
+            splines.len(),
+            if num_points == 1 { "" } else { "s" },
+        );
+    }
+
+    let patches = features.patches();
+    if !patches.is_empty() {
+        let num_targets = patches
+            .iter()
+            .map(|patch| patch.patch_targets.len())
+            .sum::<usize>();
+        println!(
+            "  Patches: {} ({num_targets} target{})",
+            patches.len(),
+            if num_targets == 1 { "" } else { "s" },
+        );
+        for patch in patches {
+            println!(
+                "    Slot {}, {}x{} at ({}, {}), {} target{}",
+                patch.ref_idx,
+                patch.width,
+                patch.height,
+                patch.x0,
+                patch.y0,
+                patch.patch_targets.len(),
+                if patch.patch_targets.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                },
+            );
+        }
+    }
+
+    if let Some(noise) = features.noise() {
+        println!("  Noise LUT: {:?}", noise.lut);
+    }
     Ok(())
 }
 
+fn print_toc(frame_header: &FrameHeader, toc: &Toc, frame_offset: usize) {
+    let groups = toc.iter_bitstream_order().collect::<Vec<_>>();
+    let permuted = groups.windows(2).any(|w| w[0].kind > w[1].kind);
+    println!(
+        "  TOC ({} group{}{}):",
+        groups.len(),
+        if groups.len() == 1 { "" } else { "s" },
+        if permuted { ", permuted" } else { "" },
+    );
+    println!(
+        "    {:>5}  {:<32}  {:>10}  {:>10}",
+        "Order", "Group", "Size", "Offset"
+    );
+    for (order, group) in groups.iter().enumerate() {
+        println!(
+            "    {order:>5}  {:<32}  {:>10}  {:>#10x}",
+            group_name(frame_header, group.kind),
+            group.size,
+            frame_offset + group.offset,
+        );
+    }
+}
+
+/// Size of a group in SVG output, in pixels.
+const SVG_CELL: u32 = 24;
+/// Gap between sections in SVG output, in pixels.
+const SVG_GAP: u32 = 16;
+
+/// Group layout of a frame in SVG output.
+struct SvgFrame {
+    body: String,
+    width: u32,
+    height: u32,
+}
+
+/// Draws the grids of LF groups and pass groups of a frame, where each group is colored and
+/// labeled with its position in the bitstream.
+fn toc_svg_frame(frame_idx: usize, frame_header: &FrameHeader, toc: &Toc) -> SvgFrame {
+    let groups = toc.iter_bitstream_order().collect::<Vec<_>>();
+    let num_groups = groups.len().max(1) as f64;
+    let mut body = String::new();
+    let title_height = 20;
+    writeln!(
+        body,
+        r#"<text x="0" y="14" font-size="14">Frame #{frame_idx}</text>"#
+    )
+    .unwrap();
+
+    let lf_cols = frame_header.lf_groups_per_row();
+    let lf_rows = frame_header.num_lf_groups().div_ceil(lf_cols);
+    let cols = frame_header.groups_per_row();
+    let rows = frame_header.num_groups().div_ceil(cols);
+    let num_passes = frame_header.passes.num_passes;
+
+    // Sections are laid out horizontally: LF groups first, then one section per pass.
+    let lf_width = lf_cols * SVG_CELL;
+    let pass_width = cols * SVG_CELL;
+    let section_x = |section: u32| {
+        if section == 0 {
+            0
+        } else {
+            lf_width + SVG_GAP + (section - 1) * (pass_width + SVG_GAP)
+        }
+    };
+
+    for (order, group) in groups.iter().enumerate() {
+        let (section, idx, per_row) = match group.kind {
+            // Single-group frames are drawn as the only pass group.
+            TocGroupKind::All => (1, 0, cols),
+            TocGroupKind::LfGroup(idx) => (0, idx, lf_cols),
+            TocGroupKind::GroupPass {
+                pass_idx,
+                group_idx,
+            } => (1 + pass_idx, group_idx, cols),
+            _ => continue,
+        };
+        let x = section_x(section) + (idx % per_row) * SVG_CELL;
+        let y = title_height + (idx / per_row) * SVG_CELL;
+        let hue = (order as f64 / num_groups * 240.0) as u32;
+        writeln!(
+            body,
+            r#"<rect x="{x}" y="{y}" width="{SVG_CELL}" height="{SVG_CELL}" fill="hsl({hue}, 70%, 60%)" stroke="black" stroke-width="0.5"><title>{}: order {order}, {} bytes</title></rect>"#,
+            group_name(frame_header, group.kind),
+            group.size,
+        )
+        .unwrap();
+        writeln!(
+            body,
+            r#"<text x="{}" y="{}" font-size="8" text-anchor="middle">{order}</text>"#,
+            x + SVG_CELL / 2,
+            y + SVG_CELL / 2 + 3,
+        )
+        .unwrap();
+    }
+
+    SvgFrame {
+        body,
+        // Leave room for the title.
+        width: (section_x(num_passes) + pass_width).max(80),
+        height: title_height + lf_rows.max(rows) * SVG_CELL,
+    }
+}
+
+/// Stacks group layouts of frames vertically into an SVG document.
+fn toc_svg(frames: &[SvgFrame]) -> String {
+    let width = frames.iter().map(|f| f.width).max().unwrap_or(0);
+    let height = frames
+        .iter()
+        .map(|f| f.height + SVG_GAP)
+        .sum::<u32>()
+        .saturating_sub(SVG_GAP);
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif">"#
+    )
+    .unwrap();
+    let mut y = 0;
+    for frame in frames {
+        writeln!(out, r#"<g transform="translate(0, {y})">"#).unwrap();
+        out.push_str(&frame.body);
+        out.push_str("</g>\n");
+        y += frame.height + SVG_GAP;
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
 fn print_colour_encoding(encoding: &EnumColourEncoding, indent: &str) {
     print!("{indent}Colorspace: ");
     match encoding.colour_space {
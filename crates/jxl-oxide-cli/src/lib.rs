@@ -1,6 +1,10 @@
+pub mod boxes;
 pub mod commands;
+pub mod compare;
 pub mod decode;
 #[cfg(feature = "__devtools")]
+pub mod dump_groups;
+#[cfg(feature = "__devtools")]
 pub mod dump_jbrd;
 pub mod error;
 #[cfg(feature = "__devtools")]
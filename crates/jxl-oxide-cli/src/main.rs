@@ -31,6 +31,8 @@ fn main() -> std::process::ExitCode {
         Some(Subcommands::Decode(args)) => jxl_oxide_cli::decode::handle_decode(args),
         None => jxl_oxide_cli::decode::handle_decode(decode.unwrap()),
         Some(Subcommands::Info(args)) => jxl_oxide_cli::info::handle_info(args),
+        Some(Subcommands::Compare(args)) => jxl_oxide_cli::compare::handle_compare(args),
+        Some(Subcommands::Boxes(args)) => jxl_oxide_cli::boxes::handle_boxes(args),
         #[cfg(feature = "__devtools")]
         Some(Subcommands::GenerateFixture(args)) => {
             jxl_oxide_cli::generate_fixture::handle_generate_fixture(args);
@@ -42,6 +44,8 @@ fn main() -> std::process::ExitCode {
         }
         #[cfg(feature = "__devtools")]
         Some(Subcommands::DumpJbrd(args)) => jxl_oxide_cli::dump_jbrd::handle_dump_jbrd(args),
+        #[cfg(feature = "__devtools")]
+        Some(Subcommands::DumpGroups(args)) => jxl_oxide_cli::dump_groups::handle_dump_groups(args),
         #[cfg(feature = "__ffmpeg")]
         Some(Subcommands::SlowMotion(args)) => jxl_oxide_cli::slow_motion::handle_slow_motion(args),
     };
@@ -0,0 +1,168 @@
+use std::io::prelude::*;
+
+use jxl_oxide::color::ColourSpace;
+use jxl_oxide::{JxlImage, Render};
+
+/// Number of scanlines in a ZIP-compressed chunk.
+const LINES_PER_CHUNK: usize = 16;
+
+/// Writes the keyframe as a single-part scanline OpenEXR image with 32-bit float channels.
+///
+/// Chunks are ZIP compressed. Chromaticities are written if the color encoding of rendered images
+/// can be described with enum values; the image should've been rendered in linear light.
+pub(crate) fn write_exr<W: Write>(
+    output: W,
+    image: &JxlImage,
+    keyframe: &Render,
+) -> std::io::Result<()> {
+    let mut stream = keyframe.stream();
+    let width = stream.width() as usize;
+    let height = stream.height() as usize;
+    let channels = stream.channels() as usize;
+    let mut buf = vec![0f32; width * height * channels];
+    stream.write_to_buffer(&mut buf);
+
+    // Channels are sorted by name, as required by the format. `order[i]` is the index of i-th
+    // channel within an interleaved pixel.
+    let (names, order): (&[&str], &[usize]) = match channels {
+        1 => (&["Y"], &[0]),
+        2 => (&["A", "Y"], &[1, 0]),
+        3 => (&["B", "G", "R"], &[2, 1, 0]),
+        4 => (&["A", "B", "G", "R"], &[3, 2, 1, 0]),
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "unsupported number of channels",
+            ))
+        }
+    };
+
+    let mut header = Vec::new();
+    header.extend_from_slice(b"\x76\x2f\x31\x01");
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for name in names {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        // Pixel type FLOAT, pLinear, reserved, x and y sampling.
+        chlist.extend_from_slice(&2i32.to_le_bytes());
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist);
+    // ZIP_COMPRESSION
+    write_attribute(&mut header, "compression", "compression", &[3]);
+
+    let mut window = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    // INCREASING_Y
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+
+    let encoding = image.rendered_color_encoding();
+    if let Some(encoding) = encoding.filter(|encoding| encoding.colour_space == ColourSpace::Rgb) {
+        let [red, green, blue] = encoding.primaries.as_chromaticity();
+        let white = encoding.white_point.as_chromaticity();
+        let mut chromaticities = Vec::new();
+        for v in [red, green, blue, white].into_iter().flatten() {
+            chromaticities.extend_from_slice(&v.to_le_bytes());
+        }
+        write_attribute(
+            &mut header,
+            "chromaticities",
+            "chromaticities",
+            &chromaticities,
+        );
+    }
+    header.push(0);
+
+    tracing::debug!("Compressing image data");
+    let row_samples = width * channels;
+    let chunks = buf
+        .chunks(row_samples * LINES_PER_CHUNK)
+        .map(|rows| {
+            let mut data = Vec::with_capacity(rows.len() * 4);
+            for row in rows.chunks_exact(row_samples) {
+                for &c in order {
+                    for px in row.chunks_exact(channels) {
+                        data.extend_from_slice(&px[c].to_le_bytes());
+                    }
+                }
+            }
+            compress_zip(data)
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = std::io::BufWriter::new(output);
+    output.write_all(&header)?;
+    let mut offset = (header.len() + chunks.len() * 8) as u64;
+    for chunk in &chunks {
+        output.write_all(&offset.to_le_bytes())?;
+        offset += 8 + chunk.len() as u64;
+    }
+    for (idx, chunk) in chunks.into_iter().enumerate() {
+        let y = (idx * LINES_PER_CHUNK) as i32;
+        output.write_all(&y.to_le_bytes())?;
+        output.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        output.write_all(&chunk)?;
+    }
+    output.flush()
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(ty.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Compresses chunk data with ZIP compression of OpenEXR.
+///
+/// Data is stored as is if compression doesn't make it smaller.
+fn compress_zip(data: Vec<u8>) -> Vec<u8> {
+    // Split bytes into even and odd indices, then delta-code them.
+    let half = data.len().div_ceil(2);
+    let mut reordered = vec![0u8; data.len()];
+    for (idx, &b) in data.iter().enumerate() {
+        let pos = if idx % 2 == 0 {
+            idx / 2
+        } else {
+            half + idx / 2
+        };
+        reordered[pos] = b;
+    }
+    let mut prev = reordered.first().copied().unwrap_or(0);
+    for b in reordered.iter_mut().skip(1) {
+        let cur = *b;
+        *b = cur.wrapping_sub(prev).wrapping_add(128);
+        prev = cur;
+    }
+
+    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&reordered, 6);
+    if compressed.len() < data.len() {
+        compressed
+    } else {
+        data
+    }
+}
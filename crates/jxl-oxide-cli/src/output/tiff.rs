@@ -0,0 +1,158 @@
+use std::io::prelude::*;
+
+use jxl_oxide::{JxlImage, Render};
+
+/// Approximate size of a strip in bytes.
+const STRIP_SIZE: usize = 1 << 16;
+
+// Field types.
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const UNDEFINED: u16 = 7;
+
+/// Writes the keyframe as an uncompressed little-endian TIFF image with 32-bit float samples.
+///
+/// Samples are written as rendered, with the ICC profile of rendered images embedded. Alpha is
+/// written as unassociated alpha.
+pub(crate) fn write_tiff<W: Write>(
+    output: W,
+    image: &JxlImage,
+    keyframe: &Render,
+) -> std::io::Result<()> {
+    let mut stream = keyframe.stream();
+    let width = stream.width() as usize;
+    let height = stream.height() as usize;
+    let channels = stream.channels() as usize;
+    if !(1..=4).contains(&channels) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "unsupported number of channels",
+        ));
+    }
+    let mut buf = vec![0f32; width * height * channels];
+    stream.write_to_buffer(&mut buf);
+
+    let row_bytes = width * channels * 4;
+    let rows_per_strip = (STRIP_SIZE / row_bytes.max(1)).clamp(1, height.max(1));
+    let strip_bytes = buf
+        .chunks(width * channels * rows_per_strip)
+        .map(|strip| strip.len() as u32 * 4)
+        .collect::<Vec<_>>();
+    let icc = image.rendered_icc();
+
+    // Layout: header, strips, ICC profile, out-of-line field values, then IFD.
+    let icc_offset = 8 + (buf.len() * 4) as u64;
+    let mut extra_offset = icc_offset + icc.len() as u64;
+    extra_offset += extra_offset % 2;
+
+    let mut strip_offsets = Vec::with_capacity(strip_bytes.len());
+    let mut offset = 8u64;
+    for &bytes in &strip_bytes {
+        strip_offsets.push(offset as u32);
+        offset += bytes as u64;
+    }
+
+    let has_alpha = matches!(channels, 2 | 4);
+    let photometric = if channels < 3 { 1 } else { 2 };
+    let mut fields = vec![
+        Field::long(256, &[width as u32]),
+        Field::long(257, &[height as u32]),
+        Field::short(258, &vec![32; channels]),
+        Field::short(259, &[1]),
+        Field::short(262, &[photometric]),
+        Field::long(273, &strip_offsets),
+        Field::short(277, &[channels as u16]),
+        Field::long(278, &[rows_per_strip as u32]),
+        Field::long(279, &strip_bytes),
+        Field::short(284, &[1]),
+    ];
+    if has_alpha {
+        fields.push(Field::short(338, &[2]));
+    }
+    fields.push(Field::short(339, &vec![3; channels]));
+    if !icc.is_empty() {
+        fields.push(Field {
+            tag: 34675,
+            ty: UNDEFINED,
+            count: icc.len() as u32,
+            data: Vec::new(),
+        });
+    }
+
+    // Place values which don't fit in the entry.
+    let mut extra = Vec::new();
+    let mut entries = Vec::with_capacity(fields.len());
+    for field in &fields {
+        let value = if field.tag == 34675 {
+            icc_offset
+        } else if field.data.len() <= 4 {
+            let mut value = [0u8; 4];
+            value[..field.data.len()].copy_from_slice(&field.data);
+            u32::from_le_bytes(value) as u64
+        } else {
+            let offset = extra_offset + extra.len() as u64;
+            extra.extend_from_slice(&field.data);
+            offset
+        };
+        entries.push((field, value as u32));
+    }
+    let ifd_offset = extra_offset + extra.len() as u64;
+    let file_size = ifd_offset + 6 + entries.len() as u64 * 12;
+    if file_size > u32::MAX as u64 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "image too large for TIFF",
+        ));
+    }
+    let ifd_offset = ifd_offset as u32;
+
+    let mut output = std::io::BufWriter::new(output);
+    output.write_all(b"II\x2a\x00")?;
+    output.write_all(&ifd_offset.to_le_bytes())?;
+    for sample in &buf {
+        output.write_all(&sample.to_le_bytes())?;
+    }
+    output.write_all(&icc)?;
+    if (icc_offset + icc.len() as u64) % 2 == 1 {
+        output.write_all(&[0])?;
+    }
+    output.write_all(&extra)?;
+
+    output.write_all(&(entries.len() as u16).to_le_bytes())?;
+    for (field, value) in entries {
+        output.write_all(&field.tag.to_le_bytes())?;
+        output.write_all(&field.ty.to_le_bytes())?;
+        output.write_all(&field.count.to_le_bytes())?;
+        output.write_all(&value.to_le_bytes())?;
+    }
+    output.write_all(&0u32.to_le_bytes())?;
+    output.flush()
+}
+
+/// IFD entry, with its value in little-endian bytes.
+struct Field {
+    tag: u16,
+    ty: u16,
+    count: u32,
+    data: Vec<u8>,
+}
+
+impl Field {
+    fn short(tag: u16, values: &[u16]) -> Self {
+        Self {
+            tag,
+            ty: SHORT,
+            count: values.len() as u32,
+            data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    fn long(tag: u16, values: &[u32]) -> Self {
+        Self {
+            tag,
+            ty: LONG,
+            count: values.len() as u32,
+            data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+}
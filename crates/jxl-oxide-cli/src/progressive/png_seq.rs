@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 
+use jxl_oxide::export::{self, ExportOptions};
 use jxl_oxide::{JxlImage, Render};
 
 use crate::{Error, Result};
@@ -35,18 +36,10 @@ impl Context {
         output_path.push(format!("frame{}.png", self.idx));
         let output = std::fs::File::create(output_path).map_err(Error::WriteImage)?;
 
-        let width = image.width();
-        let height = image.height();
-        crate::output::write_png(
-            output,
-            image,
-            std::slice::from_ref(render),
-            image.pixel_format(),
-            None,
-            width,
-            height,
-        )
-        .map_err(Error::WriteImage)?;
+        let mut options = ExportOptions::from_image(image);
+        options.animation = None;
+        export::write_png(output, std::slice::from_ref(render), &options)
+            .map_err(Error::WriteImage)?;
 
         self.idx += 1;
         Ok(())
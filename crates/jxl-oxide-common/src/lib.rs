@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use jxl_bitstream::Bitstream;
 
 #[macro_export]
@@ -287,8 +290,57 @@ where
 }
 
 /// Name type which is read by some JPEG XL headers.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct Name(String);
+///
+/// Names are stored in reference-counted buffers, so that identical names can share a single
+/// buffer using [`NamePool`]. Empty names don't allocate. Mutating a name through
+/// [`DerefMut`][std::ops::DerefMut] clones the buffer if it's shared.
+#[derive(Clone, Default)]
+pub struct Name(Option<Arc<String>>);
+
+static EMPTY_NAME: String = String::new();
+
+impl Name {
+    /// Creates a new name.
+    pub fn new(name: &str) -> Self {
+        if name.is_empty() {
+            Self(None)
+        } else {
+            Self(Some(Arc::new(name.to_owned())))
+        }
+    }
+}
+
+impl std::fmt::Debug for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Name").field(&**self).finish()
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Name {}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl std::hash::Hash for Name {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
 
 impl<Ctx> Bundle<Ctx> for Name {
     type Error = jxl_bitstream::Error;
@@ -299,9 +351,9 @@ impl<Ctx> Bundle<Ctx> for Name {
         for b in &mut data {
             *b = bitstream.read_bits(8)? as u8;
         }
-        let name = String::from_utf8(data)
+        let name = std::str::from_utf8(&data)
             .map_err(|_| jxl_bitstream::Error::ValidationFailed("non-UTF-8 name"))?;
-        Ok(Self(name))
+        Ok(Self::new(name))
     }
 }
 
@@ -309,12 +361,60 @@ impl std::ops::Deref for Name {
     type Target = String;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        self.0.as_deref().unwrap_or(&EMPTY_NAME)
     }
 }
 
 impl std::ops::DerefMut for Name {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        Arc::make_mut(self.0.get_or_insert_with(Default::default))
+    }
+}
+
+/// Buffer of an interned name, which can be looked up with `&str`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct InternedName(Arc<String>);
+
+impl std::borrow::Borrow<str> for InternedName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Pool of interned [`Name`]s, used to share buffers of identical names across frames.
+#[derive(Debug, Default)]
+pub struct NamePool {
+    names: HashSet<InternedName>,
+    total_bytes: usize,
+}
+
+impl NamePool {
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns the name, returning a name which shares the buffer with the identical one
+    /// interned before, if any.
+    pub fn intern(&mut self, name: &Name) -> Name {
+        let Some(buf) = name.0.as_ref().filter(|buf| !buf.is_empty()) else {
+            return Name(None);
+        };
+        if let Some(interned) = self.names.get(buf.as_str()) {
+            return Name(Some(Arc::clone(&interned.0)));
+        }
+        self.total_bytes += buf.len();
+        self.names.insert(InternedName(Arc::clone(buf)));
+        name.clone()
+    }
+
+    /// Returns whether the pool contains the name.
+    pub fn contains(&self, name: &str) -> bool {
+        name.is_empty() || self.names.contains(name)
+    }
+
+    /// Returns the total size of distinct names in the pool, in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
     }
 }
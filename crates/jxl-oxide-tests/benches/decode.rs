@@ -23,6 +23,148 @@ fn decode(c: &mut Criterion) {
     bench_one(c, &bench_path, "starrail.d1-e6", &pool);
     bench_one(c, &bench_path, "genshin-cafe.d2-e6-epf2", &pool);
     bench_one(c, &bench_path, "genshin-cafe.d2-e6-epf3", &pool);
+
+    #[cfg(feature = "encode")]
+    bench_lossless_synthetic(c, &pool);
+    #[cfg(feature = "encode")]
+    bench_lossless_predictors(c, &pool);
+    #[cfg(feature = "encode")]
+    bench_stream_u8(c, &pool);
+}
+
+/// Benchmarks conversion of a render into interleaved 8-bit samples, comparing vectorized
+/// per-row path against scalar per-sample path.
+#[cfg(feature = "encode")]
+fn bench_stream_u8(c: &mut Criterion, pool: &JxlThreadPool) {
+    let width = 2048;
+    let height = 1536;
+    let samples = (0..width * height * 4)
+        .map(|idx| ((idx * 7 + idx / 4099) % 256) as u8)
+        .collect::<Vec<_>>();
+    let data = jxl_oxide::JxlEncoder::new()
+        .encode(jxl_oxide::encode::ImageRef::from_u8(
+            width, height, 4, &samples,
+        ))
+        .unwrap();
+
+    let mut g = c.benchmark_group("stream-u8");
+    g.throughput(criterion::Throughput::Elements((width * height) as u64));
+    for (name, force_scalar) in [("row-simd", false), ("per-sample", true)] {
+        let mut image = jxl_oxide::JxlImage::builder()
+            .pool(pool.clone())
+            .force_scalar(force_scalar)
+            .read(Cursor::new(&data))
+            .unwrap();
+        // Color conversion produces floating point samples, as in lossy images.
+        image.set_render_linear(true);
+        let render = image.render_frame(0).unwrap();
+        let mut buf = vec![0u8; width * height * 4];
+        g.bench_function(name, |b| {
+            b.iter(|| render.stream().write_to_buffer(black_box(&mut buf)))
+        });
+    }
+    g.finish();
+    // Scalar code paths are forced globally, restore the default.
+    jxl_oxide::JxlImage::builder()
+        .force_scalar(false)
+        .build_uninit();
+}
+
+/// Benchmarks a large lossless image, where RCT and palette passes take meaningful fraction of
+/// decode time.
+#[cfg(feature = "encode")]
+fn bench_lossless_synthetic(c: &mut Criterion, pool: &JxlThreadPool) {
+    let width = 4096;
+    let height = 3072;
+
+    // Deterministic noisy gradient, roughly resembling a photo.
+    let mut state = 0x2545f491u32;
+    let mut samples = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            for c in 0..3 {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let base = (x * (c + 1) + y * 2) / 32;
+                samples.push(((base + (state % 8) as usize) % 256) as u8);
+            }
+        }
+    }
+    let data = jxl_oxide::JxlEncoder::new()
+        .encode(jxl_oxide::encode::ImageRef::from_u8(
+            width, height, 3, &samples,
+        ))
+        .unwrap();
+
+    let mut g = c.benchmark_group("synthetic-lossless");
+    g.warm_up_time(Duration::from_secs(5));
+    g.measurement_time(Duration::from_secs(15));
+    g.throughput(criterion::Throughput::Elements((width * height) as u64));
+    g.bench_function("preferred-color", |b| {
+        b.iter_with_large_drop(|| {
+            let image = jxl_oxide::JxlImage::builder()
+                .pool(pool.clone())
+                .read(Cursor::new(&data))
+                .unwrap();
+            image.render_frame(black_box(0))
+        })
+    });
+    g.finish();
+}
+
+/// Benchmarks decoding of 16-bit lossless images with each Modular predictor, comparing SIMD
+/// leaf evaluation against scalar code path.
+#[cfg(feature = "encode")]
+fn bench_lossless_predictors(c: &mut Criterion, pool: &JxlThreadPool) {
+    use jxl_oxide::encode::{ImageRef, Predictor};
+
+    let width = 2048;
+    let height = 1536;
+
+    let mut state = 0x2545f491u32;
+    let mut samples = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let base = (x * 7 + y * 3) * 4;
+            samples.push(((base + (state % 64) as usize) % 65536) as u16);
+        }
+    }
+
+    let mut g = c.benchmark_group("lossless-predictor");
+    g.warm_up_time(Duration::from_secs(3));
+    g.measurement_time(Duration::from_secs(10));
+    g.throughput(criterion::Throughput::Elements((width * height) as u64));
+    for (name, predictor) in [
+        ("zero", Predictor::Zero),
+        ("north", Predictor::North),
+        ("gradient", Predictor::Gradient),
+    ] {
+        let data = jxl_oxide::JxlEncoder::new()
+            .predictor(predictor)
+            .encode(ImageRef::from_u16(width, height, 1, &samples))
+            .unwrap();
+        for (variant, force_scalar) in [("simd", false), ("scalar", true)] {
+            g.bench_function(format!("{name}-{variant}"), |b| {
+                b.iter_with_large_drop(|| {
+                    let image = jxl_oxide::JxlImage::builder()
+                        .pool(pool.clone())
+                        .force_scalar(force_scalar)
+                        .read(Cursor::new(&data))
+                        .unwrap();
+                    image.render_frame(black_box(0))
+                })
+            });
+        }
+    }
+    g.finish();
+    // Scalar code paths are forced globally, restore the default.
+    jxl_oxide::JxlImage::builder()
+        .force_scalar(false)
+        .build_uninit();
 }
 
 fn bench_one(c: &mut Criterion, bench_path: &Path, name: &str, pool: &JxlThreadPool) {
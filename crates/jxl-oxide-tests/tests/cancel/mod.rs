@@ -0,0 +1,68 @@
+use jxl_oxide::{CancellationToken, JxlImage};
+
+use crate::fixture;
+
+#[test]
+fn cancellation_token() {
+    let (_, jxl) = fixture::image_u8(64, 64, 3);
+
+    let token = CancellationToken::new();
+    let image = JxlImage::builder()
+        .cancellation_token(token.clone())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    token.cancel();
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(err.to_string(), "rendering cancelled");
+
+    let token = CancellationToken::with_deadline(std::time::Instant::now());
+    let image = JxlImage::builder()
+        .cancellation_token(token.clone())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    assert!(image.render_frame(0).is_err());
+    assert!(token.is_cancelled());
+
+    let image = JxlImage::builder()
+        .cancellation_token(CancellationToken::new())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    image.render_frame(0).unwrap();
+}
+
+#[test]
+fn resume_cancelled_render() {
+    let (_, jxl) = fixture::image_u8(300, 280, 3);
+
+    let token = CancellationToken::new();
+    let image = JxlImage::builder()
+        .cancellation_token(token.clone())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    token.cancel();
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(err.to_string(), "rendering cancelled");
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(err.to_string(), "rendering cancelled");
+
+    token.reset();
+    let expected = fixture::read(&jxl);
+    assert!(fixture::render_f32(&image).2 == fixture::render_f32(&expected).2);
+}
+
+#[test]
+fn resume_after_deadline() {
+    let (_, jxl) = fixture::image_u8(64, 64, 3);
+
+    let token = CancellationToken::with_deadline(std::time::Instant::now());
+    let image = JxlImage::builder()
+        .cancellation_token(token.clone())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    let err = image.render_frame(0).unwrap_err();
+    assert!(matches!(err, jxl_oxide::Error::Cancelled), "{err}");
+
+    token.reset();
+    assert!(!token.is_cancelled());
+    image.render_frame(0).unwrap();
+}
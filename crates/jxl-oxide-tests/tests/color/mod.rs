@@ -0,0 +1,169 @@
+use jxl_oxide::color::TransferFunction;
+use jxl_oxide::{EnumColourEncoding, PixelFormat, RenderingIntent};
+
+use crate::fixture;
+
+#[test]
+fn unsupported_feature() {
+    let (_, jxl) = fixture::image_u8(16, 8, 3);
+
+    let mut image = fixture::read(&jxl);
+
+    // Rename `desc` tag of the sRGB profile to `lumi`, so that the profile cannot be represented
+    // as an enum color encoding.
+    let mut icc = image.rendered_icc();
+    let tag_count = u32::from_be_bytes(icc[128..132].try_into().unwrap()) as usize;
+    let desc = (0..tag_count)
+        .map(|idx| 132 + idx * 12)
+        .find(|&offset| &icc[offset..offset + 4] == b"desc")
+        .unwrap();
+    icc[desc..desc + 4].copy_from_slice(b"lumi");
+
+    image.set_cms(jxl_oxide::NullCms);
+    assert!(!image.requires_external_cms().unwrap());
+    let encoding = jxl_oxide::ColorEncodingWithProfile::with_icc(&icc).unwrap();
+    assert!(!encoding.is_representable_without_cms());
+    image.request_icc(&icc).unwrap();
+    assert!(image.requires_external_cms().unwrap());
+    let err = image.render_frame(0).unwrap_err();
+    // The underlying color management error is kept.
+    assert!(matches!(err, jxl_oxide::Error::Color(_)));
+    assert_eq!(
+        err.unsupported_feature(),
+        Some(jxl_oxide::UnsupportedFeature::ColorManagement)
+    );
+}
+
+/// CMS which converts RGB to CMYK naively, without black generation.
+struct NaiveCmykCms;
+
+impl jxl_oxide::ColorManagementSystem for NaiveCmykCms {
+    fn transform_impl(
+        &self,
+        _from: &[u8],
+        to: &[u8],
+        _intent: jxl_oxide::RenderingIntent,
+        channels: &mut [&mut [f32]],
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        assert_eq!(&to[0x10..0x14], b"CMYK");
+        let [r, g, b, k, ..] = channels else {
+            panic!("black channel is not provided");
+        };
+        for v in r.iter_mut().chain(&mut **g).chain(&mut **b) {
+            *v = 1.0 - *v;
+        }
+        k.fill(0.0);
+        Ok(4)
+    }
+}
+
+#[test]
+fn request_cmyk_icc() {
+    let (width, height) = (16usize, 8usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut image = fixture::read(&jxl);
+
+    // CMS receives samples in linear sRGB.
+    let mut srgb_linear = jxl_oxide::EnumColourEncoding::srgb(jxl_oxide::RenderingIntent::Relative);
+    srgb_linear.tf = jxl_oxide::color::TransferFunction::Linear;
+    image.request_color_encoding(srgb_linear);
+    let render = image.render_frame(0).unwrap();
+    let mut linear = vec![0u8; width * height * 3];
+    render.stream().write_to_buffer(&mut linear);
+
+    // Header-only CMYK profile.
+    let mut icc = vec![0u8; 128];
+    icc[..4].copy_from_slice(&128u32.to_be_bytes());
+    icc[0x10..0x14].copy_from_slice(b"CMYK");
+
+    image.set_cms(NaiveCmykCms);
+    image.request_icc(&icc).unwrap();
+    assert_eq!(image.pixel_format(), PixelFormat::Cmyk);
+
+    let render = image.render_frame(0).unwrap();
+    let mut stream = render.stream();
+    assert_eq!(stream.channels(), 4);
+    let mut buf = vec![0u8; width * height * 4];
+    stream.write_to_buffer(&mut buf);
+
+    // Samples are inverted back so that 0 means full ink.
+    for (cmyk, rgb) in buf.chunks_exact(4).zip(linear.chunks_exact(3)) {
+        assert_eq!(&cmyk[..3], rgb);
+        assert_eq!(cmyk[3], 255);
+    }
+}
+
+#[test]
+fn request_custom_color_encoding() {
+    let (width, height) = (16usize, 8usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+    let mut image = fixture::read(&jxl);
+
+    let mut render_with = |encoding: EnumColourEncoding| {
+        image.request_color_encoding(encoding);
+        let render = image.render_frame(0).unwrap();
+        let mut buf = vec![0f32; width * height * 3];
+        render.stream().write_to_buffer(&mut buf);
+        (buf, image.rendered_cicp(), image.rendered_icc())
+    };
+
+    // sRGB gamut described with custom values.
+    let (expected, ..) = render_with(EnumColourEncoding::srgb_gamma22(RenderingIntent::Relative));
+    let (custom, cicp, _) = render_with(EnumColourEncoding::custom_rgb(
+        [0.3127, 0.329],
+        [[0.64, 0.33], [0.3, 0.6], [0.15, 0.06]],
+        TransferFunction::gamma(2.2),
+        RenderingIntent::Relative,
+    ));
+    assert_eq!(cicp, None);
+    for (a, b) in custom.iter().zip(&expected) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    // ACEScg
+    let (acescg, _, icc) = render_with(EnumColourEncoding::custom_rgb(
+        [0.32168, 0.33767],
+        [[0.713, 0.293], [0.165, 0.83], [0.128, 0.044]],
+        TransferFunction::Linear,
+        RenderingIntent::Relative,
+    ));
+    assert!(!icc.is_empty());
+    assert!(acescg
+        .iter()
+        .zip(&expected)
+        .any(|(a, b)| (a - b).abs() > 1e-2));
+}
+
+#[test]
+fn gamut_stats() {
+    let (width, height) = (16usize, 8usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+    let mut image = fixture::read(&jxl);
+
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.gamut_stats(), None);
+
+    image.set_collect_gamut_stats(true);
+    let render = image.render_frame(0).unwrap();
+    let stats = render.gamut_stats().unwrap();
+    assert_eq!(stats.total_pixels, width * height);
+    assert_eq!(stats.clipped_pixels, 0);
+
+    // Gamut narrower than sRGB.
+    image.request_color_encoding(EnumColourEncoding::custom_rgb(
+        [0.3127, 0.329],
+        [[0.5, 0.35], [0.32, 0.45], [0.25, 0.2]],
+        TransferFunction::Srgb,
+        RenderingIntent::Relative,
+    ));
+    let render = image.render_frame(0).unwrap();
+    let stats = render.gamut_stats().unwrap();
+    assert!(stats.clipped_pixels > 0);
+    assert!(stats.max_overshoot > 0.0);
+    assert!(stats.clipped_ratio() > 0.0 && stats.clipped_ratio() <= 1.0);
+
+    let mut buf = vec![0f32; width * height * 3];
+    render.stream().write_to_buffer(&mut buf);
+    assert!(buf.iter().all(|v| (0.0..=1.0).contains(v)));
+}
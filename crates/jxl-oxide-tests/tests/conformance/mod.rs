@@ -362,3 +362,42 @@ conformance_test! {
         0.0001,
     ),
 }
+
+#[test]
+fn error_isolation() {
+    let path = util::conformance_path("animation_newtons_cradle");
+    let mut data = std::fs::read(path).unwrap();
+    // Frame offsets are relative to the codestream.
+    assert!(data.starts_with(&[0xff, 0x0a]));
+
+    let image = JxlImage::builder()
+        .read(std::io::Cursor::new(&data))
+        .unwrap();
+    let num_keyframes = image.num_loaded_keyframes();
+    let corrupt_keyframe = num_keyframes / 2;
+    let corrupt_frame = image
+        .frames()
+        .filter(|frame| frame.is_keyframe())
+        .nth(corrupt_keyframe)
+        .unwrap();
+    let group_start = corrupt_frame.offset() + corrupt_frame.header_bytes();
+    data[group_start..][..corrupt_frame.group_bytes()].fill(0xff);
+
+    let image = JxlImage::builder()
+        .read(std::io::Cursor::new(&data))
+        .unwrap();
+    assert_eq!(image.num_loaded_keyframes(), num_keyframes);
+    assert!(image.render_frame(corrupt_keyframe).is_err());
+
+    let image = JxlImage::builder()
+        .error_isolation(true)
+        .read(std::io::Cursor::new(&data))
+        .unwrap();
+    for idx in 0..num_keyframes {
+        image.render_frame(idx).expect("failed to render frame");
+        assert_eq!(
+            image.substituted_error(idx).is_some(),
+            idx == corrupt_keyframe
+        );
+    }
+}
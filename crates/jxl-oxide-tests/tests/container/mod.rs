@@ -0,0 +1,319 @@
+use jxl_oxide::JxlImage;
+
+use crate::fixture;
+
+#[test]
+fn gain_map_box() {
+    let (_, codestream) = fixture::image_u8(16, 16, 3);
+    let (gain_map_samples, gain_map_codestream) = fixture::image_u8(8, 8, 1);
+
+    let metadata = [0u8, 0, 0, 0, 0x40];
+    let mut jhgm = vec![0u8];
+    jhgm.extend_from_slice(&(metadata.len() as u16).to_be_bytes());
+    jhgm.extend_from_slice(&metadata);
+    jhgm.extend_from_slice(&[1, 1]); // color_encoding, all_default
+    jhgm.extend_from_slice(&0u32.to_be_bytes()); // no alternate ICC profile
+    jhgm.extend_from_slice(&gain_map_codestream);
+
+    let jxl = fixture::write_container(&[(*b"jxlc", &codestream), (*b"jhgm", &jhgm)]);
+
+    let image = fixture::read(&jxl);
+    let gain_map = image.gain_map().unwrap().unwrap();
+    assert_eq!(gain_map.metadata(), &metadata);
+    assert_eq!(gain_map.raw().version(), 0);
+    assert!(matches!(
+        gain_map.raw().color_encoding(),
+        Some(jxl_oxide::color::ColourEncoding::Enum(_))
+    ));
+    assert!(gain_map.raw().alt_icc().is_none());
+    assert_eq!(gain_map.image().width(), 8);
+
+    let render = gain_map.render().unwrap();
+    let mut stream = render.stream();
+    assert_eq!(stream.channels(), 1);
+    let mut buf = vec![0u8; 64];
+    stream.write_to_buffer(&mut buf);
+    assert!(buf == gain_map_samples, "decoded gain map differs");
+
+    let codestream_only = fixture::read(&codestream);
+    assert!(codestream_only.gain_map().unwrap().is_not_found());
+}
+
+/// Wraps data in a Brotli stream consisting of a single uncompressed meta-block.
+fn brotli_stored(data: &[u8]) -> Vec<u8> {
+    // WBITS = 16, ISLAST = 0, MNIBBLES = 4, MLEN - 1, ISUNCOMPRESSED = 1, padded to 24 bits.
+    let header = (((data.len() - 1) as u32) << 4) | (1 << 20);
+    let mut out = header.to_le_bytes()[..3].to_vec();
+    out.extend_from_slice(data);
+    // ISLAST = 1, ISLASTEMPTY = 1
+    out.push(0b11);
+    out
+}
+
+#[test]
+fn xmp_split_boxes() {
+    let (_, codestream) = fixture::image_u8(16, 16, 3);
+
+    let xmp = b"<x:xmpmeta xmlns:x='adobe:ns:meta/'><rdf:RDF/></x:xmpmeta>";
+    let (first, rest) = xmp.split_at(20);
+    let (second, third) = rest.split_at(16);
+    let mut brob = b"xml ".to_vec();
+    brob.extend_from_slice(&brotli_stored(second));
+
+    let jxl = fixture::write_container(&[
+        (*b"xml ", first),
+        (*b"jxlc", &codestream),
+        (*b"brob", &brob),
+        (*b"xml ", third),
+    ]);
+    let image = fixture::read(&jxl);
+    assert_eq!(image.aux_boxes().first_xml().unwrap(), first);
+    assert_eq!(&*image.xmp().unwrap(), xmp);
+
+    let jxl = fixture::write_container(&[(*b"jxlc", &codestream), (*b"xml ", xmp)]);
+    let image = fixture::read(&jxl);
+    assert_eq!(&*image.xmp().unwrap(), xmp);
+
+    let jxl = fixture::write_container(&[(*b"jxlc", &codestream)]);
+    let image = fixture::read(&jxl);
+    assert!(image.xmp().is_not_found());
+}
+
+/// Creates big endian Exif box data with the given orientation, camera model and original
+/// timestamp.
+fn exif_box(orientation: u16, model: &str, date_time_original: &str) -> Vec<u8> {
+    let mut model = model.as_bytes().to_vec();
+    model.push(0);
+    let mut date_time = date_time_original.as_bytes().to_vec();
+    date_time.push(0);
+
+    // IFD0 with 3 entries at offset 8, followed by Exif IFD with 1 entry, and then values.
+    let ifd0_len = 2 + 3 * 12 + 4;
+    let exif_ifd_offset = 8 + ifd0_len;
+    let values_offset = exif_ifd_offset + 2 + 12 + 4;
+    let model_offset = values_offset;
+    let date_time_offset = model_offset + model.len() as u32;
+
+    let mut tiff = b"MM\0*".to_vec();
+    tiff.extend_from_slice(&8u32.to_be_bytes());
+    let ifd = |tiff: &mut Vec<u8>, entries: &[(u16, u16, u32, [u8; 4])]| {
+        tiff.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (tag, ty, count, value) in entries {
+            tiff.extend_from_slice(&tag.to_be_bytes());
+            tiff.extend_from_slice(&ty.to_be_bytes());
+            tiff.extend_from_slice(&count.to_be_bytes());
+            tiff.extend_from_slice(value);
+        }
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+    };
+    let [o0, o1] = orientation.to_be_bytes();
+    ifd(
+        &mut tiff,
+        &[
+            (0x0110, 2, model.len() as u32, model_offset.to_be_bytes()),
+            (0x0112, 3, 1, [o0, o1, 0, 0]),
+            (0x8769, 4, 1, exif_ifd_offset.to_be_bytes()),
+        ],
+    );
+    ifd(
+        &mut tiff,
+        &[(
+            0x9003,
+            2,
+            date_time.len() as u32,
+            date_time_offset.to_be_bytes(),
+        )],
+    );
+    tiff.extend_from_slice(&model);
+    tiff.extend_from_slice(&date_time);
+
+    let mut exif = 0u32.to_be_bytes().to_vec();
+    exif.extend_from_slice(&tiff);
+    exif
+}
+
+#[test]
+fn exif_fields() {
+    let (_, codestream) = fixture::image_u8(8, 8, 3);
+    let exif = exif_box(6, "Camera", "2024:01:02 03:04:05");
+    let jxl = fixture::write_container(&[(*b"jxlc", &codestream), (*b"Exif", &exif)]);
+
+    let image = fixture::read(&jxl);
+    let exif = image.aux_boxes().first_exif().unwrap().unwrap();
+    assert_eq!(exif.tiff_header_offset(), 0);
+    assert_eq!(exif.orientation(), Some(6));
+    assert_eq!(exif.model(), Some("Camera"));
+    assert_eq!(exif.make(), None);
+    assert_eq!(exif.date_time_original(), Some("2024:01:02 03:04:05"));
+
+    let tags = exif.ifd0().unwrap().map(|f| f.tag()).collect::<Vec<_>>();
+    assert_eq!(tags, [0x0110, 0x0112, 0x8769]);
+}
+
+#[test]
+fn container_box_events() {
+    use jxl_oxide::container::{ContainerDetectingReader, ParseEvent};
+
+    let (_, codestream) = fixture::image_u8(37, 23, 3);
+
+    // Container with an unknown box, an empty box and the codestream in the last box.
+    let mut container = fixture::SIGNATURE.to_vec();
+    let mut expected = vec![(*b"JXL ", 0u64, 8usize, Some(4u64))];
+    for (ty, payload) in [
+        (b"abcd", &b"unknown"[..]),
+        (b"xml ", &[]),
+        (b"jxlc", &codestream),
+    ] {
+        expected.push((*ty, container.len() as u64, 8, Some(payload.len() as u64)));
+        container.extend_from_slice(&fixture::make_box(ty, payload));
+    }
+
+    for chunk_size in [1, 5, container.len()] {
+        let mut reader = ContainerDetectingReader::new();
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+        let mut pending = Vec::new();
+        for chunk in container.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            for event in reader.feed_bytes(&pending) {
+                match event.unwrap() {
+                    ParseEvent::BoxStart(info) => starts.push(info),
+                    ParseEvent::BoxEnd(info) => {
+                        assert_eq!(starts.last(), Some(&info));
+                        ends.push(info);
+                    }
+                    _ => {}
+                }
+            }
+            let consumed = reader.previous_consumed_bytes();
+            pending.drain(..consumed);
+        }
+
+        let actual = starts
+            .iter()
+            .map(|info| {
+                (
+                    info.box_type().0,
+                    info.offset(),
+                    info.header_size(),
+                    info.box_size(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+        assert_eq!(ends, starts);
+        assert_eq!(reader.boxes(), &starts[..]);
+        assert_eq!(
+            ends.last().unwrap().end_offset(),
+            Some(container.len() as u64)
+        );
+    }
+}
+
+#[test]
+fn lenient_container() {
+    let (_, codestream) = fixture::image_u8(37, 23, 3);
+    let expected = fixture::read(&codestream)
+        .render_frame(0)
+        .unwrap()
+        .image_all_channels();
+
+    let make_box = fixture::make_box;
+    let signature = fixture::SIGNATURE;
+    let split = codestream.len() / 2;
+    let jxlp0 = make_box(b"jxlp", &[&[0, 0, 0, 0][..], &codestream[..split]].concat());
+    let jxlp1 = make_box(
+        b"jxlp",
+        &[&[0x80, 0, 0, 5][..], &codestream[split..]].concat(),
+    );
+    let jxlc = make_box(b"jxlc", &codestream);
+
+    let variants = [
+        // Partial codestream box with wrong index.
+        [&signature[..], &jxlp0, &jxlp1].concat(),
+        // Box with size smaller than its header.
+        [&signature[..], &[0, 0, 0, 4, b'a', b'b', b'c', b'd'], &jxlc].concat(),
+        // Partial codestream box too small to hold the index.
+        [&signature[..], &make_box(b"jxlp", &[0, 0]), &jxlc].concat(),
+        // Garbage after the codestream box.
+        [&signature[..], &jxlc, &[0, 0, 0, 3, 0xde, 0xad, 0xbe, 0xef]].concat(),
+    ];
+
+    for container in variants {
+        let result = JxlImage::builder()
+            .read(std::io::Cursor::new(&container))
+            .and_then(|image| image.render_frame(0));
+        assert!(result.is_err());
+
+        let image = JxlImage::builder()
+            .lenient_container(true)
+            .read(std::io::Cursor::new(&container))
+            .unwrap();
+        let render = image.render_frame(0).unwrap();
+        assert_eq!(render.image_all_channels().buf(), expected.buf());
+    }
+}
+
+#[test]
+fn frame_index_box() {
+    let (_, codestream) = fixture::image_u8(37, 23, 3);
+    let image = fixture::read(&codestream);
+    let frame_offset = image.frame_offset(0).unwrap();
+    assert!(image.frame_index_box().is_not_found());
+    assert_eq!(image.keyframe_seek_point(0), None);
+
+    let write_varint = |buf: &mut Vec<u8>, mut value: u64| loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    };
+    // One indexed frame, with tick of 1/30 seconds.
+    let mut jxli = vec![1];
+    jxli.extend_from_slice(&1u32.to_be_bytes());
+    jxli.extend_from_slice(&30u32.to_be_bytes());
+    write_varint(&mut jxli, frame_offset as u64);
+    write_varint(&mut jxli, 300);
+    write_varint(&mut jxli, 1);
+
+    for (jxli, valid) in [(&jxli[..], true), (&jxli[..jxli.len() - 1], false)] {
+        let container = fixture::write_container(&[(*b"jxli", jxli), (*b"jxlc", &codestream)]);
+
+        let image = fixture::read(&container);
+        let frames = image.frames().collect::<Vec<_>>();
+        if !valid {
+            // Malformed frame index is ignored.
+            assert!(image.frame_index_box().is_not_found());
+            assert!(!frames[0].is_indexed());
+            image.render_frame(0).unwrap();
+            continue;
+        }
+
+        let frame_index = image.frame_index_box().unwrap();
+        assert_eq!(
+            (frame_index.tick_numerator(), frame_index.tick_denominator()),
+            (1, 30)
+        );
+        let [entry] = frame_index.entries() else {
+            panic!("expected one entry");
+        };
+        assert_eq!(entry.keyframe_index(), 0);
+        assert_eq!(entry.offset(), frame_offset as u64);
+        assert_eq!((entry.start_ticks(), entry.duration_ticks()), (0, 300));
+        assert_eq!(entry.num_keyframes(), 1);
+        assert_eq!(frame_index.find_by_ticks(299), Some(entry));
+        assert_eq!(image.keyframe_seek_point(0), Some(*entry));
+        assert!(frames[0].is_indexed());
+
+        let expected = image.render_frame(0).unwrap().image_all_channels();
+        let offset = entry.offset() as usize;
+        let header_only = fixture::read(&codestream[..offset]);
+        let render = header_only
+            .load_frame_at(offset, &codestream[offset..])
+            .unwrap();
+        assert_eq!(render.image_all_channels().buf(), expected.buf());
+    }
+}
@@ -0,0 +1,298 @@
+use jxl_oxide::encode::transcode_jpeg;
+use jxl_oxide::{JpegReconstructionStatus, JxlImage};
+
+use crate::fixture::jpeg::{blocks, write_jpeg, JpegParams};
+
+fn transcode_roundtrip(params: &JpegParams) -> JxlImage {
+    let jpeg = write_jpeg(params);
+    let jxl = transcode_jpeg(&jpeg).expect("failed to transcode JPEG");
+    let image = JxlImage::builder()
+        .read(std::io::Cursor::new(&jxl))
+        .expect("failed to decode image");
+    assert_eq!(image.width() as usize, params.width);
+    assert_eq!(image.height() as usize, params.height);
+    assert!(matches!(
+        image.jpeg_reconstruction_status(),
+        JpegReconstructionStatus::Available
+    ));
+
+    image.render_frame(0).expect("failed to render frame");
+    let mut reconstructed = Vec::new();
+    image
+        .reconstruct_jpeg(&mut reconstructed)
+        .expect("failed to reconstruct JPEG");
+    assert!(reconstructed == jpeg, "reconstructed JPEG differs");
+    image
+}
+
+#[test]
+fn transcode_ycbcr_444() {
+    transcode_roundtrip(&JpegParams::new(40, 25));
+}
+
+#[test]
+fn transcode_ycbcr_420() {
+    let mut params = JpegParams::new(48, 32);
+    params.luma_sampling = (2, 2);
+    transcode_roundtrip(&params);
+
+    params.width = 17;
+    params.height = 9;
+    transcode_roundtrip(&params);
+}
+
+#[test]
+fn transcode_ycbcr_422() {
+    let mut params = JpegParams::new(35, 20);
+    params.luma_sampling = (2, 1);
+    transcode_roundtrip(&params);
+}
+
+#[test]
+fn transcode_gray() {
+    let mut params = JpegParams::new(30, 21);
+    params.gray = true;
+    params.comment = Some(b"grayscale");
+    transcode_roundtrip(&params);
+}
+
+#[test]
+fn transcode_restart_interval() {
+    let mut params = JpegParams::new(70, 30);
+    params.luma_sampling = (2, 2);
+    params.restart_interval = Some(3);
+    transcode_roundtrip(&params);
+}
+
+#[test]
+fn transcode_multi_group() {
+    let mut params = JpegParams::new(300, 270);
+    params.luma_sampling = (2, 2);
+    transcode_roundtrip(&params);
+
+    params.luma_sampling = (1, 1);
+    params.width = 2100;
+    params.height = 20;
+    transcode_roundtrip(&params);
+}
+
+#[test]
+fn transcode_render_dc() {
+    let mut params = JpegParams::new(16, 16);
+    params.gray = true;
+    params.flat_dc = Some(8);
+    let image = transcode_roundtrip(&params);
+
+    // Pixels are 128 + 8 * q0 / 8 = 129 in JPEG sample values.
+    let render = image.render_frame(0).expect("failed to render frame");
+    let mut stream = render.stream();
+    let mut buf = vec![0u8; 16 * 16];
+    stream.write_to_buffer(&mut buf);
+    for sample in buf {
+        assert!(
+            sample.abs_diff(129) <= 1,
+            "unexpected sample value {sample}"
+        );
+    }
+}
+
+#[test]
+fn transcode_render_lf() {
+    let mut params = JpegParams::new(32, 16);
+    params.gray = true;
+    params.flat_dc = Some(8);
+    let image = transcode_roundtrip(&params);
+
+    let render = image.render_lf(0).expect("failed to render LF").unwrap();
+    let mut stream = render.stream();
+    assert_eq!((stream.width(), stream.height()), (4, 2));
+    let mut buf = vec![0u8; 4 * 2];
+    stream.write_to_buffer(&mut buf);
+    for sample in buf {
+        assert!(
+            sample.abs_diff(129) <= 1,
+            "unexpected sample value {sample}"
+        );
+    }
+
+    let mut params = JpegParams::new(35, 20);
+    params.luma_sampling = (2, 2);
+    let image = transcode_roundtrip(&params);
+    let render = image.render_lf(0).expect("failed to render LF").unwrap();
+    let mut stream = render.stream();
+    assert_eq!((stream.width(), stream.height()), (5, 3));
+    let mut buf = vec![0u8; 5 * 3 * 3];
+    assert_eq!(stream.write_to_buffer(&mut buf), buf.len());
+}
+
+#[test]
+fn transcode_max_output_dimension() {
+    let mut params = JpegParams::new(64, 32);
+    params.gray = true;
+    params.flat_dc = Some(8);
+    let jxl = transcode_jpeg(&write_jpeg(&params)).expect("failed to transcode JPEG");
+    let image = JxlImage::builder()
+        .max_output_dimension(4)
+        .read(std::io::Cursor::new(&jxl))
+        .expect("failed to decode image");
+    assert_eq!(image.output_downscale(), 16);
+
+    // Keyframe is rendered from the LF image, and downsampled by the remaining factor.
+    let render = image.render_frame(0).expect("failed to render frame");
+    assert_eq!(render.output_downscale(), 2);
+    let stream = render.stream();
+    assert_eq!((stream.width(), stream.height()), (8, 4));
+    let fb = render.image_scaled();
+    assert_eq!((fb.width(), fb.height()), (4, 2));
+    for &sample in fb.buf() {
+        assert!(
+            (sample * 255.0 - 129.0).abs() <= 1.0,
+            "unexpected sample value {sample}"
+        );
+    }
+}
+
+#[test]
+fn transcode_render_raw_ycbcr() {
+    let mut params = JpegParams::new(16, 16);
+    params.flat_dc = Some(8);
+    let image = transcode_roundtrip(&params);
+
+    let render = image.render_raw_color_space(0).unwrap().unwrap();
+    let mut stream = render.stream();
+    assert_eq!(stream.channels(), 3);
+    let mut buf = vec![0f32; 16 * 16 * 3];
+    stream.write_to_buffer(&mut buf);
+
+    // Channels are in Cb, Y, Cr order, centered at zero. The first quantization table entry is 1
+    // for luma and 4 for chroma.
+    let expected = [4.0 / 255.0, 1.0 / 255.0, 4.0 / 255.0];
+    for pixel in buf.chunks_exact(3) {
+        for (&sample, &expected) in pixel.iter().zip(&expected) {
+            assert!(
+                (sample - expected).abs() < 1e-4,
+                "unexpected sample value {sample}"
+            );
+        }
+    }
+}
+
+#[test]
+fn transcode_progressive_unsupported() {
+    let mut jpeg = write_jpeg(&JpegParams::new(8, 8));
+    let sof = jpeg.windows(2).position(|w| w == [0xff, 0xc0]).unwrap();
+    jpeg[sof + 1] = 0xc2;
+    assert!(transcode_jpeg(&jpeg).is_err());
+}
+
+#[test]
+fn transcode_downscale_lf_only() {
+    let mut params = JpegParams::new(32, 16);
+    params.gray = true;
+    let jxl = transcode_jpeg(&write_jpeg(&params)).expect("failed to transcode JPEG");
+
+    let render_blocks = |downscale_target| {
+        let image = JxlImage::builder()
+            .downscale_target(downscale_target)
+            .read(std::io::Cursor::new(&jxl))
+            .expect("failed to decode image");
+        let render = image.render_frame(0).expect("failed to render frame");
+        let mut buf = vec![0u8; 32 * 16];
+        render.stream().write_to_buffer(&mut buf);
+        (0..8)
+            .map(|block_idx| {
+                let (bx, by) = (block_idx % 4 * 8, block_idx / 4 * 8);
+                let block = (0..64).map(|i| buf[(by + i / 8) * 32 + bx + i % 8]);
+                block.clone().max().unwrap() - block.min().unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Only LF is decoded, so every block is flat.
+    assert!(render_blocks(8).iter().all(|&range| range <= 1));
+    assert!(render_blocks(1).iter().any(|&range| range > 1));
+}
+
+#[test]
+fn decode_pass_group_coefficients() {
+    let params = JpegParams::new(40, 24);
+    let image = transcode_roundtrip(&params);
+
+    // Every block of the image is generated in a row, so the number of nonzero AC coefficients
+    // doesn't depend on the order of blocks.
+    let expected = blocks(&params)
+        .take(5 * 3 * 3)
+        .map(|block| block[1..].iter().filter(|&&coeff| coeff != 0).count())
+        .sum::<usize>();
+
+    let frame = image.frame(0).unwrap();
+    let lf_global = frame.try_parse_lf_global::<i32>().unwrap().unwrap();
+    let lf_group = frame
+        .try_parse_lf_group(
+            lf_global.vardct.as_ref(),
+            lf_global.gmodular.ma_config(),
+            None,
+            0,
+        )
+        .unwrap()
+        .unwrap();
+    let hf_global = frame
+        .try_parse_hf_global(Some(&lf_global))
+        .unwrap()
+        .unwrap();
+
+    let group_dim = frame.header().group_dim() as usize;
+    let mut grids: [_; 3] = std::array::from_fn(|_| {
+        jxl_oxide::AlignedGrid::<i32>::with_alloc_tracker(group_dim, group_dim, None).unwrap()
+    });
+    let [x, y, b] = &mut grids;
+    let mut coeff_output = [x.as_subgrid_mut(), y.as_subgrid_mut(), b.as_subgrid_mut()];
+    frame
+        .decode_pass_group_vardct(
+            0,
+            0,
+            &lf_global,
+            &lf_group,
+            &hf_global,
+            &mut coeff_output,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+
+    let nonzero = grids
+        .iter()
+        .map(|grid| grid.buf().iter().filter(|&&coeff| coeff != 0).count())
+        .sum::<usize>();
+    assert_eq!(nonzero, expected);
+}
+
+#[test]
+fn container_boxes() {
+    let jxl = transcode_jpeg(&write_jpeg(&JpegParams::new(16, 16))).unwrap();
+    let image = JxlImage::builder()
+        .read(std::io::Cursor::new(&jxl))
+        .expect("failed to decode image");
+
+    let boxes = image.reader().boxes();
+    let types = boxes.iter().map(|b| b.box_type().0).collect::<Vec<_>>();
+    assert_eq!(types, [*b"JXL ", *b"ftyp", *b"jbrd", *b"jxlc"]);
+    let mut offset = 0;
+    for b in boxes {
+        assert_eq!(b.offset(), offset);
+        assert_eq!(b.header_size(), 8);
+        assert!(!b.is_brotli_compressed());
+        offset += b.header_size() as u64 + b.box_size().unwrap();
+    }
+    assert_eq!(offset, jxl.len() as u64);
+
+    // Feeding bytes one by one results in the same layout.
+    let mut uninit = JxlImage::builder().build_uninit();
+    let mut pending = Vec::new();
+    for &byte in &jxl {
+        pending.push(byte);
+        let consumed = uninit.feed_bytes(&pending).unwrap();
+        pending.drain(..consumed);
+    }
+    assert_eq!(uninit.reader().boxes(), boxes);
+}
@@ -0,0 +1,126 @@
+mod jpeg;
+
+use jxl_oxide::encode::{ImageRef, Predictor};
+use jxl_oxide::{FrameBuffer, JxlEncoder, JxlImage};
+
+use crate::fixture;
+
+fn decode<Sample: jxl_oxide::FrameBufferSample + Default + Clone>(
+    jxl: &[u8],
+    expected_width: usize,
+    expected_height: usize,
+    expected_channels: usize,
+) -> Vec<Sample> {
+    let image = JxlImage::builder()
+        .read(std::io::Cursor::new(jxl))
+        .expect("failed to decode image");
+    let render = image.render_frame(0).expect("failed to render frame");
+    let mut stream = render.stream();
+    assert_eq!(stream.width() as usize, expected_width);
+    assert_eq!(stream.height() as usize, expected_height);
+    assert_eq!(stream.channels() as usize, expected_channels);
+
+    let mut buf = vec![Sample::default(); expected_width * expected_height * expected_channels];
+    stream.write_to_buffer(&mut buf);
+    buf
+}
+
+fn roundtrip_u8(width: usize, height: usize, channels: usize) {
+    let (samples, jxl) = fixture::image_u8(width, height, channels);
+    let decoded = decode::<u8>(&jxl, width, height, channels);
+    assert!(decoded == samples, "decoded samples differ");
+}
+
+fn roundtrip_u16(width: usize, height: usize, channels: usize) {
+    let (samples, jxl) = fixture::image_u16(width, height, channels);
+    let decoded = decode::<u16>(&jxl, width, height, channels);
+    assert!(decoded == samples, "decoded samples differ");
+}
+
+#[test]
+fn rgb_u8() {
+    roundtrip_u8(67, 45, 3);
+}
+
+#[test]
+fn rgba_u8() {
+    roundtrip_u8(100, 31, 4);
+}
+
+#[test]
+fn gray_u8() {
+    roundtrip_u8(1, 1, 1);
+    roundtrip_u8(38, 9, 1);
+}
+
+#[test]
+fn gray_alpha_u8() {
+    roundtrip_u8(19, 64, 2);
+}
+
+#[test]
+fn rgb_u16() {
+    roundtrip_u16(40, 33, 3);
+}
+
+#[test]
+fn rgba_u16() {
+    roundtrip_u16(24, 50, 4);
+}
+
+#[test]
+fn multi_group_u8() {
+    roundtrip_u8(300, 260, 4);
+    roundtrip_u8(513, 17, 3);
+}
+
+#[test]
+fn multi_group_u16() {
+    roundtrip_u16(257, 300, 3);
+}
+
+#[test]
+fn multi_lf_group() {
+    roundtrip_u8(2100, 20, 1);
+}
+
+#[test]
+fn predictors() {
+    // Zero and North predictors are decoded a row at a time, with SIMD if available.
+    for predictor in [Predictor::Zero, Predictor::North, Predictor::Gradient] {
+        for (width, height, channels) in [(67, 45, 3), (300, 260, 1), (5, 3, 2)] {
+            let (samples, _) = fixture::image_u8(width, height, channels);
+            let jxl = JxlEncoder::new()
+                .predictor(predictor)
+                .encode(ImageRef::from_u8(width, height, channels, &samples))
+                .unwrap();
+            let decoded = decode::<u8>(&jxl, width, height, channels);
+            assert!(decoded == samples, "{predictor:?}: decoded samples differ");
+
+            let (samples, _) = fixture::image_u16(width, height, channels);
+            let jxl = JxlEncoder::new()
+                .predictor(predictor)
+                .encode(ImageRef::from_u16(width, height, channels, &samples))
+                .unwrap();
+            let decoded = decode::<u16>(&jxl, width, height, channels);
+            assert!(decoded == samples, "{predictor:?}: decoded samples differ");
+        }
+    }
+}
+
+#[test]
+fn frame_buffer() {
+    let width = 20;
+    let height = 10;
+    let mut fb = FrameBuffer::new(width, height, 3);
+    for (idx, sample) in fb.buf_mut().iter_mut().enumerate() {
+        *sample = (idx % 256) as f32 / 255.0;
+    }
+
+    let jxl = JxlEncoder::new().encode(&fb).unwrap();
+    let decoded = decode::<u8>(&jxl, width, height, 3);
+    let expected = (0..width * height * 3)
+        .map(|idx| (idx % 256) as u8)
+        .collect::<Vec<_>>();
+    assert_eq!(decoded, expected);
+}
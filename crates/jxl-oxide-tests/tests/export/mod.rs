@@ -0,0 +1,119 @@
+use jxl_oxide::export::{self, AnimationTiming, ExportOptions};
+use jxl_oxide::PixelFormat;
+
+use crate::fixture;
+
+/// Returns the chunks of a PNG file, in order.
+fn png_chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    let mut chunks = Vec::new();
+    let mut data = &png[8..];
+    while !data.is_empty() {
+        let len = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        let ty = data[4..8].try_into().unwrap();
+        chunks.push((ty, &data[8..][..len]));
+        data = &data[12 + len..];
+    }
+    chunks
+}
+
+/// Returns the top-level chunks of a WebP file, in order.
+fn webp_chunks(webp: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    assert_eq!(&webp[..4], b"RIFF");
+    assert_eq!(&webp[8..12], b"WEBP");
+    let riff_size = u32::from_le_bytes(webp[4..8].try_into().unwrap()) as usize;
+    assert_eq!(riff_size + 8, webp.len());
+    let mut chunks = Vec::new();
+    let mut data = &webp[12..];
+    while !data.is_empty() {
+        let len = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        let ty = data[..4].try_into().unwrap();
+        chunks.push((ty, &data[8..][..len]));
+        data = &data[8 + len.next_multiple_of(2)..];
+    }
+    chunks
+}
+
+#[test]
+fn export_still() {
+    let (width, height) = (23usize, 11usize);
+    let (_, jxl) = fixture::image_u8(width, height, 4);
+    let image = fixture::read(&jxl);
+    let keyframes = [image.render_frame(0).unwrap()];
+    let options = ExportOptions::from_image(&image);
+    assert_eq!(options.pixel_format, PixelFormat::Rgba);
+    assert!(!options.sixteen_bits);
+    assert!(options.animation.is_none());
+
+    let mut png = Vec::new();
+    export::write_png(&mut png, &keyframes, &options).unwrap();
+    let chunks = png_chunks(&png);
+    let types = chunks.iter().map(|&(ty, _)| ty).collect::<Vec<_>>();
+    assert_eq!(types.first(), Some(b"IHDR"));
+    assert_eq!(types.last(), Some(b"IEND"));
+    assert!(types.contains(b"iCCP"));
+    assert!(!types.contains(b"acTL"));
+    let ihdr = chunks[0].1;
+    assert_eq!(ihdr[..8], [0, 0, 0, 23, 0, 0, 0, 11]);
+    // 8-bit RGBA
+    assert_eq!(ihdr[8..10], [8, 6]);
+
+    let mut webp = Vec::new();
+    export::write_webp(&mut webp, &keyframes, &options).unwrap();
+    let chunks = webp_chunks(&webp);
+    let types = chunks.iter().map(|&(ty, _)| ty).collect::<Vec<_>>();
+    assert_eq!(types, [*b"VP8X", *b"ICCP", *b"VP8L"]);
+    // ICC profile and alpha
+    assert_eq!(chunks[0].1[0], 0x30);
+
+    let mut jpeg = Vec::new();
+    export::write_jpeg(&mut jpeg, &keyframes, &options).unwrap();
+    assert_eq!(jpeg[..2], [0xff, 0xd8]);
+    assert_eq!(jpeg[jpeg.len() - 2..], [0xff, 0xd9]);
+    let jxl = jxl_oxide::encode::transcode_jpeg(&jpeg).unwrap();
+    let image = fixture::read(&jxl);
+    assert_eq!((image.width(), image.height()), (23, 11));
+}
+
+#[test]
+fn export_animation() {
+    let (_, jxl) = fixture::image_u8(23, 11, 3);
+    let image = fixture::read(&jxl);
+    let keyframes = [
+        image.render_frame(0).unwrap(),
+        image.render_frame(0).unwrap(),
+    ];
+    let mut options = ExportOptions::from_image(&image);
+    options.icc = Vec::new();
+    options.sixteen_bits = true;
+    options.animation = Some(AnimationTiming {
+        tps_numerator: 100,
+        tps_denominator: 1,
+        num_loops: 3,
+    });
+
+    let mut png = Vec::new();
+    export::write_png(&mut png, &keyframes, &options).unwrap();
+    let chunks = png_chunks(&png);
+    let actl = chunks.iter().find(|&&(ty, _)| &ty == b"acTL").unwrap().1;
+    // Two frames, three plays
+    assert_eq!(actl, [0, 0, 0, 2, 0, 0, 0, 3]);
+    let num_fctl = chunks.iter().filter(|&&(ty, _)| &ty == b"fcTL").count();
+    assert_eq!(num_fctl, 2);
+    assert!(!chunks.iter().any(|&(ty, _)| &ty == b"iCCP"));
+    // 16-bit RGB
+    assert_eq!(chunks[0].1[8..10], [16, 2]);
+
+    let mut webp = Vec::new();
+    export::write_webp(&mut webp, &keyframes, &options).unwrap();
+    let chunks = webp_chunks(&webp);
+    let types = chunks.iter().map(|&(ty, _)| ty).collect::<Vec<_>>();
+    assert_eq!(types, [*b"VP8X", *b"ANIM", *b"ANMF", *b"ANMF"]);
+    // Animation, without alpha
+    assert_eq!(chunks[0].1[0], 0x02);
+    assert_eq!(chunks[1].1[4..6], [3, 0]);
+    for (_, anmf) in &chunks[2..] {
+        assert_eq!(anmf[6..12], [22, 0, 0, 10, 0, 0]);
+        assert_eq!(&anmf[16..20], b"VP8L");
+    }
+}
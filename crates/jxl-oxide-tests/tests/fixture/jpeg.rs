@@ -0,0 +1,253 @@
+//! Baseline JPEG images with pseudo-random coefficients, for testing JPEG transcoding.
+
+/// Parameters of a baseline JPEG image written by [`write_jpeg`].
+#[derive(Clone)]
+pub struct JpegParams {
+    pub width: usize,
+    pub height: usize,
+    pub gray: bool,
+    /// Sampling factors of the Y component.
+    pub luma_sampling: (usize, usize),
+    pub restart_interval: Option<u16>,
+    pub comment: Option<&'static [u8]>,
+    /// Write DC coefficients only, with the given quantized value.
+    pub flat_dc: Option<i16>,
+}
+
+impl JpegParams {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            gray: false,
+            luma_sampling: (1, 1),
+            restart_interval: None,
+            comment: None,
+            flat_dc: None,
+        }
+    }
+}
+
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+}
+
+/// Generates a block of quantized coefficients in zigzag order, with a few long zero runs.
+/// Returns an endless stream of blocks of quantized coefficients, in the order written by
+/// [`write_jpeg`].
+pub fn blocks(params: &JpegParams) -> impl Iterator<Item = [i16; 64]> + '_ {
+    let mut rng = Rng(0x2545f491);
+    std::iter::repeat_with(move || generate_block(&mut rng, params))
+}
+
+fn generate_block(rng: &mut Rng, params: &JpegParams) -> [i16; 64] {
+    let mut block = [0i16; 64];
+    if let Some(dc) = params.flat_dc {
+        block[0] = dc;
+        return block;
+    }
+
+    block[0] = (rng.next() % 1601) as i16 - 800;
+    for coeff in &mut block[1..] {
+        let r = rng.next();
+        if r.is_multiple_of(4) {
+            *coeff = ((r >> 8) % 61) as i16 - 30;
+        }
+    }
+    if rng.next().is_multiple_of(3) {
+        block[1..].fill(0);
+        block[(rng.next() % 30 + 34) as usize] = 1;
+    }
+    block
+}
+
+struct ScanWriter {
+    out: Vec<u8>,
+    acc: u32,
+    bits: u32,
+}
+
+impl ScanWriter {
+    fn write(&mut self, n: u32, value: u32) {
+        for i in (0..n).rev() {
+            self.acc = (self.acc << 1) | ((value >> i) & 1);
+            self.bits += 1;
+            if self.bits == 8 {
+                self.push_byte();
+            }
+        }
+    }
+
+    fn push_byte(&mut self) {
+        let byte = self.acc as u8;
+        self.out.push(byte);
+        if byte == 0xff {
+            self.out.push(0);
+        }
+        self.acc = 0;
+        self.bits = 0;
+    }
+
+    /// Pads the current byte with one bits.
+    fn flush(&mut self) {
+        if self.bits > 0 {
+            let n = 8 - self.bits;
+            self.write(n, (1 << n) - 1);
+        }
+    }
+}
+
+/// Every DC symbol has a 4-bit code, and every AC symbol has an 8-bit code.
+fn ac_symbols() -> Vec<u8> {
+    let mut symbols = vec![0x00, 0xf0];
+    for run in 0..16 {
+        for size in 1..=10 {
+            symbols.push((run << 4) | size);
+        }
+    }
+    symbols
+}
+
+fn category(value: i32) -> (u32, u32) {
+    let size = 32 - value.unsigned_abs().leading_zeros();
+    let bits = if value < 0 {
+        value + (1 << size) - 1
+    } else {
+        value
+    };
+    (size, bits as u32)
+}
+
+fn encode_block(w: &mut ScanWriter, block: &[i16; 64], dc_pred: &mut i16, ac_codes: &[u32; 256]) {
+    let (size, bits) = category((block[0] - *dc_pred) as i32);
+    *dc_pred = block[0];
+    w.write(4, size);
+    w.write(size, bits);
+
+    let mut run = 0;
+    for &coeff in &block[1..] {
+        if coeff == 0 {
+            run += 1;
+            continue;
+        }
+        while run >= 16 {
+            w.write(8, ac_codes[0xf0]);
+            run -= 16;
+        }
+        let (size, bits) = category(coeff as i32);
+        w.write(8, ac_codes[(run << 4) as usize | size as usize]);
+        w.write(size, bits);
+        run = 0;
+    }
+    if run > 0 {
+        w.write(8, ac_codes[0]);
+    }
+}
+
+fn write_segment(out: &mut Vec<u8>, marker: u8, data: &[u8]) {
+    out.extend_from_slice(&[0xff, marker]);
+    out.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Writes a baseline JPEG image with pseudo-random coefficients.
+pub fn write_jpeg(params: &JpegParams) -> Vec<u8> {
+    let mut blocks = blocks(params);
+    let mut out = vec![0xff, 0xd8];
+    write_segment(
+        &mut out,
+        0xe0,
+        b"JFIF\0\x01\x01\x00\x00\x01\x00\x01\x00\x00",
+    );
+    if let Some(comment) = params.comment {
+        write_segment(&mut out, 0xfe, comment);
+    }
+
+    // Quantization tables, which are not symmetric.
+    let num_tables = if params.gray { 1 } else { 2 };
+    let mut dqt = Vec::new();
+    for idx in 0..num_tables {
+        dqt.push(idx as u8);
+        dqt.extend((0..64).map(|k| (1 + (k * 7 + idx * 3) % 23) as u8));
+    }
+    write_segment(&mut out, 0xdb, &dqt);
+
+    let (h, v) = params.luma_sampling;
+    let comps: &[(u8, usize, usize, u8)] = if params.gray {
+        &[(1, h, v, 0)]
+    } else {
+        &[(1, h, v, 0), (2, 1, 1, 1), (3, 1, 1, 1)]
+    };
+    let mut sof = vec![8];
+    sof.extend_from_slice(&(params.height as u16).to_be_bytes());
+    sof.extend_from_slice(&(params.width as u16).to_be_bytes());
+    sof.push(comps.len() as u8);
+    for &(id, h, v, q) in comps {
+        sof.extend_from_slice(&[id, ((h << 4) | v) as u8, q]);
+    }
+    write_segment(&mut out, 0xc0, &sof);
+
+    let ac_symbols = ac_symbols();
+    let mut dht = vec![0x00];
+    dht.extend_from_slice(&[0, 0, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    dht.extend(0..12);
+    dht.push(0x10);
+    dht.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, ac_symbols.len() as u8]);
+    dht.extend_from_slice(&[0; 8]);
+    dht.extend_from_slice(&ac_symbols);
+    write_segment(&mut out, 0xc4, &dht);
+    let mut ac_codes = [0u32; 256];
+    for (code, &symbol) in ac_symbols.iter().enumerate() {
+        ac_codes[symbol as usize] = code as u32;
+    }
+
+    if let Some(interval) = params.restart_interval {
+        write_segment(&mut out, 0xdd, &interval.to_be_bytes());
+    }
+
+    let mut sos = vec![comps.len() as u8];
+    for &(id, ..) in comps {
+        sos.extend_from_slice(&[id, 0x00]);
+    }
+    sos.extend_from_slice(&[0, 63, 0]);
+    write_segment(&mut out, 0xda, &sos);
+
+    // Non-interleaved scan of a single component isn't split into MCUs of sampling factors.
+    let (mcu_h, mcu_v) = if params.gray { (1, 1) } else { (h, v) };
+    let mcus_x = params.width.div_ceil(8 * mcu_h);
+    let mcus_y = params.height.div_ceil(8 * mcu_v);
+    let mut w = ScanWriter {
+        out: Vec::new(),
+        acc: 0,
+        bits: 0,
+    };
+    let mut dc_preds = [0i16; 3];
+    for mcu_idx in 0..mcus_x * mcus_y {
+        if let Some(interval) = params.restart_interval {
+            if mcu_idx > 0 && mcu_idx % interval as usize == 0 {
+                w.flush();
+                let m = (mcu_idx / interval as usize - 1) % 8;
+                w.out.extend_from_slice(&[0xff, 0xd0 + m as u8]);
+                dc_preds = [0; 3];
+            }
+        }
+        for (c, &(_, h, v, _)) in comps.iter().enumerate() {
+            let (h, v) = if params.gray { (1, 1) } else { (h, v) };
+            for _ in 0..h * v {
+                let block = blocks.next().unwrap();
+                encode_block(&mut w, &block, &mut dc_preds[c], &ac_codes);
+            }
+        }
+    }
+    w.flush();
+    out.extend_from_slice(&w.out);
+    out.extend_from_slice(&[0xff, 0xd9]);
+    out
+}
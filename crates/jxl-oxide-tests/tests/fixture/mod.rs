@@ -0,0 +1,106 @@
+//! Test images generated with the lossless encoder, shared by the tests of the decoder API.
+
+pub mod jpeg;
+
+use jxl_oxide::encode::ImageRef;
+use jxl_oxide::{JxlEncoder, JxlImage, JxlThreadPool};
+
+/// Signature box of the JPEG XL container.
+pub const SIGNATURE: [u8; 12] = [0, 0, 0, 0xc, b'J', b'X', b'L', b' ', 0xd, 0xa, 0x87, 0xa];
+
+/// Generates deterministic noisy gradient samples.
+pub fn generate_samples(width: usize, height: usize, channels: usize, max: u32) -> Vec<u32> {
+    let mut state = 0x2545f491u32;
+    let mut out = Vec::with_capacity(width * height * channels);
+    for y in 0..height {
+        for x in 0..width {
+            for c in 0..channels {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let base = ((x * (c + 1) + y * 3) as u32 * 97) % (max + 1);
+                let noise = state % 16;
+                out.push((base + noise).min(max));
+            }
+        }
+    }
+    out
+}
+
+/// Generates an 8-bit image and encodes it losslessly.
+///
+/// Returns the samples with interleaved channels, and the encoded codestream.
+pub fn image_u8(width: usize, height: usize, channels: usize) -> (Vec<u8>, Vec<u8>) {
+    let samples = generate_samples(width, height, channels, 255)
+        .into_iter()
+        .map(|v| v as u8)
+        .collect::<Vec<_>>();
+    let jxl = encode_u8(width, height, channels, &samples);
+    (samples, jxl)
+}
+
+/// Encodes 8-bit samples with interleaved channels losslessly.
+pub fn encode_u8(width: usize, height: usize, channels: usize, samples: &[u8]) -> Vec<u8> {
+    JxlEncoder::new()
+        .encode(ImageRef::from_u8(width, height, channels, samples))
+        .expect("failed to encode image")
+}
+
+/// Generates a 16-bit image and encodes it losslessly.
+///
+/// Returns the samples with interleaved channels, and the encoded codestream.
+pub fn image_u16(width: usize, height: usize, channels: usize) -> (Vec<u16>, Vec<u8>) {
+    let samples = generate_samples(width, height, channels, 65535)
+        .into_iter()
+        .map(|v| v as u16)
+        .collect::<Vec<_>>();
+    let jxl = JxlEncoder::new()
+        .encode(ImageRef::from_u16(width, height, channels, &samples))
+        .expect("failed to encode image");
+    (samples, jxl)
+}
+
+/// Reads the image with default options.
+pub fn read(jxl: &[u8]) -> JxlImage {
+    JxlImage::builder()
+        .read(std::io::Cursor::new(jxl))
+        .expect("failed to decode image")
+}
+
+/// Renders the first keyframe, and returns its size and `f32` samples.
+pub fn render_f32(image: &JxlImage) -> (u32, u32, Vec<f32>) {
+    let render = image.render_frame(0).expect("failed to render frame");
+    let mut stream = render.stream();
+    let mut buf = vec![0f32; (stream.width() * stream.height() * stream.channels()) as usize];
+    stream.write_to_buffer(&mut buf);
+    (stream.width(), stream.height(), buf)
+}
+
+/// Renders the first keyframe using the thread pool, and returns the bits of `f32` samples.
+pub fn render_bits(jxl: &[u8], pool: JxlThreadPool) -> Vec<u32> {
+    let image = JxlImage::builder()
+        .pool(pool)
+        .read(std::io::Cursor::new(jxl))
+        .expect("failed to decode image");
+    let fb = image
+        .render_frame(0)
+        .expect("failed to render frame")
+        .image_all_channels();
+    fb.buf().iter().map(|v| v.to_bits()).collect()
+}
+
+/// Writes a box of the container with the given type and payload.
+pub fn make_box(ty: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let size = (8 + payload.len()) as u32;
+    [&size.to_be_bytes()[..], ty, payload].concat()
+}
+
+/// Wraps the boxes in a JPEG XL container, after the signature and file type boxes.
+pub fn write_container(boxes: &[([u8; 4], &[u8])]) -> Vec<u8> {
+    let mut jxl = SIGNATURE.to_vec();
+    jxl.extend_from_slice(&make_box(b"ftyp", b"jxl \0\0\0\0jxl "));
+    for (ty, data) in boxes {
+        jxl.extend_from_slice(&make_box(ty, data));
+    }
+    jxl
+}
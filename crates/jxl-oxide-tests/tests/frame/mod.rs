@@ -0,0 +1,217 @@
+use jxl_oxide::encode::{ImageRef, Patch};
+use jxl_oxide::{FrameBlendMode, JxlEncoder, PatchBlendMode};
+
+use crate::fixture;
+
+/// Generates an RGB image with the 8x6 rectangle at (4, 2) repeated at each target, and encodes
+/// it with a patch.
+fn image_with_patch(width: usize, height: usize, encoder: JxlEncoder) -> (Vec<u8>, Vec<u8>) {
+    let (mut samples, _) = fixture::image_u8(width, height, 3);
+    let targets = vec![(40, 30), (20, 36), (50, 2)];
+    for &(x, y) in &targets {
+        for dy in 0..6 {
+            let source = ((2 + dy) * width + 4) * 3;
+            let target = ((y + dy) * width + x) * 3;
+            samples.copy_within(source..source + 8 * 3, target);
+        }
+    }
+
+    let patch = Patch {
+        left: 4,
+        top: 2,
+        width: 8,
+        height: 6,
+        targets: targets
+            .into_iter()
+            .map(|(x, y)| (x as u32, y as u32))
+            .collect(),
+    };
+    let jxl = encoder
+        .patch(patch)
+        .encode(ImageRef::from_u8(width, height, 3, &samples))
+        .unwrap();
+    (samples, jxl)
+}
+
+#[test]
+fn frame_infos() {
+    let (_, jxl) = fixture::image_u8(300, 20, 3);
+    let image = fixture::read(&jxl);
+
+    let infos = image.frame_infos();
+    assert_eq!(infos.len(), 1);
+    let info = &infos[0];
+    assert_eq!(info.keyframe_index(), 0);
+    assert_eq!(info.frame_indices(), 0..=0);
+    assert!(info.header_bytes() > 0);
+    assert_eq!(info.offset() + info.total_bytes(), jxl.len());
+}
+
+#[test]
+fn frames() {
+    let (_, jxl) = fixture::image_u8(40, 30, 1);
+    let image = fixture::read(&jxl);
+
+    let frames = image.frames().collect::<Vec<_>>();
+    assert_eq!(frames.len(), 1);
+    let frame = &frames[0];
+    assert_eq!(frame.index(), 0);
+    assert_eq!(
+        frame.frame_type(),
+        jxl_oxide::frame::FrameType::RegularFrame
+    );
+    assert!(frame.is_keyframe());
+    assert_eq!(frame.duration(), 0);
+    assert_eq!(
+        frame.offset() + frame.header_bytes() + frame.group_bytes(),
+        jxl.len()
+    );
+
+    let blend_info = frame.blend_info();
+    assert_eq!(image.keyframe_blend_info(0), Some(blend_info));
+    assert_eq!(image.keyframe_blend_info(1), None);
+    assert_eq!(blend_info.mode(), FrameBlendMode::Replace);
+    assert_eq!(blend_info.source(), 0);
+    assert_eq!(blend_info.alpha_channel(), None);
+    assert_eq!((blend_info.x0(), blend_info.y0()), (0, 0));
+    assert_eq!((blend_info.width(), blend_info.height()), (40, 30));
+    // The last frame is not saved.
+    assert_eq!(blend_info.saved_reference(), None);
+}
+
+#[test]
+fn frame_features() {
+    let (_, jxl) = fixture::image_u8(16, 16, 3);
+    let image = fixture::read(&jxl);
+
+    let features = image.frame_features(0).unwrap().unwrap();
+    assert!(features.splines().is_empty());
+    assert!(features.patches().is_empty());
+    assert!(features.noise().is_none());
+    let patches = image.frame(0).unwrap().patches().unwrap().unwrap();
+    assert!(patches.is_empty());
+    assert!(image
+        .frame_features(image.num_loaded_frames())
+        .unwrap()
+        .is_none());
+
+    let lut = [0.0, 0.125, 0.25, 0.375, 0.5, 0.625, 0.75, 0.875];
+    let (_, jxl) = image_with_patch(64, 48, JxlEncoder::new().noise(lut));
+    let image = fixture::read(&jxl);
+
+    let features = image.frame_features(1).unwrap().unwrap();
+    assert!(features.splines().is_empty());
+    assert_eq!(features.noise().unwrap().lut, lut);
+    let patches = features.patches();
+    assert_eq!(patches.len(), 1);
+    let patch = &patches[0];
+    assert_eq!(patch.ref_idx, 0);
+    assert_eq!(
+        (patch.x0, patch.y0, patch.width, patch.height),
+        (0, 0, 8, 6)
+    );
+    let targets = patch
+        .patch_targets
+        .iter()
+        .map(|target| (target.x, target.y))
+        .collect::<Vec<_>>();
+    assert_eq!(targets, [(40, 30), (20, 36), (50, 2)]);
+
+    // Reference frame of the patch has neither patches nor noise.
+    let features = image.frame_features(0).unwrap().unwrap();
+    assert!(features.patches().is_empty());
+    assert!(features.noise().is_none());
+}
+
+#[test]
+fn patches() {
+    let (width, height) = (64, 48);
+    let (samples, jxl) = image_with_patch(width, height, JxlEncoder::new());
+    let image = fixture::read(&jxl);
+    assert_eq!(image.num_loaded_frames(), 2);
+    assert_eq!(image.num_loaded_keyframes(), 1);
+
+    // Reference frame of the patch doesn't have patches itself.
+    let reference = image.frame(0).unwrap();
+    assert!(reference.patches().unwrap().unwrap().is_empty());
+
+    let patches = image.frame(1).unwrap().patches().unwrap().unwrap();
+    assert_eq!(patches.len(), 1);
+    let patch = &patches[0];
+    assert_eq!(patch.ref_idx, 0);
+    assert_eq!((patch.x0, patch.y0), (0, 0));
+    assert_eq!((patch.width, patch.height), (8, 6));
+    let targets = patch
+        .patch_targets
+        .iter()
+        .map(|target| (target.x, target.y))
+        .collect::<Vec<_>>();
+    assert_eq!(targets, [(40, 30), (20, 36), (50, 2)]);
+    for target in &patch.patch_targets {
+        assert_eq!(target.blending.len(), 1);
+        assert_eq!(target.blending[0].mode, PatchBlendMode::Replace);
+    }
+
+    let render = image.render_frame(0).unwrap();
+    let mut stream = render.stream();
+    let mut buf = vec![0u8; width * height * 3];
+    stream.write_to_buffer(&mut buf);
+    assert!(buf == samples, "decoded samples differ");
+
+    // Targets should have the same samples as the source.
+    let (samples, _) = fixture::image_u8(width, height, 3);
+    let patch = Patch {
+        left: 0,
+        top: 0,
+        width: 4,
+        height: 4,
+        targets: vec![(8, 8)],
+    };
+    let result = JxlEncoder::new()
+        .patch(patch)
+        .encode(ImageRef::from_u8(width, height, 3, &samples));
+    assert!(matches!(
+        result,
+        Err(jxl_oxide::encode::Error::InvalidPatch(_))
+    ));
+}
+
+#[test]
+fn load_frame_at() {
+    let (_, jxl) = fixture::image_u8(37, 23, 4);
+
+    let image = fixture::read(&jxl);
+    let offset = image.frame_offset(0).unwrap();
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+
+    // Already loaded frame.
+    let render = image.load_frame_at(offset, &[]).unwrap();
+    assert_eq!(render.keyframe_index(), 0);
+    assert_eq!(render.image_all_channels().buf(), expected.buf());
+
+    // Image with header only.
+    let image = fixture::read(&jxl[..offset]);
+    assert_eq!(image.num_loaded_keyframes(), 0);
+    let render = image.load_frame_at(offset, &jxl[offset..]).unwrap();
+    assert_eq!(render.image_all_channels().buf(), expected.buf());
+    assert_eq!(image.num_loaded_keyframes(), 0);
+
+    image
+        .load_frame_at(offset, &jxl[offset..jxl.len() - 1])
+        .unwrap_err();
+}
+
+#[test]
+fn seek_keyframe() {
+    let (_, jxl) = fixture::image_u8(37, 23, 3);
+
+    let image = fixture::read(&jxl);
+    assert_eq!(image.keyframe_dependencies(0), Some(vec![0]));
+    assert_eq!(image.keyframe_dependencies(1), None);
+
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+    let render = image.seek_keyframe(0).unwrap();
+    assert_eq!(render.keyframe_index(), 0);
+    assert_eq!(render.image_all_channels().buf(), expected.buf());
+    image.seek_keyframe(1).unwrap_err();
+}
@@ -0,0 +1,21 @@
+use half::f16;
+
+use crate::fixture;
+
+#[test]
+fn stream_f16_matches_f32() {
+    for channels in [1usize, 3] {
+        let (width, height) = (37usize, 23usize);
+        let (_, jxl) = fixture::image_u8(width, height, channels);
+        let image = fixture::read(&jxl);
+        let render = image.render_frame(0).unwrap();
+
+        let mut expected = vec![0f32; width * height * channels];
+        render.stream().write_to_buffer(&mut expected);
+        let expected = expected.into_iter().map(f16::from_f32).collect::<Vec<_>>();
+
+        let mut buf = vec![f16::ZERO; expected.len()];
+        render.stream().write_to_buffer(&mut buf);
+        assert_eq!(buf, expected);
+    }
+}
@@ -4,6 +4,9 @@ use image::DynamicImage;
 use jxl_oxide::integration::JxlDecoder;
 use jxl_oxide_tests as util;
 
+#[cfg(feature = "encode")]
+use crate::fixture;
+
 #[test]
 fn decode_u8() {
     let path = util::conformance_path("lz77_flower");
@@ -86,3 +89,85 @@ fn icc_profile() {
         .unwrap();
     assert_eq!(&icc, include_bytes!("./grayscale.icc"));
 }
+
+#[test]
+fn animation_frames() {
+    use image::AnimationDecoder;
+
+    let path = util::conformance_path("animation_newtons_cradle");
+    let file = File::open(path).unwrap();
+    let decoder = JxlDecoder::new(file).unwrap();
+    let dimensions = image::ImageDecoder::dimensions(&decoder);
+
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 36);
+    for frame in &frames {
+        assert_eq!(frame.buffer().dimensions(), dimensions);
+        assert_ne!(frame.delay().numer_denom_ms().0, 0);
+    }
+}
+
+#[cfg(feature = "encode")]
+#[test]
+fn animation_frames_still() {
+    use image::AnimationDecoder;
+
+    let (samples, jxl) = fixture::image_u8(16, 8, 3);
+    let decoder = JxlDecoder::new(std::io::Cursor::new(jxl)).unwrap();
+
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].delay().numer_denom_ms(), (0, 1));
+    let rgba = frames[0].buffer().as_raw();
+    for (actual, expected) in rgba.chunks_exact(4).zip(samples.chunks_exact(3)) {
+        assert_eq!(&actual[..3], expected);
+        assert_eq!(actual[3], 255);
+    }
+}
+
+#[cfg(feature = "encode")]
+#[test]
+fn color_profile() {
+    use image::ImageDecoder;
+    use jxl_oxide::{DecodeOptions, EnumColourEncoding, RenderingIntent};
+
+    let (_, jxl) = fixture::image_u8(16, 8, 3);
+
+    let mut decoder = JxlDecoder::new(std::io::Cursor::new(&jxl)).unwrap();
+    assert_eq!(decoder.cicp(), Some([1, 13, 0, 1]));
+    assert!(decoder.icc_profile().unwrap().is_some());
+
+    let mut options = DecodeOptions::new();
+    options.target_color_encoding = Some(EnumColourEncoding::display_p3(RenderingIntent::Relative));
+    let mut decoder = JxlDecoder::with_options(std::io::Cursor::new(&jxl), options).unwrap();
+    assert_eq!(decoder.cicp(), Some([12, 13, 0, 1]));
+    let icc = decoder.icc_profile().unwrap().unwrap();
+    assert_eq!(&icc[36..40], b"acsp");
+    assert_eq!(
+        decoder.original_color_type(),
+        image::ExtendedColorType::Rgb8
+    );
+}
+
+#[cfg(feature = "encode")]
+#[test]
+fn truncated_input() {
+    use image::{AnimationDecoder, ImageDecoder};
+
+    let (width, height) = (512usize, 256usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 3);
+    let truncated = &jxl[..jxl.len() - 16];
+
+    let decoder = JxlDecoder::new(std::io::Cursor::new(truncated)).unwrap();
+    let mut buf = vec![0u8; decoder.total_bytes() as usize];
+    decoder.read_image(&mut buf).unwrap();
+    // The first group is fully loaded.
+    let stride = width * 3;
+    for (actual, expected) in buf.chunks_exact(stride).zip(samples.chunks_exact(stride)) {
+        assert_eq!(&actual[..stride / 2], &expected[..stride / 2]);
+    }
+
+    let decoder = JxlDecoder::new(std::io::Cursor::new(truncated)).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 1);
+}
@@ -0,0 +1,132 @@
+use jxl_oxide::encode::transcode_jpeg;
+use jxl_oxide::{AllocCategory, AllocTracker, CropInfo, JxlImage};
+
+use crate::fixture;
+use crate::fixture::jpeg::{write_jpeg, JpegParams};
+
+#[test]
+fn memory_limit() {
+    let (_, jxl) = fixture::image_u8(300, 280, 3);
+
+    let err = JxlImage::builder()
+        .memory_limit_bytes(jxl.len() / 2)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap_err();
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+    let mut limit_exceeded = false;
+    while let Some(err) = source {
+        limit_exceeded |= err.to_string().contains("memory limit exceeded");
+        source = err.source();
+    }
+    assert!(limit_exceeded, "unexpected error: {err}");
+    assert!(
+        matches!(
+            err,
+            jxl_oxide::Error::MemoryLimit(jxl_oxide::MemoryError::LimitExceeded { .. })
+        ),
+        "unexpected error: {err}"
+    );
+
+    let image = JxlImage::builder()
+        .memory_limit_bytes(jxl.len())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    let expected = fixture::read(&jxl);
+    assert!(fixture::render_f32(&image).2 == fixture::render_f32(&expected).2);
+}
+
+#[test]
+fn render_cache_budget() {
+    let (_, jxl) = fixture::image_u8(37, 23, 3);
+
+    let expected = fixture::read(&jxl)
+        .render_frame(0)
+        .unwrap()
+        .image_all_channels();
+
+    let image = JxlImage::builder()
+        .render_cache_budget(0)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    for _ in 0..2 {
+        let render = image.render_frame(0).unwrap();
+        assert_eq!(render.image_all_channels().buf(), expected.buf());
+    }
+}
+
+#[test]
+fn release_bitstream() {
+    let (width, height) = (37usize, 23usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut image = fixture::read(&jxl);
+    assert!(image.retain_bitstream());
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+
+    image.set_retain_bitstream(false);
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.image_all_channels().buf(), expected.buf());
+
+    image.set_image_region(CropInfo {
+        width: 8,
+        height: 8,
+        left: 0,
+        top: 0,
+    });
+    let err = image.render_frame(0).unwrap_err();
+    let jxl_oxide::Error::Decode {
+        stage: jxl_oxide::DecodeStage::Render,
+        frame: Some(0),
+        source,
+        ..
+    } = err
+    else {
+        panic!("unexpected error: {err}");
+    };
+    let err = source.downcast_ref::<jxl_oxide::RenderError>().unwrap();
+    assert!(matches!(err, jxl_oxide::RenderError::FrameDataReleased));
+}
+
+#[test]
+fn alloc_stats() {
+    let (_, jxl) = fixture::image_u8(70, 30, 3);
+
+    let image = fixture::read(&jxl);
+    assert!(image.alloc_stats().is_none());
+
+    let image = JxlImage::builder()
+        .alloc_tracker(AllocTracker::with_limit(64 * 1024 * 1024))
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    image.render_frame(0).unwrap();
+
+    let stats = image.alloc_stats().unwrap();
+    assert!(stats.peak_bytes() > 0);
+    assert!(stats.peak_bytes() >= stats.current_bytes());
+    // Lossless Modular images are rendered from sample grids of the Modular image.
+    for category in [AllocCategory::Bitstream, AllocCategory::Modular] {
+        let category_stats = stats.category(category);
+        assert!(category_stats.peak_bytes > 0, "{category:?}");
+        assert!(category_stats.peak_bytes <= stats.peak_bytes());
+    }
+    assert_eq!(stats.category(AllocCategory::VarDct).peak_bytes, 0);
+    let current = AllocCategory::ALL
+        .into_iter()
+        .map(|category| stats.category(category).current_bytes)
+        .sum::<usize>();
+    assert_eq!(current, stats.current_bytes());
+}
+
+#[test]
+fn alloc_stats_vardct() {
+    let jxl = transcode_jpeg(&write_jpeg(&JpegParams::new(120, 200))).unwrap();
+    let image = JxlImage::builder()
+        .alloc_tracker(AllocTracker::with_limit(64 * 1024 * 1024))
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    image.render_frame(0).unwrap();
+
+    let stats = image.alloc_stats().unwrap();
+    assert!(stats.category(AllocCategory::VarDct).peak_bytes > 0);
+    assert!(stats.category(AllocCategory::Render).peak_bytes > 0);
+}
@@ -0,0 +1,23 @@
+use crate::fixture;
+
+#[test]
+fn ndarray_output() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 4);
+    let image = fixture::read(&jxl);
+    let render = image.render_frame(0).unwrap();
+
+    let array = render.to_ndarray();
+    assert_eq!(array.dim(), (height, width, 4));
+    for ((y, x, c), &v) in array.indexed_iter() {
+        let expected = samples[(y * width + x) * 4 + c];
+        assert_eq!((v * 255.0).round() as u8, expected);
+    }
+
+    let alpha = render.channel_to_ndarray(3).unwrap();
+    assert_eq!(alpha.dim(), (height, width));
+    for ((y, x), &v) in alpha.indexed_iter() {
+        assert_eq!(v, array[[y, x, 3]]);
+    }
+    assert!(render.channel_to_ndarray(4).is_none());
+}
@@ -0,0 +1,53 @@
+use jxl_oxide::encode::transcode_jpeg;
+use jxl_oxide::{JxlImage, JxlThreadPool};
+
+use crate::fixture;
+use crate::fixture::jpeg::{write_jpeg, JpegParams};
+
+#[test]
+fn deterministic_across_thread_pools() {
+    let (_, modular) = fixture::image_u16(300, 270, 4);
+    let mut subsampled = JpegParams::new(300, 280);
+    subsampled.luma_sampling = (2, 2);
+    // A single group of VarDCT images is split into row bands.
+    let vardct = [JpegParams::new(120, 200), subsampled]
+        .map(|params| transcode_jpeg(&write_jpeg(&params)).expect("failed to transcode JPEG"));
+
+    for jxl in std::iter::once(&modular).chain(&vardct) {
+        let expected = fixture::render_bits(jxl, JxlThreadPool::none());
+        for num_threads in [2, 4, 7] {
+            let actual = fixture::render_bits(jxl, JxlThreadPool::rayon(Some(num_threads)));
+            assert!(
+                actual == expected,
+                "render differs with {num_threads} threads"
+            );
+        }
+    }
+}
+
+#[test]
+fn prefetch_keyframes() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 3);
+
+    let image = JxlImage::builder()
+        .pool(JxlThreadPool::rayon(Some(2)))
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    // Keyframes out of range are skipped.
+    assert_eq!(image.prefetch_keyframes(0..4), 1);
+    assert_eq!(image.prefetch_keyframes(1..4), 0);
+    let mut buf = vec![0u8; width * height * 3];
+    image
+        .render_frame(0)
+        .unwrap()
+        .stream()
+        .write_to_buffer(&mut buf);
+    assert_eq!(buf, samples);
+
+    let image = JxlImage::builder()
+        .pool(JxlThreadPool::none())
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    assert_eq!(image.prefetch_keyframes(0..4), 0);
+}
@@ -0,0 +1,696 @@
+use jxl_oxide::encode::ImageRef;
+use jxl_oxide::{AlphaMode, CropInfo, DecodeOptions, JxlEncoder, JxlImage, PixelFormat};
+
+use crate::fixture;
+
+#[test]
+fn render_linear() {
+    let (samples, jxl) = fixture::image_u8(32, 16, 3);
+    let mut image = fixture::read(&jxl);
+    image.set_render_linear(true);
+    assert!(image.render_linear());
+
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(
+        render.pending_transfer(),
+        Some(jxl_oxide::color::TransferFunction::Srgb)
+    );
+    let mut stream = render.stream();
+    let mut buf = vec![0f32; 32 * 16 * 3];
+    stream.write_to_buffer(&mut buf);
+    for (&linear, &expected) in buf.iter().zip(&samples) {
+        let encoded = if linear <= 0.0031308 {
+            linear * 12.92
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+        let diff = (encoded * 255.0 - expected as f32).abs();
+        assert!(diff < 0.5, "sample differs: {encoded} vs {expected}");
+    }
+
+    image.set_render_linear(false);
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.pending_transfer(), None);
+    let mut stream = render.stream();
+    let mut buf = vec![0u8; 32 * 16 * 3];
+    stream.write_to_buffer(&mut buf);
+    assert_eq!(buf, samples);
+}
+
+#[test]
+fn render_preview_absent() {
+    let (_, jxl) = fixture::image_u8(16, 16, 3);
+    let image = fixture::read(&jxl);
+    assert!(image.image_header().metadata.preview.is_none());
+    assert!(image.render_preview().unwrap().is_none());
+}
+
+#[test]
+fn rerender_with_new_options() {
+    let (width, height) = (300usize, 280usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut image = fixture::read(&jxl);
+    let (_, _, full) = fixture::render_f32(&image);
+
+    let crop = jxl_oxide::CropInfo {
+        width: 100,
+        height: 50,
+        left: 200,
+        top: 230,
+    };
+    image.set_image_region(crop);
+    let (w, h, cropped) = fixture::render_f32(&image);
+    assert_eq!((w, h), (100, 50));
+    for y in 0..50 {
+        let expected = &full[((y + 230) * width + 200) * 3..][..300];
+        assert!(&cropped[y * 300..][..300] == expected, "row {y} differs");
+    }
+
+    image.set_image_region(jxl_oxide::CropInfo {
+        width: width as u32,
+        height: height as u32,
+        left: 0,
+        top: 0,
+    });
+    let linear = jxl_oxide::EnumColourEncoding {
+        tf: jxl_oxide::color::TransferFunction::Linear,
+        ..jxl_oxide::EnumColourEncoding::srgb(jxl_oxide::RenderingIntent::Relative)
+    };
+    image.request_color_encoding(linear.clone());
+    let (_, _, rerendered) = fixture::render_f32(&image);
+
+    let mut fresh = fixture::read(&jxl);
+    fresh.request_color_encoding(linear);
+    let (_, _, expected) = fixture::render_f32(&fresh);
+    assert!(rerendered == expected, "re-rendered image differs");
+}
+
+#[test]
+fn render_scanlines() {
+    let (width, height) = (300usize, 280usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut image = fixture::read(&jxl);
+    let (_, _, full) = fixture::render_f32(&image);
+
+    let crop = jxl_oxide::CropInfo {
+        width: 200,
+        height: 150,
+        left: 50,
+        top: 100,
+    };
+    image.set_image_region(crop);
+    let mut next_y = 0;
+    image
+        .render_scanlines::<f32>(0, |y, row| {
+            assert_eq!(y, next_y);
+            let y = y as usize;
+            let expected = &full[((y + 100) * width + 50) * 3..][..600];
+            assert!(row == expected, "row {y} differs");
+            next_y += 1;
+        })
+        .unwrap();
+    assert_eq!(next_y, 150);
+
+    let (w, h, cropped) = fixture::render_f32(&image);
+    assert_eq!((w, h), (200, 150));
+    assert!(cropped[..600] == full[(100 * width + 50) * 3..][..600]);
+}
+
+#[test]
+fn render_lf_level() {
+    let (width, height) = (64usize, 48usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+    let image = fixture::read(&jxl);
+
+    let (_, _, expected) = fixture::render_f32(&image);
+    let render = image.render_lf_level(0, 0).unwrap().unwrap();
+    let mut stream = render.stream();
+    let mut buf = vec![0f32; (stream.width() * stream.height() * stream.channels()) as usize];
+    stream.write_to_buffer(&mut buf);
+    assert!(buf == expected, "LF level 0 differs from keyframe");
+
+    // Encoder doesn't emit LF frames.
+    assert!(image.render_lf_level(0, 1).unwrap().is_none());
+    assert!(image.render_lf_level(1, 0).is_err());
+}
+
+#[test]
+fn decode_options() {
+    let (width, height) = (64usize, 48usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut options = DecodeOptions::new();
+    options.crop = Some(CropInfo {
+        width: 0,
+        height: 16,
+        left: 8,
+        top: 4,
+    });
+    options.render_noise = false;
+    // Encoded images don't use restoration filters, so skipping them doesn't change the output.
+    options.skip_restoration_filters = true;
+    let image = JxlImage::builder()
+        .options(options)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    assert_eq!(image.noise_strength(), 0.0);
+    let (_, cropped_height, cropped) = fixture::render_f32(&image);
+    assert_eq!(cropped_height, 16);
+
+    let mut image = fixture::read(&jxl);
+    image.set_image_region(CropInfo {
+        width: width as u32,
+        height: 16,
+        left: 8,
+        top: 4,
+    });
+    assert!(fixture::render_f32(&image).2 == cropped);
+}
+
+#[test]
+fn rgba8_texture() {
+    use jxl_oxide::integration::{prepare_image, Rgba8Texture};
+
+    let (width, height) = (16usize, 8usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 2);
+    let mut image = fixture::read(&jxl);
+    prepare_image(&mut image);
+
+    let render = image.render_frame(0).unwrap();
+    let texture = Rgba8Texture::from_render(&render).unwrap();
+    assert_eq!(texture.size(), [width, height]);
+    for (actual, expected) in texture
+        .pixels()
+        .chunks_exact(4)
+        .zip(samples.chunks_exact(2))
+    {
+        let [l, a] = [expected[0], expected[1]];
+        assert_eq!(actual, [l, l, l, a]);
+    }
+}
+
+#[test]
+fn active_alpha() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 4);
+    let mut image = fixture::read(&jxl);
+
+    assert_eq!(image.alpha_channels(), [0]);
+    assert_eq!(image.active_alpha(), Some(0));
+    assert_eq!(image.pixel_format(), PixelFormat::Rgba);
+
+    // Not an extra channel; ignored.
+    image.set_active_alpha(Some(1));
+    assert_eq!(image.active_alpha(), Some(0));
+
+    image.set_active_alpha(None);
+    assert_eq!(image.pixel_format(), PixelFormat::Rgb);
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.active_alpha(), None);
+    let mut stream = render.stream();
+    assert_eq!(stream.channels(), 3);
+    let mut buf = vec![0u8; width * height * 3];
+    stream.write_to_buffer(&mut buf);
+    for (px, expected) in buf.chunks_exact(3).zip(samples.chunks_exact(4)) {
+        assert_eq!(px, &expected[..3]);
+    }
+
+    image.set_active_alpha(Some(0));
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.active_alpha(), Some(0));
+    assert_eq!(render.stream().channels(), 4);
+}
+
+#[test]
+fn alpha_mode() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 4);
+    let image = fixture::read(&jxl);
+    let render = image.render_frame(0).unwrap();
+
+    assert_eq!(render.stream().is_alpha_premultiplied(), Some(false));
+    assert_eq!(render.stream_no_alpha().is_alpha_premultiplied(), None);
+
+    // Encoded alpha is straight; no conversion is done.
+    let mut stream = render.stream_with_alpha_mode(AlphaMode::Straight);
+    assert_eq!(stream.is_alpha_premultiplied(), Some(false));
+    let mut buf = vec![0u8; width * height * 4];
+    stream.write_to_buffer(&mut buf);
+    assert_eq!(buf, samples);
+
+    let mut stream = render.stream_with_alpha_mode(AlphaMode::Premultiplied);
+    assert_eq!(stream.is_alpha_premultiplied(), Some(true));
+    let mut buf = vec![0f32; width * height * 4];
+    stream.write_to_buffer(&mut buf);
+    for (px, expected) in buf.chunks_exact(4).zip(samples.chunks_exact(4)) {
+        let alpha = expected[3] as f32 / 255.0;
+        for (&v, &e) in px[..3].iter().zip(&expected[..3]) {
+            assert!((v - e as f32 / 255.0 * alpha).abs() < 1e-5);
+        }
+        assert!((px[3] - alpha).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn composite_on() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 4);
+    let mut image = fixture::read(&jxl);
+
+    let background = [1.0, 0.5, 0.0];
+    let fb = image.render_frame(0).unwrap().composite_on(background);
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (width, height, 3));
+    for (px, expected) in fb.buf().chunks_exact(3).zip(samples.chunks_exact(4)) {
+        let alpha = expected[3] as f32 / 255.0;
+        for ((&v, &e), bg) in px.iter().zip(&expected[..3]).zip(background) {
+            let e = e as f32 / 255.0 * alpha + bg * (1.0 - alpha);
+            assert!((v - e).abs() < 1e-5);
+        }
+    }
+
+    // Without alpha, color samples are copied as is.
+    image.set_active_alpha(None);
+    let fb = image.render_frame(0).unwrap().composite_on(background);
+    assert_eq!(fb.channels(), 3);
+    for (px, expected) in fb.buf().chunks_exact(3).zip(samples.chunks_exact(4)) {
+        for (&v, &e) in px.iter().zip(&expected[..3]) {
+            assert!((v - e as f32 / 255.0).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn spot_color_settings() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 4);
+    let mut image = fixture::read(&jxl);
+
+    assert!(image.spot_color_channels().is_empty());
+    assert_eq!(image.spot_color_settings(0), None);
+
+    // Alpha channel is not a spot colour channel; ignored.
+    image.set_spot_color_enabled(0, false);
+    image.set_spot_color_solidity(0, Some(0.5));
+    assert_eq!(image.spot_color_settings(0), None);
+
+    let render = image.render_frame(0).unwrap();
+    let mut buf = vec![0u8; width * height * 4];
+    render.stream().write_to_buffer(&mut buf);
+    assert_eq!(buf, samples);
+}
+
+#[test]
+fn extra_channel_by_name() {
+    let (width, height) = (23usize, 11usize);
+    let (_, jxl) = fixture::image_u8(width, height, 4);
+
+    // Alpha channels are never skipped.
+    let image = JxlImage::builder()
+        .skip_extra_channels(true)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.extra_channels().0.len(), 1);
+    assert!(render.extra_channel_by_name("depth").is_none());
+
+    // Default alpha channel doesn't have a name.
+    let (ec, buffer) = render.extra_channel_by_name("").unwrap();
+    assert!(ec.is_alpha());
+    assert_eq!((buffer.width(), buffer.height()), (width, height));
+}
+
+#[test]
+fn render_diff_stats() {
+    let (width, height) = (23usize, 11usize);
+    let (samples, _) = fixture::image_u8(width, height, 3);
+    let inverted = samples.iter().map(|&v| 255 - v).collect::<Vec<_>>();
+
+    let decode = |width: usize, height: usize, samples: &[u8]| {
+        let jxl = fixture::encode_u8(width, height, 3, samples);
+        fixture::read(&jxl).render_frame(0).unwrap()
+    };
+    let render = decode(width, height, &samples);
+    let render_inverted = decode(width, height, &inverted);
+
+    let stats = render.diff_stats(&decode(width, height, &samples)).unwrap();
+    assert_eq!(stats.channels().len(), 3);
+    assert_eq!(stats.max(), 0.0);
+    assert_eq!(stats.max_mean(), 0.0);
+
+    let stats = render.diff_stats(&render_inverted).unwrap();
+    for (c, diff) in stats.channels().iter().enumerate() {
+        let diffs = samples
+            .iter()
+            .skip(c)
+            .step_by(3)
+            .map(|&v| (2.0 * v as f32 - 255.0).abs() / 255.0);
+        let max = diffs.clone().fold(0f32, f32::max);
+        let mean = diffs.sum::<f32>() / (width * height) as f32;
+        assert!((diff.max - max).abs() < 1e-5);
+        assert!((diff.mean - mean).abs() < 1e-5);
+    }
+    assert!(stats.max() > 0.0);
+
+    let other = decode(10, 11, &samples[..10 * 11 * 3]);
+    assert!(render.diff_stats(&other).is_none());
+}
+
+#[test]
+fn stream_u8_matches_f32() {
+    let (width, height, channels) = (37usize, 23usize, 4usize);
+    let (_, jxl) = fixture::image_u8(width, height, channels);
+    let mut image = fixture::read(&jxl);
+    // Linear output goes through color conversion, producing floating point samples.
+    image.set_render_linear(true);
+    let render = image.render_frame(0).unwrap();
+
+    let mut expected = vec![0f32; width * height * channels];
+    render.stream().write_to_buffer(&mut expected);
+    let expected = expected
+        .into_iter()
+        .map(|v| (v * 255.0 + 0.5).clamp(0.0, 255.0) as u8)
+        .collect::<Vec<_>>();
+
+    let mut buf = vec![0u8; expected.len()];
+    render.stream().write_to_buffer(&mut buf);
+    assert_eq!(buf, expected);
+
+    // Write in chunks not aligned to rows, so that both per-sample and per-row paths are taken.
+    let mut stream = render.stream();
+    let mut buf = vec![0u8; expected.len()];
+    let mut written = 0;
+    while written < buf.len() {
+        let end = (written + 300).min(buf.len());
+        written += stream.write_to_buffer(&mut buf[written..end]);
+    }
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn max_output_dimension() {
+    let (width, height) = (70usize, 30usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let image = fixture::read(&jxl);
+    assert_eq!(image.output_downscale(), 1);
+
+    let image = JxlImage::builder()
+        .max_output_dimension(20)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    assert_eq!(image.output_downscale(), 4);
+
+    let render = image.render_frame(0).unwrap();
+    let fb = render.image_scaled();
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (18, 8, 3));
+
+    let full = render.image_all_channels();
+    let expected = full.downsample(4);
+    assert_eq!(fb.buf(), expected.buf());
+    // The last column averages two columns of the image.
+    let (x, y) = (17usize, 3usize);
+    let mut sum = 0f32;
+    for sy in y * 4..y * 4 + 4 {
+        for sx in x * 4..width {
+            sum += full.buf()[(sy * width + sx) * 3];
+        }
+    }
+    assert!((fb.buf()[(y * 18 + x) * 3] - sum / 8.0).abs() < 1e-6);
+}
+
+#[test]
+fn set_output_downscale() {
+    let (width, height) = (70usize, 30usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut image = JxlImage::builder()
+        .max_output_dimension(20)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    assert_eq!(image.output_downscale(), 4);
+
+    // Factors are rounded down to powers of two.
+    image.set_output_downscale(3);
+    assert_eq!(image.output_downscale(), 2);
+    let fb = image.render_frame(0).unwrap().image_scaled();
+    assert_eq!((fb.width(), fb.height()), (35, 15));
+
+    image.set_output_downscale(100);
+    assert_eq!(image.output_downscale(), 64);
+    let fb = image.render_frame(0).unwrap().image_scaled();
+    assert_eq!((fb.width(), fb.height()), (2, 1));
+
+    image.set_output_downscale(1);
+    assert_eq!(image.output_downscale(), 1);
+    let fb = image.render_frame(0).unwrap().image_scaled();
+    assert_eq!((fb.width(), fb.height()), (width, height));
+    for (&v, &e) in fb.buf().iter().zip(&samples) {
+        assert!((v - e as f32 / 255.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn render_frame_image() {
+    let (width, height) = (70usize, 600usize);
+    let (_, jxl) = fixture::image_u8(width, height, 4);
+    let mut image = fixture::read(&jxl);
+
+    let check = |image: &JxlImage| {
+        let expected = image.render_frame(0).unwrap().image_all_channels();
+        let fb = image.render_frame_image(0).unwrap();
+        assert_eq!(
+            (fb.width(), fb.height(), fb.channels()),
+            (expected.width(), expected.height(), expected.channels())
+        );
+        assert!(fb.buf() == expected.buf(), "samples differ");
+    };
+
+    check(&image);
+
+    // Color transform is done stripe by stripe.
+    image.request_color_encoding(jxl_oxide::EnumColourEncoding {
+        tf: jxl_oxide::color::TransferFunction::Linear,
+        ..jxl_oxide::EnumColourEncoding::srgb(jxl_oxide::RenderingIntent::Relative)
+    });
+    check(&image);
+
+    // Crop spanning multiple stripes.
+    image.set_image_region(CropInfo {
+        width: 50,
+        height: 400,
+        left: 10,
+        top: 100,
+    });
+    check(&image);
+
+    // VarDCT keyframes are rendered from the LF image with the downscaling factor of 8.
+    let params = fixture::jpeg::JpegParams::new(300, 260);
+    let jpeg = fixture::jpeg::write_jpeg(&params);
+    let jxl = jxl_oxide::encode::transcode_jpeg(&jpeg).unwrap();
+    let mut image = fixture::read(&jxl);
+    check(&image);
+    image.set_output_downscale(8);
+    check(&image);
+    let fb = image.render_frame_image(0).unwrap();
+    assert_eq!((fb.width(), fb.height()), (300 / 8 + 1, 260 / 8 + 1));
+}
+
+#[test]
+fn stream_unoriented() {
+    let (width, height) = (37usize, 21usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 3);
+    let image = fixture::read(&jxl);
+
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.orientation(), 1);
+    let mut stream = render.stream_unoriented();
+    assert_eq!(
+        (stream.width(), stream.height()),
+        (width as u32, height as u32)
+    );
+    let mut buf = vec![0u8; width * height * 3];
+    stream.write_to_buffer(&mut buf);
+    assert!(buf == samples, "decoded samples differ");
+
+    for orientation in 2..=8 {
+        let jxl = JxlEncoder::new()
+            .orientation(orientation)
+            .encode(ImageRef::from_u8(width, height, 3, &samples))
+            .unwrap();
+        let image = fixture::read(&jxl);
+        let render = image.render_frame(0).unwrap();
+        assert_eq!(render.orientation(), orientation);
+
+        // Samples are written in encoded order.
+        let mut stream = render.stream_unoriented();
+        assert_eq!(
+            (stream.width(), stream.height()),
+            (width as u32, height as u32)
+        );
+        let mut buf = vec![0u8; width * height * 3];
+        stream.write_to_buffer(&mut buf);
+        assert!(buf == samples, "orientation {orientation}: samples differ");
+
+        let mut stream = render.stream();
+        let (out_width, out_height) = match orientation {
+            1..=4 => (width, height),
+            _ => (height, width),
+        };
+        assert_eq!(
+            (stream.width(), stream.height()),
+            (out_width as u32, out_height as u32)
+        );
+        let mut oriented = vec![0u8; width * height * 3];
+        stream.write_to_buffer(&mut oriented);
+        for y in 0..height {
+            for x in 0..width {
+                let (ox, oy) = match orientation {
+                    2 => (width - x - 1, y),
+                    3 => (width - x - 1, height - y - 1),
+                    4 => (x, height - y - 1),
+                    5 => (y, x),
+                    6 => (height - y - 1, x),
+                    7 => (height - y - 1, width - x - 1),
+                    8 => (y, width - x - 1),
+                    _ => unreachable!(),
+                };
+                let source = (y * width + x) * 3;
+                let target = (oy * out_width + ox) * 3;
+                assert_eq!(
+                    oriented[target..][..3],
+                    samples[source..][..3],
+                    "orientation {orientation}: sample ({x}, {y}) differs"
+                );
+            }
+        }
+    }
+
+    let err = JxlEncoder::new()
+        .orientation(9)
+        .encode(ImageRef::from_u8(width, height, 3, &samples))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        jxl_oxide::encode::Error::InvalidOrientation(9)
+    ));
+}
+
+#[test]
+fn image_integer() {
+    use jxl_oxide::IntegerSamples;
+
+    let (width, height) = (45usize, 30usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 4);
+    let mut image = fixture::read(&jxl);
+    let fb = image.render_frame(0).unwrap().image_integer().unwrap();
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (width, height, 4));
+    assert_eq!(fb.bits_per_sample(), 8);
+    assert_eq!(fb.into_samples(), IntegerSamples::U8(samples));
+
+    // Color transformed render has float samples.
+    image.request_color_encoding(jxl_oxide::EnumColourEncoding {
+        tf: jxl_oxide::color::TransferFunction::Linear,
+        ..jxl_oxide::EnumColourEncoding::srgb(jxl_oxide::RenderingIntent::Relative)
+    });
+    assert!(image.render_frame(0).unwrap().image_integer().is_none());
+
+    let (samples, jxl) = fixture::image_u16(width, height, 3);
+    let image = fixture::read(&jxl);
+    let fb = image.render_frame(0).unwrap().image_integer().unwrap();
+    assert_eq!(fb.bits_per_sample(), 16);
+    assert_eq!(fb.into_samples(), IntegerSamples::U16(samples));
+}
+
+#[test]
+fn noise_strength() {
+    let (width, height) = (64usize, 48usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 3);
+    let noisy = jxl_oxide::JxlEncoder::new()
+        .noise([0.5; 8])
+        .encode(jxl_oxide::encode::ImageRef::from_u8(
+            width, height, 3, &samples,
+        ))
+        .unwrap();
+    let (_, _, expected) = fixture::render_f32(&fixture::read(&jxl));
+
+    let mut image = fixture::read(&noisy);
+    let mut render_with_strength = |strength: f32| {
+        image.set_noise_strength(strength);
+        let (_, _, buf) = fixture::render_f32(&image);
+        buf
+    };
+    let mean_abs_diff = |buf: &[f32]| {
+        let sum = buf
+            .iter()
+            .zip(&expected)
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>();
+        sum / buf.len() as f32
+    };
+
+    let full = mean_abs_diff(&render_with_strength(1.0));
+    let half = mean_abs_diff(&render_with_strength(0.5));
+    assert!(full > 0.001, "noise is not rendered");
+    let ratio = half / full;
+    assert!(
+        (0.4..0.6).contains(&ratio),
+        "unexpected noise ratio {ratio}"
+    );
+    assert!(render_with_strength(0.0) == expected, "noise is rendered");
+
+    // Negative strength and NaN disable noise.
+    for strength in [-1.0, f32::NAN] {
+        image.set_noise_strength(strength);
+        assert_eq!(image.noise_strength(), 0.0);
+        let (_, _, buf) = fixture::render_f32(&image);
+        assert!(
+            buf == expected,
+            "noise is rendered with strength {strength}"
+        );
+    }
+}
+
+#[test]
+fn skip_restoration_filters() {
+    let (width, height) = (64usize, 48usize);
+    // Smooth gradient with slight noise, which edge-preserving filter doesn't treat as edges.
+    let samples = fixture::generate_samples(width, height, 3, 255)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, v)| ((idx / 3 % width) as u32 * 2 + (v & 1)) as u8)
+        .collect::<Vec<_>>();
+    let jxl = fixture::encode_u8(width, height, 3, &samples);
+    let (_, _, unfiltered) = fixture::render_f32(&fixture::read(&jxl));
+
+    for (gabor, epf_iters) in [(true, 0), (false, 1), (true, 3)] {
+        let filtered = jxl_oxide::JxlEncoder::new()
+            .gabor(gabor)
+            .epf_iterations(epf_iters)
+            .encode(jxl_oxide::encode::ImageRef::from_u8(
+                width, height, 3, &samples,
+            ))
+            .unwrap();
+        let image = fixture::read(&filtered);
+        let restoration_filter = &image.frame_header(0).unwrap().restoration_filter;
+        assert_eq!(restoration_filter.gab.enabled(), gabor);
+        assert_eq!(restoration_filter.epf.enabled(), epf_iters > 0);
+        let (_, _, buf) = fixture::render_f32(&image);
+        assert!(
+            buf != unfiltered,
+            "filters are not applied ({gabor}, {epf_iters})"
+        );
+
+        // Skipping filters renders the same as the image with both filters disabled.
+        let image = JxlImage::builder()
+            .skip_restoration_filters(true)
+            .read(std::io::Cursor::new(&filtered))
+            .unwrap();
+        let (_, _, buf) = fixture::render_f32(&image);
+        assert!(
+            buf == unfiltered,
+            "filters are applied ({gabor}, {epf_iters})"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use jxl_oxide::{CropInfo, DecodeOptions};
+
+#[test]
+fn decode_options_serde() {
+    let mut options = DecodeOptions::new();
+    options.crop = Some(CropInfo {
+        width: 32,
+        height: 16,
+        left: 8,
+        top: 4,
+    });
+    options.target_color_encoding = Some(jxl_oxide::EnumColourEncoding::bt2100_pq(
+        jxl_oxide::color::RenderingIntent::Perceptual,
+    ));
+    options.memory_limit_bytes = Some(1 << 20);
+
+    let json = serde_json::to_string(&options).unwrap();
+    let parsed: DecodeOptions = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, options);
+
+    let parsed: DecodeOptions = serde_json::from_str(r#"{ "render_linear": true }"#).unwrap();
+    assert!(parsed.render_linear);
+    assert!(parsed.render_spot_color);
+    assert!(parsed.render_noise);
+}
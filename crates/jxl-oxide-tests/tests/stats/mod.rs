@@ -0,0 +1,82 @@
+use jxl_oxide::JxlImage;
+
+use crate::fixture;
+
+#[test]
+fn decoder_stats() {
+    let (_, jxl) = fixture::image_u8(300, 280, 3);
+    let image = fixture::read(&jxl);
+
+    let stats = image.decoder_stats();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].stats().pass_groups_decoded(), 0);
+
+    fixture::render_f32(&image);
+    let stats = image.decoder_stats();
+    let frame_stats = &stats[0];
+    assert_eq!(frame_stats.frame_index(), 0);
+    assert!(frame_stats.is_keyframe());
+
+    let num_groups = frame_stats
+        .sections()
+        .filter(|group| matches!(group.kind, jxl_oxide::TocGroupKind::GroupPass { .. }))
+        .count();
+    assert_eq!(frame_stats.stats().pass_groups_decoded(), num_groups);
+    assert_eq!(frame_stats.stats().epf_iterations(), 0);
+    assert!(frame_stats.total_bytes() > 0);
+    assert!(frame_stats.total_bytes() < jxl.len() as u64);
+    // The encoder doesn't use LZ77 for Modular streams.
+    assert!(!frame_stats.uses_lz77());
+    assert_eq!(frame_stats.max_lz77_distance(), 0);
+
+    // Images without LZ77 copies decode with the strictest limit.
+    let image = JxlImage::builder()
+        .lz77_distance_limit(0)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    fixture::render_f32(&image);
+}
+
+fn pass_groups_decoded(image: &JxlImage) -> usize {
+    image.decoder_stats()[0].stats().pass_groups_decoded()
+}
+
+#[test]
+fn render_scanlines_decodes_groups_once() {
+    let params = fixture::jpeg::JpegParams::new(600, 520);
+    let jpeg = fixture::jpeg::write_jpeg(&params);
+    let jxl = jxl_oxide::encode::transcode_jpeg(&jpeg).unwrap();
+
+    let image = fixture::read(&jxl);
+    let (width, _, full) = fixture::render_f32(&image);
+    let num_groups = image.decoder_stats()[0]
+        .sections()
+        .filter(|group| matches!(group.kind, jxl_oxide::TocGroupKind::GroupPass { .. }))
+        .count();
+    assert!(num_groups > 1);
+    assert_eq!(pass_groups_decoded(&image), num_groups);
+
+    // Strips are decoded at full resolution without the LF shortcut.
+    let mut downscaled = fixture::read(&jxl);
+    downscaled.set_output_downscale(8);
+    let mut next_y = 0;
+    downscaled
+        .render_scanlines::<f32>(0, |y, row| {
+            assert_eq!(y, next_y);
+            let expected = &full[(y * width * 3) as usize..][..row.len()];
+            assert!(row == expected, "row {y} differs");
+            next_y += 1;
+        })
+        .unwrap();
+    assert_eq!(next_y, 520);
+    // The image is 3x3 groups. Each strip decodes its own group row, and adjacent group rows
+    // which filters and chroma upsampling reference across group boundaries.
+    let strip_groups = 3 * (2 + 3 + 2);
+    assert_eq!(pass_groups_decoded(&downscaled), strip_groups);
+
+    // Cached renders are kept, so the keyframe isn't rendered again.
+    image.render_scanlines::<f32>(0, |_, _| {}).unwrap();
+    assert_eq!(pass_groups_decoded(&image), num_groups + strip_groups);
+    fixture::render_f32(&image);
+    assert_eq!(pass_groups_decoded(&image), num_groups + strip_groups);
+}
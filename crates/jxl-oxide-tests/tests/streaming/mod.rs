@@ -0,0 +1,223 @@
+use jxl_oxide::{InitializeResult, JxlImage, TocGroupKind};
+
+use crate::fixture;
+
+#[test]
+fn resume_from_snapshot() {
+    let (_, codestream) = fixture::image_u8(37, 23, 3);
+
+    // Container with an XML box and the codestream split into two partial codestream boxes.
+    let split = codestream.len() / 2;
+    let xml = b"<x:xmpmeta xmlns:x='adobe:ns:meta/'/>";
+    let jxlp0 = [&[0, 0, 0, 0][..], &codestream[..split]].concat();
+    let jxlp1 = [&[0x80, 0, 0, 1][..], &codestream[split..]].concat();
+    let container =
+        fixture::write_container(&[(*b"xml ", xml), (*b"jxlp", &jxlp0), (*b"jxlp", &jxlp1)]);
+
+    for jxl in [&codestream, &container] {
+        let expected = fixture::read(jxl)
+            .render_frame(0)
+            .unwrap()
+            .image_all_channels();
+
+        let feed_until = jxl.len() * 3 / 4;
+        let mut uninit = JxlImage::builder().build_uninit();
+        let consumed = uninit.feed_bytes(&jxl[..feed_until]).unwrap();
+        let InitializeResult::Initialized(mut image) = uninit.try_init().unwrap() else {
+            panic!("image header is not read");
+        };
+        image.feed_bytes(&jxl[consumed..feed_until]).unwrap();
+        assert_eq!(image.num_loaded_keyframes(), 0);
+
+        let snapshot = image.snapshot().unwrap();
+        drop(image);
+        assert_eq!(snapshot.loaded_frames(), 0);
+        assert!(snapshot.input_offset() <= feed_until as u64);
+
+        let mut image = JxlImage::builder().resume(&snapshot).unwrap();
+        let input_offset = snapshot.input_offset() as usize;
+        image.feed_bytes(&jxl[input_offset..]).unwrap();
+        image.finalize().unwrap();
+        assert_eq!(image.frame_offset(0), Some(snapshot.codestream_offset()));
+        if jxl == &container {
+            assert_eq!(image.aux_boxes().first_xml().unwrap(), xml);
+        }
+
+        let render = image.render_frame(0).unwrap();
+        assert_eq!(render.image_all_channels().buf(), expected.buf());
+    }
+}
+
+#[test]
+fn frame_progress() {
+    let (_, jxl) = fixture::image_u8(300, 300, 3);
+
+    let mut uninit = JxlImage::builder().build_uninit();
+    let half = jxl.len() / 2;
+    let consumed = uninit.feed_bytes(&jxl[..half]).unwrap();
+    let InitializeResult::Initialized(mut image) = uninit.try_init().unwrap() else {
+        panic!("image header is not read");
+    };
+    image.feed_bytes(&jxl[consumed..half]).unwrap();
+
+    let progress = image.frame_progress(0).unwrap();
+    assert!(!progress.is_complete());
+    assert!(progress.loaded_groups() < progress.total_groups());
+    assert!(progress.fraction() > 0.0 && progress.fraction() < 1.0);
+    assert!(image.frame_progress(1).is_none());
+
+    image.feed_bytes(&jxl[half..]).unwrap();
+    let progress = image.frame_progress(0).unwrap();
+    assert!(progress.is_complete());
+    assert!(progress.is_lf_loaded());
+    assert_eq!(progress.completed_passes(), progress.num_passes());
+    assert_eq!(progress.fraction(), 1.0);
+}
+
+#[test]
+fn render_best_effort() {
+    use jxl_oxide::SampleCoverage;
+
+    let (width, height) = (300usize, 300usize);
+    let (_, jxl) = fixture::image_u8(width, height, 3);
+
+    let mut uninit = JxlImage::builder().build_uninit();
+    // Stop in the middle of pass groups.
+    let cut = jxl.len() * 3 / 4;
+    let consumed = uninit.feed_bytes(&jxl[..cut]).unwrap();
+    let InitializeResult::Initialized(mut image) = uninit.try_init().unwrap() else {
+        panic!("image header is not read");
+    };
+    image.feed_bytes(&jxl[consumed..cut]).unwrap();
+
+    let render = image.render_best_effort(0);
+    let coverage = render.coverage();
+    assert_eq!((coverage.width(), coverage.height()), (width, height));
+    assert!(!coverage.is_complete());
+    assert!(coverage.count(SampleCoverage::Complete) > 0);
+    assert!(coverage.count(SampleCoverage::Missing) > 0);
+    let fb = render.image();
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (width, height, 3));
+    for (pixel, &c) in fb.buf().chunks_exact(3).zip(coverage.samples()) {
+        if c == SampleCoverage::Missing {
+            assert_eq!(pixel, [0.5; 3]);
+        }
+    }
+
+    // Keyframes which are not loaded are filled entirely.
+    let render = image.render_best_effort(1);
+    assert_eq!(render.keyframe_index(), 1);
+    assert_eq!(
+        render.coverage().count(SampleCoverage::Missing),
+        width * height
+    );
+    assert!(render.image().buf().iter().all(|&v| v == 0.5));
+
+    image.feed_bytes(&jxl[cut..]).unwrap();
+    let render = image.render_best_effort(0);
+    assert!(render.coverage().is_complete());
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(render.image().buf(), expected.buf());
+}
+
+#[test]
+fn error_context() {
+    let (_, jxl) = fixture::image_u8(300, 300, 3);
+
+    let mut uninit = JxlImage::builder().build_uninit();
+    let cut = jxl.len() * 3 / 4;
+    let consumed = uninit.feed_bytes(&jxl[..cut]).unwrap();
+    let InitializeResult::Initialized(mut image) = uninit.try_init().unwrap() else {
+        panic!("image header is not read");
+    };
+    image.feed_bytes(&jxl[consumed..cut]).unwrap();
+
+    // The error points at the truncated group.
+    let frame = image.frame(0).unwrap();
+    let truncated = frame.current_loading_group().unwrap();
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(err.group(), Some(truncated.kind), "unexpected error: {err}");
+    assert_eq!(
+        err.offset(),
+        Some(image.frame_offset(0).unwrap() + truncated.offset)
+    );
+    let jxl_oxide::Error::Decode {
+        frame: Some(0),
+        source,
+        ..
+    } = &err
+    else {
+        panic!("unexpected error: {err}");
+    };
+    let source = source.downcast_ref::<jxl_oxide::RenderError>().unwrap();
+    assert!(matches!(source, jxl_oxide::RenderError::IncompleteFrame));
+    assert!(err.to_string().contains(&truncated.kind.to_string()));
+}
+
+#[test]
+fn skip_corrupt_groups() {
+    let (width, height) = (300usize, 300usize);
+    let (_, mut jxl) = fixture::image_u8(width, height, 3);
+
+    // Corrupt the middle of the first pass group.
+    let image = fixture::read(&jxl);
+    let frame_offset = image.frame_offset(0).unwrap();
+    let group = image
+        .frame(0)
+        .unwrap()
+        .toc()
+        .iter_bitstream_order()
+        .find(|group| matches!(group.kind, TocGroupKind::GroupPass { .. }))
+        .unwrap();
+    let start = frame_offset + group.offset + group.size as usize / 2;
+    jxl[start..start + 64].fill(0xff);
+
+    let image = fixture::read(&jxl);
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(err.group(), Some(group.kind), "unexpected error: {err}");
+
+    let image = JxlImage::builder()
+        .skip_corrupt_groups(true)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.corrupt_groups(), [group.kind]);
+    let fb = render.image_all_channels();
+    assert_eq!((fb.width(), fb.height()), (width, height));
+}
+
+#[test]
+fn feed_bytes_in_small_chunks() {
+    let (_, jxl) = fixture::image_u8(70, 30, 3);
+    let expected_image = fixture::read(&jxl);
+    let expected = expected_image.render_frame(0).unwrap().image_all_channels();
+
+    for chunk_size in [1, 3, 17, 100] {
+        let mut uninit = Some(JxlImage::builder().build_uninit());
+        let mut image: Option<JxlImage> = None;
+        // Bytes not consumed by the reader are fed again with the next chunk.
+        let mut pending = Vec::new();
+        for chunk in jxl.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            let consumed = if let Some(image) = &mut image {
+                image.feed_bytes(&pending).unwrap()
+            } else {
+                let mut current = uninit.take().unwrap();
+                let consumed = current.feed_bytes(&pending).unwrap();
+                match current.try_init().unwrap() {
+                    InitializeResult::NeedMoreData(x) => uninit = Some(x),
+                    InitializeResult::Initialized(x) => image = Some(x),
+                }
+                consumed
+            };
+            pending.drain(..consumed);
+        }
+        let mut image = image.unwrap();
+        image.feed_bytes(&pending).unwrap();
+        image.finalize().unwrap();
+
+        assert_eq!(image.frame_offset(0), expected_image.frame_offset(0));
+        let render = image.render_frame(0).unwrap();
+        assert_eq!(render.image_all_channels().buf(), expected.buf());
+    }
+}
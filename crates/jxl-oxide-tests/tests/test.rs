@@ -1,15 +1,63 @@
+#[cfg(feature = "encode")]
+mod fixture;
+
+#[cfg(all(feature = "encode", feature = "rayon"))]
+mod rayon;
+
+#[cfg(all(feature = "encode", feature = "stats"))]
+mod stats;
+
+#[cfg(feature = "serde")]
+mod serde;
+
+#[cfg(all(feature = "encode", feature = "ndarray"))]
+mod ndarray;
+
+#[cfg(all(feature = "encode", feature = "half"))]
+mod half;
+
+#[cfg(all(feature = "encode", feature = "export"))]
+mod export;
+
+#[cfg(feature = "encode")]
+mod cancel;
+
+#[cfg(feature = "encode")]
+mod color;
+
 #[cfg(feature = "conformance")]
 mod conformance;
 
+#[cfg(feature = "encode")]
+mod container;
+
 #[cfg(feature = "crop")]
 mod crop;
 
 #[cfg(feature = "decode")]
 mod decode;
 
+#[cfg(feature = "encode")]
+mod encode;
+
+#[cfg(feature = "encode")]
+mod frame;
+
 #[cfg(feature = "image")]
 mod image;
 
+#[cfg(feature = "encode")]
+mod memory;
+
+#[cfg(feature = "encode")]
+mod render;
+
+#[cfg(feature = "encode")]
+mod streaming;
+
+#[cfg(feature = "encode")]
+mod threading;
+
 mod jbrd;
 
 mod fuzz_findings;
@@ -0,0 +1,101 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use jxl_oxide::{JxlImage, JxlSpawner, JxlThreadPool};
+
+use crate::fixture;
+
+#[test]
+fn force_scalar_is_per_image() {
+    let (width, height) = (300usize, 270usize);
+    let (samples, jxl) = fixture::image_u8(width, height, 3);
+
+    let scalar = JxlImage::builder()
+        .force_scalar(true)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    let simd = fixture::read(&jxl);
+    for image in [&simd, &scalar] {
+        let render = image.render_frame(0).unwrap();
+        let mut buf = vec![0u8; width * height * 3];
+        render.stream().write_to_buffer(&mut buf);
+        assert!(buf == samples, "decoded samples differ");
+    }
+}
+
+#[test]
+fn custom_spawner() {
+    struct ThreadPerJob(Arc<AtomicUsize>);
+
+    impl JxlSpawner for ThreadPerJob {
+        fn spawn(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            std::thread::spawn(job);
+        }
+
+        fn num_threads(&self) -> usize {
+            3
+        }
+    }
+
+    let (_, jxl) = fixture::image_u16(300, 270, 4);
+
+    let num_spawned = Arc::new(AtomicUsize::new(0));
+    let expected = fixture::render_bits(&jxl, JxlThreadPool::none());
+    let pool = JxlThreadPool::with_spawner(ThreadPerJob(Arc::clone(&num_spawned)));
+    let actual = fixture::render_bits(&jxl, pool);
+    assert!(actual == expected);
+    assert!(num_spawned.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn max_concurrency() {
+    #[derive(Default)]
+    struct Counter {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    struct CountingSpawner(Arc<Counter>);
+
+    impl JxlSpawner for CountingSpawner {
+        fn spawn(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+            let counter = Arc::clone(&self.0);
+            std::thread::spawn(move || {
+                let running = counter.running.fetch_add(1, Ordering::SeqCst) + 1;
+                counter.max_running.fetch_max(running, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                job();
+                counter.running.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+
+        fn num_threads(&self) -> usize {
+            8
+        }
+    }
+
+    let (samples, jxl) = fixture::image_u8(300, 270, 3);
+
+    let counter = Arc::new(Counter::default());
+    let pool = JxlThreadPool::with_spawner(CountingSpawner(Arc::clone(&counter)));
+    let image = JxlImage::builder()
+        .pool(pool.clone())
+        .max_concurrency(2)
+        .read(std::io::Cursor::new(&jxl))
+        .unwrap();
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    for (&v, &e) in fb.buf().iter().zip(&samples) {
+        assert!((v - e as f32 / 255.0).abs() < 1e-6);
+    }
+    let max_running = counter.max_running.load(Ordering::SeqCst);
+    assert!((1..=2).contains(&max_running), "{max_running} tasks ran");
+
+    // The limit doesn't apply to the shared pool.
+    pool.scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|_| std::thread::sleep(std::time::Duration::from_millis(50)));
+        }
+    });
+    assert!(counter.max_running.load(Ordering::SeqCst) > 2);
+}
@@ -1,9 +1,19 @@
+use jxl_oxide::export::{self, ExportOptions};
 use jxl_oxide::{
-    color::ColourEncoding, EnumColourEncoding, InitializeResult, JxlImage, PixelFormat, Render,
-    RenderingIntent, UninitializedJxlImage,
+    color::ColourEncoding, DecodeOptions, EnumColourEncoding, InitializeResult, JxlImage,
+    PixelFormat, Render, RenderingIntent, UninitializedJxlImage,
 };
+
 use wasm_bindgen::prelude::*;
 
+mod player;
+#[cfg(feature = "web")]
+mod stream;
+
+pub use player::{AnimationFrame, AnimationPlayer};
+#[cfg(feature = "web")]
+pub use stream::decode_stream;
+
 #[cfg(feature = "dev")]
 #[wasm_bindgen(start)]
 fn start() {
@@ -15,6 +25,7 @@ fn start() {
 pub struct WasmJxlImage {
     inner: WasmJxlImageInner,
     force_srgb: bool,
+    has_target_color: bool,
 }
 
 enum WasmJxlImageInner {
@@ -65,9 +76,27 @@ impl WasmJxlImage {
         Self {
             inner,
             force_srgb: !is_hdr_supported(),
+            has_target_color: false,
         }
     }
 
+    /// Creates a decoder with decode options in JSON format.
+    ///
+    /// If the options specify the target color, it's used instead of the one chosen by
+    /// `forceSrgb` and HDR support of the browser.
+    #[wasm_bindgen(js_name = withOptions)]
+    pub fn with_options(options: &str) -> Result<WasmJxlImage, String> {
+        let options: DecodeOptions = serde_json::from_str(options).map_err(|e| e.to_string())?;
+        let has_target_color =
+            options.target_icc.is_some() || options.target_color_encoding.is_some();
+        let inner = WasmJxlImageInner::Uninit(JxlImage::builder().options(options).build_uninit());
+        Ok(Self {
+            inner,
+            force_srgb: !is_hdr_supported(),
+            has_target_color,
+        })
+    }
+
     #[wasm_bindgen(getter = forceSrgb)]
     pub fn force_srgb(&self) -> bool {
         self.force_srgb
@@ -101,7 +130,7 @@ impl WasmJxlImage {
                     let tagged_color_encoding = &image.image_header().metadata.colour_encoding;
                     let xyb_encoded = image.image_header().metadata.xyb_encoded;
                     if let ColourEncoding::Enum(color) = tagged_color_encoding {
-                        if xyb_encoded {
+                        if xyb_encoded && !self.has_target_color {
                             if self.force_srgb {
                                 image.request_color_encoding(EnumColourEncoding::srgb(
                                     RenderingIntent::Relative,
@@ -164,6 +193,20 @@ impl WasmJxlImage {
         }
     }
 
+    /// Returns the number of loaded frames and the offset of the group being loaded, which
+    /// changes whenever more of the image becomes available.
+    fn loading_progress(&self) -> Option<(usize, Option<usize>)> {
+        let WasmJxlImageInner::Init(image) = &self.inner else {
+            return None;
+        };
+        let num_frames = image.num_loaded_frames();
+        let loading_group = image
+            .frame(num_frames)
+            .and_then(|frame| frame.current_loading_group())
+            .map(|group| group.offset);
+        Some((num_frames, loading_group))
+    }
+
     pub fn render(&mut self, keyframe_idx: Option<u32>) -> Result<RenderResult, String> {
         let image = match &mut self.inner {
             WasmJxlImageInner::Uninit(_) => return Err(String::from("image not initialized")),
@@ -191,24 +234,19 @@ impl WasmJxlImage {
             (tps_numer * duration, tps_denom)
         };
 
-        let pixfmt = image.pixel_format();
-        let cicp = image.rendered_cicp();
-        let icc = if cicp.is_some() {
-            Vec::new()
-        } else {
-            image.rendered_icc()
-        };
+        let mut export_options = ExportOptions::from_image(image);
+        export_options.animation = None;
+        if export_options.cicp.is_some() {
+            export_options.icc = Vec::new();
+        }
 
         let has_embedded_icc = image.original_icc().is_some();
         let metadata = &image.image_header().metadata;
         let bit_depth = metadata.bit_depth.bits_per_sample();
-        let need_high_precision = !metadata.xyb_encoded && has_embedded_icc && bit_depth > 8;
+        export_options.sixteen_bits = !metadata.xyb_encoded && has_embedded_icc && bit_depth > 8;
         Ok(RenderResult {
             image: frame,
-            pixfmt,
-            need_high_precision,
-            icc,
-            cicp,
+            export_options,
             frame_duration_numer,
             frame_duration_denom,
         })
@@ -218,10 +256,7 @@ impl WasmJxlImage {
 #[wasm_bindgen]
 pub struct RenderResult {
     image: Render,
-    pixfmt: PixelFormat,
-    need_high_precision: bool,
-    icc: Vec<u8>,
-    cicp: Option<[u8; 4]>,
+    export_options: ExportOptions,
     frame_duration_numer: u32,
     frame_duration_denom: u32,
 }
@@ -253,72 +288,174 @@ impl RenderResult {
 
     #[wasm_bindgen(getter = iccProfile)]
     pub fn icc_profile(&self) -> Vec<u8> {
-        self.icc.clone()
+        self.export_options.icc.clone()
     }
 
-    #[wasm_bindgen(js_name = encodeToPng)]
-    pub fn into_png(self) -> Result<Vec<u8>, String> {
-        let image = self.image;
-        let mut stream = image.stream();
+    /// Converts the image to `ImageData`, without going through PNG.
+    ///
+    /// Returns 8-bit `ImageData` by default. If `float16` is true, returns `ImageData` with
+    /// `rgba-float16` pixel format for HDR canvases, which requires `Float16Array` support of the
+    /// browser. Samples are in the color encoding the image is rendered in; color space of the
+    /// `ImageData` is set to `display-p3` for Display P3 images, and `srgb` otherwise. Grayscale
+    /// images are expanded to RGB, and images without alpha channel are made opaque.
+    #[wasm_bindgen(js_name = toImageData)]
+    pub fn into_image_data(self, float16: Option<bool>) -> Result<ImageData, String> {
+        if matches!(
+            self.export_options.pixel_format,
+            PixelFormat::Cmyk | PixelFormat::Cmyka
+        ) {
+            return Err(String::from("unsupported colorspace"));
+        }
 
-        let mut out = Vec::new();
-        let mut encoder = png::Encoder::new(&mut out, stream.width(), stream.height());
-        let color = match self.pixfmt {
-            PixelFormat::Gray => png::ColorType::Grayscale,
-            PixelFormat::Graya => png::ColorType::GrayscaleAlpha,
-            PixelFormat::Rgb => png::ColorType::Rgb,
-            PixelFormat::Rgba => png::ColorType::Rgba,
-            PixelFormat::Cmyk | PixelFormat::Cmyka => {
-                return Err(String::from("unsupported colorspace"))
-            }
+        let mut stream = self.image.stream();
+        let width = stream.width();
+        let height = stream.height();
+        let channels = stream.channels() as usize;
+        let color_space = if let Some([12, 13, ..]) = self.export_options.cicp {
+            "display-p3"
+        } else {
+            "srgb"
+        };
+
+        let settings = js_sys::Object::new();
+        let set = |key: &str, value: &str| {
+            js_sys::Reflect::set(&settings, &JsValue::from(key), &JsValue::from(value))
+                .map(|_| ())
+                .map_err(|e| format!("{e:?}"))
         };
-        let depth = if self.need_high_precision {
-            png::BitDepth::Sixteen
+        set("colorSpace", color_space)?;
+
+        // Rows are copied to the JS array as they're read, so that the whole image is never held
+        // in the WebAssembly heap.
+        let row_len = width as usize * 4;
+        let data = if float16.unwrap_or(false) {
+            set("pixelFormat", "rgba-float16")?;
+            let data = js_sys::Uint16Array::new_with_length(row_len as u32 * height);
+            let mut fb_row = vec![0f32; width as usize * channels];
+            let mut buf_row = Vec::with_capacity(row_len);
+            for y in 0..height {
+                stream.write_to_buffer(&mut fb_row);
+                buf_row.clear();
+                expand_to_rgba(&fb_row, channels, 1.0, &mut buf_row);
+                let buf_row = buf_row
+                    .iter()
+                    .map(|&v| half::f16::from_f32(v).to_bits())
+                    .collect::<Vec<_>>();
+                let start = y * row_len as u32;
+                data.subarray(start, start + row_len as u32)
+                    .copy_from(&buf_row);
+            }
+            JsValue::from(Float16Array::new(&data.buffer()).map_err(|e| format!("{e:?}"))?)
         } else {
-            png::BitDepth::Eight
+            let data = js_sys::Uint8ClampedArray::new_with_length(row_len as u32 * height);
+            let mut fb_row = vec![0u8; width as usize * channels];
+            let mut buf_row = Vec::with_capacity(row_len);
+            for y in 0..height {
+                stream.write_to_buffer(&mut fb_row);
+                buf_row.clear();
+                expand_to_rgba(&fb_row, channels, 255, &mut buf_row);
+                let start = y * row_len as u32;
+                data.subarray(start, start + row_len as u32)
+                    .copy_from(&buf_row);
+            }
+            JsValue::from(data)
         };
-        encoder.set_color(color);
-        encoder.set_depth(depth);
-
-        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
-
-        if let Some(cicp) = self.cicp {
-            writer
-                .write_chunk(png::chunk::ChunkType([b'c', b'I', b'C', b'P']), &cicp)
-                .map_err(|e| e.to_string())?;
-        } else if !self.icc.is_empty() {
-            let compressed_icc = miniz_oxide::deflate::compress_to_vec_zlib(&self.icc, 7);
-            let mut iccp_chunk_data = vec![b'0', 0, 0];
-            iccp_chunk_data.extend(compressed_icc);
-            writer
-                .write_chunk(png::chunk::iCCP, &iccp_chunk_data)
-                .map_err(|e| e.to_string())?;
+
+        ImageData::new_with_settings(&data, width, height, &settings).map_err(|e| format!("{e:?}"))
+    }
+
+    #[wasm_bindgen(js_name = encodeToPng)]
+    pub fn into_png(self) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        self.write_png(&mut out)?;
+        Ok(out)
+    }
+
+    /// Encodes the image to PNG, passing encoded bytes to `callback` in pieces.
+    ///
+    /// `callback` is called with a `Uint8Array` for every chunk of encoded bytes, in order. Rows
+    /// are encoded as they're read from the frame, so the whole PNG is never held in memory.
+    #[wasm_bindgen(js_name = encodeToPngChunked)]
+    pub fn into_png_chunked(self, callback: &js_sys::Function) -> Result<(), String> {
+        let mut writer = CallbackWriter::new(callback);
+        self.write_png(&mut writer)
+    }
+
+    fn write_png(self, out: impl std::io::Write) -> Result<(), String> {
+        export::write_png(out, std::slice::from_ref(&self.image), &self.export_options)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// `ImageData` of the Canvas API.
+    #[derive(Clone)]
+    pub type ImageData;
+
+    #[wasm_bindgen(constructor, catch)]
+    fn new_with_settings(
+        data: &JsValue,
+        width: u32,
+        height: u32,
+        settings: &js_sys::Object,
+    ) -> Result<ImageData, JsValue>;
+
+    type Float16Array;
+
+    #[wasm_bindgen(constructor, catch)]
+    fn new(buffer: &js_sys::ArrayBuffer) -> Result<Float16Array, JsValue>;
+}
+
+/// Expands a row of grayscale or RGB samples, with or without alpha, to RGBA.
+fn expand_to_rgba<T: Copy>(row: &[T], channels: usize, opaque: T, out: &mut Vec<T>) {
+    for px in row.chunks_exact(channels) {
+        match *px {
+            [g] => out.extend_from_slice(&[g, g, g, opaque]),
+            [g, a] => out.extend_from_slice(&[g, g, g, a]),
+            [r, g, b] => out.extend_from_slice(&[r, g, b, opaque]),
+            _ => out.extend_from_slice(px),
         }
+    }
+}
 
-        if self.need_high_precision {
-            let mut fb_row = vec![0u16; (stream.width() * stream.channels()) as usize];
-            let mut buf =
-                vec![0u8; (stream.width() * stream.height() * stream.channels() * 2) as usize];
+/// Size of chunks passed to the callback of `encodeToPngChunked`.
+const CHUNK_SIZE: usize = 1 << 16;
 
-            let buf_it = buf.chunks_exact_mut((stream.width() * stream.channels() * 2) as usize);
-            for buf_row in buf_it {
-                stream.write_to_buffer(&mut fb_row);
-                for (b, s) in buf_row.chunks_exact_mut(2).zip(&fb_row) {
-                    let [b0, b1] = s.to_be_bytes();
-                    b[0] = b0;
-                    b[1] = b1;
-                }
-            }
+/// Writer which buffers encoded bytes, and passes them to a JS callback in chunks.
+struct CallbackWriter<'a> {
+    callback: &'a js_sys::Function,
+    buf: Vec<u8>,
+}
 
-            writer.write_image_data(&buf).map_err(|e| e.to_string())?;
-        } else {
-            let mut buf =
-                vec![0u8; (stream.width() * stream.height() * stream.channels()) as usize];
-            stream.write_to_buffer(&mut buf);
-            writer.write_image_data(&buf).map_err(|e| e.to_string())?;
+impl<'a> CallbackWriter<'a> {
+    fn new(callback: &'a js_sys::Function) -> Self {
+        Self {
+            callback,
+            buf: Vec::with_capacity(CHUNK_SIZE),
         }
+    }
+}
 
-        writer.finish().map_err(|e| e.to_string())?;
-        Ok(out)
+impl std::io::Write for CallbackWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = js_sys::Uint8Array::from(&self.buf[..]);
+        self.buf.clear();
+        self.callback
+            .call1(&JsValue::NULL, &chunk)
+            .map_err(|e| std::io::Error::other(format!("callback failed: {e:?}")))?;
+        Ok(())
     }
 }
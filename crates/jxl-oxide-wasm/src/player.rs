@@ -0,0 +1,198 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{ImageData, WasmJxlImage, WasmJxlImageInner};
+
+/// Animation player, which maps playback time to keyframes.
+///
+/// Keyframes are rendered to `ImageData` on first use and cached, so that later loops don't render
+/// them again. Playback stops at the last keyframe after `numLoops` loops, or never if `numLoops`
+/// is zero.
+#[wasm_bindgen]
+pub struct AnimationPlayer {
+    image: WasmJxlImage,
+    float16: bool,
+    /// Start time and duration of each keyframe within a loop, in milliseconds.
+    timeline: Vec<(f64, f64)>,
+    loop_duration: f64,
+    num_loops: u32,
+    loop_count: u32,
+    cache: Vec<Option<ImageData>>,
+}
+
+#[wasm_bindgen]
+impl AnimationPlayer {
+    /// Creates a player of the fully loaded image.
+    ///
+    /// `float16` is passed to `RenderResult.toImageData` when keyframes are rendered.
+    #[wasm_bindgen(constructor)]
+    pub fn new(image: WasmJxlImage, float16: Option<bool>) -> Result<AnimationPlayer, String> {
+        let WasmJxlImageInner::Init(inner) = &image.inner else {
+            return Err(String::from("image not initialized"));
+        };
+        if !inner.is_loading_done() {
+            return Err(String::from("image not fully loaded"));
+        }
+
+        let animation = inner.image_header().metadata.animation.as_ref();
+        let num_loops = animation.map(|anim| anim.num_loops).unwrap_or(0);
+        // Milliseconds per tick.
+        let tick_ms = animation
+            .filter(|anim| anim.tps_numerator != 0)
+            .map(|anim| anim.tps_denominator as f64 * 1000.0 / anim.tps_numerator as f64)
+            .unwrap_or(0.0);
+
+        let num_keyframes = inner.num_loaded_keyframes();
+        if num_keyframes == 0 {
+            return Err(String::from("image has no keyframes"));
+        }
+
+        let mut timeline = Vec::with_capacity(num_keyframes);
+        let mut loop_duration = 0.0;
+        for idx in 0..num_keyframes {
+            let duration = inner.frame_header(idx).unwrap().duration;
+            let duration = if duration == 0xffffffff {
+                f64::INFINITY
+            } else {
+                duration as f64 * tick_ms
+            };
+            timeline.push((loop_duration, duration));
+            loop_duration += duration;
+        }
+
+        Ok(Self {
+            image,
+            float16: float16.unwrap_or(false),
+            timeline,
+            loop_duration,
+            num_loops,
+            loop_count: 0,
+            cache: (0..num_keyframes).map(|_| None).collect(),
+        })
+    }
+
+    /// Number of loops of the animation. Zero means infinite loop.
+    #[wasm_bindgen(getter = numLoops)]
+    pub fn num_loops(&self) -> u32 {
+        self.num_loops
+    }
+
+    /// Number of loops completed as of the last `frameAt` call.
+    #[wasm_bindgen(getter = loopCount)]
+    pub fn loop_count(&self) -> u32 {
+        self.loop_count
+    }
+
+    #[wasm_bindgen(getter = numKeyframes)]
+    pub fn num_keyframes(&self) -> u32 {
+        self.timeline.len() as u32
+    }
+
+    /// Duration of a single loop in milliseconds.
+    #[wasm_bindgen(getter = loopDuration)]
+    pub fn loop_duration(&self) -> f64 {
+        self.loop_duration
+    }
+
+    /// Returns the frame to be presented at `time` milliseconds after the playback started.
+    #[wasm_bindgen(js_name = frameAt)]
+    pub fn frame_at(&mut self, time: f64) -> Result<AnimationFrame, String> {
+        let time = time.max(0.0);
+        let mut loop_index = 0u32;
+        let mut loop_time = time;
+        if self.loop_duration.is_finite() && self.loop_duration > 0.0 {
+            let loops = (time / self.loop_duration).floor();
+            loop_index = loops.min(u32::MAX as f64) as u32;
+            loop_time = time - loops * self.loop_duration;
+        }
+
+        let last_idx = self.timeline.len() - 1;
+        let finished = self.num_loops != 0 && loop_index >= self.num_loops;
+        let keyframe_index = if finished {
+            loop_index = self.num_loops - 1;
+            last_idx
+        } else {
+            let idx = self
+                .timeline
+                .partition_point(|&(start, _)| start <= loop_time);
+            idx.saturating_sub(1)
+        };
+        self.loop_count = loop_index + finished as u32;
+
+        let (start, duration) = self.timeline[keyframe_index];
+        let in_last_loop = self.num_loops != 0 && loop_index + 1 >= self.num_loops;
+        let is_last = finished
+            || !duration.is_finite()
+            || self.loop_duration == 0.0
+            || (in_last_loop && keyframe_index == last_idx);
+        let image_data = self.render(keyframe_index)?;
+        Ok(AnimationFrame {
+            image_data,
+            keyframe_index: keyframe_index as u32,
+            loop_index,
+            timestamp: loop_index as f64 * self.loop_duration + start,
+            duration,
+            is_last,
+        })
+    }
+
+    fn render(&mut self, keyframe_index: usize) -> Result<ImageData, String> {
+        if let Some(image_data) = &self.cache[keyframe_index] {
+            return Ok(image_data.clone());
+        }
+
+        let image_data = self
+            .image
+            .render(Some(keyframe_index as u32))?
+            .into_image_data(Some(self.float16))?;
+        self.cache[keyframe_index] = Some(image_data.clone());
+        Ok(image_data)
+    }
+}
+
+/// Frame returned by `AnimationPlayer.frameAt`.
+#[wasm_bindgen]
+pub struct AnimationFrame {
+    image_data: ImageData,
+    keyframe_index: u32,
+    loop_index: u32,
+    timestamp: f64,
+    duration: f64,
+    is_last: bool,
+}
+
+#[wasm_bindgen]
+impl AnimationFrame {
+    #[wasm_bindgen(getter = imageData)]
+    pub fn image_data(&self) -> ImageData {
+        self.image_data.clone()
+    }
+
+    #[wasm_bindgen(getter = keyframeIndex)]
+    pub fn keyframe_index(&self) -> u32 {
+        self.keyframe_index
+    }
+
+    /// Index of the loop the frame belongs to.
+    #[wasm_bindgen(getter = loopIndex)]
+    pub fn loop_index(&self) -> u32 {
+        self.loop_index
+    }
+
+    /// Time the frame is presented at, in milliseconds after the playback started.
+    #[wasm_bindgen(getter = timestamp)]
+    pub fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+
+    /// Duration of the frame in milliseconds. May be infinite.
+    #[wasm_bindgen(getter = duration)]
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Whether the playback ends with this frame.
+    #[wasm_bindgen(getter = last)]
+    pub fn is_last(&self) -> bool {
+        self.is_last
+    }
+}
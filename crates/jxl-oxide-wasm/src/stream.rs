@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{ReadableStream, ReadableStreamDefaultReader, ReadableStreamReadResult};
+
+use crate::WasmJxlImage;
+
+type PromiseCallback = Closure<dyn FnMut(JsValue)>;
+
+/// Decodes an image from `ReadableStream`, emitting progressive renders while bytes arrive.
+///
+/// `callback` is called with a `RenderResult` and the index of the keyframe being rendered, every
+/// time a chunk makes more of the image available to render. The first call is made as soon as the
+/// LF image of the first frame is loaded, so that viewers can show a preview without waiting for
+/// the whole image. The last call renders the fully loaded image.
+///
+/// `options` is decode options in JSON format, same as `JxlImage.withOptions`. Returns a promise
+/// which resolves to the `JxlImage` after the stream is exhausted, so that other keyframes can be
+/// rendered afterwards.
+#[wasm_bindgen(js_name = decodeStream)]
+pub fn decode_stream(
+    stream: &ReadableStream,
+    callback: js_sys::Function,
+    options: Option<String>,
+) -> Result<js_sys::Promise, String> {
+    let image = match options {
+        Some(options) => WasmJxlImage::with_options(&options)?,
+        None => WasmJxlImage::new(),
+    };
+    let reader = ReadableStreamDefaultReader::new(stream).map_err(|e| format!("{e:?}"))?;
+
+    // The executor is called synchronously.
+    let mut settle = None;
+    let promise = js_sys::Promise::new(&mut |resolve, reject| settle = Some((resolve, reject)));
+    let (resolve, reject) = settle.unwrap();
+
+    let state = Rc::new(RefCell::new(StreamDecoder {
+        image: Some(image),
+        reader,
+        callback,
+        pending: Vec::new(),
+        progress: None,
+        callbacks: None,
+        resolve,
+        reject,
+    }));
+
+    let on_read = {
+        let state = Rc::clone(&state);
+        Closure::new(move |result: JsValue| {
+            let result = result.unchecked_into::<ReadableStreamReadResult>();
+            let done = result.get_done().unwrap_or(false);
+            let chunk = (!done).then(|| js_sys::Uint8Array::new(&result.get_value()).to_vec());
+            StreamDecoder::on_chunk(&state, chunk);
+        })
+    };
+    let on_error = {
+        let state = Rc::clone(&state);
+        Closure::new(move |err: JsValue| {
+            StreamDecoder::finish(&state, Err(err));
+        })
+    };
+    state.borrow_mut().callbacks = Some((on_read, on_error));
+
+    StreamDecoder::pump(&state);
+    Ok(promise)
+}
+
+/// State of a stream being decoded by `decodeStream`.
+struct StreamDecoder {
+    image: Option<WasmJxlImage>,
+    reader: ReadableStreamDefaultReader,
+    callback: js_sys::Function,
+    /// Bytes not consumed by the decoder yet.
+    pending: Vec<u8>,
+    /// Loading progress at the time of the last progressive render.
+    progress: Option<(usize, Option<usize>)>,
+    /// Callbacks of promises returned by the reader. Dropped after decoding is done, which breaks
+    /// the reference cycle.
+    callbacks: Option<(PromiseCallback, PromiseCallback)>,
+    resolve: js_sys::Function,
+    reject: js_sys::Function,
+}
+
+impl StreamDecoder {
+    fn pump(state: &Rc<RefCell<Self>>) {
+        let decoder = state.borrow();
+        let Some((on_read, on_error)) = &decoder.callbacks else {
+            return;
+        };
+        let _ = decoder.reader.read().then2(on_read, on_error);
+    }
+
+    fn on_chunk(state: &Rc<RefCell<Self>>, chunk: Option<Vec<u8>>) {
+        let done = chunk.is_none();
+        let result = state.borrow_mut().process_chunk(chunk);
+        match result {
+            Err(e) => Self::finish(state, Err(JsValue::from(e))),
+            Ok(()) if done => Self::finish(state, Ok(())),
+            Ok(()) => Self::pump(state),
+        }
+    }
+
+    fn process_chunk(&mut self, chunk: Option<Vec<u8>>) -> Result<(), String> {
+        let done = chunk.is_none();
+        let image = self.image.as_mut().unwrap();
+        if let Some(chunk) = chunk {
+            self.pending.extend_from_slice(&chunk);
+        }
+        while !self.pending.is_empty() {
+            let consumed = image.feed_bytes(&self.pending)?;
+            if consumed == 0 {
+                break;
+            }
+            self.pending.drain(..consumed);
+        }
+
+        if !image.try_init()? {
+            return if done {
+                Err(String::from("unexpected end of stream"))
+            } else {
+                Ok(())
+            };
+        }
+
+        let progress = image.loading_progress();
+        if self.progress == progress {
+            return Ok(());
+        }
+
+        let mut keyframe_idx = image.num_loaded_keyframes();
+        if image.is_loading_done() {
+            keyframe_idx = keyframe_idx.saturating_sub(1);
+        }
+        let render = match image.render(Some(keyframe_idx)) {
+            Ok(render) => render,
+            // Nothing to render yet; try again with the next chunk.
+            Err(_) if !done => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        self.progress = progress;
+
+        self.callback
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from(render),
+                &JsValue::from(keyframe_idx),
+            )
+            .map(|_| ())
+            .map_err(|e| format!("callback failed: {e:?}"))
+    }
+
+    fn finish(state: &Rc<RefCell<Self>>, result: Result<(), JsValue>) {
+        let mut decoder = state.borrow_mut();
+        // Closures may be dropped while they're running; wasm-bindgen defers freeing them.
+        let callbacks = decoder.callbacks.take();
+        let _ = match result {
+            Ok(()) => {
+                let image = decoder.image.take().unwrap();
+                decoder.resolve.call1(&JsValue::NULL, &JsValue::from(image))
+            }
+            Err(e) => {
+                let _ = decoder.reader.cancel();
+                decoder.reject.call1(&JsValue::NULL, &e)
+            }
+        };
+        drop(decoder);
+        drop(callbacks);
+    }
+}
@@ -0,0 +1,75 @@
+use jxl_frame::data::{LfGlobal, NoiseParameters, PatchRef};
+use jxl_render::{IndexedFrame, Spline};
+
+use crate::Result;
+
+/// Features of a frame, namely splines, patches and noise parameters, returned by
+/// [`JxlImage::frame_features`][crate::JxlImage::frame_features].
+#[derive(Debug)]
+pub struct FrameFeatures {
+    splines: Vec<Spline>,
+    patches: Vec<PatchRef>,
+    noise: Option<NoiseParameters>,
+}
+
+impl FrameFeatures {
+    pub(crate) fn new(frame: &IndexedFrame) -> Option<Result<Self>> {
+        let lf_global = match frame.try_parse_lf_global::<i32>()? {
+            Ok(lf_global) => lf_global,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let LfGlobal {
+            patches,
+            splines,
+            noise,
+            vardct,
+            ..
+        } = lf_global;
+
+        let base_correlations_xb = vardct.as_ref().map(|vardct| {
+            (
+                vardct.lf_chan_corr.base_correlation_x,
+                vardct.lf_chan_corr.base_correlation_b,
+            )
+        });
+        let splines = splines
+            .map(|splines| {
+                splines
+                    .quant_splines
+                    .iter()
+                    .map(|quant_spline| {
+                        Spline::dequant(quant_spline, splines.quant_adjust, base_correlations_xb)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let patches = patches.map(|patches| patches.patches).unwrap_or_default();
+
+        Some(Ok(Self {
+            splines,
+            patches,
+            noise,
+        }))
+    }
+
+    /// Returns dequantized splines of the frame.
+    pub fn splines(&self) -> &[Spline] {
+        &self.splines
+    }
+
+    /// Returns patches of the frame.
+    ///
+    /// Each patch copies a rectangle from the reference frame in the given slot, to one or more
+    /// target positions of the frame.
+    pub fn patches(&self) -> &[PatchRef] {
+        &self.patches
+    }
+
+    /// Returns noise parameters of the frame, or `None` if the frame doesn't synthesize noise.
+    ///
+    /// Noise strength is interpolated from the lookup table, indexed by the intensity of each
+    /// sample.
+    pub fn noise(&self) -> Option<&NoiseParameters> {
+        self.noise.as_ref()
+    }
+}
@@ -1,15 +1,20 @@
+use std::borrow::Cow;
 use std::io::Write;
 
 use brotli_decompressor::DecompressorWriter;
 use jxl_bitstream::container::box_header::ContainerBoxType;
 use jxl_bitstream::ParseEvent;
 
-use crate::Result;
+use crate::{DecodeStage, Result};
 
 mod exif;
+mod frame_index;
+mod gain_map;
 mod jbrd;
 
 pub use exif::*;
+pub use frame_index::*;
+pub use gain_map::*;
 pub use jbrd::*;
 
 #[derive(Debug, Default)]
@@ -228,11 +233,13 @@ impl<T, E> AuxBoxData<std::result::Result<T, E>> {
     }
 }
 
-/// Auxiliary box list of a JPEG XL container, which may contain Exif and/or XMP metadata.
+/// Auxiliary box list of a JPEG XL container, which may contain Exif and/or XMP metadata, and gain
+/// map.
 #[derive(Debug)]
 pub struct AuxBoxList {
     boxes: Vec<(ContainerBoxType, AuxBoxReader)>,
     jbrd: Jbrd,
+    frame_index: Option<FrameIndexBox>,
     current_box_ty: Option<ContainerBoxType>,
     current_box: AuxBoxReader,
     last_box: bool,
@@ -243,6 +250,7 @@ impl AuxBoxList {
         Self {
             boxes: Vec::new(),
             jbrd: Jbrd::new(),
+            frame_index: None,
             current_box_ty: None,
             current_box: AuxBoxReader::new(),
             last_box: false,
@@ -253,6 +261,7 @@ impl AuxBoxList {
         match event {
             ParseEvent::BitstreamKind(_) => {}
             ParseEvent::Codestream(_) => {}
+            ParseEvent::BoxStart(_) | ParseEvent::BoxEnd(_) => {}
             ParseEvent::NoMoreAuxBox => {
                 self.current_box_ty = None;
                 self.last_box = true;
@@ -296,6 +305,9 @@ impl AuxBoxList {
             Some(ty) => {
                 self.current_box.finalize()?;
                 let finished_box = std::mem::replace(&mut self.current_box, AuxBoxReader::new());
+                if ty == ContainerBoxType::FRAME_INDEX {
+                    self.parse_frame_index(finished_box.data());
+                }
                 self.boxes.push((ty, finished_box));
             }
             None => {
@@ -307,6 +319,38 @@ impl AuxBoxList {
         Ok(())
     }
 
+    /// Creates a list with completed boxes, restored from a snapshot.
+    pub(super) fn restore(boxes: &[([u8; 4], Option<Vec<u8>>)]) -> Self {
+        let mut list = Self::new();
+        for (ty, data) in boxes {
+            let data = match data {
+                Some(buf) => DataKind::Raw(buf.clone()),
+                None => DataKind::NoData,
+            };
+            let reader = AuxBoxReader { data, done: true };
+            if ContainerBoxType(*ty) == ContainerBoxType::FRAME_INDEX {
+                list.parse_frame_index(reader.data());
+            }
+            list.boxes.push((ContainerBoxType(*ty), reader));
+        }
+        list
+    }
+
+    /// Parses the first frame index box. Malformed index is ignored, as it's not needed to decode
+    /// the image.
+    fn parse_frame_index(&mut self, data: AuxBoxData<&[u8]>) {
+        if self.frame_index.is_some() {
+            return;
+        }
+        let AuxBoxData::Data(data) = data else {
+            return;
+        };
+        match FrameIndexBox::parse(data) {
+            Ok(frame_index) => self.frame_index = Some(frame_index),
+            Err(e) => tracing::warn!(%e, "Ignoring malformed frame index box"),
+        }
+    }
+
     pub(super) fn eof(&mut self) -> Result<()> {
         self.finalize()?;
         self.last_box = true;
@@ -340,14 +384,77 @@ impl AuxBoxList {
         }
     }
 
+    /// Returns an iterator over auxiliary boxes read so far, with their box types.
+    ///
+    /// JPEG bitstream reconstruction data is not included.
+    pub fn iter(&self) -> impl Iterator<Item = ([u8; 4], AuxBoxData<&[u8]>)> + '_ {
+        self.boxes.iter().map(|(ty, b)| (ty.0, b.data()))
+    }
+
     /// Returns the first Exif metadata, if any.
     pub fn first_exif(&self) -> Result<AuxBoxData<RawExif>> {
         let exif = self.first_of_type(ContainerBoxType::EXIF);
-        exif.map(RawExif::new).transpose()
+        exif.map(RawExif::new)
+            .transpose()
+            .map_err(|e| e.at_stage(DecodeStage::AuxBox))
     }
 
     /// Returns the first XML metadata, if any.
     pub fn first_xml(&self) -> AuxBoxData<&[u8]> {
         self.first_of_type(ContainerBoxType::XML)
     }
+
+    /// Returns the payloads of every XML box concatenated, if any.
+    ///
+    /// XMP packet may be split into multiple XML boxes, each of which can be Brotli-compressed
+    /// individually. This method concatenates the decompressed payloads in container order, and
+    /// returns [`AuxBoxData::Decoding`] until every box is read.
+    ///
+    /// JPEG bitstream reconstruction uses only the first XML box; see [`Self::first_xml`].
+    pub fn joined_xml(&self) -> AuxBoxData<Cow<'_, [u8]>> {
+        if !self.last_box || self.current_box_ty == Some(ContainerBoxType::XML) {
+            return AuxBoxData::Decoding;
+        }
+
+        let mut payloads = self
+            .boxes
+            .iter()
+            .filter(|&&(ty, _)| ty == ContainerBoxType::XML)
+            .map(|(_, b)| b.data().unwrap_or(&[]));
+        let Some(first) = payloads.next() else {
+            return AuxBoxData::NotFound;
+        };
+        let Some(second) = payloads.next() else {
+            return AuxBoxData::Data(Cow::Borrowed(first));
+        };
+
+        let mut buf = Vec::from(first);
+        buf.extend_from_slice(second);
+        for payload in payloads {
+            buf.extend_from_slice(payload);
+        }
+        AuxBoxData::Data(Cow::Owned(buf))
+    }
+
+    /// Returns the frame index, if any.
+    ///
+    /// Malformed frame index box is treated as if it doesn't exist.
+    pub fn frame_index(&self) -> AuxBoxData<&FrameIndexBox> {
+        if let Some(frame_index) = &self.frame_index {
+            AuxBoxData::Data(frame_index)
+        } else if self.last_box && self.current_box_ty != Some(ContainerBoxType::FRAME_INDEX) {
+            AuxBoxData::NotFound
+        } else {
+            AuxBoxData::Decoding
+        }
+    }
+
+    /// Returns the first gain map bundle, if any.
+    pub fn first_gain_map(&self) -> Result<AuxBoxData<RawGainMap<'_>>> {
+        let gain_map = self.first_of_type(ContainerBoxType::GAIN_MAP);
+        gain_map
+            .map(RawGainMap::new)
+            .transpose()
+            .map_err(|e| e.at_stage(DecodeStage::AuxBox))
+    }
 }
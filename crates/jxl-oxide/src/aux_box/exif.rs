@@ -53,3 +53,256 @@ impl<'image> RawExif<'image> {
         self.payload
     }
 }
+
+/// Exif tag of the orientation field.
+const TAG_ORIENTATION: u16 = 0x0112;
+/// Exif tag of the camera make field.
+const TAG_MAKE: u16 = 0x010f;
+/// Exif tag of the camera model field.
+const TAG_MODEL: u16 = 0x0110;
+/// Exif tag of the modification date and time field.
+const TAG_DATE_TIME: u16 = 0x0132;
+/// Exif tag of the pointer to Exif IFD.
+const TAG_EXIF_IFD_POINTER: u16 = 0x8769;
+/// Exif tag of the original date and time field, stored in Exif IFD.
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+impl<'image> RawExif<'image> {
+    fn tiff(&self) -> &'image [u8] {
+        &self.payload[self.tiff_header_offset as usize..]
+    }
+
+    fn is_big_endian(&self) -> Result<bool> {
+        match self.tiff().get(..4) {
+            Some(b"II*\0") => Ok(false),
+            Some(b"MM\0*") => Ok(true),
+            _ => {
+                tracing::error!("Invalid TIFF header in Exif box");
+                Err(
+                    jxl_bitstream::Error::ValidationFailed("invalid TIFF header in Exif box")
+                        .into(),
+                )
+            }
+        }
+    }
+
+    fn ifd_at(&self, offset: u32) -> Result<ExifFields<'image>> {
+        let big_endian = self.is_big_endian()?;
+        let tiff = self.tiff();
+        let num_fields = tiff
+            .get(offset as usize..)
+            .and_then(|ifd| read_u16(ifd, big_endian));
+        let Some(num_fields) = num_fields else {
+            tracing::error!(offset, "IFD offset of Exif is out of bounds");
+            return Err(jxl_bitstream::Error::ValidationFailed(
+                "IFD offset of Exif is out of bounds",
+            )
+            .into());
+        };
+
+        Ok(ExifFields {
+            tiff,
+            big_endian,
+            offset: offset as usize + 2,
+            remaining: num_fields,
+        })
+    }
+
+    /// Returns an iterator over the fields of the first IFD (IFD0), which describes the primary
+    /// image.
+    ///
+    /// # Errors
+    /// Returns an error if the TIFF header is invalid.
+    pub fn ifd0(&self) -> Result<ExifFields<'image>> {
+        let big_endian = self.is_big_endian()?;
+        let offset = read_u32(&self.tiff()[4..], big_endian).unwrap_or(0);
+        self.ifd_at(offset)
+    }
+
+    /// Returns an iterator over the fields of Exif IFD, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the TIFF header is invalid.
+    pub fn exif_ifd(&self) -> Result<Option<ExifFields<'image>>> {
+        let Some(pointer) = self.ifd0()?.find(|f| f.tag() == TAG_EXIF_IFD_POINTER) else {
+            return Ok(None);
+        };
+        let Some(offset) = pointer.as_u32(0) else {
+            return Ok(None);
+        };
+        self.ifd_at(offset).map(Some)
+    }
+
+    fn find_in_ifd0(&self, tag: u16) -> Option<ExifField<'image>> {
+        self.ifd0().ok()?.find(|f| f.tag() == tag)
+    }
+
+    /// Returns the orientation stored in Exif, if any.
+    ///
+    /// The value has the same meaning as the orientation field of the image header, ranging from
+    /// 1 to 8.
+    pub fn orientation(&self) -> Option<u32> {
+        let orientation = self.find_in_ifd0(TAG_ORIENTATION)?.as_u32(0)?;
+        (1..=8).contains(&orientation).then_some(orientation)
+    }
+
+    /// Returns the manufacturer of the camera, if any.
+    pub fn make(&self) -> Option<&'image str> {
+        self.find_in_ifd0(TAG_MAKE)?.as_ascii()
+    }
+
+    /// Returns the model of the camera, if any.
+    pub fn model(&self) -> Option<&'image str> {
+        self.find_in_ifd0(TAG_MODEL)?.as_ascii()
+    }
+
+    /// Returns the date and time of image modification, in `YYYY:MM:DD HH:MM:SS` format, if any.
+    pub fn date_time(&self) -> Option<&'image str> {
+        self.find_in_ifd0(TAG_DATE_TIME)?.as_ascii()
+    }
+
+    /// Returns the date and time when the original image was taken, in `YYYY:MM:DD HH:MM:SS`
+    /// format, if any.
+    pub fn date_time_original(&self) -> Option<&'image str> {
+        self.exif_ifd()
+            .ok()??
+            .find(|f| f.tag() == TAG_DATE_TIME_ORIGINAL)?
+            .as_ascii()
+    }
+}
+
+fn read_u16(data: &[u8], big_endian: bool) -> Option<u16> {
+    let bytes = [*data.first()?, *data.get(1)?];
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(data: &[u8], big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(..4)?.try_into().unwrap();
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+/// Iterator over fields of an Exif IFD.
+///
+/// Fields with out-of-bounds values are skipped.
+#[derive(Debug, Clone)]
+pub struct ExifFields<'image> {
+    tiff: &'image [u8],
+    big_endian: bool,
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'image> Iterator for ExifFields<'image> {
+    type Item = ExifField<'image>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            self.remaining -= 1;
+            let entry = self.tiff.get(self.offset..)?.get(..12)?;
+            self.offset += 12;
+
+            let big_endian = self.big_endian;
+            let tag = read_u16(entry, big_endian)?;
+            let field_type = read_u16(&entry[2..], big_endian)?;
+            let count = read_u32(&entry[4..], big_endian)?;
+            let Some(len) = type_size(field_type).and_then(|size| size.checked_mul(count as usize))
+            else {
+                continue;
+            };
+
+            let data = if len <= 4 {
+                Some(&entry[8..][..len])
+            } else {
+                let offset = read_u32(&entry[8..], big_endian)? as usize;
+                self.tiff.get(offset..).and_then(|data| data.get(..len))
+            };
+            if let Some(data) = data {
+                return Some(ExifField {
+                    tag,
+                    field_type,
+                    count,
+                    data,
+                    big_endian,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Returns the size of a single value of the given TIFF field type.
+fn type_size(field_type: u16) -> Option<usize> {
+    Some(match field_type {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => return None,
+    })
+}
+
+/// A field of an Exif IFD.
+#[derive(Debug, Clone)]
+pub struct ExifField<'image> {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    data: &'image [u8],
+    big_endian: bool,
+}
+
+impl<'image> ExifField<'image> {
+    /// Returns the tag of the field.
+    pub fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    /// Returns the TIFF field type, such as 2 for ASCII and 3 for SHORT.
+    pub fn field_type(&self) -> u16 {
+        self.field_type
+    }
+
+    /// Returns the number of values in the field.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the raw value of the field, in the byte order of the Exif data.
+    pub fn raw_value(&self) -> &'image [u8] {
+        self.data
+    }
+
+    /// Returns the value at the index as an unsigned integer, if the field is of type BYTE,
+    /// SHORT or LONG.
+    pub fn as_u32(&self, index: usize) -> Option<u32> {
+        match self.field_type {
+            1 => self.data.get(index).map(|&v| v as u32),
+            3 => read_u16(self.data.get(index * 2..)?, self.big_endian).map(|v| v as u32),
+            4 => read_u32(self.data.get(index * 4..)?, self.big_endian),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string, if the field is of type ASCII.
+    ///
+    /// Trailing NUL characters are trimmed.
+    pub fn as_ascii(&self) -> Option<&'image str> {
+        if self.field_type != 2 {
+            return None;
+        }
+        let len = self
+            .data
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.data.len());
+        std::str::from_utf8(&self.data[..len]).ok()
+    }
+}
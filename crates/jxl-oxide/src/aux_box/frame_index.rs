@@ -0,0 +1,183 @@
+use crate::{DecodeStage, Error, Result};
+
+/// Frame index stored in `jxli` box.
+///
+/// The index lists keyframes which can be decoded without preceding frames, with their offsets
+/// within the codestream and timestamps. Players can use it to seek in animations without
+/// loading every frame before the target; see [`JxlImage::keyframe_seek_point`].
+///
+/// [`JxlImage::keyframe_seek_point`]: crate::JxlImage::keyframe_seek_point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameIndexBox {
+    tick_numerator: u32,
+    tick_denominator: u32,
+    entries: Vec<FrameIndexEntry>,
+}
+
+/// Indexed keyframe in [`FrameIndexBox`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameIndexEntry {
+    keyframe_index: usize,
+    offset: u64,
+    start_ticks: u64,
+    duration_ticks: u64,
+    num_keyframes: u64,
+}
+
+impl FrameIndexEntry {
+    /// Returns the index of the indexed keyframe.
+    #[inline]
+    pub fn keyframe_index(&self) -> usize {
+        self.keyframe_index
+    }
+
+    /// Returns the offset of the indexed keyframe within codestream, in bytes.
+    ///
+    /// The offset can be passed to [`JxlImage::load_frame_at`][crate::JxlImage::load_frame_at].
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the time the indexed keyframe starts at, in ticks.
+    #[inline]
+    pub fn start_ticks(&self) -> u64 {
+        self.start_ticks
+    }
+
+    /// Returns the duration from the indexed keyframe to the next one, in ticks.
+    ///
+    /// For the last entry, this is the duration until the end of the animation.
+    #[inline]
+    pub fn duration_ticks(&self) -> u64 {
+        self.duration_ticks
+    }
+
+    /// Returns the number of keyframes from the indexed keyframe to the next one.
+    #[inline]
+    pub fn num_keyframes(&self) -> u64 {
+        self.num_keyframes
+    }
+}
+
+fn invalid(msg: &'static str) -> Error {
+    tracing::error!("{msg}");
+    Error::decode(
+        DecodeStage::AuxBox,
+        jxl_bitstream::Error::ValidationFailed(msg),
+    )
+}
+
+fn read_varint(data: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let Some((&byte, rest)) = data.split_first() else {
+            return Err(invalid("frame index box is too short"));
+        };
+        *data = rest;
+
+        let bits = (byte & 0x7f) as u64;
+        if bits << shift >> shift != bits {
+            break;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint in frame index box is too large"))
+}
+
+impl FrameIndexBox {
+    pub(crate) fn parse(box_data: &[u8]) -> Result<Self> {
+        let mut data = box_data;
+        let num_frames = read_varint(&mut data)?;
+        if data.len() < 8 {
+            return Err(invalid("frame index box is too short"));
+        }
+        let (tick, mut data) = data.split_at(8);
+        let tick_numerator = u32::from_be_bytes([tick[0], tick[1], tick[2], tick[3]]);
+        let tick_denominator = u32::from_be_bytes([tick[4], tick[5], tick[6], tick[7]]);
+        if tick_numerator == 0 || tick_denominator == 0 {
+            return Err(invalid("tick duration of frame index box is zero"));
+        }
+
+        // Each entry takes at least three bytes.
+        if num_frames > (data.len() / 3) as u64 {
+            return Err(invalid("frame index box is too short"));
+        }
+
+        let mut entries = Vec::with_capacity(num_frames as usize);
+        let mut keyframe_index = 0u64;
+        let mut offset = 0u64;
+        let mut start_ticks = 0u64;
+        for _ in 0..num_frames {
+            let offset_delta = read_varint(&mut data)?;
+            let duration_ticks = read_varint(&mut data)?;
+            let num_keyframes = read_varint(&mut data)?;
+
+            offset = offset
+                .checked_add(offset_delta)
+                .ok_or_else(|| invalid("frame offset overflowed"))?;
+            entries.push(FrameIndexEntry {
+                keyframe_index: usize::try_from(keyframe_index)
+                    .map_err(|_| invalid("keyframe index overflowed"))?,
+                offset,
+                start_ticks,
+                duration_ticks,
+                num_keyframes,
+            });
+            keyframe_index = keyframe_index
+                .checked_add(num_keyframes)
+                .ok_or_else(|| invalid("keyframe index overflowed"))?;
+            start_ticks = start_ticks
+                .checked_add(duration_ticks)
+                .ok_or_else(|| invalid("timestamp overflowed"))?;
+        }
+
+        Ok(Self {
+            tick_numerator,
+            tick_denominator,
+            entries,
+        })
+    }
+
+    /// Returns the numerator of the duration of a tick, in seconds.
+    #[inline]
+    pub fn tick_numerator(&self) -> u32 {
+        self.tick_numerator
+    }
+
+    /// Returns the denominator of the duration of a tick, in seconds.
+    #[inline]
+    pub fn tick_denominator(&self) -> u32 {
+        self.tick_denominator
+    }
+
+    /// Returns indexed keyframes, in the order of keyframe index.
+    #[inline]
+    pub fn entries(&self) -> &[FrameIndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the last indexed keyframe at or before the given keyframe.
+    pub fn find_by_keyframe(&self, keyframe_index: usize) -> Option<&FrameIndexEntry> {
+        let idx = self
+            .entries
+            .partition_point(|entry| entry.keyframe_index <= keyframe_index);
+        idx.checked_sub(1).map(|idx| &self.entries[idx])
+    }
+
+    /// Returns the last indexed keyframe starting at or before the given time, in ticks.
+    pub fn find_by_ticks(&self, ticks: u64) -> Option<&FrameIndexEntry> {
+        let idx = self
+            .entries
+            .partition_point(|entry| entry.start_ticks <= ticks);
+        idx.checked_sub(1).map(|idx| &self.entries[idx])
+    }
+
+    /// Returns whether a keyframe starts at the given offset within codestream.
+    pub fn contains_offset(&self, offset: u64) -> bool {
+        self.entries.iter().any(|entry| entry.offset == offset)
+    }
+}
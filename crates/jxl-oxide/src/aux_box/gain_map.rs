@@ -0,0 +1,152 @@
+use jxl_bitstream::Bitstream;
+use jxl_color::header::ColourEncoding;
+use jxl_oxide_common::Bundle;
+
+use crate::{JxlImage, Render, Result};
+
+/// Raw gain map bundle stored in `jhgm` box.
+///
+/// The bundle consists of ISO 21496-1 gain map metadata, optional color encoding and ICC profile
+/// of the alternate rendition, and a gain map image encoded as a bare JPEG XL codestream.
+pub struct RawGainMap<'image> {
+    version: u8,
+    metadata: &'image [u8],
+    color_encoding: Option<ColourEncoding>,
+    alt_icc: Option<Vec<u8>>,
+    codestream: &'image [u8],
+}
+
+impl std::fmt::Debug for RawGainMap<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawGainMap")
+            .field("version", &self.version)
+            .field("color_encoding", &self.color_encoding)
+            .finish_non_exhaustive()
+    }
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8])> {
+    if data.len() < len {
+        tracing::error!(
+            len = data.len(),
+            expected = len,
+            "Gain map box is too short"
+        );
+        return Err(jxl_bitstream::Error::ValidationFailed("gain map box is too short").into());
+    }
+    Ok(data.split_at(len))
+}
+
+impl<'image> RawGainMap<'image> {
+    pub(crate) fn new(box_data: &'image [u8]) -> Result<Self> {
+        let (version, data) = split(box_data, 1)?;
+        let version = version[0];
+        if version != 0 {
+            tracing::error!(version, "Unsupported gain map bundle version");
+            return Err(jxl_bitstream::Error::ValidationFailed(
+                "unsupported gain map bundle version",
+            )
+            .into());
+        }
+
+        let (metadata_size, data) = split(data, 2)?;
+        let metadata_size = u16::from_be_bytes([metadata_size[0], metadata_size[1]]);
+        let (metadata, data) = split(data, metadata_size as usize)?;
+
+        let (color_encoding_size, data) = split(data, 1)?;
+        let (color_encoding, data) = split(data, color_encoding_size[0] as usize)?;
+        let color_encoding = if color_encoding.is_empty() {
+            None
+        } else {
+            let mut bitstream = Bitstream::new(color_encoding);
+            Some(ColourEncoding::parse(&mut bitstream, ())?)
+        };
+
+        let (alt_icc_size, data) = split(data, 4)?;
+        let alt_icc_size = u32::from_be_bytes([
+            alt_icc_size[0],
+            alt_icc_size[1],
+            alt_icc_size[2],
+            alt_icc_size[3],
+        ]);
+        let (alt_icc, codestream) = split(data, alt_icc_size as usize)?;
+        let alt_icc = if alt_icc.is_empty() {
+            None
+        } else {
+            let mut bitstream = Bitstream::new(alt_icc);
+            let icc = jxl_color::icc::read_icc(&mut bitstream)?;
+            Some(jxl_color::icc::decode_icc(&icc)?)
+        };
+
+        Ok(Self {
+            version,
+            metadata,
+            color_encoding,
+            alt_icc,
+            codestream,
+        })
+    }
+}
+
+impl<'image> RawGainMap<'image> {
+    /// Returns the version of the gain map bundle.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the gain map metadata in ISO 21496-1 binary format.
+    pub fn metadata(&self) -> &'image [u8] {
+        self.metadata
+    }
+
+    /// Returns the color encoding of the alternate rendition, if any.
+    pub fn color_encoding(&self) -> Option<&ColourEncoding> {
+        self.color_encoding.as_ref()
+    }
+
+    /// Returns the ICC profile of the alternate rendition, if any.
+    pub fn alt_icc(&self) -> Option<&[u8]> {
+        self.alt_icc.as_deref()
+    }
+
+    /// Returns the gain map image encoded as a bare JPEG XL codestream.
+    pub fn codestream(&self) -> &'image [u8] {
+        self.codestream
+    }
+}
+
+/// Gain map of an image, with decoded gain map image.
+#[derive(Debug)]
+pub struct GainMap<'image> {
+    raw: RawGainMap<'image>,
+    image: JxlImage,
+}
+
+impl<'image> GainMap<'image> {
+    pub(crate) fn new(raw: RawGainMap<'image>, parent: &JxlImage) -> Result<Self> {
+        let image = JxlImage::builder()
+            .pool(parent.pool().clone())
+            .read(raw.codestream)?;
+        Ok(Self { raw, image })
+    }
+
+    /// Returns the raw gain map bundle, including the metadata.
+    pub fn raw(&self) -> &RawGainMap<'image> {
+        &self.raw
+    }
+
+    /// Returns the gain map metadata in ISO 21496-1 binary format.
+    pub fn metadata(&self) -> &'image [u8] {
+        self.raw.metadata
+    }
+
+    /// Returns the decoded gain map image.
+    pub fn image(&self) -> &JxlImage {
+        &self.image
+    }
+
+    /// Renders the gain map image.
+    pub fn render(&self) -> Result<Render> {
+        self.image.render_frame(0)
+    }
+}
@@ -0,0 +1,79 @@
+use crate::Render;
+
+/// Differences between two renders of the same keyframe, returned by [`Render::diff_stats`].
+#[derive(Debug, Clone)]
+pub struct RenderDiffStats {
+    channels: Vec<ChannelDiff>,
+}
+
+/// Differences of a channel between two renders.
+///
+/// Samples are compared in their nominal range of `[0, 1]`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChannelDiff {
+    /// Maximum absolute difference of samples.
+    pub max: f32,
+    /// Mean absolute difference of samples.
+    pub mean: f32,
+}
+
+impl RenderDiffStats {
+    /// Returns differences of each channel, with color channels first, followed by extra
+    /// channels.
+    pub fn channels(&self) -> &[ChannelDiff] {
+        &self.channels
+    }
+
+    /// Returns the maximum absolute difference among all channels.
+    pub fn max(&self) -> f32 {
+        self.channels
+            .iter()
+            .fold(0f32, |acc, diff| acc.max(diff.max))
+    }
+
+    /// Returns the largest mean absolute difference among all channels.
+    pub fn max_mean(&self) -> f32 {
+        self.channels
+            .iter()
+            .fold(0f32, |acc, diff| acc.max(diff.mean))
+    }
+}
+
+impl Render {
+    /// Computes differences from another render of the same keyframe, such as a previous
+    /// progressive pass.
+    ///
+    /// Applications loading images progressively may use this to decide whether another pass is
+    /// worth fetching, by checking whether [`max`][RenderDiffStats::max] or
+    /// [`max_mean`][RenderDiffStats::max_mean] fell below a threshold. Returns `None` if renders
+    /// differ in dimensions or the number of channels.
+    pub fn diff_stats(&self, other: &Render) -> Option<RenderDiffStats> {
+        let planes = self.image_planar();
+        let other_planes = other.image_planar();
+        if planes.len() != other_planes.len() {
+            return None;
+        }
+
+        let mut channels = Vec::with_capacity(planes.len());
+        for (a, b) in planes.iter().zip(&other_planes) {
+            if a.width() != b.width() || a.height() != b.height() {
+                return None;
+            }
+
+            let mut max = 0f32;
+            let mut sum = 0f64;
+            for (&a, &b) in a.buf().iter().zip(b.buf()) {
+                let diff = (a - b).abs();
+                max = max.max(diff);
+                sum += diff as f64;
+            }
+            let count = a.buf().len().max(1);
+            channels.push(ChannelDiff {
+                max,
+                mean: (sum / count as f64) as f32,
+            });
+        }
+
+        Some(RenderDiffStats { channels })
+    }
+}
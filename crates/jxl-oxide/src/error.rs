@@ -0,0 +1,356 @@
+use jxl_frame::data::TocGroupKind;
+use jxl_render::UnsupportedFeature;
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Error returned by the decoder.
+///
+/// Errors are grouped into categories which callers can match on, such as malformed input,
+/// unsupported features and exhausted memory limit. The underlying error of each category is
+/// available through [`std::error::Error::source`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// I/O error occurred while reading the input or writing the output.
+    Io(std::io::Error),
+    /// The image is malformed.
+    Decode {
+        /// Stage of decoding the error occurred in.
+        stage: DecodeStage,
+        /// Index of the keyframe being rendered, if known.
+        frame: Option<usize>,
+        /// Group of the frame the error occurred in, if known.
+        ///
+        /// For incomplete frames, this is the group which is truncated.
+        group: Option<TocGroupKind>,
+        /// Offset within codestream where the failed structure starts, in bytes, if known.
+        offset: Option<usize>,
+        /// The underlying error.
+        source: BoxedError,
+    },
+    /// Color management failed.
+    ///
+    /// Color management may fail because the conversion is not supported, in which case
+    /// [`unsupported_feature`][Self::unsupported_feature] returns the feature.
+    Color(jxl_color::Error),
+    /// The image uses a feature the decoder can't handle, as opposed to being malformed.
+    Unsupported {
+        /// The unsupported feature.
+        feature: UnsupportedFeature,
+        /// Human readable hint about the error.
+        hint: &'static str,
+    },
+    /// Allocation failed, or the memory limit is exceeded.
+    MemoryLimit(MemoryError),
+    /// Rendering is cancelled with [`CancellationToken`][crate::CancellationToken].
+    Cancelled,
+    /// Other errors, such as requesting operations not possible with the current state.
+    Other(BoxedError),
+}
+
+/// Cause of [`Error::MemoryLimit`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MemoryError {
+    /// Allocation failed, or exceeded the limit of
+    /// [`alloc_tracker`][crate::JxlImageBuilder::alloc_tracker].
+    OutOfMemory {
+        /// Size of the failed allocation, in bytes.
+        bytes: usize,
+    },
+    /// Reservation exceeded the limit set by
+    /// [`memory_limit_bytes`][crate::JxlImageBuilder::memory_limit_bytes].
+    LimitExceeded {
+        /// Size of the failed reservation, in bytes.
+        bytes: usize,
+    },
+}
+
+impl MemoryError {
+    /// Returns the size of the failed allocation or reservation, in bytes.
+    pub fn bytes(&self) -> usize {
+        match *self {
+            Self::OutOfMemory { bytes } | Self::LimitExceeded { bytes } => bytes,
+        }
+    }
+}
+
+impl From<jxl_grid::Error> for MemoryError {
+    fn from(err: jxl_grid::Error) -> Self {
+        match err {
+            jxl_grid::Error::OutOfMemory(bytes) => Self::OutOfMemory { bytes },
+            jxl_grid::Error::MemoryLimitExceeded(bytes) => Self::LimitExceeded { bytes },
+        }
+    }
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfMemory { bytes } => write!(f, "failed to allocate {bytes} byte(s)"),
+            Self::LimitExceeded { bytes } => {
+                write!(f, "memory limit exceeded while reserving {bytes} byte(s)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MemoryError {}
+
+/// Stage of decoding where [`Error::Decode`] occurred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DecodeStage {
+    /// Container boxes.
+    Container,
+    /// Image header and embedded ICC profile.
+    ImageHeader,
+    /// Frame header and TOC.
+    FrameHeader,
+    /// Group data of frames.
+    FrameData,
+    /// Rendering of frames.
+    Render,
+    /// Auxiliary boxes such as Exif, gain map and frame index.
+    AuxBox,
+    /// JPEG bitstream reconstruction.
+    JpegReconstruction,
+    /// The stage is not tracked.
+    Other,
+}
+
+impl std::fmt::Display for DecodeStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Container => "container",
+            Self::ImageHeader => "image header",
+            Self::FrameHeader => "frame header",
+            Self::FrameData => "frame data",
+            Self::Render => "render",
+            Self::AuxBox => "auxiliary box",
+            Self::JpegReconstruction => "JPEG reconstruction",
+            Self::Other => "decoding",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl Error {
+    pub(crate) fn decode(stage: DecodeStage, source: impl Into<BoxedError>) -> Self {
+        Self::Decode {
+            stage,
+            frame: None,
+            group: None,
+            offset: None,
+            source: source.into(),
+        }
+    }
+
+    /// Categorizes the error from the renderer, using `stage` for decode errors.
+    pub(crate) fn from_render(err: jxl_render::Error, stage: DecodeStage) -> Self {
+        use jxl_render::Error as E;
+
+        match err {
+            E::UnsupportedFeature { feature, hint } => Self::Unsupported { feature, hint },
+            E::Cancelled => Self::Cancelled,
+            E::Color(jxl_color::Error::Buffer(e)) => Self::MemoryLimit(e.into()),
+            E::Color(e) => Self::Color(e),
+            E::Buffer(e) | E::Frame(jxl_frame::Error::Buffer(e)) => Self::MemoryLimit(e.into()),
+            E::Group { group, source, .. } => Self::from_render(*source, stage).at_group(group),
+            err => Self::decode(stage, err),
+        }
+    }
+
+    /// Sets the stage of decode errors whose stage is not tracked.
+    pub(crate) fn at_stage(mut self, stage: DecodeStage) -> Self {
+        if let Self::Decode { stage: s, .. } = &mut self {
+            if *s == DecodeStage::Other {
+                *s = stage;
+            }
+        }
+        self
+    }
+
+    /// Sets the keyframe index of decode errors.
+    pub(crate) fn at_frame(mut self, keyframe_index: usize) -> Self {
+        if let Self::Decode { frame, .. } = &mut self {
+            *frame = Some(keyframe_index);
+        }
+        self
+    }
+
+    /// Sets the group of decode errors.
+    pub(crate) fn at_group(mut self, group: TocGroupKind) -> Self {
+        if let Self::Decode { group: g, .. } = &mut self {
+            *g = Some(group);
+        }
+        self
+    }
+
+    /// Sets the codestream offset of decode errors.
+    pub(crate) fn at_offset(mut self, offset: usize) -> Self {
+        if let Self::Decode { offset: o, .. } = &mut self {
+            *o = Some(offset);
+        }
+        self
+    }
+
+    /// Returns the group of the frame the error occurred in, if known.
+    pub fn group(&self) -> Option<TocGroupKind> {
+        match self {
+            Self::Decode { group, .. } => *group,
+            _ => None,
+        }
+    }
+
+    /// Returns the offset within codestream where the failed structure starts, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Decode { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// Returns the unsupported feature the image uses, if this error is caused by one.
+    pub fn unsupported_feature(&self) -> Option<UnsupportedFeature> {
+        match self {
+            Self::Unsupported { feature, .. } => Some(*feature),
+            Self::Color(e) => UnsupportedFeature::from_color_error(e),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the error is caused by the input ending unexpectedly, which can be
+    /// resolved by feeding more data.
+    pub fn unexpected_eof(&self) -> bool {
+        match self {
+            Self::Io(e) => e.kind() == std::io::ErrorKind::UnexpectedEof,
+            Self::Decode { source, .. } => {
+                if let Some(e) = source.downcast_ref::<jxl_render::Error>() {
+                    e.unexpected_eof()
+                } else if let Some(e) = source.downcast_ref::<jxl_bitstream::Error>() {
+                    e.unexpected_eof()
+                } else if let Some(e) = source.downcast_ref::<jxl_frame::Error>() {
+                    e.unexpected_eof()
+                } else {
+                    false
+                }
+            }
+            Self::Color(e) => e.unexpected_eof(),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Decode {
+                stage,
+                frame,
+                group,
+                offset,
+                source,
+            } => {
+                write!(f, "{stage} error")?;
+                if let Some(frame) = frame {
+                    write!(f, " at keyframe #{frame}")?;
+                }
+                if let Some(group) = group {
+                    write!(f, " in {group}")?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " (offset {offset})")?;
+                }
+                write!(f, ": {source}")
+            }
+            Self::Color(e) => write!(f, "color management error: {e}"),
+            Self::Unsupported { feature, hint } => {
+                write!(f, "unsupported feature ({feature}): {hint}")
+            }
+            Self::MemoryLimit(e) => write!(f, "{e}"),
+            Self::Cancelled => write!(f, "rendering cancelled"),
+            Self::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Decode { source, .. } => Some(&**source),
+            Self::Color(e) => Some(e),
+            Self::MemoryLimit(e) => Some(e),
+            Self::Other(e) => Some(&**e),
+            Self::Unsupported { .. } | Self::Cancelled => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<jxl_render::Error> for Error {
+    fn from(err: jxl_render::Error) -> Self {
+        Self::from_render(err, DecodeStage::Render)
+    }
+}
+
+impl From<jxl_bitstream::Error> for Error {
+    fn from(err: jxl_bitstream::Error) -> Self {
+        match err {
+            jxl_bitstream::Error::Io(e) => Self::Io(e),
+            err => Self::from_render(err.into(), DecodeStage::Other),
+        }
+    }
+}
+
+impl From<jxl_frame::Error> for Error {
+    fn from(err: jxl_frame::Error) -> Self {
+        Self::from_render(err.into(), DecodeStage::FrameData)
+    }
+}
+
+impl From<jxl_color::Error> for Error {
+    fn from(err: jxl_color::Error) -> Self {
+        jxl_render::Error::from(err).into()
+    }
+}
+
+impl From<jxl_grid::Error> for Error {
+    fn from(err: jxl_grid::Error) -> Self {
+        Self::MemoryLimit(err.into())
+    }
+}
+
+impl From<jxl_jbr::Error> for Error {
+    fn from(err: jxl_jbr::Error) -> Self {
+        Self::decode(DecodeStage::JpegReconstruction, err)
+    }
+}
+
+impl From<&'static str> for Error {
+    fn from(msg: &'static str) -> Self {
+        Self::Other(msg.into())
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Self::Other(msg.into())
+    }
+}
+
+impl From<BoxedError> for Error {
+    fn from(err: BoxedError) -> Self {
+        match err.downcast::<Self>() {
+            Ok(err) => *err,
+            Err(err) => Self::Other(err),
+        }
+    }
+}
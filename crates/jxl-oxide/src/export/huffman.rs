@@ -0,0 +1,128 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Computes Huffman code lengths of symbols with the given frequencies, limited to `max_len` bits.
+///
+/// Symbols with zero frequency get zero length. If only one symbol is used, it gets length 1.
+pub(crate) fn code_lengths(freqs: &[u32], max_len: u8) -> Vec<u8> {
+    let mut freqs = freqs.to_vec();
+    loop {
+        let lengths = code_lengths_unlimited(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths;
+        }
+        // Flatten the distribution until the tree is shallow enough.
+        for freq in &mut freqs {
+            if *freq != 0 {
+                *freq = (*freq >> 1).max(1);
+            }
+        }
+    }
+}
+
+fn code_lengths_unlimited(freqs: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let used = freqs
+        .iter()
+        .enumerate()
+        .filter(|&(_, &freq)| freq != 0)
+        .map(|(sym, _)| sym)
+        .collect::<Vec<_>>();
+    if used.len() <= 1 {
+        for sym in used {
+            lengths[sym] = 1;
+        }
+        return lengths;
+    }
+
+    // Nodes are leaves (symbols) followed by internal nodes; `parent` links them to the root.
+    let mut parent = vec![usize::MAX; used.len()];
+    let mut heap = used
+        .iter()
+        .enumerate()
+        .map(|(node, &sym)| Reverse((freqs[sym] as u64, node)))
+        .collect::<BinaryHeap<_>>();
+    while heap.len() > 1 {
+        let Reverse((freq_a, a)) = heap.pop().unwrap();
+        let Reverse((freq_b, b)) = heap.pop().unwrap();
+        let node = parent.len();
+        parent.push(usize::MAX);
+        parent[a] = node;
+        parent[b] = node;
+        heap.push(Reverse((freq_a + freq_b, node)));
+    }
+
+    for (node, &sym) in used.iter().enumerate() {
+        let mut depth = 0u8;
+        let mut cur = node;
+        while parent[cur] != usize::MAX {
+            cur = parent[cur];
+            depth = depth.saturating_add(1);
+        }
+        lengths[sym] = depth;
+    }
+    lengths
+}
+
+/// Assigns canonical codes to symbols, ordered by code length and then by symbol.
+///
+/// Codes are MSB-first; symbols with zero length get code 0.
+pub(crate) fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let max_len = lengths.iter().copied().max().unwrap_or(0) as usize;
+    let mut count = vec![0u16; max_len + 1];
+    for &len in lengths {
+        count[len as usize] += 1;
+    }
+    count[0] = 0;
+
+    let mut next_code = vec![0u16; max_len + 1];
+    let mut code = 0u16;
+    for len in 1..=max_len {
+        code = (code + count[len - 1]) << 1;
+        next_code[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return 0;
+            }
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kraft_sum(lengths: &[u8]) -> f64 {
+        lengths
+            .iter()
+            .filter(|&&len| len != 0)
+            .map(|&len| 0.5f64.powi(len as i32))
+            .sum()
+    }
+
+    #[test]
+    fn limited_lengths() {
+        // Fibonacci frequencies produce the deepest possible tree.
+        let mut freqs = vec![1u32, 1];
+        while freqs.len() < 30 {
+            let n = freqs.len();
+            freqs.push(freqs[n - 1] + freqs[n - 2]);
+        }
+        let lengths = code_lengths(&freqs, 15);
+        assert!(lengths.iter().all(|&len| (1..=15).contains(&len)));
+        assert_eq!(kraft_sum(&lengths), 1.0);
+    }
+
+    #[test]
+    fn canonical() {
+        let codes = canonical_codes(&[2, 1, 3, 3, 0]);
+        assert_eq!(codes, [0b10, 0b0, 0b110, 0b111, 0]);
+    }
+}
@@ -0,0 +1,496 @@
+use std::io::prelude::*;
+
+use crate::{PixelFormat, Render};
+
+use super::{huffman, invalid_input, ExportOptions};
+
+#[rustfmt::skip]
+const ZIGZAG: [usize; 64] = [
+     0,  1,  8, 16,  9,  2,  3, 10,
+    17, 24, 32, 25, 18, 11,  4,  5,
+    12, 19, 26, 33, 40, 48, 41, 34,
+    27, 20, 13,  6,  7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36,
+    29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46,
+    53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Example quantization tables of ITU-T T.81 Annex K, in natural order.
+#[rustfmt::skip]
+const BASE_QUANT: [[u16; 64]; 2] = [
+    [
+        16, 11, 10, 16,  24,  40,  51,  61,
+        12, 12, 14, 19,  26,  58,  60,  55,
+        14, 13, 16, 24,  40,  57,  69,  56,
+        14, 17, 22, 29,  51,  87,  80,  62,
+        18, 22, 37, 56,  68, 109, 103,  77,
+        24, 35, 55, 64,  81, 104, 113,  92,
+        49, 64, 78, 87, 103, 121, 120, 101,
+        72, 92, 95, 98, 112, 100, 103,  99,
+    ],
+    [
+        17, 18, 24, 47, 99, 99, 99, 99,
+        18, 21, 26, 66, 99, 99, 99, 99,
+        24, 26, 56, 99, 99, 99, 99, 99,
+        47, 66, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+    ],
+];
+
+/// Quality at and above which chroma is not subsampled.
+const FULL_CHROMA_QUALITY: u8 = 90;
+
+/// Component of an image being encoded.
+struct Component {
+    /// Quantized coefficients of blocks in MCU order, in zigzag order.
+    blocks: Vec<[i16; 64]>,
+    /// Index of quantization and Huffman tables; 0 for luma, 1 for chroma.
+    table_idx: usize,
+    samp: u8,
+}
+
+/// Writes the first keyframe as a baseline JPEG image with `options.jpeg_quality`, from 1 to 100.
+///
+/// Color images are converted to YCbCr, with 4:2:0 chroma subsampling below quality 90. Alpha
+/// channel is discarded. Huffman tables are optimized for the image. The CICP tag is ignored.
+///
+/// # Errors
+/// Returns an error if the pixel format is CMYK, if the image is too large for JPEG, or if
+/// writing to `output` failed.
+pub fn write_jpeg<W: Write>(
+    output: W,
+    keyframes: &[Render],
+    options: &ExportOptions,
+) -> std::io::Result<()> {
+    let Some(keyframe) = keyframes.first() else {
+        return Err(invalid_input("no keyframes to write"));
+    };
+    if matches!(options.pixel_format, PixelFormat::Cmyk | PixelFormat::Cmyka) {
+        return Err(invalid_input("JPEG output doesn't support CMYK"));
+    }
+    if keyframes.len() > 1 {
+        tracing::warn!("JPEG doesn't support animation; writing the first keyframe");
+    }
+
+    let mut stream = keyframe.stream();
+    let width = stream.width() as usize;
+    let height = stream.height() as usize;
+    let channels = stream.channels() as usize;
+    if width > 65535 || height > 65535 {
+        return Err(invalid_input("image too large for JPEG"));
+    }
+    if channels == 2 || channels == 4 {
+        tracing::warn!("JPEG doesn't support alpha; discarding alpha channel");
+    }
+
+    let mut buf = vec![0u8; width * height * channels];
+    stream.write_to_buffer(&mut buf);
+
+    let quality = options.jpeg_quality.clamp(1, 100);
+    let is_color = channels >= 3;
+    let subsample = is_color && quality < FULL_CHROMA_QUALITY;
+    let quant = BASE_QUANT.map(|base| scale_quant(&base, quality));
+
+    tracing::debug!(quality, subsample, "Encoding JPEG");
+    let planes = to_planes(&buf, width, height, channels);
+    let mut components = Vec::with_capacity(planes.len());
+    for (idx, plane) in planes.into_iter().enumerate() {
+        let (plane, pw, ph, samp) = if idx == 0 {
+            let samp = if subsample { 2 } else { 1 };
+            (plane, width, height, samp)
+        } else if subsample {
+            let (plane, pw, ph) = downsample(&plane, width, height);
+            (plane, pw, ph, 1)
+        } else {
+            (plane, width, height, 1)
+        };
+
+        let table_idx = idx.min(1);
+        let max_samp = if subsample { 2 } else { 1 };
+        let blocks = quantize_blocks(
+            &plane,
+            pw,
+            ph,
+            samp,
+            width.div_ceil(8 * max_samp),
+            height.div_ceil(8 * max_samp),
+            &quant[table_idx],
+        );
+        components.push(Component {
+            blocks,
+            table_idx,
+            samp: samp as u8,
+        });
+    }
+
+    write_jfif(output, width, height, &components, &quant, &options.icc)
+}
+
+/// Scales the base quantization table with the IJG formula.
+fn scale_quant(base: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    base.map(|q| ((q as u32 * scale + 50) / 100).clamp(1, 255) as u16)
+}
+
+/// Converts interleaved samples to level-shifted planes of Y, or Y, Cb and Cr.
+fn to_planes(buf: &[u8], width: usize, height: usize, channels: usize) -> Vec<Vec<f32>> {
+    let num_pixels = width * height;
+    if channels < 3 {
+        let y = buf
+            .chunks_exact(channels)
+            .map(|px| px[0] as f32 - 128.0)
+            .collect();
+        return vec![y];
+    }
+
+    let mut planes = (0..3)
+        .map(|_| Vec::with_capacity(num_pixels))
+        .collect::<Vec<_>>();
+    for px in buf.chunks_exact(channels) {
+        let [r, g, b] = [px[0], px[1], px[2]].map(|v| v as f32);
+        planes[0].push(0.299 * r + 0.587 * g + 0.114 * b - 128.0);
+        planes[1].push(-0.168736 * r - 0.331264 * g + 0.5 * b);
+        planes[2].push(0.5 * r - 0.418688 * g - 0.081312 * b);
+    }
+    planes
+}
+
+/// Downsamples the plane by two in both directions.
+fn downsample(plane: &[f32], width: usize, height: usize) -> (Vec<f32>, usize, usize) {
+    let dw = width.div_ceil(2);
+    let dh = height.div_ceil(2);
+    let mut out = Vec::with_capacity(dw * dh);
+    for dy in 0..dh {
+        let y0 = dy * 2;
+        let y1 = (y0 + 1).min(height - 1);
+        for dx in 0..dw {
+            let x0 = dx * 2;
+            let x1 = (x0 + 1).min(width - 1);
+            let sum = plane[y0 * width + x0]
+                + plane[y0 * width + x1]
+                + plane[y1 * width + x0]
+                + plane[y1 * width + x1];
+            out.push(sum / 4.0);
+        }
+    }
+    (out, dw, dh)
+}
+
+/// Transforms and quantizes blocks of the plane, in MCU order.
+///
+/// Each MCU has `samp` by `samp` blocks of the plane. Blocks beyond the plane replicate the edge
+/// samples.
+fn quantize_blocks(
+    plane: &[f32],
+    width: usize,
+    height: usize,
+    samp: usize,
+    mcu_cols: usize,
+    mcu_rows: usize,
+    quant: &[u16; 64],
+) -> Vec<[i16; 64]> {
+    let dct = dct_matrix();
+    let mut blocks = Vec::with_capacity(mcu_cols * mcu_rows * samp * samp);
+    let mut block = [0f32; 64];
+    for mcu_y in 0..mcu_rows {
+        for mcu_x in 0..mcu_cols {
+            for by in 0..samp {
+                for bx in 0..samp {
+                    let left = (mcu_x * samp + bx) * 8;
+                    let top = (mcu_y * samp + by) * 8;
+                    for (i, sample) in block.iter_mut().enumerate() {
+                        let x = (left + i % 8).min(width - 1);
+                        let y = (top + i / 8).min(height - 1);
+                        *sample = plane[y * width + x];
+                    }
+
+                    let coeffs = fdct(&block, &dct);
+                    let mut out = [0i16; 64];
+                    for (k, &natural) in ZIGZAG.iter().enumerate() {
+                        let q = (coeffs[natural] / quant[natural] as f32).round();
+                        // Baseline JPEG allows up to 11 bits for DC, and 10 bits for AC.
+                        let limit = if k == 0 { 1024.0 } else { 1023.0 };
+                        out[k] = q.clamp(-limit, limit) as i16;
+                    }
+                    blocks.push(out);
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// Returns the orthonormal DCT-II matrix of size 8, indexed by `[frequency][position]`.
+fn dct_matrix() -> [[f32; 8]; 8] {
+    let mut m = [[0f32; 8]; 8];
+    for (u, row) in m.iter_mut().enumerate() {
+        let scale = if u == 0 {
+            std::f32::consts::FRAC_1_SQRT_2
+        } else {
+            1.0
+        };
+        for (x, v) in row.iter_mut().enumerate() {
+            let angle = (2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / 16.0;
+            *v = scale * angle.cos() / 2.0;
+        }
+    }
+    m
+}
+
+fn fdct(block: &[f32; 64], dct: &[[f32; 8]; 8]) -> [f32; 64] {
+    let mut tmp = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            tmp[y * 8 + u] = (0..8).map(|x| dct[u][x] * block[y * 8 + x]).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| dct[v][y] * tmp[y * 8 + u]).sum();
+        }
+    }
+    out
+}
+
+/// Returns the number of bits needed to represent the magnitude of `v`, and the bits themselves.
+fn magnitude(v: i32) -> (u8, u32) {
+    let size = (32 - v.unsigned_abs().leading_zeros()) as u8;
+    let bits = if v < 0 { v - 1 } else { v } as u32 & ((1u32 << size) - 1);
+    (size, bits)
+}
+
+/// Calls `f` with Huffman symbols of the block, and extra bits following each symbol.
+///
+/// The first symbol is of the DC coefficient, and the others are of AC coefficients.
+fn for_each_symbol(block: &[i16; 64], prev_dc: i16, mut f: impl FnMut(bool, u8, u8, u32)) {
+    let (size, bits) = magnitude(block[0] as i32 - prev_dc as i32);
+    f(true, size, size, bits);
+
+    let mut run = 0u8;
+    for &coeff in &block[1..] {
+        if coeff == 0 {
+            run += 1;
+            continue;
+        }
+        while run >= 16 {
+            f(false, 0xf0, 0, 0);
+            run -= 16;
+        }
+        let (size, bits) = magnitude(coeff as i32);
+        f(false, (run << 4) | size, size, bits);
+        run = 0;
+    }
+    if run > 0 {
+        f(false, 0x00, 0, 0);
+    }
+}
+
+/// Huffman table of JPEG, optimized for given symbol frequencies.
+struct HuffmanTable {
+    /// Number of codes of each length from 1 to 16.
+    bits: [u8; 16],
+    /// Symbols in the order of codes.
+    values: Vec<u8>,
+    codes: [(u16, u8); 256],
+}
+
+impl HuffmanTable {
+    fn new(freqs: &[u32; 256]) -> Self {
+        // Reserve a dummy symbol, so that no symbol gets all-ones code.
+        let mut freqs = freqs.to_vec();
+        freqs.push(1);
+        let mut lengths = huffman::code_lengths(&freqs, 16);
+        let max_len = lengths.iter().copied().max().unwrap();
+        // Move the dummy symbol to the longest code, so that it takes the last code.
+        if lengths[256] != max_len {
+            let sym = lengths.iter().position(|&len| len == max_len).unwrap();
+            lengths.swap(sym, 256);
+        }
+        lengths[256] = 0;
+
+        let mut order = (0..256)
+            .filter(|&sym| lengths[sym] != 0)
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&sym| (lengths[sym], sym));
+        let mut bits = [0u8; 16];
+        for &sym in &order {
+            bits[lengths[sym] as usize - 1] += 1;
+        }
+
+        let canonical = huffman::canonical_codes(&lengths[..256]);
+        let mut codes = [(0u16, 0u8); 256];
+        for &sym in &order {
+            codes[sym] = (canonical[sym], lengths[sym]);
+        }
+        Self {
+            bits,
+            values: order.into_iter().map(|sym| sym as u8).collect(),
+            codes,
+        }
+    }
+}
+
+/// Bit writer of entropy-coded segments, which stuffs zero bytes after `0xff`.
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, nbits: u8) {
+        for shift in (0..nbits).rev() {
+            self.acc = (self.acc << 1) | ((bits >> shift) & 1);
+            self.nbits += 1;
+            if self.nbits == 8 {
+                self.push_byte();
+            }
+        }
+    }
+
+    fn push_byte(&mut self) {
+        let byte = self.acc as u8;
+        self.out.push(byte);
+        if byte == 0xff {
+            self.out.push(0);
+        }
+        self.acc = 0;
+        self.nbits = 0;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        // Pad with one bits.
+        while self.nbits != 0 {
+            self.write(1, 1);
+        }
+        self.out
+    }
+}
+
+fn write_segment<W: Write>(output: &mut W, marker: u8, data: &[u8]) -> std::io::Result<()> {
+    output.write_all(&[0xff, marker])?;
+    output.write_all(&(data.len() as u16 + 2).to_be_bytes())?;
+    output.write_all(data)
+}
+
+fn write_jfif<W: Write>(
+    output: W,
+    width: usize,
+    height: usize,
+    components: &[Component],
+    quant: &[[u16; 64]; 2],
+    icc: &[u8],
+) -> std::io::Result<()> {
+    let mut output = std::io::BufWriter::new(output);
+    let num_tables = if components.len() == 1 { 1 } else { 2 };
+
+    // Gather symbol statistics, then build Huffman tables.
+    let blocks_per_mcu = components
+        .iter()
+        .map(|c| (c.samp as usize).pow(2))
+        .collect::<Vec<_>>();
+    let num_mcus = components[0].blocks.len() / blocks_per_mcu[0];
+    let mut freqs = [[[0u32; 256]; 2]; 2];
+    for (comp, &per_mcu) in components.iter().zip(&blocks_per_mcu) {
+        let mut prev_dc = 0;
+        for block in &comp.blocks[..num_mcus * per_mcu] {
+            for_each_symbol(block, prev_dc, |is_dc, sym, _, _| {
+                freqs[comp.table_idx][is_dc as usize][sym as usize] += 1;
+            });
+            prev_dc = block[0];
+        }
+    }
+    let tables = freqs.map(|[ac, dc]| [HuffmanTable::new(&ac), HuffmanTable::new(&dc)]);
+
+    output.write_all(&[0xff, 0xd8])?;
+    write_segment(
+        &mut output,
+        0xe0,
+        &[b'J', b'F', b'I', b'F', 0, 1, 1, 0, 0, 1, 0, 1, 0, 0],
+    )?;
+
+    // ICC profile, split into chunks.
+    const ICC_CHUNK_SIZE: usize = 65519;
+    let icc_chunks = icc.chunks(ICC_CHUNK_SIZE).collect::<Vec<_>>();
+    for (idx, chunk) in icc_chunks.iter().enumerate() {
+        let mut data = b"ICC_PROFILE\0".to_vec();
+        data.push(idx as u8 + 1);
+        data.push(icc_chunks.len() as u8);
+        data.extend_from_slice(chunk);
+        write_segment(&mut output, 0xe2, &data)?;
+    }
+
+    for (idx, table) in quant.iter().take(num_tables).enumerate() {
+        let mut data = vec![idx as u8];
+        data.extend(ZIGZAG.iter().map(|&natural| table[natural] as u8));
+        write_segment(&mut output, 0xdb, &data)?;
+    }
+
+    let mut sof = vec![8];
+    sof.extend_from_slice(&(height as u16).to_be_bytes());
+    sof.extend_from_slice(&(width as u16).to_be_bytes());
+    sof.push(components.len() as u8);
+    for (idx, comp) in components.iter().enumerate() {
+        sof.extend([
+            idx as u8 + 1,
+            (comp.samp << 4) | comp.samp,
+            comp.table_idx as u8,
+        ]);
+    }
+    write_segment(&mut output, 0xc0, &sof)?;
+
+    let mut dht = Vec::new();
+    for (idx, [ac, dc]) in tables.iter().take(num_tables).enumerate() {
+        for (class, table) in [(0u8, dc), (1u8, ac)] {
+            dht.push((class << 4) | idx as u8);
+            dht.extend_from_slice(&table.bits);
+            dht.extend_from_slice(&table.values);
+        }
+    }
+    write_segment(&mut output, 0xc4, &dht)?;
+
+    let mut sos = vec![components.len() as u8];
+    for (idx, comp) in components.iter().enumerate() {
+        sos.extend([
+            idx as u8 + 1,
+            ((comp.table_idx as u8) << 4) | comp.table_idx as u8,
+        ]);
+    }
+    sos.extend([0, 63, 0]);
+    write_segment(&mut output, 0xda, &sos)?;
+
+    let mut writer = BitWriter {
+        out: Vec::new(),
+        acc: 0,
+        nbits: 0,
+    };
+    let mut prev_dc = vec![0i16; components.len()];
+    for mcu in 0..num_mcus {
+        for (comp_idx, (comp, &per_mcu)) in components.iter().zip(&blocks_per_mcu).enumerate() {
+            let [ac, dc] = &tables[comp.table_idx];
+            for block in &comp.blocks[mcu * per_mcu..][..per_mcu] {
+                for_each_symbol(block, prev_dc[comp_idx], |is_dc, sym, size, bits| {
+                    let table = if is_dc { dc } else { ac };
+                    let (code, len) = table.codes[sym as usize];
+                    writer.write(code as u32, len);
+                    writer.write(bits, size);
+                });
+                prev_dc[comp_idx] = block[0];
+            }
+        }
+    }
+    output.write_all(&writer.finish())?;
+
+    output.write_all(&[0xff, 0xd9])?;
+    output.flush()
+}
@@ -0,0 +1,104 @@
+//! Exporters which encode decoded images into other image formats.
+//!
+//! Requires `export` feature. Keyframes are written with their durations and the loop count of
+//! the image, so that animations can be converted to APNG or animated WebP. JPEG output is always
+//! a still image.
+//!
+//! # Example
+//! ```no_run
+//! # use jxl_oxide::JxlImage;
+//! use jxl_oxide::export::{self, ExportOptions};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let image = JxlImage::builder().open("input.jxl")?;
+//! let keyframes = (0..image.num_loaded_keyframes())
+//!     .map(|idx| image.render_frame(idx))
+//!     .collect::<Result<Vec<_>, _>>()?;
+//!
+//! let options = ExportOptions::from_image(&image);
+//! let output = std::fs::File::create("output.png")?;
+//! export::write_png(output, &keyframes, &options)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{JxlImage, PixelFormat};
+
+mod huffman;
+mod jpeg;
+mod png;
+mod webp;
+
+pub use self::jpeg::write_jpeg;
+pub use self::png::write_png;
+pub use self::webp::write_webp;
+
+/// Options of exporters.
+///
+/// [`ExportOptions::from_image`] fills the options with the properties of rendered images.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExportOptions {
+    /// Pixel format of keyframes.
+    pub pixel_format: PixelFormat,
+    /// ICC profile to embed. Empty profile is not embedded.
+    pub icc: Vec<u8>,
+    /// CICP tag to embed, if the format supports it.
+    pub cicp: Option<[u8; 4]>,
+    /// Whether to write 16-bit samples, if the format supports it.
+    pub sixteen_bits: bool,
+    /// Timing information of the animation. `None` writes a still image.
+    pub animation: Option<AnimationTiming>,
+    /// Quality of JPEG output, from 1 to 100.
+    pub jpeg_quality: u8,
+}
+
+impl ExportOptions {
+    /// Creates options from the properties of rendered images of `image`.
+    ///
+    /// 16-bit samples are used if the image has more than 8 bits per sample. JPEG quality is set
+    /// to 90.
+    pub fn from_image(image: &JxlImage) -> Self {
+        let metadata = &image.image_header().metadata;
+        Self {
+            pixel_format: image.pixel_format(),
+            icc: image.rendered_icc(),
+            cicp: image.rendered_cicp(),
+            sixteen_bits: metadata.bit_depth.bits_per_sample() > 8,
+            animation: metadata
+                .animation
+                .as_ref()
+                .map(|animation| AnimationTiming {
+                    tps_numerator: animation.tps_numerator,
+                    tps_denominator: animation.tps_denominator,
+                    num_loops: animation.num_loops,
+                }),
+            jpeg_quality: 90,
+        }
+    }
+}
+
+/// Timing information of an animation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AnimationTiming {
+    /// TPS (ticks per second) numerator.
+    pub tps_numerator: u32,
+    /// TPS denominator.
+    pub tps_denominator: u32,
+    /// Number of loops, where 0 means it loops forever.
+    pub num_loops: u32,
+}
+
+impl AnimationTiming {
+    /// Returns the duration of `ticks` ticks in seconds, as a fraction.
+    fn duration_fraction(&self, ticks: u32) -> (u64, u64) {
+        (
+            self.tps_denominator as u64 * ticks as u64,
+            self.tps_numerator as u64,
+        )
+    }
+}
+
+fn invalid_input(msg: &'static str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,131 @@
+use std::io::prelude::*;
+
+use crate::{ImageStream, PixelFormat, Render};
+
+use super::{invalid_input, ExportOptions};
+
+/// Size of chunks written while streaming a still image.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Writes keyframes as a PNG image, or an APNG image if `options.animation` is set.
+///
+/// The ICC profile is embedded as an `iCCP` chunk, and the CICP tag as a `cICP` chunk. Frame
+/// delays are computed from keyframe durations; durations not representable in APNG are
+/// approximated. Still images are encoded row by row, so that the whole image is never buffered.
+///
+/// # Errors
+/// Returns an error if the pixel format is CMYK, which PNG doesn't support, or if writing to
+/// `output` failed.
+pub fn write_png<W: Write>(
+    output: W,
+    keyframes: &[Render],
+    options: &ExportOptions,
+) -> std::io::Result<()> {
+    let Some(first_keyframe) = keyframes.first() else {
+        return Err(invalid_input("no keyframes to write"));
+    };
+    let color_type = match options.pixel_format {
+        PixelFormat::Gray => png::ColorType::Grayscale,
+        PixelFormat::Graya => png::ColorType::GrayscaleAlpha,
+        PixelFormat::Rgb => png::ColorType::Rgb,
+        PixelFormat::Rgba => png::ColorType::Rgba,
+        PixelFormat::Cmyk | PixelFormat::Cmyka => {
+            return Err(invalid_input("PNG doesn't support CMYK"));
+        }
+    };
+
+    let stream = first_keyframe.stream();
+    let mut encoder = png::Encoder::new(output, stream.width(), stream.height());
+    encoder.set_color(color_type);
+    encoder.set_depth(if options.sixteen_bits {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    });
+    if let Some(animation) = &options.animation {
+        encoder.set_animated(keyframes.len() as u32, animation.num_loops)?;
+        encoder.validate_sequence(true);
+    }
+
+    let mut writer = encoder.write_header()?;
+
+    if !options.icc.is_empty() {
+        tracing::debug!("Embedding ICC profile");
+        let compressed_icc = miniz_oxide::deflate::compress_to_vec_zlib(&options.icc, 7);
+        let mut iccp_chunk_data = vec![b'0', 0, 0];
+        iccp_chunk_data.extend(compressed_icc);
+        writer.write_chunk(png::chunk::iCCP, &iccp_chunk_data)?;
+    }
+
+    if let Some(cicp) = options.cicp {
+        tracing::debug!(cicp = format_args!("{:?}", cicp), "Writing cICP chunk");
+        writer.write_chunk(png::chunk::ChunkType([b'c', b'I', b'C', b'P']), &cicp)?;
+    }
+
+    tracing::debug!("Writing image data");
+    let Some(animation) = &options.animation else {
+        let mut stream_writer = writer.stream_writer_with_size(CHUNK_SIZE)?;
+        let mut stream = first_keyframe.stream();
+        write_rows(&mut stream, options.sixteen_bits, |row| {
+            stream_writer.write_all(row)
+        })?;
+        stream_writer.finish()?;
+        writer.finish()?;
+        return Ok(());
+    };
+
+    for keyframe in keyframes {
+        let duration = keyframe.duration();
+        let (numer, denom) = animation.duration_fraction(duration);
+        let (numer, denom) = if numer >= 0x10000 || denom >= 0x10000 {
+            if duration == 0xffffffff {
+                tracing::warn!(numer, denom, "Writing multi-page image in APNG");
+            } else {
+                tracing::warn!(numer, denom, "Frame duration is not representable in APNG");
+            }
+            let duration = (numer as f64 / denom as f64) * 65535.0;
+            (duration as u16, 0xffffu16)
+        } else {
+            (numer as u16, denom as u16)
+        };
+        writer.set_frame_delay(numer, denom)?;
+
+        let mut stream = keyframe.stream();
+        let mut buf = Vec::new();
+        write_rows(&mut stream, options.sixteen_bits, |row| {
+            buf.extend_from_slice(row);
+            Ok(())
+        })?;
+        writer.write_image_data(&buf)?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Reads rows from `stream`, and passes them to `f` as big-endian bytes.
+fn write_rows(
+    stream: &mut ImageStream,
+    sixteen_bits: bool,
+    mut f: impl FnMut(&[u8]) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let row_len = (stream.width() * stream.channels()) as usize;
+    if sixteen_bits {
+        let mut fb_row = vec![0u16; row_len];
+        let mut buf_row = vec![0u8; row_len * 2];
+        for _ in 0..stream.height() {
+            stream.write_to_buffer(&mut fb_row);
+            for (b, s) in buf_row.chunks_exact_mut(2).zip(&fb_row) {
+                b.copy_from_slice(&s.to_be_bytes());
+            }
+            f(&buf_row)?;
+        }
+    } else {
+        let mut buf_row = vec![0u8; row_len];
+        for _ in 0..stream.height() {
+            stream.write_to_buffer(&mut buf_row);
+            f(&buf_row)?;
+        }
+    }
+    Ok(())
+}
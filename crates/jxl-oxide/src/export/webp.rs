@@ -0,0 +1,500 @@
+use std::io::prelude::*;
+
+use crate::{PixelFormat, Render};
+
+use super::{huffman, invalid_input, ExportOptions};
+
+/// Order of code length code lengths in VP8L prefix codes.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// Alphabet sizes of the five prefix codes, without color cache.
+const ALPHABET_SIZES: [usize; 5] = [256 + 24, 256, 256, 256, 40];
+
+/// Block size of the predictor transform, in log2.
+const PREDICTOR_BLOCK_BITS: u32 = 5;
+
+/// Predictor modes tried for each block.
+const PREDICTOR_CANDIDATES: [u8; 6] = [1, 2, 7, 11, 12, 13];
+
+/// Maximum width and height of WebP images.
+const MAX_DIMENSION: usize = 16384;
+
+/// Maximum frame duration of animated WebP, in milliseconds.
+const MAX_DURATION_MS: u64 = 0xffffff;
+
+/// Writes keyframes as a lossless WebP image, or an animated WebP image if `options.animation`
+/// is set.
+///
+/// Samples are written in 8 bits per channel. The image is coded with subtract green and predictor
+/// transforms, without backward references. Frame durations are rounded to milliseconds, and the
+/// loop count is clamped to the maximum value WebP can represent. The CICP tag is ignored.
+///
+/// # Errors
+/// Returns an error if the pixel format is CMYK, if the image is too large for WebP, or if
+/// writing to `output` failed.
+pub fn write_webp<W: Write>(
+    output: W,
+    keyframes: &[Render],
+    options: &ExportOptions,
+) -> std::io::Result<()> {
+    if keyframes.is_empty() {
+        return Err(invalid_input("no keyframes to write"));
+    }
+    if matches!(options.pixel_format, PixelFormat::Cmyk | PixelFormat::Cmyka) {
+        return Err(invalid_input("WebP doesn't support CMYK"));
+    }
+
+    let animation = options.animation.as_ref();
+    let keyframes = if animation.is_some() {
+        keyframes
+    } else {
+        &keyframes[..1]
+    };
+
+    let mut has_alpha = false;
+    let mut width = 0;
+    let mut height = 0;
+    let mut frames = Vec::with_capacity(keyframes.len());
+    for keyframe in keyframes {
+        let (pixels, frame_width, frame_height) = read_argb(keyframe)?;
+        let frame_has_alpha = pixels.iter().any(|px| px[0] != 255);
+        has_alpha |= frame_has_alpha;
+        width = width.max(frame_width);
+        height = height.max(frame_height);
+
+        tracing::debug!(has_alpha = frame_has_alpha, "Encoding lossless WebP");
+        let vp8l = encode_vp8l(pixels, frame_width, frame_height, frame_has_alpha);
+        frames.push((keyframe.duration(), frame_width, frame_height, vp8l));
+    }
+
+    let mut chunks = Vec::new();
+    let icc = &options.icc;
+    if !icc.is_empty() || animation.is_some() {
+        let mut vp8x = vec![0u8; 10];
+        vp8x[0] = if !icc.is_empty() { 0x20 } else { 0 }
+            | if has_alpha { 0x10 } else { 0 }
+            | if animation.is_some() { 0x02 } else { 0 };
+        vp8x[4..7].copy_from_slice(&(width as u32 - 1).to_le_bytes()[..3]);
+        vp8x[7..10].copy_from_slice(&(height as u32 - 1).to_le_bytes()[..3]);
+        chunks.push((*b"VP8X", vp8x));
+    }
+    if !icc.is_empty() {
+        chunks.push((*b"ICCP", icc.clone()));
+    }
+
+    if let Some(animation) = animation {
+        let mut anim = vec![0u8; 6];
+        // Opaque white background, which is a hint for viewers.
+        anim[..4].copy_from_slice(&[0xff; 4]);
+        let num_loops = animation.num_loops.min(u16::MAX as u32) as u16;
+        anim[4..].copy_from_slice(&num_loops.to_le_bytes());
+        chunks.push((*b"ANIM", anim));
+
+        for (duration, frame_width, frame_height, vp8l) in frames {
+            let (numer, denom) = animation.duration_fraction(duration);
+            let duration_ms = (numer * 1000 + denom / 2).checked_div(denom).unwrap_or(0);
+            if duration == 0xffffffff {
+                tracing::warn!("Writing multi-page image in animated WebP");
+            } else if duration_ms > MAX_DURATION_MS {
+                tracing::warn!(duration_ms, "Frame duration is not representable in WebP");
+            }
+            let duration_ms = duration_ms.min(MAX_DURATION_MS) as u32;
+
+            let mut anmf = vec![0u8; 16];
+            anmf[6..9].copy_from_slice(&(frame_width as u32 - 1).to_le_bytes()[..3]);
+            anmf[9..12].copy_from_slice(&(frame_height as u32 - 1).to_le_bytes()[..3]);
+            anmf[12..15].copy_from_slice(&duration_ms.to_le_bytes()[..3]);
+            // Frames cover the whole canvas; don't blend, don't dispose.
+            anmf[15] = 0x02;
+            append_chunk(&mut anmf, *b"VP8L", &vp8l);
+            chunks.push((*b"ANMF", anmf));
+        }
+    } else {
+        let (_, _, _, vp8l) = frames.pop().unwrap();
+        chunks.push((*b"VP8L", vp8l));
+    }
+
+    let riff_size = 4 + chunks
+        .iter()
+        .map(|(_, data)| 8 + data.len().next_multiple_of(2))
+        .sum::<usize>();
+    let riff_size =
+        u32::try_from(riff_size).map_err(|_| invalid_input("image too large for WebP"))?;
+
+    let mut output = std::io::BufWriter::new(output);
+    output.write_all(b"RIFF")?;
+    output.write_all(&riff_size.to_le_bytes())?;
+    output.write_all(b"WEBP")?;
+    for (fourcc, data) in chunks {
+        output.write_all(&fourcc)?;
+        output.write_all(&(data.len() as u32).to_le_bytes())?;
+        output.write_all(&data)?;
+        if data.len() % 2 == 1 {
+            output.write_all(&[0])?;
+        }
+    }
+    output.flush()
+}
+
+/// Appends a chunk to `out`, for chunks nested in `ANMF`.
+fn append_chunk(out: &mut Vec<u8>, fourcc: [u8; 4], data: &[u8]) {
+    out.extend_from_slice(&fourcc);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Reads 8-bit samples of the keyframe as ARGB pixels, as in VP8L.
+fn read_argb(keyframe: &Render) -> std::io::Result<(Vec<[u8; 4]>, usize, usize)> {
+    let mut stream = keyframe.stream();
+    let width = stream.width() as usize;
+    let height = stream.height() as usize;
+    let channels = stream.channels() as usize;
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(invalid_input("image too large for WebP"));
+    }
+
+    let mut buf = vec![0u8; width * height * channels];
+    stream.write_to_buffer(&mut buf);
+
+    let pixels = buf
+        .chunks_exact(channels)
+        .map(|px| match *px {
+            [g] => [255, g, g, g],
+            [g, a] => [a, g, g, g],
+            [r, g, b] => [255, r, g, b],
+            [r, g, b, a, ..] => [a, r, g, b],
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    Ok((pixels, width, height))
+}
+
+/// LSB-first bit writer of VP8L bitstream.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, nbits: u32) {
+        debug_assert!(nbits <= 32);
+        self.acc |= (bits as u64 & ((1u64 << nbits) - 1)) << self.nbits;
+        self.nbits += nbits;
+        while self.nbits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+fn encode_vp8l(mut pixels: Vec<[u8; 4]>, width: usize, height: usize, has_alpha: bool) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.write(0x2f, 8);
+    writer.write(width as u32 - 1, 14);
+    writer.write(height as u32 - 1, 14);
+    writer.write(has_alpha as u32, 1);
+    writer.write(0, 3);
+
+    // Subtract green transform.
+    writer.write(1, 1);
+    writer.write(2, 2);
+    for [_, r, g, b] in &mut pixels {
+        *r = r.wrapping_sub(*g);
+        *b = b.wrapping_sub(*g);
+    }
+
+    // Predictor transform.
+    writer.write(1, 1);
+    writer.write(0, 2);
+    writer.write(PREDICTOR_BLOCK_BITS - 2, 3);
+    let (modes, residuals) = apply_predictor(&pixels, width, height);
+    let block_size = 1usize << PREDICTOR_BLOCK_BITS;
+    let mode_image = modes
+        .iter()
+        .map(|&mode| [255, 0, mode, 0])
+        .collect::<Vec<_>>();
+    debug_assert_eq!(
+        mode_image.len(),
+        width.div_ceil(block_size) * height.div_ceil(block_size)
+    );
+    write_entropy_image(&mut writer, &mode_image, false);
+
+    writer.write(0, 1);
+    write_entropy_image(&mut writer, &residuals, true);
+    writer.finish()
+}
+
+/// Writes an image coded with a single group of prefix codes, without color cache.
+fn write_entropy_image(writer: &mut BitWriter, pixels: &[[u8; 4]], is_main: bool) {
+    // No color cache.
+    writer.write(0, 1);
+    if is_main {
+        // No meta prefix codes.
+        writer.write(0, 1);
+    }
+
+    // Channels in the order of prefix codes: green, red, blue and alpha.
+    const CHANNEL_ORDER: [usize; 4] = [2, 1, 3, 0];
+    let mut freqs = ALPHABET_SIZES.map(|size| vec![0u32; size]);
+    for px in pixels {
+        for (code_idx, &c) in CHANNEL_ORDER.iter().enumerate() {
+            freqs[code_idx][px[c] as usize] += 1;
+        }
+    }
+
+    let codes = freqs.map(|freqs| PrefixCode::new(&freqs, 15));
+    for code in &codes {
+        code.write_header(writer);
+    }
+    for px in pixels {
+        for (code, &c) in codes.iter().zip(&CHANNEL_ORDER) {
+            code.write_symbol(writer, px[c] as usize);
+        }
+    }
+}
+
+/// Prefix code of VP8L, with codes bit-reversed to be written LSB-first.
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+    used: Vec<usize>,
+}
+
+impl PrefixCode {
+    fn new(freqs: &[u32], max_len: u8) -> Self {
+        let used = (0..freqs.len())
+            .filter(|&sym| freqs[sym] != 0)
+            .collect::<Vec<_>>();
+        let lengths = huffman::code_lengths(freqs, max_len);
+        let codes = huffman::canonical_codes(&lengths)
+            .into_iter()
+            .zip(&lengths)
+            .map(|(code, &len)| reverse_bits(code, len))
+            .collect();
+        Self {
+            lengths,
+            codes,
+            used,
+        }
+    }
+
+    /// Returns whether the code can be written as a simple code.
+    fn is_simple(&self) -> bool {
+        match *self.used {
+            [] => true,
+            [a] => a < 256,
+            [a, b] => a < 256 && b < 256,
+            _ => false,
+        }
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, sym: usize) {
+        // Codes with a single symbol take zero bits.
+        if self.used.len() > 1 {
+            writer.write(self.codes[sym] as u32, self.lengths[sym] as u32);
+        }
+    }
+
+    fn write_header(&self, writer: &mut BitWriter) {
+        if self.is_simple() {
+            writer.write(1, 1);
+            let symbols = if self.used.is_empty() {
+                &[0][..]
+            } else {
+                &self.used[..]
+            };
+            writer.write(symbols.len() as u32 - 1, 1);
+            if symbols[0] < 2 {
+                writer.write(0, 1);
+                writer.write(symbols[0] as u32, 1);
+            } else {
+                writer.write(1, 1);
+                writer.write(symbols[0] as u32, 8);
+            }
+            if let Some(&sym) = symbols.get(1) {
+                writer.write(sym as u32, 8);
+            }
+            return;
+        }
+
+        // Code lengths, with runs of zeros coded with symbols 17 and 18.
+        let mut tokens = Vec::new();
+        let mut idx = 0;
+        while idx < self.lengths.len() {
+            let len = self.lengths[idx];
+            if len != 0 {
+                tokens.push((len, 0));
+                idx += 1;
+                continue;
+            }
+            let run = self.lengths[idx..]
+                .iter()
+                .take_while(|&&len| len == 0)
+                .count()
+                .min(138);
+            match run {
+                1..=2 => tokens.extend(std::iter::repeat_n((0, 0), run)),
+                3..=10 => tokens.push((17, run as u32 - 3)),
+                _ => tokens.push((18, run as u32 - 11)),
+            }
+            idx += run;
+        }
+
+        let mut freqs = [0u32; 19];
+        for &(sym, _) in &tokens {
+            freqs[sym as usize] += 1;
+        }
+        let length_code = PrefixCode::new(&freqs, 7);
+        let num_code_lengths = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&sym| length_code.lengths[sym] != 0)
+            .unwrap()
+            .max(3)
+            + 1;
+
+        writer.write(0, 1);
+        writer.write(num_code_lengths as u32 - 4, 4);
+        for &sym in &CODE_LENGTH_ORDER[..num_code_lengths] {
+            writer.write(length_code.lengths[sym] as u32, 3);
+        }
+        // Code lengths of all symbols are written.
+        writer.write(0, 1);
+        for (sym, extra) in tokens {
+            length_code.write_symbol(writer, sym as usize);
+            match sym {
+                17 => writer.write(extra, 3),
+                18 => writer.write(extra, 7),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn reverse_bits(code: u16, len: u8) -> u16 {
+    if len == 0 {
+        0
+    } else {
+        code.reverse_bits() >> (16 - len)
+    }
+}
+
+/// Chooses predictor modes of blocks, and returns the modes with residuals.
+fn apply_predictor(pixels: &[[u8; 4]], width: usize, height: usize) -> (Vec<u8>, Vec<[u8; 4]>) {
+    let block_size = 1usize << PREDICTOR_BLOCK_BITS;
+    let blocks_per_row = width.div_ceil(block_size);
+    let blocks_per_col = height.div_ceil(block_size);
+
+    let mut modes = Vec::with_capacity(blocks_per_row * blocks_per_col);
+    for by in 0..blocks_per_col {
+        for bx in 0..blocks_per_row {
+            let ys = (by * block_size)..((by + 1) * block_size).min(height);
+            let xs = (bx * block_size)..((bx + 1) * block_size).min(width);
+            let cost = |mode: u8| {
+                let mut cost = 0u64;
+                for y in ys.clone() {
+                    for x in xs.clone() {
+                        let pred = predict(pixels, width, x, y, mode);
+                        let px = pixels[y * width + x];
+                        for c in 0..4 {
+                            let r = px[c].wrapping_sub(pred[c]);
+                            cost += r.min(r.wrapping_neg()) as u64;
+                        }
+                    }
+                }
+                cost
+            };
+            let mode = PREDICTOR_CANDIDATES
+                .into_iter()
+                .min_by_key(|&mode| cost(mode))
+                .unwrap();
+            modes.push(mode);
+        }
+    }
+
+    let mut residuals = Vec::with_capacity(pixels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let mode =
+                modes[(y >> PREDICTOR_BLOCK_BITS) * blocks_per_row + (x >> PREDICTOR_BLOCK_BITS)];
+            let pred = predict(pixels, width, x, y, mode);
+            let px = pixels[y * width + x];
+            residuals.push(std::array::from_fn(|c| px[c].wrapping_sub(pred[c])));
+        }
+    }
+    (modes, residuals)
+}
+
+fn average2(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+    std::array::from_fn(|c| ((a[c] as u16 + b[c] as u16) / 2) as u8)
+}
+
+/// Predicts the pixel at `(x, y)` with the given mode, following the border rules of VP8L.
+fn predict(pixels: &[[u8; 4]], width: usize, x: usize, y: usize, mode: u8) -> [u8; 4] {
+    let idx = y * width + x;
+    if x == 0 && y == 0 {
+        return [255, 0, 0, 0];
+    }
+    if y == 0 {
+        return pixels[idx - 1];
+    }
+    if x == 0 {
+        return pixels[idx - width];
+    }
+
+    let l = pixels[idx - 1];
+    let t = pixels[idx - width];
+    let tl = pixels[idx - width - 1];
+    // For the rightmost column, this is the leftmost pixel of the current row.
+    let tr = pixels[idx - width + 1];
+    match mode {
+        0 => [255, 0, 0, 0],
+        1 => l,
+        2 => t,
+        3 => tr,
+        4 => tl,
+        5 => average2(average2(l, tr), t),
+        6 => average2(l, tl),
+        7 => average2(l, t),
+        8 => average2(tl, t),
+        9 => average2(t, tr),
+        10 => average2(average2(l, tl), average2(t, tr)),
+        11 => {
+            let dist = |a: [u8; 4], b: [u8; 4]| {
+                (0..4)
+                    .map(|c| (a[c] as i32 - b[c] as i32).abs())
+                    .sum::<i32>()
+            };
+            if dist(t, tl) < dist(l, tl) {
+                l
+            } else {
+                t
+            }
+        }
+        12 => {
+            std::array::from_fn(|c| (l[c] as i32 + t[c] as i32 - tl[c] as i32).clamp(0, 255) as u8)
+        }
+        13 => {
+            let avg = average2(l, t);
+            std::array::from_fn(|c| {
+                let a = avg[c] as i32;
+                (a + (a - tl[c] as i32) / 2).clamp(0, 255) as u8
+            })
+        }
+        _ => unreachable!(),
+    }
+}
@@ -2,6 +2,11 @@ use jxl_image::BitDepth;
 use jxl_render::{ImageBuffer, Region};
 use private::Sealed;
 
+mod integer;
+mod quantize;
+
+pub use integer::{IntegerFrameBuffer, IntegerSamples};
+
 /// Frame buffer representing a decoded image.
 #[derive(Debug, Clone)]
 pub struct FrameBuffer {
@@ -11,6 +16,27 @@ pub struct FrameBuffer {
     buf: Vec<f32>,
 }
 
+/// Maps the coordinate within `width`x`height` region to the coordinate in the oriented output.
+pub(crate) fn orient_coord(
+    orientation: u32,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> (usize, usize) {
+    match orientation {
+        1 => (x, y),
+        2 => (width - x - 1, y),
+        3 => (width - x - 1, height - y - 1),
+        4 => (x, height - y - 1),
+        5 => (y, x),
+        6 => (height - y - 1, x),
+        7 => (height - y - 1, width - x - 1),
+        8 => (y, width - x - 1),
+        _ => unreachable!(),
+    }
+}
+
 impl FrameBuffer {
     /// Creates a new framebuffer with given dimension.
     ///
@@ -41,6 +67,33 @@ impl FrameBuffer {
             panic!("Invalid orientation {orientation}");
         }
 
+        let width = copy_region.width as usize;
+        let height = copy_region.height as usize;
+        let (outw, outh) = match orientation {
+            1..=4 => (width, height),
+            5..=8 => (height, width),
+            _ => unreachable!(),
+        };
+        let mut out = Self::new(outw, outh, channels);
+        out.copy_from_grids(grids, bit_depth, grid_regions, copy_region, orientation);
+        out
+    }
+
+    /// Copies samples of grids within `copy_region` to the framebuffer, with orientation applied.
+    ///
+    /// Only rows covered by each grid are written, so that a frame can be copied in stripes.
+    /// Samples outside grids horizontally are set to zero.
+    pub(crate) fn copy_from_grids(
+        &mut self,
+        grids: &[&ImageBuffer],
+        bit_depth: &[BitDepth],
+        grid_regions: &[Region],
+        copy_region: Region,
+        orientation: u32,
+    ) {
+        let channels = self.channels;
+        assert_eq!(grids.len(), channels);
+
         let Region {
             left,
             top,
@@ -49,58 +102,41 @@ impl FrameBuffer {
         } = copy_region;
         let width = width as usize;
         let height = height as usize;
-
-        let (outw, outh) = match orientation {
-            1..=4 => (width, height),
-            5..=8 => (height, width),
-            _ => unreachable!(),
-        };
-        let mut out = Self::new(outw, outh, channels);
-        let buf = out.buf_mut();
-        for y in 0..height {
-            for x in 0..width {
-                for (c, (g, region)) in grids.iter().zip(grid_regions).enumerate() {
-                    let (outx, outy) = match orientation {
-                        1 => (x, y),
-                        2 => (width - x - 1, y),
-                        3 => (width - x - 1, height - y - 1),
-                        4 => (x, height - y - 1),
-                        5 => (y, x),
-                        6 => (height - y - 1, x),
-                        7 => (height - y - 1, width - x - 1),
-                        8 => (y, width - x - 1),
-                        _ => unreachable!(),
-                    };
+        let outw = self.width;
+        let buf = self.buf_mut();
+        for (c, (g, region)) in grids.iter().zip(grid_regions).enumerate() {
+            let base_x = (left - region.left) as isize;
+            let base_y = (top - region.top) as isize;
+            let y_begin = (-base_y).clamp(0, height as isize) as usize;
+            let y_end =
+                (region.height as isize - base_y).clamp(y_begin as isize, height as isize) as usize;
+
+            for y in y_begin..y_end {
+                let gy = (y as isize + base_y) as usize;
+                for x in 0..width {
+                    let (outx, outy) = orient_coord(orientation, x, y, width, height);
                     let idx = c + (outx + outy * outw) * channels;
 
-                    let base_x = (left - region.left) as isize;
-                    let base_y = (top - region.top) as isize;
-                    let Some(x) = x.checked_add_signed(base_x) else {
+                    let Some(gx) = x.checked_add_signed(base_x) else {
                         buf[idx] = 0.0;
                         continue;
                     };
-                    let Some(y) = y.checked_add_signed(base_y) else {
-                        buf[idx] = 0.0;
-                        continue;
-                    };
-                    if x >= region.width as usize || y >= region.height as usize {
+                    if gx >= region.width as usize {
                         buf[idx] = 0.0;
                         continue;
                     }
 
                     buf[idx] = match g {
-                        ImageBuffer::F32(g) => g.get(x, y).copied().unwrap_or(0.0),
+                        ImageBuffer::F32(g) => g.get(gx, gy).copied().unwrap_or(0.0),
                         ImageBuffer::I32(g) => {
-                            bit_depth[c].parse_integer_sample(g.get(x, y).copied().unwrap_or(0))
+                            bit_depth[c].parse_integer_sample(g.get(gx, gy).copied().unwrap_or(0))
                         }
                         ImageBuffer::I16(g) => bit_depth[c]
-                            .parse_integer_sample(g.get(x, y).copied().unwrap_or(0) as i32),
+                            .parse_integer_sample(g.get(gx, gy).copied().unwrap_or(0) as i32),
                     };
                 }
             }
         }
-
-        out
     }
 
     /// Returns the width of the frame buffer.
@@ -166,6 +202,86 @@ impl FrameBuffer {
             std::slice::from_raw_parts_mut(self.buf.as_mut_ptr() as *mut [f32; N], grouped_len)
         }
     }
+
+    /// Creates a frame buffer downsampled by the given factor, averaging each `factor x factor`
+    /// box of samples.
+    ///
+    /// Boxes on the right and bottom edges may be cut off; such boxes average the samples inside
+    /// the frame buffer only.
+    ///
+    /// # Panics
+    /// Panics if `factor` is zero.
+    pub fn downsample(&self, factor: usize) -> FrameBuffer {
+        assert!(factor > 0, "downsampling factor should be positive");
+        if factor == 1 {
+            return self.clone();
+        }
+
+        let channels = self.channels;
+        let width = self.width.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+        let mut out = Self::new(width, height, channels);
+        let mut acc = vec![0.0f32; channels];
+        for (y, out_row) in out.buf.chunks_exact_mut(width * channels).enumerate() {
+            let y_range = y * factor..((y + 1) * factor).min(self.height);
+            for (x, out_pixel) in out_row.chunks_exact_mut(channels).enumerate() {
+                let x_range = x * factor..((x + 1) * factor).min(self.width);
+                acc.fill(0.0);
+                for sy in y_range.clone() {
+                    let row = &self.buf[(sy * self.width + x_range.start) * channels..]
+                        [..x_range.len() * channels];
+                    for pixel in row.chunks_exact(channels) {
+                        for (acc, &sample) in acc.iter_mut().zip(pixel) {
+                            *acc += sample;
+                        }
+                    }
+                }
+
+                let count = (x_range.len() * y_range.len()) as f32;
+                for (out, acc) in out_pixel.iter_mut().zip(&acc) {
+                    *out = acc / count;
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(feature = "jxl-encode")]
+impl<'a> From<&'a FrameBuffer> for jxl_encode::ImageRef<'a> {
+    fn from(fb: &'a FrameBuffer) -> Self {
+        Self::from_f32(fb.width, fb.height, fb.channels, &fb.buf)
+    }
+}
+
+/// Alpha convention of color samples written by [`ImageStream`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// Keep the convention signalled by the alpha channel.
+    #[default]
+    AsSignalled,
+    /// Write color samples not multiplied by alpha.
+    Straight,
+    /// Write color samples multiplied by alpha.
+    Premultiplied,
+}
+
+/// Conversion applied to color samples in the output pass.
+#[derive(Debug, Copy, Clone)]
+enum AlphaConversion {
+    Premultiply,
+    Unpremultiply,
+}
+
+impl AlphaConversion {
+    #[inline]
+    fn apply(self, color: f32, alpha: f32) -> f32 {
+        match self {
+            Self::Premultiply => color * alpha,
+            Self::Unpremultiply if alpha > 0.0 => color / alpha,
+            Self::Unpremultiply => 0.0,
+        }
+    }
 }
 
 /// Image stream that writes to borrowed buffer.
@@ -177,6 +293,10 @@ pub struct ImageStream<'r> {
     start_offset_xy: Vec<(i32, i32)>,
     bit_depth: Vec<BitDepth>,
     spot_colors: Vec<ImageStreamSpotColor<'r>>,
+    color_channels: u32,
+    alpha: Option<(usize, bool)>,
+    alpha_conversion: Option<AlphaConversion>,
+    force_scalar: bool,
     y: u32,
     x: u32,
     c: u32,
@@ -227,21 +347,21 @@ impl<'r> ImageStream<'r> {
             }
         }
 
-        // Find alpha
-        if !skip_alpha {
-            for (ec_idx, (ec, (region, _))) in render
-                .extra_channels
-                .iter()
-                .zip(&regions_and_shifts[color_channels..])
-                .enumerate()
-            {
-                if ec.is_alpha() {
-                    grids.push(&fb[color_channels + ec_idx]);
-                    bit_depth.push(ec.bit_depth);
-                    start_offset_xy.push((left - region.left, top - region.top));
-                    break;
+        // Add selected alpha
+        let mut alpha = None;
+        if let Some(ec_idx) = render.active_alpha.filter(|_| !skip_alpha) {
+            let ec = &render.extra_channels[ec_idx];
+            let alpha_associated = matches!(
+                ec.ty,
+                ExtraChannelType::Alpha {
+                    alpha_associated: true
                 }
-            }
+            );
+            alpha = Some((grids.len(), alpha_associated));
+            let (region, _) = regions_and_shifts[color_channels + ec_idx];
+            grids.push(&fb[color_channels + ec_idx]);
+            bit_depth.push(ec.bit_depth);
+            start_offset_xy.push((left - region.left, top - region.top));
         }
 
         let mut spot_colors = Vec::new();
@@ -259,6 +379,11 @@ impl<'r> ImageStream<'r> {
                     solidity,
                 } = ec.ty
                 {
+                    let settings = render.spot_colors[ec_idx];
+                    if !settings.enabled {
+                        continue;
+                    }
+                    let solidity = settings.solidity.unwrap_or(solidity);
                     let grid = &fb[color_channels + ec_idx];
                     let xy = (left - region.left, top - region.top);
                     spot_colors.push(ImageStreamSpotColor {
@@ -280,11 +405,24 @@ impl<'r> ImageStream<'r> {
             bit_depth,
             start_offset_xy,
             spot_colors,
+            color_channels: color_channels as u32,
+            alpha,
+            alpha_conversion: None,
+            force_scalar: render.force_scalar,
             y: 0,
             x: 0,
             c: 0,
         }
     }
+
+    /// Makes the stream write samples in the orientation they're encoded in.
+    pub(crate) fn without_orientation(mut self) -> Self {
+        if self.orientation >= 5 {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+        self.orientation = 1;
+        self
+    }
 }
 
 impl ImageStream<'_> {
@@ -306,11 +444,42 @@ impl ImageStream<'_> {
         self.grids.len() as u32
     }
 
+    /// Sets the alpha convention of color samples, (un)multiplying them by alpha during the
+    /// output pass if it differs from the signalled one.
+    ///
+    /// This has no effect if the stream doesn't include an alpha channel. Color samples with
+    /// zero alpha are written as zero when converting to straight alpha.
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_conversion =
+            self.alpha.and_then(
+                |(_, alpha_associated)| match (alpha_mode, alpha_associated) {
+                    (AlphaMode::Straight, true) => Some(AlphaConversion::Unpremultiply),
+                    (AlphaMode::Premultiplied, false) => Some(AlphaConversion::Premultiply),
+                    _ => None,
+                },
+            );
+        self
+    }
+
+    /// Returns whether color samples written by the stream are multiplied by alpha, or `None`
+    /// if the stream doesn't include an alpha channel.
+    pub fn is_alpha_premultiplied(&self) -> Option<bool> {
+        let (_, alpha_associated) = self.alpha?;
+        Some(match self.alpha_conversion {
+            Some(AlphaConversion::Premultiply) => true,
+            Some(AlphaConversion::Unpremultiply) => false,
+            None => alpha_associated,
+        })
+    }
+
     /// Writes next samples to the buffer, returning how many samples are written.
+    ///
+    /// Whole rows are converted at once with vectorized code where possible, namely when the
+    /// image is not rotated or flipped and has no spot colors to render.
     pub fn write_to_buffer<Sample: FrameBufferSample>(&mut self, buf: &mut [Sample]) -> usize {
+        let mut count = self.write_rows_fast(buf);
         let channels = self.grids.len() as u32;
-        let mut buf_it = buf.iter_mut();
-        let mut count = 0usize;
+        let mut buf_it = buf[count..].iter_mut();
         'outer: while self.y < self.height {
             while self.x < self.width {
                 while self.c < channels {
@@ -333,7 +502,9 @@ impl ImageStream<'_> {
                     let grid = &self.grids[self.c as usize];
                     let bit_depth = self.bit_depth[self.c as usize];
 
-                    if self.c >= 3 || self.spot_colors.is_empty() {
+                    let is_color = self.c < self.color_channels;
+                    if !is_color || (self.spot_colors.is_empty() && self.alpha_conversion.is_none())
+                    {
                         v.copy_from_grid(grid, x, y, bit_depth);
                     } else {
                         let mut tmp_sample = 0f32;
@@ -365,6 +536,26 @@ impl ImageStream<'_> {
                             tmp_sample = color * mix + tmp_sample * (1.0 - mix);
                         }
 
+                        if let (Some(conversion), Some((alpha_idx, _))) =
+                            (self.alpha_conversion, self.alpha)
+                        {
+                            let (start_x, start_y) = self.start_offset_xy[alpha_idx];
+                            let xy = (
+                                orig_x.checked_add_signed(start_x),
+                                orig_y.checked_add_signed(start_y),
+                            );
+                            let mut alpha = 0f32;
+                            if let (Some(x), Some(y)) = xy {
+                                alpha.copy_from_grid(
+                                    self.grids[alpha_idx],
+                                    x as usize,
+                                    y as usize,
+                                    self.bit_depth[alpha_idx],
+                                );
+                            }
+                            tmp_sample = conversion.apply(tmp_sample, alpha);
+                        }
+
                         v.copy_from_f32(tmp_sample);
                     }
 
@@ -380,6 +571,82 @@ impl ImageStream<'_> {
         count
     }
 
+    /// Writes whole rows using the fast path of the sample type, returning how many samples are
+    /// written.
+    fn write_rows_fast<Sample: FrameBufferSample>(&mut self, buf: &mut [Sample]) -> usize {
+        if !Sample::HAS_ROW_FAST_PATH
+            || self.force_scalar
+            || self.x != 0
+            || self.c != 0
+            || self.orientation != 1
+            || !self.spot_colors.is_empty()
+            || self.alpha_conversion.is_some()
+        {
+            return 0;
+        }
+
+        let channels = self.grids.len();
+        let width = self.width as usize;
+        let row_len = width * channels;
+        // Integer samples are converted to floating point samples first.
+        let mut scratch = vec![Vec::new(); channels];
+        let mut float_rows = Vec::with_capacity(channels);
+        let mut count = 0usize;
+        while self.y < self.height && buf.len() - count >= row_len {
+            float_rows.clear();
+            for (idx, &grid) in self.grids.iter().enumerate() {
+                let (start_x, start_y) = self.start_offset_xy[idx];
+                let bit_depth = self.bit_depth[idx];
+                let (Ok(x), Some(y)) =
+                    (usize::try_from(start_x), self.y.checked_add_signed(start_y))
+                else {
+                    return count;
+                };
+                let y = y as usize;
+                let x_range = x..x + width;
+
+                let row = match grid {
+                    ImageBuffer::F32(g) => g.get_row(y).and_then(|row| row.get(x_range)).map(Some),
+                    ImageBuffer::I32(g) => {
+                        g.get_row(y).and_then(|row| row.get(x_range)).map(|row| {
+                            let out = &mut scratch[idx];
+                            out.clear();
+                            out.extend(row.iter().map(|&v| bit_depth.parse_integer_sample(v)));
+                            None
+                        })
+                    }
+                    ImageBuffer::I16(g) => {
+                        g.get_row(y).and_then(|row| row.get(x_range)).map(|row| {
+                            let out = &mut scratch[idx];
+                            out.clear();
+                            out.extend(
+                                row.iter()
+                                    .map(|&v| bit_depth.parse_integer_sample(v as i32)),
+                            );
+                            None
+                        })
+                    }
+                };
+                let Some(row) = row else {
+                    return count;
+                };
+                float_rows.push(row);
+            }
+
+            let rows = float_rows
+                .iter()
+                .zip(&scratch)
+                .map(|(&row, scratch)| row.unwrap_or(scratch))
+                .collect::<Vec<_>>();
+            if !Sample::write_rows(&rows, &mut buf[count..count + row_len]) {
+                return count;
+            }
+            count += row_len;
+            self.y += 1;
+        }
+        count
+    }
+
     #[inline]
     fn to_original_coord(&self, x: u32, y: u32) -> (u32, u32) {
         let width = self.width;
@@ -418,6 +685,10 @@ impl FrameBufferSample for u16 {}
 /// Output as 8-bit unsigned integer samples.
 impl FrameBufferSample for u8 {}
 
+/// Output as 16-bit floating point samples, with nominal range of `[0, 1]`.
+#[cfg(feature = "half")]
+impl FrameBufferSample for half::f16 {}
+
 mod private {
     use jxl_image::BitDepth;
     use jxl_render::ImageBuffer;
@@ -426,12 +697,32 @@ mod private {
     pub trait Sealed: Sized + Default {
         fn copy_from_grid(&mut self, grid: &ImageBuffer, x: usize, y: usize, bit_depth: BitDepth);
         fn copy_from_f32(&mut self, val: f32);
+
+        /// Whether the sample type has a fast path converting whole rows.
+        const HAS_ROW_FAST_PATH: bool = false;
+
+        /// Converts rows of each channel into interleaved samples, returning `false` if the fast
+        /// path is not available.
+        #[inline]
+        fn write_rows(_rows: &[&[f32]], _out: &mut [Self]) -> bool {
+            false
+        }
     }
 
     #[cfg(feature = "image")]
     pub trait Sealed: Sized + Default + bytemuck::NoUninit + bytemuck::AnyBitPattern {
         fn copy_from_grid(&mut self, grid: &ImageBuffer, x: usize, y: usize, bit_depth: BitDepth);
         fn copy_from_f32(&mut self, val: f32);
+
+        /// Whether the sample type has a fast path converting whole rows.
+        const HAS_ROW_FAST_PATH: bool = false;
+
+        /// Converts rows of each channel into interleaved samples, returning `false` if the fast
+        /// path is not available.
+        #[inline]
+        fn write_rows(_rows: &[&[f32]], _out: &mut [Self]) -> bool {
+            false
+        }
     }
 
     impl Sealed for f32 {
@@ -518,5 +809,47 @@ mod private {
         fn copy_from_f32(&mut self, val: f32) {
             *self = (val * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
         }
+
+        const HAS_ROW_FAST_PATH: bool = true;
+
+        #[inline]
+        fn write_rows(rows: &[&[f32]], out: &mut [Self]) -> bool {
+            super::quantize::quantize_rows_u8(rows, out)
+        }
+    }
+
+    #[cfg(feature = "half")]
+    impl Sealed for half::f16 {
+        #[inline]
+        fn copy_from_grid(&mut self, grid: &ImageBuffer, x: usize, y: usize, bit_depth: BitDepth) {
+            let mut flt = 0f32;
+            flt.copy_from_grid(grid, x, y, bit_depth);
+            self.copy_from_f32(flt);
+        }
+
+        #[inline]
+        fn copy_from_f32(&mut self, val: f32) {
+            *self = half::f16::from_f32(val);
+        }
+
+        const HAS_ROW_FAST_PATH: bool = true;
+
+        #[inline]
+        fn write_rows(rows: &[&[f32]], out: &mut [Self]) -> bool {
+            let channels = rows.len();
+            if channels == 1 {
+                // Conversion of contiguous slices uses F16C or NEON instructions if available.
+                use half::slice::HalfFloatSliceExt;
+                out.convert_from_f32_slice(rows[0]);
+                return true;
+            }
+
+            for (x, out) in out.chunks_exact_mut(channels).enumerate() {
+                for (v, row) in out.iter_mut().zip(rows) {
+                    *v = half::f16::from_f32(row[x]);
+                }
+            }
+            true
+        }
     }
 }
@@ -0,0 +1,182 @@
+//! Output of integer samples as stored in the codestream, without conversion to floats.
+
+use jxl_image::BitDepth;
+use jxl_render::{ImageBuffer, Region};
+
+use super::orient_coord;
+
+/// Frame buffer of integer samples, with interleaved channels.
+///
+/// Samples are the ones stored in the codestream, so lossless images round trip exactly. See
+/// [`Render::image_integer`][crate::Render::image_integer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegerFrameBuffer {
+    width: usize,
+    height: usize,
+    channels: usize,
+    bits_per_sample: u32,
+    samples: IntegerSamples,
+}
+
+/// Sample buffer of [`IntegerFrameBuffer`], with the smallest type which can hold the samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegerSamples {
+    /// Samples with at most 8 bits.
+    U8(Vec<u8>),
+    /// Samples with 9 to 16 bits.
+    U16(Vec<u16>),
+    /// Samples with more than 16 bits.
+    U32(Vec<u32>),
+}
+
+impl IntegerFrameBuffer {
+    /// Returns the width of the frame buffer.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the frame buffer.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of channels of the frame buffer.
+    #[inline]
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Returns the largest bit depth of the channels, which determines the sample type.
+    #[inline]
+    pub fn bits_per_sample(&self) -> u32 {
+        self.bits_per_sample
+    }
+
+    /// Returns the samples, in row-major order with interleaved channels.
+    #[inline]
+    pub fn samples(&self) -> &IntegerSamples {
+        &self.samples
+    }
+
+    /// Consumes the frame buffer, and returns the samples.
+    #[inline]
+    pub fn into_samples(self) -> IntegerSamples {
+        self.samples
+    }
+
+    /// Copies integer samples of grids within `copy_region`, with orientation applied.
+    ///
+    /// Returns `None` if any of the grids holds float samples, or has float bit depth. Samples
+    /// are clamped to the range of the bit depth of each channel.
+    pub(crate) fn from_grids(
+        grids: &[&ImageBuffer],
+        bit_depth: &[BitDepth],
+        grid_regions: &[Region],
+        copy_region: Region,
+        orientation: u32,
+    ) -> Option<Self> {
+        let channels = grids.len();
+        if channels == 0 || !(1..=8).contains(&orientation) {
+            return None;
+        }
+        if grids.iter().any(|g| matches!(g, ImageBuffer::F32(_))) {
+            return None;
+        }
+        let mut max_values = Vec::with_capacity(channels);
+        for bit_depth in bit_depth {
+            let BitDepth::IntegerSample { bits_per_sample } = *bit_depth else {
+                return None;
+            };
+            max_values.push(((1u64 << bits_per_sample) - 1).min(u32::MAX as u64) as u32);
+        }
+        let bits_per_sample = bit_depth.iter().map(|b| b.bits_per_sample()).max()?;
+
+        let width = copy_region.width as usize;
+        let height = copy_region.height as usize;
+        let (outw, outh) = match orientation {
+            1..=4 => (width, height),
+            _ => (height, width),
+        };
+
+        let copy = IntegerCopy {
+            grids,
+            max_values: &max_values,
+            grid_regions,
+            copy_region,
+            orientation,
+            outw,
+        };
+        let len = outw * outh * channels;
+        let samples = if bits_per_sample <= 8 {
+            IntegerSamples::U8(copy.run(len, |v| v as u8))
+        } else if bits_per_sample <= 16 {
+            IntegerSamples::U16(copy.run(len, |v| v as u16))
+        } else {
+            IntegerSamples::U32(copy.run(len, |v| v))
+        };
+
+        Some(Self {
+            width: outw,
+            height: outh,
+            channels,
+            bits_per_sample,
+            samples,
+        })
+    }
+}
+
+struct IntegerCopy<'a> {
+    grids: &'a [&'a ImageBuffer],
+    max_values: &'a [u32],
+    grid_regions: &'a [Region],
+    copy_region: Region,
+    orientation: u32,
+    outw: usize,
+}
+
+impl IntegerCopy<'_> {
+    fn run<T: Default + Clone>(&self, len: usize, convert: impl Fn(u32) -> T) -> Vec<T> {
+        let channels = self.grids.len();
+        let Region {
+            left,
+            top,
+            width,
+            height,
+        } = self.copy_region;
+        let width = width as usize;
+        let height = height as usize;
+
+        let mut buf = vec![T::default(); len];
+        for (c, (g, region)) in self.grids.iter().zip(self.grid_regions).enumerate() {
+            let max_value = self.max_values[c];
+            let base_x = (left - region.left) as isize;
+            let base_y = (top - region.top) as isize;
+
+            for y in 0..height {
+                let Some(gy) = y.checked_add_signed(base_y) else {
+                    continue;
+                };
+                for x in 0..width {
+                    let Some(gx) = x.checked_add_signed(base_x) else {
+                        continue;
+                    };
+                    let sample = match g {
+                        ImageBuffer::I32(g) => g.get(gx, gy).copied(),
+                        ImageBuffer::I16(g) => g.get(gx, gy).map(|&v| v as i32),
+                        ImageBuffer::F32(_) => unreachable!(),
+                    };
+                    let Some(sample) = sample else {
+                        continue;
+                    };
+
+                    let (outx, outy) = orient_coord(self.orientation, x, y, width, height);
+                    let idx = c + (outx + outy * self.outw) * channels;
+                    buf[idx] = convert((sample.max(0) as u32).min(max_value));
+                }
+            }
+        }
+        buf
+    }
+}
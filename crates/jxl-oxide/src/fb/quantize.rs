@@ -0,0 +1,150 @@
+//! Vectorized conversion of rendered rows into interleaved 8-bit samples.
+//!
+//! Rendered samples are already in the target color encoding, so the conversion clamps, scales
+//! and rounds samples in one pass while interleaving channels.
+
+/// Maximum number of channels supported by the fast path, which is CMYK with alpha.
+const MAX_CHANNELS: usize = 5;
+
+/// Quantizes rows of each channel into interleaved 8-bit samples.
+///
+/// Every row should have `out.len() / rows.len()` samples. Returns `false` without writing
+/// anything if vectorized implementation is not available.
+pub(super) fn quantize_rows_u8(rows: &[&[f32]], out: &mut [u8]) -> bool {
+    if rows.is_empty() || rows.len() > MAX_CHANNELS {
+        return false;
+    }
+    let width = out.len() / rows.len();
+    debug_assert!(rows.iter().all(|row| row.len() == width));
+
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        // SAFETY: Feature set is checked above.
+        unsafe { x86_64::quantize_rows_u8_avx2(rows, out) };
+        return true;
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: Feature set is checked above.
+        unsafe { aarch64::quantize_rows_u8_neon(rows, out) };
+        return true;
+    }
+
+    false
+}
+
+/// Quantizes samples from `start_x`, with the same rounding as the per-sample path.
+#[cfg_attr(
+    not(any(target_arch = "x86_64", target_arch = "aarch64")),
+    allow(dead_code)
+)]
+#[inline]
+fn quantize_rows_u8_generic(rows: &[&[f32]], start_x: usize, out: &mut [u8]) {
+    let channels = rows.len();
+    for (dx, px) in out[start_x * channels..]
+        .chunks_exact_mut(channels)
+        .enumerate()
+    {
+        for (v, row) in px.iter_mut().zip(rows) {
+            *v = (row[start_x + dx] * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Interleaves eight quantized samples of each channel.
+#[cfg_attr(
+    not(any(target_arch = "x86_64", target_arch = "aarch64")),
+    allow(dead_code)
+)]
+#[inline(always)]
+fn interleave8(samples: &[[u8; 8]], out: &mut [u8]) {
+    let channels = samples.len();
+    for (idx, px) in out.chunks_exact_mut(channels).enumerate() {
+        for (v, samples) in px.iter_mut().zip(samples) {
+            *v = samples[idx];
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64 {
+    use std::arch::x86_64::*;
+
+    use super::{interleave8, quantize_rows_u8_generic, MAX_CHANNELS};
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn quantize_rows_u8_avx2(rows: &[&[f32]], out: &mut [u8]) {
+        let channels = rows.len();
+        let width = out.len() / channels;
+        let scale = _mm256_set1_ps(255.0);
+        let half = _mm256_set1_ps(0.5);
+        let zero = _mm256_setzero_ps();
+        let maxv = _mm256_set1_ps(255.0);
+
+        let mut samples = [[0u8; 8]; MAX_CHANNELS];
+        let mut x = 0usize;
+        while x + 8 <= width {
+            for (row, samples) in rows.iter().zip(&mut samples) {
+                let v = _mm256_loadu_ps(row[x..x + 8].as_ptr());
+                let v = _mm256_add_ps(_mm256_mul_ps(v, scale), half);
+                // `max` returns the second operand if the first one is NaN, mapping NaN to zero.
+                let v = _mm256_min_ps(_mm256_max_ps(v, zero), maxv);
+                let v = _mm256_cvttps_epi32(v);
+                let lo = _mm256_castsi256_si128(v);
+                let hi = _mm256_extracti128_si256::<1>(v);
+                let v = _mm_packus_epi32(lo, hi);
+                let v = _mm_packus_epi16(v, v);
+                _mm_storel_epi64(samples.as_mut_ptr() as *mut __m128i, v);
+            }
+            interleave8(
+                &samples[..channels],
+                &mut out[x * channels..(x + 8) * channels],
+            );
+            x += 8;
+        }
+
+        quantize_rows_u8_generic(rows, x, out);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    use std::arch::aarch64::*;
+
+    use super::{interleave8, quantize_rows_u8_generic, MAX_CHANNELS};
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn quantize4(v: float32x4_t) -> uint32x4_t {
+        let v = vaddq_f32(vmulq_n_f32(v, 255.0), vdupq_n_f32(0.5));
+        // `maxnm` returns the number if one of the operands is NaN, mapping NaN to zero.
+        let v = vminq_f32(vmaxnmq_f32(v, vdupq_n_f32(0.0)), vdupq_n_f32(255.0));
+        vcvtq_u32_f32(v)
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn quantize_rows_u8_neon(rows: &[&[f32]], out: &mut [u8]) {
+        let channels = rows.len();
+        let width = out.len() / channels;
+
+        let mut samples = [[0u8; 8]; MAX_CHANNELS];
+        let mut x = 0usize;
+        while x + 8 <= width {
+            for (row, samples) in rows.iter().zip(&mut samples) {
+                let row = &row[x..x + 8];
+                let lo = quantize4(vld1q_f32(row.as_ptr()));
+                let hi = quantize4(vld1q_f32(row[4..].as_ptr()));
+                let v = vcombine_u16(vmovn_u32(lo), vmovn_u32(hi));
+                vst1_u8(samples.as_mut_ptr(), vmovn_u16(v));
+            }
+            interleave8(
+                &samples[..channels],
+                &mut out[x * channels..(x + 8) * channels],
+            );
+            x += 8;
+        }
+
+        quantize_rows_u8_generic(rows, x, out);
+    }
+}
@@ -4,9 +4,19 @@
 //!
 //! Integrations are enabled with feature flags.
 //! - `JxlDecoder`, which implements `image::ImageDecoder` (`image` feature)
+//! - `Render::to_ndarray` and `Render::channel_to_ndarray`, which convert renders to
+//!   `ndarray` arrays (`ndarray` feature)
+//!
+//! Helpers which don't depend on other crates are always available.
+//! - `Rgba8Texture`, which converts renders to RGBA8 textures of GUI toolkits such as egui and
+//!   iced
 
 #[cfg(feature = "image")]
 mod image;
+#[cfg(feature = "ndarray")]
+mod ndarray;
+mod texture;
 
 #[cfg(feature = "image")]
 pub use image::*;
+pub use texture::*;
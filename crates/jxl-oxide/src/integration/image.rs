@@ -4,7 +4,7 @@ use image::error::{DecodingError, ImageFormatHint};
 use image::{ColorType, ImageError, ImageResult};
 use jxl_grid::AllocTracker;
 
-use crate::{AuxBoxData, CropInfo, InitializeResult, JxlImage};
+use crate::{AuxBoxData, CropInfo, DecodeOptions, InitializeResult, JxlImage};
 
 /// JPEG XL decoder which implements [`ImageDecoder`][image::ImageDecoder].
 ///
@@ -13,21 +13,24 @@ use crate::{AuxBoxData, CropInfo, InitializeResult, JxlImage};
 /// Currently `JxlDecoder` supports following features:
 /// - Returning images of 8-bit, 16-bit integer and 32-bit float samples
 /// - RGB or luma-only images, with or without alpha
-/// - Returning ICC profiles via `icc_profile`
+/// - Returning ICC profiles via `icc_profile`, and CICP tags via [`JxlDecoder::cicp`]
 /// - Returning Exif metadata via `exif_metadata`
 /// - Setting decoder limits (caveat: memory limits are not strict)
 /// - Cropped decoding with [`ImageDecoderRect`][image::ImageDecoderRect]
-/// - (When `lcms2` feature is enabled) Converting CMYK images to sRGB color space
-///
-/// Some features are planned but not implemented yet:
-/// - Decoding animations
+/// - (When `lcms2` or `jxl-cms` feature is enabled) Converting CMYK images to sRGB color space
+/// - Decoding animations with [`AnimationDecoder`][image::AnimationDecoder]
+/// - Decoding truncated images, by rendering partially loaded keyframe like the CLI does
 ///
 /// # Note about color management
 ///
 /// `JxlDecoder` doesn't do color management by itself (except for CMYK images, which will be
-/// converted to sRGB color space if `lcms2` is available). Consumers should apply appropriate
-/// color transforms using ICC profile returned by [`icc_profile()`], otherwise colors may be
-/// inaccurate.
+/// converted to sRGB color space if `lcms2` or `jxl-cms` is available). Consumers should apply
+/// appropriate color transforms using ICC profile returned by [`icc_profile()`], otherwise colors
+/// may be inaccurate.
+///
+/// ICC profile always describes the color space of returned samples, including wide gamut and HDR
+/// color spaces such as Display P3 and BT.2100 PQ. Consumers which understand CICP can use
+/// [`JxlDecoder::cicp`] instead for color spaces representable by CICP.
 ///
 /// # Examples
 ///
@@ -72,21 +75,45 @@ impl<R: Read> JxlDecoder<R> {
     ///
     /// Decoder will be initialized with default thread pool.
     pub fn new(reader: R) -> ImageResult<Self> {
-        let builder = JxlImage::builder().alloc_tracker(AllocTracker::with_limit(usize::MAX));
-
-        Self::init(builder, reader)
+        Self::with_options(reader, DecodeOptions::default())
     }
 
     /// Initializes a decoder which reads from given image stream, with custom thread pool.
     pub fn with_thread_pool(reader: R, pool: crate::JxlThreadPool) -> ImageResult<Self> {
-        let builder = JxlImage::builder()
-            .pool(pool)
-            .alloc_tracker(AllocTracker::with_limit(usize::MAX));
+        let builder = JxlImage::builder().pool(pool);
+        Self::init(builder, reader, DecodeOptions::default())
+    }
+
+    /// Initializes a decoder which reads from given image stream, with decoding options.
+    ///
+    /// `crop` of the options is ignored; use [`ImageDecoderRect`][image::ImageDecoderRect] for
+    /// cropped decoding. CMYK images are always converted to sRGB if no target color is given.
+    pub fn with_options(reader: R, options: DecodeOptions) -> ImageResult<Self> {
+        Self::init(JxlImage::builder(), reader, options)
+    }
 
-        Self::init(builder, reader)
+    /// Returns the CICP tag of the color encoding of decoded images, if there's any.
+    ///
+    /// The tag consists of color primaries, transfer characteristics, matrix coefficients and
+    /// video full range flag, in this order.
+    pub fn cicp(&self) -> Option<[u8; 4]> {
+        self.image.rendered_cicp()
     }
 
-    fn init(builder: crate::JxlImageBuilder, mut reader: R) -> ImageResult<Self> {
+    fn init(
+        builder: crate::JxlImageBuilder,
+        mut reader: R,
+        options: DecodeOptions,
+    ) -> ImageResult<Self> {
+        let memory_limit = options.approx_memory_limit.unwrap_or(usize::MAX);
+        let builder = builder
+            .alloc_tracker(AllocTracker::with_limit(memory_limit))
+            .options(DecodeOptions {
+                crop: None,
+                cmyk_to_srgb: true,
+                ..options
+            });
+
         let mut buf = vec![0u8; 4096];
         let mut buf_valid = 0usize;
         let image = Self::init_image(builder, &mut reader, &mut buf, &mut buf_valid)
@@ -99,24 +126,15 @@ impl<R: Read> JxlDecoder<R> {
             top: 0,
         };
 
-        let mut decoder = Self {
+        let decoder = Self {
             reader,
             image,
-            current_memory_limit: usize::MAX,
+            current_memory_limit: memory_limit,
             current_crop: crop,
             buf,
             buf_valid,
         };
 
-        // Convert CMYK to sRGB
-        if decoder.image.pixel_format().has_black() {
-            decoder
-                .image
-                .request_color_encoding(jxl_color::EnumColourEncoding::srgb(
-                    jxl_color::RenderingIntent::Relative,
-                ));
-        }
-
         Ok(decoder)
     }
 
@@ -174,7 +192,9 @@ impl<R: Read> JxlDecoder<R> {
     }
 
     fn load_until_first_keyframe(&mut self) -> crate::Result<()> {
-        self.load_until_condition(|image| Ok(image.ctx.loaded_frames() > 0))?;
+        // Frames preceding the first keyframe, such as LF frames, can't be rendered by
+        // themselves; keep loading until the keyframe is loaded or the reader ends.
+        self.load_until_condition(|image| Ok(image.num_loaded_keyframes() > 0))?;
 
         if self.image.frame_by_keyframe(0).is_none() {
             return Err(std::io::Error::new(
@@ -225,16 +245,10 @@ impl<R: Read> JxlDecoder<R> {
         self.load_until_first_keyframe()?;
 
         let render = if self.image.num_loaded_keyframes() > 0 {
-            self.image.render_frame(0)
+            self.image.render_frame(0)?
         } else {
-            self.image.render_loading_frame()
+            self.image.render_loading_frame()?
         };
-        let render = render.map_err(|e| {
-            ImageError::Decoding(DecodingError::new(
-                ImageFormatHint::PathExtension("jxl".into()),
-                e,
-            ))
-        })?;
         let stream = render.stream();
 
         let stride_base = stream.width() as usize * stream.channels() as usize;
@@ -280,6 +294,18 @@ impl<R: Read> image::ImageDecoder for JxlDecoder<R> {
         }
     }
 
+    fn original_color_type(&self) -> image::ExtendedColorType {
+        use image::ExtendedColorType;
+
+        let metadata = &self.image.image_header().metadata;
+        let is_cmyk = metadata.ec_info.iter().any(|ec| ec.is_black());
+        if is_cmyk && !self.need_16bit() {
+            ExtendedColorType::Cmyk8
+        } else {
+            self.color_type().into()
+        }
+    }
+
     fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()>
     where
         Self: Sized,
@@ -413,6 +439,100 @@ impl<R: Read> image::ImageDecoderRect for JxlDecoder<R> {
     }
 }
 
+impl<'a, R: Read + 'a> image::AnimationDecoder<'a> for JxlDecoder<R> {
+    /// Consumes the decoder, producing keyframes as RGBA8 images.
+    ///
+    /// Frame delays are computed from durations of keyframes and ticks per second specified in
+    /// the animation header. Still images produce a single frame with zero delay.
+    fn into_frames(mut self) -> image::Frames<'a> {
+        let full_crop = CropInfo {
+            width: self.image.width(),
+            height: self.image.height(),
+            left: 0,
+            top: 0,
+        };
+        if self.current_crop != full_crop {
+            self.image.set_image_region(full_crop);
+            self.current_crop = full_crop;
+        }
+
+        let mut keyframe_idx = 0usize;
+        let mut failed = false;
+        let it = std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+
+            let result = self.read_keyframe_rgba8(keyframe_idx).transpose()?;
+            keyframe_idx += 1;
+            failed = result.is_err();
+            Some(result.map_err(|e| {
+                ImageError::Decoding(DecodingError::new(
+                    ImageFormatHint::PathExtension("jxl".into()),
+                    e,
+                ))
+            }))
+        });
+        image::Frames::new(Box::new(it))
+    }
+}
+
+impl<R: Read> JxlDecoder<R> {
+    fn read_keyframe_rgba8(&mut self, keyframe_idx: usize) -> crate::Result<Option<image::Frame>> {
+        self.load_until_condition(|image| Ok(image.num_loaded_keyframes() > keyframe_idx))?;
+
+        let num_loaded_keyframes = self.image.num_loaded_keyframes();
+        let render = if keyframe_idx < num_loaded_keyframes {
+            self.image.render_frame(keyframe_idx)?
+        } else if keyframe_idx == num_loaded_keyframes && !self.image.is_loading_done() {
+            // The reader ended in the middle of the keyframe.
+            match self.image.render_loading_frame() {
+                Ok(render) => {
+                    tracing::warn!(keyframe_idx, "Rendered partially loaded keyframe");
+                    render
+                }
+                Err(e) => {
+                    tracing::debug!(%e, "Cannot render partially loaded keyframe");
+                    return Ok(None);
+                }
+            }
+        } else {
+            return Ok(None);
+        };
+        let mut stream = render.stream();
+        let width = stream.width();
+        let height = stream.height();
+        let channels = stream.channels() as usize;
+        let mut buf = vec![0u8; width as usize * height as usize * channels];
+        stream.write_to_buffer(&mut buf);
+
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for pixel in buf.chunks_exact(channels) {
+            let px = match *pixel {
+                [l] => [l, l, l, 255],
+                [l, a] => [l, l, l, a],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a, ..] => [r, g, b, a],
+                [] => unreachable!(),
+            };
+            rgba.extend_from_slice(&px);
+        }
+        let buffer = image::RgbaImage::from_raw(width, height, rgba).unwrap();
+
+        let delay = match &self.image.image_header().metadata.animation {
+            Some(animation) if animation.tps_numerator != 0 => {
+                let numer = (render.duration() as u64)
+                    .saturating_mul(animation.tps_denominator as u64 * 1000)
+                    .min(u32::MAX as u64) as u32;
+                image::Delay::from_numer_denom_ms(numer, animation.tps_numerator)
+            }
+            _ => image::Delay::from_numer_denom_ms(0, 1),
+        };
+
+        Ok(Some(image::Frame::from_parts(buffer, 0, 0, delay)))
+    }
+}
+
 fn stream_to_buf<Sample: crate::FrameBufferSample>(
     mut stream: crate::ImageStream<'_>,
     buf: &mut [u8],
@@ -0,0 +1,42 @@
+use ndarray::{Array2, Array3};
+
+use crate::{FrameBuffer, Render};
+
+impl Render {
+    /// Converts the render into an array of shape `(height, width, channels)`, with orientation
+    /// applied.
+    ///
+    /// Samples are 32-bit float with nominal range of `[0, 1]`, without quantization. All extra
+    /// channels are included, in the same order as [`image_all_channels`][Self::image_all_channels].
+    pub fn to_ndarray(&self) -> Array3<f32> {
+        let fb = self.image_all_channels();
+        let shape = (fb.height(), fb.width(), fb.channels());
+        Array3::from_shape_vec(shape, fb.buf().to_vec()).expect("buffer size mismatch")
+    }
+
+    /// Converts a single channel of the render into an array of shape `(height, width)`, with
+    /// orientation applied.
+    ///
+    /// Channels are indexed as in [`to_ndarray`][Self::to_ndarray]; color channels come first,
+    /// followed by extra channels. Returns `None` if `channel` is out of range.
+    pub fn channel_to_ndarray(&self, channel: usize) -> Option<Array2<f32>> {
+        let grid = self.image.buffer().get(channel)?;
+        let color_channels = self.image.color_channels();
+        let bit_depth = if channel < color_channels {
+            self.color_bit_depth
+        } else {
+            self.extra_channels[channel - color_channels].bit_depth
+        };
+        let region = self.image.regions_and_shifts()[channel].0;
+
+        let fb = FrameBuffer::from_grids(
+            &[grid],
+            &[bit_depth],
+            &[region],
+            self.target_frame_region,
+            self.orientation,
+        );
+        let shape = (fb.height(), fb.width());
+        Some(Array2::from_shape_vec(shape, fb.buf().to_vec()).expect("buffer size mismatch"))
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{AlphaMode, EnumColourEncoding, JxlImage, Render};
+
+/// RGBA8 texture data in sRGB, with unassociated alpha.
+///
+/// This is the format expected by texture APIs of most GUI toolkits, such as
+/// `egui::ColorImage::from_rgba_unmultiplied` and `iced::widget::image::Handle::from_rgba`:
+///
+/// ```ignore
+/// let texture = Rgba8Texture::from_render(&render).unwrap();
+/// // egui
+/// let color_image = egui::ColorImage::from_rgba_unmultiplied(texture.size(), texture.pixels());
+/// // iced
+/// let handle = iced::widget::image::Handle::from_rgba(
+///     texture.width(),
+///     texture.height(),
+///     texture.into_pixels(),
+/// );
+/// ```
+///
+/// Call [`prepare_image`] before rendering, so that the image is rendered in sRGB.
+#[derive(Debug, Clone)]
+pub struct Rgba8Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Requests the image to be rendered in sRGB, which is assumed by [`Rgba8Texture`].
+///
+/// CMYK images are converted to sRGB as well. Rendering in linear light is disabled.
+pub fn prepare_image(image: &mut JxlImage) {
+    image.set_render_linear(false);
+    image.request_color_encoding(EnumColourEncoding::srgb(
+        jxl_color::RenderingIntent::Relative,
+    ));
+}
+
+impl Rgba8Texture {
+    /// Converts the render into an RGBA8 texture, unassociating the active alpha channel if needed.
+    ///
+    /// Returns `None` if the render is in CMYK. Color samples are taken as is, so the image
+    /// should be prepared with [`prepare_image`] to get correct colors.
+    pub fn from_render(render: &Render) -> Option<Self> {
+        if render.is_cmyk {
+            return None;
+        }
+
+        // Alpha of the active alpha channel is unassociated by the stream.
+        let mut stream = render.stream_with_alpha_mode(AlphaMode::Straight);
+        let width = stream.width();
+        let height = stream.height();
+        let channels = stream.channels() as usize;
+        let mut buf = vec![0f32; width as usize * height as usize * channels];
+        stream.write_to_buffer(&mut buf);
+
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for pixel in buf.chunks_exact(channels) {
+            let rgba = match *pixel {
+                [l] => [l, l, l, 1.0],
+                [l, a] => [l, l, l, a],
+                [r, g, b] => [r, g, b, 1.0],
+                [r, g, b, a] => [r, g, b, a],
+                _ => unreachable!(),
+            };
+            pixels.extend(rgba.map(|v| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8));
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Returns the width of the texture.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the texture.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the size of the texture in `[width, height]`.
+    #[inline]
+    pub fn size(&self) -> [usize; 2] {
+        [self.width as usize, self.height as usize]
+    }
+
+    /// Returns RGBA8 pixels of the texture.
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Consumes the texture, returning RGBA8 pixels.
+    #[inline]
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+}
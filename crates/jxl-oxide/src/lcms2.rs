@@ -1,6 +1,6 @@
-use lcms2::{Profile, Transform};
+use lcms2::{DisallowCache, Flags, GlobalContext, Profile, Transform};
 
-use crate::RenderingIntent;
+use crate::{PreparedTransform, RenderingIntent};
 
 /// Little CMS 2 integration.
 pub struct Lcms2;
@@ -13,20 +13,47 @@ impl crate::ColorManagementSystem for Lcms2 {
         intent: RenderingIntent,
         channels: &mut [&mut [f32]],
     ) -> Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let transform = Lcms2Transform::new(from, to, intent)?;
+        Ok(transform.run(channels))
+    }
+
+    fn prepare_transform(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+    ) -> Result<
+        Option<Box<dyn PreparedTransform>>,
+        Box<dyn std::error::Error + Send + Sync + 'static>,
+    > {
+        let transform = Lcms2Transform::new(from, to, intent)?;
+        Ok(Some(Box::new(transform)))
+    }
+}
+
+/// Little CMS 2 transform which can be shared between threads.
+struct Lcms2Transform {
+    from_channels: usize,
+    to_channels: usize,
+    transform: Transform<u8, u8, GlobalContext, DisallowCache>,
+}
+
+impl Lcms2Transform {
+    fn new(from: &[u8], to: &[u8], intent: RenderingIntent) -> lcms2::LCMSResult<Self> {
         use lcms2::ColorSpaceSignatureExt;
 
         let from_profile = Profile::new_icc(from)?;
         let from_channels = from_profile.color_space().channels() as usize;
         let to_profile = Profile::new_icc(to)?;
         let to_channels = to_profile.color_space().channels() as usize;
-        let max_channels = from_channels.max(to_channels);
-        assert!(channels.len() >= max_channels);
 
         #[allow(clippy::unusual_byte_groupings)]
         let format_base = 0b010_00000_000000_000_0000_100;
         let from_pixel_format = lcms2::PixelFormat(format_base | ((from_channels as u32) << 3));
         let to_pixel_format = lcms2::PixelFormat(format_base | ((to_channels as u32) << 3));
-        let transform = Transform::new(
+        // Disabling the cache makes the transform `Sync`.
+        let transform = Transform::new_flags_context(
+            GlobalContext::new(),
             &from_profile,
             from_pixel_format,
             &to_profile,
@@ -37,8 +64,22 @@ impl crate::ColorManagementSystem for Lcms2 {
                 RenderingIntent::Saturation => lcms2::Intent::Saturation,
                 RenderingIntent::Absolute => lcms2::Intent::AbsoluteColorimetric,
             },
+            Flags::NO_CACHE,
         )?;
 
+        Ok(Self {
+            from_channels,
+            to_channels,
+            transform,
+        })
+    }
+
+    fn run(&self, channels: &mut [&mut [f32]]) -> usize {
+        let from_channels = self.from_channels;
+        let to_channels = self.to_channels;
+        let max_channels = from_channels.max(to_channels);
+        assert!(channels.len() >= max_channels);
+
         let mut buf_in = vec![0f32; 1024 * from_channels];
         let mut buf_out = vec![0f32; 1024 * to_channels];
         let len = channels.iter().map(|x| x.len()).min().unwrap();
@@ -60,7 +101,8 @@ impl crate::ColorManagementSystem for Lcms2 {
                     buf_out_ptr as *mut u8,
                     chunk_len * to_channels * std::mem::size_of::<f32>(),
                 );
-                transform.transform_pixels(transform_buf_in, transform_buf_out);
+                self.transform
+                    .transform_pixels(transform_buf_in, transform_buf_out);
             }
             for k in 0..chunk_len {
                 for (channel_idx, ch) in channels[..to_channels].iter_mut().enumerate() {
@@ -69,6 +111,15 @@ impl crate::ColorManagementSystem for Lcms2 {
             }
         }
 
-        Ok(to_channels)
+        to_channels
+    }
+}
+
+impl PreparedTransform for Lcms2Transform {
+    fn transform(
+        &self,
+        channels: &mut [&mut [f32]],
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.run(channels))
     }
 }
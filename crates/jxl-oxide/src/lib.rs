@@ -91,7 +91,8 @@
 //! jxl-oxide has basic color management support, which enables color transformation between
 //! well-known color encodings and parsing simple, matrix-based ICC profiles. However, jxl-oxide
 //! alone does not support conversion to and from arbitrary ICC profiles, notably CMYK profiles.
-//! This includes converting from embedded ICC profiles.
+//! This includes converting from embedded ICC profiles. Such conversions are done by the pure
+//! Rust CMS [`PureRustCms`], which is enabled by default with `jxl-cms` feature.
 //!
 //! Use [`JxlImage::request_color_encoding`] or [`JxlImage::request_icc`] to set color encoding of
 //! rendered images. Conversion to and/or from ICC profiles may occur if you do this; in that case,
@@ -108,8 +109,13 @@
 //! image.request_color_encoding(color_encoding);
 //! ```
 //!
-//! External CMS is set to Little CMS 2 by default if `lcms2` feature is enabled. You can
-//! explicitly disable this by setting CMS to [`NullCms`].
+//! External CMS is set to Little CMS 2 by default if `lcms2` feature is enabled, or to
+//! [`PureRustCms`] if only `jxl-cms` feature is enabled. You can explicitly disable this by
+//! setting CMS to [`NullCms`].
+//!
+//! If a CMYK ICC profile is requested, rendered images have four color channels, with black
+//! channel included in the stream, regardless of whether the image itself is CMYK. Samples follow
+//! the JPEG XL convention where 0 means full ink.
 //!
 //! ```no_run
 //! # use jxl_oxide::{JxlImage, NullCms};
@@ -139,17 +145,35 @@
 //! # }
 //! ```
 //!
+//! # Multithreading and reproducibility
+//! Rendering is parallelized using the thread pool set with [`JxlImageBuilder::pool`], which is
+//! backed by Rayon by default if `rayon` feature is enabled. Work is partitioned into groups and
+//! row bands which don't depend on the number of threads, and each sample is computed in a fixed
+//! order, so renders are bit-identical regardless of the thread pool, including the
+//! single-threaded one ([`JxlThreadPool::none`]).
+//!
+//! Applications which use other executors can drive decoding parallelism without compiling in
+//! Rayon, by implementing [`JxlSpawner`] and creating a pool with
+//! [`JxlThreadPool::with_spawner`].
+//!
+//! SIMD implementations may produce slightly different results depending on CPU features. Use
+//! [`JxlImageBuilder::force_scalar`] to get identical results across platforms.
+//!
 //! # Feature flags
 //! - `rayon`: Enable multithreading with Rayon. (*default*)
+//! - `jxl-cms`: Enable pure Rust CMS which supports LUT-based ICC profiles. (*default*)
+//! - `jxl-encode`: Enable lossless JPEG XL encoder [`JxlEncoder`].
 //! - `image`: Enable integration with `image` crate.
 //! - `lcms2`: Enable integration with Little CMS 2.
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+use std::borrow::Cow;
 use std::sync::Arc;
 
-use jxl_bitstream::{Bitstream, ContainerDetectingReader, ParseEvent};
-use jxl_frame::FrameContext;
+use jxl_bitstream::{
+    Bitstream, ContainerDetectingReader, Incremental, IncrementalBuffer, ParseEvent,
+};
 use jxl_image::BitDepth;
 use jxl_oxide_common::{Bundle, Name};
 use jxl_render::ImageBuffer;
@@ -157,30 +181,72 @@ use jxl_render::ImageWithRegion;
 use jxl_render::Region;
 use jxl_render::{IndexedFrame, RenderContext};
 
+pub use jxl_bitstream::container;
+#[cfg(feature = "jxl-cms")]
+pub use jxl_cms::PureRustCms;
 pub use jxl_color::header as color;
 pub use jxl_color::{
-    ColorEncodingWithProfile, ColorManagementSystem, EnumColourEncoding, NullCms, RenderingIntent,
+    ColorEncodingWithProfile, ColorManagementSystem, EnumColourEncoding, GamutStats,
+    HlgDisplayParams, NullCms, PreparedTransform, RenderingIntent, ToneMappingOptions,
+};
+#[cfg(feature = "jxl-encode")]
+pub use jxl_encode as encode;
+#[cfg(feature = "jxl-encode")]
+pub use jxl_encode::JxlEncoder;
+pub use jxl_frame::data::{
+    BlendingModeInformation, NoiseParameters, PatchBlendMode, PatchRef, PatchTarget,
 };
 pub use jxl_frame::header as frame;
 pub use jxl_frame::{Frame, FrameHeader};
-pub use jxl_grid::{AlignedGrid, AllocTracker};
+pub use jxl_grid::{
+    AlignedGrid, AllocCategory, AllocStats, AllocTracker, CategoryStats, MemoryLimit,
+};
 pub use jxl_image as image;
 pub use jxl_image::{ExtraChannelType, ImageHeader};
 pub use jxl_jbr as jpeg_bitstream;
-pub use jxl_threadpool::JxlThreadPool;
+pub use jxl_render::{
+    CancellationToken, DecodeWarning, Error as RenderError, Spline, UnsupportedFeature,
+};
+pub use jxl_threadpool::{JxlSpawner, JxlThreadPool};
 
+mod analysis;
 mod aux_box;
+mod diff;
+mod error;
+#[cfg(feature = "export")]
+pub mod export;
 mod fb;
 pub mod integration;
 #[cfg(feature = "lcms2")]
 mod lcms2;
+mod options;
+mod recovery;
+mod snapshot;
+#[cfg(feature = "stats")]
+mod stats;
 
 #[cfg(feature = "lcms2")]
 pub use self::lcms2::Lcms2;
-pub use aux_box::{AuxBoxData, AuxBoxList, RawExif};
-pub use fb::{FrameBuffer, FrameBufferSample, ImageStream};
+pub use analysis::FrameFeatures;
+pub use aux_box::{
+    AuxBoxData, AuxBoxList, ExifField, ExifFields, FrameIndexBox, FrameIndexEntry, GainMap,
+    RawExif, RawGainMap,
+};
+pub use diff::{ChannelDiff, RenderDiffStats};
+pub use error::{DecodeStage, Error, MemoryError};
+pub use fb::{
+    AlphaMode, FrameBuffer, FrameBufferSample, ImageStream, IntegerFrameBuffer, IntegerSamples,
+};
+pub use jxl_frame::data::{Toc, TocGroup, TocGroupKind};
+#[cfg(feature = "stats")]
+pub use jxl_render::FrameStats;
+pub use options::DecodeOptions;
+pub use recovery::{BestEffortRender, Coverage, SampleCoverage};
+pub use snapshot::DecoderSnapshot;
+#[cfg(feature = "stats")]
+pub use stats::FrameDecoderStats;
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+pub type Result<T> = std::result::Result<T, Error>;
 
 #[cfg(feature = "rayon")]
 fn default_pool() -> JxlThreadPool {
@@ -196,7 +262,21 @@ fn default_pool() -> JxlThreadPool {
 #[derive(Debug, Default)]
 pub struct JxlImageBuilder {
     pool: Option<JxlThreadPool>,
+    max_concurrency: Option<usize>,
     tracker: Option<AllocTracker>,
+    memory_limit: Option<MemoryLimit>,
+    lz77_distance_limit: Option<u32>,
+    cancellation_token: Option<CancellationToken>,
+    error_isolation: bool,
+    skip_corrupt_groups: bool,
+    downscale_target: u8,
+    skip_restoration_filters: bool,
+    skip_extra_channels: bool,
+    max_output_dimension: Option<u32>,
+    render_cache_budget: Option<usize>,
+    force_scalar: bool,
+    lenient_container: bool,
+    image_options: Option<DecodeOptions>,
 }
 
 impl JxlImageBuilder {
@@ -206,19 +286,245 @@ impl JxlImageBuilder {
         self
     }
 
+    /// Limits the number of tasks of this image running at the same time on the thread pool.
+    ///
+    /// This is useful when the thread pool is shared with the rest of the application, e.g. to
+    /// keep a thumbnail decode from saturating every core. The limit applies to this image only,
+    /// even if other images use the same pool. See [`JxlThreadPool::with_max_concurrency`].
+    pub fn max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = Some(max);
+        self
+    }
+
     /// Sets an allocation tracker.
     pub fn alloc_tracker(mut self, tracker: AllocTracker) -> Self {
         self.tracker = Some(tracker);
         self
     }
 
+    /// Sets the memory limit for buffers other than sample grids, in bytes.
+    ///
+    /// Allocation tracker set by [`alloc_tracker`][Self::alloc_tracker] tracks sample grids only.
+    /// This limit bounds buffers whose sizes are controlled by the bitstream, namely compressed
+    /// frame data, embedded ICC profile and frame names, so that malformed TOC or ICC header, or
+    /// thousands of distinct frame names, cannot make the decoder reserve huge amount of memory.
+    /// Exceeding the limit results in [`Error::MemoryLimit`] with
+    /// [`MemoryError::LimitExceeded`].
+    pub fn memory_limit_bytes(mut self, memory_limit_bytes: usize) -> Self {
+        self.memory_limit = Some(MemoryLimit::new(memory_limit_bytes));
+        self
+    }
+
+    /// Sets the maximum LZ77 distance allowed in Modular streams.
+    ///
+    /// LZ77 distances of up to 1 MiB symbols are allowed by the specification, which requires
+    /// entropy decoders to keep large windows. With a limit set, decoders keep windows only as
+    /// large as the limit, and frames using larger distances fail to render. The limit of 0
+    /// rejects any LZ77 copies. With `stats` feature enabled, actual usage of LZ77 is reported by
+    /// decoding statistics of each frame.
+    pub fn lz77_distance_limit(mut self, limit: u32) -> Self {
+        self.lz77_distance_limit = Some(limit);
+        self
+    }
+
+    /// Sets a cancellation token, which can be used to abort rendering.
+    ///
+    /// The token is checked between groups, so that applications such as server-side thumbnailers
+    /// can bound the time spent on pathological images, either by cancelling the token from
+    /// another thread or by creating one with [`CancellationToken::with_deadline`]. Rendering a
+    /// frame after cancellation fails with [`jxl_render::Error::Cancelled`]; GUI applications can
+    /// abandon a render this way, and resume it later after [resetting][CancellationToken::reset]
+    /// the token.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Sets whether to isolate errors of corrupt frames in animations.
+    ///
+    /// By default, a keyframe which fails to render makes every subsequent keyframe blended on top
+    /// of it fail too. With error isolation enabled, such a keyframe is substituted with the canvas
+    /// of the previous keyframe, so that most of the animation remains viewable. The original
+    /// error can be retrieved with [`JxlImage::substituted_error`].
+    ///
+    /// Errors in frame headers still stop loading, as boundaries of subsequent frames are unknown.
+    pub fn error_isolation(mut self, error_isolation: bool) -> Self {
+        self.error_isolation = error_isolation;
+        self
+    }
+
+    /// Sets whether to treat corrupt pass groups as missing, instead of failing the whole frame.
+    ///
+    /// With this enabled, a pass group which fails to decode is rendered from lower-frequency
+    /// data, namely the LF image and passes decoded before the corrupt one, which is useful for
+    /// damaged archives. Skipped groups are reported by [`Render::corrupt_groups`]. Corrupt
+    /// global sections and LF groups still fail the frame.
+    pub fn skip_corrupt_groups(mut self, skip: bool) -> Self {
+        self.skip_corrupt_groups = skip;
+        self
+    }
+
+    /// Sets the downscaling factor the image is going to be displayed with, such as 2, 4 or 8.
+    ///
+    /// VarDCT images skip decoding HF coefficients which are not needed to reach the given
+    /// factor. With the factor of 8 or more, only the 8x downsampled LF image is decoded, which
+    /// makes it suitable for fast thumbnail generation. Rendered images keep the original
+    /// dimensions, so they should be downscaled by the application.
+    pub fn downscale_target(mut self, downscale_target: u8) -> Self {
+        self.downscale_target = downscale_target;
+        self
+    }
+
+    /// Sets whether to skip restoration filters, namely edge-preserving filter and Gabor-like
+    /// filter.
+    ///
+    /// Skipping restoration filters makes rendering faster at the cost of visible block
+    /// boundaries and ringing, which is usually acceptable for previews and small thumbnails.
+    pub fn skip_restoration_filters(mut self, skip: bool) -> Self {
+        self.skip_restoration_filters = skip;
+        self
+    }
+
+    /// Sets whether to skip auxiliary extra channels, such as depth, thermal and CFA channels.
+    ///
+    /// Skipped channels are not included in renders, saving memory. If every extra channel of
+    /// the image is auxiliary, extra channels of VarDCT frames are not decoded at all. Alpha,
+    /// black, spot colour and non-optional channels are always rendered.
+    pub fn skip_extra_channels(mut self, skip: bool) -> Self {
+        self.skip_extra_channels = skip;
+        self
+    }
+
+    /// Caps the dimensions of the output, selecting the downscaling factor automatically.
+    ///
+    /// When the image header is parsed, the smallest power of two factor which makes both width
+    /// and height fit within `max_output_dimension` is selected, and used as the
+    /// [downscaling target][Self::downscale_target] so that unneeded HF coefficients are skipped.
+    /// With the factor of 8 or larger, VarDCT keyframes which can be rendered on their own are
+    /// rendered from the LF image only, at 1/8 of the original dimensions.
+    ///
+    /// Use [`Render::image_scaled`] to retrieve images downsampled by the remaining factor, and
+    /// [`JxlImage::output_downscale`] to get the selected factor.
+    pub fn max_output_dimension(mut self, max_output_dimension: u32) -> Self {
+        self.max_output_dimension = Some(max_output_dimension);
+        self
+    }
+
+    /// Bounds the total size of rendered frames cached by the decoder, in bytes.
+    ///
+    /// The decoder caches renders of keyframes and frames they depend on, which would hold every
+    /// decoded frame of a long animation in memory. With a budget set, least recently rendered
+    /// frames are evicted when the cache exceeds the budget, and rendered again from retained frame
+    /// data on demand. The most recently rendered keyframe is always kept, so the budget may be
+    /// exceeded by a single large frame.
+    pub fn render_cache_budget(mut self, bytes: usize) -> Self {
+        self.render_cache_budget = Some(bytes);
+        self
+    }
+
+    /// Forces portable scalar code paths instead of SIMD implementations.
+    ///
+    /// SIMD implementations may produce slightly different results depending on CPU features, such
+    /// as availability of FMA. Forcing scalar code paths makes it easier to produce reference
+    /// images across platforms. The thread pool doesn't affect results, as renders are
+    /// bit-identical with any number of threads.
+    ///
+    /// The setting applies to images created by this builder only.
+    pub fn force_scalar(mut self, force_scalar: bool) -> Self {
+        self.force_scalar = force_scalar;
+        self
+    }
+
+    /// Sets whether to recover from common mistakes in containers, instead of returning errors.
+    ///
+    /// Some encoders and editing tools produce containers with partial codestream boxes out of
+    /// order, boxes too small to be valid, or garbage after the last box. Enabling this reads such
+    /// files, logging warnings instead. See [`ContainerDetectingReader::set_lenient`] for details.
+    pub fn lenient_container(mut self, lenient: bool) -> Self {
+        self.lenient_container = lenient;
+        self
+    }
+
+    /// Applies decoding options.
+    ///
+    /// Decoder-level options, such as the number of threads and memory limits, override values
+    /// set by other builder methods. Image-level options, such as cropping region and target
+    /// color encoding, are applied with [`JxlImage::apply_options`] when the image is initialized.
+    pub fn options(mut self, options: DecodeOptions) -> Self {
+        #[cfg(feature = "rayon")]
+        if let Some(num_threads) = options.num_threads {
+            self.pool = Some(JxlThreadPool::rayon(Some(num_threads)));
+        }
+        if let Some(max) = options.max_concurrency {
+            self.max_concurrency = Some(max);
+        }
+        if options.deterministic {
+            self.pool = Some(JxlThreadPool::none());
+            self.force_scalar = true;
+        }
+        if let Some(limit) = options.approx_memory_limit {
+            self.tracker = Some(AllocTracker::with_limit(limit));
+        }
+        if let Some(limit) = options.memory_limit_bytes {
+            self.memory_limit = Some(MemoryLimit::new(limit));
+        }
+        if let Some(limit) = options.lz77_distance_limit {
+            self.lz77_distance_limit = Some(limit);
+        }
+        if options.downscale_target != 0 {
+            self.downscale_target = options.downscale_target;
+        }
+        if options.error_isolation {
+            self.error_isolation = true;
+        }
+        if options.skip_corrupt_groups {
+            self.skip_corrupt_groups = true;
+        }
+        if options.skip_restoration_filters {
+            self.skip_restoration_filters = true;
+        }
+        if options.skip_extra_channels {
+            self.skip_extra_channels = true;
+        }
+        if let Some(max_output_dimension) = options.max_output_dimension {
+            self.max_output_dimension = Some(max_output_dimension);
+        }
+        if let Some(budget) = options.render_cache_budget {
+            self.render_cache_budget = Some(budget);
+        }
+        if options.lenient_container {
+            self.lenient_container = true;
+        }
+        self.image_options = Some(options);
+        self
+    }
+
     /// Consumes the builder, and creates an empty, uninitialized JPEG XL image decoder.
     pub fn build_uninit(self) -> UninitializedJxlImage {
+        let mut pool = self.pool.unwrap_or_else(default_pool);
+        if let Some(max) = self.max_concurrency {
+            pool = pool.with_max_concurrency(max);
+        }
+        let mut reader = ContainerDetectingReader::new();
+        reader.set_lenient(self.lenient_container);
         UninitializedJxlImage {
-            pool: self.pool.unwrap_or_else(default_pool),
+            pool,
             tracker: self.tracker,
-            reader: ContainerDetectingReader::new(),
+            memory_limit: self.memory_limit,
+            lz77_distance_limit: self.lz77_distance_limit,
+            cancellation_token: self.cancellation_token,
+            error_isolation: self.error_isolation,
+            skip_corrupt_groups: self.skip_corrupt_groups,
+            force_scalar: self.force_scalar,
+            downscale_target: self.downscale_target,
+            skip_restoration_filters: self.skip_restoration_filters,
+            skip_extra_channels: self.skip_extra_channels,
+            max_output_dimension: self.max_output_dimension,
+            render_cache_budget: self.render_cache_budget,
+            image_options: self.image_options,
+            reader,
             buffer: Vec::new(),
+            image_header: None,
             aux_boxes: AuxBoxList::new(),
         }
     }
@@ -302,8 +608,22 @@ impl JxlImageBuilder {
 pub struct UninitializedJxlImage {
     pool: JxlThreadPool,
     tracker: Option<AllocTracker>,
+    memory_limit: Option<MemoryLimit>,
+    lz77_distance_limit: Option<u32>,
+    cancellation_token: Option<CancellationToken>,
+    error_isolation: bool,
+    skip_corrupt_groups: bool,
+    force_scalar: bool,
+    downscale_target: u8,
+    skip_restoration_filters: bool,
+    skip_extra_channels: bool,
+    max_output_dimension: Option<u32>,
+    render_cache_budget: Option<usize>,
+    image_options: Option<DecodeOptions>,
     reader: ContainerDetectingReader,
     buffer: Vec<u8>,
+    /// Image header parsed so far, with its size in bits.
+    image_header: Option<(Arc<ImageHeader>, usize)>,
     aux_boxes: AuxBoxList,
 }
 
@@ -313,13 +633,16 @@ impl UninitializedJxlImage {
     /// Returns total consumed bytes from the buffer.
     pub fn feed_bytes(&mut self, buf: &[u8]) -> Result<usize> {
         for event in self.reader.feed_bytes(buf) {
-            match event? {
+            let event = event.map_err(|e| Error::from(e).at_stage(DecodeStage::Container))?;
+            match event {
                 ParseEvent::BitstreamKind(_) => {}
                 ParseEvent::Codestream(buf) => {
                     self.buffer.extend_from_slice(buf);
                 }
                 aux_box_event => {
-                    self.aux_boxes.handle_event(aux_box_event)?;
+                    self.aux_boxes
+                        .handle_event(aux_box_event)
+                        .map_err(|e| e.at_stage(DecodeStage::AuxBox))?;
                 }
             }
         }
@@ -339,46 +662,61 @@ impl UninitializedJxlImage {
     /// - `Ok(InitializeResult::NeedMoreData(_))` if the data was not enough, and
     /// - `Err(_)` if there was a decode error during the initialization, meaning invalid bitstream
     ///   was given.
-    pub fn try_init(mut self) -> Result<InitializeResult> {
+    pub fn try_init(self) -> Result<InitializeResult> {
+        self.try_init_inner()
+            .map_err(|e| e.at_stage(DecodeStage::ImageHeader))
+    }
+
+    fn try_init_inner(mut self) -> Result<InitializeResult> {
         let mut bitstream = Bitstream::new(&self.buffer);
-        let image_header = match ImageHeader::parse(&mut bitstream, ()) {
-            Ok(x) => x,
-            Err(e) if e.unexpected_eof() => {
-                return Ok(InitializeResult::NeedMoreData(self));
-            }
-            Err(e) => {
-                return Err(e.into());
-            }
+        let image_header = if let Some((image_header, header_bits)) = &self.image_header {
+            bitstream.skip_bits(*header_bits)?;
+            image_header.clone()
+        } else {
+            let image_header = match ImageHeader::parse(&mut bitstream, ()) {
+                Ok(x) => Arc::new(x),
+                Err(e) if e.unexpected_eof() => {
+                    return Ok(InitializeResult::NeedMoreData(self));
+                }
+                Err(e) => {
+                    return Err(e.into());
+                }
+            };
+            self.image_header = Some((image_header.clone(), bitstream.num_read_bits()));
+            image_header
         };
 
         let embedded_icc = if image_header.metadata.colour_encoding.want_icc() {
-            let icc = match jxl_color::icc::read_icc(&mut bitstream) {
+            let memory_limit = self.memory_limit.as_ref();
+            let icc = match jxl_color::icc::read_icc_with_limit(&mut bitstream, memory_limit) {
                 Ok(x) => x,
                 Err(e) if e.unexpected_eof() => {
                     return Ok(InitializeResult::NeedMoreData(self));
                 }
                 Err(e) => {
-                    return Err(e.into());
+                    return Err(jxl_render::Error::from(e).into());
                 }
             };
             tracing::debug!("Image has an embedded ICC profile");
-            let icc = jxl_color::icc::decode_icc(&icc)?;
+            let icc = jxl_color::icc::decode_icc_with_limit(&icc, memory_limit)
+                .map_err(jxl_render::Error::from)?;
             Some(icc)
         } else {
             None
         };
         bitstream.zero_pad_to_byte()?;
 
-        let image_header = Arc::new(image_header);
-        let skip_bytes = if image_header.metadata.preview.is_some() {
-            let frame = match Frame::parse(
-                &mut bitstream,
-                FrameContext {
-                    image_header: image_header.clone(),
-                    tracker: self.tracker.as_ref(),
-                    pool: self.pool.clone(),
-                },
-            ) {
+        let header_bytes = bitstream.num_read_bits() / 8;
+        let mut preview = None;
+        let mut preview_bytes = 0usize;
+        if let Some(preview_header) = &image_header.metadata.preview {
+            // The preview frame is sized as specified in the preview header.
+            let mut header = ImageHeader::clone(&image_header);
+            header.size.width = preview_header.width;
+            header.size.height = preview_header.height;
+            let mut ctx = self.build_render_context(Arc::new(header), embedded_icc.clone())?;
+
+            let frame = match ctx.load_frame_header(&mut bitstream) {
                 Ok(x) => x,
                 Err(e) if e.unexpected_eof() => {
                     return Ok(InitializeResult::NeedMoreData(self));
@@ -388,33 +726,48 @@ impl UninitializedJxlImage {
                 }
             };
 
-            let bytes_read = bitstream.num_read_bits() / 8;
-            let x = frame.toc().total_byte_size();
-            if self.buffer.len() < bytes_read + x {
+            let frame_header_bytes = bitstream.num_read_bits() / 8 - header_bytes;
+            let buf = &self.buffer[header_bytes + frame_header_bytes..];
+            let remaining = frame.feed_bytes(buf)?;
+            if !frame.is_loading_done() {
                 return Ok(InitializeResult::NeedMoreData(self));
             }
+            preview_bytes = frame_header_bytes + (buf.len() - remaining.len());
+            ctx.finalize_current_frame();
+            preview = Some(ctx);
+        }
 
-            x
-        } else {
-            0usize
+        let bytes_read = header_bytes + preview_bytes;
+        let codestream_header = self.buffer.drain(..bytes_read).collect::<Vec<_>>();
+
+        let downscale_target = self.downscale_target as u32;
+        let output_downscale = match self.max_output_dimension {
+            Some(max_output_dimension) => {
+                let width = image_header.width_with_orientation();
+                let height = image_header.height_with_orientation();
+                let max_output_dimension = max_output_dimension.max(1);
+                let output_downscale = (0..u32::BITS)
+                    .map(|shift| 1u32 << shift)
+                    .find(|&factor| {
+                        width.div_ceil(factor) <= max_output_dimension
+                            && height.div_ceil(factor) <= max_output_dimension
+                    })
+                    .unwrap_or(1 << (u32::BITS - 1));
+                tracing::debug!(
+                    max_output_dimension,
+                    output_downscale,
+                    "Selected downscaling factor"
+                );
+                self.downscale_target = self.downscale_target.max(output_downscale.min(8) as u8);
+                output_downscale
+            }
+            None => 1,
         };
 
-        let bytes_read = bitstream.num_read_bits() / 8 + skip_bytes;
-        self.buffer.drain(..bytes_read);
-
         let render_spot_color = !image_header.metadata.grayscale();
-
-        let mut builder = RenderContext::builder().pool(self.pool.clone());
-        if let Some(icc) = embedded_icc {
-            builder = builder.embedded_icc(icc);
-        }
-        if let Some(tracker) = self.tracker {
-            builder = builder.alloc_tracker(tracker);
-        }
-        #[cfg_attr(not(feature = "lcms2"), allow(unused_mut))]
-        let mut ctx = builder.build(image_header.clone())?;
-        #[cfg(feature = "lcms2")]
-        ctx.set_cms(Lcms2);
+        let spot_colors = vec![SpotColorSettings::default(); image_header.metadata.ec_info.len()];
+        let active_alpha = image_header.metadata.alpha();
+        let ctx = self.build_render_context(image_header.clone(), embedded_icc)?;
 
         let mut image = JxlImage {
             pool: self.pool.clone(),
@@ -422,18 +775,73 @@ impl UninitializedJxlImage {
             image_header,
             ctx,
             render_spot_color,
+            spot_colors,
+            active_alpha,
+            render_linear: false,
+            retain_bitstream: true,
+            pending_transfer: None,
+            downscale_target,
+            output_downscale,
+            preview,
             inner: JxlImageInner {
                 end_of_image: false,
                 buffer: Vec::new(),
+                header_buffer: IncrementalBuffer::new(),
                 buffer_offset: bytes_read,
                 frame_offsets: Vec::new(),
+                frame_header_sizes: Vec::new(),
                 aux_boxes: self.aux_boxes,
+                codestream_header,
+                saved_references: None,
             },
         };
-        image.inner.feed_bytes_inner(&mut image.ctx, &self.buffer)?;
+        if let Some(options) = &self.image_options {
+            image.apply_options(options)?;
+        }
+        image
+            .inner
+            .feed_bytes_inner(&mut image.ctx, &image.image_header, &self.buffer)?;
 
         Ok(InitializeResult::Initialized(image))
     }
+
+    fn build_render_context(
+        &self,
+        image_header: Arc<ImageHeader>,
+        embedded_icc: Option<Vec<u8>>,
+    ) -> Result<RenderContext> {
+        let mut builder = RenderContext::builder()
+            .pool(self.pool.clone())
+            .error_isolation(self.error_isolation)
+            .skip_corrupt_groups(self.skip_corrupt_groups)
+            .force_scalar(self.force_scalar)
+            .downscale_target(self.downscale_target as u32)
+            .skip_restoration_filters(self.skip_restoration_filters)
+            .skip_extra_channels(self.skip_extra_channels);
+        if let Some(icc) = embedded_icc {
+            builder = builder.embedded_icc(icc);
+        }
+        if let Some(tracker) = &self.tracker {
+            builder = builder.alloc_tracker(tracker.clone());
+        }
+        if let Some(memory_limit) = &self.memory_limit {
+            builder = builder.memory_limit(memory_limit.clone());
+        }
+        if let Some(limit) = self.lz77_distance_limit {
+            builder = builder.lz77_distance_limit(limit);
+        }
+        if let Some(token) = &self.cancellation_token {
+            builder = builder.cancellation_token(token.clone());
+        }
+        if let Some(budget) = self.render_cache_budget {
+            builder = builder.render_cache_budget(budget);
+        }
+        #[cfg_attr(not(feature = "lcms2"), allow(unused_mut))]
+        let mut ctx = builder.build(image_header)?;
+        #[cfg(feature = "lcms2")]
+        ctx.set_cms(Lcms2);
+        Ok(ctx)
+    }
 }
 
 /// Initialization result from [`UninitializedJxlImage::try_init`].
@@ -445,6 +853,14 @@ pub enum InitializeResult {
 }
 
 /// JPEG XL image.
+///
+/// # Memory usage
+/// The decoder retains compressed data of every group of loaded frames, so that any keyframe can
+/// be rendered again with different options, such as cropping region or color encoding, without
+/// re-reading the input. This costs memory roughly equal to the size of the codestream, in
+/// addition to the rendered images cached by the decoder. Use
+/// [`set_retain_bitstream`][Self::set_retain_bitstream] to release compressed data of rendered
+/// keyframes if they don't need to be rendered again.
 #[derive(Debug)]
 pub struct JxlImage {
     pool: JxlThreadPool,
@@ -452,6 +868,14 @@ pub struct JxlImage {
     image_header: Arc<ImageHeader>,
     ctx: RenderContext,
     render_spot_color: bool,
+    spot_colors: Vec<SpotColorSettings>,
+    active_alpha: Option<usize>,
+    render_linear: bool,
+    retain_bitstream: bool,
+    pending_transfer: Option<color::TransferFunction>,
+    downscale_target: u32,
+    output_downscale: u32,
+    preview: Option<RenderContext>,
     inner: JxlImageInner,
 }
 
@@ -477,16 +901,24 @@ impl JxlImage {
     /// Returns total consumed bytes from the buffer.
     pub fn feed_bytes(&mut self, buf: &[u8]) -> Result<usize> {
         for event in self.reader.feed_bytes(buf) {
-            match event? {
+            let event = event.map_err(|e| Error::from(e).at_stage(DecodeStage::Container))?;
+            match event {
                 ParseEvent::BitstreamKind(_) => {}
                 ParseEvent::Codestream(buf) => {
-                    self.inner.feed_bytes_inner(&mut self.ctx, buf)?;
+                    self.inner
+                        .feed_bytes_inner(&mut self.ctx, &self.image_header, buf)?;
                 }
                 aux_box_event => {
-                    self.inner.aux_boxes.handle_event(aux_box_event)?;
+                    self.inner
+                        .aux_boxes
+                        .handle_event(aux_box_event)
+                        .map_err(|e| e.at_stage(DecodeStage::AuxBox))?;
                 }
             }
         }
+        if !self.retain_bitstream {
+            self.ctx.release_rendered_frame_data();
+        }
         Ok(self.reader.previous_consumed_bytes())
     }
 
@@ -494,8 +926,10 @@ impl JxlImage {
     ///
     /// This is automatically done if `open()` or `read()` is used to decode the image.
     pub fn finalize(&mut self) -> Result<()> {
-        self.inner.aux_boxes.eof()?;
-        Ok(())
+        self.inner
+            .aux_boxes
+            .eof()
+            .map_err(|e| e.at_stage(DecodeStage::AuxBox))
     }
 }
 
@@ -547,17 +981,21 @@ impl JxlImage {
         encoding.encoding().cicp()
     }
 
+    /// Returns the color encoding of rendered images, if it can be described with enum values.
+    pub fn rendered_color_encoding(&self) -> Option<EnumColourEncoding> {
+        let encoding = self.ctx.requested_color_encoding();
+        match encoding.encoding() {
+            jxl_color::ColourEncoding::Enum(encoding) => Some(encoding.clone()),
+            jxl_color::ColourEncoding::IccProfile(_) => None,
+        }
+    }
+
     /// Returns the pixel format of the rendered image.
     pub fn pixel_format(&self) -> PixelFormat {
         let encoding = self.ctx.requested_color_encoding();
         let is_grayscale = encoding.is_grayscale();
         let has_black = encoding.is_cmyk();
-        let mut has_alpha = false;
-        for ec_info in &self.image_header.metadata.ec_info {
-            if ec_info.is_alpha() {
-                has_alpha = true;
-            }
-        }
+        let has_alpha = self.active_alpha.is_some();
 
         match (is_grayscale, has_black, has_alpha) {
             (false, false, false) => PixelFormat::Rgb,
@@ -597,6 +1035,155 @@ impl JxlImage {
         self
     }
 
+    /// Returns the indices of spot colour channels among extra channels.
+    pub fn spot_color_channels(&self) -> Vec<usize> {
+        self.image_header
+            .metadata
+            .ec_info
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, ec_info)| {
+                matches!(ec_info.ty, ExtraChannelType::SpotColour { .. }).then_some(idx)
+            })
+            .collect()
+    }
+
+    /// Returns the rendering settings of the spot colour channel at the given extra channel
+    /// index, or `None` if the channel is not a spot colour channel.
+    pub fn spot_color_settings(&self, ec_index: usize) -> Option<SpotColorSettings> {
+        self.is_spot_color_channel(ec_index)
+            .then(|| self.spot_colors[ec_index])
+    }
+
+    /// Sets whether the spot colour channel at the given extra channel index is rendered.
+    ///
+    /// This only has effect if spot colour channels are rendered; see
+    /// [`set_render_spot_color`][Self::set_render_spot_color].
+    pub fn set_spot_color_enabled(&mut self, ec_index: usize, enabled: bool) -> &mut Self {
+        if !self.is_spot_color_channel(ec_index) {
+            tracing::warn!(ec_index, "Extra channel is not a spot colour channel");
+            return self;
+        }
+        self.spot_colors[ec_index].enabled = enabled;
+        self
+    }
+
+    /// Overrides solidity of the spot colour channel at the given extra channel index, or
+    /// restores the signalled solidity if `None` is given.
+    pub fn set_spot_color_solidity(&mut self, ec_index: usize, solidity: Option<f32>) -> &mut Self {
+        if !self.is_spot_color_channel(ec_index) {
+            tracing::warn!(ec_index, "Extra channel is not a spot colour channel");
+            return self;
+        }
+        self.spot_colors[ec_index].solidity = solidity;
+        self
+    }
+
+    fn is_spot_color_channel(&self, ec_index: usize) -> bool {
+        self.image_header
+            .metadata
+            .ec_info
+            .get(ec_index)
+            .is_some_and(|ec_info| matches!(ec_info.ty, ExtraChannelType::SpotColour { .. }))
+    }
+
+    /// Returns the strength of synthesized noise, relative to the strength signalled in the
+    /// bitstream.
+    #[inline]
+    pub fn noise_strength(&self) -> f32 {
+        self.ctx.noise_strength()
+    }
+
+    /// Sets the strength of synthesized noise, relative to the strength signalled in the
+    /// bitstream.
+    ///
+    /// Strength of 1.0 renders noise as signalled, and 0.0 skips noise synthesis entirely, which
+    /// is useful for downscaled thumbnails. Negative strength and NaN are treated as zero. Cached
+    /// renders are discarded.
+    pub fn set_noise_strength(&mut self, strength: f32) -> &mut Self {
+        if let Some(preview) = &mut self.preview {
+            preview.set_noise_strength(strength);
+        }
+        self.ctx.set_noise_strength(strength);
+        self
+    }
+
+    /// Sets whether the noise will be synthesized.
+    ///
+    /// This is a shorthand of [`set_noise_strength`][Self::set_noise_strength] with strength of
+    /// either 1.0 or 0.0.
+    #[inline]
+    pub fn set_render_noise(&mut self, render_noise: bool) -> &mut Self {
+        self.set_noise_strength(if render_noise { 1.0 } else { 0.0 })
+    }
+
+    /// Returns whether compressed data of rendered keyframes is retained.
+    #[inline]
+    pub fn retain_bitstream(&self) -> bool {
+        self.retain_bitstream
+    }
+
+    /// Sets whether compressed data of rendered keyframes is retained.
+    ///
+    /// By default, compressed data of every frame is kept so that keyframes can be rendered again.
+    /// If disabled, compressed data of keyframes which are rendered and not referenced by other
+    /// frames, such as a still image after the first render, is released; immediately upon calling
+    /// this method, and when more data is fed afterwards. Rendering these keyframes keeps returning
+    /// the cached result, but rendering them again with different options such as cropping region
+    /// or color encoding fails with [`jxl_render::Error::FrameDataReleased`]. Analysis methods
+    /// reading frame data, such as [`frame_features`][Self::frame_features] and JPEG
+    /// reconstruction, don't work for released keyframes either.
+    pub fn set_retain_bitstream(&mut self, retain: bool) -> &mut Self {
+        self.retain_bitstream = retain;
+        if !retain {
+            let count = self.ctx.release_rendered_frame_data();
+            tracing::debug!(count, "Released compressed data of rendered keyframes");
+        }
+        self
+    }
+
+    /// Returns the indices of alpha channels among extra channels.
+    pub fn alpha_channels(&self) -> Vec<usize> {
+        self.image_header
+            .metadata
+            .ec_info
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, ec_info)| ec_info.is_alpha().then_some(idx))
+            .collect()
+    }
+
+    /// Returns the extra channel index of the alpha channel included in the output, if any.
+    ///
+    /// Defaults to the first alpha channel of the image.
+    #[inline]
+    pub fn active_alpha(&self) -> Option<usize> {
+        self.active_alpha
+    }
+
+    /// Selects the alpha channel included in the output by its extra channel index, or excludes
+    /// alpha from the output if `None` is given.
+    ///
+    /// The selected channel is used by [`Render::stream`], [`pixel_format`][Self::pixel_format]
+    /// and integrations. Blending of frames is not affected, as it uses alpha channels specified
+    /// by the frames themselves.
+    pub fn set_active_alpha(&mut self, ec_index: Option<usize>) -> &mut Self {
+        if let Some(idx) = ec_index {
+            let is_alpha = self
+                .image_header
+                .metadata
+                .ec_info
+                .get(idx)
+                .is_some_and(|ec_info| ec_info.is_alpha());
+            if !is_alpha {
+                tracing::warn!(ec_index = idx, "Extra channel is not an alpha channel");
+                return self;
+            }
+        }
+        self.active_alpha = ec_index;
+        self
+    }
+
     /// Returns the list of auxiliary boxes in the JPEG XL container.
     ///
     /// The list may contain Exif and XMP metadata.
@@ -604,6 +1191,91 @@ impl JxlImage {
         &self.inner.aux_boxes
     }
 
+    /// Returns the complete XMP packet stored in the JPEG XL container, if any.
+    ///
+    /// XMP metadata split into multiple XML boxes is reassembled into a single packet. See
+    /// [`AuxBoxList::joined_xml`].
+    pub fn xmp(&self) -> AuxBoxData<Cow<'_, [u8]>> {
+        self.aux_boxes().joined_xml()
+    }
+
+    /// Returns the gain map stored in the JPEG XL container, if any.
+    ///
+    /// The gain map image is decoded using the thread pool of this image.
+    pub fn gain_map(&self) -> Result<AuxBoxData<GainMap<'_>>> {
+        self.aux_boxes()
+            .first_gain_map()?
+            .map(|raw| GainMap::new(raw, self))
+            .transpose()
+    }
+
+    /// Returns the frame index stored in the JPEG XL container, if any.
+    ///
+    /// The frame index lists keyframes which can be decoded without preceding frames. See
+    /// [`keyframe_seek_point`][Self::keyframe_seek_point].
+    pub fn frame_index_box(&self) -> AuxBoxData<&FrameIndexBox> {
+        self.aux_boxes().frame_index()
+    }
+
+    /// Returns the indexed keyframe to start loading from in order to display the given keyframe,
+    /// or `None` if the image doesn't have a frame index.
+    ///
+    /// The keyframe doesn't need to be loaded. Players can pass the offset of the returned entry
+    /// to [`load_frame_at`][Self::load_frame_at], or start feeding the codestream from the offset,
+    /// and then skip keyframes until the target, instead of loading the whole animation from the
+    /// beginning.
+    pub fn keyframe_seek_point(&self, keyframe_index: usize) -> Option<FrameIndexEntry> {
+        let AuxBoxData::Data(frame_index) = self.frame_index_box() else {
+            return None;
+        };
+        frame_index.find_by_keyframe(keyframe_index).copied()
+    }
+
+    /// Returns the downscaling factor selected by [`JxlImageBuilder::max_output_dimension`] or
+    /// [`set_output_downscale`][Self::set_output_downscale], or 1 if the output is not scaled.
+    #[inline]
+    pub fn output_downscale(&self) -> u32 {
+        self.output_downscale
+    }
+
+    /// Sets the downscaling factor of the output, for reduced-resolution playback.
+    ///
+    /// Keyframes are rendered with only the HF passes needed to reach the factor, and
+    /// [`Render::image_scaled`] returns images downsampled by the factor. With the factor of 8 or
+    /// larger, VarDCT keyframes which can be rendered on their own are rendered from the LF image
+    /// only, at 1/8 of the original dimensions. This trades quality for rendering speed, so that
+    /// large animations can be played in real time on slow hardware. The factor of 1 restores
+    /// full-resolution rendering.
+    ///
+    /// The factor is rounded down to a power of two. This overrides the factor selected by
+    /// [`JxlImageBuilder::max_output_dimension`]. Cached renders are discarded if the factor
+    /// changes.
+    pub fn set_output_downscale(&mut self, factor: u32) -> &mut Self {
+        let factor = 1u32 << factor.max(1).ilog2();
+        self.output_downscale = factor;
+        self.ctx
+            .set_downscale_target(self.downscale_target.max(factor.min(8)));
+        self
+    }
+
+    /// Returns whether the keyframe can be rendered from its LF image to reach the output
+    /// downscaling factor, without blending with other frames or rendering extra channels.
+    fn renders_lf_only(&self, keyframe_index: usize) -> bool {
+        if self.output_downscale < 8 || !self.image_header.metadata.ec_info.is_empty() {
+            return false;
+        }
+        let Some(frame) = self.ctx.keyframe(keyframe_index) else {
+            return false;
+        };
+        let frame_header = frame.header();
+        frame_header.encoding == jxl_frame::header::Encoding::VarDct
+            && frame_header.blending_info.mode == jxl_frame::header::BlendMode::Replace
+            && frame_header.x0 == 0
+            && frame_header.y0 == 0
+            && frame_header.width == self.image_header.size.width
+            && frame_header.height == self.image_header.size.height
+    }
+
     /// Returns the number of currently loaded keyframes.
     #[inline]
     pub fn num_loaded_keyframes(&self) -> usize {
@@ -647,46 +1319,383 @@ impl JxlImage {
         self.ctx.frame(frame_idx)
     }
 
+    /// Returns decoded splines, patches and noise parameters of the frame, or `None` if LfGlobal
+    /// section of the frame is not loaded yet.
+    ///
+    /// Splines and patches are read-only vector data which the renderer rasterizes onto the frame,
+    /// and noise is synthesized on top of them. This method parses LfGlobal section again, so it's
+    /// relatively expensive.
+    pub fn frame_features(&self, frame_index: usize) -> Result<Option<FrameFeatures>> {
+        let Some(frame) = self.frame(frame_index) else {
+            return Ok(None);
+        };
+        FrameFeatures::new(frame).transpose()
+    }
+
     /// Returns the offset of frame within codestream, in bytes.
     pub fn frame_offset(&self, frame_index: usize) -> Option<usize> {
         self.inner.frame_offsets.get(frame_index).copied()
     }
 
-    /// Returns the thread pool used by the renderer.
-    #[inline]
-    pub fn pool(&self) -> &JxlThreadPool {
-        &self.pool
-    }
-
-    /// Returns the internal reader.
-    pub fn reader(&self) -> &ContainerDetectingReader {
-        &self.reader
-    }
-}
+    /// Returns compressed size statistics of currently loaded keyframes.
+    ///
+    /// Each keyframe accounts for itself and every preceding frame that is not displayed directly,
+    /// so the sum of the sizes is the number of bytes spent on frames up to the last loaded
+    /// keyframe.
+    pub fn frame_infos(&self) -> Vec<KeyframeInfo> {
+        let mut first_frame_index = 0;
+        (0..self.num_loaded_keyframes())
+            .filter_map(|keyframe_index| {
+                let keyframe = self.ctx.keyframe(keyframe_index)?;
+                let frame_index = keyframe.index();
+                let mut header_bytes = 0;
+                let mut group_bytes = 0;
+                for idx in first_frame_index..=frame_index {
+                    let frame = self.ctx.frame(idx)?;
+                    header_bytes += self.inner.frame_header_sizes[idx];
+                    group_bytes += frame.toc().total_byte_size();
+                }
 
-/// # Color management methods
-impl JxlImage {
-    /// Sets color management system implementation to be used by the renderer.
-    #[inline]
-    pub fn set_cms(&mut self, cms: impl ColorManagementSystem + Send + Sync + 'static) {
-        self.ctx.set_cms(cms);
+                let info = KeyframeInfo {
+                    keyframe_index,
+                    frame_index,
+                    first_frame_index,
+                    offset: self.inner.frame_offsets[first_frame_index],
+                    header_bytes,
+                    group_bytes,
+                    duration: keyframe.header().duration,
+                };
+                first_frame_index = frame_index + 1;
+                Some(info)
+            })
+            .collect()
     }
 
-    /// Requests the decoder to render in specific color encoding, described by an ICC profile.
+    /// Returns loading progress of the keyframe, or `None` if the keyframe does not exist.
     ///
-    /// # Errors
-    /// This function will return an error if it cannot parse the ICC profile.
-    pub fn request_icc(&mut self, icc_profile: &[u8]) -> Result<()> {
-        self.ctx
-            .request_color_encoding(ColorEncodingWithProfile::with_icc(icc_profile)?);
+    /// The keyframe currently being loaded has index of
+    /// [`num_loaded_keyframes`][Self::num_loaded_keyframes]. Progressive viewers may use this to
+    /// report how much detail is loaded, and to decide whether rendering the keyframe again is
+    /// worth it.
+    pub fn frame_progress(&self, keyframe_index: usize) -> Option<FrameProgress> {
+        let frame = self.ctx.keyframe(keyframe_index)?;
+        let toc = frame.toc();
+        let num_passes = frame.header().passes.num_passes;
+        // Frames with released data are loaded completely.
+        let num_loaded_groups = if frame.is_loading_done() {
+            usize::MAX
+        } else {
+            frame.num_loaded_groups()
+        };
+
+        let mut total_groups = 0usize;
+        let mut loaded_groups = 0usize;
+        let mut lf_loaded = true;
+        let mut pass_loaded = vec![true; num_passes as usize];
+        for (idx, group) in toc.iter_bitstream_order().enumerate() {
+            total_groups += 1;
+            if idx < num_loaded_groups {
+                loaded_groups += 1;
+                continue;
+            }
+            match group.kind {
+                TocGroupKind::GroupPass { pass_idx, .. } => {
+                    pass_loaded[pass_idx as usize] = false;
+                }
+                TocGroupKind::All => {
+                    lf_loaded = false;
+                    pass_loaded.fill(false);
+                }
+                TocGroupKind::LfGlobal | TocGroupKind::LfGroup(_) | TocGroupKind::HfGlobal => {
+                    lf_loaded = false;
+                }
+            }
+        }
+        let completed_passes = if lf_loaded {
+            pass_loaded.iter().take_while(|&&loaded| loaded).count() as u32
+        } else {
+            0
+        };
+
+        let total_bytes = toc.total_byte_size();
+        let loaded_bytes = if frame.is_loading_done() {
+            total_bytes
+        } else {
+            frame.loaded_bytes()
+        };
+
+        Some(FrameProgress {
+            keyframe_index,
+            num_passes,
+            completed_passes,
+            lf_loaded,
+            loaded_groups,
+            total_groups,
+            loaded_bytes,
+            total_bytes,
+        })
+    }
+
+    /// Returns an iterator of metadata of currently loaded frames, including frames that are not
+    /// displayed directly.
+    pub fn frames(&self) -> impl Iterator<Item = FrameInfo> + '_ {
+        let frame_index = self.frame_index_box().map(Some).unwrap_or(None);
+        (0..self.num_loaded_frames()).filter_map(move |index| {
+            let frame = self.ctx.frame(index)?;
+            let header = frame.header();
+            Some(FrameInfo {
+                index,
+                frame_type: header.frame_type,
+                is_keyframe: header.is_keyframe(),
+                blend_info: FrameBlendInfo::from_header(&self.image_header, header),
+                duration: header.duration,
+                offset: self.inner.frame_offsets[index],
+                header_bytes: self.inner.frame_header_sizes[index],
+                group_bytes: frame.toc().total_byte_size(),
+                is_indexed: frame_index.is_some_and(|frame_index| {
+                    frame_index.contains_offset(self.inner.frame_offsets[index] as u64)
+                }),
+            })
+        })
+    }
+
+    /// Returns how the keyframe is blended onto the canvas, or `None` if the keyframe is not
+    /// loaded.
+    ///
+    /// Keyframes composed of multiple frames report blending info of the last frame, which is
+    /// the one displayed. Use [`frames`][Self::frames] to inspect every frame.
+    pub fn keyframe_blend_info(&self, keyframe_index: usize) -> Option<FrameBlendInfo> {
+        let keyframe = self.ctx.keyframe(keyframe_index)?;
+        Some(FrameBlendInfo::from_header(
+            &self.image_header,
+            keyframe.header(),
+        ))
+    }
+
+    /// Returns the thread pool used by the renderer.
+    #[inline]
+    pub fn pool(&self) -> &JxlThreadPool {
+        &self.pool
+    }
+
+    /// Returns current and peak memory usage recorded by the allocation tracker, broken down by
+    /// [category][AllocCategory].
+    ///
+    /// Returns `None` if no tracker is set with
+    /// [`JxlImageBuilder::alloc_tracker`]. Peak usage covers the whole lifetime of the tracker,
+    /// so it includes allocations of other images if the tracker is shared.
+    pub fn alloc_stats(&self) -> Option<AllocStats> {
+        self.ctx.alloc_tracker().map(|tracker| tracker.stats())
+    }
+
+    /// Returns the internal reader.
+    pub fn reader(&self) -> &ContainerDetectingReader {
+        &self.reader
+    }
+}
+
+/// # Color management methods
+impl JxlImage {
+    /// Sets color management system implementation to be used by the renderer.
+    ///
+    /// If the CMS implements [`ColorManagementSystem::prepare_transform`], prepared transforms are
+    /// cached and reused across frames.
+    #[inline]
+    pub fn set_cms(&mut self, cms: impl ColorManagementSystem + Send + Sync + 'static) {
+        if let Some(preview) = &mut self.preview {
+            let cms = Arc::new(cms);
+            preview.set_cms(SharedCms(cms.clone()));
+            self.ctx.set_cms(SharedCms(cms));
+        } else {
+            self.ctx.set_cms(cms);
+        }
+    }
+
+    /// Returns whether rendering in the requested color encoding needs external CMS set by
+    /// [`set_cms`][Self::set_cms].
+    ///
+    /// If this returns `true` and no CMS is available, rendering will fail with an error whose
+    /// [unsupported feature][Error::unsupported_feature] is
+    /// [`UnsupportedFeature::ColorManagement`].
+    ///
+    /// # Errors
+    /// This function will return an error if the embedded ICC profile cannot be parsed.
+    pub fn requires_external_cms(&self) -> Result<bool> {
+        Ok(self.ctx.requires_external_cms()?)
+    }
+
+    /// Requests the decoder to render in specific color encoding, described by an ICC profile.
+    ///
+    /// If the profile describes a CMYK color space, the image is rendered in CMYK through the
+    /// external CMS, and [`pixel_format`][Self::pixel_format] returns [`PixelFormat::Cmyk`] or
+    /// [`PixelFormat::Cmyka`].
+    ///
+    /// # Errors
+    /// This function will return an error if it cannot parse the ICC profile.
+    pub fn request_icc(&mut self, icc_profile: &[u8]) -> Result<()> {
+        let encoding = ColorEncodingWithProfile::with_icc(icc_profile)?;
+        self.set_requested_encoding(encoding);
         Ok(())
     }
 
     /// Requests the decoder to render in specific color encoding, described by
     /// `EnumColourEncoding`.
+    ///
+    /// Custom white point, primaries and gamma values are supported; see
+    /// [`EnumColourEncoding::custom_rgb`].
     pub fn request_color_encoding(&mut self, color_encoding: EnumColourEncoding) {
-        self.ctx
-            .request_color_encoding(ColorEncodingWithProfile::new(color_encoding))
+        let encoding = ColorEncodingWithProfile::new(color_encoding);
+        self.set_requested_encoding(encoding);
+    }
+
+    /// Returns the tone mapping operator used when HDR images are rendered in SDR color encodings.
+    #[inline]
+    pub fn tone_mapping(&self) -> ToneMappingOptions {
+        self.ctx.tone_mapping()
+    }
+
+    /// Sets the tone mapping operator used when HDR images are rendered in SDR color encodings.
+    ///
+    /// Defaults to [`ToneMappingOptions::Rec2408`]. Applications may select other operators to
+    /// match tone mapping done by the system.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMappingOptions) -> &mut Self {
+        if let Some(preview) = &mut self.preview {
+            preview.set_tone_mapping(tone_mapping);
+        }
+        self.ctx.set_tone_mapping(tone_mapping);
+        self
+    }
+
+    /// Returns the display parameters used when rendering HLG images.
+    #[inline]
+    pub fn hlg_display(&self) -> HlgDisplayParams {
+        self.ctx.hlg_display()
+    }
+
+    /// Sets the display parameters used when rendering HLG images.
+    ///
+    /// System gamma of HLG OOTF is computed from the nominal peak luminance of the display and
+    /// the surround luminance. By default, the intensity target of the image and the reference
+    /// surround luminance of 5 nits are used.
+    pub fn set_hlg_display(&mut self, hlg_display: HlgDisplayParams) -> &mut Self {
+        if let Some(preview) = &mut self.preview {
+            preview.set_hlg_display(hlg_display);
+        }
+        self.ctx.set_hlg_display(hlg_display);
+        self
+    }
+
+    /// Returns whether statistics of clipped samples are collected while rendering.
+    #[inline]
+    pub fn collect_gamut_stats(&self) -> bool {
+        self.ctx.collect_gamut_stats()
+    }
+
+    /// Sets whether to collect statistics of samples clipped while transforming to the requested
+    /// color encoding.
+    ///
+    /// If enabled, output samples are clipped to the range of `[0, 1]` in the final stage of
+    /// color transformation, and statistics are returned by [`Render::gamut_stats`]. Callers can
+    /// use this to fall back to a wider target color encoding if too many pixels are clipped.
+    pub fn set_collect_gamut_stats(&mut self, collect_gamut_stats: bool) -> &mut Self {
+        if let Some(preview) = &mut self.preview {
+            preview.set_collect_gamut_stats(collect_gamut_stats);
+        }
+        self.ctx.set_collect_gamut_stats(collect_gamut_stats);
+        self
+    }
+
+    /// Returns whether images are requested to be rendered in linear light, leaving the transfer
+    /// function to the consumer.
+    #[inline]
+    pub fn render_linear(&self) -> bool {
+        self.render_linear
+    }
+
+    /// Sets whether images are rendered in linear light.
+    ///
+    /// If enabled, images are rendered in the requested color encoding but with linear transfer
+    /// function. The transfer function of the requested color encoding is then returned by
+    /// [`Render::pending_transfer`], so that consumers can apply it by themselves, e.g. in a
+    /// shader. [`rendered_icc`][Self::rendered_icc] and [`rendered_cicp`][Self::rendered_cicp]
+    /// describe the linear color encoding.
+    ///
+    /// This has no effect if the requested color encoding is described by an ICC profile which
+    /// cannot be represented with enum values, or if it's already linear.
+    pub fn set_render_linear(&mut self, render_linear: bool) -> &mut Self {
+        let encoding = self.requested_encoding();
+        self.render_linear = render_linear;
+        self.set_requested_encoding(encoding);
+        self
+    }
+
+    /// Returns the requested color encoding, with the pending transfer function applied.
+    fn requested_encoding(&self) -> ColorEncodingWithProfile {
+        let encoding = self.ctx.requested_color_encoding();
+        match (self.pending_transfer, encoding.encoding()) {
+            (Some(tf), jxl_color::ColourEncoding::Enum(encoding)) => {
+                ColorEncodingWithProfile::new(EnumColourEncoding {
+                    tf,
+                    ..encoding.clone()
+                })
+            }
+            _ => encoding.clone(),
+        }
+    }
+
+    fn set_requested_encoding(&mut self, encoding: ColorEncodingWithProfile) {
+        self.pending_transfer = None;
+        let encoding = match encoding.encoding() {
+            jxl_color::ColourEncoding::Enum(enum_encoding)
+                if self.render_linear && enum_encoding.tf != color::TransferFunction::Linear =>
+            {
+                self.pending_transfer = Some(enum_encoding.tf);
+                ColorEncodingWithProfile::new(EnumColourEncoding {
+                    tf: color::TransferFunction::Linear,
+                    ..enum_encoding.clone()
+                })
+            }
+            jxl_color::ColourEncoding::IccProfile(_) if self.render_linear => {
+                tracing::warn!("Cannot render in linear light with requested ICC profile");
+                encoding
+            }
+            _ => encoding,
+        };
+        if let Some(preview) = &mut self.preview {
+            preview.request_color_encoding(encoding.clone());
+        }
+        self.ctx.request_color_encoding(encoding);
+    }
+}
+
+/// CMS shared between render contexts of the main frames and the preview frame.
+struct SharedCms<T>(Arc<T>);
+
+impl<T: ColorManagementSystem> ColorManagementSystem for SharedCms<T> {
+    fn transform_impl(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+        channels: &mut [&mut [f32]],
+    ) -> std::result::Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.0.transform_impl(from, to, intent, channels)
+    }
+
+    fn prepare_transform(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        intent: RenderingIntent,
+    ) -> std::result::Result<
+        Option<Box<dyn PreparedTransform>>,
+        Box<dyn std::error::Error + Send + Sync + 'static>,
+    > {
+        self.0.prepare_transform(from, to, intent)
+    }
+
+    fn supports_linear_tf(&self) -> bool {
+        self.0.supports_linear_tf()
     }
 }
 
@@ -698,8 +1707,195 @@ impl JxlImage {
     }
 
     /// Renders the given keyframe with optional cropping region.
+    ///
+    /// If the [output downscaling factor][Self::output_downscale] is 8 or larger, VarDCT keyframes
+    /// which don't blend with other frames are rendered from the LF image, as in
+    /// [`render_lf`](Self::render_lf), for images without extra channels.
     pub fn render_frame_cropped(&self, keyframe_index: usize) -> Result<Render> {
-        let image = self.ctx.render_keyframe(keyframe_index)?;
+        if self.renders_lf_only(keyframe_index) {
+            if let Some(render) = self.render_lf(keyframe_index)? {
+                return Ok(render);
+            }
+        }
+
+        let image = self
+            .ctx
+            .render_keyframe(keyframe_index)
+            .map_err(|e| self.keyframe_error(keyframe_index, e))?;
+        Ok(self.make_render(
+            keyframe_index,
+            image,
+            self.ctx.image_region(),
+            self.output_downscale,
+        ))
+    }
+
+    /// Wraps the rendered keyframe, which covers the given image region.
+    fn make_render(
+        &self,
+        keyframe_index: usize,
+        image: Arc<ImageWithRegion>,
+        image_region: Region,
+        output_downscale: u32,
+    ) -> Render {
+        let image_region = image_region.apply_orientation(&self.image_header);
+        let frame = self.ctx.keyframe(keyframe_index).unwrap();
+        let frame_header = frame.header();
+        let target_frame_region = image_region.translate(-frame_header.x0, -frame_header.y0);
+
+        let is_cmyk = self.ctx.requested_color_encoding().is_cmyk();
+        Render {
+            keyframe_index,
+            name: frame_header.name.clone(),
+            duration: frame_header.duration,
+            orientation: self.image_header.metadata.orientation,
+            image,
+            extra_channels: self.convert_ec_info(),
+            target_frame_region,
+            color_bit_depth: self.image_header.metadata.bit_depth,
+            is_cmyk,
+            render_spot_color: self.render_spot_color,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: self.active_alpha,
+            pending_transfer: self.pending_transfer,
+            output_downscale,
+            corrupt_groups: frame.corrupt_groups(),
+            warnings: frame.warnings(),
+            force_scalar: self.ctx.force_scalar(),
+        }
+    }
+
+    /// Renders the given keyframe directly into a buffer with interleaved channels, with
+    /// orientation applied.
+    ///
+    /// The result is the same as [`Render::image_all_channels`] of
+    /// [`render_frame_cropped`](Self::render_frame_cropped), but the keyframe is transformed to
+    /// the requested color encoding in group-sized stripes, each of which is written to the
+    /// output right away. This avoids keeping a transformed copy of the whole keyframe alongside
+    /// the output, which reduces peak memory usage for large images.
+    ///
+    /// Keyframes rendered from the LF image only, with the output downscaling factor of 8 or
+    /// larger, are small enough to be transformed at once, and are not split into stripes.
+    pub fn render_frame_image(&self, keyframe_index: usize) -> Result<FrameBuffer> {
+        if self.renders_lf_only(keyframe_index) {
+            if let Some(render) = self.render_lf(keyframe_index)? {
+                return Ok(render.image_all_channels());
+            }
+        }
+
+        let frame = self.ctx.keyframe(keyframe_index).ok_or_else(|| {
+            self.keyframe_error(keyframe_index, jxl_render::Error::IncompleteFrame)
+        })?;
+        let image_region = self
+            .ctx
+            .image_region()
+            .apply_orientation(&self.image_header);
+        let frame_header = frame.header();
+        let target_frame_region = image_region.translate(-frame_header.x0, -frame_header.y0);
+        let orientation = self.image_header.metadata.orientation;
+        let (width, height) = match orientation {
+            1..=4 => (target_frame_region.width, target_frame_region.height),
+            _ => (target_frame_region.height, target_frame_region.width),
+        };
+
+        let color_bit_depth = self.image_header.metadata.bit_depth;
+        let ec_bit_depth: Vec<_> = self
+            .convert_ec_info()
+            .into_iter()
+            .map(|ec| ec.bit_depth)
+            .collect();
+
+        let mut fb = None;
+        self.ctx
+            .render_keyframe_stripes(keyframe_index, |stripe| {
+                let color_channels = stripe.color_channels();
+                let fb = fb.get_or_insert_with(|| {
+                    FrameBuffer::new(width as usize, height as usize, stripe.channels())
+                });
+
+                let grids: Vec<_> = stripe.buffer().iter().collect();
+                let mut bit_depth = vec![color_bit_depth; color_channels];
+                bit_depth.extend_from_slice(&ec_bit_depth[..grids.len() - color_channels]);
+                let regions: Vec<_> = stripe
+                    .regions_and_shifts()
+                    .iter()
+                    .map(|(region, _)| *region)
+                    .collect();
+                fb.copy_from_grids(
+                    &grids,
+                    &bit_depth,
+                    &regions,
+                    target_frame_region,
+                    orientation,
+                );
+            })
+            .map_err(|e| self.keyframe_error(keyframe_index, e))?;
+        Ok(fb.expect("at least one stripe should be rendered"))
+    }
+
+    /// Adds the keyframe index and the location of the failed group to the render error.
+    ///
+    /// Incomplete frames are located at the group currently being loaded, which is the first
+    /// truncated group in the codestream.
+    fn keyframe_error(&self, keyframe_index: usize, err: jxl_render::Error) -> Error {
+        let group_offset = |frame_index: usize, group: TocGroupKind| {
+            let frame = self.ctx.frame(frame_index)?;
+            let toc_group = frame
+                .toc()
+                .iter_bitstream_order()
+                .find(|g| g.kind == group)?;
+            Some(self.inner.frame_offsets.get(frame_index)? + toc_group.offset)
+        };
+
+        let (group, offset) = match &err {
+            jxl_render::Error::Group { frame, group, .. } => {
+                (Some(*group), group_offset(*frame, *group))
+            }
+            jxl_render::Error::IncompleteFrame => {
+                let frame_index = self.inner.frame_offsets.len().saturating_sub(1);
+                let truncated = self
+                    .ctx
+                    .frame(frame_index)
+                    .filter(|frame| !frame.is_loading_done())
+                    .and_then(|frame| frame.current_loading_group());
+                match truncated {
+                    Some(toc_group) => (
+                        Some(toc_group.kind),
+                        group_offset(frame_index, toc_group.kind),
+                    ),
+                    None => (None, None),
+                }
+            }
+            _ => (None, None),
+        };
+
+        let mut err = Error::from(err).at_frame(keyframe_index);
+        if let Some(group) = group {
+            err = err.at_group(group);
+        }
+        if let Some(offset) = offset {
+            err = err.at_offset(offset);
+        }
+        err
+    }
+
+    /// Renders the given keyframe in the color space it's encoded in, without any color transform.
+    ///
+    /// Color channels are in XYB color space if the image is XYB encoded, in YCbCr if the
+    /// keyframe is YCbCr encoded, or in the color encoding signalled in the image header
+    /// otherwise. XYB samples are not in the nominal range of `[0, 1]`, so the render should be
+    /// written to `f32` buffers. Black channel of CMYK images is not merged into the stream.
+    ///
+    /// Returns `None` if the keyframe is composited from frames which are already transformed to
+    /// the signalled color encoding.
+    pub fn render_raw_color_space(&self, keyframe_index: usize) -> Result<Option<Render>> {
+        let Some(image) = self
+            .ctx
+            .render_keyframe_raw(keyframe_index)
+            .map_err(|e| self.keyframe_error(keyframe_index, e))?
+        else {
+            return Ok(None);
+        };
 
         let image_region = self
             .ctx
@@ -709,8 +1905,7 @@ impl JxlImage {
         let frame_header = frame.header();
         let target_frame_region = image_region.translate(-frame_header.x0, -frame_header.y0);
 
-        let is_cmyk = self.ctx.requested_color_encoding().is_cmyk();
-        let result = Render {
+        Ok(Some(Render {
             keyframe_index,
             name: frame_header.name.clone(),
             duration: frame_header.duration,
@@ -719,10 +1914,300 @@ impl JxlImage {
             extra_channels: self.convert_ec_info(),
             target_frame_region,
             color_bit_depth: self.image_header.metadata.bit_depth,
+            is_cmyk: false,
+            render_spot_color: false,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: self.active_alpha,
+            pending_transfer: None,
+            output_downscale: self.output_downscale,
+            corrupt_groups: frame.corrupt_groups(),
+            warnings: frame.warnings(),
+            force_scalar: self.ctx.force_scalar(),
+        }))
+    }
+
+    /// Renders the given keyframe for seeking, decoding only frames the keyframe depends on.
+    ///
+    /// Reference frames and LF frames actually read by the keyframe are decoded, and unrelated
+    /// intermediate frames are skipped entirely, which makes scrubbing long animations cheap.
+    /// Renders of keyframes are cached, so seeking back to a rendered keyframe is free. The
+    /// keyframe should be loaded in prior to seeking; use
+    /// [`keyframe_seek_point`][Self::keyframe_seek_point] to find where to load from.
+    pub fn seek_keyframe(&self, keyframe_index: usize) -> Result<Render> {
+        let deps = self
+            .keyframe_dependencies(keyframe_index)
+            .ok_or(jxl_render::Error::IncompleteFrame)?;
+        tracing::debug!(
+            keyframe_index,
+            num_frames = deps.len(),
+            "Seeking to keyframe"
+        );
+        self.render_frame_cropped(keyframe_index)
+    }
+
+    /// Returns indices of frames the keyframe depends on, including the keyframe itself, in
+    /// ascending order.
+    ///
+    /// These are the frames decoded by [`seek_keyframe`][Self::seek_keyframe]. Returns `None` if
+    /// the keyframe is not loaded.
+    pub fn keyframe_dependencies(&self, keyframe_index: usize) -> Option<Vec<usize>> {
+        self.ctx.keyframe_dependencies(keyframe_index)
+    }
+
+    /// Starts rendering keyframes in the range in the background, and returns the number of
+    /// keyframes scheduled.
+    ///
+    /// Players can call this method to render ahead of playback. Rendering is scheduled on the
+    /// thread pool, and [`render_frame`][Self::render_frame] on a prefetched keyframe waits for
+    /// the background render instead of rendering again. Keyframes which are not loaded are
+    /// skipped. This method does nothing if the image is decoded without multithreading.
+    pub fn prefetch_keyframes(&self, keyframes: std::ops::Range<usize>) -> usize {
+        self.ctx.prefetch_keyframes(keyframes)
+    }
+
+    /// Loads and renders a single keyframe from codestream bytes starting at the given offset,
+    /// without loading frames before it.
+    ///
+    /// `offset` is the offset within codestream, as returned by
+    /// [`frame_offset`][Self::frame_offset], and `bytes` is the codestream from the offset. For
+    /// images in a container, payloads of partial codestream boxes should be concatenated. Frames
+    /// are read until the first keyframe, so the offset may point to LF frames or reference-only
+    /// frames the keyframe depends on. If a frame at the offset is already loaded, the keyframe is
+    /// rendered from loaded frames instead, and `bytes` is not used.
+    ///
+    /// Frames which depend on frames before the offset, namely by blending onto the previous
+    /// canvas or copying patches from reference frames, can't be rendered this way and result in
+    /// an error. Keyframe index of the returned render is 0 unless the keyframe is already loaded.
+    pub fn load_frame_at(&self, offset: usize, bytes: &[u8]) -> Result<Render> {
+        if let Some(frame_idx) = self.inner.frame_offsets.iter().position(|&o| o == offset) {
+            let keyframe_index = (0..self.ctx.loaded_keyframes())
+                .find(|&idx| self.ctx.keyframe(idx).unwrap().index() >= frame_idx);
+            if let Some(keyframe_index) = keyframe_index {
+                return self.render_frame_cropped(keyframe_index);
+            }
+        }
+
+        let mut ctx = self.ctx.empty_clone();
+        let mut saved_references = [false; 4];
+        let mut buf = bytes;
+        loop {
+            if buf.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "codestream ended before keyframe",
+                )
+                .into());
+            }
+
+            let mut bitstream = Bitstream::new(buf);
+            let frame = ctx.load_frame_header(&mut bitstream)?;
+            let header = frame.header();
+            if blends_onto_unsaved(&self.image_header, header, &saved_references) {
+                return Err("frame blends onto frames before the offset".into());
+            }
+            let is_keyframe = header.is_keyframe();
+            let saved_reference = header
+                .can_reference()
+                .then_some(header.save_as_reference as usize);
+            let has_patches = header.flags.patches();
+
+            let header_bytes = bitstream.num_read_bits() / 8;
+            buf = frame.feed_bytes(&buf[header_bytes..])?;
+            if !frame.is_loading_done() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "frame data is incomplete",
+                )
+                .into());
+            }
+            if has_patches && copies_unsaved_patches(frame, &saved_references)? {
+                return Err("frame copies patches from frames before the offset".into());
+            }
+            ctx.finalize_current_frame();
+
+            if let Some(idx) = saved_reference {
+                saved_references[idx] = true;
+            }
+            if is_keyframe {
+                break;
+            }
+        }
+
+        let image = ctx.render_keyframe(0)?;
+        let image_region = ctx.image_region().apply_orientation(&self.image_header);
+        let frame_header = ctx.keyframe(0).unwrap().header();
+        let target_frame_region = image_region.translate(-frame_header.x0, -frame_header.y0);
+
+        let is_cmyk = ctx.requested_color_encoding().is_cmyk();
+        Ok(Render {
+            keyframe_index: 0,
+            name: frame_header.name.clone(),
+            duration: frame_header.duration,
+            orientation: self.image_header.metadata.orientation,
+            image,
+            extra_channels: self.convert_ec_info(),
+            target_frame_region,
+            color_bit_depth: self.image_header.metadata.bit_depth,
             is_cmyk,
             render_spot_color: self.render_spot_color,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: self.active_alpha,
+            pending_transfer: self.pending_transfer,
+            output_downscale: self.output_downscale,
+            corrupt_groups: ctx.keyframe(0).unwrap().corrupt_groups(),
+            warnings: ctx.keyframe(0).unwrap().warnings(),
+            force_scalar: ctx.force_scalar(),
+        })
+    }
+
+    /// Returns the error the keyframe failed to render with, if it was substituted with the
+    /// previous keyframe by [error isolation][JxlImageBuilder::error_isolation].
+    ///
+    /// Errors of non-keyframes composited into the keyframe are also reported. Returns `None` if
+    /// the keyframe is not rendered yet, or rendered without errors.
+    pub fn substituted_error(&self, keyframe_index: usize) -> Option<&jxl_render::Error> {
+        let frame_idx = self.ctx.keyframe(keyframe_index)?.index();
+        let first_frame_idx = keyframe_index
+            .checked_sub(1)
+            .and_then(|idx| self.ctx.keyframe(idx))
+            .map(|frame| frame.index() + 1)
+            .unwrap_or(0);
+        (first_frame_idx..=frame_idx)
+            .rev()
+            .find_map(|idx| self.ctx.frame(idx)?.substituted_error())
+    }
+
+    /// Renders the LF frame with the given LF level used by the keyframe, or returns `None` if
+    /// there's no such LF frame.
+    ///
+    /// Images with progressive DC contain LF frames, each `8^lf_level` times downsampled from the
+    /// image, so they form a resolution pyramid that can be rendered without computing
+    /// downsamples. LF level of 0 renders the keyframe itself. The cropping region is downsampled
+    /// accordingly.
+    pub fn render_lf_level(&self, keyframe_index: usize, lf_level: u32) -> Result<Option<Render>> {
+        if lf_level == 0 {
+            return self.render_frame_cropped(keyframe_index).map(Some);
+        }
+        let Some((frame, image)) = self
+            .ctx
+            .render_lf_frame(keyframe_index, lf_level)
+            .map_err(|e| self.keyframe_error(keyframe_index, e))?
+        else {
+            return Ok(None);
         };
-        Ok(result)
+
+        let frame_header = frame.header();
+        let target_frame_region = self
+            .ctx
+            .image_region()
+            .apply_orientation(&self.image_header)
+            .translate(-frame_header.x0, -frame_header.y0)
+            .downsample(lf_level * 3);
+
+        let keyframe_header = self.ctx.keyframe(keyframe_index).unwrap().header();
+        let is_cmyk = self.ctx.requested_color_encoding().is_cmyk();
+        Ok(Some(Render {
+            keyframe_index,
+            name: keyframe_header.name.clone(),
+            duration: keyframe_header.duration,
+            orientation: self.image_header.metadata.orientation,
+            image,
+            extra_channels: self.convert_ec_info(),
+            target_frame_region,
+            color_bit_depth: self.image_header.metadata.bit_depth,
+            is_cmyk,
+            render_spot_color: self.render_spot_color,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: self.active_alpha,
+            pending_transfer: self.pending_transfer,
+            output_downscale: (self.output_downscale >> (lf_level * 3)).max(1),
+            corrupt_groups: frame.corrupt_groups(),
+            warnings: frame.warnings(),
+            force_scalar: self.ctx.force_scalar(),
+        }))
+    }
+
+    /// Renders the LF image of the given keyframe, or returns `None` if the keyframe is not a
+    /// VarDCT frame.
+    ///
+    /// The LF image is 8x downsampled from the keyframe, and is decoded without any of the HF
+    /// passes, which makes it suitable for fast previews and perceptual hashing. The keyframe is
+    /// not blended with other frames, and extra channels are not included. The cropping region is
+    /// downsampled accordingly.
+    pub fn render_lf(&self, keyframe_index: usize) -> Result<Option<Render>> {
+        let Some((_, image)) = self
+            .ctx
+            .render_keyframe_lf(keyframe_index)
+            .map_err(|e| self.keyframe_error(keyframe_index, e))?
+        else {
+            return Ok(None);
+        };
+
+        let keyframe_header = self.ctx.keyframe(keyframe_index).unwrap().header();
+        let upsampling_shift = keyframe_header.upsampling.trailing_zeros();
+        let target_frame_region = self
+            .ctx
+            .image_region()
+            .apply_orientation(&self.image_header)
+            .translate(-keyframe_header.x0, -keyframe_header.y0)
+            .downsample(3 + upsampling_shift);
+
+        Ok(Some(Render {
+            keyframe_index,
+            name: keyframe_header.name.clone(),
+            duration: keyframe_header.duration,
+            orientation: self.image_header.metadata.orientation,
+            image,
+            extra_channels: Vec::new(),
+            target_frame_region,
+            color_bit_depth: self.image_header.metadata.bit_depth,
+            is_cmyk: false,
+            render_spot_color: false,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: None,
+            pending_transfer: self.pending_transfer,
+            output_downscale: (self.output_downscale >> (3 + upsampling_shift)).max(1),
+            corrupt_groups: Vec::new(),
+            warnings: Vec::new(),
+            force_scalar: self.ctx.force_scalar(),
+        }))
+    }
+
+    /// Renders the preview frame, or returns `None` if the image doesn't have one.
+    ///
+    /// The preview frame is loaded while initializing the image, so it can be rendered before any
+    /// of the main frames are loaded. The cropping region doesn't apply to the preview.
+    pub fn render_preview(&self) -> Result<Option<Render>> {
+        let Some(ctx) = &self.preview else {
+            return Ok(None);
+        };
+        let image = ctx.render_keyframe(0)?;
+
+        let frame = ctx.keyframe(0).unwrap();
+        let frame_header = frame.header();
+        let image_region = ctx.image_region().apply_orientation(frame.image_header());
+        let target_frame_region = image_region.translate(-frame_header.x0, -frame_header.y0);
+
+        let is_cmyk = ctx.requested_color_encoding().is_cmyk();
+        Ok(Some(Render {
+            keyframe_index: 0,
+            name: frame_header.name.clone(),
+            duration: frame_header.duration,
+            orientation: self.image_header.metadata.orientation,
+            image,
+            extra_channels: self.convert_ec_info(),
+            target_frame_region,
+            color_bit_depth: self.image_header.metadata.bit_depth,
+            is_cmyk,
+            render_spot_color: self.render_spot_color,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: self.active_alpha,
+            pending_transfer: self.pending_transfer,
+            output_downscale: 1,
+            corrupt_groups: frame.corrupt_groups(),
+            warnings: frame.warnings(),
+            force_scalar: ctx.force_scalar(),
+        }))
     }
 
     /// Renders the currently loading keyframe.
@@ -761,17 +2246,167 @@ impl JxlImage {
             color_bit_depth: self.image_header.metadata.bit_depth,
             is_cmyk,
             render_spot_color: self.render_spot_color,
+            spot_colors: self.spot_colors.clone(),
+            active_alpha: self.active_alpha,
+            pending_transfer: self.pending_transfer,
+            output_downscale: self.output_downscale,
+            corrupt_groups: frame.corrupt_groups(),
+            warnings: frame.warnings(),
+            force_scalar: self.ctx.force_scalar(),
         };
         Ok(result)
     }
 
+    /// Renders the keyframe from whatever data is available, together with the coverage map of
+    /// samples decoded from actual data.
+    ///
+    /// This is meant for photo recovery and forensic tools working on truncated or damaged files,
+    /// and never fails. Loaded keyframes are rendered as usual. The keyframe currently being
+    /// loaded is rendered progressively; groups whose HF data is missing are upsampled from the
+    /// LF image if available, and groups without any data are filled with neutral gray. Keyframes
+    /// which fail to render or are not loaded yet are filled with neutral gray entirely.
+    pub fn render_best_effort(&mut self, keyframe_index: usize) -> BestEffortRender {
+        let ec_alpha = self
+            .convert_ec_info()
+            .iter()
+            .map(|ec| ec.is_alpha())
+            .collect::<Vec<_>>();
+        let fill_for =
+            |color_channels: usize| recovery::fill_values(color_channels, ec_alpha.iter().copied());
+
+        let num_loaded_keyframes = self.num_loaded_keyframes();
+        if keyframe_index < num_loaded_keyframes {
+            match self.render_frame_cropped(keyframe_index) {
+                Ok(render) => {
+                    return BestEffortRender::complete(keyframe_index, render.image_all_channels());
+                }
+                Err(e) => tracing::warn!(%e, keyframe_index, "Failed to render keyframe"),
+            }
+        } else if keyframe_index == num_loaded_keyframes {
+            let is_keyframe_loading = self
+                .ctx
+                .frame(self.ctx.loaded_frames())
+                .is_some_and(|frame| frame.header().is_keyframe());
+            let render = if is_keyframe_loading {
+                self.render_loading_frame_cropped()
+            } else {
+                Err(jxl_render::Error::IncompleteFrame.into())
+            };
+            match render {
+                Ok(render) => {
+                    let frame = self.ctx.frame(self.ctx.loaded_frames()).unwrap();
+                    let fill = fill_for(render.image.color_channels());
+                    return BestEffortRender::partial(
+                        keyframe_index,
+                        render.image_all_channels(),
+                        frame,
+                        render.target_frame_region,
+                        render.orientation,
+                        &fill,
+                    );
+                }
+                Err(e) => tracing::debug!(%e, "Loading keyframe is not renderable"),
+            }
+        }
+
+        let region = self
+            .ctx
+            .image_region()
+            .apply_orientation(&self.image_header);
+        let (mut width, mut height) = (region.width as usize, region.height as usize);
+        if self.image_header.metadata.orientation >= 5 {
+            std::mem::swap(&mut width, &mut height);
+        }
+        let color_channels = if self.ctx.requested_color_encoding().is_grayscale() {
+            1
+        } else {
+            3
+        };
+        BestEffortRender::missing(keyframe_index, width, height, &fill_for(color_channels))
+    }
+
     /// Sets the cropping region (region of interest).
     ///
-    /// Subsequent rendering methods will crop the image buffer according to the region.
+    /// Subsequent rendering methods will crop the image buffer according to the region. Cached
+    /// renders are discarded, and keyframes are decoded again from the retained group data.
     pub fn set_image_region(&mut self, region: CropInfo) -> &mut Self {
         self.ctx.request_image_region(region.into());
         self
     }
+
+    /// Renders the given keyframe strip by strip, passing completed rows to `f` from top to
+    /// bottom.
+    ///
+    /// Rows are delivered as interleaved samples of the channels included in [`Render::stream`],
+    /// together with the y coordinate relative to the cropping region. The keyframe is rendered
+    /// in strips one group tall, so only a single strip is kept in memory at a time.
+    ///
+    /// The keyframe is always decoded at full resolution; output downscaling is not applied, even
+    /// if it's 8 or larger. Decoded global data and LF groups are reused across strips, and
+    /// cached renders are kept as is.
+    pub fn render_scanlines<Sample: FrameBufferSample + Default + Clone>(
+        &self,
+        keyframe_index: usize,
+        mut f: impl FnMut(u32, &[Sample]),
+    ) -> Result<()> {
+        let frame = self.ctx.keyframe(keyframe_index).ok_or_else(|| {
+            self.keyframe_error(keyframe_index, jxl_render::Error::IncompleteFrame)
+        })?;
+        let strip_height = frame.header().group_dim() as i32;
+        let mut renderer = self
+            .ctx
+            .keyframe_region_renderer(keyframe_index)
+            .map_err(|e| self.keyframe_error(keyframe_index, e))?;
+
+        let full_region = self.ctx.image_region();
+        let bottom = full_region.top + full_region.height as i32;
+        let mut row = Vec::new();
+        let mut y = 0u32;
+        let mut top = full_region.top;
+        while top < bottom {
+            // Align strips to group boundaries so that each group row is decoded only once.
+            let next_top = (top.div_euclid(strip_height) + 1) * strip_height;
+            let next_top = next_top.min(bottom);
+            let strip_region = Region {
+                top,
+                height: (next_top - top) as u32,
+                ..full_region
+            };
+            let image = renderer
+                .render(strip_region)
+                .map_err(|e| self.keyframe_error(keyframe_index, e))?;
+
+            let render = self.make_render(keyframe_index, image, strip_region, 1);
+            let mut stream = render.stream();
+            let row_len = (stream.width() * stream.channels()) as usize;
+            row.resize(row_len, Sample::default());
+            for _ in 0..stream.height() {
+                stream.write_to_buffer(&mut row);
+                f(y, &row);
+                y += 1;
+            }
+
+            top = next_top;
+        }
+        Ok(())
+    }
+}
+
+/// # Decoding statistics
+#[cfg(feature = "stats")]
+impl JxlImage {
+    /// Returns decoding statistics of every loaded frame, including frames which are not displayed
+    /// directly.
+    ///
+    /// Statistics include the number of groups decoded, edge-preserving filter iterations, sizes
+    /// of each section, entropy coders used, and time spent on rendering. Counters are updated as
+    /// frames are rendered, so this should be called after rendering the image.
+    pub fn decoder_stats(&self) -> Vec<FrameDecoderStats<'_>> {
+        (0..self.num_loaded_frames())
+            .filter_map(|idx| self.frame(idx))
+            .map(FrameDecoderStats::new)
+            .collect()
+    }
 }
 
 /// # JPEG bitstream reconstruction
@@ -891,6 +2526,7 @@ impl JxlImage {
             .metadata
             .ec_info
             .iter()
+            .take(self.ctx.num_output_extra_channels())
             .map(|ec_info| ExtraChannel {
                 ty: ec_info.ty,
                 name: ec_info.name.clone(),
@@ -900,17 +2536,86 @@ impl JxlImage {
     }
 }
 
+/// Returns whether the frame blends onto reference frames not marked in `saved_references`.
+fn blends_onto_unsaved(
+    image_header: &ImageHeader,
+    header: &FrameHeader,
+    saved_references: &[bool; 4],
+) -> bool {
+    if !header.frame_type.is_normal_frame() {
+        return false;
+    }
+
+    let size = &image_header.size;
+    let full_frame = !header.have_crop
+        || (header.x0 <= 0
+            && header.y0 <= 0
+            && header.x0 as i64 + header.width as i64 >= size.width as i64
+            && header.y0 as i64 + header.height as i64 >= size.height as i64);
+    std::iter::once(&header.blending_info)
+        .chain(&header.ec_blending_info)
+        .any(|info| {
+            let resets_canvas = info.mode == frame::BlendMode::Replace && full_frame;
+            !resets_canvas && !saved_references[info.source as usize]
+        })
+}
+
+/// Returns whether the frame copies patches from reference frames not marked in
+/// `saved_references`.
+fn copies_unsaved_patches(frame: &IndexedFrame, saved_references: &[bool; 4]) -> Result<bool> {
+    let Some(patches) = frame.patches().transpose()? else {
+        return Ok(false);
+    };
+    Ok(patches
+        .iter()
+        .any(|patch| !saved_references[patch.ref_idx as usize]))
+}
+
+/// Checks if a completely loaded frame of a resumed image copies patches from frames before the
+/// snapshot, and marks its reference slot as saved.
+fn check_resumed_frame(
+    saved_references: &mut Option<[bool; 4]>,
+    frame: &IndexedFrame,
+) -> Result<()> {
+    let Some(saved_references) = saved_references else {
+        return Ok(());
+    };
+
+    let header = frame.header();
+    if header.flags.patches() && copies_unsaved_patches(frame, saved_references)? {
+        return Err("frame copies patches from frames before the snapshot".into());
+    }
+    if header.can_reference() {
+        saved_references[header.save_as_reference as usize] = true;
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct JxlImageInner {
     end_of_image: bool,
+    /// Bytes after the last frame.
     buffer: Vec<u8>,
+    /// Bytes of a frame header split across calls to `feed_bytes`.
+    header_buffer: IncrementalBuffer,
     buffer_offset: usize,
     frame_offsets: Vec<usize>,
+    frame_header_sizes: Vec<usize>,
     aux_boxes: AuxBoxList,
+    /// Codestream bytes of the image header, embedded ICC profile and preview frame.
+    codestream_header: Vec<u8>,
+    /// Reference slots saved after resuming from a snapshot, used to reject frames that depend on
+    /// frames before the snapshot. `None` if the image is not resumed.
+    saved_references: Option<[bool; 4]>,
 }
 
 impl JxlImageInner {
-    fn feed_bytes_inner(&mut self, ctx: &mut RenderContext, mut buf: &[u8]) -> Result<()> {
+    fn feed_bytes_inner(
+        &mut self,
+        ctx: &mut RenderContext,
+        image_header: &ImageHeader,
+        mut buf: &[u8],
+    ) -> Result<()> {
         if buf.is_empty() {
             return Ok(());
         }
@@ -921,7 +2626,7 @@ impl JxlImageInner {
         }
 
         if let Some(loading_frame) = ctx.current_loading_frame() {
-            debug_assert!(self.buffer.is_empty());
+            debug_assert!(self.header_buffer.is_empty());
             let len = buf.len();
             buf = loading_frame.feed_bytes(buf)?;
             let count = len - buf.len();
@@ -929,6 +2634,7 @@ impl JxlImageInner {
 
             if loading_frame.is_loading_done() {
                 let is_last = loading_frame.header().is_last;
+                check_resumed_frame(&mut self.saved_references, loading_frame)?;
                 ctx.finalize_current_frame();
                 if is_last {
                     self.end_of_image = true;
@@ -941,33 +2647,42 @@ impl JxlImageInner {
             }
         }
 
-        self.buffer.extend_from_slice(buf);
-        let mut buf = &*self.buffer;
         while !buf.is_empty() {
-            let mut bitstream = Bitstream::new(buf);
-            let frame = match ctx.load_frame_header(&mut bitstream) {
-                Ok(x) => x,
-                Err(e) if e.unexpected_eof() => {
-                    self.buffer = buf.to_vec();
-                    return Ok(());
-                }
-                Err(e) => {
-                    return Err(e.into());
-                }
+            let buffered_len = self.header_buffer.pending().len();
+            let parsed = self
+                .header_buffer
+                .parse(
+                    buf,
+                    |bitstream| ctx.load_frame_header(bitstream).map(|_| ()),
+                    |e| e.unexpected_eof(),
+                )
+                .map_err(|e| {
+                    Error::from_render(e, DecodeStage::FrameHeader).at_offset(self.buffer_offset)
+                })?;
+            let consumed = match parsed {
+                Incremental::Done { consumed, .. } => consumed,
+                Incremental::NeedMoreBytes { .. } => return Ok(()),
             };
+            let frame = ctx.pending_frame().unwrap();
+            if let Some(saved_references) = &self.saved_references {
+                if blends_onto_unsaved(image_header, frame.header(), saved_references) {
+                    return Err("frame blends onto frames before the snapshot".into());
+                }
+            }
             let frame_index = frame.index();
             assert_eq!(self.frame_offsets.len(), frame_index);
             self.frame_offsets.push(self.buffer_offset);
 
-            let read_bytes = bitstream.num_read_bits() / 8;
-            buf = &buf[read_bytes..];
+            let header_bytes = buffered_len + consumed;
+            self.frame_header_sizes.push(header_bytes);
+            buf = &buf[consumed..];
             let len = buf.len();
             buf = frame.feed_bytes(buf)?;
-            let read_bytes = read_bytes + (len - buf.len());
-            self.buffer_offset += read_bytes;
+            self.buffer_offset += header_bytes + (len - buf.len());
 
             if frame.is_loading_done() {
                 let is_last = frame.header().is_last;
+                check_resumed_frame(&mut self.saved_references, frame)?;
                 ctx.finalize_current_frame();
                 if is_last {
                     self.end_of_image = true;
@@ -977,7 +2692,6 @@ impl JxlImageInner {
             }
         }
 
-        self.buffer.clear();
         Ok(())
     }
 }
@@ -1079,6 +2793,13 @@ pub struct Render {
     color_bit_depth: BitDepth,
     is_cmyk: bool,
     render_spot_color: bool,
+    spot_colors: Vec<SpotColorSettings>,
+    active_alpha: Option<usize>,
+    pending_transfer: Option<color::TransferFunction>,
+    output_downscale: u32,
+    corrupt_groups: Vec<TocGroupKind>,
+    warnings: Vec<DecodeWarning>,
+    force_scalar: bool,
 }
 
 impl Render {
@@ -1100,16 +2821,68 @@ impl Render {
         self.duration
     }
 
-    /// Returns the orientation of the image.
+    /// Returns the orientation of the image, as defined in Exif.
+    ///
+    /// The value is in `1..=8`, where 1 means no transformation. Values 2 to 4 flip or rotate the
+    /// image by 180 degrees, and values 5 to 8 transpose the image, which swaps width and height.
+    /// Callers which render with [`stream_unoriented`](Render::stream_unoriented) should apply
+    /// the orientation themselves.
     #[inline]
     pub fn orientation(&self) -> u32 {
         self.orientation
     }
 
+    /// Returns the extra channel index of the alpha channel included in the output, if any.
+    ///
+    /// See [`JxlImage::set_active_alpha`].
+    #[inline]
+    pub fn active_alpha(&self) -> Option<usize> {
+        self.active_alpha
+    }
+
+    /// Returns the transfer function yet to be applied to color channels, if the image is
+    /// rendered in linear light.
+    ///
+    /// See [`JxlImage::set_render_linear`].
+    #[inline]
+    pub fn pending_transfer(&self) -> Option<color::TransferFunction> {
+        self.pending_transfer
+    }
+
+    /// Returns statistics of samples clipped while transforming to the requested color encoding.
+    ///
+    /// Returns `None` if statistics are not collected; see [`JxlImage::set_collect_gamut_stats`].
+    #[inline]
+    pub fn gamut_stats(&self) -> Option<GamutStats> {
+        self.image.gamut_stats()
+    }
+
+    /// Returns the factor [`image_scaled`](Render::image_scaled) downsamples the image by.
+    #[inline]
+    pub fn output_downscale(&self) -> u32 {
+        self.output_downscale
+    }
+
+    /// Returns pass groups of the keyframe which failed to decode and are rendered from
+    /// lower-frequency data, in TOC order.
+    ///
+    /// This is always empty unless [`JxlImageBuilder::skip_corrupt_groups`] is enabled.
+    #[inline]
+    pub fn corrupt_groups(&self) -> &[TocGroupKind] {
+        &self.corrupt_groups
+    }
+
+    /// Returns non-fatal issues found while decoding the keyframe, such as samples clamped due to
+    /// overflow.
+    #[inline]
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
     /// Creates a stream that writes to borrowed buffer.
     ///
     /// The stream will include black and alpha channels, if exist, in addition to color channels.
-    /// Orientation is applied.
+    /// Only the [active alpha channel][Self::active_alpha] is included. Orientation is applied.
     pub fn stream(&self) -> ImageStream {
         ImageStream::from_render(self, false)
     }
@@ -1122,6 +2895,66 @@ impl Render {
         ImageStream::from_render(self, true)
     }
 
+    /// Creates a stream that writes to borrowed buffer, without applying orientation.
+    ///
+    /// This is the same as [`stream`](Render::stream), except that samples are written in the
+    /// orientation they're encoded in, skipping rotation and transposition on the CPU. Callers
+    /// which can handle rotation themselves, such as with the texture sampler of GPUs, should
+    /// apply [`orientation`](Render::orientation) to the output.
+    pub fn stream_unoriented(&self) -> ImageStream<'_> {
+        ImageStream::from_render(self, false).without_orientation()
+    }
+
+    /// Creates a stream that writes to borrowed buffer, with color samples in the given alpha
+    /// convention.
+    ///
+    /// This is the same as [`stream`](Render::stream), except that color samples are multiplied
+    /// or divided by alpha as needed. See [`ImageStream::with_alpha_mode`].
+    pub fn stream_with_alpha_mode(&self, alpha_mode: AlphaMode) -> ImageStream<'_> {
+        ImageStream::from_render(self, false).with_alpha_mode(alpha_mode)
+    }
+
+    /// Creates an opaque buffer with interleaved channels by blending the image against the
+    /// given background color, with orientation applied.
+    ///
+    /// The background color is given in the output color space, with nominal range of `[0, 1]`.
+    /// Grayscale images are blended against the Rec. 709 luminance of the color. Black channel
+    /// of CMYK images is included but not blended. If the render doesn't have an
+    /// [active alpha channel][Self::active_alpha], samples are copied as is.
+    pub fn composite_on(&self, color: [f32; 3]) -> FrameBuffer {
+        let mut stream = self.stream_with_alpha_mode(AlphaMode::Straight);
+        let width = stream.width() as usize;
+        let height = stream.height() as usize;
+        let channels = stream.channels() as usize;
+        let mut buf = vec![0f32; width * height * channels];
+        stream.write_to_buffer(&mut buf);
+
+        let has_alpha = stream.is_alpha_premultiplied().is_some();
+        let out_channels = if has_alpha { channels - 1 } else { channels };
+        let color_channels = self.image.color_channels();
+        let background = if color_channels == 1 {
+            vec![0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]]
+        } else {
+            color[..color_channels.min(3)].to_vec()
+        };
+
+        let mut fb = FrameBuffer::new(width, height, out_channels);
+        for (out, px) in fb
+            .buf_mut()
+            .chunks_exact_mut(out_channels)
+            .zip(buf.chunks_exact(channels))
+        {
+            out.copy_from_slice(&px[..out_channels]);
+            if has_alpha {
+                let alpha = px[channels - 1].clamp(0.0, 1.0);
+                for (v, &bg) in out.iter_mut().zip(&background) {
+                    *v = *v * alpha + bg * (1.0 - alpha);
+                }
+            }
+        }
+        fb
+    }
+
     /// Creates a buffer with interleaved channels, with orientation applied.
     ///
     /// All extra channels are included. Use [`stream`](Render::stream) if only color, black and
@@ -1149,6 +2982,53 @@ impl Render {
         )
     }
 
+    /// Creates a buffer with interleaved channels, with orientation applied, downsampled by the
+    /// [remaining factor](Render::output_downscale) of the one selected by
+    /// [`JxlImageBuilder::max_output_dimension`].
+    ///
+    /// All extra channels are included. This is the same as
+    /// [`image_all_channels`](Render::image_all_channels) if the output is not capped, or the
+    /// render is already downscaled to the selected factor.
+    pub fn image_scaled(&self) -> FrameBuffer {
+        let fb = self.image_all_channels();
+        if self.output_downscale == 1 {
+            fb
+        } else {
+            fb.downsample(self.output_downscale as usize)
+        }
+    }
+
+    /// Creates a buffer of integer samples with interleaved channels, with orientation applied.
+    ///
+    /// Samples are copied from the codestream without going through floats, so lossless images
+    /// which are not XYB encoded round trip exactly. All extra channels are included, and
+    /// samples are clamped to the range of the bit depth of each channel.
+    ///
+    /// Returns `None` if any of the channels doesn't have integer samples, such as for lossy or
+    /// XYB encoded images, channels with float bit depth, or when the image is transformed to a
+    /// color encoding other than the signalled one.
+    pub fn image_integer(&self) -> Option<IntegerFrameBuffer> {
+        let grids: Vec<_> = self.image.buffer().iter().collect();
+        let mut bit_depth = vec![self.color_bit_depth; self.image.color_channels()];
+        for ec in &self.extra_channels {
+            bit_depth.push(ec.bit_depth);
+        }
+        let regions: Vec<_> = self
+            .image
+            .regions_and_shifts()
+            .iter()
+            .map(|(region, _)| *region)
+            .collect();
+
+        IntegerFrameBuffer::from_grids(
+            &grids,
+            &bit_depth,
+            &regions,
+            self.target_frame_region,
+            self.orientation,
+        )
+    }
+
     /// Creates a separate buffer by channel, with orientation applied.
     ///
     /// All extra channels are included.
@@ -1195,6 +3075,368 @@ impl Render {
         let color_channels = self.image.color_channels();
         (&self.extra_channels, &self.image.buffer()[color_channels..])
     }
+
+    /// Returns the first extra channel with the given name.
+    ///
+    /// Orientation is not applied.
+    pub fn extra_channel_by_name(&self, name: &str) -> Option<(&ExtraChannel, &ImageBuffer)> {
+        let (ec_info, buffers) = self.extra_channels();
+        ec_info
+            .iter()
+            .zip(buffers)
+            .find(|(ec, _)| ec.name() == name)
+    }
+}
+
+/// Compressed size statistics of a keyframe.
+///
+/// A keyframe may be composed of multiple frames, such as reference-only frames and frames with
+/// zero duration. Sizes include all of those frames.
+#[derive(Debug, Clone)]
+pub struct KeyframeInfo {
+    keyframe_index: usize,
+    frame_index: usize,
+    first_frame_index: usize,
+    offset: usize,
+    header_bytes: usize,
+    group_bytes: usize,
+    duration: u32,
+}
+
+impl KeyframeInfo {
+    /// Returns the keyframe index.
+    #[inline]
+    pub fn keyframe_index(&self) -> usize {
+        self.keyframe_index
+    }
+
+    /// Returns the frame index of the keyframe.
+    #[inline]
+    pub fn frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    /// Returns the range of frame indices which make up the keyframe.
+    #[inline]
+    pub fn frame_indices(&self) -> std::ops::RangeInclusive<usize> {
+        self.first_frame_index..=self.frame_index
+    }
+
+    /// Returns the offset of the first frame within codestream, in bytes.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the total size of frame headers and TOCs, in bytes.
+    #[inline]
+    pub fn header_bytes(&self) -> usize {
+        self.header_bytes
+    }
+
+    /// Returns the total size of groups, in bytes.
+    #[inline]
+    pub fn group_bytes(&self) -> usize {
+        self.group_bytes
+    }
+
+    /// Returns the compressed size of the keyframe, in bytes.
+    #[inline]
+    pub fn total_bytes(&self) -> usize {
+        self.header_bytes + self.group_bytes
+    }
+
+    /// Returns the duration of the keyframe, in ticks.
+    #[inline]
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+}
+
+/// Metadata of a single frame.
+#[derive(Debug, Clone)]
+pub struct FrameInfo {
+    index: usize,
+    frame_type: jxl_frame::header::FrameType,
+    is_keyframe: bool,
+    blend_info: FrameBlendInfo,
+    duration: u32,
+    offset: usize,
+    header_bytes: usize,
+    group_bytes: usize,
+    is_indexed: bool,
+}
+
+impl FrameInfo {
+    /// Returns the frame index.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the type of the frame.
+    #[inline]
+    pub fn frame_type(&self) -> jxl_frame::header::FrameType {
+        self.frame_type
+    }
+
+    /// Returns whether the frame is a keyframe that is displayed.
+    #[inline]
+    pub fn is_keyframe(&self) -> bool {
+        self.is_keyframe
+    }
+
+    /// Returns how the frame is blended onto the canvas.
+    #[inline]
+    pub fn blend_info(&self) -> FrameBlendInfo {
+        self.blend_info
+    }
+
+    /// Returns the duration of the frame, in ticks.
+    #[inline]
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    /// Returns the offset of the frame within codestream, in bytes.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the size of the frame header and TOC, in bytes.
+    #[inline]
+    pub fn header_bytes(&self) -> usize {
+        self.header_bytes
+    }
+
+    /// Returns the total size of groups listed in TOC, in bytes.
+    #[inline]
+    pub fn group_bytes(&self) -> usize {
+        self.group_bytes
+    }
+
+    /// Returns whether the frame is listed in the frame index of the container.
+    ///
+    /// Decoding can start from indexed frames. See [`JxlImage::frame_index_box`].
+    #[inline]
+    pub fn is_indexed(&self) -> bool {
+        self.is_indexed
+    }
+}
+
+/// Blending mode of color channels of a frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameBlendMode {
+    /// The frame replaces the source canvas.
+    Replace,
+    /// The frame is added to the source canvas.
+    Add,
+    /// The frame is alpha blended over the source canvas.
+    Blend,
+    /// The frame, multiplied by alpha, is added to the source canvas.
+    MulAdd,
+    /// The frame is multiplied with the source canvas.
+    Mul,
+}
+
+impl From<jxl_frame::header::BlendMode> for FrameBlendMode {
+    fn from(value: jxl_frame::header::BlendMode) -> Self {
+        use jxl_frame::header::BlendMode;
+
+        match value {
+            BlendMode::Replace => Self::Replace,
+            BlendMode::Add => Self::Add,
+            BlendMode::Blend => Self::Blend,
+            BlendMode::MulAdd => Self::MulAdd,
+            BlendMode::Mul => Self::Mul,
+        }
+    }
+}
+
+/// Describes how a frame is blended onto the canvas.
+///
+/// Tools converting animations to other formats can use this to preserve blending and disposal
+/// semantics, instead of flattening every frame to the full canvas. Frame region is in the
+/// coordinate of the image before orientation is applied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameBlendInfo {
+    mode: FrameBlendMode,
+    source: u32,
+    alpha_channel: Option<u32>,
+    clamp: bool,
+    x0: i32,
+    y0: i32,
+    width: u32,
+    height: u32,
+    saved_reference: Option<u32>,
+}
+
+impl FrameBlendInfo {
+    fn from_header(image_header: &ImageHeader, header: &FrameHeader) -> Self {
+        let blending_info = &header.blending_info;
+        let has_alpha_channel = !image_header.metadata.ec_info.is_empty()
+            && matches!(
+                blending_info.mode,
+                jxl_frame::header::BlendMode::Blend | jxl_frame::header::BlendMode::MulAdd
+            );
+        Self {
+            mode: blending_info.mode.into(),
+            source: blending_info.source,
+            alpha_channel: has_alpha_channel.then_some(blending_info.alpha_channel),
+            clamp: blending_info.clamp,
+            x0: header.x0,
+            y0: header.y0,
+            width: header.width,
+            height: header.height,
+            saved_reference: header.can_reference().then_some(header.save_as_reference),
+        }
+    }
+
+    /// Returns the blending mode of color channels.
+    #[inline]
+    pub fn mode(&self) -> FrameBlendMode {
+        self.mode
+    }
+
+    /// Returns the reference slot the frame is blended onto.
+    ///
+    /// Slots not saved by any previous frame are transparent black.
+    #[inline]
+    pub fn source(&self) -> u32 {
+        self.source
+    }
+
+    /// Returns the extra channel index of the alpha channel used for blending, if the blending
+    /// mode uses alpha.
+    #[inline]
+    pub fn alpha_channel(&self) -> Option<u32> {
+        self.alpha_channel
+    }
+
+    /// Returns whether blended samples are clamped to `[0, 1]`.
+    #[inline]
+    pub fn clamp(&self) -> bool {
+        self.clamp
+    }
+
+    /// Returns the horizontal offset of the frame region.
+    #[inline]
+    pub fn x0(&self) -> i32 {
+        self.x0
+    }
+
+    /// Returns the vertical offset of the frame region.
+    #[inline]
+    pub fn y0(&self) -> i32 {
+        self.y0
+    }
+
+    /// Returns the width of the frame region.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the frame region.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the reference slot the blended canvas is saved to, if any.
+    ///
+    /// A keyframe blended onto the slot it saves to keeps the previous canvas, which corresponds
+    /// to not disposing the previous frame.
+    #[inline]
+    pub fn saved_reference(&self) -> Option<u32> {
+        self.saved_reference
+    }
+}
+
+/// Loading progress of a keyframe, returned by [`JxlImage::frame_progress`].
+///
+/// Progress is measured by compressed data loaded so far; groups are decoded when the keyframe
+/// is rendered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameProgress {
+    keyframe_index: usize,
+    num_passes: u32,
+    completed_passes: u32,
+    lf_loaded: bool,
+    loaded_groups: usize,
+    total_groups: usize,
+    loaded_bytes: usize,
+    total_bytes: usize,
+}
+
+impl FrameProgress {
+    /// Returns the keyframe index.
+    #[inline]
+    pub fn keyframe_index(&self) -> usize {
+        self.keyframe_index
+    }
+
+    /// Returns the number of passes of the keyframe.
+    #[inline]
+    pub fn num_passes(&self) -> u32 {
+        self.num_passes
+    }
+
+    /// Returns the number of passes of which all groups are loaded.
+    ///
+    /// Passes are counted from the first one, so a pass loaded before a previous pass is not
+    /// counted.
+    #[inline]
+    pub fn completed_passes(&self) -> u32 {
+        self.completed_passes
+    }
+
+    /// Returns whether LF global, LF groups and HF global sections are loaded, meaning that at
+    /// least 8x downsampled image is available.
+    #[inline]
+    pub fn is_lf_loaded(&self) -> bool {
+        self.lf_loaded
+    }
+
+    /// Returns the number of groups loaded completely.
+    #[inline]
+    pub fn loaded_groups(&self) -> usize {
+        self.loaded_groups
+    }
+
+    /// Returns the number of groups listed in TOC.
+    #[inline]
+    pub fn total_groups(&self) -> usize {
+        self.total_groups
+    }
+
+    /// Returns the number of bytes of group data loaded so far.
+    #[inline]
+    pub fn loaded_bytes(&self) -> usize {
+        self.loaded_bytes
+    }
+
+    /// Returns the total size of groups listed in TOC, in bytes.
+    #[inline]
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Returns the fraction of group data loaded so far, in range of `[0, 1]`.
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.loaded_bytes as f32 / self.total_bytes as f32
+        }
+    }
+
+    /// Returns whether all groups of the keyframe are loaded.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.loaded_groups == self.total_groups
+    }
 }
 
 /// Extra channel of the image.
@@ -1237,8 +3479,29 @@ impl ExtraChannel {
     }
 }
 
+/// Rendering settings of a spot colour channel.
+///
+/// See [`JxlImage::set_spot_color_enabled`] and [`JxlImage::set_spot_color_solidity`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpotColorSettings {
+    /// Whether the channel is composited onto color channels.
+    pub enabled: bool,
+    /// Solidity overriding the one signalled in the image header, if any.
+    pub solidity: Option<f32>,
+}
+
+impl Default for SpotColorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            solidity: None,
+        }
+    }
+}
+
 /// Cropping region information.
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CropInfo {
     pub width: u32,
     pub height: u32,
@@ -0,0 +1,160 @@
+use crate::{CropInfo, EnumColourEncoding, JxlImage, Result};
+
+/// Decoding options, accepted by every frontend of jxl-oxide.
+///
+/// This struct collects options which are otherwise set using builder methods and setters of
+/// [`JxlImage`], so that configuration can be shared between the library, the CLI, WebAssembly
+/// bindings and `image` integration. With `serde` feature enabled, options can be serialized, e.g.
+/// to be attached to bug reports.
+///
+/// Pass options to [`JxlImageBuilder::options`][crate::JxlImageBuilder::options] to apply them
+/// when the image is initialized, or call [`JxlImage::apply_options`] for an initialized image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    /// Region to render.
+    ///
+    /// Zero width or height is replaced with the width or height of the image. `None`, or the
+    /// region with both width and height of zero, renders the whole image.
+    pub crop: Option<CropInfo>,
+    /// Color encoding to render the image in.
+    pub target_color_encoding: Option<EnumColourEncoding>,
+    /// ICC profile to render the image in. Takes precedence over `target_color_encoding`.
+    pub target_icc: Option<Vec<u8>>,
+    /// Whether to render CMYK images in sRGB, if no target color is given.
+    pub cmyk_to_srgb: bool,
+    /// Whether to render spot color channels. Ignored for grayscale images.
+    pub render_spot_color: bool,
+    /// Whether to render in linear light. See [`JxlImage::set_render_linear`].
+    pub render_linear: bool,
+    /// Whether to synthesize noise. See [`JxlImage::set_render_noise`].
+    pub render_noise: bool,
+    /// Number of threads to use. `None` uses the default thread pool.
+    ///
+    /// This is ignored if `rayon` feature is disabled.
+    pub num_threads: Option<usize>,
+    /// Maximum number of tasks running at the same time. See
+    /// [`JxlImageBuilder::max_concurrency`][crate::JxlImageBuilder::max_concurrency].
+    pub max_concurrency: Option<usize>,
+    /// Approximate memory limit of sample buffers, in bytes. See [`crate::AllocTracker`].
+    pub approx_memory_limit: Option<usize>,
+    /// Memory limit of buffers other than sample buffers, in bytes. See
+    /// [`JxlImageBuilder::memory_limit_bytes`][crate::JxlImageBuilder::memory_limit_bytes].
+    pub memory_limit_bytes: Option<usize>,
+    /// Maximum LZ77 distance allowed in Modular streams. See
+    /// [`JxlImageBuilder::lz77_distance_limit`][crate::JxlImageBuilder::lz77_distance_limit].
+    pub lz77_distance_limit: Option<u32>,
+    /// Downscaling factor the image is going to be displayed with. See
+    /// [`JxlImageBuilder::downscale_target`][crate::JxlImageBuilder::downscale_target].
+    pub downscale_target: u8,
+    /// Maximum width and height of the output. See
+    /// [`JxlImageBuilder::max_output_dimension`][crate::JxlImageBuilder::max_output_dimension].
+    pub max_output_dimension: Option<u32>,
+    /// Maximum total size of cached renders, in bytes. See
+    /// [`JxlImageBuilder::render_cache_budget`][crate::JxlImageBuilder::render_cache_budget].
+    pub render_cache_budget: Option<usize>,
+    /// Whether to decode sequentially using portable scalar code paths. See
+    /// [`JxlImageBuilder::force_scalar`][crate::JxlImageBuilder::force_scalar].
+    pub deterministic: bool,
+    /// Whether to substitute corrupt keyframes with the previous keyframe. See
+    /// [`JxlImageBuilder::error_isolation`][crate::JxlImageBuilder::error_isolation].
+    pub error_isolation: bool,
+    /// Whether to treat corrupt pass groups as missing. See
+    /// [`JxlImageBuilder::skip_corrupt_groups`][crate::JxlImageBuilder::skip_corrupt_groups].
+    pub skip_corrupt_groups: bool,
+    /// Whether to skip restoration filters. See
+    /// [`JxlImageBuilder::skip_restoration_filters`][crate::JxlImageBuilder::skip_restoration_filters].
+    pub skip_restoration_filters: bool,
+    /// Whether to skip auxiliary extra channels. See
+    /// [`JxlImageBuilder::skip_extra_channels`][crate::JxlImageBuilder::skip_extra_channels].
+    pub skip_extra_channels: bool,
+    /// Whether to recover from common mistakes in containers. See
+    /// [`JxlImageBuilder::lenient_container`][crate::JxlImageBuilder::lenient_container].
+    pub lenient_container: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            crop: None,
+            target_color_encoding: None,
+            target_icc: None,
+            cmyk_to_srgb: false,
+            render_spot_color: true,
+            render_linear: false,
+            render_noise: true,
+            num_threads: None,
+            max_concurrency: None,
+            approx_memory_limit: None,
+            memory_limit_bytes: None,
+            lz77_distance_limit: None,
+            downscale_target: 0,
+            max_output_dimension: None,
+            render_cache_budget: None,
+            deterministic: false,
+            error_isolation: false,
+            skip_corrupt_groups: false,
+            skip_restoration_filters: false,
+            skip_extra_channels: false,
+            lenient_container: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Creates default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cropping region resolved against the given image dimensions, or `None` if the
+    /// whole image should be rendered.
+    pub fn resolve_crop(&self, width: u32, height: u32) -> Option<CropInfo> {
+        let crop = self.crop?;
+        match (crop.width, crop.height) {
+            (0, 0) => None,
+            (0, _) => Some(CropInfo { width, ..crop }),
+            (_, 0) => Some(CropInfo { height, ..crop }),
+            _ => Some(crop),
+        }
+    }
+}
+
+/// # Decoding options
+impl JxlImage {
+    /// Applies image-level decoding options, namely cropping region and color related options.
+    ///
+    /// Options which configure the decoder itself, such as thread pool and memory limits, are
+    /// applied by [`JxlImageBuilder::options`][crate::JxlImageBuilder::options] only.
+    ///
+    /// # Errors
+    /// This function will return an error if it cannot parse the target ICC profile.
+    pub fn apply_options(&mut self, options: &DecodeOptions) -> Result<()> {
+        if let Some(icc) = &options.target_icc {
+            tracing::debug!("Setting target ICC profile");
+            self.request_icc(icc)?;
+        } else if let Some(encoding) = &options.target_color_encoding {
+            tracing::debug!(?encoding, "Setting target color space");
+            self.request_color_encoding(encoding.clone());
+        } else if options.cmyk_to_srgb && self.pixel_format().has_black() {
+            tracing::debug!("Input is CMYK; setting target color encoding to sRGB");
+            self.request_color_encoding(EnumColourEncoding::srgb(
+                jxl_color::RenderingIntent::Relative,
+            ));
+        }
+
+        if !self.image_header.metadata.grayscale() {
+            self.set_render_spot_color(options.render_spot_color);
+        }
+        self.set_render_linear(options.render_linear);
+        self.set_render_noise(options.render_noise);
+
+        if let Some(crop) = options.resolve_crop(self.width(), self.height()) {
+            tracing::debug!(?crop, "Cropped decoding");
+            self.set_image_region(crop);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use jxl_frame::data::TocGroupKind;
+use jxl_frame::header::Encoding;
+use jxl_render::{IndexedFrame, Region};
+
+use crate::fb::orient_coord;
+use crate::FrameBuffer;
+
+/// How a sample of [`BestEffortRender`] is reconstructed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SampleCoverage {
+    /// Decoded from complete data.
+    Complete,
+    /// Decoded from incomplete data, such as upsampled LF image or some of the passes.
+    Partial,
+    /// No data is available, and the sample is filled with neutral gray.
+    Missing,
+}
+
+/// Per-sample coverage of [`BestEffortRender`], with orientation applied.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    width: usize,
+    height: usize,
+    samples: Vec<SampleCoverage>,
+}
+
+impl Coverage {
+    fn filled(width: usize, height: usize, coverage: SampleCoverage) -> Self {
+        Self {
+            width,
+            height,
+            samples: vec![coverage; width * height],
+        }
+    }
+
+    /// Returns the width of the coverage map, which is the same as the width of the image.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the coverage map, which is the same as the height of the image.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the coverage of every sample, in row-major order.
+    #[inline]
+    pub fn samples(&self) -> &[SampleCoverage] {
+        &self.samples
+    }
+
+    /// Returns the coverage of the sample at the given coordinate.
+    ///
+    /// # Panics
+    /// Panics if the coordinate is out of bounds.
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> SampleCoverage {
+        assert!(x < self.width && y < self.height);
+        self.samples[x + y * self.width]
+    }
+
+    /// Returns the number of samples with the given coverage.
+    pub fn count(&self, coverage: SampleCoverage) -> usize {
+        self.samples.iter().filter(|&&c| c == coverage).count()
+    }
+
+    /// Returns whether every sample is decoded from complete data.
+    pub fn is_complete(&self) -> bool {
+        self.samples.iter().all(|&c| c == SampleCoverage::Complete)
+    }
+}
+
+/// Keyframe rendered from whatever data is available, returned by
+/// [`JxlImage::render_best_effort`][crate::JxlImage::render_best_effort].
+#[derive(Debug, Clone)]
+pub struct BestEffortRender {
+    keyframe_index: usize,
+    image: FrameBuffer,
+    coverage: Coverage,
+}
+
+impl BestEffortRender {
+    /// Returns the keyframe index.
+    #[inline]
+    pub fn keyframe_index(&self) -> usize {
+        self.keyframe_index
+    }
+
+    /// Returns the rendered image, with all extra channels and orientation applied.
+    #[inline]
+    pub fn image(&self) -> &FrameBuffer {
+        &self.image
+    }
+
+    /// Consumes the render, and returns the rendered image.
+    #[inline]
+    pub fn into_image(self) -> FrameBuffer {
+        self.image
+    }
+
+    /// Returns which samples of the image are decoded from actual data.
+    #[inline]
+    pub fn coverage(&self) -> &Coverage {
+        &self.coverage
+    }
+}
+
+/// Sample values used to fill missing regions: neutral gray for color channels, opaque for alpha
+/// channels, and zero for other extra channels.
+pub(crate) fn fill_values(color_channels: usize, alpha: impl Iterator<Item = bool>) -> Vec<f32> {
+    std::iter::repeat_n(0.5f32, color_channels)
+        .chain(alpha.map(|is_alpha| if is_alpha { 1.0 } else { 0.0 }))
+        .collect()
+}
+
+impl BestEffortRender {
+    /// Creates a render filled with neutral gray, without any data.
+    pub(crate) fn missing(
+        keyframe_index: usize,
+        width: usize,
+        height: usize,
+        fill: &[f32],
+    ) -> Self {
+        let mut image = FrameBuffer::new(width, height, fill.len());
+        for pixel in image.buf_mut().chunks_exact_mut(fill.len()) {
+            pixel.copy_from_slice(fill);
+        }
+        Self {
+            keyframe_index,
+            image,
+            coverage: Coverage::filled(width, height, SampleCoverage::Missing),
+        }
+    }
+
+    /// Creates a render from a complete render of the keyframe.
+    pub(crate) fn complete(keyframe_index: usize, image: FrameBuffer) -> Self {
+        let coverage = Coverage::filled(image.width(), image.height(), SampleCoverage::Complete);
+        Self {
+            keyframe_index,
+            image,
+            coverage,
+        }
+    }
+
+    /// Creates a render from a render of partially loaded frame, filling groups without data.
+    ///
+    /// `frame_region` is the region of the frame copied to the image, before orientation is
+    /// applied. Samples outside the frame come from the previous canvas, which is complete.
+    pub(crate) fn partial(
+        keyframe_index: usize,
+        mut image: FrameBuffer,
+        frame: &IndexedFrame,
+        frame_region: Region,
+        orientation: u32,
+        fill: &[f32],
+    ) -> Self {
+        let groups = group_coverage(frame);
+        let header = frame.header();
+        let upsampling = header.upsampling as i64;
+        let channels = image.channels();
+        let out_width = image.width();
+        let width = frame_region.width as usize;
+        let height = frame_region.height as usize;
+
+        let mut coverage =
+            Coverage::filled(image.width(), image.height(), SampleCoverage::Complete);
+        let buf = image.buf_mut();
+        for y in 0..height {
+            let fy = frame_region.top as i64 + y as i64;
+            for x in 0..width {
+                let fx = frame_region.left as i64 + x as i64;
+                if fx < 0 || fy < 0 || fx >= header.width as i64 || fy >= header.height as i64 {
+                    continue;
+                }
+
+                let group_idx =
+                    header.group_idx_from_coord((fx / upsampling) as u32, (fy / upsampling) as u32);
+                let sample_coverage = group_idx
+                    .map(|idx| groups[idx as usize])
+                    .unwrap_or(SampleCoverage::Missing);
+                if sample_coverage == SampleCoverage::Complete {
+                    continue;
+                }
+
+                let (outx, outy) = orient_coord(orientation, x, y, width, height);
+                let idx = outx + outy * out_width;
+                coverage.samples[idx] = sample_coverage;
+                if sample_coverage == SampleCoverage::Missing {
+                    buf[idx * channels..][..channels].copy_from_slice(fill);
+                }
+            }
+        }
+
+        Self {
+            keyframe_index,
+            image,
+            coverage,
+        }
+    }
+}
+
+/// Computes coverage of each group of the frame, from the groups loaded so far.
+fn group_coverage(frame: &IndexedFrame) -> Vec<SampleCoverage> {
+    let header = frame.header();
+    let toc = frame.toc();
+    let num_groups = header.num_groups() as usize;
+    let num_passes = header.passes.num_passes as usize;
+    let num_loaded_groups = if frame.is_loading_done() {
+        usize::MAX
+    } else {
+        frame.num_loaded_groups()
+    };
+
+    let mut lf_global = false;
+    let mut hf_global = header.encoding != Encoding::VarDct;
+    let mut lf_groups = vec![false; header.num_lf_groups() as usize];
+    let mut passes = vec![0usize; num_groups];
+    for group in toc.iter_bitstream_order().take(num_loaded_groups) {
+        match group.kind {
+            TocGroupKind::All => {
+                lf_global = true;
+                hf_global = true;
+                lf_groups.fill(true);
+                passes.fill(num_passes);
+            }
+            TocGroupKind::LfGlobal => lf_global = true,
+            TocGroupKind::LfGroup(idx) => lf_groups[idx as usize] = true,
+            TocGroupKind::HfGlobal => hf_global = true,
+            TocGroupKind::GroupPass { group_idx, .. } => passes[group_idx as usize] += 1,
+        }
+    }
+
+    let is_vardct = header.encoding == Encoding::VarDct;
+    (0..num_groups)
+        .map(|group_idx| {
+            let lf_group_idx = header.lf_group_idx_from_group_idx(group_idx as u32);
+            if !lf_global || !lf_groups[lf_group_idx as usize] {
+                SampleCoverage::Missing
+            } else if hf_global && passes[group_idx] == num_passes {
+                SampleCoverage::Complete
+            } else if is_vardct || passes[group_idx] > 0 {
+                SampleCoverage::Partial
+            } else {
+                SampleCoverage::Missing
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,165 @@
+use jxl_bitstream::container::box_header::ContainerBoxType;
+use jxl_bitstream::{BitstreamKind, ContainerDetectingReader, ResumePosition};
+
+use crate::aux_box::{AuxBoxData, AuxBoxList};
+use crate::{InitializeResult, JxlImage, JxlImageBuilder, Result};
+
+/// Snapshot of parsing progress of [`JxlImage`], created by [`JxlImage::snapshot`].
+///
+/// A snapshot records how far the input has been parsed, so that decoding can be resumed with
+/// [`JxlImageBuilder::resume`] without feeding previous bytes again. This is useful for services
+/// which receive images in chunks and can't keep the decoder alive in the meantime. With `serde`
+/// feature enabled, snapshots can be serialized.
+///
+/// Snapshots contain the image header and completed auxiliary boxes, but not the frames loaded so
+/// far. Resumed images continue from the first frame not completely loaded, with frame indices
+/// starting from zero. JPEG bitstream reconstruction data is not included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecoderSnapshot {
+    codestream_header: Vec<u8>,
+    codestream_offset: usize,
+    input_offset: u64,
+    container: bool,
+    in_codestream_box: bool,
+    codestream_bytes_left: Option<u64>,
+    jxlp_index: Option<u32>,
+    jxlp_finished: bool,
+    loaded_frames: usize,
+    end_of_image: bool,
+    aux_boxes: Vec<([u8; 4], Option<Vec<u8>>)>,
+}
+
+impl DecoderSnapshot {
+    /// Returns the offset in the input to resume feeding bytes from.
+    #[inline]
+    pub fn input_offset(&self) -> u64 {
+        self.input_offset
+    }
+
+    /// Returns the offset in the codestream where decoding resumes.
+    #[inline]
+    pub fn codestream_offset(&self) -> usize {
+        self.codestream_offset
+    }
+
+    /// Returns the number of frames completely loaded before the snapshot was taken.
+    #[inline]
+    pub fn loaded_frames(&self) -> usize {
+        self.loaded_frames
+    }
+
+    /// Returns whether the last frame was loaded before the snapshot was taken.
+    #[inline]
+    pub fn is_end_of_image(&self) -> bool {
+        self.end_of_image
+    }
+
+    fn resume_position(&self) -> ResumePosition {
+        ResumePosition {
+            input_offset: self.input_offset,
+            kind: if self.container {
+                BitstreamKind::Container
+            } else {
+                BitstreamKind::BareCodestream
+            },
+            in_codestream_box: self.in_codestream_box,
+            codestream_bytes_left: self.codestream_bytes_left,
+            jxlp_index: self.jxlp_index,
+            jxlp_finished: self.jxlp_finished,
+        }
+    }
+}
+
+impl JxlImage {
+    /// Takes a snapshot of parsing progress, which can be used to resume decoding later.
+    ///
+    /// Bytes fed after the snapshot should start from
+    /// [`DecoderSnapshot::input_offset`]. A frame partially loaded at the time of the snapshot is
+    /// loaded again from the beginning after resuming.
+    pub fn snapshot(&self) -> Result<DecoderSnapshot> {
+        let loaded_frames = self.ctx.loaded_frames();
+        let codestream_offset = self
+            .inner
+            .frame_offsets
+            .get(loaded_frames)
+            .copied()
+            .unwrap_or(self.inner.buffer_offset);
+        let position = self
+            .reader
+            .resume_position(codestream_offset as u64)
+            .ok_or("cannot determine input position of the snapshot")?;
+
+        // Boxes read completely before the position are saved; others are read again.
+        let num_aux_boxes = self
+            .reader
+            .boxes()
+            .iter()
+            .filter(|info| {
+                let ty = info.box_type();
+                let is_aux_box = ty != ContainerBoxType::JXL
+                    && ty != ContainerBoxType::CODESTREAM
+                    && ty != ContainerBoxType::PARTIAL_CODESTREAM
+                    && ty != ContainerBoxType::JPEG_RECONSTRUCTION;
+                let box_end = info
+                    .box_size()
+                    .map(|size| info.offset() + info.header_size() as u64 + size);
+                is_aux_box && box_end.is_some_and(|end| end <= position.input_offset)
+            })
+            .count();
+        let aux_boxes = self
+            .inner
+            .aux_boxes
+            .iter()
+            .take(num_aux_boxes)
+            .map(|(ty, data)| {
+                let data = match data {
+                    AuxBoxData::Data(buf) => Some(buf.to_vec()),
+                    _ => None,
+                };
+                (ty, data)
+            })
+            .collect();
+
+        Ok(DecoderSnapshot {
+            codestream_header: self.inner.codestream_header.clone(),
+            codestream_offset,
+            input_offset: position.input_offset,
+            container: position.kind == BitstreamKind::Container,
+            in_codestream_box: position.in_codestream_box,
+            codestream_bytes_left: position.codestream_bytes_left,
+            jxlp_index: position.jxlp_index,
+            jxlp_finished: position.jxlp_finished,
+            loaded_frames,
+            end_of_image: self.inner.end_of_image,
+            aux_boxes,
+        })
+    }
+}
+
+impl JxlImageBuilder {
+    /// Consumes the builder, and creates a JPEG XL image decoder resuming from the snapshot.
+    ///
+    /// Bytes fed into the returned image should start from [`DecoderSnapshot::input_offset`].
+    /// Frames loaded before the snapshot are not available, so frames which blend onto them or
+    /// copy patches from them result in an error.
+    pub fn resume(self, snapshot: &DecoderSnapshot) -> Result<JxlImage> {
+        let mut uninit = self.build_uninit();
+        let lenient = uninit.reader.is_lenient();
+        uninit.reader = ContainerDetectingReader::resume_at(snapshot.resume_position());
+        uninit.reader.set_lenient(lenient);
+        uninit.buffer = snapshot.codestream_header.clone();
+        uninit.aux_boxes = AuxBoxList::restore(&snapshot.aux_boxes);
+
+        let mut image = match uninit.try_init()? {
+            InitializeResult::Initialized(image) => image,
+            InitializeResult::NeedMoreData(_) => {
+                return Err("snapshot contains incomplete image header".into());
+            }
+        };
+        image.inner.buffer_offset = snapshot.codestream_offset;
+        image.inner.end_of_image = snapshot.end_of_image;
+        image.inner.saved_references = Some([false; 4]);
+        Ok(image)
+    }
+}
@@ -0,0 +1,56 @@
+use jxl_frame::data::TocGroup;
+use jxl_render::{FrameStats, IndexedFrame};
+
+/// Decoding statistics of a frame, returned by [`JxlImage::decoder_stats`][crate::JxlImage::decoder_stats].
+#[derive(Debug, Copy, Clone)]
+pub struct FrameDecoderStats<'image> {
+    frame: &'image IndexedFrame,
+}
+
+impl<'image> FrameDecoderStats<'image> {
+    pub(crate) fn new(frame: &'image IndexedFrame) -> Self {
+        Self { frame }
+    }
+
+    /// Returns the index of the frame.
+    pub fn frame_index(&self) -> usize {
+        self.frame.index()
+    }
+
+    /// Returns whether the frame is a keyframe, which is displayed directly.
+    pub fn is_keyframe(&self) -> bool {
+        self.frame.header().is_keyframe()
+    }
+
+    /// Returns counters and timings collected while rendering the frame.
+    pub fn stats(&self) -> &'image FrameStats {
+        self.frame.stats()
+    }
+
+    /// Returns the sections of the frame with their sizes in bytes, in bitstream order.
+    pub fn sections(&self) -> impl Iterator<Item = TocGroup> + 'image {
+        self.frame.toc().iter_bitstream_order()
+    }
+
+    /// Returns whether any entropy decoder of Modular streams in the frame has LZ77 enabled.
+    ///
+    /// Modular streams are tracked if they use the global MA tree, or are nested in the global
+    /// Modular stream.
+    pub fn uses_lz77(&self) -> bool {
+        self.frame.lz77_tracker().is_lz77_used()
+    }
+
+    /// Returns the largest LZ77 distance used by Modular streams decoded so far, in symbols.
+    ///
+    /// Returns 0 if no LZ77 copy has been decoded. Streams requiring the full window use distances
+    /// close to 1 MiB symbols. This can be compared against
+    /// [`JxlImageBuilder::lz77_distance_limit`][crate::JxlImageBuilder::lz77_distance_limit].
+    pub fn max_lz77_distance(&self) -> u32 {
+        self.frame.lz77_tracker().max_distance()
+    }
+
+    /// Returns the total size of the sections of the frame in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.sections().map(|group| group.size as u64).sum()
+    }
+}
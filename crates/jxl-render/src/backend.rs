@@ -0,0 +1,12 @@
+/// Backend which renders frames.
+///
+/// Only the CPU backend is available at the moment. The enum is non-exhaustive so that other
+/// backends, such as a GPU backend running IDCT and filters as compute shaders, can be added
+/// later.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Renders frames on the CPU, using the thread pool of the render context.
+    #[default]
+    Cpu,
+}
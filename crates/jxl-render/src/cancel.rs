@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::{Error, Result};
+
+/// Cooperative cancellation token for rendering.
+///
+/// The renderer checks the token before decoding each LF group and pass group, and before
+/// rendering each frame, including reference frames rendered in the background. Once the token is
+/// cancelled, rendering fails with [`Error::Cancelled`].
+///
+/// Cancelled renders keep what's been decoded so far, and can be resumed after resetting the token
+/// with [`reset`][Self::reset].
+///
+/// Cloned tokens share the cancellation state, so a token can be cancelled from another thread
+/// while rendering is in progress.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+#[derive(Debug, Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+}
+
+impl CancellationToken {
+    /// Creates a new token which is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new token which is cancelled automatically after `deadline`.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        let token = Self::default();
+        token.set_deadline(Some(deadline));
+        token
+    }
+
+    /// Sets the deadline after which the token is cancelled, or removes it with `None`.
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        *self.inner.deadline.lock().unwrap() = deadline;
+    }
+
+    /// Cancels rendering associated with the token.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Resets the token to the non-cancelled state, so that cancelled renders can be resumed.
+    ///
+    /// The deadline, if any, is removed. Set a new one with [`set_deadline`][Self::set_deadline]
+    /// to resume rendering with a time limit.
+    pub fn reset(&self) {
+        self.inner.cancelled.store(false, Ordering::Relaxed);
+        self.set_deadline(None);
+    }
+
+    /// Returns whether the token is cancelled, either explicitly or by reaching the deadline.
+    pub fn is_cancelled(&self) -> bool {
+        if self.inner.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        self.inner
+            .deadline
+            .lock()
+            .unwrap()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            tracing::debug!("Rendering cancelled");
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,3 +1,7 @@
+use jxl_frame::data::TocGroupKind;
+
+use crate::IndexedFrame;
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
@@ -13,11 +17,90 @@ pub enum Error {
     InvalidReference(u32),
     NotReady,
     NotSupported(&'static str),
+    Cancelled,
+    /// Compressed data of the frame is released, so the frame cannot be rendered again.
+    FrameDataReleased,
+    /// The image uses a feature the decoder can't handle, as opposed to being corrupt.
+    UnsupportedFeature {
+        feature: UnsupportedFeature,
+        hint: &'static str,
+    },
+    /// Error occurred while decoding a group of the frame.
+    Group {
+        /// Index of the frame, counted from the first frame of the image.
+        frame: usize,
+        group: TocGroupKind,
+        source: Box<Error>,
+    },
+}
+
+/// Feature of the image which the decoder can't handle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UnsupportedFeature {
+    /// Color conversion requires a color management system, which is not available.
+    ColorManagement,
+    /// Conversion between the color encodings is not implemented.
+    ColorConversion,
+    /// The ICC profile cannot be handled without a color management system.
+    IccProfile,
+    /// The image exceeds the limits of the decoder profile.
+    ProfileLimits,
+}
+
+impl UnsupportedFeature {
+    /// Returns the unsupported feature the color management error is caused by, if any.
+    pub fn from_color_error(err: &jxl_color::Error) -> Option<Self> {
+        match err {
+            jxl_color::Error::CmsNotAvailable => Some(Self::ColorManagement),
+            jxl_color::Error::UnsupportedColorEncoding => Some(Self::ColorConversion),
+            jxl_color::Error::UnsupportedIccProfile => Some(Self::IccProfile),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::ColorManagement => "color management",
+            Self::ColorConversion => "color conversion",
+            Self::IccProfile => "ICC profile",
+            Self::ProfileLimits => "profile limits",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Non-fatal issue found while decoding a frame, which may affect the decoded image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DecodeWarning {
+    /// Inverse Squeeze transform overflowed, and samples are clamped to the range of the sample
+    /// type.
+    SqueezeOverflow {
+        /// Number of clamped samples.
+        clamped_samples: usize,
+    },
+}
+
+impl std::fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SqueezeOverflow { clamped_samples } => write!(
+                f,
+                "inverse squeeze overflowed, {clamped_samples} sample(s) are clamped"
+            ),
+        }
+    }
 }
 
 impl From<jxl_bitstream::Error> for Error {
     fn from(err: jxl_bitstream::Error) -> Self {
-        Self::Bitstream(err)
+        match err {
+            jxl_bitstream::Error::ProfileConformance(hint) => Self::profile_limits(hint),
+            err => Self::Bitstream(err),
+        }
     }
 }
 
@@ -35,19 +118,35 @@ impl From<jxl_grid::Error> for Error {
 
 impl From<jxl_modular::Error> for Error {
     fn from(err: jxl_modular::Error) -> Self {
-        Self::Modular(err)
+        match err {
+            jxl_modular::Error::Bitstream(jxl_bitstream::Error::ProfileConformance(hint)) => {
+                Self::profile_limits(hint)
+            }
+            err => Self::Modular(err),
+        }
     }
 }
 
 impl From<jxl_frame::Error> for Error {
     fn from(err: jxl_frame::Error) -> Self {
-        Self::Frame(err)
+        match err {
+            jxl_frame::Error::Bitstream(jxl_bitstream::Error::ProfileConformance(hint))
+            | jxl_frame::Error::Modular(jxl_modular::Error::Bitstream(
+                jxl_bitstream::Error::ProfileConformance(hint),
+            )) => Self::profile_limits(hint),
+            err => Self::Frame(err),
+        }
     }
 }
 
 impl From<jxl_color::Error> for Error {
     fn from(err: jxl_color::Error) -> Self {
-        Self::Color(err)
+        match err {
+            jxl_color::Error::Bitstream(jxl_bitstream::Error::ProfileConformance(hint)) => {
+                Self::profile_limits(hint)
+            }
+            err => Self::Color(err),
+        }
     }
 }
 
@@ -70,6 +169,16 @@ impl std::fmt::Display for Error {
             InvalidReference(idx) => write!(f, "invalid reference {idx}"),
             NotReady => write!(f, "image is not ready to be rendered"),
             NotSupported(msg) => write!(f, "not supported: {}", msg),
+            Cancelled => write!(f, "rendering cancelled"),
+            FrameDataReleased => write!(f, "compressed frame data is released"),
+            UnsupportedFeature { feature, hint } => {
+                write!(f, "unsupported feature ({feature}): {hint}")
+            }
+            Group {
+                frame,
+                group,
+                source,
+            } => write!(f, "{group} of frame #{frame}: {source}"),
         }
     }
 }
@@ -85,12 +194,61 @@ impl std::error::Error for Error {
             Modular(err) => Some(err),
             Frame(err) => Some(err),
             Color(err) => Some(err),
+            Group { source, .. } => Some(&**source),
             _ => None,
         }
     }
 }
 
 impl Error {
+    fn profile_limits(hint: &'static str) -> Self {
+        Self::UnsupportedFeature {
+            feature: UnsupportedFeature::ProfileLimits,
+            hint,
+        }
+    }
+
+    /// Marks the error as occurred while decoding the group of the frame.
+    ///
+    /// Only errors caused by the group data are marked; errors such as incomplete frame and
+    /// cancellation are returned as is.
+    pub(crate) fn in_group(self, frame: &IndexedFrame, group: TocGroupKind) -> Self {
+        let group = if frame.toc().is_single_entry() {
+            TocGroupKind::All
+        } else {
+            group
+        };
+        match self {
+            Error::Frame(jxl_frame::Error::Buffer(_)) => self,
+            Error::Bitstream(_) | Error::Decoder(_) | Error::Modular(_) | Error::Frame(_) => {
+                Error::Group {
+                    frame: frame.index(),
+                    group,
+                    source: Box::new(self),
+                }
+            }
+            err => err,
+        }
+    }
+
+    /// Returns the group of the frame the error occurred in, if known.
+    pub fn group(&self) -> Option<TocGroupKind> {
+        match self {
+            Error::Group { group, .. } => Some(*group),
+            _ => None,
+        }
+    }
+
+    /// Returns the unsupported feature the image uses, if this error is caused by one.
+    pub fn unsupported_feature(&self) -> Option<UnsupportedFeature> {
+        match self {
+            Error::UnsupportedFeature { feature, .. } => Some(*feature),
+            Error::Color(e) => UnsupportedFeature::from_color_error(e),
+            Error::Group { source, .. } => source.unsupported_feature(),
+            _ => None,
+        }
+    }
+
     pub fn unexpected_eof(&self) -> bool {
         match self {
             Error::Bitstream(e) => e.unexpected_eof(),
@@ -98,6 +256,7 @@ impl Error {
             Error::Modular(e) => e.unexpected_eof(),
             Error::Frame(e) => e.unexpected_eof(),
             Error::Color(e) => e.unexpected_eof(),
+            Error::Group { source, .. } => source.unexpected_eof(),
             _ => false,
         }
     }
@@ -9,6 +9,7 @@ use crate::{ImageWithRegion, Region, Result};
 // Padding for 5x5 kernel convolution step
 const PADDING: usize = 2;
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_noise(
     header: &FrameHeader,
     visible_frames_num: usize,
@@ -16,6 +17,7 @@ pub fn render_noise(
     base_correlations_xb: Option<(f32, f32)>,
     grid: &mut ImageWithRegion,
     params: &NoiseParameters,
+    strength: f32,
     pool: &JxlThreadPool,
 ) -> Result<()> {
     let (region, shift) = grid.regions_and_shifts()[0];
@@ -44,6 +46,9 @@ pub fn render_noise(
     let mut lut = [0f32; 9];
     lut[..8].copy_from_slice(&params.lut);
     lut[8] = params.lut[7];
+    for v in &mut lut {
+        *v *= strength;
+    }
     for fy in 0..height {
         let y = fy + top;
         let row_x = grid_x.get_row_mut(fy).unwrap();
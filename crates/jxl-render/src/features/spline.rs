@@ -9,7 +9,7 @@ use crate::ImageWithRegion;
 
 /// Holds control point coordinates and dequantized DCT32 coefficients of XYB channels, σ parameter of the spline
 #[derive(Debug)]
-struct Spline {
+pub struct Spline {
     points: Vec<Point>,
     xyb_dct: [[f32; 32]; 3],
     sigma_dct: [f32; 32],
@@ -38,7 +38,28 @@ struct SplineArc {
 }
 
 impl Spline {
-    fn dequant(
+    /// Returns the control points of the spline, in frame coordinates.
+    pub fn control_points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.points.iter().map(|p| (p.x, p.y))
+    }
+
+    /// Returns the dequantized DCT32 coefficients of colors along the spline, in XYB order.
+    pub fn xyb_dct(&self) -> &[[f32; 32]; 3] {
+        &self.xyb_dct
+    }
+
+    /// Returns the dequantized DCT32 coefficients of σ, the thickness of the spline.
+    pub fn sigma_dct(&self) -> &[f32; 32] {
+        &self.sigma_dct
+    }
+}
+
+impl Spline {
+    /// Dequantizes the spline, applying color correlation of the frame.
+    ///
+    /// `base_correlations_xb` is the base correlation of X and B channels, or `None` if the frame
+    /// is not a VarDCT frame.
+    pub fn dequant(
         quant_spline: &QuantSpline,
         quant_adjust: i32,
         base_correlations_xb: Option<(f32, f32)>,
@@ -5,8 +5,10 @@ use jxl_grid::{AlignedGrid, MutableSubgrid, SharedSubgrid};
 use jxl_modular::Sample;
 use jxl_threadpool::JxlThreadPool;
 
+use super::impls::generic;
 use crate::{util, ImageWithRegion, Region};
 
+#[allow(clippy::too_many_arguments)]
 pub fn apply_epf<S: Sample>(
     fb_image: &mut ImageWithRegion,
     mut fb_scratch_arr: [AlignedGrid<f32>; 3],
@@ -15,6 +17,7 @@ pub fn apply_epf<S: Sample>(
     frame_header: &FrameHeader,
     epf_params: &EpfParams,
     pool: &jxl_threadpool::JxlThreadPool,
+    force_scalar: bool,
 ) {
     let iters = epf_params.iters;
 
@@ -41,10 +44,20 @@ pub fn apply_epf<S: Sample>(
         }
     }
 
+    let [epf_step0, epf_step1, epf_step2]: [EpfStepFn; 3] = if force_scalar {
+        [generic::epf::<0>, generic::epf::<1>, generic::epf::<2>]
+    } else {
+        [
+            super::impls::epf::<0>,
+            super::impls::epf::<1>,
+            super::impls::epf::<2>,
+        ]
+    };
+
     // Step 0
     if iters == 3 {
         tracing::debug!("Running step 0");
-        super::impls::epf::<0>(
+        epf_step0(
             &mut fb,
             &mut fb_scratch,
             color_padded_region,
@@ -59,7 +72,7 @@ pub fn apply_epf<S: Sample>(
     // Step 1
     {
         tracing::debug!("Running step 1");
-        super::impls::epf::<1>(
+        epf_step1(
             &mut fb,
             &mut fb_scratch,
             color_padded_region,
@@ -74,7 +87,7 @@ pub fn apply_epf<S: Sample>(
     // Step 2
     if iters >= 2 {
         tracing::debug!("Running step 2");
-        super::impls::epf::<2>(
+        epf_step2(
             &mut fb,
             &mut fb_scratch,
             color_padded_region,
@@ -103,6 +116,16 @@ pub fn apply_epf<S: Sample>(
     }
 }
 
+type EpfStepFn = fn(
+    &mut [MutableSubgrid<f32>; 3],
+    &mut [MutableSubgrid<f32>; 3],
+    Region,
+    &FrameHeader,
+    &[Option<&AlignedGrid<f32>>],
+    &EpfParams,
+    &JxlThreadPool,
+);
+
 pub(super) struct EpfRow<'buf, 'epf> {
     pub input_rows: [[&'buf [f32]; 7]; 3],
     #[allow(unused)]
@@ -11,6 +11,7 @@ pub fn apply_gabor_like(
     fb_scratch: &mut [AlignedGrid<f32>; 3],
     weights: [[f32; 2]; 3],
     pool: &jxl_threadpool::JxlThreadPool,
+    force_scalar: bool,
 ) {
     tracing::debug!("Running gaborish");
     let region = fb.regions_and_shifts()[0].0;
@@ -23,7 +24,11 @@ pub fn apply_gabor_like(
     let buffers = fb.as_color_floats_mut();
     let buffers = buffers.map(|g| g.as_subgrid_mut().subgrid(left..right, top..bottom));
 
-    super::impls::apply_gabor_like(buffers, fb_scratch, weights, pool);
+    if force_scalar {
+        super::impls::generic::apply_gabor_like(buffers, fb_scratch, weights, pool);
+    } else {
+        super::impls::apply_gabor_like(buffers, fb_scratch, weights, pool);
+    }
 
     let left = color_padded_region.left;
     let top = color_padded_region.top;
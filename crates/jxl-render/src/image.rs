@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
+use jxl_color::GamutStats;
 use jxl_frame::{data::GlobalModular, FrameHeader};
-use jxl_grid::{AlignedGrid, AllocTracker, MutableSubgrid};
+use jxl_grid::{AlignedGrid, AllocCategory, AllocTracker, MutableSubgrid};
 use jxl_image::{BitDepth, ImageHeader};
 use jxl_modular::{ChannelShift, Sample};
 use jxl_threadpool::JxlThreadPool;
@@ -97,8 +98,7 @@ impl ImageBuffer {
         Ok(match self {
             Self::F32(g) => g,
             Self::I32(g) => {
-                let mut out =
-                    AlignedGrid::with_alloc_tracker(g.width(), g.height(), g.tracker().as_ref())?;
+                let mut out = float_grid_for(g)?;
                 for (o, &i) in out.buf_mut().iter_mut().zip(g.buf()) {
                     *o = bit_depth.parse_integer_sample(i);
                 }
@@ -107,8 +107,7 @@ impl ImageBuffer {
                 self.as_float_mut().unwrap()
             }
             Self::I16(g) => {
-                let mut out =
-                    AlignedGrid::with_alloc_tracker(g.width(), g.height(), g.tracker().as_ref())?;
+                let mut out = float_grid_for(g)?;
                 for (o, &i) in out.buf_mut().iter_mut().zip(g.buf()) {
                     *o = bit_depth.parse_integer_sample(i as i32);
                 }
@@ -123,8 +122,7 @@ impl ImageBuffer {
         Ok(match self {
             Self::F32(g) => g,
             Self::I32(g) => {
-                let mut out =
-                    AlignedGrid::with_alloc_tracker(g.width(), g.height(), g.tracker().as_ref())?;
+                let mut out = float_grid_for(g)?;
                 for (o, &i) in out.buf_mut().iter_mut().zip(g.buf()) {
                     *o = i as f32;
                 }
@@ -133,8 +131,7 @@ impl ImageBuffer {
                 self.as_float_mut().unwrap()
             }
             Self::I16(g) => {
-                let mut out =
-                    AlignedGrid::with_alloc_tracker(g.width(), g.height(), g.tracker().as_ref())?;
+                let mut out = float_grid_for(g)?;
                 for (o, &i) in out.buf_mut().iter_mut().zip(g.buf()) {
                     *o = i as f32;
                 }
@@ -188,6 +185,28 @@ impl ImageBuffer {
         Ok([y, x, b])
     }
 
+    /// Clones rows of the buffer within the range.
+    pub(crate) fn try_clone_rows(&self, rows: std::ops::Range<usize>) -> Result<Self> {
+        fn inner<S: Default + Clone + Copy>(
+            g: &AlignedGrid<S>,
+            rows: std::ops::Range<usize>,
+        ) -> std::result::Result<AlignedGrid<S>, jxl_grid::Error> {
+            let width = g.width();
+            let tracker = g.tracker();
+            let mut out = AlignedGrid::with_alloc_tracker(width, rows.len(), tracker.as_ref())?;
+            out.buf_mut()
+                .copy_from_slice(&g.buf()[rows.start * width..rows.end * width]);
+            Ok(out)
+        }
+
+        match self {
+            Self::F32(g) => inner(g, rows).map(Self::F32),
+            Self::I32(g) => inner(g, rows).map(Self::I32),
+            Self::I16(g) => inner(g, rows).map(Self::I16),
+        }
+        .map_err(From::from)
+    }
+
     pub(crate) fn upsample_nn(&self, factor: u32) -> Result<ImageBuffer> {
         #[inline]
         fn inner<S: Copy>(
@@ -258,6 +277,18 @@ impl ImageBuffer {
     }
 }
 
+/// Allocates a float grid with the same size as `grid`, attributed to render buffers.
+fn float_grid_for<S>(grid: &AlignedGrid<S>) -> Result<AlignedGrid<f32>> {
+    let tracker = grid
+        .tracker()
+        .map(|tracker| tracker.with_category(AllocCategory::Render));
+    Ok(AlignedGrid::with_alloc_tracker(
+        grid.width(),
+        grid.height(),
+        tracker.as_ref(),
+    )?)
+}
+
 #[derive(Debug)]
 pub struct ImageWithRegion {
     buffer: Vec<ImageBuffer>,
@@ -265,6 +296,7 @@ pub struct ImageWithRegion {
     color_channels: usize,
     ct_done: bool,
     blend_done: bool,
+    gamut_stats: Option<GamutStats>,
     tracker: Option<AllocTracker>,
 }
 
@@ -276,30 +308,73 @@ impl ImageWithRegion {
             color_channels,
             ct_done: false,
             blend_done: false,
+            gamut_stats: None,
             tracker: tracker.cloned(),
         }
     }
 
     pub(crate) fn try_clone(&self) -> Result<Self> {
+        self.try_clone_channels(self.buffer.len())
+    }
+
+    /// Clones the first `count` channels of the image.
+    pub(crate) fn try_clone_channels(&self, count: usize) -> Result<Self> {
+        assert!(count >= self.color_channels);
         Ok(Self {
-            buffer: self
-                .buffer
+            buffer: self.buffer[..count]
                 .iter()
                 .map(|x| x.try_clone())
                 .collect::<std::result::Result<_, _>>()?,
-            regions: self.regions.clone(),
+            regions: self.regions[..count].to_vec(),
             color_channels: self.color_channels,
             ct_done: self.ct_done,
             blend_done: false,
+            gamut_stats: self.gamut_stats,
             tracker: self.tracker.clone(),
         })
     }
 
+    /// Clones rows of the first `count` channels within `top..bottom`, in frame coordinates.
+    ///
+    /// Regions of the channels are cropped to the rows.
+    pub(crate) fn try_clone_stripe(&self, count: usize, top: i32, bottom: i32) -> Result<Self> {
+        assert!(count >= self.color_channels);
+        let mut out = Self::new(self.color_channels, self.tracker.as_ref());
+        out.ct_done = self.ct_done;
+        for (buffer, &(region, shift)) in self.buffer[..count].iter().zip(&self.regions) {
+            debug_assert_eq!((shift.hshift(), shift.vshift()), (0, 0));
+            let stripe_top = top.clamp(region.top, region.bottom());
+            let stripe_bottom = bottom.clamp(stripe_top, region.bottom());
+            let rows = (stripe_top - region.top) as usize..(stripe_bottom - region.top) as usize;
+            let stripe_region = Region {
+                top: stripe_top,
+                height: rows.len() as u32,
+                ..region
+            };
+            out.append_channel(buffer.try_clone_rows(rows)?, stripe_region);
+        }
+        Ok(out)
+    }
+
     #[inline]
     pub(crate) fn alloc_tracker(&self) -> Option<&AllocTracker> {
         self.tracker.as_ref()
     }
 
+    /// Returns the total size of sample buffers in bytes.
+    pub(crate) fn byte_size(&self) -> usize {
+        self.buffer
+            .iter()
+            .map(|buffer| {
+                let sample_size = match buffer {
+                    ImageBuffer::F32(_) | ImageBuffer::I32(_) => 4,
+                    ImageBuffer::I16(_) => 2,
+                };
+                buffer.width() * buffer.height() * sample_size
+            })
+            .sum()
+    }
+
     #[inline]
     pub fn channels(&self) -> usize {
         self.buffer.len()
@@ -414,6 +489,22 @@ impl ImageWithRegion {
         Ok(())
     }
 
+    /// Adds a zero-filled color channel for the black channel of CMYK output, with the same
+    /// dimension as the first color channel.
+    pub(crate) fn add_black_channel(&mut self) -> Result<()> {
+        assert_eq!(self.color_channels, 3);
+
+        let (region, shift) = self.regions[0];
+        let width = self.buffer[0].width();
+        let height = self.buffer[0].height();
+        let black = ImageBuffer::zeroed_f32(width, height, self.tracker.as_ref())?;
+        self.buffer.insert(3, black);
+        self.regions.insert(3, (region, shift));
+
+        self.color_channels = 4;
+        Ok(())
+    }
+
     pub(crate) fn convert_modular_color(&mut self, bit_depth: BitDepth) -> Result<()> {
         assert!(self.buffer.len() >= self.color_channels);
         for g in self.buffer.iter_mut().take(self.color_channels) {
@@ -592,6 +683,13 @@ impl ImageWithRegion {
         self.color_channels = count;
     }
 
+    #[inline]
+    pub(crate) fn truncate_channels(&mut self, count: usize) {
+        assert!(count >= self.color_channels);
+        self.buffer.truncate(count);
+        self.regions.truncate(count);
+    }
+
     #[inline]
     pub fn color_channels(&self) -> usize {
         self.color_channels
@@ -611,6 +709,18 @@ impl ImageWithRegion {
     pub(crate) fn set_blend_done(&mut self, blend_done: bool) {
         self.blend_done = blend_done;
     }
+
+    /// Returns statistics of samples clipped while transforming to the requested color encoding,
+    /// if collected.
+    #[inline]
+    pub fn gamut_stats(&self) -> Option<GamutStats> {
+        self.gamut_stats
+    }
+
+    #[inline]
+    pub(crate) fn set_gamut_stats(&mut self, gamut_stats: GamutStats) {
+        self.gamut_stats = Some(gamut_stats);
+    }
 }
 
 impl ImageWithRegion {
@@ -735,7 +845,13 @@ impl<S: Sample> RenderedImage<S> {
 
         if !(grid.ct_done() || frame_header.save_before_ct || skip_blending && frame_header.is_last)
         {
-            util::convert_color_for_record(image_header, frame_header.do_ycbcr, &mut grid, pool)?;
+            util::convert_color_for_record(
+                image_header,
+                frame_header.do_ycbcr,
+                &mut grid,
+                pool,
+                self.image.frame.force_scalar(),
+            )?;
         }
 
         if skip_blending {
@@ -1,19 +1,26 @@
 //! This crate is the core of jxl-oxide that provides JPEG XL renderer.
-use std::sync::Arc;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use jxl_bitstream::Bitstream;
 use jxl_color::{
     ColorEncodingWithProfile, ColorManagementSystem, ColourEncoding, ColourSpace,
-    EnumColourEncoding,
+    EnumColourEncoding, HlgDisplayParams, ToneMappingOptions,
 };
-use jxl_frame::{header::FrameType, Frame, FrameContext};
-use jxl_grid::AllocTracker;
+use jxl_frame::{
+    data::{PatchRef, TocGroupKind},
+    header::FrameType,
+    Frame, FrameContext, FrameHeader,
+};
+use jxl_grid::{AllocTracker, MemoryLimit, MemoryReservation};
 use jxl_image::{ImageHeader, ImageMetadata};
 use jxl_modular::Sample;
-use jxl_oxide_common::Bundle;
+use jxl_oxide_common::{Bundle, NamePool};
 use jxl_threadpool::JxlThreadPool;
 
+mod backend;
 mod blend;
+mod cancel;
 mod error;
 mod features;
 mod filter;
@@ -22,20 +29,36 @@ mod modular;
 mod region;
 mod render;
 mod state;
+#[cfg(feature = "stats")]
+mod stats;
 mod util;
 mod vardct;
 
-pub use error::{Error, Result};
-pub use features::render_spot_color;
+pub use backend::Backend;
+pub use cancel::CancellationToken;
+pub use error::{DecodeWarning, Error, Result, UnsupportedFeature};
+pub use features::{render_spot_color, Spline};
 pub use image::{ImageBuffer, ImageWithRegion};
 pub use region::Region;
 use state::*;
+#[cfg(feature = "stats")]
+pub use stats::FrameStats;
 
 /// Render context that tracks loaded and rendered frames.
 pub struct RenderContext {
     image_header: Arc<ImageHeader>,
     pool: JxlThreadPool,
     tracker: Option<AllocTracker>,
+    memory_limit: Option<MemoryLimit>,
+    lz77_distance_limit: Option<u32>,
+    cancellation_token: Option<CancellationToken>,
+    error_isolation: bool,
+    skip_corrupt_groups: bool,
+    force_scalar: bool,
+    render_cache_budget: Option<usize>,
+    render_cache_lru: Mutex<VecDeque<usize>>,
+    name_pool: NamePool,
+    name_reservation: Option<MemoryReservation>,
     pub(crate) frames: Vec<Arc<IndexedFrame>>,
     pub(crate) renders_wide: Vec<Arc<FrameRenderHandle<i32>>>,
     pub(crate) renders_narrow: Vec<Arc<FrameRenderHandle<i16>>>,
@@ -50,9 +73,17 @@ pub struct RenderContext {
     pub(crate) loading_render_cache_narrow: Option<RenderCache<i16>>,
     pub(crate) loading_region: Option<Region>,
     requested_image_region: Region,
+    downscale_target: u32,
+    noise_strength: f32,
+    skip_restoration_filters: bool,
+    skip_extra_channels: bool,
+    backend: Backend,
     embedded_icc: Vec<u8>,
     requested_color_encoding: ColorEncodingWithProfile,
-    cms: Box<dyn ColorManagementSystem + Send + Sync>,
+    tone_mapping: ToneMappingOptions,
+    hlg_display: HlgDisplayParams,
+    collect_gamut_stats: bool,
+    cms: Arc<dyn ColorManagementSystem + Send + Sync>,
 }
 
 impl std::fmt::Debug for RenderContext {
@@ -72,6 +103,17 @@ pub struct RenderContextBuilder {
     embedded_icc: Vec<u8>,
     pool: Option<JxlThreadPool>,
     tracker: Option<AllocTracker>,
+    memory_limit: Option<MemoryLimit>,
+    lz77_distance_limit: Option<u32>,
+    cancellation_token: Option<CancellationToken>,
+    error_isolation: bool,
+    skip_corrupt_groups: bool,
+    force_scalar: bool,
+    render_cache_budget: Option<usize>,
+    downscale_target: u32,
+    skip_restoration_filters: bool,
+    skip_extra_channels: bool,
+    backend: Backend,
 }
 
 impl RenderContextBuilder {
@@ -90,6 +132,103 @@ impl RenderContextBuilder {
         self
     }
 
+    /// Sets the memory limit applied to compressed frame data.
+    pub fn memory_limit(mut self, memory_limit: MemoryLimit) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
+
+    /// Sets the maximum LZ77 distance allowed in Modular streams.
+    ///
+    /// Frames using larger distances fail to render with an entropy decoder error. Entropy
+    /// decoders keep LZ77 windows no larger than needed for the limit. The limit of 0 rejects any
+    /// LZ77 copies.
+    pub fn lz77_distance_limit(mut self, limit: u32) -> Self {
+        self.lz77_distance_limit = Some(limit);
+        self
+    }
+
+    /// Sets the cancellation token checked during rendering.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Sets whether to isolate errors of corrupt frames.
+    ///
+    /// If enabled, a normal frame which fails to render is substituted with the canvas of the
+    /// previous keyframe, so that subsequent frames can still be rendered on top of it. The error
+    /// is reported by [`IndexedFrame::substituted_error`]. Frames without previous keyframe, and
+    /// frames whose header is corrupt, still fail.
+    pub fn error_isolation(mut self, error_isolation: bool) -> Self {
+        self.error_isolation = error_isolation;
+        self
+    }
+
+    /// Sets whether to treat corrupt pass groups as missing.
+    ///
+    /// If enabled, a pass group which fails to decode is rendered from the data decoded so far,
+    /// such as the LF image and previous passes, instead of failing the whole frame. Such groups
+    /// are reported by [`IndexedFrame::corrupt_groups`].
+    pub fn skip_corrupt_groups(mut self, skip: bool) -> Self {
+        self.skip_corrupt_groups = skip;
+        self
+    }
+
+    /// Sets whether to use portable scalar code instead of SIMD implementations selected at
+    /// runtime.
+    ///
+    /// This makes rendered images identical across CPUs with different feature sets, at the cost
+    /// of decoding speed.
+    pub fn force_scalar(mut self, force_scalar: bool) -> Self {
+        self.force_scalar = force_scalar;
+        self
+    }
+
+    /// Bounds the total size of rendered frames kept in the render context, in bytes.
+    ///
+    /// Renders of keyframes and frames they depend on are cached by default, so that keyframes
+    /// can be rendered again cheaply. With a budget set, least recently rendered frames are
+    /// evicted when cached renders exceed the budget, and rendered again from frame data on
+    /// demand. The most recently rendered keyframe is always kept.
+    pub fn render_cache_budget(mut self, bytes: usize) -> Self {
+        self.render_cache_budget = Some(bytes);
+        self
+    }
+
+    /// Sets the downscaling factor the rendered images are going to be displayed with.
+    ///
+    /// VarDCT frames skip decoding HF passes which are not needed to reach the target. With the
+    /// target of 8 or more, only the LF image is decoded. Rendered images keep the original
+    /// dimensions.
+    pub fn downscale_target(mut self, downscale_target: u32) -> Self {
+        self.downscale_target = downscale_target;
+        self
+    }
+
+    /// Skips edge-preserving filter and Gabor-like filter of every frame, trading quality for
+    /// speed.
+    pub fn skip_restoration_filters(mut self, skip: bool) -> Self {
+        self.skip_restoration_filters = skip;
+        self
+    }
+
+    /// Skips auxiliary extra channels, such as depth, thermal and CFA channels.
+    ///
+    /// See [`ExtraChannelInfo::is_auxiliary`][jxl_image::ExtraChannelInfo::is_auxiliary].
+    pub fn skip_extra_channels(mut self, skip: bool) -> Self {
+        self.skip_extra_channels = skip;
+        self
+    }
+
+    /// Sets the backend which renders frames.
+    ///
+    /// Defaults to [`Backend::Cpu`].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
     pub fn build(self, image_header: Arc<ImageHeader>) -> Result<RenderContext> {
         let color_encoding = &image_header.metadata.colour_encoding;
         let requested_color_encoding = match color_encoding {
@@ -156,6 +295,16 @@ impl RenderContextBuilder {
         Ok(RenderContext {
             image_header,
             tracker: self.tracker,
+            memory_limit: self.memory_limit,
+            lz77_distance_limit: self.lz77_distance_limit,
+            cancellation_token: self.cancellation_token,
+            error_isolation: self.error_isolation,
+            skip_corrupt_groups: self.skip_corrupt_groups,
+            force_scalar: self.force_scalar,
+            render_cache_budget: self.render_cache_budget,
+            render_cache_lru: Mutex::new(VecDeque::new()),
+            name_pool: NamePool::new(),
+            name_reservation: None,
             pool: self.pool.unwrap_or_else(JxlThreadPool::none),
             frames: Vec::new(),
             renders_wide: Vec::new(),
@@ -171,9 +320,17 @@ impl RenderContextBuilder {
             loading_render_cache_narrow: None,
             loading_region: None,
             requested_image_region: full_image_region,
+            downscale_target: self.downscale_target.max(1),
+            noise_strength: 1.0,
+            skip_restoration_filters: self.skip_restoration_filters,
+            skip_extra_channels: self.skip_extra_channels,
+            backend: self.backend,
             embedded_icc: self.embedded_icc,
             requested_color_encoding,
-            cms: Box::new(jxl_color::NullCms),
+            tone_mapping: ToneMappingOptions::default(),
+            hlg_display: HlgDisplayParams::default(),
+            collect_gamut_stats: false,
+            cms: default_cms(),
         })
     }
 }
@@ -183,12 +340,89 @@ impl RenderContext {
     pub fn alloc_tracker(&self) -> Option<&AllocTracker> {
         self.tracker.as_ref()
     }
+
+    /// Returns whether portable scalar code is used instead of SIMD implementations.
+    #[inline]
+    pub fn force_scalar(&self) -> bool {
+        self.force_scalar
+    }
+
+    /// Returns the backend which renders frames.
+    #[inline]
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Creates a render context with the same configuration, requested image region and color
+    /// encoding, without any loaded frames.
+    pub fn empty_clone(&self) -> RenderContext {
+        RenderContext {
+            image_header: Arc::clone(&self.image_header),
+            pool: self.pool.clone(),
+            tracker: self.tracker.clone(),
+            memory_limit: self.memory_limit.clone(),
+            lz77_distance_limit: self.lz77_distance_limit,
+            cancellation_token: self.cancellation_token.clone(),
+            error_isolation: self.error_isolation,
+            skip_corrupt_groups: self.skip_corrupt_groups,
+            force_scalar: self.force_scalar,
+            render_cache_budget: self.render_cache_budget,
+            render_cache_lru: Mutex::new(VecDeque::new()),
+            name_pool: NamePool::new(),
+            name_reservation: None,
+            frames: Vec::new(),
+            renders_wide: Vec::new(),
+            renders_narrow: Vec::new(),
+            keyframes: Vec::new(),
+            keyframe_in_progress: None,
+            refcounts: Vec::new(),
+            frame_deps: Vec::new(),
+            lf_frame: [usize::MAX; 4],
+            reference: [usize::MAX; 4],
+            loading_frame: None,
+            loading_render_cache_wide: None,
+            loading_render_cache_narrow: None,
+            loading_region: None,
+            requested_image_region: self.requested_image_region,
+            downscale_target: self.downscale_target,
+            noise_strength: self.noise_strength,
+            skip_restoration_filters: self.skip_restoration_filters,
+            skip_extra_channels: self.skip_extra_channels,
+            backend: self.backend.clone(),
+            embedded_icc: self.embedded_icc.clone(),
+            requested_color_encoding: self.requested_color_encoding.clone(),
+            tone_mapping: self.tone_mapping,
+            hlg_display: self.hlg_display,
+            collect_gamut_stats: self.collect_gamut_stats,
+            cms: Arc::clone(&self.cms),
+        }
+    }
+}
+
+/// Returns the CMS used until [`RenderContext::set_cms`] is called.
+///
+/// This is [`jxl_cms::PureRustCms`] with `jxl-cms` feature, so that LUT-based ICC profiles can be
+/// handled without an external CMS. Otherwise, only the color encodings jxl-color handles by
+/// itself can be converted.
+fn default_cms() -> Arc<dyn ColorManagementSystem + Send + Sync> {
+    #[cfg(feature = "jxl-cms")]
+    {
+        Arc::new(jxl_color::CachingCms::new(jxl_cms::PureRustCms))
+    }
+    #[cfg(not(feature = "jxl-cms"))]
+    {
+        Arc::new(jxl_color::NullCms)
+    }
 }
 
 impl RenderContext {
+    /// Sets color management system implementation to be used by the renderer.
+    ///
+    /// Transforms prepared by the CMS are cached and reused across render calls. The default is
+    /// `PureRustCms` of jxl-cms with `jxl-cms` feature, and `NullCms` otherwise.
     #[inline]
     pub fn set_cms(&mut self, cms: impl ColorManagementSystem + Send + Sync + 'static) {
-        self.cms = Box::new(cms);
+        self.cms = Arc::new(jxl_color::CachingCms::new(cms));
     }
 
     pub fn suggested_hdr_tf(&self) -> Option<jxl_color::TransferFunction> {
@@ -216,6 +450,72 @@ impl RenderContext {
         &self.requested_color_encoding
     }
 
+    /// Sets the tone mapping operator used when HDR images are rendered to SDR color encodings.
+    #[inline]
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMappingOptions) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    #[inline]
+    pub fn tone_mapping(&self) -> ToneMappingOptions {
+        self.tone_mapping
+    }
+
+    /// Sets the display parameters used to compute system gamma of HLG OOTF.
+    #[inline]
+    pub fn set_hlg_display(&mut self, hlg_display: HlgDisplayParams) {
+        self.hlg_display = hlg_display;
+    }
+
+    #[inline]
+    pub fn hlg_display(&self) -> HlgDisplayParams {
+        self.hlg_display
+    }
+
+    /// Sets whether to clip samples to the nominal range of the requested color encoding while
+    /// collecting statistics of clipped samples.
+    ///
+    /// Statistics are available from [`ImageWithRegion::gamut_stats`] of rendered images.
+    #[inline]
+    pub fn set_collect_gamut_stats(&mut self, collect_gamut_stats: bool) {
+        self.collect_gamut_stats = collect_gamut_stats;
+    }
+
+    #[inline]
+    pub fn collect_gamut_stats(&self) -> bool {
+        self.collect_gamut_stats
+    }
+
+    #[inline]
+    pub fn skip_extra_channels(&self) -> bool {
+        self.skip_extra_channels
+    }
+
+    /// Returns the number of extra channels included in rendered keyframes.
+    ///
+    /// Trailing auxiliary extra channels are not included if they are skipped.
+    pub fn num_output_extra_channels(&self) -> usize {
+        let ec_info = &self.metadata().ec_info;
+        if !self.skip_extra_channels {
+            return ec_info.len();
+        }
+        ec_info.len()
+            - ec_info
+                .iter()
+                .rev()
+                .take_while(|ec_info| ec_info.is_auxiliary())
+                .count()
+    }
+
+    /// Returns whether rendering in the requested color encoding needs external CMS.
+    ///
+    /// # Errors
+    /// This function will return an error if the embedded ICC profile cannot be parsed.
+    pub fn requires_external_cms(&self) -> Result<bool> {
+        let transform = self.prepare_color_transform(false)?;
+        Ok(transform.requires_cms())
+    }
+
     #[inline]
     pub fn request_image_region(&mut self, image_region: Region) {
         self.requested_image_region = image_region;
@@ -226,6 +526,39 @@ impl RenderContext {
     pub fn image_region(&self) -> Region {
         self.requested_image_region
     }
+
+    /// Sets the strength of synthesized noise, relative to the strength signalled in the
+    /// bitstream.
+    ///
+    /// Noise synthesis is skipped if the strength is zero. Negative strength and NaN are treated
+    /// as zero. Cached renders are discarded.
+    pub fn set_noise_strength(&mut self, strength: f32) {
+        // `f32::max` returns the other operand if one is NaN.
+        self.noise_strength = strength.max(0.0);
+        self.reset_cache();
+    }
+
+    #[inline]
+    pub fn noise_strength(&self) -> f32 {
+        self.noise_strength
+    }
+
+    /// Sets the downscaling factor the image is going to be displayed with.
+    ///
+    /// HF passes which are not needed to reach the factor are skipped. Cached renders are
+    /// discarded.
+    pub fn set_downscale_target(&mut self, downscale_target: u32) {
+        let downscale_target = downscale_target.max(1);
+        if self.downscale_target != downscale_target {
+            self.downscale_target = downscale_target;
+            self.reset_cache();
+        }
+    }
+
+    #[inline]
+    pub fn downscale_target(&self) -> u32 {
+        self.downscale_target
+    }
 }
 
 impl RenderContext {
@@ -286,16 +619,14 @@ impl RenderContext {
         } else {
             usize::MAX
         };
-        for ref_idx in self.reference {
+        let ref_slots = self.used_reference_slots(&frame);
+        for ref_idx in ref_slots {
             if ref_idx != usize::MAX {
                 self.refcounts[ref_idx] += 1;
             }
         }
 
-        let deps = FrameDependence {
-            lf,
-            ref_slots: self.reference,
-        };
+        let deps = FrameDependence { lf, ref_slots };
 
         if header.can_reference() {
             let ref_idx = header.save_as_reference as usize;
@@ -332,7 +663,8 @@ impl RenderContext {
             };
             let refs = reference_frames.refs.clone();
 
-            let render_op = self.render_op::<i16>(Arc::clone(&frame), reference_frames);
+            let substitute = self.substitute_render(&frame, &self.renders_narrow);
+            let render_op = self.render_op::<i16>(Arc::clone(&frame), reference_frames, substitute);
             let handle = if let Some(cache) = self.loading_render_cache_narrow.take() {
                 FrameRenderHandle::from_cache(
                     Arc::clone(&frame),
@@ -360,7 +692,8 @@ impl RenderContext {
             };
             let refs = reference_frames.refs.clone();
 
-            let render_op = self.render_op::<i32>(Arc::clone(&frame), reference_frames);
+            let substitute = self.substitute_render(&frame, &self.renders_wide);
+            let render_op = self.render_op::<i32>(Arc::clone(&frame), reference_frames, substitute);
             let handle = if let Some(cache) = self.loading_render_cache_wide.take() {
                 FrameRenderHandle::from_cache(
                     Arc::clone(&frame),
@@ -379,6 +712,46 @@ impl RenderContext {
         self.frame_deps.push(deps);
     }
 
+    /// Returns indices of frames in reference slots which the frame reads from, either by
+    /// blending or by patches. Unused slots are set to `usize::MAX`.
+    ///
+    /// All saved slots are considered used if patches of the frame cannot be parsed.
+    fn used_reference_slots(&self, frame: &IndexedFrame) -> [usize; 4] {
+        let header = frame.header();
+        let mut used = [false; 4];
+        if header.frame_type.is_normal_frame() && !header.resets_canvas {
+            for blending_info in
+                std::iter::once(&header.blending_info).chain(&header.ec_blending_info)
+            {
+                used[blending_info.source as usize] = true;
+            }
+        }
+
+        if header.flags.patches() {
+            let patch_refs = if self.narrow_modular() {
+                patch_ref_slots::<i16>(frame)
+            } else {
+                patch_ref_slots::<i32>(frame)
+            };
+            match patch_refs {
+                Some(patch_refs) => {
+                    for ref_idx in patch_refs {
+                        used[ref_idx as usize] = true;
+                    }
+                }
+                None => used = [true; 4],
+            }
+        }
+
+        let mut ref_slots = self.reference;
+        for (ref_idx, used) in ref_slots.iter_mut().zip(used) {
+            if !used {
+                *ref_idx = usize::MAX;
+            }
+        }
+        ref_slots
+    }
+
     fn loading_frame(&self) -> Option<&IndexedFrame> {
         let search_from = self
             .keyframe_in_progress
@@ -402,12 +775,15 @@ impl RenderContext {
         let image_header = &self.image_header;
 
         let bitstream_original = bitstream.clone();
-        let frame = match Frame::parse(
+        let mut frame = match Frame::parse(
             bitstream,
             FrameContext {
                 image_header: image_header.clone(),
                 tracker: self.tracker.as_ref(),
+                memory_limit: self.memory_limit.as_ref(),
+                lz77_distance_limit: self.lz77_distance_limit,
                 pool: self.pool.clone(),
+                force_scalar: self.force_scalar,
             },
         ) {
             Ok(frame) => frame,
@@ -417,21 +793,47 @@ impl RenderContext {
             }
         };
 
+        self.intern_frame_name(&mut frame)?;
+
         let header = frame.header();
         // Check if LF frame exists
         if header.flags.use_lf_frame() && self.lf_frame[header.lf_level as usize] == usize::MAX {
             return Err(Error::UninitializedLfFrame(header.lf_level));
         }
 
-        self.loading_frame = Some(IndexedFrame::new(frame, self.frames.len()));
+        self.loading_frame = Some(IndexedFrame::new(
+            frame,
+            self.frames.len(),
+            self.cancellation_token.clone(),
+            self.skip_corrupt_groups,
+        ));
         Ok(self.loading_frame.as_mut().unwrap())
     }
 
+    /// Interns the frame name, accounting newly seen names against the memory limit.
+    fn intern_frame_name(&mut self, frame: &mut Frame) -> Result<()> {
+        let name = &frame.header().name;
+        if let (false, Some(memory_limit)) = (self.name_pool.contains(name), &self.memory_limit) {
+            let bytes = name.len();
+            match &mut self.name_reservation {
+                Some(reservation) => reservation.grow(bytes)?,
+                None => self.name_reservation = Some(memory_limit.reserve(bytes)?),
+            }
+        }
+        frame.intern_name(&mut self.name_pool);
+        Ok(())
+    }
+
     pub fn current_loading_frame(&mut self) -> Option<&mut IndexedFrame> {
         self.try_finalize_current_frame();
         self.loading_frame.as_mut()
     }
 
+    /// Returns the frame being loaded, without finalizing it even if it's fully loaded.
+    pub fn pending_frame(&mut self) -> Option<&mut IndexedFrame> {
+        self.loading_frame.as_mut()
+    }
+
     pub fn finalize_current_frame(&mut self) {
         if !self.try_finalize_current_frame() {
             panic!("frame is not fully loaded");
@@ -477,11 +879,20 @@ impl RenderContext {
         &self,
         frame: Arc<IndexedFrame>,
         reference_frames: ReferenceFrames<S>,
+        substitute: Option<Arc<FrameRenderHandle<S>>>,
     ) -> RenderOp<S> {
         let prev_frame_visibility = self.get_previous_frames_visibility(&frame);
+        let downscale_target = self.downscale_target;
+        let noise_strength = self.noise_strength;
+        let skip_restoration_filters = self.skip_restoration_filters;
+        let skip_extra_channels = self.skip_extra_channels;
 
         let pool = self.pool.clone();
         Arc::new(move |mut state, image_region| {
+            if frame.is_data_released() {
+                return FrameRender::Err(Error::FrameDataReleased);
+            }
+
             if let Some(lf) = &reference_frames.lf {
                 tracing::trace!(idx = lf.frame.idx, "Spawn LF frame renderer");
                 let lf_handle = Arc::clone(&lf.image);
@@ -515,21 +926,64 @@ impl RenderContext {
                 image_region,
                 pool.clone(),
                 prev_frame_visibility,
+                downscale_target,
+                noise_strength,
+                skip_restoration_filters,
+                skip_extra_channels,
             );
-            match result {
-                Ok(grid) => FrameRender::Done(grid),
-                Err(e) if e.unexpected_eof() || matches!(e, Error::IncompleteFrame) => {
-                    if frame.is_loading_done() {
-                        FrameRender::Err(e)
-                    } else {
-                        FrameRender::InProgress(cache)
-                    }
+            let err = match result {
+                Ok(grid) => return FrameRender::Done(grid),
+                // Keep the cache so that rendering can be resumed after resetting the token.
+                Err(Error::Cancelled) => return FrameRender::InProgress(cache),
+                Err(e)
+                    if (e.unexpected_eof() || matches!(e, Error::IncompleteFrame))
+                        && !frame.is_loading_done() =>
+                {
+                    return FrameRender::InProgress(cache);
+                }
+                Err(e) => e,
+            };
+
+            let Some(substitute) = &substitute else {
+                return FrameRender::Err(err);
+            };
+            let image = Arc::clone(substitute)
+                .run_with_image()
+                .and_then(|image| image.blend(None, &pool));
+            match image {
+                Ok(image) => {
+                    tracing::warn!(
+                        index = frame.idx,
+                        %err,
+                        "Frame failed to render; substituting with previous keyframe"
+                    );
+                    frame.substituted_error.set(err).ok();
+                    FrameRender::Blended(image)
                 }
-                Err(e) => FrameRender::Err(e),
+                Err(_) => FrameRender::Err(err),
             }
         })
     }
 
+    /// Returns the render of the previous keyframe, which substitutes the frame if it fails to
+    /// render with error isolation enabled.
+    fn substitute_render<S: Sample>(
+        &self,
+        frame: &IndexedFrame,
+        renders: &[Arc<FrameRenderHandle<S>>],
+    ) -> Option<Arc<FrameRenderHandle<S>>> {
+        if !self.error_isolation || !frame.header().frame_type.is_normal_frame() {
+            return None;
+        }
+
+        let idx = frame.index();
+        let prev = self
+            .keyframes
+            .partition_point(|&k| k < idx)
+            .checked_sub(1)?;
+        Some(Arc::clone(&renders[self.keyframes[prev]]))
+    }
+
     fn get_previous_frames_visibility<'a>(&'a self, frame: &'a IndexedFrame) -> (usize, usize) {
         let frame_idx = frame.index();
         let (is_keyframe, keyframe_idx) = match self.keyframes.binary_search(&frame_idx) {
@@ -606,10 +1060,329 @@ impl RenderContext {
             .ok_or(Error::IncompleteFrame)?;
         let grid = self.render_by_index(idx)?;
         let frame = &*self.frames[idx];
+        self.update_render_cache(keyframe_idx);
 
         self.postprocess_keyframe(frame, grid)
     }
 
+    /// Renders the keyframe, and calls `f` with each stripe of the keyframe transformed to the
+    /// requested color encoding, from top to bottom.
+    ///
+    /// Stripes are as tall as groups of the keyframe, and have regions within the keyframe
+    /// covering their rows. The result is the same as [`render_keyframe`](Self::render_keyframe)
+    /// split into stripes, but only a stripe is transformed at a time, so the whole keyframe is
+    /// not copied even if it's kept as a reference frame. Gamut statistics of each stripe cover
+    /// the stripe only.
+    pub fn render_keyframe_stripes(
+        &self,
+        keyframe_idx: usize,
+        mut f: impl FnMut(&ImageWithRegion),
+    ) -> Result<()> {
+        let idx = *self
+            .keyframes
+            .get(keyframe_idx)
+            .ok_or(Error::IncompleteFrame)?;
+        let grid = self.render_by_index(idx)?;
+        let frame = &*self.frames[idx];
+        self.update_render_cache(keyframe_idx);
+
+        let frame_header = frame.header();
+        let num_channels =
+            (grid.color_channels() + self.num_output_extra_channels()).min(grid.channels());
+        let transform = self.prepare_color_transform(grid.ct_done())?;
+        let needs_transform = !grid.ct_done() && (!transform.is_noop() || frame_header.do_ycbcr);
+
+        let regions = &grid.regions_and_shifts()[..num_channels];
+        let top = regions.iter().map(|(r, _)| r.top).min().unwrap_or(0);
+        let bottom = regions.iter().map(|(r, _)| r.bottom()).max().unwrap_or(0);
+        let stripe_height = frame_header.group_dim() as i32;
+
+        let _guard = tracing::trace_span!("Render keyframe in stripes", keyframe_idx).entered();
+        let mut y = top;
+        loop {
+            frame.check_cancelled()?;
+            let stripe_bottom = y.saturating_add(stripe_height).min(bottom);
+            let mut stripe = grid.try_clone_stripe(num_channels, y, stripe_bottom)?;
+            if needs_transform {
+                self.transform_color(frame_header, &mut stripe, &transform)?;
+            }
+            f(&stripe);
+
+            y = stripe_bottom;
+            if y >= bottom {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the keyframe without transforming it to the requested color encoding, or returns
+    /// `None` if the color transform is already done while compositing reference frames.
+    ///
+    /// Color channels are in XYB color space if the image is XYB encoded, in YCbCr if the
+    /// keyframe is YCbCr encoded, or in the color encoding signalled in the image header
+    /// otherwise.
+    pub fn render_keyframe_raw(&self, keyframe_idx: usize) -> Result<Option<Arc<ImageWithRegion>>> {
+        let idx = *self
+            .keyframes
+            .get(keyframe_idx)
+            .ok_or(Error::IncompleteFrame)?;
+        let grid = self.render_by_index(idx)?;
+        self.update_render_cache(keyframe_idx);
+
+        if grid.ct_done() {
+            return Ok(None);
+        }
+        Ok(Some(self.strip_extra_channels(grid)?))
+    }
+
+    /// Creates a renderer which renders regions of the keyframe one at a time.
+    ///
+    /// Unlike [`request_image_region`](Self::request_image_region), rendering regions with the
+    /// renderer doesn't discard cached renders. Frames the keyframe depends on are rendered
+    /// within the requested image region and cached as usual, and decoded global data and LF
+    /// groups of the keyframe are kept in the renderer to be reused by the following regions.
+    pub fn keyframe_region_renderer(
+        &self,
+        keyframe_idx: usize,
+    ) -> Result<KeyframeRegionRenderer<'_>> {
+        let idx = *self
+            .keyframes
+            .get(keyframe_idx)
+            .ok_or(Error::IncompleteFrame)?;
+        let frame = &self.frames[idx];
+        let cache = if self.narrow_modular() {
+            RegionRenderCache::Narrow(Box::new(RenderCache::new(frame)))
+        } else {
+            RegionRenderCache::Wide(Box::new(RenderCache::new(frame)))
+        };
+        Ok(KeyframeRegionRenderer {
+            ctx: self,
+            idx,
+            cache,
+        })
+    }
+
+    /// Renders the frame within the region using the given cache, without going through the
+    /// render handle of the frame.
+    fn render_region<S: Sample>(
+        &self,
+        idx: usize,
+        renders: &[Arc<FrameRenderHandle<S>>],
+        cache: &mut RenderCache<S>,
+        image_region: Region,
+    ) -> Result<Arc<ImageWithRegion>> {
+        let frame = &self.frames[idx];
+        if frame.is_data_released() {
+            return Err(Error::FrameDataReleased);
+        }
+
+        let deps = self.frame_deps[idx];
+        let reference_frames = ReferenceFrames {
+            lf: (deps.lf != usize::MAX).then(|| Reference {
+                frame: Arc::clone(&self.frames[deps.lf]),
+                image: Arc::clone(&renders[deps.lf]),
+            }),
+            refs: deps.ref_slots.map(|r| {
+                (r != usize::MAX).then(|| Reference {
+                    frame: Arc::clone(&self.frames[r]),
+                    image: Arc::clone(&renders[r]),
+                })
+            }),
+        };
+        // Blend reference frames within the requested image region first, so that the cached
+        // blending result covers every region of the keyframe.
+        for reference in reference_frames.refs.iter().flatten() {
+            Arc::clone(&reference.image)
+                .run_with_image()?
+                .blend(None, &self.pool)?;
+        }
+
+        #[cfg(feature = "stats")]
+        let started_at = std::time::Instant::now();
+        let grid = render::render_frame(
+            frame,
+            reference_frames.clone(),
+            cache,
+            image_region,
+            self.pool.clone(),
+            self.get_previous_frames_visibility(frame),
+            // Regions are rendered at full resolution, so every HF pass is needed.
+            1,
+            self.noise_strength,
+            self.skip_restoration_filters,
+            self.skip_extra_channels,
+        )?;
+        #[cfg(feature = "stats")]
+        frame.stats().add_render_time(started_at.elapsed());
+
+        let render_op: RenderOp<S> = Arc::new(|render, _| render);
+        let handle = FrameRenderHandle::new(
+            Arc::clone(frame),
+            image_region,
+            render_op,
+            reference_frames.refs,
+        );
+        drop(handle.done_render(FrameRender::Done(grid)));
+        image::RenderedImage::new(Arc::new(handle)).blend(None, &self.pool)
+    }
+
+    /// Schedules rendering of keyframes in the range on the thread pool, and returns the number
+    /// of keyframes scheduled.
+    ///
+    /// Keyframes which are not loaded are skipped. Rendering is not scheduled if the thread pool
+    /// is single-threaded, as it would run immediately on the current thread.
+    pub fn prefetch_keyframes(&self, keyframes: std::ops::Range<usize>) -> usize {
+        if !self.pool.is_multithreaded() {
+            return 0;
+        }
+
+        let end = keyframes.end.min(self.keyframes.len());
+        let start = keyframes.start.min(end);
+        for &idx in &self.keyframes[start..end] {
+            self.spawn_renderer(idx);
+        }
+        end - start
+    }
+
+    /// Marks frames used by the keyframe as recently rendered, and evicts least recently rendered
+    /// frames if cached renders exceed the budget.
+    fn update_render_cache(&self, keyframe_idx: usize) {
+        let Some(budget) = self.render_cache_budget else {
+            return;
+        };
+        let Some(deps) = self.keyframe_dependencies(keyframe_idx) else {
+            return;
+        };
+
+        let mut lru = self.render_cache_lru.lock().unwrap();
+        for idx in deps {
+            lru.retain(|&x| x != idx);
+            lru.push_back(idx);
+        }
+
+        let mut total_bytes = lru
+            .iter()
+            .map(|&idx| self.cached_render_bytes(idx))
+            .sum::<usize>();
+        // The keyframe itself comes last, and is never evicted.
+        while total_bytes > budget && lru.len() > 1 {
+            let idx = lru.pop_front().unwrap();
+            let bytes = self.cached_render_bytes(idx);
+            tracing::debug!(idx, bytes, "Evicting cached render");
+            if self.narrow_modular() {
+                self.renders_narrow[idx].evict();
+            } else {
+                self.renders_wide[idx].evict();
+            }
+            total_bytes -= bytes;
+        }
+    }
+
+    fn cached_render_bytes(&self, index: usize) -> usize {
+        if self.narrow_modular() {
+            self.renders_narrow[index].cached_bytes()
+        } else {
+            self.renders_wide[index].cached_bytes()
+        }
+    }
+
+    /// Returns the index of the frame with the LF level used by the keyframe, following the chain
+    /// of LF frames.
+    ///
+    /// LF level of 0 refers to the keyframe itself.
+    pub fn lf_frame_index(&self, keyframe_idx: usize, lf_level: u32) -> Option<usize> {
+        let mut idx = *self.keyframes.get(keyframe_idx)?;
+        for _ in 0..lf_level {
+            idx = self.frame_deps[idx].lf;
+            if idx == usize::MAX {
+                return None;
+            }
+        }
+        Some(idx)
+    }
+
+    /// Returns indices of frames the keyframe depends on, including the keyframe itself, in
+    /// ascending order.
+    ///
+    /// Only frames actually read by blending, patches or LF frames are included, so rendering the
+    /// keyframe decodes exactly these frames. Returns `None` if the keyframe is not loaded.
+    pub fn keyframe_dependencies(&self, keyframe_idx: usize) -> Option<Vec<usize>> {
+        let idx = *self.keyframes.get(keyframe_idx)?;
+        let mut visited = vec![false; idx + 1];
+        let mut stack = vec![idx];
+        while let Some(idx) = stack.pop() {
+            if std::mem::replace(&mut visited[idx], true) {
+                continue;
+            }
+            let deps = &self.frame_deps[idx];
+            for dep in std::iter::once(deps.lf).chain(deps.ref_slots) {
+                if dep != usize::MAX {
+                    stack.push(dep);
+                }
+            }
+        }
+        Some(
+            visited
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, visited)| visited.then_some(idx))
+                .collect(),
+        )
+    }
+
+    /// Renders the LF frame with the LF level used by the keyframe, which is `8^lf_level` times
+    /// downsampled.
+    ///
+    /// Returns `None` if the keyframe doesn't use LF frame with the LF level.
+    pub fn render_lf_frame(
+        &self,
+        keyframe_idx: usize,
+        lf_level: u32,
+    ) -> Result<Option<(&IndexedFrame, Arc<ImageWithRegion>)>> {
+        let Some(idx) = self.lf_frame_index(keyframe_idx, lf_level) else {
+            return Ok(None);
+        };
+        let grid = self.render_by_index(idx)?;
+        let frame = &*self.frames[idx];
+
+        let grid = self.postprocess_keyframe(frame, grid)?;
+        Ok(Some((frame, grid)))
+    }
+
+    /// Renders the LF image of the keyframe, which is 8x downsampled from the keyframe, or returns
+    /// `None` if the keyframe is not a VarDCT frame.
+    ///
+    /// HF passes are not decoded. Only the keyframe itself is rendered without blending, and the
+    /// image doesn't have extra channels. LF frame is rendered instead if the keyframe uses one.
+    pub fn render_keyframe_lf(
+        &self,
+        keyframe_idx: usize,
+    ) -> Result<Option<(&IndexedFrame, Arc<ImageWithRegion>)>> {
+        let Some(&idx) = self.keyframes.get(keyframe_idx) else {
+            return Err(Error::IncompleteFrame);
+        };
+        let frame = &*self.frames[idx];
+        let frame_header = frame.header();
+        if frame_header.encoding != jxl_frame::header::Encoding::VarDct {
+            return Ok(None);
+        }
+        if frame_header.flags.use_lf_frame() {
+            return self.render_lf_frame(keyframe_idx, 1);
+        }
+
+        let image = if self.narrow_modular() {
+            let mut cache = RenderCache::<i16>::new(frame);
+            vardct::render_vardct_lf(frame, &mut cache, &self.pool)?
+        } else {
+            let mut cache = RenderCache::<i32>::new(frame);
+            vardct::render_vardct_lf(frame, &mut cache, &self.pool)?
+        };
+
+        let grid = self.postprocess_keyframe(frame, Arc::new(image))?;
+        Ok(Some((frame, grid)))
+    }
+
     pub fn render_loading_keyframe(&mut self) -> Result<(&IndexedFrame, Arc<ImageWithRegion>)> {
         let mut current_frame_grid = None;
         if self.loading_frame().is_some() {
@@ -636,12 +1409,73 @@ impl RenderContext {
         Ok((frame, grid))
     }
 
+    /// Releases compressed data of keyframes which are rendered and not referenced by other
+    /// frames, keeping their rendered images.
+    ///
+    /// Released keyframes cannot be rendered again, e.g. after changing requested image region or
+    /// color encoding; such renders fail with [`Error::FrameDataReleased`]. Returns the number of
+    /// keyframes released.
+    pub fn release_rendered_frame_data(&mut self) -> usize {
+        let mut count = 0usize;
+        for &idx in &self.keyframes {
+            let frame = &self.frames[idx];
+            let header = frame.header();
+            if frame.is_data_released()
+                || header.can_reference()
+                || frame.substituted_error().is_some()
+            {
+                continue;
+            }
+
+            let frame = Arc::new(frame.without_data());
+            if self.narrow_modular() {
+                let old_handle = &self.renders_narrow[idx];
+                let image = match &*old_handle.render.lock().unwrap() {
+                    FrameRender::Blended(image) => Arc::clone(image),
+                    _ => continue,
+                };
+                let render_op =
+                    self.render_op::<i16>(Arc::clone(&frame), ReferenceFrames::default(), None);
+                let handle = FrameRenderHandle::new(
+                    Arc::clone(&frame),
+                    old_handle.image_region,
+                    render_op,
+                    Default::default(),
+                );
+                drop(handle.done_render(FrameRender::Blended(image)));
+                self.renders_narrow[idx] = Arc::new(handle);
+            } else {
+                let old_handle = &self.renders_wide[idx];
+                let image = match &*old_handle.render.lock().unwrap() {
+                    FrameRender::Blended(image) => Arc::clone(image),
+                    _ => continue,
+                };
+                let render_op =
+                    self.render_op::<i32>(Arc::clone(&frame), ReferenceFrames::default(), None);
+                let handle = FrameRenderHandle::new(
+                    Arc::clone(&frame),
+                    old_handle.image_region,
+                    render_op,
+                    Default::default(),
+                );
+                drop(handle.done_render(FrameRender::Blended(image)));
+                self.renders_wide[idx] = Arc::new(handle);
+            }
+
+            tracing::debug!(idx, "Released compressed frame data");
+            self.frames[idx] = frame;
+            count += 1;
+        }
+        count
+    }
+
     pub fn reset_cache(&mut self) {
         let image_region = self.requested_image_region;
 
         self.loading_region = None;
         self.loading_render_cache_wide = None;
         self.loading_render_cache_narrow = None;
+        self.render_cache_lru.get_mut().unwrap().clear();
         for (idx, frame) in self.frames.iter().enumerate() {
             if frame.header().frame_type == FrameType::ReferenceOnly {
                 continue;
@@ -663,7 +1497,9 @@ impl RenderContext {
                 };
                 let refs = reference_frames.refs.clone();
 
-                let render_op = self.render_op::<i16>(Arc::clone(frame), reference_frames);
+                let substitute = self.substitute_render(frame, &self.renders_narrow);
+                let render_op =
+                    self.render_op::<i16>(Arc::clone(frame), reference_frames, substitute);
                 let handle =
                     FrameRenderHandle::new(Arc::clone(frame), image_region, render_op, refs);
                 self.renders_narrow[idx] = Arc::new(handle);
@@ -682,7 +1518,9 @@ impl RenderContext {
                 };
                 let refs = reference_frames.refs.clone();
 
-                let render_op = self.render_op::<i32>(Arc::clone(frame), reference_frames);
+                let substitute = self.substitute_render(frame, &self.renders_wide);
+                let render_op =
+                    self.render_op::<i32>(Arc::clone(frame), reference_frames, substitute);
                 let handle =
                     FrameRenderHandle::new(Arc::clone(frame), image_region, render_op, refs);
                 self.renders_wide[idx] = Arc::new(handle);
@@ -712,10 +1550,11 @@ impl RenderContext {
         }
 
         let lf_frame_idx = self.lf_frame[header.lf_level as usize];
+        let ref_slots = self.used_reference_slots(frame);
         if header.flags.use_lf_frame() {
             self.spawn_renderer(lf_frame_idx);
         }
-        for idx in self.reference {
+        for idx in ref_slots {
             if idx != usize::MAX {
                 self.spawn_renderer(idx);
             }
@@ -733,7 +1572,7 @@ impl RenderContext {
                     frame: Arc::clone(&self.frames[lf_frame_idx]),
                     image: Arc::clone(&self.renders_narrow[lf_frame_idx]),
                 }),
-                refs: self.reference.map(|r| {
+                refs: ref_slots.map(|r| {
                     (r != usize::MAX).then(|| Reference {
                         frame: Arc::clone(&self.frames[r]),
                         image: Arc::clone(&self.renders_narrow[r]),
@@ -749,6 +1588,10 @@ impl RenderContext {
                 image_region,
                 self.pool.clone(),
                 self.get_previous_frames_visibility(frame),
+                self.downscale_target,
+                self.noise_strength,
+                self.skip_restoration_filters,
+                self.skip_extra_channels,
             );
             match image_result {
                 Ok(image) => image,
@@ -768,7 +1611,7 @@ impl RenderContext {
                     frame: Arc::clone(&self.frames[lf_frame_idx]),
                     image: Arc::clone(&self.renders_wide[lf_frame_idx]),
                 }),
-                refs: self.reference.map(|r| {
+                refs: ref_slots.map(|r| {
                     (r != usize::MAX).then(|| Reference {
                         frame: Arc::clone(&self.frames[r]),
                         image: Arc::clone(&self.renders_wide[r]),
@@ -784,6 +1627,10 @@ impl RenderContext {
                 image_region,
                 self.pool.clone(),
                 self.get_previous_frames_visibility(frame),
+                self.downscale_target,
+                self.noise_strength,
+                self.skip_restoration_filters,
+                self.skip_extra_channels,
             );
             match image_result {
                 Ok(image) => image,
@@ -802,92 +1649,154 @@ impl RenderContext {
         }
     }
 
+    /// Prepares color transformation from the color encoding of frames to the requested one.
+    ///
+    /// `ct_done` indicates that frames are already transformed to the signalled color encoding.
+    fn prepare_color_transform(&self, ct_done: bool) -> Result<jxl_color::ColorTransform> {
+        let metadata = self.metadata();
+        let header_color_encoding = &metadata.colour_encoding;
+        let frame_color_encoding = if !ct_done && metadata.xyb_encoded {
+            ColorEncodingWithProfile::new(EnumColourEncoding::xyb(
+                jxl_color::RenderingIntent::Perceptual,
+            ))
+        } else if let ColourEncoding::Enum(encoding) = header_color_encoding {
+            ColorEncodingWithProfile::new(encoding.clone())
+        } else {
+            ColorEncodingWithProfile::with_icc(&self.embedded_icc)?
+        };
+        tracing::trace!(?frame_color_encoding);
+        tracing::trace!(requested_color_encoding = ?self.requested_color_encoding);
+
+        let mut transform = jxl_color::ColorTransform::builder();
+        transform.set_srgb_icc(!self.cms.supports_linear_tf());
+        transform.from_pq(self.suggested_hdr_tf() == Some(jxl_color::TransferFunction::Pq));
+        transform.set_tone_mapping(self.tone_mapping);
+        transform.set_hlg_display(self.hlg_display);
+        transform.set_clip_output(self.collect_gamut_stats);
+        transform.set_force_scalar(self.force_scalar);
+        let transform = transform.build(
+            &frame_color_encoding,
+            &self.requested_color_encoding,
+            &metadata.opsin_inverse_matrix,
+            &metadata.tone_mapping,
+        )?;
+        Ok(transform)
+    }
+
+    /// Removes trailing extra channels skipped by the renderer.
+    fn strip_extra_channels(&self, grid: Arc<ImageWithRegion>) -> Result<Arc<ImageWithRegion>> {
+        let num_channels = grid.color_channels() + self.num_output_extra_channels();
+        if grid.channels() <= num_channels {
+            return Ok(grid);
+        }
+
+        let grid = match Arc::try_unwrap(grid) {
+            Ok(mut grid) => {
+                grid.truncate_channels(num_channels);
+                grid
+            }
+            Err(grid) => grid.try_clone_channels(num_channels)?,
+        };
+        Ok(Arc::new(grid))
+    }
+
     fn postprocess_keyframe(
         &self,
         frame: &IndexedFrame,
         grid: Arc<ImageWithRegion>,
     ) -> Result<Arc<ImageWithRegion>> {
         let frame_header = frame.header();
-        let metadata = self.metadata();
+        let grid = self.strip_extra_channels(grid)?;
 
         tracing::trace_span!("Transform to requested color encoding").in_scope(|| -> Result<_> {
-            let header_color_encoding = &metadata.colour_encoding;
-            let frame_color_encoding = if !grid.ct_done() && metadata.xyb_encoded {
-                ColorEncodingWithProfile::new(EnumColourEncoding::xyb(
-                    jxl_color::RenderingIntent::Perceptual,
-                ))
-            } else if let ColourEncoding::Enum(encoding) = header_color_encoding {
-                ColorEncodingWithProfile::new(encoding.clone())
-            } else {
-                ColorEncodingWithProfile::with_icc(&self.embedded_icc)?
-            };
-            tracing::trace!(?frame_color_encoding);
-            tracing::trace!(requested_color_encoding = ?self.requested_color_encoding);
             tracing::trace!(do_ycbcr = frame_header.do_ycbcr);
-
-            let mut transform = jxl_color::ColorTransform::builder();
-            transform.set_srgb_icc(!self.cms.supports_linear_tf());
-            transform.from_pq(self.suggested_hdr_tf() == Some(jxl_color::TransferFunction::Pq));
-            let transform = transform.build(
-                &frame_color_encoding,
-                &self.requested_color_encoding,
-                &metadata.opsin_inverse_matrix,
-                &metadata.tone_mapping,
-            )?;
+            let transform = self.prepare_color_transform(grid.ct_done())?;
             if grid.ct_done() || (transform.is_noop() && !frame_header.do_ycbcr) {
                 return Ok(grid);
             }
 
-            let mut grid = grid.try_clone()?;
-
-            if !grid.ct_done() && frame_header.do_ycbcr {
-                grid.convert_modular_color(self.image_header.metadata.bit_depth)?;
-                jxl_color::ycbcr_to_rgb(grid.as_color_floats_mut());
-            }
-            if transform.is_noop() {
-                let output_channels = transform.output_channels();
-                grid.remove_color_channels(output_channels);
-                return Ok(Arc::new(grid));
-            }
+            let mut grid = match Arc::try_unwrap(grid) {
+                Ok(grid) => grid,
+                Err(grid) => grid.try_clone()?,
+            };
+            self.transform_color(frame_header, &mut grid, &transform)?;
+            Ok(Arc::new(grid))
+        })
+    }
 
-            let encoded_color_channels = frame_header.encoded_color_channels();
-            if encoded_color_channels < 3 {
-                grid.clone_gray()?;
-            }
+    /// Transforms color channels of the image to the requested color encoding, in place.
+    fn transform_color(
+        &self,
+        frame_header: &FrameHeader,
+        grid: &mut ImageWithRegion,
+        transform: &jxl_color::ColorTransform,
+    ) -> Result<()> {
+        let metadata = self.metadata();
 
+        if !grid.ct_done() && frame_header.do_ycbcr {
             grid.convert_modular_color(self.image_header.metadata.bit_depth)?;
-            let (color_channels, extra_channels) = grid.buffer_mut().split_at_mut(3);
-            let mut channels = Vec::new();
-            for grid in color_channels {
-                channels.push(grid.as_float_mut().unwrap().buf_mut());
-            }
-
-            let mut has_black = false;
-            for (grid, ec_info) in extra_channels.iter_mut().zip(&metadata.ec_info) {
-                if ec_info.is_black() {
-                    channels.push(grid.convert_to_float_modular(ec_info.bit_depth)?.buf_mut());
-                    has_black = true;
-                    break;
-                }
+            jxl_color::ycbcr_to_rgb_with(grid.as_color_floats_mut(), self.force_scalar);
+        }
+        if transform.is_noop() {
+            let output_channels = transform.output_channels();
+            grid.remove_color_channels(output_channels);
+            return Ok(());
+        }
+
+        let encoded_color_channels = frame_header.encoded_color_channels();
+        if encoded_color_channels < 3 {
+            grid.clone_gray()?;
+        }
+
+        grid.convert_modular_color(self.image_header.metadata.bit_depth)?;
+
+        let has_black = metadata.ec_info.iter().any(|ec_info| ec_info.is_black());
+        if self.requested_color_encoding.is_cmyk() && !has_black {
+            // CMS writes black samples to the fourth channel.
+            grid.add_black_channel()?;
+        }
+
+        let color_channel_count = grid.color_channels();
+        let (color_channels, extra_channels) = grid.buffer_mut().split_at_mut(color_channel_count);
+        let mut channels = Vec::new();
+        for grid in color_channels {
+            channels.push(grid.as_float_mut().unwrap().buf_mut());
+        }
+
+        for (grid, ec_info) in extra_channels.iter_mut().zip(&metadata.ec_info) {
+            if ec_info.is_black() {
+                channels.push(grid.convert_to_float_modular(ec_info.bit_depth)?.buf_mut());
+                break;
             }
+        }
 
-            if has_black {
-                // 0 means full ink; invert samples
-                for grid in channels.iter_mut() {
-                    for v in &mut **grid {
-                        *v = 1.0 - *v;
-                    }
+        if has_black {
+            // 0 means full ink; invert samples
+            for grid in channels.iter_mut() {
+                for v in &mut **grid {
+                    *v = 1.0 - *v;
                 }
             }
+        }
 
-            let output_channels =
-                transform.run_with_threads(&mut channels, &*self.cms, &self.pool)?;
-            if output_channels < 3 {
-                grid.remove_color_channels(output_channels);
+        let (output_channels, gamut_stats) =
+            transform.run_with_stats(&mut channels, &*self.cms, &self.pool)?;
+        if output_channels == 4 {
+            // CMS output is in ICC convention where 0 means no ink; invert back
+            for grid in channels.iter_mut() {
+                for v in &mut **grid {
+                    *v = 1.0 - *v;
+                }
             }
-            grid.set_ct_done(true);
-            Ok(Arc::new(grid))
-        })
+        }
+        if output_channels < 3 {
+            grid.remove_color_channels(output_channels);
+        }
+        if self.collect_gamut_stats {
+            grid.set_gamut_stats(gamut_stats);
+        }
+        grid.set_ct_done(true);
+        Ok(())
     }
 }
 
@@ -896,13 +1805,47 @@ impl RenderContext {
 pub struct IndexedFrame {
     f: Frame,
     idx: usize,
+    cancellation_token: Option<CancellationToken>,
+    substituted_error: OnceLock<Error>,
+    skip_corrupt_groups: bool,
+    corrupt_groups: Mutex<BTreeSet<TocGroupKind>>,
+    warnings: Mutex<Vec<DecodeWarning>>,
+    #[cfg(feature = "stats")]
+    stats: Arc<FrameStats>,
 }
 
 impl IndexedFrame {
-    fn new(frame: Frame, index: usize) -> Self {
+    fn new(
+        frame: Frame,
+        index: usize,
+        cancellation_token: Option<CancellationToken>,
+        skip_corrupt_groups: bool,
+    ) -> Self {
         IndexedFrame {
             f: frame,
             idx: index,
+            cancellation_token,
+            substituted_error: OnceLock::new(),
+            skip_corrupt_groups,
+            corrupt_groups: Mutex::new(BTreeSet::new()),
+            warnings: Mutex::new(Vec::new()),
+            #[cfg(feature = "stats")]
+            stats: Arc::new(FrameStats::default()),
+        }
+    }
+
+    /// Returns a copy of the frame with compressed data discarded, sharing decoding statistics.
+    fn without_data(&self) -> Self {
+        IndexedFrame {
+            f: self.f.without_data(),
+            idx: self.idx,
+            cancellation_token: self.cancellation_token.clone(),
+            substituted_error: OnceLock::new(),
+            skip_corrupt_groups: self.skip_corrupt_groups,
+            corrupt_groups: Mutex::new(self.corrupt_groups.lock().unwrap().clone()),
+            warnings: Mutex::new(self.warnings.lock().unwrap().clone()),
+            #[cfg(feature = "stats")]
+            stats: Arc::clone(&self.stats),
         }
     }
 
@@ -910,6 +1853,95 @@ impl IndexedFrame {
     pub fn index(&self) -> usize {
         self.idx
     }
+
+    /// Returns the error the frame failed to render with, if it's substituted with the previous
+    /// keyframe by error isolation.
+    pub fn substituted_error(&self) -> Option<&Error> {
+        self.substituted_error.get()
+    }
+
+    /// Returns pass groups which failed to decode and are rendered as missing, in TOC order.
+    ///
+    /// This is always empty unless [`RenderContextBuilder::skip_corrupt_groups`] is enabled.
+    pub fn corrupt_groups(&self) -> Vec<TocGroupKind> {
+        self.corrupt_groups
+            .lock()
+            .unwrap()
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Records the pass group which failed to decode, and returns whether the group should be
+    /// treated as missing.
+    pub(crate) fn skip_corrupt_group(&self, group: TocGroupKind, err: &jxl_frame::Error) -> bool {
+        if !self.skip_corrupt_groups
+            || matches!(
+                err,
+                jxl_frame::Error::Buffer(_) | jxl_frame::Error::OutOfMemory
+            )
+        {
+            return false;
+        }
+
+        let group = if self.toc().is_single_entry() {
+            TocGroupKind::All
+        } else {
+            group
+        };
+        tracing::warn!(
+            index = self.idx,
+            %group,
+            %err,
+            "Pass group is corrupt; rendering as missing"
+        );
+        self.corrupt_groups.lock().unwrap().insert(group);
+        true
+    }
+
+    /// Returns non-fatal issues found while rendering the frame.
+    ///
+    /// Warnings are updated on each render, so they describe the last render of the frame.
+    pub fn warnings(&self) -> Vec<DecodeWarning> {
+        self.warnings.lock().unwrap().clone()
+    }
+
+    /// Records the number of samples clamped by inverse Modular transforms in the last render.
+    pub(crate) fn record_clamped_samples(&self, clamped_samples: usize) {
+        let mut warnings = self.warnings.lock().unwrap();
+        warnings.retain(|w| !matches!(w, DecodeWarning::SqueezeOverflow { .. }));
+        if clamped_samples != 0 {
+            warnings.push(DecodeWarning::SqueezeOverflow { clamped_samples });
+        }
+    }
+
+    /// Returns patches of the frame, or `None` if `LfGlobal` section is not loaded yet.
+    ///
+    /// Each patch copies a rectangle from the reference frame in slot `ref_idx`, to one or more
+    /// target positions with blending modes specified per channel. Frames without patches return
+    /// an empty list. This parses `LfGlobal` section again, so it's relatively expensive.
+    pub fn patches(&self) -> Option<Result<Vec<PatchRef>>> {
+        let lf_global = match self.try_parse_lf_global::<i32>()? {
+            Ok(lf_global) => lf_global,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let patches = lf_global.patches.map(|patches| patches.patches);
+        Some(Ok(patches.unwrap_or_default()))
+    }
+
+    /// Returns `Err(Error::Cancelled)` if rendering is cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<()> {
+        match &self.cancellation_token {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the decoding statistics of the frame.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
 }
 
 impl std::ops::Deref for IndexedFrame {
@@ -926,6 +1958,55 @@ impl std::ops::DerefMut for IndexedFrame {
     }
 }
 
+/// Returns reference slots used by patches of the frame, or `None` if `LfGlobal` cannot be parsed.
+fn patch_ref_slots<S: Sample>(frame: &IndexedFrame) -> Option<Vec<u32>> {
+    let lf_global = frame.try_parse_lf_global::<S>()?.ok()?;
+    let patches = lf_global.patches.map(|patches| patches.patches);
+    Some(
+        patches
+            .unwrap_or_default()
+            .iter()
+            .map(|patch| patch.ref_idx)
+            .collect(),
+    )
+}
+
+/// Renderer which renders regions of a keyframe one at a time, created with
+/// [`RenderContext::keyframe_region_renderer`].
+#[derive(Debug)]
+pub struct KeyframeRegionRenderer<'ctx> {
+    ctx: &'ctx RenderContext,
+    idx: usize,
+    cache: RegionRenderCache,
+}
+
+#[derive(Debug)]
+enum RegionRenderCache {
+    Narrow(Box<RenderCache<i16>>),
+    Wide(Box<RenderCache<i32>>),
+}
+
+impl KeyframeRegionRenderer<'_> {
+    /// Renders the keyframe within the region, and transforms it to the requested color
+    /// encoding.
+    ///
+    /// The region is in the same coordinate system as
+    /// [`RenderContext::request_image_region`], and should be within the requested image region.
+    /// Output downscaling is not applied; the keyframe is decoded at full resolution.
+    pub fn render(&mut self, image_region: Region) -> Result<Arc<ImageWithRegion>> {
+        let ctx = self.ctx;
+        let grid = match &mut self.cache {
+            RegionRenderCache::Narrow(cache) => {
+                ctx.render_region(self.idx, &ctx.renders_narrow, cache, image_region)?
+            }
+            RegionRenderCache::Wide(cache) => {
+                ctx.render_region(self.idx, &ctx.renders_wide, cache, image_region)?
+            }
+        };
+        ctx.postprocess_keyframe(&ctx.frames[self.idx], grid)
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 struct FrameDependence {
     pub(crate) lf: usize,
@@ -1,4 +1,8 @@
-use jxl_frame::{data::GlobalModular, FrameHeader};
+use jxl_frame::{
+    data::{GlobalModular, TocGroupKind},
+    FrameHeader,
+};
+use jxl_grid::AllocCategory;
 use jxl_modular::{image::TransformedModularSubimage, Sample};
 
 use crate::{util, Error, ImageWithRegion, IndexedFrame, Region, RenderCache, Result};
@@ -11,7 +15,10 @@ pub(crate) fn render_modular<S: Sample>(
 ) -> Result<ImageWithRegion> {
     let image_header = frame.image_header();
     let frame_header = frame.header();
-    let tracker = frame.alloc_tracker();
+    let tracker = frame
+        .alloc_tracker()
+        .map(|tracker| tracker.with_category(AllocCategory::Render));
+    let tracker = tracker.as_ref();
     let xyb_encoded = image_header.metadata.xyb_encoded;
 
     let lf_global = if let Some(x) = &cache.lf_global {
@@ -19,7 +26,12 @@ pub(crate) fn render_modular<S: Sample>(
     } else {
         let lf_global = frame
             .try_parse_lf_global()
-            .ok_or(Error::IncompleteFrame)??;
+            .ok_or(Error::IncompleteFrame)?
+            .map_err(|e| Error::from(e).in_group(frame, TocGroupKind::LfGlobal))?;
+        #[cfg(feature = "stats")]
+        if let Some(ma_config) = &lf_global.gmodular.ma_config {
+            frame.stats().add_decoder(ma_config.decoder());
+        }
         cache.lf_global = Some(lf_global);
         cache.lf_global.as_ref().unwrap()
     };
@@ -98,10 +110,18 @@ pub(crate) fn render_modular<S: Sample>(
                      group_idx,
                      modular,
                  }| {
+                    if let Err(e) = frame.check_cancelled() {
+                        *result.write().unwrap() = Err(e);
+                        return;
+                    }
                     let bitstream = match frame.pass_group_bitstream(pass_idx, group_idx) {
                         Some(Ok(bitstream)) => bitstream,
                         Some(Err(e)) => {
-                            *result.write().unwrap() = Err(e.into());
+                            let group = TocGroupKind::GroupPass {
+                                pass_idx,
+                                group_idx,
+                            };
+                            *result.write().unwrap() = Err(Error::from(e).in_group(frame, group));
                             return;
                         }
                         None => return,
@@ -122,8 +142,18 @@ pub(crate) fn render_modular<S: Sample>(
                         tracker,
                         pool,
                     );
-                    if !allow_partial && r.is_err() {
-                        *result.write().unwrap() = r.map_err(From::from);
+                    #[cfg(feature = "stats")]
+                    if r.is_ok() {
+                        frame.stats().add_pass_group();
+                    }
+                    if let (false, Err(e)) = (allow_partial, r) {
+                        let group = TocGroupKind::GroupPass {
+                            pass_idx,
+                            group_idx,
+                        };
+                        if !frame.skip_corrupt_group(group, &e) {
+                            *result.write().unwrap() = Err(Error::from(e).in_group(frame, group));
+                        }
                     }
                 },
             );
@@ -134,6 +164,7 @@ pub(crate) fn render_modular<S: Sample>(
     tracing::trace_span!("Inverse Modular transform").in_scope(|| {
         modular_image.prepare_subimage().unwrap().finish(pool);
     });
+    frame.record_clamped_samples(modular_image.clamped_samples());
 
     let mut fb = ImageWithRegion::new(frame_header.encoded_color_channels(), tracker);
     fb.extend_from_gmodular(gmodular);
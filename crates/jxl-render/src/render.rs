@@ -11,6 +11,7 @@ use crate::{
     IndexedFrame, Reference, ReferenceFrames, Region, Result,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn render_frame<S: Sample>(
     frame: &IndexedFrame,
     reference_frames: ReferenceFrames<S>,
@@ -18,7 +19,13 @@ pub(crate) fn render_frame<S: Sample>(
     image_region: Region,
     pool: JxlThreadPool,
     frame_visibility: (usize, usize),
+    downscale_target: u32,
+    noise_strength: f32,
+    skip_restoration_filters: bool,
+    skip_extra_channels: bool,
 ) -> Result<ImageWithRegion> {
+    frame.check_cancelled()?;
+
     let frame_region = util::image_region_to_frame(frame, image_region, false);
     tracing::debug!(
         index = frame.idx,
@@ -52,6 +59,8 @@ pub(crate) fn render_frame<S: Sample>(
                 cache,
                 color_padded_region,
                 &pool,
+                downscale_target,
+                skip_extra_channels,
             );
             match (result, reference_frames.lf) {
                 (Ok(grid), _) => grid,
@@ -69,9 +78,16 @@ pub(crate) fn render_frame<S: Sample>(
         fb.upsample_jpeg(color_padded_region, image_header.metadata.bit_depth)?;
     }
 
+    let (gab, epf) = if skip_restoration_filters {
+        (&Gabor::Disabled, &EdgePreservingFilter::Disabled)
+    } else {
+        let restoration_filter = &frame_header.restoration_filter;
+        (&restoration_filter.gab, &restoration_filter.epf)
+    };
+
     let color_channels = fb.color_channels();
     let mut scratch_buffer = None;
-    if let Gabor::Enabled(weights) = frame_header.restoration_filter.gab {
+    if let Gabor::Enabled(weights) = *gab {
         if fb.color_channels() < 3 {
             tracing::trace!("Cloning gray channel");
             fb.clone_gray()?;
@@ -94,11 +110,12 @@ pub(crate) fn render_frame<S: Sample>(
             &mut fb_scratch,
             weights,
             &pool,
+            frame.force_scalar(),
         );
         scratch_buffer = Some(fb_scratch);
     }
 
-    if let EdgePreservingFilter::Enabled(epf_params) = &frame_header.restoration_filter.epf {
+    if let EdgePreservingFilter::Enabled(epf_params) = epf {
         if fb.color_channels() < 3 {
             tracing::trace!("Cloning gray channel");
             fb.clone_gray()?;
@@ -125,7 +142,10 @@ pub(crate) fn render_frame<S: Sample>(
             frame_header,
             epf_params,
             &pool,
+            frame.force_scalar(),
         );
+        #[cfg(feature = "stats")]
+        frame.stats().add_epf_iterations(epf_params.iters);
     }
 
     // Truncate cloned gray channels.
@@ -141,13 +161,20 @@ pub(crate) fn render_frame<S: Sample>(
         cache,
         frame_visibility.0,
         frame_visibility.1,
+        noise_strength,
         &pool,
     )?;
 
     fb.upsample_nonseparable(image_header, frame_header, upsampling_valid_region, false)?;
 
     if !frame_header.save_before_ct && !frame_header.is_last {
-        util::convert_color_for_record(image_header, frame_header.do_ycbcr, &mut fb, &pool)?;
+        util::convert_color_for_record(
+            image_header,
+            frame_header.do_ycbcr,
+            &mut fb,
+            &pool,
+            frame.force_scalar(),
+        )?;
     }
 
     Ok(fb)
@@ -162,6 +189,7 @@ fn render_features<S: Sample>(
     cache: &mut RenderCache<S>,
     visible_frames_num: usize,
     invisible_frames_num: usize,
+    noise_strength: f32,
     pool: &JxlThreadPool,
 ) -> Result<()> {
     let image_header = frame.image_header();
@@ -202,7 +230,7 @@ fn render_features<S: Sample>(
         }
     }
 
-    if let Some(noise) = &lf_global.noise {
+    if let Some(noise) = lf_global.noise.as_ref().filter(|_| noise_strength != 0.0) {
         if grid.color_channels() == 3 {
             grid.convert_modular_color(image_header.metadata.bit_depth)?;
             features::render_noise(
@@ -212,6 +240,7 @@ fn render_features<S: Sample>(
                 base_correlations_xb,
                 grid,
                 noise,
+                noise_strength,
                 pool,
             )?;
         } else {
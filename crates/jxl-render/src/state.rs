@@ -128,10 +128,15 @@ impl<S: Sample> FrameRenderHandle<S> {
         let render = if let Some(state) = self.start_render()? {
             let _guard = tracing::trace_span!("Run with image", index = self.frame.idx).entered();
 
+            #[cfg(feature = "stats")]
+            let started_at = std::time::Instant::now();
             let render_result = (self.render_op)(state, self.image_region);
+            #[cfg(feature = "stats")]
+            self.frame.stats().add_render_time(started_at.elapsed());
             match render_result {
                 FrameRender::InProgress(_) => {
                     drop(self.done_render(render_result));
+                    self.frame.check_cancelled()?;
                     return Err(Error::IncompleteFrame);
                 }
                 FrameRender::Err(e) => {
@@ -153,11 +158,33 @@ impl<S: Sample> FrameRenderHandle<S> {
         if let Some(state) = self.start_render_silent() {
             let _guard = tracing::trace_span!("Run", index = self.frame.idx).entered();
 
+            #[cfg(feature = "stats")]
+            let started_at = std::time::Instant::now();
             let render_result = (self.render_op)(state, image_region);
+            #[cfg(feature = "stats")]
+            self.frame.stats().add_render_time(started_at.elapsed());
             drop(self.done_render(render_result));
         }
     }
 
+    /// Returns the size of the rendered image kept in the handle, in bytes.
+    pub(crate) fn cached_bytes(&self) -> usize {
+        match &*self.render.lock().unwrap() {
+            FrameRender::Done(image) => image.byte_size(),
+            FrameRender::Blended(image) => image.byte_size(),
+            _ => 0,
+        }
+    }
+
+    /// Drops the rendered image kept in the handle, so that the frame is rendered again from
+    /// frame data on demand. Frames being rendered are left untouched.
+    pub(crate) fn evict(&self) {
+        let mut render_ref = self.render.lock().unwrap();
+        if matches!(&*render_ref, FrameRender::Done(_) | FrameRender::Blended(_)) {
+            *render_ref = FrameRender::None;
+        }
+    }
+
     pub fn reset(&self) -> FrameRender<S> {
         let mut render_ref = self.render.lock().unwrap();
         std::mem::replace(&mut *render_ref, FrameRender::None)
@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use jxl_coding::Decoder;
+
+/// Decoding statistics of a frame.
+///
+/// Statistics are accumulated over renders, so frames rendered multiple times, e.g. after
+/// changing cropping region, report the sum of every render.
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    lf_groups: AtomicUsize,
+    pass_groups: AtomicUsize,
+    epf_iterations: AtomicU32,
+    ans_decoders: AtomicUsize,
+    prefix_decoders: AtomicUsize,
+    render_time_nanos: AtomicU64,
+}
+
+impl FrameStats {
+    pub(crate) fn add_lf_group(&self) {
+        self.lf_groups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_pass_group(&self) {
+        self.pass_groups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_epf_iterations(&self, iters: u32) {
+        self.epf_iterations.fetch_add(iters, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_decoder(&self, decoder: &Decoder) {
+        if decoder.is_prefix_code() {
+            self.prefix_decoders.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.ans_decoders.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn add_render_time(&self, time: Duration) {
+        let nanos = time.as_nanos().min(u64::MAX as u128) as u64;
+        self.render_time_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+impl FrameStats {
+    /// Returns the number of LF groups decoded.
+    pub fn lf_groups_decoded(&self) -> usize {
+        self.lf_groups.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of pass groups decoded, counting each pass separately.
+    pub fn pass_groups_decoded(&self) -> usize {
+        self.pass_groups.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of edge-preserving filter iterations run.
+    pub fn epf_iterations(&self) -> u32 {
+        self.epf_iterations.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of ANS entropy decoders in global sections of the frame.
+    ///
+    /// Entropy decoders of the global MA tree and HF coefficients are counted.
+    pub fn ans_decoders(&self) -> usize {
+        self.ans_decoders.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of prefix code entropy decoders in global sections of the frame.
+    ///
+    /// Entropy decoders of the global MA tree and HF coefficients are counted.
+    pub fn prefix_decoders(&self) -> usize {
+        self.prefix_decoders.load(Ordering::Relaxed)
+    }
+
+    /// Returns the time spent on rendering the frame, excluding its reference frames.
+    pub fn render_time(&self) -> Duration {
+        Duration::from_nanos(self.render_time_nanos.load(Ordering::Relaxed))
+    }
+}
@@ -2,18 +2,19 @@ use jxl_color::{
     ColorEncodingWithProfile, ColorTransform, ColourEncoding, ColourSpace, EnumColourEncoding,
 };
 use jxl_frame::{
-    data::{LfGlobal, LfGroup},
+    data::{LfGlobal, LfGroup, TocGroupKind},
     filter::{EdgePreservingFilter, EpfParams},
     header::FrameType,
     Frame, FrameHeader,
 };
-use jxl_grid::{AlignedGrid, MutableSubgrid};
+use jxl_grid::{AlignedGrid, AllocCategory, MutableSubgrid};
 use jxl_image::ImageHeader;
 use jxl_modular::{image::TransformedModularSubimage, ChannelShift, Sample};
 use jxl_threadpool::JxlThreadPool;
 
 use crate::{
-    image::ImageBuffer, vardct::copy_lf_dequant, ImageWithRegion, IndexedFrame, Region, Result,
+    image::ImageBuffer, vardct::copy_lf_dequant, Error, ImageWithRegion, IndexedFrame, Region,
+    Result,
 };
 
 pub(crate) fn image_region_to_frame(
@@ -143,7 +144,10 @@ pub(crate) fn load_lf_groups<S: Sample>(
     let shifts_cbycr: [_; 3] =
         std::array::from_fn(|idx| ChannelShift::from_jpeg_upsampling(jpeg_upsampling, idx));
     let mut lf_xyb = if lf_global_vardct.is_some() && !frame_header.flags.use_lf_frame() {
-        let tracker = frame.alloc_tracker();
+        let tracker = frame
+            .alloc_tracker()
+            .map(|tracker| tracker.with_category(AllocCategory::Render));
+        let tracker = tracker.as_ref();
         let mut out = ImageWithRegion::new(3, tracker);
         let Region { width, height, .. } = lf_region;
         for shift in shifts_cbycr {
@@ -237,14 +241,21 @@ pub(crate) fn load_lf_groups<S: Sample>(
         let loaded = lf_group.as_ref().map(|g| !g.partial).unwrap_or(false);
 
         if !loaded {
+            if let Err(e) = frame.check_cancelled() {
+                *result.write().unwrap() = Err(e);
+                return;
+            }
             let parse_result =
                 frame.try_parse_lf_group(lf_global_vardct, global_ma_config, modular.take(), idx);
             match parse_result {
                 Some(Ok(g)) => {
+                    #[cfg(feature = "stats")]
+                    frame.stats().add_lf_group();
                     *lf_group = Some(g);
                 }
                 Some(Err(e)) => {
-                    *result.write().unwrap() = Err(e.into());
+                    let group = TocGroupKind::LfGroup(idx);
+                    *result.write().unwrap() = Err(Error::from(e).in_group(frame, group));
                     return;
                 }
                 None => {
@@ -313,6 +324,7 @@ pub(crate) fn convert_color_for_record(
     do_ycbcr: bool,
     fb: &mut ImageWithRegion,
     pool: &JxlThreadPool,
+    force_scalar: bool,
 ) -> Result<()> {
     // save_before_ct = false
 
@@ -321,7 +333,7 @@ pub(crate) fn convert_color_for_record(
         // xyb_encoded = false
         fb.convert_modular_color(metadata.bit_depth)?;
         let [cb, y, cr] = fb.as_color_floats_mut();
-        jxl_color::ycbcr_to_rgb([cb, y, cr]);
+        jxl_color::ycbcr_to_rgb_with([cb, y, cr], force_scalar);
         if metadata.colour_encoding.colour_space() == ColourSpace::Grey {
             fb.remove_color_channels(1);
         }
@@ -350,15 +362,18 @@ pub(crate) fn convert_color_for_record(
             fb.convert_modular_color(metadata.bit_depth)?;
             let [x, y, b] = fb.as_color_floats_mut();
             tracing::trace!(colour_encoding = ?encoding);
-            let transform = ColorTransform::new(
-                &ColorEncodingWithProfile::new(EnumColourEncoding::xyb(
-                    jxl_color::RenderingIntent::Perceptual,
-                )),
-                &ColorEncodingWithProfile::new(encoding.clone()),
-                &metadata.opsin_inverse_matrix,
-                &metadata.tone_mapping,
-            )
-            .unwrap();
+            let mut transform = ColorTransform::builder();
+            transform.set_force_scalar(force_scalar);
+            let transform = transform
+                .build(
+                    &ColorEncodingWithProfile::new(EnumColourEncoding::xyb(
+                        jxl_color::RenderingIntent::Perceptual,
+                    )),
+                    &ColorEncodingWithProfile::new(encoding.clone()),
+                    &metadata.opsin_inverse_matrix,
+                    &metadata.tone_mapping,
+                )
+                .unwrap();
             let output_channels = transform
                 .run_with_threads(
                     &mut [x.buf_mut(), y.buf_mut(), b.buf_mut()],
@@ -3,6 +3,9 @@ use jxl_grid::{MutableSubgrid, SimdVector};
 use super::super::dct_common::{self, DctDirection};
 use std::arch::aarch64::*;
 
+/// Maximum number of lanes needed for scratch space, for 256x256 transforms.
+const MAX_SCRATCH_LANES: usize = 256 * 2;
+
 const LANE_SIZE: usize = 4;
 type Lane = float32x4_t;
 
@@ -36,9 +39,10 @@ pub(crate) unsafe fn dct_2d_aarch64_neon(io: &mut MutableSubgrid<'_>, direction:
 fn dct_2d_lane(io: &mut MutableSubgrid<'_, Lane>, direction: DctDirection) {
     let scratch_size = io.height().max(io.width() * LANE_SIZE) * 2;
     unsafe {
-        let mut scratch_lanes = vec![Lane::zero(); scratch_size];
-        column_dct_lane(io, &mut scratch_lanes, direction);
-        row_dct_lane(io, &mut scratch_lanes, direction);
+        let mut scratch_lanes = [Lane::zero(); MAX_SCRATCH_LANES];
+        let scratch_lanes = &mut scratch_lanes[..scratch_size];
+        column_dct_lane(io, scratch_lanes, direction);
+        row_dct_lane(io, scratch_lanes, direction);
     }
 }
 
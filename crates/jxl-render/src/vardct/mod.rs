@@ -1,15 +1,16 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
 use jxl_frame::{
-    data::{HfGlobal, LfGlobal, LfGroup, PassGroupParams, PassGroupParamsVardct},
+    data::{HfGlobal, LfGlobal, LfGroup, PassGroupParams, PassGroupParamsVardct, TocGroupKind},
     FrameHeader,
 };
-use jxl_grid::{AlignedGrid, MutableSubgrid, SharedSubgrid};
+use jxl_grid::{AlignedGrid, AllocCategory, MutableSubgrid, SharedSubgrid};
 use jxl_image::ImageHeader;
 use jxl_modular::{ChannelShift, Sample};
 use jxl_threadpool::JxlThreadPool;
 use jxl_vardct::{
-    BlockInfo, LfChannelCorrelation, LfChannelDequantization, Quantizer, TransformType,
+    BlockInfo, HfMetadata, LfChannelCorrelation, LfChannelDequantization, Quantizer, TransformType,
 };
 
 use crate::{
@@ -49,13 +50,18 @@ pub(crate) fn render_vardct<S: Sample>(
     cache: &mut RenderCache<S>,
     region: Region,
     pool: &JxlThreadPool,
+    downscale_target: u32,
+    skip_extra_channels: bool,
 ) -> Result<ImageWithRegion> {
     let span = tracing::span!(tracing::Level::TRACE, "Render VarDCT");
     let _guard = span.enter();
 
     let image_header = frame.image_header();
     let frame_header = frame.header();
-    let tracker = frame.alloc_tracker();
+    let tracker = frame
+        .alloc_tracker()
+        .map(|tracker| tracker.with_category(AllocCategory::VarDct));
+    let tracker = tracker.as_ref();
 
     let jpeg_upsampling = frame_header.jpeg_upsampling;
     let subsampled = jpeg_upsampling.into_iter().any(|x| x != 0);
@@ -65,28 +71,20 @@ pub(crate) fn render_vardct<S: Sample>(
     } else {
         let lf_global = frame
             .try_parse_lf_global()
-            .ok_or(Error::IncompleteFrame)??;
+            .ok_or(Error::IncompleteFrame)?
+            .map_err(|e| Error::from(e).in_group(frame, TocGroupKind::LfGlobal))?;
+        #[cfg(feature = "stats")]
+        if let Some(ma_config) = &lf_global.gmodular.ma_config {
+            frame.stats().add_decoder(ma_config.decoder());
+        }
         cache.lf_global = Some(lf_global);
         cache.lf_global.as_ref().unwrap()
     };
     let mut gmodular = lf_global.gmodular.try_clone()?;
     let lf_global_vardct = lf_global.vardct.as_ref().unwrap();
 
-    let width = frame_header.color_sample_width() as usize;
-    let height = frame_header.color_sample_height() as usize;
-    let (width_rounded, height_rounded) = {
-        let mut bw = (width + 7) / 8;
-        let mut bh = (height + 7) / 8;
-        let h_upsample = jpeg_upsampling.into_iter().any(|j| j == 1 || j == 2);
-        let v_upsample = jpeg_upsampling.into_iter().any(|j| j == 1 || j == 3);
-        if h_upsample {
-            bw = (bw + 1) / 2 * 2;
-        }
-        if v_upsample {
-            bh = (bh + 1) / 2 * 2;
-        }
-        (bw * 8, bh * 8)
-    };
+    let (bw, bh) = size_in_blocks(frame_header);
+    let (width_rounded, height_rounded) = (bw * 8, bh * 8);
 
     let aligned_region = region.container_aligned(frame_header.group_dim());
     let aligned_lf_region = {
@@ -122,6 +120,15 @@ pub(crate) fn render_vardct<S: Sample>(
                 height_rounded as u32 / 8,
             ));
 
+    // Extra channels of pass groups are stored after HF coefficients, so they can be skipped
+    // altogether if none of them are needed. Skipped channels are left zeroed.
+    let skip_pass_group_modular = skip_extra_channels
+        && image_header
+            .metadata
+            .ec_info
+            .iter()
+            .all(|ec_info| ec_info.is_auxiliary());
+
     let mut modular_image = gmodular.modular.image_mut();
     let groups = modular_image
         .as_mut()
@@ -136,16 +143,37 @@ pub(crate) fn render_vardct<S: Sample>(
         ret
     });
 
+    // Passes with Modular pass group images should be decoded regardless of downscaling target.
+    let num_hf_passes = num_required_hf_passes(frame_header, downscale_target);
+    let num_passes = pass_group_image
+        .iter()
+        .rposition(|images| images.iter().any(|image| !image.is_empty()))
+        .map(|idx| idx as u32 + 1)
+        .unwrap_or(0)
+        .max(num_hf_passes);
+    if num_passes < frame_header.passes.num_passes {
+        tracing::debug!(num_passes, downscale_target, "Skipping HF passes");
+    }
+
     let hf_global = &mut cache.hf_global;
     let lf_groups = &mut cache.lf_groups;
     let group_dim = frame_header.group_dim();
 
     let result = std::sync::RwLock::new(Result::Ok(()));
     let (mut fb, lf_xyb) = pool.scope(|scope| -> Result<_> {
-        if hf_global.is_none() {
+        if hf_global.is_none() && num_passes > 0 {
             scope.spawn(|_| {
                 let ret = tracing::trace_span!("Parse HfGlobal").in_scope(|| -> Result<_> {
-                    *hf_global = frame.try_parse_hf_global(Some(lf_global)).transpose()?;
+                    *hf_global = frame
+                        .try_parse_hf_global(Some(lf_global))
+                        .transpose()
+                        .map_err(|e| Error::from(e).in_group(frame, TocGroupKind::HfGlobal))?;
+                    #[cfg(feature = "stats")]
+                    if let Some(hf_global) = hf_global {
+                        for hf_pass in &hf_global.hf_passes {
+                            frame.stats().add_decoder(hf_pass.decoder());
+                        }
+                    }
                     Ok(())
                 });
                 if let Err(e) = ret {
@@ -173,26 +201,7 @@ pub(crate) fn render_vardct<S: Sample>(
             })?
         } else {
             let mut lf_xyb = lf_xyb.unwrap();
-
-            if !subsampled {
-                tracing::trace_span!("LF CfL").in_scope(|| {
-                    chroma_from_luma_lf(
-                        lf_xyb.as_color_floats_mut(),
-                        &lf_global_vardct.lf_chan_corr,
-                    );
-                });
-            }
-
-            if !frame_header.flags.skip_adaptive_lf_smoothing() {
-                tracing::trace_span!("Adaptive LF smoothing").in_scope(|| {
-                    adaptive_lf_smoothing(
-                        lf_xyb.as_color_floats_mut(),
-                        &lf_global.lf_dequant,
-                        &lf_global_vardct.quantizer,
-                    )
-                })?;
-            }
-
+            postprocess_lf_xyb(&mut lf_xyb, frame_header, lf_global, frame.force_scalar())?;
             lf_xyb
         };
 
@@ -241,7 +250,11 @@ pub(crate) fn render_vardct<S: Sample>(
         pool.scope(|scope| {
             let global_ma_config = gmodular.ma_config.as_ref();
 
-            for (pass_idx, pass_image) in pass_group_image.into_iter().enumerate() {
+            for (pass_idx, pass_image) in pass_group_image
+                .into_iter()
+                .enumerate()
+                .take(num_passes as usize)
+            {
                 let pass_idx = pass_idx as u32;
                 let mut image_it = pass_image.into_iter().enumerate();
                 for &(group_idx, ref grid_xyb, lf_group) in &it {
@@ -259,7 +272,11 @@ pub(crate) fn render_vardct<S: Sample>(
                     let bitstream = match frame.pass_group_bitstream(pass_idx, group_idx) {
                         Some(Ok(bitstream)) => bitstream,
                         Some(Err(e)) => {
-                            *result.write().unwrap() = Err(e.into());
+                            let group = TocGroupKind::GroupPass {
+                                pass_idx,
+                                group_idx,
+                            };
+                            *result.write().unwrap() = Err(Error::from(e).in_group(frame, group));
                             continue;
                         }
                         None => continue,
@@ -267,12 +284,21 @@ pub(crate) fn render_vardct<S: Sample>(
                     let allow_partial = bitstream.partial;
                     let mut bitstream = bitstream.bitstream;
 
-                    let modular = image_it
-                        .find(|(image_idx, _)| *image_idx == group_idx as usize)
-                        .map(|(_, modular)| modular);
+                    let modular = if skip_pass_group_modular {
+                        None
+                    } else {
+                        image_it
+                            .find(|(image_idx, _)| *image_idx == group_idx as usize)
+                            .map(|(_, modular)| modular)
+                    };
 
                     let result = &result;
                     scope.spawn(move |_| {
+                        if let Err(e) = frame.check_cancelled() {
+                            *result.write().unwrap() = Err(e);
+                            return;
+                        }
+
                         let vardct = Some(PassGroupParamsVardct {
                             lf_vardct: lf_global_vardct,
                             hf_global,
@@ -294,8 +320,19 @@ pub(crate) fn render_vardct<S: Sample>(
                                 pool,
                             },
                         );
-                        if !allow_partial && r.is_err() {
-                            *result.write().unwrap() = r.map_err(From::from);
+                        #[cfg(feature = "stats")]
+                        if r.is_ok() {
+                            frame.stats().add_pass_group();
+                        }
+                        if let (false, Err(e)) = (allow_partial, r) {
+                            let group = TocGroupKind::GroupPass {
+                                pass_idx,
+                                group_idx,
+                            };
+                            if !frame.skip_corrupt_group(group, &e) {
+                                *result.write().unwrap() =
+                                    Err(Error::from(e).in_group(frame, group));
+                            }
                         }
                     });
                 }
@@ -305,11 +342,36 @@ pub(crate) fn render_vardct<S: Sample>(
         result.into_inner().unwrap()
     })?;
 
+    let force_scalar = frame.force_scalar();
     tracing::trace_span!("Dequant and transform").in_scope(|| {
         let groups_per_row = frame_header.groups_per_row();
 
-        pool.for_each_vec(it, |job| {
-            let (group_idx, mut grid_xyb, lf_group) = job;
+        // A single group would be processed by only one thread; split it into row bands so that
+        // the thread pool can be utilized. Bands are split regardless of the thread pool, so that
+        // work is partitioned in the same way with any number of threads.
+        let split_groups = !subsampled && it.len() == 1;
+        let mut jobs = Vec::with_capacity(it.len());
+        for (group_idx, mut grid_xyb, lf_group) in it {
+            let hf_meta = lf_group.hf_meta.as_ref().filter(|_| split_groups);
+            let Some(hf_meta) = hf_meta else {
+                jobs.push((group_idx, grid_xyb, lf_group, 0..(group_dim / 8) as usize));
+                continue;
+            };
+
+            let block_info = group_block_info(frame_header, group_idx, hf_meta);
+            for band in split_group_bands(&block_info) {
+                let band_height = band.len() * 8;
+                let next_grid_xyb = grid_xyb.each_mut().map(|grid| {
+                    let band_height = band_height.min(grid.height());
+                    grid.split_vertical_in_place(band_height)
+                });
+                jobs.push((group_idx, grid_xyb, lf_group, band));
+                grid_xyb = next_grid_xyb;
+            }
+        }
+
+        pool.for_each_vec(jobs, |job| {
+            let (group_idx, mut grid_xyb, lf_group, block_rows) = job;
             let grid_xyb = &mut grid_xyb;
             let group_x = group_idx % groups_per_row;
             let group_y = group_idx / groups_per_row;
@@ -328,7 +390,15 @@ pub(crate) fn render_vardct<S: Sample>(
             };
 
             if lf_group.hf_meta.is_none() || hf_global.is_none() || !transform_hf {
-                transform_with_lf_grouped(&lf_xyb, grid_xyb, group_idx, frame_header, lf_groups);
+                transform_with_lf_grouped(
+                    &lf_xyb,
+                    grid_xyb,
+                    group_idx,
+                    block_rows,
+                    frame_header,
+                    lf_groups,
+                    force_scalar,
+                );
                 return;
             }
 
@@ -337,6 +407,7 @@ pub(crate) fn render_vardct<S: Sample>(
             dequant_hf_varblock_grouped(
                 grid_xyb,
                 group_idx,
+                block_rows.clone(),
                 image_header,
                 frame_header,
                 lf_global,
@@ -348,7 +419,7 @@ pub(crate) fn render_vardct<S: Sample>(
                 let hf_meta = lf_group.hf_meta.as_ref().unwrap();
                 let lf_chan_corr = &lf_global_vardct.lf_chan_corr;
                 let cfl_base_x = ((group_x % 8) * group_dim / 64) as usize;
-                let cfl_base_y = ((group_y % 8) * group_dim / 64) as usize;
+                let cfl_base_y = ((group_y % 8) * group_dim / 64) as usize + block_rows.start / 8;
                 let gw = (grid_xyb[0].width() + 63) / 64;
                 let gh = (grid_xyb[0].height() + 63) / 64;
                 let x_from_y = hf_meta
@@ -362,7 +433,15 @@ pub(crate) fn render_vardct<S: Sample>(
                 chroma_from_luma_hf_grouped(grid_xyb, &x_from_y, &b_from_y, lf_chan_corr);
             }
 
-            transform_with_lf_grouped(&lf_xyb, grid_xyb, group_idx, frame_header, lf_groups);
+            transform_with_lf_grouped(
+                &lf_xyb,
+                grid_xyb,
+                group_idx,
+                block_rows,
+                frame_header,
+                lf_groups,
+                force_scalar,
+            );
         });
     });
 
@@ -370,12 +449,134 @@ pub(crate) fn render_vardct<S: Sample>(
         tracing::trace_span!("Extra channel inverse transform").in_scope(|| {
             modular_image.prepare_subimage().unwrap().finish(pool);
         });
+        frame.record_clamped_samples(modular_image.clamped_samples());
         fb.extend_from_gmodular(gmodular);
     }
 
     Ok(fb)
 }
 
+/// Decodes the LF image of the frame, which is the frame downsampled by 8x in XYB color space.
+///
+/// HF passes are not decoded at all. The frame shouldn't use LF frames.
+pub(crate) fn render_vardct_lf<S: Sample>(
+    frame: &IndexedFrame,
+    cache: &mut RenderCache<S>,
+    pool: &JxlThreadPool,
+) -> Result<ImageWithRegion> {
+    let span = tracing::span!(tracing::Level::TRACE, "Render VarDCT LF");
+    let _guard = span.enter();
+
+    let frame_header = frame.header();
+    assert!(!frame_header.flags.use_lf_frame());
+
+    let lf_global = if let Some(x) = &cache.lf_global {
+        x
+    } else {
+        let lf_global = frame
+            .try_parse_lf_global()
+            .ok_or(Error::IncompleteFrame)?
+            .map_err(|e| Error::from(e).in_group(frame, TocGroupKind::LfGlobal))?;
+        cache.lf_global = Some(lf_global);
+        cache.lf_global.as_ref().unwrap()
+    };
+    let mut gmodular = lf_global.gmodular.try_clone()?;
+
+    let (bw, bh) = size_in_blocks(frame_header);
+    let lf_region = Region::with_size(bw as u32, bh as u32);
+    let modular_lf_region =
+        modular::compute_modular_region(frame_header, &gmodular, lf_region, true)
+            .intersection(lf_region);
+
+    let mut modular_image = gmodular.modular.image_mut();
+    let lf_group_image = modular_image
+        .as_mut()
+        .map(|x| x.prepare_groups(frame.pass_shifts()))
+        .transpose()?
+        .map(|x| x.lf_groups)
+        .unwrap_or_default();
+
+    let lf_xyb = tracing::trace_span!("Load LF groups").in_scope(|| {
+        util::load_lf_groups(
+            frame,
+            lf_global,
+            &mut cache.lf_groups,
+            lf_group_image,
+            modular_lf_region,
+            pool,
+        )
+    })?;
+    let mut lf_xyb = lf_xyb.unwrap();
+    postprocess_lf_xyb(&mut lf_xyb, frame_header, lf_global, frame.force_scalar())?;
+
+    if frame_header.do_ycbcr {
+        lf_xyb.upsample_jpeg(modular_lf_region, frame.image_header().metadata.bit_depth)?;
+    }
+    Ok(lf_xyb)
+}
+
+/// Returns the size of the frame in 8x8 blocks, rounded up to cover whole chroma subsampled
+/// blocks.
+fn size_in_blocks(frame_header: &FrameHeader) -> (usize, usize) {
+    let jpeg_upsampling = frame_header.jpeg_upsampling;
+    let mut bw = (frame_header.color_sample_width() as usize).div_ceil(8);
+    let mut bh = (frame_header.color_sample_height() as usize).div_ceil(8);
+    let h_upsample = jpeg_upsampling.into_iter().any(|j| j == 1 || j == 2);
+    let v_upsample = jpeg_upsampling.into_iter().any(|j| j == 1 || j == 3);
+    if h_upsample {
+        bw = bw.next_multiple_of(2);
+    }
+    if v_upsample {
+        bh = bh.next_multiple_of(2);
+    }
+    (bw, bh)
+}
+
+/// Applies chroma-from-luma and adaptive smoothing to the dequantized LF image.
+fn postprocess_lf_xyb<S: Sample>(
+    lf_xyb: &mut ImageWithRegion,
+    frame_header: &FrameHeader,
+    lf_global: &LfGlobal<S>,
+    force_scalar: bool,
+) -> Result<()> {
+    let lf_global_vardct = lf_global.vardct.as_ref().unwrap();
+    let subsampled = frame_header.jpeg_upsampling.into_iter().any(|x| x != 0);
+    if !subsampled {
+        tracing::trace_span!("LF CfL").in_scope(|| {
+            chroma_from_luma_lf(lf_xyb.as_color_floats_mut(), &lf_global_vardct.lf_chan_corr);
+        });
+    }
+
+    if !frame_header.flags.skip_adaptive_lf_smoothing() {
+        tracing::trace_span!("Adaptive LF smoothing").in_scope(|| {
+            adaptive_lf_smoothing(
+                lf_xyb.as_color_floats_mut(),
+                &lf_global.lf_dequant,
+                &lf_global_vardct.quantizer,
+                force_scalar,
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the number of HF passes needed to render the frame downscaled by `downscale_target`.
+fn num_required_hf_passes(frame_header: &FrameHeader, downscale_target: u32) -> u32 {
+    // LF image is 8x downsampled.
+    if downscale_target >= 8 {
+        return 0;
+    }
+
+    let passes = &frame_header.passes;
+    passes
+        .downsample
+        .iter()
+        .zip(&passes.last_pass)
+        .filter(|&(&downsample, _)| downsample <= downscale_target)
+        .map(|(_, &last_pass)| last_pass + 1)
+        .fold(passes.num_passes, u32::min)
+}
+
 pub fn copy_lf_dequant<S: Sample>(
     grid: &mut MutableSubgrid<f32>,
     quantizer: &Quantizer,
@@ -407,6 +608,7 @@ pub fn adaptive_lf_smoothing(
     lf_image: [&mut AlignedGrid<f32>; 3],
     lf_dequant: &LfChannelDequantization,
     quantizer: &Quantizer,
+    force_scalar: bool,
 ) -> Result<()> {
     let scale_inv = quantizer.global_scale as u64 * quantizer.quant_lf as u64;
     let lf_x = (512.0 * lf_dequant.m_x_lf as f64 / scale_inv as f64) as f32;
@@ -422,7 +624,12 @@ pub fn adaptive_lf_smoothing(
     let in_y = in_y.buf_mut();
     let in_b = in_b.buf_mut();
 
-    impls::adaptive_lf_smoothing_impl(
+    let smoothing_impl = if force_scalar {
+        generic::adaptive_lf_smoothing_impl
+    } else {
+        impls::adaptive_lf_smoothing_impl
+    };
+    smoothing_impl(
         width,
         height,
         [in_x, in_y, in_b],
@@ -431,9 +638,11 @@ pub fn adaptive_lf_smoothing(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn dequant_hf_varblock_grouped<S: Sample>(
     out: &mut [MutableSubgrid<'_, f32>; 3],
     group_idx: u32,
+    block_rows: Range<usize>,
     image_header: &ImageHeader,
     frame_header: &FrameHeader,
     lf_global: &LfGlobal<S>,
@@ -455,30 +664,18 @@ pub fn dequant_hf_varblock_grouped<S: Sample>(
 
     let quant_bias_numerator = oim.quant_bias_numerator;
 
-    let group_dim = frame_header.group_dim();
-    let groups_per_row = frame_header.groups_per_row();
-
-    let group_x = group_idx % groups_per_row;
-    let group_y = group_idx / groups_per_row;
-
     let lf_group_idx = frame_header.lf_group_idx_from_group_idx(group_idx);
     let Some(lf_group) = lf_groups.get(&lf_group_idx) else {
         return;
     };
-    let left_in_lf = ((group_x % 8) * (group_dim / 8)) as usize;
-    let top_in_lf = ((group_y % 8) * (group_dim / 8)) as usize;
-
     let Some(hf_meta) = &lf_group.hf_meta else {
         return;
     };
 
-    let block_info = &hf_meta.block_info;
-    let lf_width = (block_info.width() - left_in_lf).min(group_dim as usize / 8);
-    let lf_height = (block_info.height() - top_in_lf).min(group_dim as usize / 8);
-    let block_info = hf_meta.block_info.as_subgrid().subgrid(
-        left_in_lf..(left_in_lf + lf_width),
-        top_in_lf..(top_in_lf + lf_height),
-    );
+    let block_info = group_block_info(frame_header, group_idx, hf_meta);
+    let block_rows =
+        block_rows.start.min(block_info.height())..block_rows.end.min(block_info.height());
+    let block_info = block_info.subgrid(.., block_rows);
 
     for (channel, coeff) in out.iter_mut().enumerate() {
         let quant_bias = oim.quant_bias[channel];
@@ -598,8 +795,10 @@ pub fn transform_with_lf_grouped<S: Sample>(
     lf: &ImageWithRegion,
     coeff_out: &mut [MutableSubgrid<'_, f32>; 3],
     group_idx: u32,
+    block_rows: Range<usize>,
     frame_header: &FrameHeader,
     lf_groups: &HashMap<u32, LfGroup<S>>,
+    force_scalar: bool,
 ) {
     let lf_regions = <[_; 3]>::try_from(&lf.regions_and_shifts()[..3]).unwrap();
     let [lf_x, lf_y, lf_b] = lf.as_color_floats();
@@ -623,9 +822,10 @@ pub fn transform_with_lf_grouped<S: Sample>(
         let lf_base_left = lf_base_left.checked_add_signed(-lf_region.left).unwrap();
         let lf_base_top = lf_base_top.checked_add_signed(-lf_region.top).unwrap();
         let lf_width = (lf_region.width - lf_base_left).min(group_dim / 8);
-        let lf_height = (lf_region.height - lf_base_top).min(group_dim / 8);
+        let lf_height = (lf_region.height - lf_base_top).min(group_dim / 8) as usize;
         let lf_base_left = lf_base_left as usize;
-        let lf_base_top = lf_base_top as usize;
+        let lf_base_top = lf_base_top as usize + block_rows.start.min(lf_height);
+        let lf_height = (block_rows.end.min(lf_height) - block_rows.start.min(lf_height)) as u32;
 
         let lf_base_left = lf_base_left >> shift.hshift();
         let lf_base_top = lf_base_top >> shift.vshift();
@@ -661,15 +861,76 @@ pub fn transform_with_lf_grouped<S: Sample>(
         let lf_base_left = lf_base_left.checked_add_signed(-lf_region.left).unwrap();
         let lf_base_top = lf_base_top.checked_add_signed(-lf_region.top).unwrap();
         let lf_width = (lf_region.width - lf_base_left).min(group_dim / 8);
-        let lf_height = (lf_region.height - lf_base_top).min(group_dim / 8);
+        let lf_height = (lf_region.height - lf_base_top).min(group_dim / 8) as usize;
+        let block_top = top_in_lf + block_rows.start.min(lf_height);
+        let block_bottom = top_in_lf + block_rows.end.min(lf_height);
 
         hf_meta.block_info.as_subgrid().subgrid(
             left_in_lf..(left_in_lf + lf_width as usize),
-            top_in_lf..(top_in_lf + lf_height as usize),
+            block_top..block_bottom,
         )
     };
 
-    impls::transform_varblocks(&lf, coeff_out, shifts_cbycr, &block_info);
+    if force_scalar {
+        generic::transform_varblocks(&lf, coeff_out, shifts_cbycr, &block_info);
+    } else {
+        impls::transform_varblocks(&lf, coeff_out, shifts_cbycr, &block_info);
+    }
+}
+
+/// Height of row bands in blocks, used when a group is split into multiple jobs.
+const GROUP_BAND_HEIGHT: usize = 8;
+
+/// Returns varblock information of the group.
+fn group_block_info<'a>(
+    frame_header: &FrameHeader,
+    group_idx: u32,
+    hf_meta: &'a HfMetadata,
+) -> SharedSubgrid<'a, BlockInfo> {
+    let group_dim = frame_header.group_dim();
+    let groups_per_row = frame_header.groups_per_row();
+    let group_x = group_idx % groups_per_row;
+    let group_y = group_idx / groups_per_row;
+    let left_in_lf = ((group_x % 8) * (group_dim / 8)) as usize;
+    let top_in_lf = ((group_y % 8) * (group_dim / 8)) as usize;
+
+    let block_info = &hf_meta.block_info;
+    let lf_width = (block_info.width() - left_in_lf).min(group_dim as usize / 8);
+    let lf_height = (block_info.height() - top_in_lf).min(group_dim as usize / 8);
+    block_info.as_subgrid().subgrid(
+        left_in_lf..(left_in_lf + lf_width),
+        top_in_lf..(top_in_lf + lf_height),
+    )
+}
+
+/// Splits block rows of a group into bands, at multiples of [`GROUP_BAND_HEIGHT`] which are not
+/// crossed by any varblock.
+fn split_group_bands(block_info: &SharedSubgrid<BlockInfo>) -> Vec<Range<usize>> {
+    let height = block_info.height();
+    let mut crossed = vec![false; height];
+    for by in 0..height {
+        for bx in 0..block_info.width() {
+            let &BlockInfo::Data { dct_select, .. } = block_info.get(bx, by) else {
+                continue;
+            };
+            let (_, bh) = dct_select.dct_select_size();
+            let bottom = (by + bh as usize).min(height);
+            for crossed in &mut crossed[(by + 1)..bottom] {
+                *crossed = true;
+            }
+        }
+    }
+
+    let mut bands = Vec::new();
+    let mut top = 0;
+    for y in (GROUP_BAND_HEIGHT..height).step_by(GROUP_BAND_HEIGHT) {
+        if !crossed[y] {
+            bands.push(top..y);
+            top = y;
+        }
+    }
+    bands.push(top..height);
+    bands
 }
 
 #[derive(Debug)]
@@ -719,3 +980,38 @@ fn for_each_varblocks(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(grid: &mut AlignedGrid<BlockInfo>, bx: usize, by: usize, dct_select: TransformType) {
+        let (bw, bh) = dct_select.dct_select_size();
+        for y in by..(by + bh as usize) {
+            for x in bx..(bx + bw as usize) {
+                *grid.get_mut(x, y).unwrap() = BlockInfo::Occupied;
+            }
+        }
+        *grid.get_mut(bx, by).unwrap() = BlockInfo::Data {
+            dct_select,
+            hf_mul: 1,
+        };
+    }
+
+    #[test]
+    fn split_bands_avoid_crossing_varblocks() {
+        let mut grid = AlignedGrid::with_alloc_tracker(8, 36, None).unwrap();
+        for by in 0..36 {
+            for bx in 0..8 {
+                place(&mut grid, bx, by, TransformType::Dct8);
+            }
+        }
+        // Crosses the boundary at row 8.
+        place(&mut grid, 0, 6, TransformType::Dct32);
+        // Ends right at the boundary at row 24.
+        place(&mut grid, 4, 20, TransformType::Dct32);
+
+        let bands = split_group_bands(&grid.as_subgrid());
+        assert_eq!(bands, [0..16, 16..24, 24..32, 32..36]);
+    }
+}
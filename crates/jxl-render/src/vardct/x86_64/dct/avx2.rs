@@ -0,0 +1,342 @@
+use std::arch::x86_64::*;
+
+use jxl_grid::{MutableSubgrid, SimdVector};
+
+use super::super::super::dct_common::{self, DctDirection};
+
+const LANE_SIZE: usize = 8;
+type Lane = __m256;
+
+/// Maximum number of lanes needed for scratch space, for 256x256 transforms.
+const MAX_SCRATCH_LANES: usize = 256 * 2;
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn transpose_lane(lanes: &mut [Lane]) {
+    let [r0, r1, r2, r3, r4, r5, r6, r7] = lanes else {
+        panic!()
+    };
+
+    let t0 = _mm256_unpacklo_ps(*r0, *r1);
+    let t1 = _mm256_unpackhi_ps(*r0, *r1);
+    let t2 = _mm256_unpacklo_ps(*r2, *r3);
+    let t3 = _mm256_unpackhi_ps(*r2, *r3);
+    let t4 = _mm256_unpacklo_ps(*r4, *r5);
+    let t5 = _mm256_unpackhi_ps(*r4, *r5);
+    let t6 = _mm256_unpacklo_ps(*r6, *r7);
+    let t7 = _mm256_unpackhi_ps(*r6, *r7);
+
+    let s0 = _mm256_shuffle_ps::<0b01000100>(t0, t2);
+    let s1 = _mm256_shuffle_ps::<0b11101110>(t0, t2);
+    let s2 = _mm256_shuffle_ps::<0b01000100>(t1, t3);
+    let s3 = _mm256_shuffle_ps::<0b11101110>(t1, t3);
+    let s4 = _mm256_shuffle_ps::<0b01000100>(t4, t6);
+    let s5 = _mm256_shuffle_ps::<0b11101110>(t4, t6);
+    let s6 = _mm256_shuffle_ps::<0b01000100>(t5, t7);
+    let s7 = _mm256_shuffle_ps::<0b11101110>(t5, t7);
+
+    *r0 = _mm256_permute2f128_ps::<0x20>(s0, s4);
+    *r1 = _mm256_permute2f128_ps::<0x20>(s1, s5);
+    *r2 = _mm256_permute2f128_ps::<0x20>(s2, s6);
+    *r3 = _mm256_permute2f128_ps::<0x20>(s3, s7);
+    *r4 = _mm256_permute2f128_ps::<0x31>(s0, s4);
+    *r5 = _mm256_permute2f128_ps::<0x31>(s1, s5);
+    *r6 = _mm256_permute2f128_ps::<0x31>(s2, s6);
+    *r7 = _mm256_permute2f128_ps::<0x31>(s3, s7);
+}
+
+/// Performs 2D DCT using 8-wide lanes.
+///
+/// Blocks of 8x8 or smaller, or those not aligned to 8-wide lanes are handled by the SSE2
+/// implementation.
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn dct_2d_x86_64_avx2(io: &mut MutableSubgrid<'_>, direction: DctDirection) {
+    if io.width() % LANE_SIZE != 0
+        || io.height() % LANE_SIZE != 0
+        || (io.width() == LANE_SIZE && io.height() == LANE_SIZE)
+    {
+        return super::dct_2d_x86_64_sse2(io, direction);
+    }
+
+    let Some(mut io) = io.as_vectored() else {
+        tracing::trace!("Input buffer is not aligned");
+        return super::dct_2d_x86_64_sse2(io, direction);
+    };
+
+    let scratch_size = io.height().max(io.width() * LANE_SIZE) * 2;
+    let mut scratch_lanes = [_mm256_setzero_ps(); MAX_SCRATCH_LANES];
+    let scratch_lanes = &mut scratch_lanes[..scratch_size];
+    column_dct_lane(&mut io, scratch_lanes, direction);
+    row_dct_lane(&mut io, scratch_lanes, direction);
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn column_dct_lane(
+    io: &mut MutableSubgrid<'_, Lane>,
+    scratch: &mut [Lane],
+    direction: DctDirection,
+) {
+    let width = io.width();
+    let height = io.height();
+    let (io_lanes, scratch_lanes) = scratch[..height * 2].split_at_mut(height);
+    for x in 0..width {
+        for (y, input) in io_lanes.iter_mut().enumerate() {
+            *input = io.get(x, y);
+        }
+        dct(io_lanes, scratch_lanes, direction);
+        for (y, output) in io_lanes.chunks_exact_mut(LANE_SIZE).enumerate() {
+            transpose_lane(output);
+            for (dy, output) in output.iter_mut().enumerate() {
+                *io.get_mut(x, y * LANE_SIZE + dy) = *output;
+            }
+        }
+    }
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn row_dct_lane(
+    io: &mut MutableSubgrid<'_, Lane>,
+    scratch: &mut [Lane],
+    direction: DctDirection,
+) {
+    let width = io.width() * LANE_SIZE;
+    let height = io.height();
+    let (io_lanes, scratch_lanes) = scratch[..width * 2].split_at_mut(width);
+    for y in (0..height).step_by(LANE_SIZE) {
+        for (x, input) in io_lanes.chunks_exact_mut(LANE_SIZE).enumerate() {
+            for (dy, input) in input.iter_mut().enumerate() {
+                *input = io.get(x, y + dy);
+            }
+        }
+        dct(io_lanes, scratch_lanes, direction);
+        for (x, output) in io_lanes.chunks_exact_mut(LANE_SIZE).enumerate() {
+            transpose_lane(output);
+            for (dy, output) in output.iter_mut().enumerate() {
+                *io.get_mut(x, y + dy) = *output;
+            }
+        }
+    }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn dct4_forward(input: [Lane; 4]) -> [Lane; 4] {
+    let sec0 = Lane::splat_f32(0.5411961 / 4.0);
+    let sec1 = Lane::splat_f32(1.306563 / 4.0);
+    let quarter = Lane::splat_f32(0.25);
+    let sqrt2 = Lane::splat_f32(std::f32::consts::SQRT_2);
+
+    let sum03 = input[0].add(input[3]);
+    let sum12 = input[1].add(input[2]);
+    let tmp0 = input[0].sub(input[3]).mul(sec0);
+    let tmp1 = input[1].sub(input[2]).mul(sec1);
+    let out0 = tmp0.add(tmp1);
+    let out1 = tmp0.sub(tmp1);
+
+    [
+        sum03.add(sum12).mul(quarter),
+        out0.muladd(sqrt2, out1),
+        sum03.sub(sum12).mul(quarter),
+        out1,
+    ]
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn dct4_inverse(input: [Lane; 4]) -> [Lane; 4] {
+    let sec0 = Lane::splat_f32(0.5411961);
+    let sec1 = Lane::splat_f32(1.306563);
+    let sqrt2 = Lane::splat_f32(std::f32::consts::SQRT_2);
+
+    let tmp0 = input[1].mul(sqrt2);
+    let tmp1 = input[1].add(input[3]);
+    let out0 = tmp0.add(tmp1).mul(sec0);
+    let out1 = tmp0.sub(tmp1).mul(sec1);
+    let sum02 = input[0].add(input[2]);
+    let sub02 = input[0].sub(input[2]);
+
+    [
+        sum02.add(out0),
+        sub02.add(out1),
+        sub02.sub(out1),
+        sum02.sub(out0),
+    ]
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn dct8_forward(io: &mut [Lane]) {
+    assert!(io.len() == 8);
+    let half = Lane::splat_f32(0.5);
+    let sqrt2 = Lane::splat_f32(std::f32::consts::SQRT_2);
+    let sec = dct_common::sec_half_small(8);
+
+    let mut input0 = [Lane::zero(); 4];
+    let mut input1 = [Lane::zero(); 4];
+    for idx in 0..4 {
+        input0[idx] = io[idx].add(io[7 - idx]).mul(half);
+        input1[idx] = io[idx]
+            .sub(io[7 - idx])
+            .mul(Lane::splat_f32(sec[idx] / 2.0));
+    }
+    let output0 = dct4_forward(input0);
+    for (idx, v) in output0.into_iter().enumerate() {
+        io[idx * 2] = v;
+    }
+    let mut output1 = dct4_forward(input1);
+    output1[0] = output1[0].mul(sqrt2);
+    for idx in 0..3 {
+        io[idx * 2 + 1] = output1[idx].add(output1[idx + 1]);
+    }
+    io[7] = output1[3];
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn dct8_inverse(io: &mut [Lane]) {
+    assert!(io.len() == 8);
+    let sqrt2 = Lane::splat_f32(std::f32::consts::SQRT_2);
+    let sec = dct_common::sec_half_small(8);
+
+    let input0 = [io[0], io[2], io[4], io[6]];
+    let input1 = [
+        io[1].mul(sqrt2),
+        io[3].add(io[1]),
+        io[5].add(io[3]),
+        io[7].add(io[5]),
+    ];
+    let output0 = dct4_inverse(input0);
+    let output1 = dct4_inverse(input1);
+    for (idx, &sec) in sec.iter().enumerate() {
+        let r = output1[idx].mul(Lane::splat_f32(sec));
+        io[idx] = output0[idx].add(r);
+        io[7 - idx] = output0[idx].sub(r);
+    }
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn dct(io: &mut [Lane], scratch: &mut [Lane], direction: DctDirection) {
+    let n = io.len();
+    assert!(scratch.len() == n);
+
+    if n <= 1 {
+        return;
+    }
+
+    let half = Lane::splat_f32(0.5);
+    if n == 2 {
+        let tmp0 = io[0].add(io[1]);
+        let tmp1 = io[0].sub(io[1]);
+        if direction == DctDirection::Forward {
+            io[0] = tmp0.mul(half);
+            io[1] = tmp1.mul(half);
+        } else {
+            io[0] = tmp0;
+            io[1] = tmp1;
+        }
+        return;
+    }
+
+    if n == 4 {
+        if direction == DctDirection::Forward {
+            io.copy_from_slice(&dct4_forward([io[0], io[1], io[2], io[3]]));
+        } else {
+            io.copy_from_slice(&dct4_inverse([io[0], io[1], io[2], io[3]]));
+        }
+        return;
+    }
+
+    if n == 8 {
+        if direction == DctDirection::Forward {
+            dct8_forward(io);
+        } else {
+            dct8_inverse(io);
+        }
+        return;
+    }
+
+    assert!(n.is_power_of_two());
+
+    let sqrt2 = Lane::splat_f32(std::f32::consts::SQRT_2);
+    if direction == DctDirection::Forward {
+        let (input0, input1) = scratch.split_at_mut(n / 2);
+        for (idx, &sec) in dct_common::sec_half(n).iter().enumerate() {
+            input0[idx] = io[idx].add(io[n - idx - 1]).mul(half);
+            input1[idx] = io[idx].sub(io[n - idx - 1]).mul(Lane::splat_f32(sec / 2.0));
+        }
+        let (output0, output1) = io.split_at_mut(n / 2);
+        dct(input0, output0, DctDirection::Forward);
+        dct(input1, output1, DctDirection::Forward);
+        for (idx, v) in input0.iter().enumerate() {
+            io[idx * 2] = *v;
+        }
+        input1[0] = input1[0].mul(sqrt2);
+        for idx in 0..(n / 2 - 1) {
+            io[idx * 2 + 1] = input1[idx].add(input1[idx + 1]);
+        }
+        io[n - 1] = input1[n / 2 - 1];
+    } else {
+        let (input0, input1) = scratch.split_at_mut(n / 2);
+        for idx in 1..(n / 2) {
+            let idx = n / 2 - idx;
+            input0[idx] = io[idx * 2];
+            input1[idx] = io[idx * 2 + 1].add(io[idx * 2 - 1]);
+        }
+        input0[0] = io[0];
+        input1[0] = io[1].mul(sqrt2);
+        let (output0, output1) = io.split_at_mut(n / 2);
+        dct(input0, output0, DctDirection::Inverse);
+        dct(input1, output1, DctDirection::Inverse);
+        for (idx, &sec) in dct_common::sec_half(n).iter().enumerate() {
+            let r = input1[idx].mul(Lane::splat_f32(sec));
+            output0[idx] = input0[idx].add(r);
+            output1[n / 2 - idx - 1] = input0[idx].sub(r);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jxl_grid::AlignedGrid;
+
+    use super::*;
+
+    #[test]
+    fn matches_generic() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let mut state = 0x2545f491u32;
+        for (width, height) in [(16, 16), (32, 8), (8, 32), (64, 32), (256, 256)] {
+            for direction in [DctDirection::Forward, DctDirection::Inverse] {
+                let mut expected =
+                    AlignedGrid::<f32>::with_alloc_tracker(width, height, None).unwrap();
+                for v in expected.buf_mut() {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    *v = (state % 2048) as f32 / 1024.0 - 1.0;
+                }
+                let mut actual = expected.try_clone().unwrap();
+
+                super::super::super::generic::dct_2d(&mut expected.as_subgrid_mut(), direction);
+                unsafe {
+                    dct_2d_x86_64_avx2(&mut actual.as_subgrid_mut(), direction);
+                }
+
+                // Rounding errors accumulate relative to the largest output.
+                let peak = expected
+                    .buf()
+                    .iter()
+                    .fold(1.0f32, |peak, v| peak.max(v.abs()));
+                for (&e, &a) in expected.buf().iter().zip(actual.buf()) {
+                    assert!(
+                        (e - a).abs() <= 1e-4 * peak,
+                        "{width}x{height} {direction:?}: expected {e}, got {a}",
+                    );
+                }
+            }
+        }
+    }
+}
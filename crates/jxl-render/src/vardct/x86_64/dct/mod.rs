@@ -4,6 +4,12 @@ use jxl_grid::{MutableSubgrid, SimdVector};
 
 use super::super::dct_common::{self, DctDirection};
 
+mod avx2;
+pub(crate) use avx2::dct_2d_x86_64_avx2;
+
+/// Maximum number of lanes needed for scratch space, for 256x256 transforms.
+const MAX_SCRATCH_LANES: usize = 256 * 2;
+
 const LANE_SIZE: usize = 4;
 type Lane = __m128;
 
@@ -40,9 +46,10 @@ pub(crate) fn dct_2d_x86_64_sse2(io: &mut MutableSubgrid<'_>, direction: DctDire
 fn dct_2d_lane(io: &mut MutableSubgrid<'_, Lane>, direction: DctDirection) {
     let scratch_size = io.height().max(io.width() * LANE_SIZE) * 2;
     unsafe {
-        let mut scratch_lanes = vec![_mm_setzero_ps(); scratch_size];
-        column_dct_lane(io, &mut scratch_lanes, direction);
-        row_dct_lane(io, &mut scratch_lanes, direction);
+        let mut scratch_lanes = [_mm_setzero_ps(); MAX_SCRATCH_LANES];
+        let scratch_lanes = &mut scratch_lanes[..scratch_size];
+        column_dct_lane(io, scratch_lanes, direction);
+        row_dct_lane(io, scratch_lanes, direction);
     }
 }
 
@@ -152,6 +152,29 @@ fn transform_dct(coeff: &mut MutableSubgrid<'_>) {
     super::dct::dct_2d_x86_64_sse2(coeff, DctDirection::Inverse);
 }
 
+#[target_feature(enable = "avx2")]
+unsafe fn transform_dct_x86_64_avx2(coeff: &mut MutableSubgrid<'_>) {
+    super::dct::dct_2d_x86_64_avx2(coeff, DctDirection::Inverse);
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn transform_x86_64_avx2(coeff: &mut MutableSubgrid<'_>, dct_select: TransformType) {
+    use TransformType::*;
+
+    match dct_select {
+        Dct2 => transform_dct2_x86_64_sse41(coeff),
+        Dct4 => transform_dct4_x86_64_sse2(coeff),
+        Hornuss => generic::transform_hornuss(coeff),
+        Dct4x8 => transform_dct4x8_x86_64_sse2::<false>(coeff),
+        Dct8x4 => transform_dct4x8_x86_64_sse2::<true>(coeff),
+        Afv0 => generic::transform_afv::<0>(coeff),
+        Afv1 => generic::transform_afv::<1>(coeff),
+        Afv2 => generic::transform_afv::<2>(coeff),
+        Afv3 => generic::transform_afv::<3>(coeff),
+        _ => transform_dct_x86_64_avx2(coeff),
+    }
+}
+
 #[target_feature(enable = "sse4.1")]
 #[target_feature(enable = "sse3")]
 unsafe fn transform_x86_64_sse41(coeff: &mut MutableSubgrid<'_>, dct_select: TransformType) {
@@ -206,12 +229,34 @@ unsafe fn transform_varblocks_x86_64_sse41(
     );
 }
 
+#[target_feature(enable = "avx2")]
+unsafe fn transform_varblocks_x86_64_avx2(
+    lf: &[SharedSubgrid<f32>; 3],
+    coeff_out: &mut [MutableSubgrid<'_, f32>; 3],
+    shifts_cbycr: [ChannelShift; 3],
+    block_info: &SharedSubgrid<BlockInfo>,
+) {
+    transform_varblocks_inner(
+        lf,
+        coeff_out,
+        shifts_cbycr,
+        block_info,
+        super::dct::dct_2d_x86_64_avx2,
+        transform_x86_64_avx2,
+    );
+}
+
 pub fn transform_varblocks(
     lf: &[SharedSubgrid<f32>; 3],
     coeff_out: &mut [MutableSubgrid<'_, f32>; 3],
     shifts_cbycr: [ChannelShift; 3],
     block_info: &SharedSubgrid<BlockInfo>,
 ) {
+    if is_x86_feature_detected!("avx2") {
+        unsafe {
+            return transform_varblocks_x86_64_avx2(lf, coeff_out, shifts_cbycr, block_info);
+        }
+    }
     if is_x86_feature_detected!("sse4.1") {
         unsafe {
             return transform_varblocks_x86_64_sse41(lf, coeff_out, shifts_cbycr, block_info);
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// User-implemented executor which drives a [`JxlThreadPool`][crate::JxlThreadPool].
+///
+/// Implement this trait to run decoding tasks on thread pools other than Rayon, such as blocking
+/// thread pools of async runtimes or platform-provided thread pools. Create a thread pool using
+/// [`JxlThreadPool::with_spawner`][crate::JxlThreadPool::with_spawner].
+///
+/// Fork-join operations are implemented on top of [`spawn`][Self::spawn]. The thread waiting for
+/// the completion of spawned tasks also runs the tasks by itself, so the decoder makes progress
+/// even if jobs are delayed or executed on a single worker.
+///
+/// # Example
+/// ```
+/// use jxl_threadpool::{JxlSpawner, JxlThreadPool};
+///
+/// struct ThreadPerJob;
+///
+/// impl JxlSpawner for ThreadPerJob {
+///     fn spawn(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+///         std::thread::spawn(job);
+///     }
+///
+///     fn num_threads(&self) -> usize {
+///         4
+///     }
+/// }
+///
+/// let pool = JxlThreadPool::with_spawner(ThreadPerJob);
+/// assert!(pool.is_multithreaded());
+/// ```
+pub trait JxlSpawner: Send + Sync + 'static {
+    /// Runs the job on a worker thread.
+    ///
+    /// Every job should be run eventually, but it's okay to run jobs after a delay.
+    fn spawn(&self, job: Box<dyn FnOnce() + Send + 'static>);
+
+    /// Returns the number of worker threads, which is used as the number of tasks in parallel
+    /// iterations.
+    fn num_threads(&self) -> usize;
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Clone)]
+pub(crate) struct CustomSpawner(pub(crate) Arc<dyn JxlSpawner>);
+
+impl std::fmt::Debug for CustomSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomSpawner")
+            .field("num_threads", &self.0.num_threads())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared state of a fork-join scope running on a [`CustomSpawner`].
+///
+/// Jobs are queued in the scope, and each spawned runner pops and runs one of them. The thread
+/// which owns the scope also drains the queue while waiting, so that jobs never wait on runners
+/// blocked by other scopes.
+#[derive(Default)]
+pub(crate) struct ScopeState {
+    inner: Mutex<ScopeStateInner>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct ScopeStateInner {
+    queue: VecDeque<Job>,
+    pending: usize,
+    panic: Option<Box<dyn std::any::Any + Send + 'static>>,
+}
+
+impl std::fmt::Debug for ScopeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopeState").finish_non_exhaustive()
+    }
+}
+
+impl ScopeState {
+    /// Queues the job, and spawns a runner for it.
+    ///
+    /// # Safety
+    /// The job should not outlive the scope, i.e. [`wait`][Self::wait] should be called before
+    /// the lifetime `'scope` ends.
+    pub(crate) unsafe fn spawn<'scope>(
+        self: &Arc<Self>,
+        spawner: &CustomSpawner,
+        job: Box<dyn FnOnce() + Send + 'scope>,
+    ) {
+        // SAFETY: The scope waits for every queued job to finish, so the job is not used after
+        // `'scope` ends. Runners spawned after that find the queue empty.
+        let job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.queue.push_back(job);
+            inner.pending += 1;
+        }
+        self.cond.notify_all();
+
+        let state = Arc::clone(self);
+        spawner.0.spawn(Box::new(move || state.run_one()));
+    }
+
+    fn run_one(&self) {
+        let job = self.inner.lock().unwrap().queue.pop_front();
+        if let Some(job) = job {
+            self.run(job);
+        }
+    }
+
+    fn run(&self, job: Job) {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+        let mut inner = self.inner.lock().unwrap();
+        if let Err(payload) = result {
+            inner.panic.get_or_insert(payload);
+        }
+        inner.pending -= 1;
+        if inner.pending == 0 {
+            self.cond.notify_all();
+        }
+    }
+
+    /// Runs queued jobs in the current thread, and waits for running jobs to finish.
+    ///
+    /// Resumes the panic of a job, if any.
+    pub(crate) fn wait(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        loop {
+            if let Some(job) = inner.queue.pop_front() {
+                drop(inner);
+                self.run(job);
+                inner = self.inner.lock().unwrap();
+            } else if inner.pending == 0 {
+                break;
+            } else {
+                inner = self.cond.wait(inner).unwrap();
+            }
+        }
+
+        if let Some(payload) = inner.panic.take() {
+            drop(inner);
+            std::panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// Runs `op` for each item of `it` using at most `num_threads` tasks, each of which has its own
+/// clone of `init`.
+pub(crate) fn for_each_with<I, U>(
+    pool: &crate::JxlThreadPool,
+    num_threads: usize,
+    it: I,
+    init: U,
+    op: impl Fn(&mut U, I::Item) + Send + Sync,
+) where
+    I: Iterator + Send,
+    I::Item: Send,
+    U: Send + Clone,
+{
+    let it = Mutex::new(it);
+    let it = &it;
+    let op = &op;
+    let run = move |mut init: U| loop {
+        let Some(item) = it.lock().unwrap().next() else {
+            break;
+        };
+        op(&mut init, item);
+    };
+
+    pool.scope(|scope| {
+        for _ in 1..num_threads {
+            let init = init.clone();
+            scope.spawn(move |_| run(init));
+        }
+        run(init);
+    });
+}
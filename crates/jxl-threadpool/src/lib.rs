@@ -2,12 +2,20 @@
 //!
 //! [`JxlThreadPool`] is re-exported by `jxl-oxide`.
 
+mod custom;
+mod limit;
+
+use custom::{CustomSpawner, ScopeState};
+
+pub use custom::JxlSpawner;
+
 /// Thread pool wrapper.
 ///
 /// This struct wraps internal thread pool implementation and provides interfaces to access it. If
-/// `rayon` feature is enabled, users can create an actual thread pool backed by Rayon; if not,
-/// this struct won't have any multithreading capability, and every spawn operation will just run
-/// the given closure in place.
+/// `rayon` feature is enabled, users can create an actual thread pool backed by Rayon. Other
+/// executors can be used by implementing [`JxlSpawner`]. Thread pool created with
+/// [`none`][Self::none] doesn't have any multithreading capability, and every spawn operation will
+/// just run the given closure in place.
 #[derive(Debug, Clone)]
 pub struct JxlThreadPool(JxlThreadPoolImpl);
 
@@ -15,6 +23,7 @@ pub struct JxlThreadPool(JxlThreadPoolImpl);
 enum JxlThreadPoolImpl {
     #[cfg(feature = "rayon")]
     Rayon(std::sync::Arc<rayon_core::ThreadPool>),
+    Custom(CustomSpawner),
     None,
 }
 
@@ -26,6 +35,11 @@ pub struct JxlScope<'r, 'scope>(JxlScopeInner<'r, 'scope>);
 enum JxlScopeInner<'r, 'scope> {
     #[cfg(feature = "rayon")]
     Rayon(&'r rayon_core::Scope<'scope>),
+    Custom {
+        spawner: &'r CustomSpawner,
+        state: &'r std::sync::Arc<ScopeState>,
+        _marker: std::marker::PhantomData<&'scope ()>,
+    },
     None(std::marker::PhantomData<&'r &'scope ()>),
 }
 
@@ -43,6 +57,15 @@ impl JxlThreadPool {
         Self(JxlThreadPoolImpl::Rayon(pool))
     }
 
+    /// Creates a thread pool backed by a user-implemented [`JxlSpawner`].
+    ///
+    /// This can be used without enabling `rayon` feature.
+    pub fn with_spawner(spawner: impl JxlSpawner) -> Self {
+        Self(JxlThreadPoolImpl::Custom(CustomSpawner(
+            std::sync::Arc::new(spawner),
+        )))
+    }
+
     /// Creates a thread pool backed by Rayon.
     ///
     /// If `num_threads_requested` is `None` or zero, this method queries available paralleism and
@@ -86,8 +109,25 @@ impl JxlThreadPool {
     pub fn as_rayon_pool(&self) -> Option<&rayon_core::ThreadPool> {
         match &self.0 {
             JxlThreadPoolImpl::Rayon(pool) => Some(&**pool),
-            JxlThreadPoolImpl::None => None,
+            JxlThreadPoolImpl::Custom(_) | JxlThreadPoolImpl::None => None,
+        }
+    }
+
+    /// Returns a thread pool which runs at most `max` tasks at the same time on this pool.
+    ///
+    /// The limit is shared by every clone of the returned pool, but not by the other pools
+    /// created from this pool, so that e.g. each image can be given its own limit while sharing
+    /// the global Rayon pool. Tasks over the limit are queued without blocking worker threads.
+    /// The thread waiting for a fork-join operation also runs its tasks, in addition to `max`
+    /// tasks on worker threads.
+    ///
+    /// `max` of zero is treated as one. If this pool is not multithreaded, this method returns a
+    /// clone of this pool.
+    pub fn with_max_concurrency(&self, max: usize) -> Self {
+        if !self.is_multithreaded() {
+            return self.clone();
         }
+        Self::with_spawner(limit::ConcurrencyLimiter::new(self.clone(), max))
     }
 
     /// Returns if the thread pool is capable of multithreading.
@@ -95,6 +135,7 @@ impl JxlThreadPool {
         match self.0 {
             #[cfg(feature = "rayon")]
             JxlThreadPoolImpl::Rayon(_) => true,
+            JxlThreadPoolImpl::Custom(_) => true,
             JxlThreadPoolImpl::None => false,
         }
     }
@@ -106,6 +147,7 @@ impl JxlThreadPool {
         match &self.0 {
             #[cfg(feature = "rayon")]
             JxlThreadPoolImpl::Rayon(pool) => pool.spawn(op),
+            JxlThreadPoolImpl::Custom(spawner) => spawner.0.spawn(Box::new(op)),
             JxlThreadPoolImpl::None => op(),
         }
     }
@@ -121,6 +163,21 @@ impl JxlThreadPool {
                 let scope = JxlScope(JxlScopeInner::Rayon(scope));
                 op(scope)
             }),
+            JxlThreadPoolImpl::Custom(spawner) => {
+                let state = std::sync::Arc::new(ScopeState::default());
+                let scope = JxlScope(JxlScopeInner::Custom {
+                    spawner,
+                    state: &state,
+                    _marker: Default::default(),
+                });
+                let result =
+                    std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || op(scope)));
+                state.wait();
+                match result {
+                    Ok(result) => result,
+                    Err(payload) => std::panic::resume_unwind(payload),
+                }
+            }
             JxlThreadPoolImpl::None => op(JxlScope(JxlScopeInner::None(Default::default()))),
         }
     }
@@ -130,6 +187,13 @@ impl JxlThreadPool {
         match &self.0 {
             #[cfg(feature = "rayon")]
             JxlThreadPoolImpl::Rayon(pool) => pool.install(|| par_for_each(v, op)),
+            JxlThreadPoolImpl::Custom(spawner) => custom::for_each_with(
+                self,
+                spawner.0.num_threads(),
+                v.into_iter(),
+                (),
+                |_, item| op(item),
+            ),
             JxlThreadPoolImpl::None => v.into_iter().for_each(op),
         }
     }
@@ -144,6 +208,9 @@ impl JxlThreadPool {
         match &self.0 {
             #[cfg(feature = "rayon")]
             JxlThreadPoolImpl::Rayon(pool) => pool.install(|| par_for_each_with(v, init, op)),
+            JxlThreadPoolImpl::Custom(spawner) => {
+                custom::for_each_with(self, spawner.0.num_threads(), v.into_iter(), init, op)
+            }
             JxlThreadPoolImpl::None => {
                 let mut init = init;
                 v.into_iter().for_each(|item| op(&mut init, item))
@@ -160,6 +227,13 @@ impl JxlThreadPool {
         match &self.0 {
             #[cfg(feature = "rayon")]
             JxlThreadPoolImpl::Rayon(pool) => pool.install(|| par_for_each(v, op)),
+            JxlThreadPoolImpl::Custom(spawner) => custom::for_each_with(
+                self,
+                spawner.0.num_threads(),
+                v.iter_mut(),
+                (),
+                |_, item| op(item),
+            ),
             JxlThreadPoolImpl::None => v.iter_mut().for_each(op),
         }
     }
@@ -174,6 +248,9 @@ impl JxlThreadPool {
         match &self.0 {
             #[cfg(feature = "rayon")]
             JxlThreadPoolImpl::Rayon(pool) => pool.install(|| par_for_each_with(v, init, op)),
+            JxlThreadPoolImpl::Custom(spawner) => {
+                custom::for_each_with(self, spawner.0.num_threads(), v.iter_mut(), init, op)
+            }
             JxlThreadPoolImpl::None => {
                 let mut init = init;
                 v.iter_mut().for_each(|item| op(&mut init, item))
@@ -210,6 +287,23 @@ impl<'scope> JxlScope<'_, 'scope> {
                 let scope = JxlScope(JxlScopeInner::Rayon(scope));
                 op(scope)
             }),
+            JxlScopeInner::Custom { spawner, state, .. } => {
+                let job = {
+                    let spawner = spawner.clone();
+                    let state = std::sync::Arc::clone(state);
+                    move || {
+                        let scope = JxlScope(JxlScopeInner::Custom {
+                            spawner: &spawner,
+                            state: &state,
+                            _marker: Default::default(),
+                        });
+                        op(scope)
+                    }
+                };
+                // SAFETY: `JxlThreadPool::scope` waits for every job queued in the state before
+                // returning, so jobs don't outlive `'scope`.
+                unsafe { state.spawn(spawner, Box::new(job)) }
+            }
             JxlScopeInner::None(_) => op(JxlScope(JxlScopeInner::None(Default::default()))),
         }
     }
@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::{JxlSpawner, JxlThreadPool};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Spawner which runs at most `max` jobs at the same time on the underlying thread pool.
+///
+/// Jobs exceeding the limit are queued, and run by one of the active runners when it finishes
+/// its current job. Runners never block, so that the underlying pool can be shared with other
+/// decoders and applications.
+pub(crate) struct ConcurrencyLimiter {
+    pool: JxlThreadPool,
+    max: usize,
+    state: Arc<Mutex<LimiterState>>,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    queue: VecDeque<Job>,
+    active: usize,
+}
+
+impl ConcurrencyLimiter {
+    pub(crate) fn new(pool: JxlThreadPool, max: usize) -> Self {
+        Self {
+            pool,
+            max: max.max(1),
+            state: Default::default(),
+        }
+    }
+}
+
+fn run(pool: JxlThreadPool, state: Arc<Mutex<LimiterState>>) {
+    loop {
+        let job = {
+            let mut state = state.lock().unwrap();
+            match state.queue.pop_front() {
+                Some(job) => job,
+                None => {
+                    state.active -= 1;
+                    return;
+                }
+            }
+        };
+
+        if let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)) {
+            // Hand over remaining jobs to a new runner before unwinding.
+            let state = Arc::clone(&state);
+            let pool_inner = pool.clone();
+            pool.spawn(move || run(pool_inner, state));
+            std::panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl JxlSpawner for ConcurrencyLimiter {
+    fn spawn(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.queue.push_back(job);
+            if state.active >= self.max {
+                return;
+            }
+            state.active += 1;
+        }
+
+        let pool = self.pool.clone();
+        let state = Arc::clone(&self.state);
+        self.pool.spawn(move || run(pool, state));
+    }
+
+    fn num_threads(&self) -> usize {
+        self.max
+    }
+}
@@ -414,6 +414,7 @@ pub struct DequantMatrixSetParams<'a, 'pool, 'tracker> {
     global_ma_config: Option<&'a jxl_modular::MaConfig>,
     tracker: Option<&'tracker AllocTracker>,
     pool: &'pool jxl_threadpool::JxlThreadPool,
+    force_scalar: bool,
 }
 
 impl<'a, 'pool, 'tracker> DequantMatrixSetParams<'a, 'pool, 'tracker> {
@@ -434,8 +435,16 @@ impl<'a, 'pool, 'tracker> DequantMatrixSetParams<'a, 'pool, 'tracker> {
             global_ma_config,
             tracker,
             pool,
+            force_scalar: false,
         }
     }
+
+    /// Sets whether to use portable scalar code instead of SIMD implementations selected at
+    /// runtime.
+    pub fn force_scalar(mut self, force_scalar: bool) -> Self {
+        self.force_scalar = force_scalar;
+        self
+    }
 }
 
 impl Bundle<DequantMatrixSetParams<'_, '_, '_>> for DequantMatrixParams {
@@ -482,6 +491,7 @@ impl Bundle<DequantMatrixSetParams<'_, '_, '_>> for DequantMatrixParams {
             global_ma_config,
             tracker,
             pool,
+            force_scalar,
         } = params;
 
         let encoding_mode = bitstream.read_bits(3)?;
@@ -543,7 +553,7 @@ impl Bundle<DequantMatrixSetParams<'_, '_, '_>> for DequantMatrixParams {
                 let (width, height) = dct_select.dequant_matrix_size();
 
                 let denominator = bitstream.read_f16_as_f32()?;
-                let modular_params = ModularParams::new(
+                let mut modular_params = ModularParams::new(
                     width,
                     height,
                     256,
@@ -552,6 +562,7 @@ impl Bundle<DequantMatrixSetParams<'_, '_, '_>> for DequantMatrixParams {
                     global_ma_config,
                     tracker,
                 );
+                modular_params.force_scalar = force_scalar;
                 let mut params = Modular::parse(bitstream, modular_params)?;
                 let image = params.image_mut().unwrap();
                 let mut subimage = image.prepare_subimage()?;
@@ -1,7 +1,7 @@
 use std::sync::atomic::AtomicI32;
 
 use jxl_bitstream::Bitstream;
-use jxl_grid::{AllocTracker, SharedSubgrid};
+use jxl_grid::{AllocCategory, AllocTracker, SharedSubgrid};
 use jxl_modular::{ChannelShift, Sample};
 
 use crate::{BlockInfo, HfBlockContext, HfPass, Result};
@@ -79,7 +79,9 @@ pub fn write_hf_coeff<S: Sample>(
         .map(|tracker| {
             let len =
                 non_zeros_grid_lengths[0] + non_zeros_grid_lengths[1] + non_zeros_grid_lengths[2];
-            tracker.alloc::<u32>(len)
+            tracker
+                .with_category(AllocCategory::VarDct)
+                .alloc::<u32>(len)
         })
         .transpose()?;
     let mut non_zeros_grid_row = [
@@ -87,6 +89,7 @@ pub fn write_hf_coeff<S: Sample>(
         vec![0u32; non_zeros_grid_lengths[1]],
         vec![0u32; non_zeros_grid_lengths[2]],
     ];
+    let mut num_coeffs = 0u64;
 
     for y in 0..height {
         for x in 0..width {
@@ -184,6 +187,7 @@ pub fn write_hf_coeff<S: Sample>(
                         jxl_bitstream::Error::ValidationFailed("non_zeros too large").into(),
                     );
                 }
+                num_coeffs += non_zeros as u64;
 
                 let non_zeros_val = (non_zeros + num_blocks - 1) >> num_blocks_log;
                 for dx in 0..w8 as usize {
@@ -245,6 +249,7 @@ pub fn write_hf_coeff<S: Sample>(
     }
 
     dist.finalize()?;
+    tracing::trace!(num_coeffs, "HfCoeff");
 
     Ok(())
 }
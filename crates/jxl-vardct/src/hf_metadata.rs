@@ -1,5 +1,5 @@
 use jxl_bitstream::Bitstream;
-use jxl_grid::{AlignedGrid, AllocTracker};
+use jxl_grid::{AlignedGrid, AllocCategory, AllocTracker};
 use jxl_modular::{MaConfig, Modular, ModularChannelParams, ModularParams};
 use jxl_oxide_common::Bundle;
 
@@ -19,6 +19,8 @@ pub struct HfMetadataParams<'ma, 'pool, 'tracker> {
     pub quantizer_global_scale: u32,
     pub tracker: Option<&'tracker AllocTracker>,
     pub pool: &'pool jxl_threadpool::JxlThreadPool,
+    /// Use portable scalar code instead of SIMD implementations selected at runtime.
+    pub force_scalar: bool,
 }
 
 /// Data for decoding and rendering varblocks within an LF group.
@@ -65,6 +67,7 @@ impl Bundle<HfMetadataParams<'_, '_, '_>> for HfMetadata {
             quantizer_global_scale,
             tracker,
             pool,
+            force_scalar,
         } = params;
 
         let mut bw = ((lf_width + 7) / 8) as usize;
@@ -88,8 +91,9 @@ impl Bundle<HfMetadataParams<'_, '_, '_>> for HfMetadata {
             ModularChannelParams::new(nb_blocks, 2),
             ModularChannelParams::new(bw as u32, bh as u32),
         ];
-        let params =
+        let mut params =
             ModularParams::with_channels(0, bits_per_sample, channels, global_ma_config, tracker);
+        params.force_scalar = force_scalar;
         let mut modular = Modular::parse(bitstream, params)?;
         let image = modular.image_mut().unwrap();
         let mut subimage = image.prepare_subimage()?;
@@ -105,6 +109,8 @@ impl Bundle<HfMetadataParams<'_, '_, '_>> for HfMetadata {
 
         let sharpness = sharpness.buf();
 
+        let tracker = tracker.map(|tracker| tracker.with_category(AllocCategory::VarDct));
+        let tracker = tracker.as_ref();
         let mut epf_sigma = AlignedGrid::with_alloc_tracker(bw, bh, tracker)?;
         let epf_sigma_buf = epf_sigma.buf_mut();
         let epf = epf.map(|(quant_mul, sharp_lut)| {
@@ -76,6 +76,12 @@ impl Bundle<HfPassParams<'_>> for HfPass {
 }
 
 impl HfPass {
+    /// Returns the entropy decoder of HF coefficients.
+    #[inline]
+    pub fn decoder(&self) -> &Decoder {
+        &self.hf_dist
+    }
+
     #[inline]
     pub(crate) fn clone_decoder(&self) -> Decoder {
         self.hf_dist.clone()
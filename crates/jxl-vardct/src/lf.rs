@@ -125,6 +125,8 @@ pub struct LfCoeffParams<'ma, 'pool, 'tracker> {
     pub allow_partial: bool,
     pub tracker: Option<&'tracker AllocTracker>,
     pub pool: &'pool jxl_threadpool::JxlThreadPool,
+    /// Use portable scalar code instead of SIMD implementations selected at runtime.
+    pub force_scalar: bool,
 }
 
 /// Quantized LF image.
@@ -149,6 +151,7 @@ impl<S: Sample> Bundle<LfCoeffParams<'_, '_, '_>> for LfCoeff<S> {
             allow_partial,
             tracker,
             pool,
+            force_scalar,
         } = params;
 
         let extra_precision = bitstream.read_bits(2)? as u8;
@@ -159,7 +162,7 @@ impl<S: Sample> Bundle<LfCoeffParams<'_, '_, '_>> for LfCoeff<S> {
             .into_iter()
             .map(|idx| ChannelShift::from_jpeg_upsampling(jpeg_upsampling, idx))
             .collect();
-        let lf_quant_params = ModularParams::new(
+        let mut lf_quant_params = ModularParams::new(
             width,
             height,
             0,
@@ -168,6 +171,7 @@ impl<S: Sample> Bundle<LfCoeffParams<'_, '_, '_>> for LfCoeff<S> {
             global_ma_config,
             tracker,
         );
+        lf_quant_params.force_scalar = force_scalar;
         let mut lf_quant = Modular::parse(bitstream, lf_quant_params)?;
         let image = lf_quant.image_mut().unwrap();
         let mut subimage = image.prepare_subimage()?;